@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+/**
+ * @title FarmerPayments
+ * @dev Settlement register for FPO purchases. One record per batch, keyed
+ * like OilseedValueChain (nyx.sol) by keccak256 of the batch ID, so a
+ * farmer's payment can be checked against the purchase anchored there.
+ * Amounts are in paise; funds move off chain and `paymentRef` is the hash
+ * of the bank or UPI reference.
+ */
+contract FarmerPayments {
+    // ======================== STATE VARIABLES ========================
+    struct Settlement {
+        bytes32 farmerDID;
+        uint256 amountPaise;
+        bytes32 paymentRef;
+        uint64 settledAt;
+    }
+
+    // Role-based access control
+    mapping(address => bool) public authorizedRecorders;
+    address public admin;
+
+    // Batch hash → settlement
+    mapping(bytes32 => Settlement) private _settlements;
+
+    // Events
+    event PaymentSettled(
+        bytes32 indexed batchHash,
+        bytes32 indexed farmerDID,
+        uint256 amountPaise,
+        bytes32 paymentRef,
+        uint64 settledAt
+    );
+
+    // Custom errors
+    error Unauthorized();
+    error ZeroAddress();
+    error AlreadySettled();
+    error InvalidAmount();
+
+    // ======================== CONSTRUCTOR ========================
+    constructor() {
+        admin = msg.sender;
+        authorizedRecorders[msg.sender] = true;
+    }
+
+    // ======================== MODIFIERS ========================
+    modifier onlyAdmin() {
+        if (msg.sender != admin) revert Unauthorized();
+        _;
+    }
+
+    modifier onlyRecorder() {
+        if (!authorizedRecorders[msg.sender]) revert Unauthorized();
+        _;
+    }
+
+    // ======================== ACCESS CONTROL ========================
+    function setAdmin(address newAdmin) external onlyAdmin {
+        if (newAdmin == address(0)) revert ZeroAddress();
+        admin = newAdmin;
+    }
+
+    function addRecorder(address recorder) external onlyAdmin {
+        if (recorder == address(0)) revert ZeroAddress();
+        authorizedRecorders[recorder] = true;
+    }
+
+    function removeRecorder(address recorder) external onlyAdmin {
+        authorizedRecorders[recorder] = false;
+    }
+
+    // ======================== SETTLEMENTS ========================
+    function recordPayment(
+        bytes32 batchHash,
+        bytes32 farmerDID,
+        uint256 amountPaise,
+        bytes32 paymentRef
+    ) external onlyRecorder {
+        if (amountPaise == 0) revert InvalidAmount();
+        if (_settlements[batchHash].settledAt != 0) revert AlreadySettled();
+
+        uint64 settledAt = uint64(block.timestamp);
+        _settlements[batchHash] = Settlement(
+            farmerDID,
+            amountPaise,
+            paymentRef,
+            settledAt
+        );
+
+        emit PaymentSettled(
+            batchHash,
+            farmerDID,
+            amountPaise,
+            paymentRef,
+            settledAt
+        );
+    }
+
+    function settlementOf(
+        bytes32 batchHash
+    )
+        external
+        view
+        returns (
+            bytes32 farmerDID,
+            uint256 amountPaise,
+            bytes32 paymentRef,
+            uint64 settledAt
+        )
+    {
+        Settlement storage s = _settlements[batchHash];
+        return (s.farmerDID, s.amountPaise, s.paymentRef, s.settledAt);
+    }
+}
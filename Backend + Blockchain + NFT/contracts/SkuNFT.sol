@@ -0,0 +1,253 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+import {IERC721Receiver} from "./FarmerCreditNFT.sol";
+
+/**
+ * @title SkuNFT
+ * @dev One ERC721 token per packaged SKU. The token URI points to ERC721
+ * metadata JSON on IPFS, which links the SKU's batch folder, whose CID is
+ * also carried by the SKUPackaged event of OilseedValueChain (nyx.sol).
+ */
+contract SkuNFT {
+    // ======================== STATE VARIABLES ========================
+    string public name = "OilseedSKU";
+    string public symbol = "OSKU";
+    uint256 private _tokenIdCounter;
+
+    // Role-based access control
+    mapping(address => bool) public authorizedMinters;
+    address public admin;
+
+    // ERC721 mappings
+    mapping(uint256 => address) private _owners;
+    mapping(address => uint256) private _balances;
+    mapping(uint256 => address) private _tokenApprovals;
+    mapping(address => mapping(address => bool)) private _operatorApprovals;
+
+    // SKU ID (keccak256 of the SKU string) → token ID, and back
+    mapping(bytes32 => uint256) public tokenOfSku;
+    mapping(uint256 => bytes32) public skuOfToken;
+    mapping(uint256 => string) private _tokenURIs;
+
+    // Events
+    event Transfer(
+        address indexed from,
+        address indexed to,
+        uint256 indexed tokenId
+    );
+    event Approval(
+        address indexed owner,
+        address indexed approved,
+        uint256 indexed tokenId
+    );
+    event ApprovalForAll(
+        address indexed owner,
+        address indexed operator,
+        bool approved
+    );
+    event SkuMinted(
+        uint256 indexed tokenId,
+        bytes32 indexed skuId,
+        address indexed recipient,
+        string tokenURI
+    );
+
+    // Custom errors
+    error Unauthorized();
+    error SkuAlreadyMinted();
+    error ZeroAddress();
+    error TokenNotFound();
+    error NotOwnerOrApproved();
+
+    // ======================== CONSTRUCTOR ========================
+    constructor() {
+        admin = msg.sender;
+        authorizedMinters[msg.sender] = true;
+    }
+
+    // ======================== MODIFIERS ========================
+    modifier onlyAdmin() {
+        if (msg.sender != admin) revert Unauthorized();
+        _;
+    }
+
+    modifier onlyMinter() {
+        if (!authorizedMinters[msg.sender]) revert Unauthorized();
+        _;
+    }
+
+    // ======================== ERC721 IMPLEMENTATION ========================
+    function balanceOf(address owner) public view returns (uint256) {
+        if (owner == address(0)) revert ZeroAddress();
+        return _balances[owner];
+    }
+
+    function ownerOf(uint256 tokenId) public view returns (address) {
+        address owner = _owners[tokenId];
+        if (owner == address(0)) revert TokenNotFound();
+        return owner;
+    }
+
+    function tokenURI(uint256 tokenId) public view returns (string memory) {
+        if (!_exists(tokenId)) revert TokenNotFound();
+        return _tokenURIs[tokenId];
+    }
+
+    function approve(address to, uint256 tokenId) public {
+        address owner = ownerOf(tokenId);
+        if (to == owner) revert();
+        if (msg.sender != owner && !isApprovedForAll(owner, msg.sender))
+            revert NotOwnerOrApproved();
+
+        _tokenApprovals[tokenId] = to;
+        emit Approval(owner, to, tokenId);
+    }
+
+    function getApproved(uint256 tokenId) public view returns (address) {
+        if (!_exists(tokenId)) revert TokenNotFound();
+        return _tokenApprovals[tokenId];
+    }
+
+    function setApprovalForAll(address operator, bool approved) public {
+        if (operator == msg.sender) revert();
+        _operatorApprovals[msg.sender][operator] = approved;
+        emit ApprovalForAll(msg.sender, operator, approved);
+    }
+
+    function isApprovedForAll(
+        address owner,
+        address operator
+    ) public view returns (bool) {
+        return _operatorApprovals[owner][operator];
+    }
+
+    function transferFrom(address from, address to, uint256 tokenId) public {
+        if (!_isApprovedOrOwner(msg.sender, tokenId))
+            revert NotOwnerOrApproved();
+        _transfer(from, to, tokenId);
+    }
+
+    function safeTransferFrom(
+        address from,
+        address to,
+        uint256 tokenId
+    ) public {
+        safeTransferFrom(from, to, tokenId, "");
+    }
+
+    function safeTransferFrom(
+        address from,
+        address to,
+        uint256 tokenId,
+        bytes memory data
+    ) public {
+        if (!_isApprovedOrOwner(msg.sender, tokenId))
+            revert NotOwnerOrApproved();
+        _transfer(from, to, tokenId);
+        if (!_checkOnERC721Received(from, to, tokenId, data)) revert();
+    }
+
+    // ======================== ACCESS CONTROL ========================
+    function setAdmin(address newAdmin) external onlyAdmin {
+        if (newAdmin == address(0)) revert ZeroAddress();
+        admin = newAdmin;
+    }
+
+    function addMinter(address minter) external onlyAdmin {
+        if (minter == address(0)) revert ZeroAddress();
+        authorizedMinters[minter] = true;
+    }
+
+    function removeMinter(address minter) external onlyAdmin {
+        authorizedMinters[minter] = false;
+    }
+
+    // ======================== MINTING ========================
+    function mint(
+        address recipient,
+        bytes32 skuId,
+        string calldata uri
+    ) external onlyMinter returns (uint256) {
+        if (recipient == address(0)) revert ZeroAddress();
+        if (tokenOfSku[skuId] != 0) revert SkuAlreadyMinted();
+
+        // Token IDs start at 1 so that 0 means "not minted"
+        uint256 tokenId = ++_tokenIdCounter;
+
+        _balances[recipient] += 1;
+        _owners[tokenId] = recipient;
+        tokenOfSku[skuId] = tokenId;
+        skuOfToken[tokenId] = skuId;
+        _tokenURIs[tokenId] = uri;
+
+        emit Transfer(address(0), recipient, tokenId);
+        emit SkuMinted(tokenId, skuId, recipient, uri);
+
+        return tokenId;
+    }
+
+    function totalSupply() external view returns (uint256) {
+        return _tokenIdCounter;
+    }
+
+    // ======================== INTERNAL FUNCTIONS ========================
+    function _exists(uint256 tokenId) internal view returns (bool) {
+        return _owners[tokenId] != address(0);
+    }
+
+    function _transfer(address from, address to, uint256 tokenId) internal {
+        if (ownerOf(tokenId) != from) revert();
+        if (to == address(0)) revert ZeroAddress();
+
+        delete _tokenApprovals[tokenId];
+
+        _balances[from] -= 1;
+        _balances[to] += 1;
+        _owners[tokenId] = to;
+
+        emit Transfer(from, to, tokenId);
+    }
+
+    function _isApprovedOrOwner(
+        address spender,
+        uint256 tokenId
+    ) internal view returns (bool) {
+        if (!_exists(tokenId)) return false;
+        address owner = ownerOf(tokenId);
+        return (spender == owner ||
+            getApproved(tokenId) == spender ||
+            isApprovedForAll(owner, spender));
+    }
+
+    function _checkOnERC721Received(
+        address from,
+        address to,
+        uint256 tokenId,
+        bytes memory data
+    ) private returns (bool) {
+        if (to.code.length == 0) return true;
+        try
+            IERC721Receiver(to).onERC721Received(
+                msg.sender,
+                from,
+                tokenId,
+                data
+            )
+        returns (bytes4 retval) {
+            return retval == IERC721Receiver.onERC721Received.selector;
+        } catch {
+            return false;
+        }
+    }
+
+    // ======================== INTERFACES ========================
+    function supportsInterface(
+        bytes4 interfaceId
+    ) public pure returns (bool) {
+        return
+            interfaceId == 0x01ffc9a7 || // ERC165 Interface ID for ERC165
+            interfaceId == 0x80ac58cd || // ERC165 Interface ID for ERC721
+            interfaceId == 0x5b5e139f; // ERC165 Interface ID for ERC721Metadata
+    }
+}
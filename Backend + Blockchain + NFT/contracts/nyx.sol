@@ -35,6 +35,7 @@ contract OilseedValueChain {
     error LengthMismatch();
     error RevealTooEarly();
     error RevealTooLate();
+    error InvalidTransferType();
 
     constructor() {
         roles[msg.sender] = ROLE_ADMIN;
@@ -116,6 +117,32 @@ contract OilseedValueChain {
         );
     }
 
+    // metadataCID: IPFS CID of the updated profile JSON
+    event FarmerUpdated(
+        bytes32 indexed farmerDID,
+        bytes32 indexed cropIDHash,
+        uint64 timestamp,
+        string metadataCID
+    );
+
+    function updateFarmerMetadata(
+        bytes32 farmerDID,
+        bytes32 cropIDHash,
+        string calldata metadataCID
+    ) external onlyRole(ROLE_FPO) {
+        if (farmers[farmerDID].registeredAt == 0) revert FarmerNotRegistered();
+
+        // Registration time is kept; only the crop can change
+        farmers[farmerDID].cropIDHash = cropIDHash;
+
+        emit FarmerUpdated(
+          farmerDID,
+          cropIDHash,
+          uint64(block.timestamp),
+          metadataCID
+        );
+    }
+
     // ======================== STAGE 2: FPO VERIFICATION ========================
     // On-chain: Batch transfer event + optional IPFS metadata CID
     // Off-chain: Quality report, weight slips, photos on IPFS
@@ -152,6 +179,31 @@ contract OilseedValueChain {
         );
     }
 
+    // Custody changes after the FPO purchase (warehouse, processor, retail),
+    // recorded by whichever custodian hands the batch over
+    function recordOwnershipTransfer(
+        bytes32 batchHash,
+        bytes32 fromDID,
+        address toAddress,
+        uint8 transferType,
+        string calldata metadataCID
+    )
+        external
+        onlyRole(ROLE_FPO | ROLE_WAREHOUSE | ROLE_PROCESSOR | ROLE_PACKAGER)
+    {
+        if (transferType < TRANSFER_WAREHOUSE || transferType > TRANSFER_RETAIL)
+            revert InvalidTransferType();
+
+        emit OwnershipTransfer(
+            batchHash,
+            fromDID,
+            toAddress,
+            uint64(block.timestamp),
+            transferType,
+            metadataCID
+        );
+    }
+
     // ======================== STAGE 3: WAREHOUSE STORAGE ========================
     // On-chain: Timed anchor digest: warehouse state hash + optional CID
     // Off-chain: Continuous IoT logs on IPFS
@@ -462,6 +514,32 @@ contract OilseedValueChain {
         }
     }
 
+    function batchRegisterFarmers(
+        bytes32[] calldata farmerDIDs,
+        bytes32[] calldata cropIDHashes
+    ) external onlyRole(ROLE_FPO) {
+        uint256 length = farmerDIDs.length;
+        if (length != cropIDHashes.length) revert LengthMismatch();
+
+        uint64 timestamp = uint64(block.timestamp);
+
+        for (uint256 i = 0; i < length; ) {
+            if (farmers[farmerDIDs[i]].registeredAt != 0) revert AlreadyRegistered();
+
+            farmers[farmerDIDs[i]] = FarmerRecord({
+                cropIDHash: cropIDHashes[i],
+                registeredAt: timestamp
+            });
+
+            // No CID in batch mode; profiles stay in the off-chain registry
+            emit FarmerRegistered(farmerDIDs[i], cropIDHashes[i], timestamp, "");
+
+            unchecked {
+                ++i;
+            }
+        }
+    }
+
     // ======================== VERIFICATION / VIEW FUNCTIONS ========================
 
     function verifyPackageOrigin(
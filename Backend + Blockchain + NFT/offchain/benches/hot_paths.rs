@@ -0,0 +1,219 @@
+//! Benchmarks for the hot paths behind batch records, unit trees, folder
+//! uploads and batch timelines
+//!
+//! Run with `cargo bench --bench hot_paths`. After the run each benchmark's
+//! mean is compared against its ceiling in `benches/thresholds.json`
+//! (milliseconds) and the run fails when one is exceeded. Benchmarks without
+//! results (filtered out) are skipped; BENCH_THRESHOLDS=off only reports.
+
+use criterion::{criterion_group, BatchSize, BenchmarkId, Criterion, Throughput};
+use offchain::cid_provenance::folder_hash;
+use offchain::hash_schemes::{canonical_json_v2, HashScheme};
+use offchain::indexer::IndexedEvent;
+use offchain::ipfs::folder_files;
+use offchain::merkle::{leaf, merkle_root};
+use offchain::sku_units::UnitTree;
+use offchain::timeline::assemble_entries;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+
+const THRESHOLDS_PATH: &str = "benches/thresholds.json";
+
+/// A stage record shaped like the ones written to batch folders
+fn record(i: usize) -> Value {
+    json!({
+        "batch_id": format!("BATCH-{:06}", i),
+        "warehouse_id": format!("WH-{}", i % 17),
+        "quantity_kg": 1250.5 + i as f64,
+        "moisture_pct": 7.0,
+        "iot_data": {
+            "temperature_c": [21.5, 22.0, 22.25, 23.0],
+            "humidity_pct": [54.0, 55.5, 56.0, 55.0],
+            "sensor": { "id": format!("SENSOR-{}", i), "firmware": "2.4.1" }
+        },
+        "quality": { "grade": "A", "oil_content_pct": 42.0, "free_fatty_acid": 1.25 },
+        "verified": true,
+        "notes": null
+    })
+}
+
+fn canonical_json(c: &mut Criterion) {
+    let mut group = c.benchmark_group("canonical_json");
+    for n in [100, 1000] {
+        let doc = Value::Array((0..n).map(record).collect());
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("serialize", n), &doc, |b, doc| {
+            b.iter(|| canonical_json_v2(doc))
+        });
+        group.bench_with_input(BenchmarkId::new("sha256_json_v2", n), &doc, |b, doc| {
+            b.iter(|| HashScheme::Sha256JsonV2.hash_json(doc).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn merkle(c: &mut Criterion) {
+    let mut group = c.benchmark_group("merkle_root");
+    group.sample_size(20);
+    for n in [1_000, 10_000, 100_000] {
+        let unit_ids: Vec<String> = (0..n).map(|i| format!("UNIT-{:06}", i)).collect();
+        let leaves: Vec<_> = unit_ids.iter().map(|id| leaf(id.as_bytes())).collect();
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("leaves", n), &leaves, |b, leaves| {
+            b.iter(|| merkle_root(leaves))
+        });
+        group.bench_with_input(BenchmarkId::new("unit_tree", n), &unit_ids, |b, ids| {
+            b.iter_batched(
+                || ids.clone(),
+                |ids| UnitTree::build("SKU-BENCH", "BATCH-BENCH", ids).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// Batch folder with `files` stage records, a quarter of them in a subfolder
+fn batch_folder(files: usize) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("ovc-bench-folder-{}-{}", files, std::process::id()));
+    fs::create_dir_all(dir.join("photos")).unwrap();
+    for i in 0..files {
+        let name = if i % 4 == 0 {
+            format!("photos/photo_{}.json", i)
+        } else {
+            format!("record_{}.json", i)
+        };
+        fs::write(
+            dir.join(name),
+            serde_json::to_vec_pretty(&record(i)).unwrap(),
+        )
+        .unwrap();
+    }
+    dir
+}
+
+fn folder_upload(c: &mut Criterion) {
+    let mut group = c.benchmark_group("folder_upload");
+    for n in [10, 100] {
+        let dir = batch_folder(n);
+        let path = dir.to_str().unwrap();
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::new("assemble", n), path, |b, path| {
+            b.iter(|| folder_files(path).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("folder_hash", n), path, |b, path| {
+            b.iter(|| folder_hash(path).unwrap())
+        });
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    group.finish();
+}
+
+const BATCH_HASH: &str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+
+fn event(i: u64) -> IndexedEvent {
+    let (name, fields) = match i % 5 {
+        0 => ("OwnershipTransfer", json!({ "transfer_type": 1 })),
+        1 => ("WarehouseStateUpdated", json!({ "temperature": 22 })),
+        2 => ("LogisticsMilestone", json!({ "is_delivered": false })),
+        3 => ("BatchProcessed", json!({ "output_count": 2 })),
+        _ => ("AIScoreRevealed", json!({ "score": 87 })),
+    };
+    IndexedEvent {
+        block_number: 1_000 + i,
+        log_index: i % 3,
+        tx_hash: format!("0x{:064x}", i),
+        contract: "0x5FbDB2315678afecb367f032d93F642f64180aa3".to_string(),
+        event: name.to_string(),
+        // Newest first, as a mix of sources may return them
+        timestamp: 1_700_000_000 + (10_000 - i) * 60,
+        subject: BATCH_HASH.to_string(),
+        farmer_did: None,
+        metadata_cid: Some(format!("QmBench{}", i)),
+        batch_hashes: vec![BATCH_HASH.to_string()],
+        fields,
+    }
+}
+
+fn timeline(c: &mut Criterion) {
+    let records: HashMap<String, Value> = ["fpo_purchase.json", "processing.json", "ai_score.json"]
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| (name.to_string(), record(i)))
+        .collect();
+    let mut group = c.benchmark_group("timeline");
+    for n in [100, 1000] {
+        // Every event is reached twice, as when batch and warehouse filters overlap
+        let events: Vec<IndexedEvent> = (0..n).chain(0..n).map(event).collect();
+        group.throughput(Throughput::Elements(n));
+        group.bench_with_input(BenchmarkId::new("assemble", n), &events, |b, events| {
+            b.iter_batched(
+                || events.clone(),
+                |events| assemble_entries(events, BATCH_HASH, &records, true),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, canonical_json, merkle, folder_upload, timeline);
+
+fn criterion_home() -> PathBuf {
+    if let Ok(home) = std::env::var("CRITERION_HOME") {
+        return PathBuf::from(home);
+    }
+    std::env::var("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("target"))
+        .join("criterion")
+}
+
+/// Benchmarks whose mean exceeds their ceiling in THRESHOLDS_PATH
+fn regressions() -> Result<Vec<String>, String> {
+    let raw = fs::read_to_string(THRESHOLDS_PATH)
+        .map_err(|e| format!("Failed to read {}: {}", THRESHOLDS_PATH, e))?;
+    let thresholds: BTreeMap<String, f64> =
+        serde_json::from_str(&raw).map_err(|e| format!("Invalid {}: {}", THRESHOLDS_PATH, e))?;
+
+    let mut over = Vec::new();
+    for (id, max_ms) in thresholds {
+        let path = criterion_home().join(&id).join("new/estimates.json");
+        let Ok(raw) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let mean_ns = serde_json::from_str::<Value>(&raw)
+            .ok()
+            .and_then(|v| v["mean"]["point_estimate"].as_f64())
+            .ok_or_else(|| format!("Unreadable estimates in {}", path.display()))?;
+        let mean_ms = mean_ns / 1e6;
+        if mean_ms > max_ms {
+            over.push(format!("  {}: {:.3} ms > {:.3} ms", id, mean_ms, max_ms));
+        }
+    }
+    Ok(over)
+}
+
+fn main() {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+
+    let listing = std::env::args().any(|a| a == "--list" || a == "--test");
+    if listing || std::env::var("BENCH_THRESHOLDS").as_deref() == Ok("off") {
+        return;
+    }
+    match regressions() {
+        Ok(over) if over.is_empty() => {}
+        Ok(over) => {
+            eprintln!("Benchmarks over their thresholds:\n{}", over.join("\n"));
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,21 @@
+//! Canonical JSON v2 of any parsed document must itself parse and be a
+//! fixed point, so re-hashing a fetched document reproduces its hash.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use offchain::hash_schemes::{canonical_json_v2, HashScheme};
+use serde_json::Value;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(value) = serde_json::from_slice::<Value>(data) else {
+        return;
+    };
+    let canonical = canonical_json_v2(&value);
+    let reparsed: Value = serde_json::from_str(&canonical).expect("canonical JSON must parse");
+    assert_eq!(canonical_json_v2(&reparsed), canonical);
+    assert_eq!(
+        HashScheme::Sha256JsonV2.hash_json(&reparsed).unwrap(),
+        HashScheme::Sha256JsonV2.hash_json(&value).unwrap()
+    );
+});
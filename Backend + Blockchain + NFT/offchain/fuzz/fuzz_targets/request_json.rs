@@ -0,0 +1,48 @@
+//! Request bodies that carry free-form JSON (`metadata`, `iot_data`,
+//! `score_data`, ...) must deserialize or fail cleanly, and whatever they
+//! accept must hash under every JSON scheme without panicking.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use offchain::hash_schemes::{HashScheme, VerifyHashRequest};
+use offchain::supply_chain_handlers::{
+    LabResultRequest, LogisticsUpdateRequest, ProcessBatchRequest, RegisterFarmerRequest,
+    RevealAiScoreRequest, WarehouseUpdateRequest,
+};
+use offchain::workflows::CompleteWorkflowData;
+use serde_json::Value;
+
+fn hash_all(value: &Value) {
+    for scheme in HashScheme::ALL {
+        let _ = scheme.hash_json(value);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(req) = serde_json::from_slice::<RegisterFarmerRequest>(data) {
+        hash_all(&req.metadata);
+    }
+    if let Ok(req) = serde_json::from_slice::<WarehouseUpdateRequest>(data) {
+        hash_all(&req.iot_data);
+    }
+    if let Ok(req) = serde_json::from_slice::<LogisticsUpdateRequest>(data) {
+        hash_all(&req.gps_data);
+    }
+    if let Ok(req) = serde_json::from_slice::<ProcessBatchRequest>(data) {
+        hash_all(&req.process_metadata);
+    }
+    if let Ok(req) = serde_json::from_slice::<RevealAiScoreRequest>(data) {
+        hash_all(&req.score_data);
+    }
+    if let Ok(req) = serde_json::from_slice::<LabResultRequest>(data) {
+        hash_all(&req.results);
+    }
+    if let Ok(req) = serde_json::from_slice::<VerifyHashRequest>(data) {
+        let _ = HashScheme::parse(&req.scheme);
+        if let Some(json) = &req.json {
+            hash_all(json);
+        }
+    }
+    let _ = serde_json::from_slice::<CompleteWorkflowData>(data);
+});
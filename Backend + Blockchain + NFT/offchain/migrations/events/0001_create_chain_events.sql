@@ -0,0 +1,35 @@
+-- Contract events indexed from the chain (rebuildable; see src/indexer.rs)
+CREATE TABLE IF NOT EXISTS chain_events (
+    block_number INTEGER NOT NULL,
+    log_index    INTEGER NOT NULL,
+    tx_hash      TEXT NOT NULL,
+    contract     TEXT NOT NULL,
+    event        TEXT NOT NULL,
+    -- Timestamp carried by the event (unix seconds)
+    timestamp    INTEGER NOT NULL,
+    -- Primary indexed id: farmer DID, batch, warehouse, shipment or SKU hash
+    subject      TEXT NOT NULL,
+    farmer_did   TEXT,
+    metadata_cid TEXT,
+    batch_hashes TEXT NOT NULL,
+    fields       TEXT NOT NULL,
+    PRIMARY KEY (block_number, log_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_chain_events_timestamp ON chain_events (timestamp);
+CREATE INDEX IF NOT EXISTS idx_chain_events_farmer ON chain_events (farmer_did);
+CREATE INDEX IF NOT EXISTS idx_chain_events_subject ON chain_events (subject);
+
+-- Batches each event concerns (a processing step has several)
+CREATE TABLE IF NOT EXISTS chain_event_batches (
+    batch_hash   TEXT NOT NULL,
+    block_number INTEGER NOT NULL,
+    log_index    INTEGER NOT NULL,
+    PRIMARY KEY (batch_hash, block_number, log_index)
+);
+
+CREATE TABLE IF NOT EXISTS indexer_cursor (
+    id         INTEGER PRIMARY KEY CHECK (id = 1),
+    -- First block not yet indexed
+    next_block INTEGER NOT NULL
+);
@@ -0,0 +1,26 @@
+-- Summaries folded from chain_events as they are stored (see src/read_models.rs).
+-- Each row keeps the position of the latest event folded into it, so an event
+-- stored again is not counted twice.
+CREATE TABLE IF NOT EXISTS batch_status (
+    -- Batch hash
+    id           TEXT PRIMARY KEY,
+    block_number INTEGER NOT NULL,
+    log_index    INTEGER NOT NULL,
+    model        TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS sku_summary (
+    -- SKU id hash
+    id           TEXT PRIMARY KEY,
+    block_number INTEGER NOT NULL,
+    log_index    INTEGER NOT NULL,
+    model        TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS farmer_summary (
+    -- Farmer DID
+    id           TEXT PRIMARY KEY,
+    block_number INTEGER NOT NULL,
+    log_index    INTEGER NOT NULL,
+    model        TEXT NOT NULL
+);
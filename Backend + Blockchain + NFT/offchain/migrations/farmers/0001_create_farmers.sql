@@ -0,0 +1,15 @@
+-- Farmer verification registry (previously data/farmers_db.json)
+CREATE TABLE IF NOT EXISTS farmers (
+    farmer_did        TEXT PRIMARY KEY NOT NULL,
+    mobile            TEXT NOT NULL UNIQUE,
+    name              TEXT NOT NULL,
+    location          TEXT NOT NULL,
+    state_code        TEXT NOT NULL,
+    district_code     TEXT NOT NULL,
+    land_acres        REAL NOT NULL,
+    crop              TEXT NOT NULL,
+    verified          BOOLEAN NOT NULL,
+    registration_date TEXT NOT NULL,
+    ipfscid           TEXT NOT NULL DEFAULT '',
+    updated_at        TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,14 @@
+-- Audit trail of farmer profile edits (PATCH /api/farmer/:farmer_did):
+-- the values each edit replaced and the ones it wrote, as JSON objects
+CREATE TABLE IF NOT EXISTS farmer_profile_changes (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    farmer_did  TEXT NOT NULL,
+    changed_at  TEXT NOT NULL,
+    changed_by  TEXT NOT NULL,
+    previous    TEXT NOT NULL,
+    updated     TEXT NOT NULL,
+    ipfscid     TEXT NOT NULL DEFAULT '',
+    tx_hash     TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_farmer_profile_changes_did ON farmer_profile_changes (farmer_did, id);
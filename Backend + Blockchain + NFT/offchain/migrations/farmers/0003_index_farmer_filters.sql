@@ -0,0 +1,4 @@
+-- Filters of GET /api/farmers
+CREATE INDEX IF NOT EXISTS idx_farmers_region
+    ON farmers (state_code COLLATE NOCASE, district_code COLLATE NOCASE);
+CREATE INDEX IF NOT EXISTS idx_farmers_crop ON farmers (crop COLLATE NOCASE);
@@ -0,0 +1,21 @@
+-- Chain writes the transaction queue gave up on, retried by the outbox
+-- worker (see src/outbox.rs)
+CREATE TABLE IF NOT EXISTS outbox (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- Contract function, e.g. fpoPurchase
+    label           TEXT NOT NULL,
+    to_address      TEXT,
+    -- 0x-prefixed hex
+    calldata        TEXT NOT NULL,
+    -- pending, done or abandoned
+    status          TEXT NOT NULL,
+    attempts        INTEGER NOT NULL DEFAULT 0,
+    last_error      TEXT,
+    -- Unix seconds
+    next_attempt_at INTEGER NOT NULL,
+    tx_hash         TEXT,
+    created_at      TEXT NOT NULL,
+    updated_at      TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_outbox_due ON outbox (status, next_attempt_at);
@@ -0,0 +1,377 @@
+//! Processor input-lot acceptance
+//!
+//! A processor inspects each incoming batch before processing it and
+//! records the outcome at `POST /api/processing/acceptance`: accepted or
+//! rejected, with reasons, inspection photos (uploaded with purpose
+//! `acceptance` and the batch ID as reference) and the grade the processor
+//! measured, checked against the crop's grade taxonomy. The decision is
+//! written to `acceptance.json` in the batch folder, so it is part of the
+//! metadata hashed on-chain with the processing record, and kept in
+//! `data/lot_acceptance.json`. A lot may be re-inspected; the latest
+//! decision counts.
+//!
+//! A rejected batch cannot be processed. A batch with no decision cannot
+//! be processed either unless LOT_ACCEPTANCE_REQUIRED is set to false.
+//!
+//! `GET /api/quality/stats` feeds the outcomes back to the suppliers: per
+//! FPO and per farmer, how many lots were accepted, and how far the
+//! processor's grade score drifted from the grade declared at purchase.
+
+use crate::batch_ledger;
+use crate::error::{ApiError, ApiResult};
+use crate::hash_schemes::{record_folder_hash, HashRecord};
+use crate::photo_evidence::{PhotoEvidence, PhotoPurpose};
+use crate::state::AppState;
+use crate::supply_chain_handlers::batch_folder;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tokio::sync::Mutex;
+
+const ACCEPTANCE_PATH: &str = "data/lot_acceptance.json";
+const MAX_REASONS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LotDecision {
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LotAcceptance {
+    pub batch_id: String,
+    pub decision: LotDecision,
+    pub reasons: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inspector: Option<String>,
+    pub crop_type: String,
+    /// Grade declared at purchase and its score
+    pub declared_grade: String,
+    pub declared_score: f64,
+    /// Grade measured by the processor and its score
+    pub regraded_grade: String,
+    pub regraded_score: f64,
+    /// Image CIDs of the inspection photos
+    #[serde(default)]
+    pub photos: Vec<String>,
+    pub farmer_did: String,
+    /// `submitted_by` of the purchase
+    pub fpo: String,
+    pub decided_at: String,
+}
+
+impl LotAcceptance {
+    /// Regraded minus declared score; negative when the lot was worse than
+    /// declared
+    pub fn grade_drift(&self) -> f64 {
+        self.regraded_score - self.declared_score
+    }
+}
+
+fn acceptance_required() -> bool {
+    std::env::var("LOT_ACCEPTANCE_REQUIRED")
+        .map(|v| v != "false" && v != "0")
+        .unwrap_or(true)
+}
+
+pub struct AcceptanceStore {
+    /// Every decision, oldest first
+    decisions: Mutex<Vec<LotAcceptance>>,
+    required: bool,
+}
+
+impl AcceptanceStore {
+    pub fn load() -> Result<Self> {
+        let decisions = match std::fs::read_to_string(ACCEPTANCE_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid lot acceptance file {}", ACCEPTANCE_PATH))?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Self {
+            decisions: Mutex::new(decisions),
+            required: acceptance_required(),
+        })
+    }
+
+    fn save(decisions: &[LotAcceptance]) -> Result<()> {
+        std::fs::write(ACCEPTANCE_PATH, serde_json::to_string_pretty(decisions)?)
+            .with_context(|| format!("Failed to write {}", ACCEPTANCE_PATH))
+    }
+
+    /// Latest decision on `batch_id`
+    pub async fn latest(&self, batch_id: &str) -> Option<LotAcceptance> {
+        self.decisions
+            .lock()
+            .await
+            .iter()
+            .rev()
+            .find(|d| d.batch_id == batch_id)
+            .cloned()
+    }
+
+    /// Refuse to process a batch the processor rejected or has not accepted
+    pub async fn ensure_accepted(&self, batch_id: &str) -> Result<(), ApiError> {
+        match self.latest(batch_id).await {
+            Some(d) if d.decision == LotDecision::Accepted => Ok(()),
+            Some(d) => {
+                tracing::warn!(batch_id = %batch_id, "Refused to process rejected lot");
+                Err(ApiError::new(
+                    StatusCode::CONFLICT,
+                    format!(
+                        "Batch {} was rejected by the processor: {}",
+                        batch_id,
+                        d.reasons.join("; ")
+                    ),
+                ))
+            }
+            None if self.required => Err(ApiError::new(
+                StatusCode::CONFLICT,
+                format!(
+                    "Batch {} has not been accepted by the processor yet",
+                    batch_id
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Latest decision of every batch
+    async fn current(&self) -> Vec<LotAcceptance> {
+        let decisions = self.decisions.lock().await;
+        let mut latest: BTreeMap<&str, &LotAcceptance> = BTreeMap::new();
+        for decision in decisions.iter() {
+            latest.insert(&decision.batch_id, decision);
+        }
+        latest.into_values().cloned().collect()
+    }
+}
+
+// ======================== QUALITY STATISTICS ========================
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct SupplierQuality {
+    pub lots: usize,
+    pub accepted: usize,
+    pub rejected: usize,
+    pub acceptance_rate: f64,
+    /// Mean regraded minus declared score
+    pub avg_grade_drift: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QualityStats {
+    pub by_fpo: BTreeMap<String, SupplierQuality>,
+    pub by_farmer: BTreeMap<String, SupplierQuality>,
+}
+
+fn add(stats: &mut SupplierQuality, decision: &LotAcceptance) {
+    let lots = stats.lots as f64;
+    stats.avg_grade_drift = (stats.avg_grade_drift * lots + decision.grade_drift()) / (lots + 1.0);
+    stats.lots += 1;
+    match decision.decision {
+        LotDecision::Accepted => stats.accepted += 1,
+        LotDecision::Rejected => stats.rejected += 1,
+    }
+    stats.acceptance_rate = stats.accepted as f64 / stats.lots as f64;
+}
+
+/// Acceptance rate and grade drift per FPO and per farmer
+pub fn quality_stats(decisions: &[LotAcceptance]) -> QualityStats {
+    let mut stats = QualityStats {
+        by_fpo: BTreeMap::new(),
+        by_farmer: BTreeMap::new(),
+    };
+    for decision in decisions {
+        add(
+            stats.by_fpo.entry(decision.fpo.clone()).or_default(),
+            decision,
+        );
+        add(
+            stats
+                .by_farmer
+                .entry(decision.farmer_did.clone())
+                .or_default(),
+            decision,
+        );
+    }
+    stats
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct AcceptanceRequest {
+    pub batch_id: String,
+    pub decision: LotDecision,
+    #[serde(default)]
+    pub reasons: Vec<String>,
+    /// Grade the processor measured, in the crop's taxonomy
+    pub quality_grade: String,
+    #[serde(default)]
+    pub photos: Vec<String>,
+    #[serde(default)]
+    pub inspector: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AcceptanceResponse {
+    #[serde(flatten)]
+    pub acceptance: LotAcceptance,
+    pub metadata_cid: String,
+}
+
+fn purchase_field<'a>(purchase: &'a Value, pointer: &str) -> &'a str {
+    purchase
+        .pointer(pointer)
+        .and_then(Value::as_str)
+        .unwrap_or("")
+}
+
+/// Accept or reject an incoming batch before processing it
+pub async fn record_acceptance(
+    State(state): State<AppState>,
+    Json(payload): Json<AcceptanceRequest>,
+) -> ApiResult<AcceptanceResponse> {
+    let purchase = batch_ledger::fpo_purchase(&payload.batch_id)?.ok_or_else(|| {
+        ApiError::not_found(format!("No FPO purchase for batch {}", payload.batch_id))
+    })?;
+    let reasons: Vec<String> = payload
+        .reasons
+        .iter()
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .collect();
+    if payload.decision == LotDecision::Rejected && reasons.is_empty() {
+        return Err(ApiError::bad_request(
+            "A rejection needs at least one reason",
+        ));
+    }
+    if reasons.len() > MAX_REASONS {
+        return Err(ApiError::bad_request(format!(
+            "At most {} reasons per decision",
+            MAX_REASONS
+        )));
+    }
+
+    let crop_type = purchase_field(&purchase, "/farmer_info/crop_type").to_string();
+    let regraded = state.grades.resolve(&crop_type, &payload.quality_grade)?;
+    let photos = state
+        .photos
+        .resolve(&payload.photos, PhotoPurpose::Acceptance, &payload.batch_id)
+        .await?;
+
+    let acceptance = LotAcceptance {
+        batch_id: payload.batch_id.clone(),
+        decision: payload.decision,
+        reasons,
+        inspector: payload.inspector.filter(|i| !i.trim().is_empty()),
+        declared_grade: purchase_field(&purchase, "/batch_info/quality_grade").to_string(),
+        declared_score: purchase
+            .pointer("/batch_info/grade_score")
+            .and_then(Value::as_f64)
+            .unwrap_or(0.0),
+        regraded_grade: regraded.code.clone(),
+        regraded_score: regraded.score,
+        crop_type,
+        photos: photos.iter().map(|p| p.image_cid.clone()).collect(),
+        farmer_did: purchase_field(&purchase, "/farmer_info/farmer_did").to_string(),
+        fpo: purchase_field(&purchase, "/submitted_by").to_string(),
+        decided_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    // Save the decision in the batch folder next to the other stages
+    let folder = batch_folder(&payload.batch_id);
+    let mut record = serde_json::to_value(&acceptance).map_err(anyhow::Error::from)?;
+    record["photos"] = photos.iter().map(PhotoEvidence::metadata_entry).collect();
+    state
+        .ipfs_client
+        .write_json_to_folder(&folder, "acceptance.json", &record)
+        .map_err(ApiError::ipfs_upload_failed)?;
+    let hash = HashRecord::of_json(&record).map_err(ApiError::from)?;
+    record_folder_hash(&folder, "acceptance.json", hash).map_err(ApiError::ipfs_upload_failed)?;
+    let metadata_cid = state
+        .ipfs_client
+        .upload_folder(&folder)
+        .await
+        .map_err(ApiError::ipfs_upload_failed)?;
+
+    let mut decisions = state.acceptance.decisions.lock().await;
+    decisions.push(acceptance.clone());
+    AcceptanceStore::save(&decisions)?;
+
+    tracing::info!(
+        batch_id = %acceptance.batch_id,
+        decision = ?acceptance.decision,
+        drift = acceptance.grade_drift(),
+        "Lot acceptance recorded"
+    );
+    Ok(Json(AcceptanceResponse {
+        acceptance,
+        metadata_cid,
+    }))
+}
+
+pub async fn get_acceptance(
+    State(state): State<AppState>,
+    Path(batch_id): Path<String>,
+) -> ApiResult<LotAcceptance> {
+    state
+        .acceptance
+        .latest(&batch_id)
+        .await
+        .map(Json)
+        .ok_or_else(|| {
+            ApiError::not_found(format!("No acceptance decision for batch {}", batch_id))
+        })
+}
+
+pub async fn get_quality_stats(State(state): State<AppState>) -> ApiResult<QualityStats> {
+    let decisions = state.acceptance.current().await;
+    Ok(Json(quality_stats(&decisions)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decision(batch: &str, fpo: &str, decision: LotDecision, regraded: f64) -> LotAcceptance {
+        LotAcceptance {
+            batch_id: batch.to_string(),
+            decision,
+            reasons: Vec::new(),
+            inspector: None,
+            crop_type: "groundnut".to_string(),
+            declared_grade: "A".to_string(),
+            declared_score: 90.0,
+            regraded_grade: "B".to_string(),
+            regraded_score: regraded,
+            photos: Vec::new(),
+            farmer_did: format!("did:farmer:{}", batch),
+            fpo: fpo.to_string(),
+            decided_at: "2025-01-01T00:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_quality_stats_per_fpo() {
+        let stats = quality_stats(&[
+            decision("B-1", "user:fpo1", LotDecision::Accepted, 90.0),
+            decision("B-2", "user:fpo1", LotDecision::Rejected, 60.0),
+            decision("B-3", "user:fpo2", LotDecision::Accepted, 80.0),
+        ]);
+
+        let fpo1 = &stats.by_fpo["user:fpo1"];
+        assert_eq!((fpo1.lots, fpo1.accepted, fpo1.rejected), (2, 1, 1));
+        assert_eq!(fpo1.acceptance_rate, 0.5);
+        assert_eq!(fpo1.avg_grade_drift, -15.0);
+        assert_eq!(stats.by_fpo["user:fpo2"].avg_grade_drift, -10.0);
+        assert_eq!(stats.by_farmer.len(), 3);
+    }
+}
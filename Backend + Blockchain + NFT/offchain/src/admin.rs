@@ -0,0 +1,307 @@
+use crate::auth::{self, Role};
+use crate::chain::roles;
+use crate::error::{format_tx_hash, ApiError, ApiResult};
+use crate::logging::build_directives;
+use crate::slowlog::{self, SlowLogEntry, SlowOperation};
+use crate::state::AppState;
+use alloy::primitives::Address;
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+// ======================== ADMIN AUTHENTICATION ========================
+
+/// Check the `Authorization: Bearer <ADMIN_API_TOKEN>` header.
+///
+/// A signed-in user with the admin role (see [`crate::auth`]) also passes.
+/// Admin endpoints are disabled entirely when ADMIN_API_TOKEN is not set.
+pub fn require_admin(state: &AppState, headers: &HeaderMap) -> Result<(), ApiError> {
+    if let Some(token) = auth::bearer_token(headers).filter(|t| auth::is_jwt(t)) {
+        let mut claims = state.auth.verify(token)?;
+        state.chain_roles.apply_to_claims(&mut claims)?;
+        if !claims.has_role(Role::Admin) {
+            return Err(ApiError::forbidden("Requires role: admin"));
+        }
+        return Ok(());
+    }
+
+    let expected = state
+        .admin_token
+        .as_deref()
+        .ok_or_else(|| ApiError::forbidden("Admin API is disabled (ADMIN_API_TOKEN not set)"))?;
+
+    let provided = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or_else(|| ApiError::unauthorized("Missing admin bearer token"))?;
+
+    if provided != expected {
+        tracing::warn!("Rejected admin request with invalid token");
+        return Err(ApiError::unauthorized("Invalid admin token"));
+    }
+
+    Ok(())
+}
+
+// ======================== LOG LEVEL ========================
+
+#[derive(Debug, Serialize)]
+pub struct LogLevelResponse {
+    pub active_filter: String,
+    pub base_filter: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reverts_in_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetLogLevelRequest {
+    /// Full EnvFilter directive string, e.g. "offchain=trace,tower_http=info"
+    #[serde(default)]
+    pub filter: Option<String>,
+    /// Per-target levels applied on top of `filter` (or the current filter)
+    #[serde(default)]
+    pub targets: BTreeMap<String, String>,
+    /// Seconds until the base filter is restored; 0 keeps the change until restart
+    #[serde(default)]
+    pub revert_after_secs: Option<u64>,
+}
+
+pub async fn get_log_level(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<LogLevelResponse> {
+    require_admin(&state, &headers)?;
+
+    Ok(Json(LogLevelResponse {
+        active_filter: state.log_control.active_filter().await,
+        base_filter: state.log_control.base_filter().to_string(),
+        reverts_in_secs: None,
+    }))
+}
+
+pub async fn set_log_level(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SetLogLevelRequest>,
+) -> ApiResult<LogLevelResponse> {
+    require_admin(&state, &headers)?;
+
+    if payload.filter.is_none() && payload.targets.is_empty() {
+        return Err(ApiError::bad_request(
+            "Provide either 'filter' or at least one entry in 'targets'",
+        ));
+    }
+
+    let base = match &payload.filter {
+        Some(filter) => filter.clone(),
+        None => state.log_control.active_filter().await,
+    };
+    let directives = build_directives(Some(&base), &payload.targets);
+
+    let revert_after = match payload.revert_after_secs {
+        Some(0) => None,
+        Some(secs) => Some(Duration::from_secs(secs)),
+        None => state.log_control.default_revert(),
+    };
+
+    state
+        .log_control
+        .apply(&directives, revert_after)
+        .await
+        .map_err(|e| ApiError::bad_request(format!("{:#}", e)))?;
+
+    Ok(Json(LogLevelResponse {
+        active_filter: directives,
+        base_filter: state.log_control.base_filter().to_string(),
+        reverts_in_secs: revert_after.map(|d| d.as_secs()),
+    }))
+}
+
+// ======================== SLOW LOG ========================
+
+#[derive(Debug, Deserialize)]
+pub struct SlowLogQuery {
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SlowLogThresholds {
+    pub ipfs_upload_ms: u64,
+    pub receipt_wait_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SlowLogResponse {
+    pub capacity: usize,
+    pub thresholds: SlowLogThresholds,
+    pub entries: Vec<SlowLogEntry>,
+}
+
+pub async fn get_slowlog(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SlowLogQuery>,
+) -> ApiResult<SlowLogResponse> {
+    require_admin(&state, &headers)?;
+
+    let log = slowlog::global();
+    let limit = query.limit.unwrap_or(log.capacity());
+
+    Ok(Json(SlowLogResponse {
+        capacity: log.capacity(),
+        thresholds: SlowLogThresholds {
+            ipfs_upload_ms: SlowOperation::IpfsUpload.threshold().as_millis() as u64,
+            receipt_wait_ms: SlowOperation::ReceiptWait.threshold().as_millis() as u64,
+        },
+        entries: log.recent(limit),
+    }))
+}
+
+pub async fn clear_slowlog(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<serde_json::Value> {
+    require_admin(&state, &headers)?;
+
+    slowlog::global().clear();
+
+    Ok(Json(serde_json::json!({ "success": true })))
+}
+
+// ======================== ON-CHAIN ROLES ========================
+
+fn parse_account(account: &str) -> Result<Address, ApiError> {
+    account
+        .parse()
+        .map_err(|e| ApiError::bad_request(format!("Invalid account address: {}", e)))
+}
+
+fn parse_roles(names: &[String]) -> Result<u64, ApiError> {
+    if names.is_empty() {
+        return Err(ApiError::bad_request("Provide at least one role"));
+    }
+    roles::from_names(names).map_err(|e| ApiError::bad_request(e.to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoleChangeRequest {
+    pub account: String,
+    /// Role names, e.g. ["PACKAGER", "AI_ORACLE"]
+    pub roles: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoleChangeResponse {
+    pub tx_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
+    pub account: String,
+    /// Roles held by the account after the transaction
+    pub roles: Vec<&'static str>,
+}
+
+async fn role_summary(state: &AppState, account: Address) -> Result<Vec<&'static str>, ApiError> {
+    let bits = state
+        .chain()
+        .get_roles(account)
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+    Ok(roles::names(bits.saturating_to()))
+}
+
+pub async fn grant_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<RoleChangeRequest>,
+) -> ApiResult<RoleChangeResponse> {
+    require_admin(&state, &headers)?;
+
+    let account = parse_account(&payload.account)?;
+    let role = parse_roles(&payload.roles)?;
+
+    let receipt = state
+        .chain()
+        .grant_role(account, role)
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+
+    Ok(Json(RoleChangeResponse {
+        tx_hash: format_tx_hash(receipt.transaction_hash),
+        explorer_url: state.chain().explorer_url(receipt.transaction_hash),
+        account: payload.account,
+        roles: role_summary(&state, account).await?,
+    }))
+}
+
+pub async fn revoke_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<RoleChangeRequest>,
+) -> ApiResult<RoleChangeResponse> {
+    require_admin(&state, &headers)?;
+
+    let account = parse_account(&payload.account)?;
+    let role = parse_roles(&payload.roles)?;
+
+    let receipt = state
+        .chain()
+        .revoke_role(account, role)
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+
+    Ok(Json(RoleChangeResponse {
+        tx_hash: format_tx_hash(receipt.transaction_hash),
+        explorer_url: state.chain().explorer_url(receipt.transaction_hash),
+        account: payload.account,
+        roles: role_summary(&state, account).await?,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoleCheckQuery {
+    pub account: String,
+    /// Role name to test; only the full role list is returned when absent
+    #[serde(default)]
+    pub role: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoleCheckResponse {
+    pub account: String,
+    pub roles: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub has_role: Option<bool>,
+}
+
+pub async fn check_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<RoleCheckQuery>,
+) -> ApiResult<RoleCheckResponse> {
+    require_admin(&state, &headers)?;
+
+    let account = parse_account(&query.account)?;
+    let has_role = match &query.role {
+        Some(name) => Some(
+            state
+                .chain()
+                .has_role(account, parse_roles(std::slice::from_ref(name))?)
+                .await
+                .map_err(ApiError::blockchain_failed)?,
+        ),
+        None => None,
+    };
+
+    Ok(Json(RoleCheckResponse {
+        account: query.account,
+        roles: role_summary(&state, account).await?,
+        has_role,
+    }))
+}
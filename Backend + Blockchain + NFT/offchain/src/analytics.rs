@@ -0,0 +1,81 @@
+//! Snapshot reads for analytics
+//!
+//! Scheduled reports, scheme entitlements, auditor exports and the public
+//! statistics read the farmer registry and the event index through a pool of
+//! read-only connections of their own per database (ANALYTICS_MAX_CONNECTIONS,
+//! default 2). Both databases run in WAL mode, where a read works on the
+//! snapshot it started on: it does not wait for writes, does not hold them up
+//! and sees none that commit while it runs. However long an export takes,
+//! live requests keep the connections of the main pools.
+//!
+//! An in-memory database cannot be shared between pools, so stores opened on
+//! one (tests) read analytics through their main pool.
+
+use anyhow::{Context, Result};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+
+const DEFAULT_MAX_CONNECTIONS: u32 = 2;
+
+/// Read-only pool over the database `options` open
+pub async fn open_pool(options: &SqliteConnectOptions) -> Result<SqlitePool> {
+    let max_connections = std::env::var("ANALYTICS_MAX_CONNECTIONS")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_MAX_CONNECTIONS)
+        .max(1);
+    SqlitePoolOptions::new()
+        .max_connections(max_connections)
+        .connect_with(options.clone().read_only(true))
+        .await
+        .context("Failed to open analytics connections")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqliteJournalMode;
+
+    #[tokio::test]
+    async fn test_snapshot_read_neither_blocks_nor_sees_live_writes() {
+        let dir =
+            std::env::temp_dir().join(format!("ovc-analytics-test-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let options = SqliteConnectOptions::new()
+            .filename(dir.join("store.db"))
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal);
+        let live = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options.clone())
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE rows (id INTEGER PRIMARY KEY)")
+            .execute(&live)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO rows (id) VALUES (1)")
+            .execute(&live)
+            .await
+            .unwrap();
+
+        let analytics = open_pool(&options).await.unwrap();
+        let mut export = analytics.begin().await.unwrap();
+        let count = || sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM rows");
+        assert_eq!(count().fetch_one(&mut *export).await.unwrap(), 1);
+
+        // Commits while the export is open, without waiting for it
+        sqlx::query("INSERT INTO rows (id) VALUES (2)")
+            .execute(&live)
+            .await
+            .unwrap();
+        assert_eq!(count().fetch_one(&mut *export).await.unwrap(), 1);
+        export.commit().await.unwrap();
+        assert_eq!(count().fetch_one(&analytics).await.unwrap(), 2);
+
+        assert!(sqlx::query("INSERT INTO rows (id) VALUES (3)")
+            .execute(&analytics)
+            .await
+            .is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
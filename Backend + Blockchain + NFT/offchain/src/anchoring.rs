@@ -0,0 +1,416 @@
+//! Periodic anchoring of primary-chain events to a public L1
+//!
+//! When the primary chain is a permissioned or L2 network, its history is
+//! only as trustworthy as its operators. Every ANCHOR_INTERVAL_SECS the
+//! anchor job collects the contract events of newly confirmed blocks, builds
+//! a Merkle tree over their hashes (see [`EventRef::leaf`]) and posts the
+//! root to the L1 configured by ANCHOR_L1_RPC_URL.
+//!
+//! Anchors are stored in `data/anchors/`. An inclusion proof links any
+//! event (tx hash + log index) to the L1 transaction that carries the root,
+//! so a third party can check it with nothing but an L1 node.
+
+use crate::admin::require_admin;
+use crate::chain::{AnchorClient, EventRef};
+use crate::error::{format_hash, format_tx_hash, ApiError, ApiResult};
+use crate::hash_schemes::HashScheme;
+use crate::merkle::{merkle_proof, merkle_root, MERKLE_HASH_SCHEME};
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use alloy::primitives::FixedBytes;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+const ANCHORS_DIR: &str = "data/anchors";
+const DEFAULT_INTERVAL_SECS: u64 = 3600;
+const DEFAULT_CONFIRMATIONS: u64 = 12;
+const DEFAULT_MAX_BLOCK_RANGE: u64 = 5000;
+
+// ======================== STORAGE ========================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Anchor {
+    pub id: u64,
+    pub from_block: u64,
+    pub to_block: u64,
+    pub root: FixedBytes<32>,
+    pub l1_chain_id: u64,
+    pub l1_tx_hash: FixedBytes<32>,
+    pub l1_block: Option<u64>,
+    pub anchored_at: String,
+    pub events: Vec<EventRef>,
+    /// Scheme of the event leaves and tree nodes
+    #[serde(default)]
+    pub hash_scheme: HashScheme,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct AnchorCursor {
+    /// First primary-chain block not yet covered by an anchor
+    next_block: Option<u64>,
+}
+
+/// Anchors written so far plus the block cursor of the job
+pub struct AnchorStore {
+    anchors: Mutex<Vec<Anchor>>,
+    cursor: Mutex<AnchorCursor>,
+}
+
+impl AnchorStore {
+    pub fn load() -> Result<Self> {
+        let mut anchors = Vec::new();
+        let mut cursor = AnchorCursor::default();
+
+        if let Ok(entries) = std::fs::read_dir(ANCHORS_DIR) {
+            for entry in entries.filter_map(|e| e.ok()) {
+                let path = entry.path();
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                if entry.file_name() == "cursor.json" {
+                    cursor = serde_json::from_str(&content)
+                        .with_context(|| format!("Invalid anchor cursor {}", path.display()))?;
+                } else {
+                    anchors.push(
+                        serde_json::from_str::<Anchor>(&content)
+                            .with_context(|| format!("Invalid anchor {}", path.display()))?,
+                    );
+                }
+            }
+        }
+        anchors.sort_by_key(|a| a.id);
+
+        Ok(Self {
+            anchors: Mutex::new(anchors),
+            cursor: Mutex::new(cursor),
+        })
+    }
+
+    fn save_json(filename: &str, value: &impl Serialize) -> Result<()> {
+        std::fs::create_dir_all(ANCHORS_DIR)
+            .with_context(|| format!("Failed to create {}", ANCHORS_DIR))?;
+        let path = std::path::Path::new(ANCHORS_DIR).join(filename);
+        std::fs::write(&path, serde_json::to_string_pretty(value)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Anchor containing an event, with the event's position in it
+    async fn find_event(
+        &self,
+        tx_hash: FixedBytes<32>,
+        log_index: Option<u64>,
+    ) -> Option<(Anchor, usize)> {
+        let anchors = self.anchors.lock().await;
+        anchors.iter().find_map(|anchor| {
+            anchor
+                .events
+                .iter()
+                .position(|e| e.tx_hash == tx_hash && log_index.is_none_or(|i| e.log_index == i))
+                .map(|index| (anchor.clone(), index))
+        })
+    }
+}
+
+// ======================== ANCHOR JOB ========================
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Blocks the next anchor covers, or `None` while no new block is confirmed
+fn block_range(
+    next_block: Option<u64>,
+    start_block: Option<u64>,
+    latest: u64,
+    confirmations: u64,
+    max_range: u64,
+) -> Option<(u64, u64)> {
+    let confirmed = latest.checked_sub(confirmations)?;
+    // First run starts at ANCHOR_START_BLOCK, or only covers new blocks
+    let from_block = next_block.or(start_block).unwrap_or(confirmed);
+    if from_block > confirmed {
+        return None;
+    }
+    Some((from_block, confirmed.min(from_block + max_range.max(1) - 1)))
+}
+
+/// Anchor the next range of confirmed blocks; returns the new anchor, if any
+pub async fn run_once(state: &AppState, l1: &AnchorClient) -> Result<Option<Anchor>> {
+    let confirmations = env_u64("ANCHOR_CONFIRMATIONS", DEFAULT_CONFIRMATIONS);
+    let max_range = env_u64("ANCHOR_MAX_BLOCK_RANGE", DEFAULT_MAX_BLOCK_RANGE);
+    let start_block = std::env::var("ANCHOR_START_BLOCK")
+        .ok()
+        .and_then(|v| v.parse().ok());
+
+    let mut cursor = state.anchors.cursor.lock().await;
+
+    let latest = state.chain().latest_block().await?;
+    let Some((from_block, to_block)) = block_range(
+        cursor.next_block,
+        start_block,
+        latest,
+        confirmations,
+        max_range,
+    ) else {
+        return Ok(None);
+    };
+
+    let events = state.chain().contract_events(from_block, to_block).await?;
+
+    let anchor = if events.is_empty() {
+        None
+    } else {
+        let leaves: Vec<FixedBytes<32>> = events.iter().map(EventRef::leaf).collect();
+        let root = merkle_root(&leaves);
+        let receipt = l1.post_digest(root, from_block, to_block).await?;
+
+        let mut anchors = state.anchors.anchors.lock().await;
+        let anchor = Anchor {
+            id: anchors.last().map(|a| a.id + 1).unwrap_or(1),
+            from_block,
+            to_block,
+            root,
+            l1_chain_id: l1.chain_id(),
+            l1_tx_hash: receipt.transaction_hash,
+            l1_block: receipt.block_number,
+            anchored_at: chrono::Utc::now().to_rfc3339(),
+            events,
+            hash_scheme: MERKLE_HASH_SCHEME,
+        };
+        AnchorStore::save_json(&format!("{:08}.json", anchor.id), &anchor)?;
+        anchors.push(anchor.clone());
+        Some(anchor)
+    };
+
+    cursor.next_block = Some(to_block + 1);
+    AnchorStore::save_json("cursor.json", &*cursor)?;
+
+    Ok(anchor)
+}
+
+/// Start the periodic anchor job if an L1 is configured
+pub fn spawn(state: AppState) {
+    let Some(l1) = state.anchor_client.clone() else {
+        tracing::info!("ANCHOR_L1_RPC_URL not set, L1 anchoring disabled");
+        return;
+    };
+    let interval =
+        Duration::from_secs(env_u64("ANCHOR_INTERVAL_SECS", DEFAULT_INTERVAL_SECS).max(1));
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match run_once(&state, &l1).await {
+                Ok(Some(anchor)) => tracing::info!(
+                    id = anchor.id,
+                    events = anchor.events.len(),
+                    "Anchor job posted digest"
+                ),
+                Ok(None) => tracing::debug!("Anchor job found no new events"),
+                Err(e) => tracing::error!(error = %format!("{:#}", e), "Anchor job failed"),
+            }
+        }
+    });
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct AnchorSummary {
+    pub id: u64,
+    pub from_block: u64,
+    pub to_block: u64,
+    pub root: String,
+    pub event_count: usize,
+    pub l1_chain_id: u64,
+    pub l1_tx_hash: String,
+    pub l1_block: Option<u64>,
+    pub anchored_at: String,
+    pub hash_scheme: HashScheme,
+}
+
+impl From<&Anchor> for AnchorSummary {
+    fn from(anchor: &Anchor) -> Self {
+        Self {
+            id: anchor.id,
+            from_block: anchor.from_block,
+            to_block: anchor.to_block,
+            root: format_hash(anchor.root),
+            event_count: anchor.events.len(),
+            l1_chain_id: anchor.l1_chain_id,
+            l1_tx_hash: format_tx_hash(anchor.l1_tx_hash),
+            l1_block: anchor.l1_block,
+            anchored_at: anchor.anchored_at.clone(),
+            hash_scheme: anchor.hash_scheme,
+        }
+    }
+}
+
+pub async fn list_anchors(
+    State(state): State<AppState>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<AnchorSummary>> {
+    let summaries: Vec<AnchorSummary> = state
+        .anchors
+        .anchors
+        .lock()
+        .await
+        .iter()
+        .map(AnchorSummary::from)
+        .collect();
+
+    Ok(Json(paginate("anchors", summaries, &params, |a| a.id)?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InclusionProofQuery {
+    /// Required when a transaction emitted more than one event
+    #[serde(default)]
+    pub log_index: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InclusionProof {
+    pub event: EventRef,
+    pub leaf: FixedBytes<32>,
+    pub proof: Vec<FixedBytes<32>>,
+    pub anchor: AnchorSummary,
+    /// Calldata prefix of the L1 transaction: "OVC1" || root || from_block || to_block
+    pub l1_calldata_prefix: String,
+}
+
+pub async fn inclusion_proof(
+    State(state): State<AppState>,
+    Path(tx_hash): Path<String>,
+    Query(query): Query<InclusionProofQuery>,
+) -> ApiResult<InclusionProof> {
+    let tx_hash: FixedBytes<32> = tx_hash
+        .parse()
+        .map_err(|e| ApiError::invalid_hash("tx_hash", e))?;
+
+    let (anchor, index) = state
+        .anchors
+        .find_event(tx_hash, query.log_index)
+        .await
+        .ok_or_else(|| ApiError::not_found("Event not anchored yet"))?;
+    if anchor.hash_scheme != MERKLE_HASH_SCHEME {
+        return Err(ApiError::internal(format!(
+            "Anchor {} uses hash scheme {}, which this build cannot prove",
+            anchor.id,
+            anchor.hash_scheme.id()
+        )));
+    }
+
+    let leaves: Vec<FixedBytes<32>> = anchor.events.iter().map(EventRef::leaf).collect();
+    let event = anchor.events[index];
+    let leaf = event.leaf();
+    let proof = merkle_proof(&leaves, index);
+
+    Ok(Json(InclusionProof {
+        event,
+        leaf,
+        proof,
+        l1_calldata_prefix: format!("0x{}", hex::encode(crate::chain::ANCHOR_MAGIC)),
+        anchor: AnchorSummary::from(&anchor),
+    }))
+}
+
+/// Run the anchor job immediately (admin only)
+pub async fn trigger_anchor(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<Option<AnchorSummary>> {
+    require_admin(&state, &headers)?;
+
+    let l1 = state
+        .anchor_client
+        .clone()
+        .ok_or_else(|| ApiError::bad_request("L1 anchoring is not configured"))?;
+
+    let anchor = run_once(&state, &l1)
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+
+    Ok(Json(anchor.as_ref().map(AnchorSummary::from)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::verify_merkle_proof;
+
+    fn event(byte: u8, log_index: u64) -> EventRef {
+        EventRef {
+            block_number: 100,
+            tx_hash: FixedBytes::repeat_byte(byte),
+            log_index,
+        }
+    }
+
+    #[test]
+    fn test_block_range_waits_for_confirmations() {
+        // Too few blocks for the confirmation depth
+        assert_eq!(block_range(None, None, 5, 12, 5000), None);
+        // First run covers only the newest confirmed block
+        assert_eq!(block_range(None, None, 112, 12, 5000), Some((100, 100)));
+        assert_eq!(block_range(None, Some(40), 112, 12, 5000), Some((40, 100)));
+        // Resumes from the cursor, capped at the maximum range
+        assert_eq!(block_range(Some(50), Some(40), 112, 12, 10), Some((50, 59)));
+        assert_eq!(block_range(Some(101), None, 112, 12, 5000), None);
+        assert_eq!(block_range(Some(7), None, 112, 12, 0), Some((7, 7)));
+    }
+
+    #[tokio::test]
+    async fn test_events_prove_against_their_anchor() {
+        let events = vec![event(0xaa, 0), event(0xaa, 1), event(0xbb, 0)];
+        let leaves: Vec<FixedBytes<32>> = events.iter().map(EventRef::leaf).collect();
+        let root = merkle_root(&leaves);
+        let store = AnchorStore {
+            anchors: Mutex::new(vec![Anchor {
+                id: 1,
+                from_block: 90,
+                to_block: 110,
+                root,
+                l1_chain_id: 1,
+                l1_tx_hash: FixedBytes::ZERO,
+                l1_block: None,
+                anchored_at: String::new(),
+                events,
+                hash_scheme: MERKLE_HASH_SCHEME,
+            }]),
+            cursor: Mutex::new(AnchorCursor::default()),
+        };
+
+        let (anchor, index) = store
+            .find_event(FixedBytes::repeat_byte(0xaa), Some(1))
+            .await
+            .unwrap();
+        assert_eq!(index, 1);
+        let proof = merkle_proof(&leaves, index);
+        assert!(verify_merkle_proof(
+            anchor.events[index].leaf(),
+            &proof,
+            root
+        ));
+        assert!(!verify_merkle_proof(leaves[0], &proof, root));
+
+        assert!(store
+            .find_event(FixedBytes::repeat_byte(0xaa), Some(5))
+            .await
+            .is_none());
+        assert!(store
+            .find_event(FixedBytes::repeat_byte(0xcc), None)
+            .await
+            .is_none());
+    }
+}
@@ -0,0 +1,442 @@
+//! API keys for machine integrations
+//!
+//! FPO apps and warehouse IoT gateways call the API without a person signing
+//! in. An admin issues each integration a key with the roles it may act as
+//! (fpo, warehouse, processor, lender), optionally with an expiry. Keys are sent in
+//! the `X-API-Key` header on `/api/*` routes and pass the same role guards as
+//! user tokens (see [`crate::auth::restrict`]).
+//!
+//! Keys are returned once at creation or rotation; only their SHA-256 is
+//! stored in `data/api_keys.json`. Rotating a key keeps the previous secret
+//! valid for API_KEY_ROTATION_GRACE_SECS (default one day) so gateways can be
+//! reconfigured without downtime. Last-use times are kept in memory and
+//! written with the next change to the key file.
+
+use crate::admin::require_admin;
+use crate::auth::{Principal, Role};
+use crate::error::{ApiError, ApiResult};
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::HeaderMap,
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+const API_KEYS_PATH: &str = "data/api_keys.json";
+const KEY_PREFIX: &str = "ak_";
+pub const API_KEY_HEADER: &str = "x-api-key";
+const DEFAULT_GRACE_SECS: i64 = 24 * 3600;
+/// Roles a machine credential may carry; farmer and admin access stays with people
+pub const KEY_ROLES: [Role; 4] = [Role::Fpo, Role::Warehouse, Role::Processor, Role::Lender];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyStatus {
+    Active,
+    Expired,
+    Revoked,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: u64,
+    /// Integration the key was issued to, e.g. "warehouse-gw-nashik"
+    pub name: String,
+    pub scopes: Vec<Role>,
+    pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotated_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<String>,
+    /// Hex SHA-256 of the current key
+    key_hash: String,
+    /// Hex SHA-256 of the key replaced by the last rotation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_hash: Option<String>,
+    /// End of the rotation grace period of `previous_hash`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous_valid_until: Option<String>,
+}
+
+fn before(deadline: Option<&str>, now: DateTime<Utc>) -> bool {
+    match deadline {
+        None => true,
+        Some(deadline) => DateTime::parse_from_rfc3339(deadline)
+            .map(|d| now < d)
+            .unwrap_or(false),
+    }
+}
+
+impl ApiKey {
+    pub fn status(&self, now: DateTime<Utc>) -> KeyStatus {
+        if self.revoked_at.is_some() {
+            KeyStatus::Revoked
+        } else if before(self.expires_at.as_deref(), now) {
+            KeyStatus::Active
+        } else {
+            KeyStatus::Expired
+        }
+    }
+
+    /// Whether `key_hash` is the current key, or the previous one within its grace period
+    fn matches(&self, key_hash: &str, now: DateTime<Utc>) -> bool {
+        self.key_hash == key_hash
+            || (self.previous_hash.as_deref() == Some(key_hash)
+                && before(self.previous_valid_until.as_deref(), now))
+    }
+}
+
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+fn new_key() -> String {
+    format!("{}{}", KEY_PREFIX, hex::encode(rand::random::<[u8; 32]>()))
+}
+
+pub struct ApiKeyStore {
+    grace: Duration,
+    keys: Mutex<Vec<ApiKey>>,
+}
+
+impl ApiKeyStore {
+    pub fn load() -> Result<Self> {
+        let grace = std::env::var("API_KEY_ROTATION_GRACE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_GRACE_SECS);
+
+        let keys = match std::fs::read_to_string(API_KEYS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid API key file {}", API_KEYS_PATH))?,
+            Err(_) => Vec::new(),
+        };
+
+        Ok(Self {
+            grace: Duration::seconds(grace.max(0)),
+            keys: Mutex::new(keys),
+        })
+    }
+
+    fn save(keys: &[ApiKey]) -> Result<()> {
+        std::fs::write(API_KEYS_PATH, serde_json::to_string_pretty(keys)?)
+            .with_context(|| format!("Failed to write {}", API_KEYS_PATH))
+    }
+
+    pub async fn has_key(&self, id: u64) -> bool {
+        self.keys.lock().await.iter().any(|k| k.id == id)
+    }
+
+    /// Active key matching `key`, recording its use
+    pub async fn authenticate(&self, key: &str) -> Option<ApiKey> {
+        if !key.starts_with(KEY_PREFIX) {
+            return None;
+        }
+        let key_hash = hash_key(key);
+        let now = Utc::now();
+        let mut keys = self.keys.lock().await;
+        let api_key = keys
+            .iter_mut()
+            .find(|k| k.matches(&key_hash, now) && k.status(now) == KeyStatus::Active)?;
+        api_key.last_used_at = Some(now.to_rfc3339());
+        Some(api_key.clone())
+    }
+}
+
+pub fn api_key_header(headers: &HeaderMap) -> Option<&str> {
+    headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok())
+}
+
+// ======================== MIDDLEWARE ========================
+
+/// Validate `X-API-Key` on `/api/*` routes and set the [`Principal`]
+///
+/// Runs after [`crate::auth::authenticate`]; a request may carry either a
+/// bearer token or an API key, not both.
+pub async fn validate_key(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if !request.uri().path().starts_with("/api/") {
+        return Ok(next.run(request).await);
+    }
+    let Some(key) = api_key_header(request.headers()) else {
+        return Ok(next.run(request).await);
+    };
+    if request.extensions().get::<Principal>().is_some() {
+        return Err(ApiError::bad_request(
+            "Send either a bearer token or an API key, not both",
+        ));
+    }
+
+    let api_key = state.api_keys.authenticate(key).await.ok_or_else(|| {
+        tracing::warn!(path = %request.uri().path(), "Rejected request with invalid API key");
+        ApiError::unauthorized("Invalid, expired or revoked API key")
+    })?;
+    tracing::debug!(api_key_id = api_key.id, name = %api_key.name, "Request authorized by API key");
+
+    let scopes = state.chain_roles.key_scopes(api_key.id, &api_key.scopes);
+    request.extensions_mut().insert(Principal::ApiKey {
+        id: api_key.id,
+        name: api_key.name,
+        scopes,
+    });
+    Ok(next.run(request).await)
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct ApiKeySummary {
+    pub id: u64,
+    pub name: String,
+    pub scopes: Vec<Role>,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    pub rotated_at: Option<String>,
+    /// When the secret replaced by the last rotation stops working
+    pub previous_valid_until: Option<String>,
+    pub revoked_at: Option<String>,
+    pub last_used_at: Option<String>,
+    pub status: KeyStatus,
+}
+
+impl From<&ApiKey> for ApiKeySummary {
+    fn from(k: &ApiKey) -> Self {
+        Self {
+            id: k.id,
+            name: k.name.clone(),
+            scopes: k.scopes.clone(),
+            created_at: k.created_at.clone(),
+            expires_at: k.expires_at.clone(),
+            rotated_at: k.rotated_at.clone(),
+            previous_valid_until: k.previous_valid_until.clone(),
+            revoked_at: k.revoked_at.clone(),
+            last_used_at: k.last_used_at.clone(),
+            status: k.status(Utc::now()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<Role>,
+    /// RFC 3339; keys without an expiry stay valid until revoked
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApiKeyResponse {
+    pub api_key: ApiKeySummary,
+    /// Value for the X-API-Key header; shown only once
+    pub key: String,
+}
+
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateApiKeyRequest>,
+) -> ApiResult<ApiKeyResponse> {
+    require_admin(&state, &headers)?;
+
+    let name = payload.name.trim().to_string();
+    if name.is_empty() {
+        return Err(ApiError::bad_request("name is required"));
+    }
+    if payload.scopes.is_empty() {
+        return Err(ApiError::bad_request("Provide at least one scope"));
+    }
+    if let Some(scope) = payload.scopes.iter().find(|s| !KEY_ROLES.contains(s)) {
+        return Err(ApiError::bad_request(format!(
+            "API keys cannot carry the {} role",
+            scope
+        )));
+    }
+    let now = Utc::now();
+    let expires_at = match payload.expires_at {
+        Some(expires_at) => {
+            let expires_at = DateTime::parse_from_rfc3339(&expires_at)
+                .map_err(|e| ApiError::bad_request(format!("Invalid expires_at: {}", e)))?
+                .with_timezone(&Utc);
+            if expires_at <= now {
+                return Err(ApiError::bad_request("expires_at must be in the future"));
+            }
+            Some(expires_at.to_rfc3339())
+        }
+        None => None,
+    };
+
+    let mut scopes = Vec::new();
+    for scope in payload.scopes {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    let key = new_key();
+    let mut keys = state.api_keys.keys.lock().await;
+    let api_key = ApiKey {
+        id: keys.last().map(|k| k.id + 1).unwrap_or(1),
+        name,
+        scopes,
+        created_at: now.to_rfc3339(),
+        expires_at,
+        rotated_at: None,
+        revoked_at: None,
+        last_used_at: None,
+        key_hash: hash_key(&key),
+        previous_hash: None,
+        previous_valid_until: None,
+    };
+    keys.push(api_key.clone());
+    ApiKeyStore::save(&keys)?;
+
+    tracing::info!(api_key_id = api_key.id, name = %api_key.name, "API key issued");
+    Ok(Json(ApiKeyResponse {
+        api_key: ApiKeySummary::from(&api_key),
+        key,
+    }))
+}
+
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<ApiKeySummary>> {
+    require_admin(&state, &headers)?;
+
+    let keys: Vec<ApiKeySummary> = state
+        .api_keys
+        .keys
+        .lock()
+        .await
+        .iter()
+        .map(ApiKeySummary::from)
+        .collect();
+    Ok(Json(paginate("api-keys", keys, &params, |k| k.id)?))
+}
+
+/// Issue a new secret; the old one keeps working for the grace period
+pub async fn rotate_api_key(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> ApiResult<ApiKeyResponse> {
+    require_admin(&state, &headers)?;
+
+    let now = Utc::now();
+    let key = new_key();
+    let mut keys = state.api_keys.keys.lock().await;
+    let api_key = keys
+        .iter_mut()
+        .find(|k| k.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("API key {} not found", id)))?;
+    if api_key.status(now) != KeyStatus::Active {
+        return Err(ApiError::bad_request(format!(
+            "API key {} is no longer active",
+            id
+        )));
+    }
+    api_key.previous_hash = Some(std::mem::replace(&mut api_key.key_hash, hash_key(&key)));
+    api_key.previous_valid_until = Some((now + state.api_keys.grace).to_rfc3339());
+    api_key.rotated_at = Some(now.to_rfc3339());
+    let summary = ApiKeySummary::from(&*api_key);
+    ApiKeyStore::save(&keys)?;
+
+    tracing::info!(
+        api_key_id = id,
+        previous_valid_until = ?summary.previous_valid_until,
+        "API key rotated"
+    );
+    Ok(Json(ApiKeyResponse {
+        api_key: summary,
+        key,
+    }))
+}
+
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> ApiResult<ApiKeySummary> {
+    require_admin(&state, &headers)?;
+
+    let mut keys = state.api_keys.keys.lock().await;
+    let api_key = keys
+        .iter_mut()
+        .find(|k| k.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("API key {} not found", id)))?;
+    if api_key.revoked_at.is_none() {
+        api_key.revoked_at = Some(Utc::now().to_rfc3339());
+        tracing::info!(api_key_id = id, "API key revoked");
+    }
+    let summary = ApiKeySummary::from(&*api_key);
+    ApiKeyStore::save(&keys)?;
+
+    Ok(Json(summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_key(expires_at: Option<&str>) -> ApiKey {
+        ApiKey {
+            id: 1,
+            name: "warehouse-gw".to_string(),
+            scopes: vec![Role::Warehouse],
+            created_at: "2025-06-01T00:00:00Z".to_string(),
+            expires_at: expires_at.map(String::from),
+            rotated_at: None,
+            revoked_at: None,
+            last_used_at: None,
+            key_hash: hash_key("ak_new"),
+            previous_hash: Some(hash_key("ak_old")),
+            previous_valid_until: Some("2025-07-02T00:00:00Z".to_string()),
+        }
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_key_status() {
+        let now = at("2025-07-01T00:00:00Z");
+        assert_eq!(api_key(None).status(now), KeyStatus::Active);
+        assert_eq!(
+            api_key(Some("2025-06-30T00:00:00Z")).status(now),
+            KeyStatus::Expired
+        );
+        let mut revoked = api_key(None);
+        revoked.revoked_at = Some("2025-06-15T00:00:00Z".to_string());
+        assert_eq!(revoked.status(now), KeyStatus::Revoked);
+    }
+
+    #[test]
+    fn test_previous_key_valid_during_grace_period() {
+        let key = api_key(None);
+        let during = at("2025-07-01T12:00:00Z");
+        let after = at("2025-07-03T00:00:00Z");
+        assert!(key.matches(&hash_key("ak_new"), after));
+        assert!(key.matches(&hash_key("ak_old"), during));
+        assert!(!key.matches(&hash_key("ak_old"), after));
+        assert!(!key.matches(&hash_key("ak_other"), during));
+    }
+}
@@ -0,0 +1,492 @@
+//! Audit log of API mutations with daily Merkle digests
+//!
+//! Every non-GET request is appended to `data/audit/<YYYY-MM-DD>.jsonl`
+//! (method, path without the query string, status, actor and a hash of the
+//! request body, never the body itself). Once a UTC day is over, the digest job builds a Merkle tree
+//! over that day's entries and publishes the root on the primary chain as
+//! calldata `"OVA1" || root || yyyymmdd (u32) || entry count (u64)` in a
+//! self-transaction of the backend signer.
+//!
+//! Digests and per-entry inclusion proofs are served publicly, so anyone
+//! holding an entry can check that it is part of the history committed on
+//! that day, and that the day's log has not been rewritten since.
+
+use crate::admin::require_admin;
+use crate::auth::Principal;
+use crate::delegation::Actor;
+use crate::error::{format_hash, format_tx_hash, ApiError, ApiResult};
+use crate::hash_schemes::HashScheme;
+use crate::merkle::{merkle_proof, merkle_root, MERKLE_HASH_SCHEME};
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use alloy::primitives::FixedBytes;
+use anyhow::{Context, Result};
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Path, Query, Request, State},
+    http::{HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+const AUDIT_DIR: &str = "data/audit";
+const DIGESTS_FILE: &str = "digests.json";
+pub const AUDIT_MAGIC: &[u8; 4] = b"OVA1";
+/// Largest request body the audit layer will buffer for hashing
+const MAX_AUDITED_BODY_BYTES: usize = 16 * 1024 * 1024;
+const DEFAULT_CHECK_INTERVAL_SECS: u64 = 3600;
+
+// ======================== LOG ========================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position within the day, starting at 0
+    pub seq: u64,
+    pub timestamp: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    /// "admin" for requests with a valid admin token, otherwise "anonymous"
+    pub actor: String,
+    pub body_hash: FixedBytes<32>,
+}
+
+impl AuditEntry {
+    /// Merkle leaf: hash of the entry's JSON encoding
+    pub fn leaf(&self) -> FixedBytes<32> {
+        let json = serde_json::to_vec(self).expect("audit entries always serialize");
+        MERKLE_HASH_SCHEME.hash_bytes(&json)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditDigest {
+    /// UTC day, YYYY-MM-DD
+    pub day: String,
+    pub entry_count: u64,
+    pub root: FixedBytes<32>,
+    pub hash_scheme: HashScheme,
+    /// Absent when on-chain publication is disabled
+    pub tx_hash: Option<FixedBytes<32>>,
+    pub block_number: Option<u64>,
+    pub published_at: String,
+}
+
+struct Writer {
+    day: NaiveDate,
+    next_seq: u64,
+}
+
+/// Append-only daily audit files plus the digests published so far
+pub struct AuditLog {
+    writer: Mutex<Writer>,
+    digests: Mutex<Vec<AuditDigest>>,
+}
+
+fn day_path(day: NaiveDate) -> std::path::PathBuf {
+    std::path::Path::new(AUDIT_DIR).join(format!("{}.jsonl", day.format("%Y-%m-%d")))
+}
+
+fn read_entries(day: NaiveDate) -> Result<Vec<AuditEntry>> {
+    let path = day_path(day);
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Invalid audit entry in {}", path.display()))
+        })
+        .collect()
+}
+
+impl AuditLog {
+    pub fn load() -> Result<Self> {
+        let today = Utc::now().date_naive();
+        let next_seq = read_entries(today)?.len() as u64;
+
+        let digests_path = std::path::Path::new(AUDIT_DIR).join(DIGESTS_FILE);
+        let digests = match std::fs::read_to_string(&digests_path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid audit digests {}", digests_path.display()))?,
+            Err(_) => Vec::new(),
+        };
+
+        Ok(Self {
+            writer: Mutex::new(Writer {
+                day: today,
+                next_seq,
+            }),
+            digests: Mutex::new(digests),
+        })
+    }
+
+    async fn append(
+        &self,
+        method: &Method,
+        path: &str,
+        status: StatusCode,
+        actor: &str,
+        body_hash: FixedBytes<32>,
+    ) -> Result<()> {
+        let now = Utc::now();
+        let mut writer = self.writer.lock().await;
+        if writer.day != now.date_naive() {
+            writer.day = now.date_naive();
+            writer.next_seq = 0;
+        }
+
+        let entry = AuditEntry {
+            seq: writer.next_seq,
+            timestamp: now.to_rfc3339(),
+            method: method.to_string(),
+            path: path.to_string(),
+            status: status.as_u16(),
+            actor: actor.to_string(),
+            body_hash,
+        };
+
+        std::fs::create_dir_all(AUDIT_DIR)
+            .with_context(|| format!("Failed to create {}", AUDIT_DIR))?;
+        let path = day_path(writer.day);
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)
+            .with_context(|| format!("Failed to append to {}", path.display()))?;
+
+        writer.next_seq += 1;
+        Ok(())
+    }
+
+    fn save_digests(digests: &[AuditDigest]) -> Result<()> {
+        std::fs::create_dir_all(AUDIT_DIR)
+            .with_context(|| format!("Failed to create {}", AUDIT_DIR))?;
+        let path = std::path::Path::new(AUDIT_DIR).join(DIGESTS_FILE);
+        std::fs::write(&path, serde_json::to_string_pretty(digests)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+// ======================== MIDDLEWARE ========================
+
+/// Record every mutating request in the audit log
+pub async fn record_mutations(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        return next.run(request).await;
+    }
+
+    let method = request.method().clone();
+    // The query string is left out: it can carry credentials such as webhook
+    // secrets, and entries are served publicly with their proofs
+    let path = request.uri().path().to_string();
+    // Same checks as require_admin and require_scope, without logging
+    // rejected tokens; JWTs, delegations and API keys were already verified
+    // by auth::authenticate and api_keys::validate_key
+    let bearer = request
+        .headers()
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let principal = match request.extensions().get::<Principal>() {
+        Some(Principal::User(claims)) => Some(Actor::User(claims.clone())),
+        Some(Principal::Delegate(delegation)) => Some(Actor::Delegate(delegation.clone())),
+        Some(Principal::ApiKey { id, .. }) => Some(Actor::ApiKey(*id)),
+        _ => None,
+    };
+    let actor = match (bearer, principal) {
+        (_, Some(actor)) => actor,
+        (Some(token), None) if state.admin_token.as_deref() == Some(token) => Actor::Admin,
+        (Some(token), None) => match state.delegations.find_by_token(token).await {
+            Some(delegation) => Actor::Delegate(delegation),
+            None => match state.auditors.find_by_token(token).await {
+                Some(auditor) => Actor::Auditor(auditor.id),
+                None => Actor::Anonymous,
+            },
+        },
+        (None, None) => Actor::Anonymous,
+    };
+
+    let (parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, MAX_AUDITED_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            return ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, "Request body too large")
+                .into_response()
+        }
+    };
+    let body_hash = MERKLE_HASH_SCHEME.hash_bytes(&bytes);
+
+    let response = next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await;
+
+    if let Err(e) = state
+        .audit
+        .append(&method, &path, response.status(), &actor.label(), body_hash)
+        .await
+    {
+        tracing::error!(error = %format!("{:#}", e), path = %path, "Failed to write audit entry");
+    }
+
+    response
+}
+
+// ======================== DIGEST JOB ========================
+
+fn publish_on_chain() -> bool {
+    std::env::var("AUDIT_DIGEST_ON_CHAIN")
+        .map(|v| !matches!(v.to_lowercase().as_str(), "false" | "0" | "off"))
+        .unwrap_or(true)
+}
+
+/// Digest every finished day that has entries but no digest yet
+pub async fn run_once(state: &AppState) -> Result<Vec<AuditDigest>> {
+    let today = Utc::now().date_naive();
+    let mut digests = state.audit.digests.lock().await;
+
+    let mut pending: Vec<NaiveDate> = match std::fs::read_dir(AUDIT_DIR) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                NaiveDate::parse_from_str(name.strip_suffix(".jsonl")?, "%Y-%m-%d").ok()
+            })
+            .filter(|day| *day < today)
+            .filter(|day| {
+                let day = day.format("%Y-%m-%d").to_string();
+                !digests.iter().any(|d| d.day == day)
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    pending.sort();
+
+    let mut published = Vec::new();
+    for day in pending {
+        let entries = read_entries(day)?;
+        if entries.is_empty() {
+            continue;
+        }
+        let leaves: Vec<FixedBytes<32>> = entries.iter().map(AuditEntry::leaf).collect();
+        let root = merkle_root(&leaves);
+
+        let receipt = if publish_on_chain() {
+            let day_number: u32 = day.format("%Y%m%d").to_string().parse()?;
+            let mut calldata = AUDIT_MAGIC.to_vec();
+            calldata.extend_from_slice(root.as_slice());
+            calldata.extend_from_slice(&day_number.to_be_bytes());
+            calldata.extend_from_slice(&(entries.len() as u64).to_be_bytes());
+            Some(state.chain().publish_data(calldata).await?)
+        } else {
+            None
+        };
+
+        let digest = AuditDigest {
+            day: day.format("%Y-%m-%d").to_string(),
+            entry_count: entries.len() as u64,
+            root,
+            hash_scheme: MERKLE_HASH_SCHEME,
+            tx_hash: receipt.as_ref().map(|r| r.transaction_hash),
+            block_number: receipt.as_ref().and_then(|r| r.block_number),
+            published_at: Utc::now().to_rfc3339(),
+        };
+        digests.push(digest.clone());
+        AuditLog::save_digests(&digests)?;
+        published.push(digest);
+    }
+
+    Ok(published)
+}
+
+/// Start the periodic digest job
+pub fn spawn(state: AppState) {
+    let secs = std::env::var("AUDIT_DIGEST_CHECK_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_CHECK_INTERVAL_SECS);
+    let interval = Duration::from_secs(secs.max(60));
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match run_once(&state).await {
+                Ok(published) => {
+                    for digest in published {
+                        tracing::info!(
+                            day = %digest.day,
+                            entries = digest.entry_count,
+                            root = %format_hash(digest.root),
+                            "Audit digest published"
+                        );
+                    }
+                }
+                Err(e) => tracing::error!(error = %format!("{:#}", e), "Audit digest job failed"),
+            }
+        }
+    });
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct AuditDigestSummary {
+    pub day: String,
+    pub entry_count: u64,
+    pub root: String,
+    pub hash_scheme: HashScheme,
+    pub tx_hash: Option<String>,
+    pub block_number: Option<u64>,
+    pub published_at: String,
+}
+
+impl From<&AuditDigest> for AuditDigestSummary {
+    fn from(digest: &AuditDigest) -> Self {
+        Self {
+            day: digest.day.clone(),
+            entry_count: digest.entry_count,
+            root: format_hash(digest.root),
+            hash_scheme: digest.hash_scheme,
+            tx_hash: digest.tx_hash.map(format_tx_hash),
+            block_number: digest.block_number,
+            published_at: digest.published_at.clone(),
+        }
+    }
+}
+
+pub async fn list_digests(
+    State(state): State<AppState>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<AuditDigestSummary>> {
+    let summaries: Vec<AuditDigestSummary> = state
+        .audit
+        .digests
+        .lock()
+        .await
+        .iter()
+        .map(AuditDigestSummary::from)
+        .collect();
+
+    Ok(Json(paginate("audit-digests", summaries, &params, |d| {
+        d.day.clone()
+    })?))
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditProof {
+    pub entry: AuditEntry,
+    pub leaf: FixedBytes<32>,
+    pub proof: Vec<FixedBytes<32>>,
+    pub digest: AuditDigestSummary,
+}
+
+/// Inclusion proof of one entry in its day's published digest
+pub async fn entry_proof(
+    State(state): State<AppState>,
+    Path((day, seq)): Path<(String, u64)>,
+) -> ApiResult<AuditProof> {
+    let date = NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+        .map_err(|_| ApiError::bad_request("Day must be formatted as YYYY-MM-DD"))?;
+
+    let digest = state
+        .audit
+        .digests
+        .lock()
+        .await
+        .iter()
+        .find(|d| d.day == day)
+        .cloned()
+        .ok_or_else(|| ApiError::not_found(format!("No digest published for {}", day)))?;
+
+    let entries = read_entries(date).map_err(ApiError::from)?;
+    let index = entries
+        .iter()
+        .position(|e| e.seq == seq)
+        .ok_or_else(|| ApiError::not_found(format!("No audit entry {} on {}", seq, day)))?;
+
+    let leaves: Vec<FixedBytes<32>> = entries.iter().map(AuditEntry::leaf).collect();
+    if merkle_root(&leaves) != digest.root {
+        tracing::error!(day = %day, "Audit log no longer matches its published digest");
+        return Err(ApiError::internal(
+            "Audit log for this day does not match its published digest",
+        ));
+    }
+
+    Ok(Json(AuditProof {
+        leaf: leaves[index],
+        proof: merkle_proof(&leaves, index),
+        entry: entries[index].clone(),
+        digest: AuditDigestSummary::from(&digest),
+    }))
+}
+
+/// Publish digests for finished days now (admin only)
+pub async fn trigger_digest(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<Vec<AuditDigestSummary>> {
+    require_admin(&state, &headers)?;
+
+    let published = run_once(&state)
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+
+    Ok(Json(
+        published.iter().map(AuditDigestSummary::from).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::verify_merkle_proof;
+
+    fn entry(seq: u64) -> AuditEntry {
+        AuditEntry {
+            seq,
+            timestamp: "2025-01-01T00:00:00+00:00".to_string(),
+            method: "POST".to_string(),
+            path: "/api/fpo/purchase".to_string(),
+            status: 200,
+            actor: "anonymous".to_string(),
+            body_hash: FixedBytes::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_rewritten_entry_changes_root() {
+        let entries: Vec<AuditEntry> = (0..5).map(entry).collect();
+        let leaves: Vec<FixedBytes<32>> = entries.iter().map(AuditEntry::leaf).collect();
+        let root = merkle_root(&leaves);
+        assert!(verify_merkle_proof(
+            leaves[3],
+            &merkle_proof(&leaves, 3),
+            root
+        ));
+
+        let mut rewritten = entries.clone();
+        rewritten[3].status = 500;
+        let leaves: Vec<FixedBytes<32>> = rewritten.iter().map(AuditEntry::leaf).collect();
+        assert_ne!(merkle_root(&leaves), root);
+    }
+}
@@ -0,0 +1,728 @@
+//! Auditor accounts and read-only console API
+//!
+//! Government auditors need recurring access to the event history, which
+//! one-off share links (see [`crate::share`]) do not cover. An admin invites
+//! an auditor by email; the invitation carries a single-use verification
+//! token, and verifying it proves ownership of the address and returns the
+//! auditor's bearer token (shown once).
+//!
+//! Auditor tokens are read-only: they are accepted by the `/api/auditor/*`
+//! console endpoints only, which query the event index and let auditors keep
+//! saved queries and a history of their exports. Accounts, saved queries and
+//! export records are kept in `data/auditors.json`; tokens only as SHA-256.
+
+use crate::admin::require_admin;
+use crate::chain::hash_string;
+use crate::did_resolver::chain_did;
+use crate::error::{format_hash, ApiError, ApiResult};
+use crate::indexer::{self, EventFilter, IndexedEvent};
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use tokio::sync::Mutex;
+
+const AUDITORS_PATH: &str = "data/auditors.json";
+const TOKEN_PREFIX: &str = "aud_";
+const INVITE_PREFIX: &str = "inv_";
+const INVITE_VALIDITY_HOURS: i64 = 72;
+const MAX_SAVED_QUERIES: usize = 50;
+/// Events returned by one export; larger result sets are marked truncated
+const MAX_EXPORT_ROWS: usize = 10_000;
+
+// ======================== ACCOUNTS ========================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditorStatus {
+    /// Invitation sent, email not verified yet
+    Invited,
+    Active,
+    Disabled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Auditor {
+    pub id: u64,
+    pub email: String,
+    pub name: String,
+    pub organization: String,
+    pub invited_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disabled_at: Option<String>,
+    /// Hex SHA-256 of the pending verification token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    invite_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    invite_expires_at: Option<String>,
+    /// Hex SHA-256 of the bearer token issued at verification
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    token_hash: Option<String>,
+}
+
+impl Auditor {
+    pub fn status(&self) -> AuditorStatus {
+        if self.disabled_at.is_some() {
+            AuditorStatus::Disabled
+        } else if self.verified_at.is_some() {
+            AuditorStatus::Active
+        } else {
+            AuditorStatus::Invited
+        }
+    }
+
+    /// Whether `invite_hash` matches a pending, unexpired invitation
+    fn accepts_invite(&self, invite_hash: &str, now: DateTime<Utc>) -> bool {
+        self.status() != AuditorStatus::Disabled
+            && self.invite_hash.as_deref() == Some(invite_hash)
+            && self
+                .invite_expires_at
+                .as_deref()
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                .is_some_and(|expires_at| now < expires_at)
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn new_token(prefix: &str) -> String {
+    format!("{}{}", prefix, hex::encode(rand::random::<[u8; 32]>()))
+}
+
+/// Lowercased address if it looks deliverable (`local@domain.tld`)
+pub fn normalize_email(raw: &str) -> Option<String> {
+    let email = raw.trim().to_lowercase();
+    let (local, domain) = email.split_once('@')?;
+    let valid = !local.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !email.contains(char::is_whitespace)
+        && !domain.contains('@');
+    valid.then_some(email)
+}
+
+// ======================== SAVED QUERIES ========================
+
+/// Event index query an auditor can save, run and export
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum QuerySpec {
+    Batch {
+        batch_id: String,
+    },
+    Farmer {
+        farmer_did: String,
+    },
+    TimeRange {
+        #[serde(default)]
+        from: Option<u64>,
+        #[serde(default)]
+        to: Option<u64>,
+        #[serde(default)]
+        event: Option<String>,
+    },
+}
+
+impl QuerySpec {
+    pub fn filter(&self) -> Result<EventFilter, ApiError> {
+        match self {
+            QuerySpec::Batch { batch_id } => {
+                if batch_id.trim().is_empty() {
+                    return Err(ApiError::bad_request("batch_id is required"));
+                }
+                Ok(EventFilter::Batch(format_hash(hash_string(batch_id))))
+            }
+            QuerySpec::Farmer { farmer_did } => {
+                let farmer_did = chain_did(farmer_did).map_err(ApiError::invalid_did)?;
+                Ok(EventFilter::Farmer(format_hash(farmer_did)))
+            }
+            QuerySpec::TimeRange { from, to, event } => {
+                if let (Some(from), Some(to)) = (from, to) {
+                    if from > to {
+                        return Err(ApiError::bad_request("`from` must not be after `to`"));
+                    }
+                }
+                Ok(EventFilter::TimeRange {
+                    from: *from,
+                    to: *to,
+                    event: event.clone(),
+                })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedQuery {
+    pub id: u64,
+    pub auditor_id: u64,
+    pub name: String,
+    pub query: QuerySpec,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportRecord {
+    pub id: u64,
+    pub auditor_id: u64,
+    /// Saved query the export was taken from
+    pub query_id: u64,
+    pub query: QuerySpec,
+    pub rows: usize,
+    /// More than MAX_EXPORT_ROWS events matched
+    pub truncated: bool,
+    /// Hex SHA-256 of the exported events as JSON, to tie a file to this record
+    pub sha256: String,
+    pub exported_at: String,
+}
+
+// ======================== STORE ========================
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AuditorData {
+    #[serde(default)]
+    auditors: Vec<Auditor>,
+    #[serde(default)]
+    queries: Vec<SavedQuery>,
+    #[serde(default)]
+    exports: Vec<ExportRecord>,
+}
+
+pub struct AuditorStore {
+    /// Console page that completes verification with `?token=`
+    console_url: String,
+    data: Mutex<AuditorData>,
+}
+
+impl AuditorStore {
+    pub fn load() -> Result<Self> {
+        let data = match std::fs::read_to_string(AUDITORS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid auditors file {}", AUDITORS_PATH))?,
+            Err(_) => AuditorData::default(),
+        };
+
+        Ok(Self {
+            console_url: std::env::var("AUDITOR_CONSOLE_URL")
+                .unwrap_or_else(|_| "https://oilseed-valuechain.gov.in/auditor/verify".to_string()),
+            data: Mutex::new(data),
+        })
+    }
+
+    fn save(data: &AuditorData) -> Result<()> {
+        std::fs::write(AUDITORS_PATH, serde_json::to_string_pretty(data)?)
+            .with_context(|| format!("Failed to write {}", AUDITORS_PATH))
+    }
+
+    /// Auditor holding `token`, whatever its status
+    pub async fn find_by_token(&self, token: &str) -> Option<Auditor> {
+        if !token.starts_with(TOKEN_PREFIX) {
+            return None;
+        }
+        let token_hash = hash_token(token);
+        self.data
+            .lock()
+            .await
+            .auditors
+            .iter()
+            .find(|a| a.token_hash.as_deref() == Some(token_hash.as_str()))
+            .cloned()
+    }
+}
+
+/// Authorize a console request; only active auditors pass
+pub async fn require_auditor(state: &AppState, headers: &HeaderMap) -> Result<Auditor, ApiError> {
+    let token = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or_else(|| ApiError::unauthorized("Missing auditor bearer token"))?;
+
+    let auditor = state.auditors.find_by_token(token).await.ok_or_else(|| {
+        tracing::warn!("Rejected console request with unknown auditor token");
+        ApiError::unauthorized("Invalid auditor token")
+    })?;
+    if auditor.status() != AuditorStatus::Active {
+        return Err(ApiError::forbidden(format!(
+            "Auditor account {} is disabled",
+            auditor.id
+        )));
+    }
+    Ok(auditor)
+}
+
+// ======================== ADMIN HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct AuditorSummary {
+    pub id: u64,
+    pub email: String,
+    pub name: String,
+    pub organization: String,
+    pub invited_at: String,
+    pub verified_at: Option<String>,
+    pub disabled_at: Option<String>,
+    pub status: AuditorStatus,
+}
+
+impl From<&Auditor> for AuditorSummary {
+    fn from(a: &Auditor) -> Self {
+        Self {
+            id: a.id,
+            email: a.email.clone(),
+            name: a.name.clone(),
+            organization: a.organization.clone(),
+            invited_at: a.invited_at.clone(),
+            verified_at: a.verified_at.clone(),
+            disabled_at: a.disabled_at.clone(),
+            status: a.status(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InviteAuditorRequest {
+    pub email: String,
+    pub name: String,
+    #[serde(default)]
+    pub organization: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InviteAuditorResponse {
+    pub auditor: AuditorSummary,
+    /// Whether the invitation email reached the provider (false in dry run)
+    pub email_sent: bool,
+    pub invite_expires_at: String,
+}
+
+/// Invite an auditor, or re-send the invitation of an existing account
+/// (e.g. after a lost token; verifying again replaces the bearer token)
+pub async fn invite_auditor(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<InviteAuditorRequest>,
+) -> ApiResult<InviteAuditorResponse> {
+    require_admin(&state, &headers)?;
+
+    let email = normalize_email(&payload.email)
+        .ok_or_else(|| ApiError::bad_request(format!("Invalid email {}", payload.email)))?;
+    if payload.name.trim().is_empty() {
+        return Err(ApiError::bad_request("name is required"));
+    }
+
+    let now = Utc::now();
+    let invite = new_token(INVITE_PREFIX);
+    let invite_expires_at = (now + chrono::Duration::hours(INVITE_VALIDITY_HOURS)).to_rfc3339();
+
+    let summary = {
+        let mut data = state.auditors.data.lock().await;
+        let index = match data.auditors.iter().position(|a| a.email == email) {
+            Some(index) => index,
+            None => {
+                let id = data.auditors.last().map(|a| a.id + 1).unwrap_or(1);
+                data.auditors.push(Auditor {
+                    id,
+                    email: email.clone(),
+                    name: String::new(),
+                    organization: String::new(),
+                    invited_at: String::new(),
+                    verified_at: None,
+                    disabled_at: None,
+                    invite_hash: None,
+                    invite_expires_at: None,
+                    token_hash: None,
+                });
+                data.auditors.len() - 1
+            }
+        };
+        let auditor = &mut data.auditors[index];
+        if auditor.status() == AuditorStatus::Disabled {
+            return Err(ApiError::bad_request(format!(
+                "Auditor {} is disabled",
+                auditor.id
+            )));
+        }
+        auditor.name = payload.name.trim().to_string();
+        auditor.organization = payload.organization.trim().to_string();
+        auditor.invited_at = now.to_rfc3339();
+        auditor.invite_hash = Some(hash_token(&invite));
+        auditor.invite_expires_at = Some(invite_expires_at.clone());
+        let summary = AuditorSummary::from(&*auditor);
+        AuditorStore::save(&data)?;
+        summary
+    };
+
+    let link = format!("{}?token={}", state.auditors.console_url, invite);
+    let text = format!(
+        "Hello {},\n\nYou have been invited to the Oilseed Value Chain auditor console. \
+         Open the link below within {} hours to verify your email address and receive \
+         your read-only access token:\n\n{}\n",
+        summary.name, INVITE_VALIDITY_HOURS, link
+    );
+    let email_sent = state
+        .notifications
+        .email()
+        .send(&email, "Verify your auditor account", &text, &[])
+        .await
+        .map_err(|e| ApiError::internal(format!("Invitation email failed: {:#}", e)))?;
+
+    tracing::info!(auditor_id = summary.id, email = %email, email_sent, "Auditor invited");
+
+    Ok(Json(InviteAuditorResponse {
+        auditor: summary,
+        email_sent,
+        invite_expires_at,
+    }))
+}
+
+pub async fn list_auditors(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<Vec<AuditorSummary>> {
+    require_admin(&state, &headers)?;
+
+    let data = state.auditors.data.lock().await;
+    Ok(Json(
+        data.auditors.iter().map(AuditorSummary::from).collect(),
+    ))
+}
+
+pub async fn disable_auditor(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> ApiResult<AuditorSummary> {
+    require_admin(&state, &headers)?;
+
+    let mut data = state.auditors.data.lock().await;
+    let auditor = data
+        .auditors
+        .iter_mut()
+        .find(|a| a.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("Auditor {} not found", id)))?;
+    if auditor.disabled_at.is_none() {
+        auditor.disabled_at = Some(Utc::now().to_rfc3339());
+        auditor.invite_hash = None;
+        auditor.invite_expires_at = None;
+        tracing::info!(auditor_id = id, "Auditor disabled");
+    }
+    let summary = AuditorSummary::from(&*auditor);
+    AuditorStore::save(&data)?;
+
+    Ok(Json(summary))
+}
+
+// ======================== VERIFICATION ========================
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyAuditorRequest {
+    /// Token from the invitation email
+    pub token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyAuditorResponse {
+    pub auditor: AuditorSummary,
+    /// Read-only bearer token for the console API; shown only once
+    pub token: String,
+}
+
+pub async fn verify_auditor(
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyAuditorRequest>,
+) -> ApiResult<VerifyAuditorResponse> {
+    let invite_hash = hash_token(payload.token.trim());
+    let now = Utc::now();
+
+    let mut data = state.auditors.data.lock().await;
+    let auditor = data
+        .auditors
+        .iter_mut()
+        .find(|a| a.accepts_invite(&invite_hash, now))
+        .ok_or_else(|| ApiError::unauthorized("Invalid or expired invitation"))?;
+
+    let token = new_token(TOKEN_PREFIX);
+    auditor.verified_at = Some(now.to_rfc3339());
+    auditor.invite_hash = None;
+    auditor.invite_expires_at = None;
+    auditor.token_hash = Some(hash_token(&token));
+    let summary = AuditorSummary::from(&*auditor);
+    AuditorStore::save(&data)?;
+
+    tracing::info!(auditor_id = summary.id, email = %summary.email, "Auditor email verified");
+
+    Ok(Json(VerifyAuditorResponse {
+        auditor: summary,
+        token,
+    }))
+}
+
+// ======================== CONSOLE HANDLERS ========================
+
+pub async fn auditor_profile(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<AuditorSummary> {
+    let auditor = require_auditor(&state, &headers).await?;
+    Ok(Json(AuditorSummary::from(&auditor)))
+}
+
+pub async fn list_queries(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<SavedQuery>> {
+    let auditor = require_auditor(&state, &headers).await?;
+
+    let queries: Vec<SavedQuery> = state
+        .auditors
+        .data
+        .lock()
+        .await
+        .queries
+        .iter()
+        .filter(|q| q.auditor_id == auditor.id)
+        .cloned()
+        .collect();
+    let scope = format!("auditor/{}/queries", auditor.id);
+    Ok(Json(paginate(&scope, queries, &params, |q| q.id)?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveQueryRequest {
+    pub name: String,
+    pub query: QuerySpec,
+}
+
+pub async fn save_query(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SaveQueryRequest>,
+) -> ApiResult<SavedQuery> {
+    let auditor = require_auditor(&state, &headers).await?;
+
+    if payload.name.trim().is_empty() {
+        return Err(ApiError::bad_request("name is required"));
+    }
+    payload.query.filter()?;
+
+    let mut data = state.auditors.data.lock().await;
+    let saved = data
+        .queries
+        .iter()
+        .filter(|q| q.auditor_id == auditor.id)
+        .count();
+    if saved >= MAX_SAVED_QUERIES {
+        return Err(ApiError::bad_request(format!(
+            "At most {} saved queries per auditor",
+            MAX_SAVED_QUERIES
+        )));
+    }
+    let query = SavedQuery {
+        id: data.queries.last().map(|q| q.id + 1).unwrap_or(1),
+        auditor_id: auditor.id,
+        name: payload.name.trim().to_string(),
+        query: payload.query,
+        created_at: Utc::now().to_rfc3339(),
+    };
+    data.queries.push(query.clone());
+    AuditorStore::save(&data)?;
+
+    Ok(Json(query))
+}
+
+pub async fn delete_query(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> ApiResult<SavedQuery> {
+    let auditor = require_auditor(&state, &headers).await?;
+
+    let mut data = state.auditors.data.lock().await;
+    let index = data
+        .queries
+        .iter()
+        .position(|q| q.id == id && q.auditor_id == auditor.id)
+        .ok_or_else(|| ApiError::not_found(format!("Saved query {} not found", id)))?;
+    let query = data.queries.remove(index);
+    AuditorStore::save(&data)?;
+
+    Ok(Json(query))
+}
+
+/// Saved query `id` of `auditor`
+async fn saved_query(state: &AppState, auditor: &Auditor, id: u64) -> Result<SavedQuery, ApiError> {
+    state
+        .auditors
+        .data
+        .lock()
+        .await
+        .queries
+        .iter()
+        .find(|q| q.id == id && q.auditor_id == auditor.id)
+        .cloned()
+        .ok_or_else(|| ApiError::not_found(format!("Saved query {} not found", id)))
+}
+
+pub async fn run_query(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<IndexedEvent>> {
+    let auditor = require_auditor(&state, &headers).await?;
+    let query = saved_query(&state, &auditor, id).await?;
+
+    let scope = format!("auditor/queries/{}", query.id);
+    indexer::query_page(&state, &scope, query.query.filter()?, &params).await
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryExport {
+    pub export: ExportRecord,
+    pub events: Vec<IndexedEvent>,
+}
+
+/// Export every event matching a saved query and record it in the history
+pub async fn export_query(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> ApiResult<QueryExport> {
+    let auditor = require_auditor(&state, &headers).await?;
+    let query = saved_query(&state, &auditor, id).await?;
+
+    let mut events = state
+        .events
+        .query_snapshot(&query.query.filter()?, None, MAX_EXPORT_ROWS + 1)
+        .await?;
+    let truncated = events.len() > MAX_EXPORT_ROWS;
+    events.truncate(MAX_EXPORT_ROWS);
+    let body = serde_json::to_vec(&events).map_err(ApiError::json_failed)?;
+
+    let mut data = state.auditors.data.lock().await;
+    let export = ExportRecord {
+        id: data.exports.last().map(|e| e.id + 1).unwrap_or(1),
+        auditor_id: auditor.id,
+        query_id: query.id,
+        query: query.query,
+        rows: events.len(),
+        truncated,
+        sha256: hex::encode(Sha256::digest(&body)),
+        exported_at: Utc::now().to_rfc3339(),
+    };
+    data.exports.push(export.clone());
+    AuditorStore::save(&data)?;
+
+    tracing::info!(
+        auditor_id = auditor.id,
+        query_id = export.query_id,
+        export_id = export.id,
+        rows = export.rows,
+        "Auditor export"
+    );
+
+    Ok(Json(QueryExport { export, events }))
+}
+
+/// Exports of the signed-in auditor, newest first
+pub async fn list_exports(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<ExportRecord>> {
+    let auditor = require_auditor(&state, &headers).await?;
+
+    let exports: Vec<ExportRecord> = state
+        .auditors
+        .data
+        .lock()
+        .await
+        .exports
+        .iter()
+        .filter(|e| e.auditor_id == auditor.id)
+        .cloned()
+        .collect();
+    let scope = format!("auditor/{}/exports", auditor.id);
+    Ok(Json(paginate(&scope, exports, &params, |e| Reverse(e.id))?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_email() {
+        assert_eq!(
+            normalize_email("  Auditor@CAG.gov.in "),
+            Some("auditor@cag.gov.in".to_string())
+        );
+        assert_eq!(normalize_email("auditor@localhost"), None);
+        assert_eq!(normalize_email("@cag.gov.in"), None);
+        assert_eq!(normalize_email("a b@cag.gov.in"), None);
+        assert_eq!(normalize_email("a@b@cag.gov.in"), None);
+    }
+
+    #[test]
+    fn test_invite_is_single_account_and_expires() {
+        let now = DateTime::parse_from_rfc3339("2025-07-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut auditor = Auditor {
+            id: 1,
+            email: "auditor@cag.gov.in".to_string(),
+            name: "Auditor".to_string(),
+            organization: "CAG".to_string(),
+            invited_at: "2025-06-30T00:00:00Z".to_string(),
+            verified_at: None,
+            disabled_at: None,
+            invite_hash: Some(hash_token("inv_test")),
+            invite_expires_at: Some("2025-07-03T00:00:00Z".to_string()),
+            token_hash: None,
+        };
+        assert_eq!(auditor.status(), AuditorStatus::Invited);
+        assert!(auditor.accepts_invite(&hash_token("inv_test"), now));
+        assert!(!auditor.accepts_invite(&hash_token("inv_other"), now));
+
+        let later = now + chrono::Duration::days(3);
+        assert!(!auditor.accepts_invite(&hash_token("inv_test"), later));
+
+        auditor.disabled_at = Some("2025-07-01T00:00:00Z".to_string());
+        assert_eq!(auditor.status(), AuditorStatus::Disabled);
+        assert!(!auditor.accepts_invite(&hash_token("inv_test"), now));
+    }
+
+    #[test]
+    fn test_query_spec_filters() {
+        let spec: QuerySpec =
+            serde_json::from_str(r#"{"kind":"time_range","from":20,"to":10}"#).unwrap();
+        assert!(spec.filter().is_err());
+
+        let spec = QuerySpec::Farmer {
+            farmer_did: "not-a-did".to_string(),
+        };
+        assert!(spec.filter().is_err());
+
+        let spec = QuerySpec::Batch {
+            batch_id: "23322".to_string(),
+        };
+        match spec.filter().unwrap() {
+            EventFilter::Batch(hash) => assert_eq!(hash, format_hash(hash_string("23322"))),
+            _ => panic!("expected batch filter"),
+        }
+    }
+}
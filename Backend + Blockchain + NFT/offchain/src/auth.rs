@@ -0,0 +1,695 @@
+//! JWT authentication and role guards
+//!
+//! Users sign in at `POST /api/auth/login` and receive an HS256 JWT whose
+//! `role` claim is one of farmer, fpo, warehouse, processor, lender or
+//! admin.
+//! Accounts are created by an admin (`POST /api/admin/users`) and kept in
+//! `data/users.json` with Argon2 password hashes. Tokens are signed with
+//! JWT_SECRET (at least 32 bytes) and expire after JWT_TTL_SECS (default
+//! 8 hours); without a secret a random one is generated per process, so
+//! tokens do not survive a restart.
+//!
+//! [`authenticate`] runs on every request and resolves the bearer token to a
+//! [`Principal`]: the admin token, a JWT user, or an active delegation token.
+//! Unknown, expired and revoked delegation tokens are rejected there. Other
+//! bearer tokens (share links, auditor console) are left to their own
+//! endpoints. Routes are then wrapped with [`restrict`], which admins pass
+//! everywhere; delegations only pass [`restrict_scoped`] guards naming one of
+//! their scopes, on endpoints that check the scope again with
+//! [`crate::delegation::require_scope`]. Accounts bound to an on-chain
+//! address take their roles from the contract instead (see
+//! [`crate::chain_roles`]).
+
+use crate::admin::require_admin;
+use crate::delegation::{Delegation, Scope};
+use crate::did_resolver::{canonical, same_farmer};
+use crate::error::{ApiError, ApiResult};
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use anyhow::{bail, Context, Result};
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::HeaderMap,
+    middleware::{self, Next},
+    response::Response,
+    routing::MethodRouter,
+    Extension, Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+const USERS_PATH: &str = "data/users.json";
+const DEFAULT_TTL_SECS: i64 = 8 * 3600;
+const MIN_SECRET_BYTES: usize = 32;
+const MIN_PASSWORD_LEN: usize = 10;
+
+// ======================== ROLES AND CLAIMS ========================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Farmer,
+    Fpo,
+    Warehouse,
+    Processor,
+    /// Bank or NBFC financing stored stock
+    Lender,
+    Admin,
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Role::Farmer => "farmer",
+            Role::Fpo => "fpo",
+            Role::Warehouse => "warehouse",
+            Role::Processor => "processor",
+            Role::Lender => "lender",
+            Role::Admin => "admin",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    /// Username
+    pub sub: String,
+    pub role: Role,
+    /// Farmer DID of farmer accounts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub did: Option<String>,
+    /// Further roles held on-chain by the account's bound address (see
+    /// [`crate::chain_roles`]); never part of an issued token
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<Role>,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+impl Claims {
+    pub fn has_role(&self, role: Role) -> bool {
+        self.role == role || self.roles.contains(&role)
+    }
+}
+
+/// Who a request's bearer token belongs to, set by [`authenticate`]
+#[derive(Debug, Clone)]
+pub enum Principal {
+    /// ADMIN_API_TOKEN
+    Admin,
+    User(Claims),
+    /// Active delegation token; see [`restrict_scoped`]
+    Delegate(Delegation),
+    /// Integration key (see [`crate::api_keys`]) acting as `scopes`
+    ApiKey {
+        id: u64,
+        name: String,
+        scopes: Vec<Role>,
+    },
+}
+
+impl Principal {
+    /// Whether a guard admitting `roles` lets this principal through
+    pub fn admits(&self, roles: &[Role]) -> bool {
+        match self {
+            Principal::Admin => true,
+            Principal::User(claims) => {
+                claims.has_role(Role::Admin) || roles.iter().any(|r| claims.has_role(*r))
+            }
+            Principal::Delegate(_) => false,
+            Principal::ApiKey { scopes, .. } => scopes.iter().any(|s| roles.contains(s)),
+        }
+    }
+
+    /// Whether this principal is a delegation carrying `scope`
+    pub fn holds_scope(&self, scope: Scope) -> bool {
+        matches!(self, Principal::Delegate(d) if d.scopes.contains(&scope))
+    }
+}
+
+/// JWTs are the only bearer tokens with three dot-separated segments
+pub fn is_jwt(token: &str) -> bool {
+    token.split('.').count() == 3
+}
+
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+// ======================== USERS ========================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    pub id: u64,
+    pub username: String,
+    pub role: Role,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub did: Option<String>,
+    pub active: bool,
+    pub created_at: String,
+    /// Argon2 PHC string
+    password_hash: String,
+}
+
+/// Account as returned by the admin endpoints, without the password hash
+#[derive(Debug, Serialize)]
+pub struct UserSummary {
+    pub id: u64,
+    pub username: String,
+    pub role: Role,
+    pub did: Option<String>,
+    pub active: bool,
+    pub created_at: String,
+}
+
+impl From<&User> for UserSummary {
+    fn from(u: &User) -> Self {
+        Self {
+            id: u.id,
+            username: u.username.clone(),
+            role: u.role,
+            did: u.did.clone(),
+            active: u.active,
+            created_at: u.created_at.clone(),
+        }
+    }
+}
+
+/// Usernames are case-insensitive: 3-64 of `a-z 0-9 . _ -`
+pub fn normalize_username(raw: &str) -> Option<String> {
+    let username = raw.trim().to_ascii_lowercase();
+    let valid = (3..=64).contains(&username.len())
+        && username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+    valid.then_some(username)
+}
+
+fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>())
+        .map_err(|e| anyhow::anyhow!("Failed to encode salt: {}", e))?;
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))
+}
+
+fn verify_password(password: &str, phc: &str) -> bool {
+    PasswordHash::new(phc)
+        .map(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+pub struct AuthService {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    ttl: Duration,
+    users: Mutex<Vec<User>>,
+}
+
+impl AuthService {
+    pub fn load() -> Result<Self> {
+        let secret = match std::env::var("JWT_SECRET").ok().filter(|s| !s.is_empty()) {
+            Some(secret) if secret.len() < MIN_SECRET_BYTES => {
+                bail!("JWT_SECRET must be at least {} bytes", MIN_SECRET_BYTES)
+            }
+            Some(secret) => secret.into_bytes(),
+            None => {
+                tracing::warn!("JWT_SECRET not set, tokens will not survive a restart");
+                rand::random::<[u8; 32]>().to_vec()
+            }
+        };
+        let ttl = std::env::var("JWT_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+
+        let users = match std::fs::read_to_string(USERS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid users file {}", USERS_PATH))?,
+            Err(_) => Vec::new(),
+        };
+
+        Ok(Self {
+            encoding: EncodingKey::from_secret(&secret),
+            decoding: DecodingKey::from_secret(&secret),
+            ttl: Duration::seconds(ttl.max(60)),
+            users: Mutex::new(users),
+        })
+    }
+
+    fn save(users: &[User]) -> Result<()> {
+        std::fs::write(USERS_PATH, serde_json::to_string_pretty(users)?)
+            .with_context(|| format!("Failed to write {}", USERS_PATH))
+    }
+
+    fn issue(&self, user: &User, now: DateTime<Utc>) -> Result<(String, DateTime<Utc>)> {
+        let expires_at = now + self.ttl;
+        let claims = Claims {
+            sub: user.username.clone(),
+            role: user.role,
+            did: user.did.clone(),
+            roles: Vec::new(),
+            iat: now.timestamp(),
+            exp: expires_at.timestamp(),
+        };
+        let token = jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, &self.encoding)
+            .context("Failed to sign token")?;
+        Ok((token, expires_at))
+    }
+
+    /// Claims of a valid, unexpired token
+    pub fn verify(&self, token: &str) -> Result<Claims, ApiError> {
+        jsonwebtoken::decode::<Claims>(token, &self.decoding, &Validation::new(Algorithm::HS256))
+            .map(|data| data.claims)
+            .map_err(|e| {
+                tracing::debug!(error = %e, "Rejected JWT");
+                ApiError::unauthorized("Invalid or expired token")
+            })
+    }
+
+    pub async fn has_user(&self, username: &str) -> bool {
+        self.users
+            .lock()
+            .await
+            .iter()
+            .any(|u| u.username == username)
+    }
+
+    async fn is_active(&self, username: &str) -> bool {
+        self.users
+            .lock()
+            .await
+            .iter()
+            .any(|u| u.username == username && u.active)
+    }
+}
+
+// ======================== MIDDLEWARE ========================
+
+/// Resolve the bearer token into a [`Principal`] request extension
+///
+/// Invalid or expired JWTs, and JWTs of disabled accounts, are rejected here
+/// so no handler sees them.
+pub async fn authenticate(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let principal = match bearer_token(request.headers()) {
+        Some(token) if state.admin_token.as_deref() == Some(token) => Some(Principal::Admin),
+        Some(token) if is_jwt(token) => {
+            let mut claims = state.auth.verify(token)?;
+            if !state.auth.is_active(&claims.sub).await {
+                return Err(ApiError::unauthorized("Account is disabled"));
+            }
+            state.chain_roles.apply_to_claims(&mut claims)?;
+            Some(Principal::User(claims))
+        }
+        Some(token) if token.starts_with(crate::delegation::TOKEN_PREFIX) => {
+            let delegation = state.delegations.authenticate(token, Utc::now()).await?;
+            Some(Principal::Delegate(delegation))
+        }
+        _ => None,
+    };
+    if let Some(principal) = principal {
+        request.extensions_mut().insert(principal);
+    }
+    Ok(next.run(request).await)
+}
+
+/// Roles a route admits, plus the delegation scope its handler checks
+#[derive(Debug, Clone, Copy)]
+struct Guard {
+    roles: &'static [Role],
+    scope: Option<Scope>,
+}
+
+impl Guard {
+    fn admits(&self, principal: &Principal) -> bool {
+        principal.admits(self.roles) || self.scope.is_some_and(|s| principal.holds_scope(s))
+    }
+}
+
+async fn require_role(
+    State(guard): State<Guard>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    match request.extensions().get::<Principal>() {
+        Some(principal) if guard.admits(principal) => Ok(next.run(request).await),
+        Some(_) => {
+            let allowed: Vec<String> = guard.roles.iter().map(Role::to_string).collect();
+            Err(ApiError::forbidden(format!(
+                "Requires role: {}",
+                allowed.join(" or ")
+            )))
+        }
+        None => Err(ApiError::unauthorized(
+            "Sign in at /api/auth/login and send the token as a bearer token",
+        )),
+    }
+}
+
+/// Admit only `roles` (and admins) to a route
+pub fn restrict(route: MethodRouter<AppState>, roles: &'static [Role]) -> MethodRouter<AppState> {
+    let guard = Guard { roles, scope: None };
+    route.route_layer(middleware::from_fn_with_state(guard, require_role))
+}
+
+/// Like [`restrict`], also admitting delegations carrying `scope`; the
+/// handler must call [`crate::delegation::require_scope`] with it
+pub fn restrict_scoped(
+    route: MethodRouter<AppState>,
+    roles: &'static [Role],
+    scope: Scope,
+) -> MethodRouter<AppState> {
+    let guard = Guard {
+        roles,
+        scope: Some(scope),
+    };
+    route.route_layer(middleware::from_fn_with_state(guard, require_role))
+}
+
+/// Farmer accounts may only read their own records
+pub fn check_farmer_access(
+    principal: Option<&Principal>,
+    farmer_did: Option<&str>,
+) -> Result<(), ApiError> {
+    match principal {
+        Some(Principal::User(claims)) if claims.role == Role::Farmer => {
+            let own = claims.did.as_deref().zip(farmer_did);
+            if own.is_some_and(|(own, requested)| same_farmer(own, requested)) {
+                Ok(())
+            } else {
+                Err(ApiError::forbidden(
+                    "Farmers can only access their own batches",
+                ))
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoginResponse {
+    pub token: String,
+    pub token_type: &'static str,
+    pub expires_at: String,
+    pub role: Role,
+}
+
+pub async fn login(
+    State(state): State<AppState>,
+    Json(payload): Json<LoginRequest>,
+) -> ApiResult<LoginResponse> {
+    let invalid = || ApiError::unauthorized("Invalid username or password");
+    let username = normalize_username(&payload.username).ok_or_else(invalid)?;
+
+    let user = state
+        .auth
+        .users
+        .lock()
+        .await
+        .iter()
+        .find(|u| u.username == username && u.active)
+        .cloned();
+    let Some(user) = user else {
+        tracing::warn!(username = %username, "Login for unknown or disabled account");
+        return Err(invalid());
+    };
+
+    // Argon2 is deliberately slow; keep it off the async workers
+    let password = payload.password;
+    let phc = user.password_hash.clone();
+    let valid = tokio::task::spawn_blocking(move || verify_password(&password, &phc))
+        .await
+        .map_err(|e| ApiError::internal(format!("Password check failed: {}", e)))?;
+    if !valid {
+        tracing::warn!(username = %username, "Login with wrong password");
+        return Err(invalid());
+    }
+
+    let (token, expires_at) = state.auth.issue(&user, Utc::now())?;
+    tracing::info!(username = %user.username, role = %user.role, "User signed in");
+
+    Ok(Json(LoginResponse {
+        token,
+        token_type: "Bearer",
+        expires_at: expires_at.to_rfc3339(),
+        role: user.role,
+    }))
+}
+
+/// Claims of the caller's token
+pub async fn current_user(principal: Option<Extension<Principal>>) -> ApiResult<Claims> {
+    match principal {
+        Some(Extension(Principal::User(claims))) => Ok(Json(claims)),
+        _ => Err(ApiError::unauthorized("Not signed in")),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateUserRequest {
+    pub username: String,
+    pub password: String,
+    pub role: Role,
+    /// Required for farmer accounts
+    #[serde(default)]
+    pub did: Option<String>,
+}
+
+pub async fn create_user(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateUserRequest>,
+) -> ApiResult<UserSummary> {
+    require_admin(&state, &headers)?;
+
+    let username = normalize_username(&payload.username).ok_or_else(|| {
+        ApiError::bad_request("username must be 3-64 characters of a-z, 0-9, '.', '_' or '-'")
+    })?;
+    if payload.password.chars().count() < MIN_PASSWORD_LEN {
+        return Err(ApiError::bad_request(format!(
+            "password must be at least {} characters",
+            MIN_PASSWORD_LEN
+        )));
+    }
+    let did = match (payload.role, payload.did) {
+        (Role::Farmer, Some(did)) => Some(canonical(&did).map_err(ApiError::invalid_did)?),
+        (Role::Farmer, None) => {
+            return Err(ApiError::bad_request("did is required for farmer accounts"))
+        }
+        (_, did) => did,
+    };
+
+    let password = payload.password;
+    let password_hash = tokio::task::spawn_blocking(move || hash_password(&password))
+        .await
+        .map_err(|e| ApiError::internal(format!("Password hashing failed: {}", e)))??;
+
+    let mut users = state.auth.users.lock().await;
+    if users.iter().any(|u| u.username == username) {
+        return Err(ApiError::bad_request(format!(
+            "User {} already exists",
+            username
+        )));
+    }
+    let user = User {
+        id: users.last().map(|u| u.id + 1).unwrap_or(1),
+        username,
+        role: payload.role,
+        did,
+        active: true,
+        created_at: Utc::now().to_rfc3339(),
+        password_hash,
+    };
+    users.push(user.clone());
+    AuthService::save(&users)?;
+
+    tracing::info!(user_id = user.id, username = %user.username, role = %user.role, "User created");
+    Ok(Json(UserSummary::from(&user)))
+}
+
+pub async fn list_users(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<UserSummary>> {
+    require_admin(&state, &headers)?;
+    let users: Vec<UserSummary> = state
+        .auth
+        .users
+        .lock()
+        .await
+        .iter()
+        .map(UserSummary::from)
+        .collect();
+    Ok(Json(paginate("users", users, &params, |u| u.id)?))
+}
+
+/// Disable an account; its tokens stop working immediately
+pub async fn disable_user(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> ApiResult<UserSummary> {
+    require_admin(&state, &headers)?;
+
+    let mut users = state.auth.users.lock().await;
+    let user = users
+        .iter_mut()
+        .find(|u| u.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("User {} not found", id)))?;
+    user.active = false;
+    let user = user.clone();
+    AuthService::save(&users)?;
+
+    tracing::info!(user_id = id, username = %user.username, "User disabled");
+    Ok(Json(UserSummary::from(&user)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> AuthService {
+        AuthService {
+            encoding: EncodingKey::from_secret(b"test-secret-test-secret-test-secret"),
+            decoding: DecodingKey::from_secret(b"test-secret-test-secret-test-secret"),
+            ttl: Duration::hours(1),
+            users: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn user(role: Role) -> User {
+        User {
+            id: 1,
+            username: "fpo.nashik".to_string(),
+            role,
+            did: None,
+            active: true,
+            created_at: String::new(),
+            password_hash: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_token_round_trip_and_expiry() {
+        let auth = service();
+        let (token, _) = auth.issue(&user(Role::Fpo), Utc::now()).unwrap();
+        assert!(is_jwt(&token));
+        let claims = auth.verify(&token).unwrap();
+        assert_eq!(
+            (claims.sub.as_str(), claims.role),
+            ("fpo.nashik", Role::Fpo)
+        );
+
+        let (expired, _) = auth
+            .issue(&user(Role::Fpo), Utc::now() - Duration::hours(3))
+            .unwrap();
+        assert!(auth.verify(&expired).is_err());
+
+        let mut tampered = token.clone();
+        tampered.push('x');
+        assert!(auth.verify(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_guards_admit_roles() {
+        let claims = |role| {
+            Principal::User(Claims {
+                sub: "u".to_string(),
+                role,
+                did: None,
+                roles: Vec::new(),
+                iat: 0,
+                exp: 0,
+            })
+        };
+        assert!(claims(Role::Fpo).admits(&[Role::Fpo]));
+        assert!(!claims(Role::Farmer).admits(&[Role::Fpo]));
+        assert!(claims(Role::Admin).admits(&[Role::Processor]));
+        assert!(Principal::Admin.admits(&[Role::Warehouse]));
+        let key = Principal::ApiKey {
+            id: 1,
+            name: "warehouse-gw-nashik".to_string(),
+            scopes: vec![Role::Warehouse],
+        };
+        assert!(key.admits(&[Role::Warehouse, Role::Processor]));
+        assert!(!key.admits(&[Role::Admin]));
+    }
+
+    #[tokio::test]
+    async fn test_delegates_pass_only_scoped_guards() {
+        use axum::{body::Body, http::StatusCode, routing::post, Router};
+        use tower::ServiceExt;
+
+        let delegation: Delegation = serde_json::from_value(serde_json::json!({
+            "id": 7, "delegator": "FPO-001", "delegate": "agent-7",
+            "scopes": ["fpo_purchase"], "issued_at": "2025-06-01T00:00:00Z",
+            "expires_at": "2099-01-01T00:00:00Z", "token_hash": ""
+        }))
+        .unwrap();
+        let status = |guard: Guard| {
+            let app = Router::new()
+                .route(
+                    "/",
+                    post(|| async { "ok" })
+                        .route_layer(middleware::from_fn_with_state(guard, require_role)),
+                )
+                .layer(Extension(Principal::Delegate(delegation.clone())));
+            async move {
+                let request = axum::http::Request::post("/").body(Body::empty()).unwrap();
+                app.oneshot(request).await.unwrap().status()
+            }
+        };
+
+        let fpo_only = Guard {
+            roles: &[Role::Fpo],
+            scope: None,
+        };
+        assert_eq!(status(fpo_only).await, StatusCode::FORBIDDEN);
+        let purchase = Guard {
+            scope: Some(Scope::FpoPurchase),
+            ..fpo_only
+        };
+        assert_eq!(status(purchase).await, StatusCode::OK);
+    }
+
+    #[test]
+    fn test_passwords_and_usernames() {
+        let hash = hash_password("correct horse battery").unwrap();
+        assert!(verify_password("correct horse battery", &hash));
+        assert!(!verify_password("wrong horse battery", &hash));
+
+        assert_eq!(
+            normalize_username(" FPO.Nashik "),
+            Some("fpo.nashik".to_string())
+        );
+        assert_eq!(normalize_username("ab"), None);
+        assert_eq!(normalize_username("a b c"), None);
+    }
+}
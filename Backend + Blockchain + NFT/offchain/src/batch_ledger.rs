@@ -0,0 +1,313 @@
+//! Read access to the per-batch records kept in `data/<batch_id>/`
+//!
+//! Every supply chain stage writes its metadata JSON into the batch folder
+//! before the folder is pinned to IPFS. This module reads those files back
+//! so feature-phone channels (SMS, USSD) can report batch progress and
+//! payments without a chain round trip.
+
+use crate::compact_payload;
+use crate::holds::{self, SettlementStatus};
+use crate::reference_data;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Root directory holding one folder per batch
+pub const DATA_DIR: &str = "data";
+
+/// Folder holding the records of a batch
+pub fn batch_path(batch_id: &str) -> PathBuf {
+    Path::new(DATA_DIR).join(batch_id)
+}
+
+/// Batch IDs are used as folder names, so reject anything that could escape `data/`
+pub fn is_valid_batch_id(batch_id: &str) -> bool {
+    !batch_id.is_empty()
+        && batch_id.len() <= 64
+        && batch_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Six-digit numeric code for keypad entry on USSD/IVR channels.
+///
+/// Codes are derived from the keccak hash of the identifier, so they are
+/// stable across restarts; callers resolve them against a known candidate set.
+pub fn short_code(id: &str) -> String {
+    let hash = crate::chain::hash_string(id);
+    let prefix = u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]);
+    format!("{:06}", prefix % 1_000_000)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchStatus {
+    pub batch_id: String,
+    pub farmer_did: Option<String>,
+    pub crop_type: Option<String>,
+    pub quantity_kg: Option<f64>,
+    pub quality_grade: Option<String>,
+    pub purchased_at: Option<String>,
+    pub processed: bool,
+    pub ai_scored: bool,
+    pub packaged_skus: usize,
+}
+
+impl BatchStatus {
+    /// Latest stage reached, in supply chain order
+    /// `batch_stage` code (see [`crate::reference_data`])
+    pub fn stage(&self) -> &'static str {
+        if self.packaged_skus > 0 {
+            "packaged"
+        } else if self.ai_scored {
+            "quality_scored"
+        } else if self.processed {
+            "processed"
+        } else {
+            "purchased"
+        }
+    }
+
+    pub fn stage_label(&self) -> &'static str {
+        reference_data::english_label("batch_stage", self.stage())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PaymentRecord {
+    pub batch_id: String,
+    pub quantity_kg: f64,
+    pub price_per_kg: f64,
+    pub total_cost: f64,
+    pub timestamp: String,
+    pub settlement: SettlementStatus,
+    /// Reasons of active payment holds
+    pub hold_reasons: Vec<String>,
+}
+
+fn read_json(path: &Path) -> Result<Value> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let value = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid JSON in {}", path.display()))?;
+    compact_payload::decode(value).with_context(|| format!("Invalid payload in {}", path.display()))
+}
+
+/// Read the FPO purchase record of a batch, if the batch exists
+pub fn fpo_purchase(batch_id: &str) -> Result<Option<Value>> {
+    if !is_valid_batch_id(batch_id) {
+        return Ok(None);
+    }
+    let path = batch_path(batch_id).join("fpo_purchase.json");
+    if !path.exists() {
+        return Ok(None);
+    }
+    read_json(&path).map(Some)
+}
+
+/// Every JSON record in a batch folder, keyed by file name
+pub fn batch_records(batch_id: &str) -> Result<Vec<(String, Value)>> {
+    if !is_valid_batch_id(batch_id) {
+        return Ok(Vec::new());
+    }
+    let entries = match fs::read_dir(batch_path(batch_id)) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut records = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.ends_with(".json") {
+            records.push((name, read_json(&entry.path())?));
+        }
+    }
+    records.sort_by(|a, b| a.0.cmp(&b.0));
+
+    Ok(records)
+}
+
+/// Packaging record of a SKU together with the batch it was packed from
+#[derive(Debug, Clone)]
+pub struct SkuRecord {
+    pub batch_id: String,
+    pub packaging: Value,
+}
+
+/// Locate the batch folder holding `packaging_<sku_id>.json`
+pub fn find_sku(sku_id: &str) -> Result<Option<SkuRecord>> {
+    if !is_valid_batch_id(sku_id) {
+        return Ok(None);
+    }
+    let entries = match fs::read_dir(DATA_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(None),
+    };
+
+    let filename = format!("packaging_{}.json", sku_id);
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path().join(&filename);
+        if path.exists() {
+            return Ok(Some(SkuRecord {
+                batch_id: entry.file_name().to_string_lossy().to_string(),
+                packaging: read_json(&path)?,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Every packaged SKU as `(sku_id, batch_id)`
+pub fn all_skus() -> Vec<(String, String)> {
+    let mut skus = Vec::new();
+
+    let entries = match fs::read_dir(DATA_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return skus,
+    };
+
+    for batch in entries.filter_map(|e| e.ok()) {
+        let Ok(files) = fs::read_dir(batch.path()) else {
+            continue;
+        };
+        let batch_id = batch.file_name().to_string_lossy().to_string();
+        for file in files.filter_map(|e| e.ok()) {
+            let name = file.file_name().to_string_lossy().to_string();
+            if let Some(sku_id) = name
+                .strip_prefix("packaging_")
+                .and_then(|n| n.strip_suffix(".json"))
+            {
+                skus.push((sku_id.to_string(), batch_id.clone()));
+            }
+        }
+    }
+
+    skus
+}
+
+/// Summarise which stages have written records for a batch
+pub fn batch_status(batch_id: &str) -> Result<Option<BatchStatus>> {
+    if !is_valid_batch_id(batch_id) {
+        return Ok(None);
+    }
+    let folder = batch_path(batch_id);
+    if !folder.is_dir() {
+        return Ok(None);
+    }
+
+    let purchase = fpo_purchase(batch_id)?;
+    let field = |pointer: &str| purchase.as_ref().and_then(|p| p.pointer(pointer)).cloned();
+
+    let packaged_skus = fs::read_dir(&folder)
+        .with_context(|| format!("Failed to list {}", folder.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .starts_with("packaging_")
+        })
+        .count();
+
+    Ok(Some(BatchStatus {
+        batch_id: batch_id.to_string(),
+        farmer_did: field("/farmer_info/farmer_did").and_then(|v| v.as_str().map(String::from)),
+        crop_type: field("/farmer_info/crop_type").and_then(|v| v.as_str().map(String::from)),
+        quantity_kg: field("/batch_info/quantity_kg").and_then(|v| v.as_f64()),
+        quality_grade: field("/batch_info/quality_grade")
+            .and_then(|v| v.as_str().map(String::from)),
+        purchased_at: field("/timestamp").and_then(|v| v.as_str().map(String::from)),
+        processed: folder.join("processing.json").exists(),
+        ai_scored: folder.join("ai_score.json").exists(),
+        packaged_skus,
+    }))
+}
+
+/// FPO purchase records of every batch, keyed by batch ID
+///
+/// Unreadable records are logged and skipped.
+pub fn all_purchases() -> Vec<(String, Value)> {
+    let mut purchases = Vec::new();
+
+    let entries = match fs::read_dir(DATA_DIR) {
+        Ok(entries) => entries,
+        Err(_) => return purchases,
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        if !entry.path().is_dir() {
+            continue;
+        }
+        let batch_id = entry.file_name().to_string_lossy().to_string();
+        match fpo_purchase(&batch_id) {
+            Ok(Some(purchase)) => purchases.push((batch_id, purchase)),
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!(batch_id = %batch_id, error = %e, "Skipping unreadable batch record");
+            }
+        }
+    }
+
+    purchases
+}
+
+/// All FPO purchase payments made to a farmer, newest first
+pub fn payments_for_farmer(farmer_did: &str) -> Result<Vec<PaymentRecord>> {
+    let mut payments = Vec::new();
+
+    for (batch_id, purchase) in all_purchases() {
+        if purchase
+            .pointer("/farmer_info/farmer_did")
+            .and_then(|v| v.as_str())
+            != Some(farmer_did)
+        {
+            continue;
+        }
+
+        let number = |pointer: &str| {
+            purchase
+                .pointer(pointer)
+                .and_then(|v| v.as_f64())
+                .unwrap_or(0.0)
+        };
+        let settlement = holds::settlement(&batch_id)?;
+        payments.push(PaymentRecord {
+            batch_id,
+            quantity_kg: number("/batch_info/quantity_kg"),
+            price_per_kg: number("/pricing/price_per_kg"),
+            total_cost: number("/pricing/total_cost"),
+            timestamp: purchase
+                .get("timestamp")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            settlement: settlement.as_ref().map(|s| s.status).unwrap_or_default(),
+            hold_reasons: settlement.map(|s| s.reasons()).unwrap_or_default(),
+        });
+    }
+
+    payments.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    Ok(payments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_id_validation() {
+        assert!(is_valid_batch_id("batch-21_a"));
+        assert!(!is_valid_batch_id("../farmers_db.json"));
+        assert!(!is_valid_batch_id(""));
+    }
+
+    #[test]
+    fn test_short_code_is_stable_six_digits() {
+        let code = short_code("21");
+        assert_eq!(code.len(), 6);
+        assert!(code.chars().all(|c| c.is_ascii_digit()));
+        assert_eq!(code, short_code("21"));
+    }
+}
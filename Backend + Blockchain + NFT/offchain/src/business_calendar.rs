@@ -0,0 +1,336 @@
+//! Business calendars per state
+//!
+//! Calendars are read from `data/business_calendars.json` (override with
+//! BUSINESS_CALENDARS_PATH). `default` applies everywhere; a state entry,
+//! keyed by the farmer's state code, overrides its settings and adds its own
+//! holidays to the default ones:
+//!
+//! ```json
+//! { "default": { "utc_offset_minutes": 330, "weekly_off": ["sun"],
+//!                "cutoff": "17:00", "holidays": ["2025-01-26", "2025-08-15"] },
+//!   "states": { "MH": { "holidays": ["2025-05-01"] },
+//!               "KA": { "cutoff": "16:00", "holidays": ["2025-11-01"] } } }
+//! ```
+//!
+//! Anything that happens after the cutoff, or on a weekly off or holiday,
+//! counts towards the next business day. Settlements use this for the payment
+//! due date (PAYMENT_TERMS_DAYS business days after the purchase, default 3)
+//! and scheduled reports for their windows. Without a file every day is a
+//! business day in UTC with no cutoff.
+
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+const DEFAULT_CALENDARS_PATH: &str = "data/business_calendars.json";
+const DEFAULT_PAYMENT_TERMS_DAYS: u32 = 3;
+const MAX_LISTED_DAYS: u32 = 60;
+
+// ======================== CONFIGURATION ========================
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct CalendarSpec {
+    #[serde(default)]
+    utc_offset_minutes: Option<i32>,
+    /// Day names such as "sun" or "Saturday"
+    #[serde(default)]
+    weekly_off: Option<Vec<String>>,
+    /// Daily cutoff as HH:MM local time
+    #[serde(default)]
+    cutoff: Option<String>,
+    /// YYYY-MM-DD
+    #[serde(default)]
+    holidays: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct CalendarsFile {
+    #[serde(default)]
+    default: CalendarSpec,
+    #[serde(default)]
+    states: BTreeMap<String, CalendarSpec>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BusinessCalendar {
+    pub name: String,
+    offset: FixedOffset,
+    weekly_off: Vec<Weekday>,
+    cutoff: Option<NaiveTime>,
+    holidays: BTreeSet<NaiveDate>,
+}
+
+impl Default for BusinessCalendar {
+    fn default() -> Self {
+        Self {
+            name: "default".to_string(),
+            offset: FixedOffset::east_opt(0).expect("zero offset"),
+            weekly_off: Vec::new(),
+            cutoff: None,
+            holidays: BTreeSet::new(),
+        }
+    }
+}
+
+impl BusinessCalendar {
+    /// Apply `spec` on top of `self`, adding its holidays
+    fn extend(&self, name: &str, spec: &CalendarSpec) -> Result<Self> {
+        let mut calendar = self.clone();
+        calendar.name = name.to_string();
+
+        if let Some(minutes) = spec.utc_offset_minutes {
+            calendar.offset = FixedOffset::east_opt(minutes * 60)
+                .with_context(|| format!("Calendar {}: invalid UTC offset", name))?;
+        }
+        if let Some(days) = &spec.weekly_off {
+            calendar.weekly_off = days
+                .iter()
+                .map(|d| {
+                    d.parse::<Weekday>()
+                        .map_err(|_| anyhow::anyhow!("Calendar {}: invalid weekday {}", name, d))
+                })
+                .collect::<Result<_>>()?;
+            if calendar.weekly_off.len() >= 7 {
+                bail!("Calendar {}: every weekday is off", name);
+            }
+        }
+        if let Some(cutoff) = &spec.cutoff {
+            calendar.cutoff = Some(
+                NaiveTime::parse_from_str(cutoff, "%H:%M")
+                    .with_context(|| format!("Calendar {}: cutoff must be HH:MM", name))?,
+            );
+        }
+        for day in &spec.holidays {
+            calendar.holidays.insert(
+                NaiveDate::parse_from_str(day, "%Y-%m-%d")
+                    .with_context(|| format!("Calendar {}: invalid holiday {}", name, day))?,
+            );
+        }
+        Ok(calendar)
+    }
+
+    pub fn is_business_day(&self, day: NaiveDate) -> bool {
+        !self.weekly_off.contains(&day.weekday()) && !self.holidays.contains(&day)
+    }
+
+    /// `day` itself when it is a business day, else the next one
+    pub fn roll_forward(&self, day: NaiveDate) -> NaiveDate {
+        let mut day = day;
+        // Holidays are finite and at least one weekday is on
+        while !self.is_business_day(day) {
+            day += Duration::days(1);
+        }
+        day
+    }
+
+    /// Business day `days` business days after `day`
+    pub fn add_business_days(&self, day: NaiveDate, days: u32) -> NaiveDate {
+        let mut day = self.roll_forward(day);
+        for _ in 0..days {
+            day = self.roll_forward(day + Duration::days(1));
+        }
+        day
+    }
+
+    /// Calendar date at `at` in this calendar's timezone
+    pub fn local_date(&self, at: DateTime<Utc>) -> NaiveDate {
+        at.with_timezone(&self.offset).date_naive()
+    }
+
+    /// Business day an event at `at` counts towards: after the cutoff it
+    /// moves to the next day, and off days roll forward
+    pub fn effective_date(&self, at: DateTime<Utc>) -> NaiveDate {
+        let local = at.with_timezone(&self.offset);
+        let mut day = local.date_naive();
+        if self.cutoff.is_some_and(|cutoff| local.time() >= cutoff) {
+            day += Duration::days(1);
+        }
+        self.roll_forward(day)
+    }
+
+    /// First instant of `day` in this calendar's timezone
+    pub fn day_start(&self, day: NaiveDate) -> DateTime<Utc> {
+        let midnight = day.and_hms_opt(0, 0, 0).unwrap_or_default();
+        (midnight - Duration::seconds(self.offset.local_minus_utc() as i64)).and_utc()
+    }
+}
+
+pub struct CalendarRegistry {
+    default: BusinessCalendar,
+    states: HashMap<String, BusinessCalendar>,
+    payment_terms_days: u32,
+}
+
+impl CalendarRegistry {
+    pub fn load() -> Result<Self> {
+        let path = std::env::var("BUSINESS_CALENDARS_PATH")
+            .unwrap_or_else(|_| DEFAULT_CALENDARS_PATH.to_string());
+
+        let file: CalendarsFile = if std::path::Path::new(&path).exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read business calendars {}", path))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid business calendars {}", path))?
+        } else {
+            CalendarsFile::default()
+        };
+
+        let default = BusinessCalendar::default().extend("default", &file.default)?;
+        let states = file
+            .states
+            .iter()
+            .map(|(code, spec)| {
+                let code = code.to_uppercase();
+                Ok((code.clone(), default.extend(&code, spec)?))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            default,
+            states,
+            payment_terms_days: std::env::var("PAYMENT_TERMS_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_PAYMENT_TERMS_DAYS),
+        })
+    }
+
+    /// Calendar of a state, falling back to the default one
+    pub fn for_state(&self, state_code: Option<&str>) -> &BusinessCalendar {
+        state_code
+            .and_then(|code| self.states.get(&code.trim().to_uppercase()))
+            .unwrap_or(&self.default)
+    }
+
+    /// Date a farmer is due to be paid for a purchase made at `purchased_at`
+    pub fn payment_due(&self, state_code: Option<&str>, purchased_at: DateTime<Utc>) -> NaiveDate {
+        let calendar = self.for_state(state_code);
+        calendar.add_business_days(
+            calendar.effective_date(purchased_at),
+            self.payment_terms_days,
+        )
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarParams {
+    /// First day listed; defaults to today's effective business day
+    pub from: Option<String>,
+    /// Business days listed (default 10, at most 60)
+    pub days: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CalendarView {
+    pub calendar: String,
+    pub utc_offset_minutes: i32,
+    pub weekly_off: Vec<String>,
+    pub cutoff: Option<String>,
+    /// Business day something happening now counts towards
+    pub effective_date: String,
+    pub business_days: Vec<String>,
+    pub upcoming_holidays: Vec<String>,
+}
+
+/// Business days of a state's calendar
+pub async fn get_calendar(
+    State(state): State<AppState>,
+    Path(state_code): Path<String>,
+    Query(params): Query<CalendarParams>,
+) -> ApiResult<CalendarView> {
+    let calendar = state.calendars.for_state(Some(&state_code));
+    let effective = calendar.effective_date(Utc::now());
+    let from = match params.from.as_deref() {
+        Some(from) => NaiveDate::parse_from_str(from, "%Y-%m-%d")
+            .map_err(|_| ApiError::bad_request("from must be a YYYY-MM-DD date"))?,
+        None => effective,
+    };
+    let count = params.days.unwrap_or(10).clamp(1, MAX_LISTED_DAYS);
+
+    let mut business_days = Vec::with_capacity(count as usize);
+    let mut day = calendar.roll_forward(from);
+    for _ in 0..count {
+        business_days.push(day.format("%Y-%m-%d").to_string());
+        day = calendar.add_business_days(day, 1);
+    }
+
+    Ok(Json(CalendarView {
+        calendar: calendar.name.clone(),
+        utc_offset_minutes: calendar.offset.local_minus_utc() / 60,
+        weekly_off: calendar.weekly_off.iter().map(|d| d.to_string()).collect(),
+        cutoff: calendar.cutoff.map(|c| c.format("%H:%M").to_string()),
+        effective_date: effective.format("%Y-%m-%d").to_string(),
+        business_days,
+        upcoming_holidays: calendar
+            .holidays
+            .range(from..)
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn calendar() -> BusinessCalendar {
+        let spec = CalendarSpec {
+            utc_offset_minutes: Some(330),
+            weekly_off: Some(vec!["sun".to_string()]),
+            cutoff: Some("17:00".to_string()),
+            holidays: vec!["2025-08-15".to_string()],
+        };
+        BusinessCalendar::default().extend("IN", &spec).unwrap()
+    }
+
+    #[test]
+    fn test_effective_date_after_cutoff_and_holidays() {
+        let cal = calendar();
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        // 16:00 IST on Wednesday 2025-08-13
+        assert_eq!(
+            cal.effective_date(at("2025-08-13T10:30:00Z")),
+            day("2025-08-13")
+        );
+        // 18:00 IST Thursday rolls past the Friday holiday to Saturday
+        assert_eq!(
+            cal.effective_date(at("2025-08-14T12:30:00Z")),
+            day("2025-08-16")
+        );
+        // 23:30 UTC Saturday is already Sunday in IST, an off day
+        assert_eq!(
+            cal.effective_date(at("2025-08-16T23:30:00Z")),
+            day("2025-08-18")
+        );
+    }
+
+    #[test]
+    fn test_add_business_days_skips_off_days() {
+        let cal = calendar();
+        assert_eq!(
+            cal.add_business_days(day("2025-08-13"), 3),
+            day("2025-08-18")
+        );
+        assert_eq!(
+            cal.add_business_days(day("2025-08-17"), 0),
+            day("2025-08-18")
+        );
+        assert_eq!(
+            cal.day_start(day("2025-08-13")).to_rfc3339(),
+            "2025-08-12T18:30:00+00:00"
+        );
+    }
+}
@@ -1,17 +1,33 @@
 use alloy::{
-    network::EthereumWallet,
-    primitives::{Address, FixedBytes},
-    providers::ProviderBuilder,
-    rpc::types::TransactionReceipt,
+    network::{EthereumWallet, TransactionBuilder},
+    primitives::{Address, Bytes, FixedBytes, U256},
+    providers::{Provider, ProviderBuilder},
+    rpc::types::{Filter, TransactionReceipt, TransactionRequest},
     signers::local::PrivateKeySigner,
     sol,
+    sol_types::{SolEvent, SolEventInterface},
     transports::http::{Client, Http},
 };
+use crate::confirmations::{self, ConfirmationPolicy};
+use crate::degradation;
+use crate::error::{explorer_tx_url, format_tx_hash};
+use crate::metering::{self, Metric};
+use crate::networks::{self, EnvVars};
+use crate::outbox::{self, Outbox};
+use crate::readiness::Readiness;
+use crate::revert::ContractRevert;
+use crate::signers::{self, SignerSet};
+use crate::slowlog::{self, SlowOperation};
+use crate::tx_queue::{self, GasStrategy, TxQueue};
 use anyhow::{Context, Result};
+use std::collections::HashMap;
 use std::env;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
 
 // Type alias for the provider with all recommended fillers + wallet
-type AppProvider = alloy::providers::fillers::FillProvider<
+pub(crate) type AppProvider = alloy::providers::fillers::FillProvider<
     alloy::providers::fillers::JoinFill<
         alloy::providers::fillers::JoinFill<
             alloy::providers::Identity,
@@ -37,6 +53,20 @@ type AppProvider = alloy::providers::fillers::FillProvider<
 sol! {
     #[sol(rpc)]
     contract OilseedValueChain {
+        error Unauthorized();
+        error AlreadyRegistered();
+        error FarmerNotRegistered();
+        error SKUAlreadyExists();
+        error SKUNotFound();
+        error AlreadyCommitted();
+        error NotCommitted();
+        error AlreadyRevealed();
+        error InvalidReveal();
+        error LengthMismatch();
+        error RevealTooEarly();
+        error RevealTooLate();
+        error InvalidTransferType();
+
         event RoleGranted(address indexed account, uint256 role);
         event RoleRevoked(address indexed account, uint256 role);
 
@@ -47,6 +77,13 @@ sol! {
             string metadataCID
         );
 
+        event FarmerUpdated(
+            bytes32 indexed farmerDID,
+            bytes32 indexed cropIDHash,
+            uint64 timestamp,
+            string metadataCID
+        );
+
         event OwnershipTransfer(
             bytes32 indexed batchHash,
             bytes32 indexed fromDID,
@@ -138,10 +175,18 @@ sol! {
 
         // Stage 1: Farmer Registration
         function registerFarmer(bytes32 farmerDID, bytes32 cropIDHash, string calldata metadataCID) external;
+        function updateFarmerMetadata(bytes32 farmerDID, bytes32 cropIDHash, string calldata metadataCID) external;
         function farmers(bytes32 farmerDID) external view returns (FarmerRecord memory);
 
         // Stage 2: FPO Verification
         function fpoPurchase(bytes32 batchHash, bytes32 farmerDID, string calldata metadataCID) external;
+        function recordOwnershipTransfer(
+            bytes32 batchHash,
+            bytes32 fromDID,
+            address toAddress,
+            uint8 transferType,
+            string calldata metadataCID
+        ) external;
 
         // Stage 3: Warehouse Storage
         function updateWarehouseState(bytes32 warehouseId, bytes32 stateHash, string calldata metadataCID) external;
@@ -183,6 +228,11 @@ sol! {
             bool[] calldata deliveryStatuses
         ) external;
 
+        function batchRegisterFarmers(
+            bytes32[] calldata farmerDIDs,
+            bytes32[] calldata cropIDHashes
+        ) external;
+
         // Verification Functions
         function verifyPackageOrigin(bytes32 skuId) external view
             returns (bytes32 parentBatchHash, bytes32 merkleRoot, uint64 packagedAt);
@@ -198,52 +248,427 @@ sol! {
     }
 }
 
+// ERC-721 token per packaged SKU, matching SkuNFT.sol
+sol! {
+    #[sol(rpc)]
+    contract SkuNFT {
+        error Unauthorized();
+        error SkuAlreadyMinted();
+        error ZeroAddress();
+        error TokenNotFound();
+
+        event SkuMinted(
+            uint256 indexed tokenId,
+            bytes32 indexed skuId,
+            address indexed recipient,
+            string tokenURI
+        );
+
+        function mint(address recipient, bytes32 skuId, string calldata uri)
+            external returns (uint256);
+
+        function tokenOfSku(bytes32 skuId) external view returns (uint256);
+    }
+}
+
+// Settlement register of FPO purchases, matching FarmerPayments.sol
+sol! {
+    #[sol(rpc)]
+    contract FarmerPayments {
+        error Unauthorized();
+        error ZeroAddress();
+        error AlreadySettled();
+        error InvalidAmount();
+
+        event PaymentSettled(
+            bytes32 indexed batchHash,
+            bytes32 indexed farmerDID,
+            uint256 amountPaise,
+            bytes32 paymentRef,
+            uint64 settledAt
+        );
+
+        function recordPayment(
+            bytes32 batchHash,
+            bytes32 farmerDID,
+            uint256 amountPaise,
+            bytes32 paymentRef
+        ) external;
+
+        function settlementOf(bytes32 batchHash) external view
+            returns (bytes32 farmerDID, uint256 amountPaise, bytes32 paymentRef, uint64 settledAt);
+    }
+}
+
+/// Role bits as defined by the `ROLE_*` constants in nyx.sol
+pub mod roles {
+    pub const ADMIN: u64 = 1 << 0;
+    pub const FARMER: u64 = 1 << 1;
+    pub const FPO: u64 = 1 << 2;
+    pub const WAREHOUSE: u64 = 1 << 3;
+    pub const LOGISTICS: u64 = 1 << 4;
+    pub const PROCESSOR: u64 = 1 << 5;
+    pub const PACKAGER: u64 = 1 << 6;
+    pub const AI_ORACLE: u64 = 1 << 7;
+
+    /// Roles the backend signer needs to submit every supply chain stage
+    pub const OPERATIONAL: &[(&str, u64)] = &[
+        ("FPO", FPO),
+        ("WAREHOUSE", WAREHOUSE),
+        ("LOGISTICS", LOGISTICS),
+        ("PROCESSOR", PROCESSOR),
+        ("PACKAGER", PACKAGER),
+        ("AI_ORACLE", AI_ORACLE),
+    ];
+
+    pub const ALL: &[(&str, u64)] = &[
+        ("ADMIN", ADMIN),
+        ("FARMER", FARMER),
+        ("FPO", FPO),
+        ("WAREHOUSE", WAREHOUSE),
+        ("LOGISTICS", LOGISTICS),
+        ("PROCESSOR", PROCESSOR),
+        ("PACKAGER", PACKAGER),
+        ("AI_ORACLE", AI_ORACLE),
+    ];
+
+    /// Combined bits of role names (case-insensitive)
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> anyhow::Result<u64> {
+        names.iter().try_fold(0, |bits, name| {
+            let name = name.as_ref();
+            ALL.iter()
+                .find(|(role, _)| role.eq_ignore_ascii_case(name))
+                .map(|(_, bit)| bits | bit)
+                .ok_or_else(|| anyhow::anyhow!("Unknown role '{}'", name))
+        })
+    }
+
+    /// Names of the roles set in `bits`
+    pub fn names(bits: u64) -> Vec<&'static str> {
+        ALL.iter()
+            .filter(|(_, bit)| bits & bit != 0)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
 pub struct ChainConfig {
+    /// Name the network is selected by, see [`crate::networks`]
+    pub network: String,
     pub rpc_url: String,
+    /// Signs writes without a role key of their own
     pub private_key: String,
+    /// PRIVATE_KEY_<ROLE> keys as (role bit, key)
+    pub role_keys: Vec<(u64, String)>,
     pub contract_address: String,
+    /// Previous contract kept readable during a blue/green migration
+    pub legacy_contract_address: Option<String>,
+    /// Send writes to CONTRACT_ADDRESS (true) or still to the legacy contract
+    pub write_cutover: bool,
+    /// SkuNFT contract; SKU minting is disabled without it
+    pub nft_contract_address: Option<String>,
+    /// FarmerPayments contract; settlements stay off chain without it
+    pub payments_contract_address: Option<String>,
+    /// Block explorer linked from transaction responses, e.g.
+    /// https://amoy.polygonscan.com
+    pub explorer_base_url: Option<String>,
     pub chain_id: u64,
+    pub validate_on_startup: bool,
+    /// Fee and gas limit controls for every write
+    pub gas: GasStrategy,
+    /// Blocks a write waits for before the API responds
+    pub confirmations: ConfirmationPolicy,
 }
 
 impl ChainConfig {
-    pub fn from_env() -> Result<Self> {
-        let rpc_url = env::var("RPC_URL").context("RPC_URL environment variable is required")?;
-        let private_key =
-            env::var("PRIVATE_KEY").context("PRIVATE_KEY environment variable is required")?;
-        let contract_address = env::var("CONTRACT_ADDRESS")
-            .context("CONTRACT_ADDRESS environment variable is required")?;
-        let chain_id = env::var("CHAIN_ID")
-            .unwrap_or_else(|_| "1".to_string())
+    /// Default network, configured by the unsuffixed variables
+    pub fn from_env(vars: &EnvVars) -> Result<Self> {
+        Self::load(&networks::default_network(), str::to_string, vars)
+    }
+
+    /// Named network, configured by `<VAR>_<NAME>` for the variables in
+    /// [`networks::PER_NETWORK_VARS`]; keys, gas and confirmation settings
+    /// are shared with the default network
+    pub fn for_network(network: &str, vars: &EnvVars) -> Result<Self> {
+        let suffix = networks::var_suffix(network);
+        Self::load(
+            network,
+            |name| {
+                if networks::PER_NETWORK_VARS.contains(&name) {
+                    format!("{}_{}", name, suffix)
+                } else {
+                    name.to_string()
+                }
+            },
+            vars,
+        )
+        .with_context(|| format!("Network '{}' (variables ending in _{})", network, suffix))
+    }
+
+    /// Read the configuration from `vars`, taking each variable from
+    /// `var(name)`
+    fn load(network: &str, var: impl Fn(&str) -> String, vars: &EnvVars) -> Result<Self> {
+        let required = |name: &str| {
+            let name = var(name);
+            vars.get(&name)
+                .with_context(|| format!("{} environment variable is required", name))
+        };
+        let rpc_url = required("RPC_URL")?;
+        let private_key = required("PRIVATE_KEY")?;
+        let contract_address = required("CONTRACT_ADDRESS")?;
+        let optional = |name: &str| vars.get(&var(name)).filter(|v| !v.is_empty());
+        let legacy_contract_address = optional("LEGACY_CONTRACT_ADDRESS");
+        let nft_contract_address = optional("NFT_CONTRACT_ADDRESS");
+        let payments_contract_address = optional("PAYMENTS_CONTRACT_ADDRESS");
+        let explorer_base_url = optional("EXPLORER_BASE_URL");
+        let write_cutover = vars
+            .get(&var("CONTRACT_WRITE_CUTOVER"))
+            .map(|v| !matches!(v.to_lowercase().as_str(), "false" | "0" | "off"))
+            .unwrap_or(true);
+        let chain_id = vars
+            .get(&var("CHAIN_ID"))
+            .unwrap_or_else(|| "1".to_string())
             .parse::<u64>()
             .context("CHAIN_ID must be a valid u64")?;
+        let validate_on_startup = vars
+            .get("CHAIN_STARTUP_VALIDATION")
+            .map(|v| !matches!(v.to_lowercase().as_str(), "false" | "0" | "off"))
+            .unwrap_or(true);
 
-        Ok(Self {
+        let config = Self {
+            network: network.to_string(),
             rpc_url,
             private_key,
+            role_keys: signers::role_keys_from_env(vars),
             contract_address,
+            legacy_contract_address,
+            write_cutover,
+            nft_contract_address,
+            payments_contract_address,
+            explorer_base_url,
             chain_id,
-        })
+            validate_on_startup,
+            gas: GasStrategy::from_env(vars)?,
+            confirmations: ConfirmationPolicy::from_env(vars)?,
+        };
+        config.validate_format()?;
+
+        Ok(config)
+    }
+
+    /// Check that every value parses before any network call is made
+    pub fn validate_format(&self) -> Result<()> {
+        let mut problems = Vec::new();
+
+        if self.rpc_url.parse::<reqwest::Url>().is_err() {
+            problems.push(format!(
+                "RPC_URL '{}' is not a valid URL (expected e.g. https://rpc-amoy.polygon.technology)",
+                self.rpc_url
+            ));
+        }
+        if let Some(explorer) = &self.explorer_base_url {
+            if explorer.parse::<reqwest::Url>().is_err() {
+                problems.push(format!(
+                    "EXPLORER_BASE_URL '{}' is not a valid URL (expected e.g. https://amoy.polygonscan.com)",
+                    explorer
+                ));
+            }
+        }
+        if let Err(problem) = signers::check_key("PRIVATE_KEY", &self.private_key) {
+            problems.push(problem);
+        }
+        for (bit, key) in &self.role_keys {
+            let name = format!("PRIVATE_KEY_{}", roles::names(*bit).join("|"));
+            if let Err(problem) = signers::check_key(&name, key) {
+                problems.push(problem);
+            }
+        }
+        if self.contract_address.parse::<Address>().is_err() {
+            problems.push(format!(
+                "CONTRACT_ADDRESS '{}' is not a valid address (expected 0x followed by 40 hex characters)",
+                self.contract_address
+            ));
+        }
+        match &self.legacy_contract_address {
+            Some(legacy) if legacy.parse::<Address>().is_err() => problems.push(format!(
+                "LEGACY_CONTRACT_ADDRESS '{}' is not a valid address (expected 0x followed by 40 hex characters)",
+                legacy
+            )),
+            Some(legacy) if legacy.eq_ignore_ascii_case(&self.contract_address) => problems.push(
+                "LEGACY_CONTRACT_ADDRESS must differ from CONTRACT_ADDRESS".to_string(),
+            ),
+            None if !self.write_cutover => problems.push(
+                "CONTRACT_WRITE_CUTOVER=false requires LEGACY_CONTRACT_ADDRESS".to_string(),
+            ),
+            _ => {}
+        }
+        for (var, address) in self.optional_contracts() {
+            let Some(address) = address else { continue };
+            if address.parse::<Address>().is_err() {
+                problems.push(format!(
+                    "{} '{}' is not a valid address (expected 0x followed by 40 hex characters)",
+                    var, address
+                ));
+            } else if address.eq_ignore_ascii_case(&self.contract_address) {
+                problems.push(format!("{} must differ from CONTRACT_ADDRESS", var));
+            }
+        }
+        if self.chain_id == 0 {
+            problems.push("CHAIN_ID must be non-zero".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            anyhow::bail!(
+                "Invalid chain configuration:\n  - {}",
+                problems.join("\n  - ")
+            )
+        }
+    }
+
+    /// Contracts deployed next to the value chain, by environment variable
+    fn optional_contracts(&self) -> [(&'static str, Option<&str>); 2] {
+        [
+            ("NFT_CONTRACT_ADDRESS", self.nft_contract_address.as_deref()),
+            (
+                "PAYMENTS_CONTRACT_ADDRESS",
+                self.payments_contract_address.as_deref(),
+            ),
+        ]
+    }
+
+    /// Parsed address of an optional contract
+    fn optional_address(&self, var: &str) -> Result<Option<Address>> {
+        self.optional_contracts()
+            .into_iter()
+            .find(|(name, _)| *name == var)
+            .and_then(|(_, address)| address)
+            .map(|a| a.parse().with_context(|| format!("Failed to parse {}", var)))
+            .transpose()
     }
 }
 
+/// The RPC endpoint did not answer the startup validation
+#[derive(Debug)]
+pub struct RpcUnreachable(String);
+
+impl std::fmt::Display for RpcUnreachable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for RpcUnreachable {}
+
+/// Environment variable that counts as unset when empty
+fn optional_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+type AppContract = OilseedValueChain::OilseedValueChainInstance<Http<Client>, AppProvider>;
+type NftContract = SkuNFT::SkuNFTInstance<Http<Client>, AppProvider>;
+type PaymentsContract = FarmerPayments::FarmerPaymentsInstance<Http<Client>, AppProvider>;
+
 #[derive(Clone)]
 pub struct ChainClient {
-    contract: OilseedValueChain::OilseedValueChainInstance<Http<Client>, AppProvider>,
+    /// Network name, see [`crate::networks`]
+    network: String,
+    /// Contract that receives writes
+    contract: AppContract,
+    /// Other side of a blue/green migration, consulted by verification reads
+    /// when the write contract has no record
+    secondary: Option<AppContract>,
+    /// ERC-721 contract of packaged SKUs, if configured
+    nft: Option<NftContract>,
+    /// Settlement register of FPO purchases, if configured
+    payments: Option<PaymentsContract>,
+    explorer_base_url: Option<String>,
+    /// Key per on-chain role, swapped by a reload
+    signers: Arc<SignerSet>,
+    chain_id: u64,
+    validate_on_startup: bool,
+    /// Packaged SKUs are immutable on-chain, so positive lookups are cached
+    package_cache: Arc<RwLock<PackageCache>>,
+    /// Single writer for the signer's transactions
+    queue: TxQueue,
+    /// Writes the queue gave up on, retried in the background
+    outbox: Arc<Outbox>,
+    confirmations: ConfirmationPolicy,
+    /// Validated (or validation skipped); writes wait for it, see
+    /// [`crate::readiness`]
+    readiness: Arc<Readiness>,
 }
 
-impl ChainClient {
-    pub async fn new(config: ChainConfig) -> Result<Self> {
-        let signer = config
-            .private_key
-            .parse::<PrivateKeySigner>()
-            .context("Failed to parse private key")?;
+/// Block span of a single eth_getLogs request
+const LOG_WINDOW_BLOCKS: u64 = 5000;
 
-        tracing::info!(
-            address = ?signer.address(),
-            "Initialized signer"
-        );
+/// How far back event lookups search by default
+const DEFAULT_LOG_LOOKBACK_BLOCKS: u64 = 200_000;
 
-        let wallet = EthereumWallet::from(signer);
+/// (parent batch hash, merkle root, packaged at)
+pub type PackageOrigin = (FixedBytes<32>, FixedBytes<32>, u64);
+
+/// Package origins kept in memory at most
+const PACKAGE_CACHE_CAPACITY: usize = 10_000;
+
+/// Age after which a cached package origin is read from the chain again
+const PACKAGE_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Positive package origin lookups, bounded in size and age; the oldest
+/// entry makes room when the cache is full
+#[derive(Default)]
+struct PackageCache {
+    entries: HashMap<FixedBytes<32>, (Instant, PackageOrigin)>,
+}
+
+impl PackageCache {
+    fn get(&self, sku_id: &FixedBytes<32>) -> Option<PackageOrigin> {
+        self.entries
+            .get(sku_id)
+            .filter(|(cached_at, _)| cached_at.elapsed() < PACKAGE_CACHE_TTL)
+            .map(|(_, origin)| *origin)
+    }
+
+    fn insert(&mut self, sku_id: FixedBytes<32>, origin: PackageOrigin) {
+        if self.entries.len() >= PACKAGE_CACHE_CAPACITY && !self.entries.contains_key(&sku_id) {
+            self.entries
+                .retain(|_, (cached_at, _)| cached_at.elapsed() < PACKAGE_CACHE_TTL);
+            if self.entries.len() >= PACKAGE_CACHE_CAPACITY {
+                let oldest = self
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, (cached_at, _))| *cached_at)
+                    .map(|(id, _)| *id);
+                if let Some(oldest) = oldest {
+                    self.entries.remove(&oldest);
+                }
+            }
+        }
+        self.entries.insert(sku_id, (Instant::now(), origin));
+    }
+
+    fn remove(&mut self, sku_id: &FixedBytes<32>) {
+        self.entries.remove(sku_id);
+    }
+}
+
+impl ChainClient {
+    /// Client for `config`; with `previous`, a reloaded client of the same
+    /// network that keeps its transaction queue entries and outbox
+    async fn new(config: ChainConfig, previous: Option<&ChainClient>) -> Result<Self> {
+        let signers = Arc::new(SignerSet::load(&config.private_key, &config.role_keys)?);
+        for signer in signers.signers() {
+            tracing::info!(
+                address = ?signer.address,
+                roles = ?signer.roles,
+                "Initialized signer"
+            );
+        }
+
+        // Queued writes are signed by the queue with the key of their role,
+        // so the provider's own wallet never signs
+        let wallet = EthereumWallet::from(PrivateKeySigner::random());
 
         let provider = ProviderBuilder::new()
             .with_recommended_fillers()
@@ -256,20 +681,470 @@ impl ChainClient {
             .context("Failed to parse contract address")?;
 
         tracing::info!(
+            network = %config.network,
             contract_address = ?contract_address,
             rpc_url = %config.rpc_url,
             chain_id = config.chain_id,
             "Initialized ChainClient"
         );
 
-        let contract = OilseedValueChain::new(contract_address, provider);
+        let legacy_address: Option<Address> = config
+            .legacy_contract_address
+            .as_deref()
+            .map(|a| a.parse().context("Failed to parse legacy contract address"))
+            .transpose()?;
+
+        tracing::info!(gas = ?config.gas, "Transaction gas strategy");
+        tracing::info!(confirmations = ?config.confirmations, "Confirmation depth");
+        let (queue, outbox) = match previous {
+            Some(previous) => (
+                previous.queue.restart(
+                    provider.clone(),
+                    signers.clone(),
+                    config.chain_id,
+                    config.gas.clone(),
+                ),
+                previous.outbox.clone(),
+            ),
+            None => {
+                let queue = TxQueue::start(
+                    provider.clone(),
+                    signers.clone(),
+                    config.chain_id,
+                    config.gas.clone(),
+                    networks::data_path(&config.network, tx_queue::QUEUE_PATH),
+                )?;
+                let outbox_path = networks::data_path(&config.network, outbox::DB_PATH);
+                (queue, Arc::new(Outbox::open(&outbox_path).await?))
+            }
+        };
+        let nft = config
+            .optional_address("NFT_CONTRACT_ADDRESS")?
+            .map(|address| SkuNFT::new(address, provider.clone()));
+        let payments = config
+            .optional_address("PAYMENTS_CONTRACT_ADDRESS")?
+            .map(|address| FarmerPayments::new(address, provider.clone()));
+        let current = OilseedValueChain::new(contract_address, provider.clone());
+        let legacy = legacy_address.map(|a| OilseedValueChain::new(a, provider));
+        let (contract, secondary) = match legacy {
+            Some(legacy) if !config.write_cutover => (legacy, Some(current)),
+            legacy => (current, legacy),
+        };
+
+        if let Some(secondary) = &secondary {
+            tracing::info!(
+                write_contract = ?contract.address(),
+                read_fallback = ?secondary.address(),
+                "Blue/green contract migration: dual-read enabled"
+            );
+        }
+
+        Ok(Self {
+            network: config.network,
+            contract,
+            secondary,
+            nft,
+            payments,
+            explorer_base_url: config.explorer_base_url,
+            signers,
+            chain_id: config.chain_id,
+            validate_on_startup: config.validate_on_startup,
+            package_cache: Arc::new(RwLock::new(PackageCache::default())),
+            queue,
+            outbox,
+            confirmations: config.confirmations,
+            readiness: Arc::new(Readiness::default()),
+        })
+    }
 
-        Ok(Self { contract })
+    /// Client for the server start: like [`Self::connect`], but an RPC that
+    /// does not answer leaves the client not ready for [`crate::readiness`]
+    /// to retry instead of failing the start
+    pub async fn start(config: ChainConfig) -> Result<Self> {
+        let client = Self::new(config, None).await?;
+        if !client.validate_on_startup {
+            tracing::warn!("CHAIN_STARTUP_VALIDATION disabled, skipping on-chain checks");
+            client.readiness.mark_ready();
+            return Ok(client);
+        }
+        match client.validate().await {
+            Ok(()) => client.readiness.mark_ready(),
+            Err(e) if e.is::<RpcUnreachable>() => {
+                client.readiness.record_failure(&e);
+                tracing::warn!(
+                    network = %client.network,
+                    error = %e,
+                    "RPC not reachable, starting without it; contract writes go to the outbox until it is"
+                );
+            }
+            Err(e) => return Err(e),
+        }
+        Ok(client)
     }
 
     pub async fn from_env() -> Result<Self> {
-        let config = ChainConfig::from_env()?;
-        Self::new(config).await
+        Self::connect(ChainConfig::from_env(&EnvVars::process())?, None).await
+    }
+
+    /// Replacement for this client built from `config`, validated like at
+    /// startup. Writes already submitted finish on this client.
+    pub async fn reconnect(&self, config: ChainConfig) -> Result<Self> {
+        Self::connect(config, Some(self)).await
+    }
+
+    /// Create the client and run the startup validation, if enabled; writes
+    /// already submitted on `previous` finish there
+    pub async fn connect(config: ChainConfig, previous: Option<&ChainClient>) -> Result<Self> {
+        let client = Self::new(config, previous).await?;
+
+        if client.validate_on_startup {
+            client.validate().await?;
+        } else {
+            tracing::warn!("CHAIN_STARTUP_VALIDATION disabled, skipping on-chain checks");
+        }
+        client.readiness.mark_ready();
+
+        Ok(client)
+    }
+
+    /// Verify that the RPC endpoint, contract and signer match the configuration.
+    ///
+    /// All problems are collected and reported together so a misconfigured
+    /// deployment can be fixed in one pass instead of failing on the first request.
+    pub async fn validate(&self) -> Result<()> {
+        let provider = self.contract.provider();
+        let contract_address = *self.contract.address();
+        let mut problems = Vec::new();
+
+        match provider.get_chain_id().await {
+            Ok(remote_chain_id) if remote_chain_id != self.chain_id => problems.push(format!(
+                "CHAIN_ID is {} but RPC_URL reports chain {}; point RPC_URL at the right network or set CHAIN_ID={}",
+                self.chain_id, remote_chain_id, remote_chain_id
+            )),
+            Ok(_) => {}
+            Err(e) => {
+                return Err(RpcUnreachable(format!(
+                    "Startup validation failed: could not read eth_chainId from RPC_URL ({}). \
+                     Check that the node is reachable, or set CHAIN_STARTUP_VALIDATION=false to skip",
+                    e
+                ))
+                .into());
+            }
+        }
+
+        match provider.get_code_at(contract_address).await {
+            Ok(code) if code.is_empty() => problems.push(format!(
+                "No contract code at CONTRACT_ADDRESS {:?} on chain {}; redeploy nyx.sol or update CONTRACT_ADDRESS",
+                contract_address, self.chain_id
+            )),
+            Ok(_) => {}
+            Err(e) => problems.push(format!(
+                "Could not fetch code for CONTRACT_ADDRESS {:?}: {}",
+                contract_address, e
+            )),
+        }
+
+        if let Some(secondary) = &self.secondary {
+            match provider.get_code_at(*secondary.address()).await {
+                Ok(code) if code.is_empty() => problems.push(format!(
+                    "No contract code at {:?} (read fallback of the blue/green migration) on chain {}",
+                    secondary.address(),
+                    self.chain_id
+                )),
+                Ok(_) => {}
+                Err(e) => problems.push(format!(
+                    "Could not fetch code for {:?}: {}",
+                    secondary.address(),
+                    e
+                )),
+            }
+        }
+
+        for (var, address) in self.optional_contracts() {
+            match provider.get_code_at(address).await {
+                Ok(code) if code.is_empty() => problems.push(format!(
+                    "No contract code at {} {:?} on chain {}; deploy it or unset {}",
+                    var, address, self.chain_id, var
+                )),
+                Ok(_) => {}
+                Err(e) => problems.push(format!(
+                    "Could not fetch code for {} {:?}: {}",
+                    var, address, e
+                )),
+            }
+        }
+
+        problems.extend(self.signers.remote_problems().await);
+
+        if problems.is_empty() {
+            // Each operational role has to be held by the key that signs for it
+            let mut required: Vec<(Address, Vec<(&str, u64)>)> = Vec::new();
+            for (name, bit) in roles::OPERATIONAL {
+                let address = self.signers.for_role(*bit).address();
+                match required.iter_mut().find(|(a, _)| *a == address) {
+                    Some((_, needed)) => needed.push((name, *bit)),
+                    None => required.push((address, vec![(name, *bit)])),
+                }
+            }
+            for (address, needed) in required {
+                match self.contract.getRoles(address).call().await {
+                    Ok(result) => {
+                        let granted = result._0;
+                        let missing: Vec<&str> = needed
+                            .iter()
+                            .filter(|(_, bit)| granted & U256::from(*bit) == U256::ZERO)
+                            .map(|(name, _)| *name)
+                            .collect();
+                        if !missing.is_empty() {
+                            problems.push(format!(
+                                "Signer {:?} is missing role(s) {}; an admin must call grantRole for them",
+                                address,
+                                missing.join(", ")
+                            ));
+                        }
+                    }
+                    Err(e) => problems.push(format!(
+                        "Could not read roles for signer {:?}: {}",
+                        address, e
+                    )),
+                }
+            }
+        }
+
+        if !problems.is_empty() {
+            anyhow::bail!(
+                "Startup validation failed:\n  - {}",
+                problems.join("\n  - ")
+            );
+        }
+
+        tracing::info!(
+            chain_id = self.chain_id,
+            contract_address = ?contract_address,
+            signers = self.signers.signers().len(),
+            "Chain configuration validated"
+        );
+
+        Ok(())
+    }
+
+    pub fn network(&self) -> &str {
+        &self.network
+    }
+
+    pub fn readiness(&self) -> Arc<Readiness> {
+        self.readiness.clone()
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    pub fn explorer_base_url(&self) -> Option<&str> {
+        self.explorer_base_url.as_deref()
+    }
+
+    /// Explorer page of a transaction, when EXPLORER_BASE_URL is set
+    pub fn explorer_url(&self, tx_hash: impl std::fmt::Debug) -> Option<String> {
+        self.explorer_base_url
+            .as_deref()
+            .map(|base| explorer_tx_url(base, &format_tx_hash(tx_hash)))
+    }
+
+    pub fn tx_queue(&self) -> &TxQueue {
+        &self.queue
+    }
+
+    pub fn outbox(&self) -> &Outbox {
+        &self.outbox
+    }
+
+    pub fn confirmation_policy(&self) -> &ConfirmationPolicy {
+        &self.confirmations
+    }
+
+    pub async fn transaction_receipt(
+        &self,
+        tx_hash: FixedBytes<32>,
+    ) -> Result<Option<TransactionReceipt>> {
+        self.contract
+            .provider()
+            .get_transaction_receipt(tx_hash)
+            .await
+            .with_context(|| format!("Failed to read receipt of {:?}", tx_hash))
+    }
+
+    /// Wait until the transaction of `receipt` has the configured number of
+    /// confirmations, following it if a reorg moves it to another block
+    async fn wait_for_confirmations(
+        &self,
+        label: &str,
+        mut receipt: TransactionReceipt,
+    ) -> Result<TransactionReceipt> {
+        let required = self.confirmations.confirmations;
+        let tx_hash = receipt.transaction_hash;
+        let deadline = tokio::time::Instant::now() + self.confirmations.timeout;
+
+        loop {
+            let confirmed = match self.transaction_receipt(tx_hash).await? {
+                Some(current) => {
+                    if current.block_hash != receipt.block_hash {
+                        tracing::warn!(
+                            label,
+                            ?tx_hash,
+                            from = ?receipt.block_number,
+                            to = ?current.block_number,
+                            "Transaction moved by a reorg"
+                        );
+                    }
+                    receipt = current;
+                    let latest = self.latest_block().await?;
+                    let confirmed = receipt
+                        .block_number
+                        .map(|block| confirmations::confirmations_at(block, latest))
+                        .unwrap_or(0);
+                    if confirmed >= required {
+                        return Ok(receipt);
+                    }
+                    confirmed
+                }
+                None => {
+                    tracing::warn!(label, ?tx_hash, "Receipt disappeared, waiting for re-inclusion");
+                    0
+                }
+            };
+
+            if tokio::time::Instant::now() >= deadline {
+                anyhow::bail!(
+                    "{} transaction {:?} has {} of {} confirmations after {}s; poll GET /api/chain/tx/{:?}",
+                    label,
+                    tx_hash,
+                    confirmed,
+                    required,
+                    self.confirmations.timeout.as_secs(),
+                    tx_hash
+                );
+            }
+            tokio::time::sleep(confirmations::POLL_INTERVAL).await;
+        }
+    }
+
+    /// Send a transaction through the queue and wait until it is mined; a
+    /// transaction that fails is saved to the outbox for retrying. Mined
+    /// transactions count against the requesting tenant's quota.
+    async fn submit(&self, label: &str, tx: TransactionRequest) -> Result<TransactionReceipt> {
+        // Not reachable since the start: keep the write for the outbox worker
+        if let Err(unavailable) = self.readiness.check(&format!("The {} chain RPC", self.network)) {
+            return Err(self.queue_for_retry(label, &tx, unavailable.into()).await);
+        }
+        let tenant = metering::admit_current(Metric::Transactions, 1)?;
+        let sent =
+            slowlog::observe(SlowOperation::ReceiptWait, label, self.queue.submit(label, tx.clone()))
+                .await;
+        if let Ok(receipt) = &sent {
+            self.readiness.record_up();
+            let gas_used = u64::try_from(receipt.gas_used).unwrap_or(u64::MAX);
+            metering::global().record_transaction(&tenant, gas_used);
+        }
+        let e = match sent {
+            Ok(receipt) if self.confirmations.waits() => {
+                return self.wait_for_confirmations(label, receipt).await;
+            }
+            Ok(receipt) => return Ok(receipt),
+            Err(e) => e,
+        };
+        // Sending it again would revert the same way
+        if ContractRevert::from_error(&e).is_some() {
+            return Err(e.context(format!("{} transaction reverted", label)));
+        }
+        self.readiness.record_down(&e);
+        Err(self.queue_for_retry(label, &tx, e).await)
+    }
+
+    /// Save a write that could not be sent to the outbox; the error to
+    /// answer with is [`outbox::Queued`] once it is saved
+    async fn queue_for_retry(&self, label: &str, tx: &TransactionRequest, e: anyhow::Error) -> anyhow::Error {
+        match self.outbox.record(label, tx, &format!("{:#}", e)).await {
+            Ok(id) => {
+                tracing::warn!(id, label, "Failed transaction saved to the outbox");
+                degradation::note(degradation::WRITE_QUEUED);
+                outbox::Queued {
+                    label: label.to_string(),
+                    id,
+                    reason: format!("{:#}", e),
+                }
+                .into()
+            }
+            Err(record_error) => {
+                tracing::error!(label, error = %format!("{:#}", record_error), "Failed to save transaction to the outbox");
+                e.context(format!("{} transaction failed", label))
+            }
+        }
+    }
+
+    pub async fn grant_role(&self, account: Address, role: u64) -> Result<TransactionReceipt> {
+        tracing::info!(?account, roles = ?roles::names(role), "Granting role");
+
+        let tx = self
+            .contract
+            .grantRole(account, U256::from(role))
+            .into_transaction_request();
+
+        let receipt = self.submit("grantRole", tx).await?;
+
+        tracing::info!(
+            tx_hash = ?receipt.transaction_hash,
+            "Role granted successfully"
+        );
+
+        Ok(receipt)
+    }
+
+    pub async fn revoke_role(&self, account: Address, role: u64) -> Result<TransactionReceipt> {
+        tracing::info!(?account, roles = ?roles::names(role), "Revoking role");
+
+        let tx = self
+            .contract
+            .revokeRole(account, U256::from(role))
+            .into_transaction_request();
+
+        let receipt = self.submit("revokeRole", tx).await?;
+
+        tracing::info!(
+            tx_hash = ?receipt.transaction_hash,
+            "Role revoked successfully"
+        );
+
+        Ok(receipt)
+    }
+
+    /// Whether `account` holds any of the role bits in `role`
+    pub async fn has_role(&self, account: Address, role: u64) -> Result<bool> {
+        let result = self
+            .contract
+            .hasRole(account, U256::from(role))
+            .call()
+            .await
+            .context("Failed to call hasRole")?;
+
+        Ok(result._0)
+    }
+
+    /// Contract that writes go to and role checks are made against
+    pub fn write_contract_address(&self) -> Address {
+        *self.contract.address()
+    }
+
+    /// Role bitmask of `account`
+    pub async fn get_roles(&self, account: Address) -> Result<U256> {
+        let result = self
+            .contract
+            .getRoles(account)
+            .call()
+            .await
+            .context("Failed to call getRoles")?;
+
+        Ok(result._0)
     }
 
     pub async fn register_farmer(
@@ -283,14 +1158,9 @@ impl ChainClient {
         let tx = self
             .contract
             .registerFarmer(farmer_did, crop_id_hash, metadata_cid)
-            .send()
-            .await
-            .context("Failed to send registerFarmer transaction")?;
+            .into_transaction_request();
 
-        let receipt = tx
-            .get_receipt()
-            .await
-            .context("Failed to get transaction receipt")?;
+        let receipt = self.submit("registerFarmer", tx).await?;
 
         tracing::info!(
             tx_hash = ?receipt.transaction_hash,
@@ -300,18 +1170,77 @@ impl ChainClient {
         Ok(receipt)
     }
 
+    pub async fn update_farmer_metadata(
+        &self,
+        farmer_did: FixedBytes<32>,
+        crop_id_hash: FixedBytes<32>,
+        metadata_cid: String,
+    ) -> Result<TransactionReceipt> {
+        tracing::info!(?farmer_did, cid = %metadata_cid, "Updating farmer metadata");
+
+        let tx = self
+            .contract
+            .updateFarmerMetadata(farmer_did, crop_id_hash, metadata_cid)
+            .into_transaction_request();
+
+        let receipt = self.submit("updateFarmerMetadata", tx).await?;
+
+        tracing::info!(
+            tx_hash = ?receipt.transaction_hash,
+            "Farmer metadata updated successfully"
+        );
+
+        Ok(receipt)
+    }
+
+    pub async fn batch_register_farmers(
+        &self,
+        farmer_dids: Vec<FixedBytes<32>>,
+        crop_id_hashes: Vec<FixedBytes<32>>,
+    ) -> Result<TransactionReceipt> {
+        tracing::info!(count = farmer_dids.len(), "Batch registering farmers");
+
+        let tx = self
+            .contract
+            .batchRegisterFarmers(farmer_dids, crop_id_hashes)
+            .into_transaction_request();
+
+        let receipt = self.submit("batchRegisterFarmers", tx).await?;
+
+        tracing::info!(
+            tx_hash = ?receipt.transaction_hash,
+            "Farmers batch registered successfully"
+        );
+
+        Ok(receipt)
+    }
+
+    /// Write contract first, then the blue/green read fallback (if any)
+    fn read_contracts(&self) -> impl Iterator<Item = &AppContract> {
+        std::iter::once(&self.contract).chain(self.secondary.as_ref())
+    }
+
     pub async fn verify_farmer(
         &self,
         farmer_did: FixedBytes<32>,
     ) -> Result<(bool, FixedBytes<32>, u64)> {
-        let result = self
-            .contract
-            .verifyFarmer(farmer_did)
-            .call()
-            .await
-            .context("Failed to call verifyFarmer")?;
+        let mut farmer = (false, FixedBytes::ZERO, 0);
+        for contract in self.read_contracts() {
+            let result = contract
+                .verifyFarmer(farmer_did)
+                .call()
+                .await
+                .with_context(|| {
+                    format!("Failed to call verifyFarmer on {:?}", contract.address())
+                })?;
+
+            farmer = (result.exists, result.cropIDHash, result.registeredAt);
+            if farmer.0 {
+                break;
+            }
+        }
 
-        Ok((result.exists, result.cropIDHash, result.registeredAt))
+        Ok(farmer)
     }
 
     pub async fn fpo_purchase(
@@ -325,14 +1254,9 @@ impl ChainClient {
         let tx = self
             .contract
             .fpoPurchase(batch_hash, farmer_did, metadata_cid)
-            .send()
-            .await
-            .context("Failed to send fpoPurchase transaction")?;
+            .into_transaction_request();
 
-        let receipt = tx
-            .get_receipt()
-            .await
-            .context("Failed to get transaction receipt")?;
+        let receipt = self.submit("fpoPurchase", tx).await?;
 
         tracing::info!(
             tx_hash = ?receipt.transaction_hash,
@@ -342,6 +1266,34 @@ impl ChainClient {
         Ok(receipt)
     }
 
+    /// Record a custody change after the FPO purchase; `transfer_type` is
+    /// one of the contract's TRANSFER_* codes (2 = warehouse, 3 = processor,
+    /// 4 = retail)
+    pub async fn record_ownership_transfer(
+        &self,
+        batch_hash: FixedBytes<32>,
+        from_did: FixedBytes<32>,
+        to_address: Address,
+        transfer_type: u8,
+        metadata_cid: String,
+    ) -> Result<TransactionReceipt> {
+        tracing::info!(?batch_hash, ?from_did, ?to_address, transfer_type, cid = %metadata_cid, "Recording ownership transfer");
+
+        let tx = self
+            .contract
+            .recordOwnershipTransfer(batch_hash, from_did, to_address, transfer_type, metadata_cid)
+            .into_transaction_request();
+
+        let receipt = self.submit("recordOwnershipTransfer", tx).await?;
+
+        tracing::info!(
+            tx_hash = ?receipt.transaction_hash,
+            "Ownership transfer recorded successfully"
+        );
+
+        Ok(receipt)
+    }
+
     pub async fn update_warehouse_state(
         &self,
         warehouse_id: FixedBytes<32>,
@@ -353,14 +1305,9 @@ impl ChainClient {
         let tx = self
             .contract
             .updateWarehouseState(warehouse_id, state_hash, metadata_cid)
-            .send()
-            .await
-            .context("Failed to send updateWarehouseState transaction")?;
+            .into_transaction_request();
 
-        let receipt = tx
-            .get_receipt()
-            .await
-            .context("Failed to get transaction receipt")?;
+        let receipt = self.submit("updateWarehouseState", tx).await?;
 
         tracing::info!(
             tx_hash = ?receipt.transaction_hash,
@@ -370,6 +1317,69 @@ impl ChainClient {
         Ok(receipt)
     }
 
+    pub async fn get_warehouse_state(
+        &self,
+        warehouse_id: FixedBytes<32>,
+    ) -> Result<(FixedBytes<32>, u64)> {
+        let result = self
+            .contract
+            .getWarehouseState(warehouse_id)
+            .call()
+            .await
+            .context("Failed to call getWarehouseState")?;
+
+        Ok((result.stateHash, result.lastUpdated))
+    }
+
+    /// Metadata CID of the warehouse update recorded at `updated_at`.
+    ///
+    /// The CID is only emitted in `WarehouseStateUpdated`, so recent blocks are
+    /// searched backwards (up to WAREHOUSE_LOG_LOOKBACK_BLOCKS) in windows small
+    /// enough for public RPC log limits.
+    pub async fn warehouse_metadata_cid(
+        &self,
+        warehouse_id: FixedBytes<32>,
+        updated_at: u64,
+    ) -> Result<Option<String>> {
+        let lookback = env::var("WAREHOUSE_LOG_LOOKBACK_BLOCKS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_LOG_LOOKBACK_BLOCKS);
+
+        let latest = self.latest_block().await?;
+        let floor = latest.saturating_sub(lookback);
+        let mut to_block = latest;
+
+        loop {
+            let from_block = to_block
+                .saturating_sub(LOG_WINDOW_BLOCKS - 1)
+                .max(floor);
+
+            let events = self
+                .contract
+                .WarehouseStateUpdated_filter()
+                .topic1(warehouse_id)
+                .from_block(from_block)
+                .to_block(to_block)
+                .query()
+                .await
+                .context("Failed to query WarehouseStateUpdated events")?;
+
+            if let Some((event, _)) = events
+                .into_iter()
+                .rev()
+                .find(|(event, _)| event.timestamp == updated_at)
+            {
+                return Ok(Some(event.metadataCID));
+            }
+
+            if from_block == floor {
+                return Ok(None);
+            }
+            to_block = from_block - 1;
+        }
+    }
+
     pub async fn batch_update_warehouse(
         &self,
         warehouse_ids: Vec<FixedBytes<32>>,
@@ -383,14 +1393,9 @@ impl ChainClient {
         let tx = self
             .contract
             .batchUpdateWarehouse(warehouse_ids, state_hashes)
-            .send()
-            .await
-            .context("Failed to send batchUpdateWarehouse transaction")?;
+            .into_transaction_request();
 
-        let receipt = tx
-            .get_receipt()
-            .await
-            .context("Failed to get transaction receipt")?;
+        let receipt = self.submit("batchUpdateWarehouse", tx).await?;
 
         tracing::info!(
             tx_hash = ?receipt.transaction_hash,
@@ -418,14 +1423,9 @@ impl ChainClient {
         let tx = self
             .contract
             .recordLogistics(shipment_id, location_hash, is_delivered, metadata_cid)
-            .send()
-            .await
-            .context("Failed to send recordLogistics transaction")?;
+            .into_transaction_request();
 
-        let receipt = tx
-            .get_receipt()
-            .await
-            .context("Failed to get transaction receipt")?;
+        let receipt = self.submit("recordLogistics", tx).await?;
 
         tracing::info!(
             tx_hash = ?receipt.transaction_hash,
@@ -435,6 +1435,42 @@ impl ChainClient {
         Ok(receipt)
     }
 
+    pub async fn batch_record_logistics(
+        &self,
+        shipment_ids: Vec<FixedBytes<32>>,
+        location_hashes: Vec<FixedBytes<32>>,
+        delivery_statuses: Vec<bool>,
+    ) -> Result<TransactionReceipt> {
+        // Mirror the contract's LengthMismatch check to avoid paying for a revert
+        anyhow::ensure!(
+            shipment_ids.len() == location_hashes.len()
+                && shipment_ids.len() == delivery_statuses.len(),
+            "Length mismatch: {} shipment IDs, {} location hashes, {} delivery statuses",
+            shipment_ids.len(),
+            location_hashes.len(),
+            delivery_statuses.len()
+        );
+
+        tracing::info!(
+            count = shipment_ids.len(),
+            "Batch recording logistics milestones"
+        );
+
+        let tx = self
+            .contract
+            .batchRecordLogistics(shipment_ids, location_hashes, delivery_statuses)
+            .into_transaction_request();
+
+        let receipt = self.submit("batchRecordLogistics", tx).await?;
+
+        tracing::info!(
+            tx_hash = ?receipt.transaction_hash,
+            "Logistics milestones batch recorded successfully"
+        );
+
+        Ok(receipt)
+    }
+
     pub async fn process_batch(
         &self,
         input_batch_hash: FixedBytes<32>,
@@ -458,14 +1494,9 @@ impl ChainClient {
                 output_batch_hashes,
                 metadata_cid,
             )
-            .send()
-            .await
-            .context("Failed to send processBatch transaction")?;
+            .into_transaction_request();
 
-        let receipt = tx
-            .get_receipt()
-            .await
-            .context("Failed to get transaction receipt")?;
+        let receipt = self.submit("processBatch", tx).await?;
 
         tracing::info!(
             tx_hash = ?receipt.transaction_hash,
@@ -487,14 +1518,11 @@ impl ChainClient {
         let tx = self
             .contract
             .createSKU(sku_id, parent_batch_hash, merkle_root, metadata_cid)
-            .send()
-            .await
-            .context("Failed to send createSKU transaction")?;
+            .into_transaction_request();
 
-        let receipt = tx
-            .get_receipt()
-            .await
-            .context("Failed to get transaction receipt")?;
+        let receipt = self.submit("createSKU", tx).await?;
+        // The next lookup reads the package this write recorded
+        self.package_cache.write().await.remove(&sku_id);
 
         tracing::info!(
             tx_hash = ?receipt.transaction_hash,
@@ -504,18 +1532,104 @@ impl ChainClient {
         Ok(receipt)
     }
 
-    pub async fn verify_package_origin(
-        &self,
-        sku_id: FixedBytes<32>,
-    ) -> Result<(FixedBytes<32>, FixedBytes<32>, u64)> {
+    fn nft(&self) -> Result<&NftContract> {
+        self.nft
+            .as_ref()
+            .context("NFT_CONTRACT_ADDRESS is not set, SKU NFTs are disabled")
+    }
+
+    pub fn nft_address(&self) -> Option<Address> {
+        self.nft.as_ref().map(|nft| *nft.address())
+    }
+
+    /// Configured contracts besides the value chain, by environment variable
+    fn optional_contracts(&self) -> Vec<(&'static str, Address)> {
+        let nft = self.nft.as_ref().map(|c| ("NFT_CONTRACT_ADDRESS", *c.address()));
+        let payments = self
+            .payments
+            .as_ref()
+            .map(|c| ("PAYMENTS_CONTRACT_ADDRESS", *c.address()));
+        nft.into_iter().chain(payments).collect()
+    }
+
+    /// Token minted for a SKU, if any
+    pub async fn sku_token(&self, sku_id: FixedBytes<32>) -> Result<Option<U256>> {
         let result = self
-            .contract
-            .verifyPackageOrigin(sku_id)
+            .nft()?
+            .tokenOfSku(sku_id)
             .call()
             .await
-            .context("Failed to call verifyPackageOrigin")?;
+            .context("Failed to call tokenOfSku")?;
 
-        Ok((result.parentBatchHash, result.merkleRoot, result.packagedAt))
+        let token_id = result._0;
+        Ok((!token_id.is_zero()).then_some(token_id))
+    }
+
+    /// Mint the ERC-721 token of a SKU to `recipient`, or to the default
+    /// signer when `None`; returns the token ID with the receipt
+    pub async fn mint_sku_nft(
+        &self,
+        sku_id: FixedBytes<32>,
+        recipient: Option<Address>,
+        token_uri: String,
+    ) -> Result<(U256, TransactionReceipt)> {
+        let recipient = recipient.unwrap_or_else(|| self.signers.default_signer().address());
+        tracing::info!(?sku_id, ?recipient, uri = %token_uri, "Minting SKU NFT");
+
+        let tx = self
+            .nft()?
+            .mint(recipient, sku_id, token_uri)
+            .into_transaction_request();
+
+        let receipt = self.submit("mintSkuNFT", tx).await?;
+
+        let token_id = receipt
+            .inner
+            .logs()
+            .iter()
+            .find_map(|log| {
+                SkuNFT::SkuMinted::decode_log(&log.inner, true)
+                    .ok()
+                    .map(|event| event.data.tokenId)
+            })
+            .context("Mint receipt has no SkuMinted event")?;
+
+        tracing::info!(
+            tx_hash = ?receipt.transaction_hash,
+            %token_id,
+            "SKU NFT minted successfully"
+        );
+
+        Ok((token_id, receipt))
+    }
+
+    pub async fn verify_package_origin(&self, sku_id: FixedBytes<32>) -> Result<PackageOrigin> {
+        if let Some(origin) = self.package_cache.read().await.get(&sku_id) {
+            return Ok(origin);
+        }
+
+        // SKUs packaged before a migration stay on the legacy contract
+        let mut origin = (FixedBytes::ZERO, FixedBytes::ZERO, 0);
+        for contract in self.read_contracts() {
+            let result = contract
+                .verifyPackageOrigin(sku_id)
+                .call()
+                .await
+                .with_context(|| {
+                    format!("Failed to call verifyPackageOrigin on {:?}", contract.address())
+                })?;
+
+            origin = (result.parentBatchHash, result.merkleRoot, result.packagedAt);
+            if origin.2 > 0 {
+                break;
+            }
+        }
+
+        if origin.2 > 0 {
+            self.package_cache.write().await.insert(sku_id, origin);
+        }
+
+        Ok(origin)
     }
 
     pub async fn report_fraud(
@@ -529,14 +1643,9 @@ impl ChainClient {
         let tx = self
             .contract
             .reportFraud(sku_id, evidence_hash, evidence_cid)
-            .send()
-            .await
-            .context("Failed to send reportFraud transaction")?;
+            .into_transaction_request();
 
-        let receipt = tx
-            .get_receipt()
-            .await
-            .context("Failed to get transaction receipt")?;
+        let receipt = self.submit("reportFraud", tx).await?;
 
         tracing::info!(
             tx_hash = ?receipt.transaction_hash,
@@ -556,14 +1665,9 @@ impl ChainClient {
         let tx = self
             .contract
             .commitAIScore(batch_hash, commit_hash)
-            .send()
-            .await
-            .context("Failed to send commitAIScore transaction")?;
+            .into_transaction_request();
 
-        let receipt = tx
-            .get_receipt()
-            .await
-            .context("Failed to get transaction receipt")?;
+        let receipt = self.submit("commitAIScore", tx).await?;
 
         tracing::info!(
             tx_hash = ?receipt.transaction_hash,
@@ -585,18 +1689,259 @@ impl ChainClient {
         let tx = self
             .contract
             .revealAIScore(batch_hash, reveal_hash, nonce, metadata_cid)
-            .send()
+            .into_transaction_request();
+
+        let receipt = self.submit("revealAIScore", tx).await?;
+
+        tracing::info!(
+            tx_hash = ?receipt.transaction_hash,
+            "AI score revealed successfully"
+        );
+
+        Ok(receipt)
+    }
+
+    /// (commit hash, reveal hash, committed at, revealed at); zero timestamps when absent
+    pub async fn get_ai_score(
+        &self,
+        batch_hash: FixedBytes<32>,
+    ) -> Result<(FixedBytes<32>, FixedBytes<32>, u64, u64)> {
+        let mut score = (FixedBytes::ZERO, FixedBytes::ZERO, 0, 0);
+        for contract in self.read_contracts() {
+            let result = contract
+                .getAIScore(batch_hash)
+                .call()
+                .await
+                .with_context(|| format!("Failed to call getAIScore on {:?}", contract.address()))?;
+
+            score = (
+                result.commitHash,
+                result.revealHash,
+                result.committedAt,
+                result.revealedAt,
+            );
+            if score.2 > 0 {
+                break;
+            }
+        }
+
+        Ok(score)
+    }
+
+    pub async fn latest_block(&self) -> Result<u64> {
+        self.contract
+            .provider()
+            .get_block_number()
+            .await
+            .context("Failed to read latest block number")
+    }
+
+    pub fn signers(&self) -> &SignerSet {
+        &self.signers
+    }
+
+    /// Native token balance of a backend signer, in wei
+    pub async fn signer_balance(&self, address: Address) -> Result<U256> {
+        self.contract
+            .provider()
+            .get_balance(address)
+            .await
+            .with_context(|| format!("Failed to read balance of signer {:?}", address))
+    }
+
+    /// Publish `calldata` on the primary chain in a zero-value transaction
+    /// from the backend signer to itself
+    pub async fn publish_data(&self, calldata: Vec<u8>) -> Result<TransactionReceipt> {
+        let tx = TransactionRequest::default()
+            .with_to(self.signers.default_signer().address())
+            .with_chain_id(self.chain_id)
+            .with_input(Bytes::from(calldata));
+
+        self.submit("publishData", tx).await
+    }
+
+    /// Contract events emitted in `from_block..=to_block`, in chain order
+    pub async fn contract_events(&self, from_block: u64, to_block: u64) -> Result<Vec<EventRef>> {
+        let filter = Filter::new()
+            .address(*self.contract.address())
+            .from_block(from_block)
+            .to_block(to_block);
+
+        let logs = self
+            .contract
+            .provider()
+            .get_logs(&filter)
+            .await
+            .with_context(|| format!("Failed to fetch logs for blocks {}..={}", from_block, to_block))?;
+
+        let mut events: Vec<EventRef> = logs
+            .into_iter()
+            .filter_map(|log| {
+                Some(EventRef {
+                    block_number: log.block_number?,
+                    tx_hash: log.transaction_hash?,
+                    log_index: log.log_index?,
+                })
+            })
+            .collect();
+        events.sort_by_key(|e| (e.block_number, e.log_index));
+
+        Ok(events)
+    }
+
+    /// Decoded events of the write contract and, during a blue/green
+    /// migration, the legacy contract, emitted in `from_block..=to_block`.
+    /// Logs that match no event in the ABI are skipped.
+    pub async fn decoded_events(
+        &self,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<DecodedEvent>> {
+        let addresses: Vec<Address> = self.read_contracts().map(|c| *c.address()).collect();
+        let filter = Filter::new()
+            .address(addresses)
+            .from_block(from_block)
+            .to_block(to_block);
+
+        let logs = self
+            .contract
+            .provider()
+            .get_logs(&filter)
+            .await
+            .with_context(|| format!("Failed to fetch logs for blocks {}..={}", from_block, to_block))?;
+
+        let mut events: Vec<DecodedEvent> = logs
+            .into_iter()
+            .filter_map(|log| {
+                let event =
+                    OilseedValueChain::OilseedValueChainEvents::decode_log(&log.inner, true)
+                        .ok()?
+                        .data;
+                Some(DecodedEvent {
+                    position: EventRef {
+                        block_number: log.block_number?,
+                        tx_hash: log.transaction_hash?,
+                        log_index: log.log_index?,
+                    },
+                    contract: log.address(),
+                    event,
+                })
+            })
+            .collect();
+        events.sort_by_key(|e| (e.position.block_number, e.position.log_index));
+
+        Ok(events)
+    }
+}
+
+/// Contract event decoded against the ABI
+pub struct DecodedEvent {
+    pub position: EventRef,
+    pub contract: Address,
+    pub event: OilseedValueChain::OilseedValueChainEvents,
+}
+
+/// Position of a contract event on the primary chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct EventRef {
+    pub block_number: u64,
+    pub tx_hash: FixedBytes<32>,
+    pub log_index: u64,
+}
+
+impl EventRef {
+    /// Leaf hash used when anchoring: keccak256(tx_hash || log_index)
+    pub fn leaf(&self) -> FixedBytes<32> {
+        keccak256([self.tx_hash.as_slice(), &self.log_index.to_be_bytes()].concat())
+    }
+}
+
+/// Client for posting anchor digests to a public L1
+///
+/// Digests are sent as calldata of a zero-value transaction from the anchor
+/// signer to itself, so no contract deployment is needed on the L1.
+#[derive(Clone)]
+pub struct AnchorClient {
+    provider: AppProvider,
+    signer_address: Address,
+    chain_id: u64,
+}
+
+/// Calldata prefix identifying anchor transactions
+pub const ANCHOR_MAGIC: &[u8; 4] = b"OVC1";
+
+impl AnchorClient {
+    /// Build from ANCHOR_L1_* variables; `None` when ANCHOR_L1_RPC_URL is
+    /// unset or empty
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(rpc_url) = optional_var("ANCHOR_L1_RPC_URL") else {
+            return Ok(None);
+        };
+        let private_key = optional_var("ANCHOR_L1_PRIVATE_KEY")
+            .or_else(|| optional_var("PRIVATE_KEY"))
+            .context("ANCHOR_L1_PRIVATE_KEY (or PRIVATE_KEY) is required for anchoring")?;
+        let chain_id = optional_var("ANCHOR_L1_CHAIN_ID")
+            .unwrap_or_else(|| "1".to_string())
+            .parse::<u64>()
+            .context("ANCHOR_L1_CHAIN_ID must be a valid u64")?;
+
+        let signer = private_key.parse::<PrivateKeySigner>().context(
+            "ANCHOR_L1_PRIVATE_KEY is not a valid secp256k1 key (anchoring does not use the remote signer)",
+        )?;
+        let signer_address = signer.address();
+        let wallet = EthereumWallet::from(signer);
+
+        let provider = ProviderBuilder::new()
+            .with_recommended_fillers()
+            .wallet(wallet)
+            .on_http(rpc_url.parse().context("Invalid ANCHOR_L1_RPC_URL")?);
+
+        tracing::info!(signer = ?signer_address, chain_id, "Initialized L1 anchor client");
+
+        Ok(Some(Self {
+            provider,
+            signer_address,
+            chain_id,
+        }))
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// Post `root` covering primary-chain blocks `from_block..=to_block`
+    pub async fn post_digest(
+        &self,
+        root: FixedBytes<32>,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<TransactionReceipt> {
+        let mut calldata = ANCHOR_MAGIC.to_vec();
+        calldata.extend_from_slice(root.as_slice());
+        calldata.extend_from_slice(&from_block.to_be_bytes());
+        calldata.extend_from_slice(&to_block.to_be_bytes());
+
+        let tx = TransactionRequest::default()
+            .with_to(self.signer_address)
+            .with_chain_id(self.chain_id)
+            .with_input(Bytes::from(calldata));
+
+        let pending = self
+            .provider
+            .send_transaction(tx)
             .await
-            .context("Failed to send revealAIScore transaction")?;
+            .context("Failed to send L1 anchor transaction")?;
 
-        let receipt = tx
-            .get_receipt()
+        let receipt = slowlog::observe(SlowOperation::ReceiptWait, "l1Anchor", pending.get_receipt())
             .await
-            .context("Failed to get transaction receipt")?;
+            .context("Failed to get L1 anchor receipt")?;
 
         tracing::info!(
             tx_hash = ?receipt.transaction_hash,
-            "AI score revealed successfully"
+            ?root,
+            from_block,
+            to_block,
+            "Anchored digest on L1"
         );
 
         Ok(receipt)
@@ -616,3 +1961,28 @@ pub fn hash_bytes(data: &[u8]) -> FixedBytes<32> {
 pub fn generate_commit_hash(reveal_hash: FixedBytes<32>, nonce: FixedBytes<32>) -> FixedBytes<32> {
     keccak256([reveal_hash.as_slice(), nonce.as_slice()].concat())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_cache_is_bounded() {
+        let origin = (FixedBytes::ZERO, FixedBytes::ZERO, 1);
+        let mut cache = PackageCache::default();
+        for i in 0..=PACKAGE_CACHE_CAPACITY as u64 {
+            cache.insert(FixedBytes::from(U256::from(i)), origin);
+        }
+        assert_eq!(cache.entries.len(), PACKAGE_CACHE_CAPACITY);
+        assert!(cache.get(&FixedBytes::from(U256::ZERO)).is_none());
+
+        let newest = FixedBytes::from(U256::from(PACKAGE_CACHE_CAPACITY));
+        assert_eq!(cache.get(&newest), Some(origin));
+        cache.remove(&newest);
+        assert!(cache.get(&newest).is_none());
+
+        let stale = FixedBytes::from(U256::from(1));
+        cache.entries.get_mut(&stale).unwrap().0 -= PACKAGE_CACHE_TTL;
+        assert!(cache.get(&stale).is_none());
+    }
+}
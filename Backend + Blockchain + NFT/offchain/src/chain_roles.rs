@@ -0,0 +1,388 @@
+//! API permissions derived from on-chain roles
+//!
+//! A user account or API key can be bound to an on-chain address
+//! (`PUT /api/admin/chain-roles/bindings`). From then on its API roles
+//! follow that address's role bitmask on the contract instead of the role
+//! stored with the account or the scopes of the key, so on-chain governance
+//! decides who may do what:
+//!
+//! | on-chain role | API role  |
+//! |---------------|-----------|
+//! | ADMIN         | admin     |
+//! | FARMER        | farmer    |
+//! | FPO           | fpo       |
+//! | WAREHOUSE     | warehouse |
+//! | LOGISTICS     | warehouse |
+//! | PROCESSOR     | processor |
+//! | PACKAGER      | processor |
+//!
+//! AI_ORACLE has no API role. Entries of a JSON file named by
+//! ROLE_PERMISSIONS_PATH replace rows of the table, e.g.
+//! `{ "AI_ORACLE": ["processor"], "LOGISTICS": [] }`.
+//!
+//! The bitmask is read with `getRoles` when the binding is made and then
+//! kept current by the event indexer, which applies the write contract's
+//! RoleGranted and RoleRevoked events as their blocks are confirmed. A bound
+//! principal whose address holds no mapped role is refused. A bound API key
+//! keeps only the roles a key may carry ([`KEY_ROLES`]), so an ADMIN or
+//! FARMER address grants a key nothing. Lenders have no on-chain role and
+//! should not be bound. Bindings and the indexed bitmasks
+//! are kept in `data/chain_roles.json`.
+
+use crate::admin::require_admin;
+use crate::api_keys::KEY_ROLES;
+use crate::auth::{Claims, Role};
+use crate::chain::{roles, DecodedEvent, OilseedValueChain::OilseedValueChainEvents};
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+use alloy::primitives::Address;
+use anyhow::{Context, Result};
+use axum::{extract::State, http::HeaderMap, Json};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+
+const CHAIN_ROLES_PATH: &str = "data/chain_roles.json";
+
+/// API roles in the order a user's primary role is chosen
+const PRECEDENCE: [Role; 6] = [
+    Role::Admin,
+    Role::Processor,
+    Role::Warehouse,
+    Role::Fpo,
+    Role::Farmer,
+    Role::Lender,
+];
+
+fn default_permissions() -> Vec<(u64, Vec<Role>)> {
+    vec![
+        (roles::ADMIN, vec![Role::Admin]),
+        (roles::FARMER, vec![Role::Farmer]),
+        (roles::FPO, vec![Role::Fpo]),
+        (roles::WAREHOUSE, vec![Role::Warehouse]),
+        (roles::LOGISTICS, vec![Role::Warehouse]),
+        (roles::PROCESSOR, vec![Role::Processor]),
+        (roles::PACKAGER, vec![Role::Processor]),
+    ]
+}
+
+/// On-chain role bit to the API roles it grants
+#[derive(Debug, Clone)]
+pub struct PermissionMap(Vec<(u64, Vec<Role>)>);
+
+impl PermissionMap {
+    fn load() -> Result<Self> {
+        let mut map = default_permissions();
+        let Some(path) = std::env::var("ROLE_PERMISSIONS_PATH")
+            .ok()
+            .filter(|v| !v.is_empty())
+        else {
+            return Ok(Self(map));
+        };
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read ROLE_PERMISSIONS_PATH {}", path))?;
+        let overrides: HashMap<String, Vec<Role>> = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid role permissions file {}", path))?;
+        for (name, api_roles) in overrides {
+            let bit = roles::from_names(&[&name])?;
+            map.retain(|(b, _)| *b != bit);
+            map.push((bit, api_roles));
+        }
+        Ok(Self(map))
+    }
+
+    /// API roles granted by `bits`, highest precedence first
+    pub fn api_roles(&self, bits: u64) -> Vec<Role> {
+        PRECEDENCE
+            .into_iter()
+            .filter(|role| {
+                self.0
+                    .iter()
+                    .any(|(bit, granted)| bits & bit != 0 && granted.contains(role))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ChainRoleState {
+    /// Principal label (`user:<username>` or `api_key:<id>`) to address
+    bindings: BTreeMap<String, Address>,
+    /// Role bitmask of every address seen in a role event or binding
+    roles: BTreeMap<Address, u64>,
+}
+
+impl ChainRoleState {
+    fn bits(&self, address: &Address) -> u64 {
+        self.roles.get(address).copied().unwrap_or(0)
+    }
+
+    /// Apply one RoleGranted (`grant`) or RoleRevoked event
+    fn apply(&mut self, account: Address, bits: u64, grant: bool) {
+        let held = self.roles.entry(account).or_default();
+        if grant {
+            *held |= bits;
+        } else {
+            *held &= !bits;
+        }
+    }
+
+    fn bound_to(&self, address: &Address) -> Vec<&str> {
+        self.bindings
+            .iter()
+            .filter(|(_, a)| *a == address)
+            .map(|(principal, _)| principal.as_str())
+            .collect()
+    }
+}
+
+pub struct ChainRoleStore {
+    state: RwLock<ChainRoleState>,
+    permissions: PermissionMap,
+}
+
+impl ChainRoleStore {
+    pub fn load() -> Result<Self> {
+        let state = match std::fs::read_to_string(CHAIN_ROLES_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid chain roles file {}", CHAIN_ROLES_PATH))?,
+            Err(_) => ChainRoleState::default(),
+        };
+        Ok(Self {
+            state: RwLock::new(state),
+            permissions: PermissionMap::load()?,
+        })
+    }
+
+    fn save(state: &ChainRoleState) -> Result<()> {
+        std::fs::write(CHAIN_ROLES_PATH, serde_json::to_string_pretty(state)?)
+            .with_context(|| format!("Failed to write {}", CHAIN_ROLES_PATH))
+    }
+
+    /// API roles of `principal`, or `None` when it is not bound to an address
+    pub fn roles_for(&self, principal: &str) -> Option<Vec<Role>> {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        let address = state.bindings.get(principal)?;
+        Some(self.permissions.api_roles(state.bits(address)))
+    }
+
+    /// Replace the role of a bound user's claims with the roles its address
+    /// holds on-chain
+    pub fn apply_to_claims(&self, claims: &mut Claims) -> Result<(), ApiError> {
+        let Some(api_roles) = self.roles_for(&format!("user:{}", claims.sub)) else {
+            return Ok(());
+        };
+        let (primary, others) = api_roles
+            .split_first()
+            .ok_or_else(|| ApiError::forbidden("The account's on-chain address holds no role"))?;
+        claims.role = *primary;
+        claims.roles = others.to_vec();
+        Ok(())
+    }
+
+    /// Scopes of an API key: its own, or those of its bound address that a
+    /// key may carry
+    pub fn key_scopes(&self, id: u64, scopes: &[Role]) -> Vec<Role> {
+        match self.roles_for(&format!("api_key:{}", id)) {
+            Some(roles) => roles
+                .into_iter()
+                .filter(|r| KEY_ROLES.contains(r))
+                .collect(),
+            None => scopes.to_vec(),
+        }
+    }
+
+    /// Apply the role events `contract` emitted; returns how many applied
+    pub fn apply_events(&self, events: &[DecodedEvent], contract: Address) -> Result<usize> {
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        let mut applied = 0;
+        for decoded in events.iter().filter(|e| e.contract == contract) {
+            let (account, bits, grant) = match &decoded.event {
+                OilseedValueChainEvents::RoleGranted(e) => (e.account, e.role, true),
+                OilseedValueChainEvents::RoleRevoked(e) => (e.account, e.role, false),
+                _ => continue,
+            };
+            let before = self.permissions.api_roles(state.bits(&account));
+            state.apply(account, bits.saturating_to(), grant);
+            let after = self.permissions.api_roles(state.bits(&account));
+            applied += 1;
+
+            if before != after {
+                for principal in state.bound_to(&account) {
+                    tracing::info!(
+                        principal = %principal,
+                        account = %account,
+                        before = ?before,
+                        after = ?after,
+                        block = decoded.position.block_number,
+                        "API permissions follow on-chain role change"
+                    );
+                }
+            }
+        }
+        if applied > 0 {
+            Self::save(&state)?;
+        }
+        Ok(applied)
+    }
+
+    fn view(&self, state: &ChainRoleState, principal: &str, address: Address) -> BindingView {
+        let bits = state.bits(&address);
+        BindingView {
+            principal: principal.to_string(),
+            address: address.to_string(),
+            chain_roles: roles::names(bits),
+            api_roles: self.permissions.api_roles(bits),
+        }
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct BindingView {
+    pub principal: String,
+    pub address: String,
+    pub chain_roles: Vec<&'static str>,
+    /// Roles the principal is admitted as
+    pub api_roles: Vec<Role>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChainRolesView {
+    pub bindings: Vec<BindingView>,
+    /// On-chain role name to the API roles it grants
+    pub permissions: BTreeMap<&'static str, Vec<Role>>,
+}
+
+pub async fn list_bindings(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<ChainRolesView> {
+    require_admin(&state, &headers)?;
+
+    let store = &state.chain_roles;
+    let chain_state = store.state.read().unwrap_or_else(|e| e.into_inner());
+    Ok(Json(ChainRolesView {
+        bindings: chain_state
+            .bindings
+            .iter()
+            .map(|(principal, address)| store.view(&chain_state, principal, *address))
+            .collect(),
+        permissions: roles::ALL
+            .iter()
+            .map(|(name, bit)| (*name, store.permissions.api_roles(*bit)))
+            .collect(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BindRequest {
+    /// `user:<username>` or `api_key:<id>`
+    pub principal: String,
+    /// Omit to remove the binding
+    #[serde(default)]
+    pub address: Option<String>,
+}
+
+/// Bind a user or API key to an on-chain address, or unbind it (admin only)
+pub async fn bind_principal(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<BindRequest>,
+) -> ApiResult<Option<BindingView>> {
+    require_admin(&state, &headers)?;
+
+    let principal = payload.principal.trim();
+    let exists = match principal.split_once(':') {
+        Some(("user", username)) => state.auth.has_user(username).await,
+        Some(("api_key", id)) => match id.parse() {
+            Ok(id) => state.api_keys.has_key(id).await,
+            Err(_) => false,
+        },
+        _ => {
+            return Err(ApiError::bad_request(
+                "principal must be user:<username> or api_key:<id>",
+            ))
+        }
+    };
+    if !exists {
+        return Err(ApiError::not_found(format!(
+            "Unknown principal {}",
+            principal
+        )));
+    }
+
+    let store = &state.chain_roles;
+    let Some(address) = payload.address.filter(|a| !a.trim().is_empty()) else {
+        let mut chain_state = store.state.write().unwrap_or_else(|e| e.into_inner());
+        if chain_state.bindings.remove(principal).is_some() {
+            ChainRoleStore::save(&chain_state)?;
+            tracing::info!(principal = %principal, "On-chain role binding removed");
+        }
+        return Ok(Json(None));
+    };
+    let address: Address = address
+        .trim()
+        .parse()
+        .map_err(|e| ApiError::bad_request(format!("Invalid address: {}", e)))?;
+    // Seed the bitmask; the indexer keeps it current from here on
+    let bits = state
+        .chain()
+        .get_roles(address)
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+
+    let mut chain_state = store.state.write().unwrap_or_else(|e| e.into_inner());
+    chain_state.roles.insert(address, bits.saturating_to());
+    chain_state.bindings.insert(principal.to_string(), address);
+    ChainRoleStore::save(&chain_state)?;
+
+    let view = store.view(&chain_state, principal, address);
+    tracing::info!(
+        principal = %principal,
+        address = %address,
+        api_roles = ?view.api_roles,
+        "Principal bound to on-chain roles"
+    );
+    Ok(Json(Some(view)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_events_drive_api_roles() {
+        let permissions = PermissionMap(default_permissions());
+        let mut state = ChainRoleState::default();
+        let account = Address::repeat_byte(0x11);
+
+        state.apply(account, roles::FPO | roles::PACKAGER, true);
+        assert_eq!(
+            permissions.api_roles(state.bits(&account)),
+            vec![Role::Processor, Role::Fpo]
+        );
+
+        state.apply(account, roles::PACKAGER, false);
+        assert_eq!(permissions.api_roles(state.bits(&account)), vec![Role::Fpo]);
+
+        state.apply(account, roles::FPO | roles::AI_ORACLE, false);
+        assert!(permissions.api_roles(state.bits(&account)).is_empty());
+    }
+
+    #[test]
+    fn test_bound_keys_never_gain_person_roles() {
+        let account = Address::repeat_byte(0x22);
+        let mut state = ChainRoleState::default();
+        state.bindings.insert("api_key:7".to_string(), account);
+        state.apply(account, roles::ADMIN | roles::FARMER | roles::FPO, true);
+        let store = ChainRoleStore {
+            state: RwLock::new(state),
+            permissions: PermissionMap(default_permissions()),
+        };
+
+        assert_eq!(store.key_scopes(7, &[Role::Lender]), vec![Role::Fpo]);
+        assert_eq!(store.key_scopes(8, &[Role::Lender]), vec![Role::Lender]);
+    }
+}
@@ -0,0 +1,338 @@
+//! Deferred pinning with hash-first anchoring
+//!
+//! Chain writes normally wait for the batch folder to be pinned so the
+//! transaction can carry its CID. With PIN_DEFERRAL_TIMEOUT_SECS set, a pin
+//! that takes longer than that (or every pin, when it is 0) is deferred: the
+//! folder is snapshotted to `data/deferred_pins/`, its content hash (see
+//! [`folder_hash`]) is anchored on chain right away as the placeholder CID
+//! `pending:<hash>`, and a background task pins the snapshot later.
+//!
+//! Once pinned, the CID is recorded against the placeholder in this
+//! provenance ledger (`data/cid_provenance.json`). IPFS reads resolve
+//! placeholders through it, and `/api/cids/provenance` exposes the mapping
+//! so third parties can link an on-chain placeholder to its content.
+
+use crate::error::{format_hash, ApiError, ApiResult};
+use crate::merkle::{leaf, merkle_root};
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use crate::tenant_storage;
+use alloy::primitives::{keccak256, FixedBytes};
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+const PROVENANCE_PATH: &str = "data/cid_provenance.json";
+const SNAPSHOTS_DIR: &str = "data/deferred_pins";
+const DEFAULT_RETRY_SECS: u64 = 30;
+pub const PLACEHOLDER_PREFIX: &str = "pending:";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CidRecord {
+    pub content_hash: FixedBytes<32>,
+    /// CID anchored on chain while the pin was pending
+    pub placeholder: String,
+    /// Folder the snapshot was taken from
+    pub folder: String,
+    pub tenant: String,
+    pub cid: Option<String>,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub deferred_at: String,
+    pub pinned_at: Option<String>,
+}
+
+impl CidRecord {
+    fn snapshot_dir(&self) -> PathBuf {
+        snapshot_dir(self.content_hash)
+    }
+}
+
+fn snapshot_dir(content_hash: FixedBytes<32>) -> PathBuf {
+    std::path::Path::new(SNAPSHOTS_DIR).join(hex::encode(content_hash))
+}
+
+/// Placeholder CID anchored for content with `content_hash`
+pub fn placeholder(content_hash: FixedBytes<32>) -> String {
+    format!("{}{}", PLACEHOLDER_PREFIX, format_hash(content_hash))
+}
+
+/// How long to wait for Pinata before deferring a pin (PIN_DEFERRAL_TIMEOUT_SECS);
+/// `None` disables deferral
+pub fn deferral_timeout() -> Option<Duration> {
+    std::env::var("PIN_DEFERRAL_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_secs)
+}
+
+/// Merkle root over the folder's files; each leaf commits to a file's path
+/// relative to the folder and the keccak hash of its bytes
+pub fn folder_hash(folder: &str) -> Result<FixedBytes<32>> {
+    let mut files = Vec::new();
+    for entry in walkdir::WalkDir::new(folder).sort_by_file_name() {
+        let entry =
+            entry.with_context(|| format!("Failed to read directory entry in: {}", folder))?;
+        if entry.path().is_file() {
+            let bytes = std::fs::read(entry.path())
+                .with_context(|| format!("Failed to read file: {}", entry.path().display()))?;
+            let rel_path = entry
+                .path()
+                .strip_prefix(folder)
+                .with_context(|| {
+                    format!(
+                        "Failed to get relative path for: {}",
+                        entry.path().display()
+                    )
+                })?
+                .to_string_lossy()
+                .to_string();
+            files.push((rel_path, keccak256(&bytes)));
+        }
+    }
+    if files.is_empty() {
+        anyhow::bail!("Folder {} has no files to pin", folder);
+    }
+    files.sort();
+
+    let leaves: Vec<FixedBytes<32>> = files
+        .iter()
+        .map(|(path, hash)| {
+            let mut data = path.as_bytes().to_vec();
+            data.push(0);
+            data.extend_from_slice(hash.as_slice());
+            leaf(&data)
+        })
+        .collect();
+    Ok(merkle_root(&leaves))
+}
+
+fn copy_folder(from: &str, to: &std::path::Path) -> Result<()> {
+    for entry in walkdir::WalkDir::new(from) {
+        let entry =
+            entry.with_context(|| format!("Failed to read directory entry in: {}", from))?;
+        let target = to.join(entry.path().strip_prefix(from)?);
+        if entry.path().is_dir() {
+            std::fs::create_dir_all(&target)
+                .with_context(|| format!("Failed to create {}", target.display()))?;
+        } else {
+            std::fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+// ======================== LEDGER ========================
+
+#[derive(Debug)]
+pub struct CidProvenance {
+    records: Mutex<Vec<CidRecord>>,
+}
+
+impl CidProvenance {
+    pub fn load() -> Result<Self> {
+        let records = match std::fs::read_to_string(PROVENANCE_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid CID provenance {}", PROVENANCE_PATH))?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Self {
+            records: Mutex::new(records),
+        })
+    }
+
+    fn save(records: &[CidRecord]) -> Result<()> {
+        std::fs::create_dir_all("data").context("Failed to create data directory")?;
+        std::fs::write(PROVENANCE_PATH, serde_json::to_string_pretty(records)?)
+            .with_context(|| format!("Failed to write {}", PROVENANCE_PATH))
+    }
+
+    /// Snapshot `folder` for a later pin by `tenant`; returns the CID to
+    /// anchor now (the real one, if identical content was pinned before)
+    pub async fn defer(&self, folder: &str, tenant: &str) -> Result<String> {
+        let content_hash = folder_hash(folder)?;
+        let mut records = self.records.lock().await;
+        if let Some(existing) = records.iter().find(|r| r.content_hash == content_hash) {
+            return Ok(existing
+                .cid
+                .clone()
+                .unwrap_or_else(|| existing.placeholder.clone()));
+        }
+
+        let snapshot = snapshot_dir(content_hash);
+        copy_folder(folder, &snapshot)?;
+
+        records.push(CidRecord {
+            content_hash,
+            placeholder: placeholder(content_hash),
+            folder: folder.to_string(),
+            tenant: tenant.to_string(),
+            cid: None,
+            attempts: 0,
+            last_error: None,
+            deferred_at: chrono::Utc::now().to_rfc3339(),
+            pinned_at: None,
+        });
+        Self::save(&records)?;
+
+        Ok(placeholder(content_hash))
+    }
+
+    /// Pinned CID of a placeholder; other CIDs are returned unchanged
+    pub async fn resolve(&self, cid: &str) -> Result<String> {
+        if !cid.starts_with(PLACEHOLDER_PREFIX) {
+            return Ok(cid.to_string());
+        }
+        let records = self.records.lock().await;
+        let record = records
+            .iter()
+            .find(|r| r.placeholder == cid)
+            .with_context(|| format!("Unknown placeholder CID {}", cid))?;
+        record
+            .cid
+            .clone()
+            .with_context(|| format!("{} is not pinned yet", cid))
+    }
+
+    async fn pending(&self) -> Vec<CidRecord> {
+        let records = self.records.lock().await;
+        records
+            .iter()
+            .filter(|r| r.cid.is_none())
+            .cloned()
+            .collect()
+    }
+
+    async fn finish(&self, content_hash: FixedBytes<32>, result: &Result<String>) -> Result<()> {
+        let mut records = self.records.lock().await;
+        let Some(record) = records.iter_mut().find(|r| r.content_hash == content_hash) else {
+            return Ok(());
+        };
+        record.attempts += 1;
+        match result {
+            Ok(cid) => {
+                record.cid = Some(cid.clone());
+                record.last_error = None;
+                record.pinned_at = Some(chrono::Utc::now().to_rfc3339());
+                if let Err(e) = std::fs::remove_dir_all(record.snapshot_dir()) {
+                    tracing::warn!(error = %e, "Failed to remove pinned snapshot");
+                }
+            }
+            Err(e) => record.last_error = Some(format!("{:#}", e)),
+        }
+        Self::save(&records)
+    }
+}
+
+// ======================== PIN JOB ========================
+
+/// Pin every deferred snapshot; returns how many were pinned
+pub async fn run_once(state: &AppState) -> Result<usize> {
+    let ipfs = &state.ipfs_client;
+    let mut pinned = 0;
+    for record in ipfs.provenance().pending().await {
+        let snapshot = record.snapshot_dir().to_string_lossy().to_string();
+        let result = tenant_storage::run_as(
+            ipfs.tenants(),
+            &record.tenant,
+            ipfs.upload_folder(&snapshot),
+        )
+        .await;
+        match &result {
+            Ok(cid) => {
+                pinned += 1;
+                tracing::info!(placeholder = %record.placeholder, cid = %cid, "Deferred pin completed");
+            }
+            Err(e) => tracing::warn!(
+                placeholder = %record.placeholder,
+                error = %format!("{:#}", e),
+                "Deferred pin failed, will retry"
+            ),
+        }
+        ipfs.provenance()
+            .finish(record.content_hash, &result)
+            .await?;
+    }
+    Ok(pinned)
+}
+
+/// Start the background pinning of deferred snapshots
+pub fn spawn(state: AppState) {
+    let interval = std::env::var("PIN_DEFERRAL_RETRY_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_RETRY_SECS)
+        .max(1);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+        loop {
+            ticker.tick().await;
+            if let Err(e) = run_once(&state).await {
+                tracing::error!(error = %format!("{:#}", e), "Deferred pin job failed");
+            }
+        }
+    });
+}
+
+// ======================== HANDLERS ========================
+
+pub async fn list_provenance(
+    State(state): State<AppState>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<CidRecord>> {
+    let records = state.ipfs_client.provenance().records.lock().await.clone();
+    Ok(Json(paginate("cid_provenance", records, &params, |r| {
+        (r.deferred_at.clone(), r.placeholder.clone())
+    })?))
+}
+
+/// Provenance of a placeholder CID, or of the content hash it carries
+pub async fn get_provenance(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<CidRecord> {
+    let placeholder = if id.starts_with(PLACEHOLDER_PREFIX) {
+        id
+    } else {
+        format!("{}{}", PLACEHOLDER_PREFIX, id)
+    };
+    let records = state.ipfs_client.provenance().records.lock().await;
+    records
+        .iter()
+        .find(|r| r.placeholder.eq_ignore_ascii_case(&placeholder))
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found("No deferred pin with this placeholder"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folder_hash_commits_to_paths_and_contents() {
+        let dir = std::env::temp_dir().join(format!("cid-provenance-{}", std::process::id()));
+        let folder = dir.to_string_lossy().to_string();
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("fpo_purchase.json"), "{}").unwrap();
+        let original = folder_hash(&folder).unwrap();
+
+        let copy = dir.with_extension("copy");
+        copy_folder(&folder, &copy).unwrap();
+        assert_eq!(folder_hash(&copy.to_string_lossy()).unwrap(), original);
+
+        std::fs::write(dir.join("processing.json"), "{}").unwrap();
+        assert_ne!(folder_hash(&folder).unwrap(), original);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::remove_dir_all(&copy).unwrap();
+    }
+}
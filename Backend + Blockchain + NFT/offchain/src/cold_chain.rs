@@ -0,0 +1,339 @@
+//! In-transit temperature monitoring
+//!
+//! Sensors on a shipment post their readings to `POST /api/logistics/readings`,
+//! in batches as connectivity allows. `GET /api/logistics/cold-chain/:shipment_id`
+//! reports the exposure of the shipment between its first and last reading.
+//!
+//! Each reading is taken to hold until the next one, as long as the next one
+//! follows within COLD_CHAIN_GAP_MINUTES (default 30). A longer silence is
+//! reported as an unmonitored interval and is not filled in: the spoilage
+//! score only covers monitored time, `confidence` is the monitored share of
+//! the trip, and `worst_case_score` counts every unmonitored minute as an
+//! excursion above COLD_CHAIN_MAX_TEMP_C (default 30).
+
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+const READINGS_PATH: &str = "data/cold_chain.json";
+const DEFAULT_GAP_MINUTES: i64 = 30;
+const DEFAULT_MAX_TEMP_C: f64 = 30.0;
+/// Readings accepted in one request
+const MAX_READINGS_PER_REQUEST: usize = 5000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemperatureReading {
+    /// RFC 3339 time of the measurement
+    pub recorded_at: String,
+    pub temperature_celsius: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub humidity_percent: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sensor_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MonitoringPolicy {
+    max_gap: Duration,
+    max_temp_c: f64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct UnmonitoredInterval {
+    pub from: String,
+    pub to: String,
+    pub minutes: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+impl RiskLevel {
+    fn of(score: f64) -> Self {
+        if score < 0.05 {
+            RiskLevel::Low
+        } else if score < 0.2 {
+            RiskLevel::Medium
+        } else {
+            RiskLevel::High
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SpoilageRisk {
+    /// Share of monitored time above the temperature limit
+    pub score: f64,
+    pub level: RiskLevel,
+    /// Share of the trip covered by readings
+    pub confidence: f64,
+    /// Score if every unmonitored minute was an excursion
+    pub worst_case_score: f64,
+    pub worst_case_level: RiskLevel,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ColdChainReport {
+    pub shipment_id: String,
+    pub readings: usize,
+    pub first_reading: Option<String>,
+    pub last_reading: Option<String>,
+    pub max_gap_minutes: i64,
+    pub max_temperature_celsius: f64,
+    pub monitored_minutes: i64,
+    pub excursion_minutes: i64,
+    pub unmonitored: Vec<UnmonitoredInterval>,
+    pub spoilage: Option<SpoilageRisk>,
+}
+
+/// Readings must be sorted by time
+fn analyze(
+    shipment_id: &str,
+    readings: &[(DateTime<Utc>, f64)],
+    policy: MonitoringPolicy,
+) -> ColdChainReport {
+    let mut monitored = Duration::zero();
+    let mut excursion = Duration::zero();
+    let mut unmonitored = Vec::new();
+    let mut unmonitored_total = Duration::zero();
+
+    for pair in readings.windows(2) {
+        let ((from, temperature), (to, _)) = (pair[0], pair[1]);
+        let span = to - from;
+        if span > policy.max_gap {
+            unmonitored_total += span;
+            unmonitored.push(UnmonitoredInterval {
+                from: from.to_rfc3339(),
+                to: to.to_rfc3339(),
+                minutes: span.num_minutes(),
+            });
+        } else {
+            monitored += span;
+            if temperature > policy.max_temp_c {
+                excursion += span;
+            }
+        }
+    }
+
+    let total = monitored + unmonitored_total;
+    let spoilage = (total > Duration::zero()).then(|| {
+        let seconds = |d: Duration| d.num_seconds() as f64;
+        let score = if monitored > Duration::zero() {
+            seconds(excursion) / seconds(monitored)
+        } else {
+            0.0
+        };
+        let worst_case_score = seconds(excursion + unmonitored_total) / seconds(total);
+        SpoilageRisk {
+            score,
+            level: RiskLevel::of(score),
+            confidence: seconds(monitored) / seconds(total),
+            worst_case_score,
+            worst_case_level: RiskLevel::of(worst_case_score),
+        }
+    });
+
+    ColdChainReport {
+        shipment_id: shipment_id.to_string(),
+        readings: readings.len(),
+        first_reading: readings.first().map(|(t, _)| t.to_rfc3339()),
+        last_reading: readings.last().map(|(t, _)| t.to_rfc3339()),
+        max_gap_minutes: policy.max_gap.num_minutes(),
+        max_temperature_celsius: policy.max_temp_c,
+        monitored_minutes: monitored.num_minutes(),
+        excursion_minutes: excursion.num_minutes(),
+        unmonitored,
+        spoilage,
+    }
+}
+
+pub struct ColdChainStore {
+    readings: Mutex<HashMap<String, Vec<TemperatureReading>>>,
+    policy: MonitoringPolicy,
+}
+
+impl ColdChainStore {
+    pub fn load() -> Result<Self> {
+        let readings = match std::fs::read_to_string(READINGS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid cold-chain file {}", READINGS_PATH))?,
+            Err(_) => HashMap::new(),
+        };
+        let gap_minutes = std::env::var("COLD_CHAIN_GAP_MINUTES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|m: &i64| *m > 0)
+            .unwrap_or(DEFAULT_GAP_MINUTES);
+        let max_temp_c = std::env::var("COLD_CHAIN_MAX_TEMP_C")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_TEMP_C);
+
+        Ok(Self {
+            readings: Mutex::new(readings),
+            policy: MonitoringPolicy {
+                max_gap: Duration::minutes(gap_minutes),
+                max_temp_c,
+            },
+        })
+    }
+
+    fn save(readings: &HashMap<String, Vec<TemperatureReading>>) -> Result<()> {
+        std::fs::write(READINGS_PATH, serde_json::to_string_pretty(readings)?)
+            .with_context(|| format!("Failed to write {}", READINGS_PATH))
+    }
+
+    /// Report of `shipment_id`, `None` when it has no readings
+    pub async fn report(&self, shipment_id: &str) -> Option<ColdChainReport> {
+        let all = self.readings.lock().await;
+        let mut readings: Vec<(DateTime<Utc>, f64)> = all
+            .get(shipment_id)?
+            .iter()
+            .filter_map(|r| Some((parse_time(r)?, r.temperature_celsius)))
+            .collect();
+        readings.sort_by_key(|(t, _)| *t);
+        Some(analyze(shipment_id, &readings, self.policy))
+    }
+}
+
+fn parse_time(reading: &TemperatureReading) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(&reading.recorded_at)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct RecordReadingsRequest {
+    pub shipment_id: String,
+    pub readings: Vec<TemperatureReading>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecordReadingsResponse {
+    pub shipment_id: String,
+    pub accepted: usize,
+    /// Readings already stored for the same time and sensor
+    pub duplicates: usize,
+}
+
+/// Store a batch of sensor readings for a shipment
+pub async fn record_readings(
+    State(state): State<AppState>,
+    Json(payload): Json<RecordReadingsRequest>,
+) -> ApiResult<RecordReadingsResponse> {
+    if payload.shipment_id.trim().is_empty() {
+        return Err(ApiError::bad_request("shipment_id is required"));
+    }
+    if payload.readings.is_empty() || payload.readings.len() > MAX_READINGS_PER_REQUEST {
+        return Err(ApiError::bad_request(format!(
+            "Between 1 and {} readings per request",
+            MAX_READINGS_PER_REQUEST
+        )));
+    }
+    for reading in &payload.readings {
+        if parse_time(reading).is_none() {
+            return Err(ApiError::bad_request(format!(
+                "recorded_at {:?} is not an RFC 3339 timestamp",
+                reading.recorded_at
+            )));
+        }
+        if !reading.temperature_celsius.is_finite() {
+            return Err(ApiError::bad_request(
+                "temperature_celsius must be a number",
+            ));
+        }
+    }
+
+    let mut all = state.cold_chain.readings.lock().await;
+    let stored = all.entry(payload.shipment_id.clone()).or_default();
+    let mut accepted = 0;
+    for reading in payload.readings.iter() {
+        let time = parse_time(reading);
+        let duplicate = stored
+            .iter()
+            .any(|r| parse_time(r) == time && r.sensor_id == reading.sensor_id);
+        if !duplicate {
+            stored.push(reading.clone());
+            accepted += 1;
+        }
+    }
+    ColdChainStore::save(&all)?;
+
+    Ok(Json(RecordReadingsResponse {
+        shipment_id: payload.shipment_id,
+        accepted,
+        duplicates: payload.readings.len() - accepted,
+    }))
+}
+
+/// Exposure, unmonitored intervals and spoilage risk of a shipment
+pub async fn cold_chain_report(
+    State(state): State<AppState>,
+    Path(shipment_id): Path<String>,
+) -> ApiResult<ColdChainReport> {
+    let report = state.cold_chain.report(&shipment_id).await.ok_or_else(|| {
+        ApiError::not_found(format!("No temperature readings for {}", shipment_id))
+    })?;
+    if !report.unmonitored.is_empty() {
+        tracing::info!(
+            shipment_id = %shipment_id,
+            gaps = report.unmonitored.len(),
+            "Cold-chain report has unmonitored intervals"
+        );
+    }
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gaps_are_unmonitored_not_interpolated() {
+        let start = DateTime::parse_from_rfc3339("2025-06-02T06:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let at = |minutes: i64, temperature: f64| (start + Duration::minutes(minutes), temperature);
+        // 60 monitored minutes, 20 of them hot, then a two-hour silence
+        let readings = [
+            at(0, 25.0),
+            at(20, 34.0),
+            at(40, 26.0),
+            at(60, 27.0),
+            at(180, 26.0),
+        ];
+        let policy = MonitoringPolicy {
+            max_gap: Duration::minutes(30),
+            max_temp_c: 30.0,
+        };
+
+        let report = analyze("SHIP-1", &readings, policy);
+        assert_eq!(report.monitored_minutes, 60);
+        assert_eq!(report.excursion_minutes, 20);
+        assert_eq!(report.unmonitored.len(), 1);
+        assert_eq!(report.unmonitored[0].minutes, 120);
+
+        let risk = report.spoilage.unwrap();
+        assert!((risk.score - 1.0 / 3.0).abs() < 1e-9);
+        assert!((risk.confidence - 1.0 / 3.0).abs() < 1e-9);
+        assert!((risk.worst_case_score - 140.0 / 180.0).abs() < 1e-9);
+        assert_eq!(risk.worst_case_level, RiskLevel::High);
+
+        assert!(analyze("SHIP-2", &readings[..1], policy).spoilage.is_none());
+    }
+}
@@ -0,0 +1,544 @@
+//! Privacy-preserving batch commitments and selective disclosure proofs
+//!
+//! A batch commitment is the root of a keccak Merkle tree whose leaves are
+//! salted commitments to individual batch attributes (farmer DID, district,
+//! state, crop, grade, quantity). The root can be published; without the
+//! salts it reveals nothing about the attributes.
+//!
+//! A selective proof opens exactly one leaf. The district proof shows that a
+//! SKU descends from a batch whose farmer is in one of a buyer's approved
+//! districts, without revealing the farmer or any other attribute:
+//!
+//! ```text
+//! SKU ──(on-chain parentBatch)──▶ batch hash
+//! batch commitment root ──(Merkle path)──▶ district leaf = H(field, value, salt)
+//! approved set root     ──(Merkle path)──▶ H(district)
+//! ```
+//!
+//! Salts are kept in `data/commitments/<batch_id>.json`, outside the batch
+//! folder that gets pinned to IPFS. Only the root goes into the batch folder.
+//!
+//! Approved district sets are named lists kept on the server in
+//! `data/district_sets.json` (override with DISTRICT_SETS_CONFIG_PATH):
+//!
+//! ```json
+//! { "north-mustard": ["HR002", "PB001", "RJ010"] }
+//! ```
+//!
+//! Verification trusts neither root in the proof: the set root is rebuilt from
+//! the named set and the commitment root is read from the batch's pinned
+//! `commitment.json`, located through the SKU's on-chain parent batch.
+//! Hashing uses domain-separated keccak256 with sorted pairs, so the proofs
+//! can be checked on-chain or inside a circuit. Openings and proofs record
+//! the hash scheme (see [`crate::hash_schemes`]) they were built with.
+
+use crate::admin::require_admin;
+use crate::batch_ledger;
+use crate::chain::hash_string;
+use crate::error::{format_hash, ipfs_gateway_url, ApiError, ApiResult};
+use crate::hash_schemes::HashScheme;
+use crate::merkle::{
+    self, merkle_proof, merkle_root, verify_merkle_proof, MERKLE_HASH_SCHEME, TREE_SCHEME,
+};
+use crate::state::AppState;
+use alloy::primitives::FixedBytes;
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const OPENINGS_DIR: &str = "data/commitments";
+const DEFAULT_DISTRICT_SETS_CONFIG_PATH: &str = "data/district_sets.json";
+
+/// Attribute order inside a batch commitment
+pub const COMMITTED_FIELDS: &[&str] = &[
+    "farmer_did",
+    "district_code",
+    "state_code",
+    "crop_type",
+    "quality_grade",
+    "quantity_kg",
+];
+
+// ======================== LEAVES ========================
+
+/// Salted commitment to a single attribute
+pub fn attribute_leaf(field: &str, value: &str, salt: &FixedBytes<32>) -> FixedBytes<32> {
+    let mut data = Vec::new();
+    data.extend_from_slice(field.as_bytes());
+    data.push(0x1f);
+    data.extend_from_slice(value.as_bytes());
+    data.extend_from_slice(salt.as_slice());
+    merkle::leaf(&data)
+}
+
+/// Unsalted leaf for public set members (e.g. approved districts)
+pub fn set_member_leaf(value: &str) -> FixedBytes<32> {
+    merkle::leaf(value.as_bytes())
+}
+
+// ======================== BATCH COMMITMENTS ========================
+
+/// Private openings of a batch commitment (never published)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchOpenings {
+    pub batch_id: String,
+    pub root: FixedBytes<32>,
+    /// (field, value, salt) in [`COMMITTED_FIELDS`] order
+    pub attributes: Vec<(String, String, FixedBytes<32>)>,
+    pub created_at: String,
+    #[serde(default)]
+    pub hash_scheme: HashScheme,
+}
+
+impl BatchOpenings {
+    pub fn leaves(&self) -> Vec<FixedBytes<32>> {
+        self.attributes
+            .iter()
+            .map(|(field, value, salt)| attribute_leaf(field, value, salt))
+            .collect()
+    }
+
+    fn path(batch_id: &str) -> std::path::PathBuf {
+        std::path::Path::new(OPENINGS_DIR).join(format!("{}.json", batch_id))
+    }
+
+    pub fn load(batch_id: &str) -> Result<Option<Self>> {
+        let path = Self::path(batch_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .with_context(|| format!("Invalid commitment openings {}", path.display()))
+    }
+
+    fn save(&self) -> Result<()> {
+        std::fs::create_dir_all(OPENINGS_DIR)
+            .with_context(|| format!("Failed to create {}", OPENINGS_DIR))?;
+        let path = Self::path(&self.batch_id);
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Commit to the given attribute values with fresh random salts
+pub fn commit_attributes(batch_id: &str, values: &[(&str, String)]) -> BatchOpenings {
+    let attributes: Vec<(String, String, FixedBytes<32>)> = values
+        .iter()
+        .map(|(field, value)| {
+            let salt: [u8; 32] = rand::random();
+            (field.to_string(), value.clone(), FixedBytes::from(salt))
+        })
+        .collect();
+
+    let mut openings = BatchOpenings {
+        batch_id: batch_id.to_string(),
+        root: FixedBytes::ZERO,
+        attributes,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        hash_scheme: MERKLE_HASH_SCHEME,
+    };
+    openings.root = merkle_root(&openings.leaves());
+    openings
+}
+
+/// Commitment root published in the batch folder, if the batch has one
+pub fn published_root(batch_id: &str) -> Result<Option<FixedBytes<32>>> {
+    if !batch_ledger::is_valid_batch_id(batch_id) {
+        return Ok(None);
+    }
+    let path = batch_ledger::batch_path(batch_id).join("commitment.json");
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let public: serde_json::Value = serde_json::from_str(&content)
+        .with_context(|| format!("Invalid JSON in {}", path.display()))?;
+    let root = public
+        .get("commitment_root")
+        .and_then(|v| v.as_str())
+        .with_context(|| format!("No commitment_root in {}", path.display()))?;
+    root.parse()
+        .map(Some)
+        .with_context(|| format!("Invalid commitment_root in {}", path.display()))
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchCommitmentResponse {
+    pub batch_id: String,
+    pub batch_hash: String,
+    pub commitment_root: String,
+    pub fields: Vec<String>,
+    pub metadata_cid: String,
+    pub ipfs_url: String,
+}
+
+/// Create (or rotate) the commitment for a batch (admin only)
+pub async fn create_batch_commitment(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(batch_id): Path<String>,
+) -> ApiResult<BatchCommitmentResponse> {
+    require_admin(&state, &headers)?;
+
+    let purchase = batch_ledger::fpo_purchase(&batch_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::not_found(format!("Batch {} not found", batch_id)))?;
+    let text = |pointer: &str| {
+        purchase
+            .pointer(pointer)
+            .map(|v| match v {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .unwrap_or_default()
+    };
+
+    let farmer_did = text("/farmer_info/farmer_did");
+    let farmer = state
+        .farmer_verification
+        .get_farmer_by_did(&farmer_did)
+        .await?
+        .ok_or_else(|| ApiError::bad_request("Batch farmer is not in the farmer database"))?;
+
+    let values = [
+        ("farmer_did", farmer_did),
+        ("district_code", farmer.district_code),
+        ("state_code", farmer.state_code),
+        ("crop_type", text("/farmer_info/crop_type")),
+        ("quality_grade", text("/batch_info/quality_grade")),
+        ("quantity_kg", text("/batch_info/quantity_kg")),
+    ];
+    let openings = commit_attributes(&batch_id, &values);
+    openings.save().map_err(ApiError::from)?;
+
+    // Publish only the root alongside the batch records
+    let folder = batch_ledger::batch_path(&batch_id)
+        .to_string_lossy()
+        .to_string();
+    let public = serde_json::json!({
+        "commitment_root": format_hash(openings.root),
+        "fields": COMMITTED_FIELDS,
+        "scheme": TREE_SCHEME,
+        "hash_scheme": openings.hash_scheme,
+        "created_at": openings.created_at,
+    });
+    state
+        .ipfs_client
+        .write_json_to_folder(&folder, "commitment.json", &public)
+        .map_err(ApiError::ipfs_upload_failed)?;
+    let metadata_cid = state
+        .ipfs_client
+        .upload_folder(&folder)
+        .await
+        .map_err(ApiError::ipfs_upload_failed)?;
+
+    tracing::info!(batch_id = %batch_id, root = %format_hash(openings.root), "Batch commitment created");
+
+    Ok(Json(BatchCommitmentResponse {
+        batch_hash: format_hash(hash_string(&batch_id)),
+        batch_id,
+        commitment_root: format_hash(openings.root),
+        fields: COMMITTED_FIELDS.iter().map(|f| f.to_string()).collect(),
+        ipfs_url: ipfs_gateway_url(&metadata_cid),
+        metadata_cid,
+    }))
+}
+
+// ======================== DISTRICT SETS ========================
+
+/// Named sets of approved districts, each sorted and deduplicated
+pub struct DistrictSets {
+    sets: HashMap<String, Vec<String>>,
+}
+
+impl DistrictSets {
+    pub fn load() -> Result<Self> {
+        let path = std::env::var("DISTRICT_SETS_CONFIG_PATH")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| DEFAULT_DISTRICT_SETS_CONFIG_PATH.to_string());
+
+        let sets: HashMap<String, Vec<String>> = if std::path::Path::new(&path).exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read district sets {}", path))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid district sets {}", path))?
+        } else {
+            HashMap::new()
+        };
+        Self::from_sets(sets)
+    }
+
+    fn from_sets(sets: HashMap<String, Vec<String>>) -> Result<Self> {
+        let mut checked = HashMap::with_capacity(sets.len());
+        for (name, mut districts) in sets {
+            districts.retain(|d| !d.trim().is_empty());
+            if districts.is_empty() {
+                bail!("District set {} is empty", name);
+            }
+            districts.sort();
+            districts.dedup();
+            checked.insert(name, districts);
+        }
+        Ok(Self { sets: checked })
+    }
+
+    pub fn get(&self, name: &str) -> Result<&[String], ApiError> {
+        self.sets
+            .get(name)
+            .map(Vec::as_slice)
+            .ok_or_else(|| ApiError::not_found(format!("District set {} not found", name)))
+    }
+}
+
+// ======================== DISTRICT PROOFS ========================
+
+/// Proof that a SKU's source farmer is in a named approved district set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DistrictProof {
+    pub sku_id: String,
+    /// On-chain parent batch of the SKU (keccak of the batch ID)
+    pub batch_hash: FixedBytes<32>,
+    /// Root the prover used; [`verify_district_proof`] checks against the
+    /// pinned root instead
+    pub commitment_root: FixedBytes<32>,
+    pub district_code: String,
+    pub district_salt: FixedBytes<32>,
+    pub commitment_proof: Vec<FixedBytes<32>>,
+    /// Name of the approved set on this server
+    pub district_set: String,
+    /// Root of the set when the proof was built, for offline checks
+    pub approved_set_root: FixedBytes<32>,
+    pub set_proof: Vec<FixedBytes<32>>,
+    #[serde(default)]
+    pub hash_scheme: HashScheme,
+}
+
+fn set_root(approved: &[String]) -> FixedBytes<32> {
+    let leaves: Vec<FixedBytes<32>> = approved.iter().map(|d| set_member_leaf(d)).collect();
+    merkle_root(&leaves)
+}
+
+impl DistrictProof {
+    /// Check both Merkle paths against trusted roots: the batch's published
+    /// commitment root and the root of the server's approved set. The
+    /// SKU → batch link is checked on-chain.
+    pub fn verify(&self, commitment_root: FixedBytes<32>, approved: &[String]) -> bool {
+        // Paths built under another scheme cannot be recomputed here
+        if self.hash_scheme != MERKLE_HASH_SCHEME {
+            return false;
+        }
+        let leaf = attribute_leaf("district_code", &self.district_code, &self.district_salt);
+        verify_merkle_proof(leaf, &self.commitment_proof, commitment_root)
+            && verify_merkle_proof(
+                set_member_leaf(&self.district_code),
+                &self.set_proof,
+                set_root(approved),
+            )
+    }
+}
+
+/// Build a district proof from batch openings, if the district is in the
+/// sorted, deduplicated `approved` set
+pub fn prove_district(
+    sku_id: &str,
+    openings: &BatchOpenings,
+    district_set: &str,
+    approved: &[String],
+) -> Option<DistrictProof> {
+    let index = openings
+        .attributes
+        .iter()
+        .position(|(field, _, _)| field == "district_code")?;
+    let (_, district, salt) = &openings.attributes[index];
+
+    let set_index = approved.iter().position(|d| d == district)?;
+    let set_leaves: Vec<FixedBytes<32>> = approved.iter().map(|d| set_member_leaf(d)).collect();
+
+    Some(DistrictProof {
+        sku_id: sku_id.to_string(),
+        batch_hash: hash_string(&openings.batch_id),
+        commitment_root: openings.root,
+        district_code: district.clone(),
+        district_salt: *salt,
+        commitment_proof: merkle_proof(&openings.leaves(), index),
+        district_set: district_set.to_string(),
+        approved_set_root: merkle_root(&set_leaves),
+        set_proof: merkle_proof(&set_leaves, set_index),
+        hash_scheme: openings.hash_scheme,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DistrictProofRequest {
+    pub sku_id: String,
+    /// Name of an approved set in the district set config
+    pub district_set: String,
+}
+
+/// Batch the SKU was packed from according to the chain, with its on-chain
+/// parent hash; `None` if the SKU was never packaged
+async fn sku_batch(
+    state: &AppState,
+    sku_id: &str,
+) -> Result<Option<(FixedBytes<32>, Option<String>)>, ApiError> {
+    let (parent_batch_hash, _, packaged_at) = state
+        .chain()
+        .verify_package_origin(hash_string(sku_id))
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+    if packaged_at == 0 {
+        return Ok(None);
+    }
+    let batch_id = batch_ledger::find_sku(sku_id)
+        .map_err(ApiError::from)?
+        .map(|record| record.batch_id)
+        .filter(|batch_id| hash_string(batch_id) == parent_batch_hash);
+    Ok(Some((parent_batch_hash, batch_id)))
+}
+
+pub async fn district_proof(
+    State(state): State<AppState>,
+    Json(payload): Json<DistrictProofRequest>,
+) -> ApiResult<DistrictProof> {
+    let approved = state.district_sets.get(&payload.district_set)?;
+
+    let (_, batch_id) = sku_batch(&state, &payload.sku_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("SKU {} not found", payload.sku_id)))?;
+    let batch_id = batch_id.ok_or_else(|| ApiError::not_found("No batch records for this SKU"))?;
+
+    let openings = BatchOpenings::load(&batch_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::not_found("Batch has no commitment yet"))?;
+
+    let proof = prove_district(&payload.sku_id, &openings, &payload.district_set, approved)
+        .ok_or_else(|| {
+            ApiError::new(
+                axum::http::StatusCode::UNPROCESSABLE_ENTITY,
+                "SKU does not originate from an approved district",
+            )
+        })?;
+
+    Ok(Json(proof))
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyDistrictProofResponse {
+    pub valid: bool,
+    pub sku_on_chain: bool,
+}
+
+/// Verify a district proof, including the SKU → batch link on chain
+pub async fn verify_district_proof(
+    State(state): State<AppState>,
+    Json(proof): Json<DistrictProof>,
+) -> ApiResult<VerifyDistrictProofResponse> {
+    let approved = state.district_sets.get(&proof.district_set)?;
+
+    let batch = sku_batch(&state, &proof.sku_id).await?;
+    let sku_on_chain = batch
+        .as_ref()
+        .is_some_and(|(parent_batch_hash, _)| *parent_batch_hash == proof.batch_hash);
+    let commitment_root = match batch {
+        Some((_, Some(batch_id))) if sku_on_chain => {
+            published_root(&batch_id).map_err(ApiError::from)?
+        }
+        _ => None,
+    };
+
+    Ok(Json(VerifyDistrictProofResponse {
+        valid: commitment_root.is_some_and(|root| proof.verify(root, approved)),
+        sku_on_chain,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn openings() -> BatchOpenings {
+        let values = [
+            ("farmer_did", "0xabc".to_string()),
+            ("district_code", "PB001".to_string()),
+            ("state_code", "PB".to_string()),
+            ("crop_type", "mustard".to_string()),
+            ("quality_grade", "A".to_string()),
+            ("quantity_kg", "100.0".to_string()),
+        ];
+        commit_attributes("21", &values)
+    }
+
+    fn approved() -> Vec<String> {
+        vec![
+            "HR002".to_string(),
+            "PB001".to_string(),
+            "RJ010".to_string(),
+        ]
+    }
+
+    #[test]
+    fn test_district_proof_round_trip() {
+        let openings = openings();
+        let approved = approved();
+
+        let proof = prove_district("SKU-1", &openings, "north", &approved).unwrap();
+        assert!(proof.verify(openings.root, &approved));
+        assert_eq!(proof.batch_hash, hash_string("21"));
+
+        let mut forged = proof.clone();
+        forged.district_code = "HR002".to_string();
+        assert!(!forged.verify(openings.root, &approved));
+    }
+
+    #[test]
+    fn test_proof_roots_are_not_trusted() {
+        // A prover-built tree over an invented district and set
+        let fake = commit_attributes("21", &[("district_code", "MH999".to_string())]);
+        let fake_set = vec!["MH999".to_string()];
+        let proof = prove_district("SKU-1", &fake, "north", &fake_set).unwrap();
+        assert!(proof.verify(fake.root, &fake_set));
+
+        // Rejected against the published root or the server's set
+        assert!(!proof.verify(openings().root, &fake_set));
+        assert!(!proof.verify(fake.root, &approved()));
+    }
+
+    #[test]
+    fn test_unapproved_district_has_no_proof() {
+        let approved = ["HR002".to_string()];
+        assert!(prove_district("SKU-1", &openings(), "north", &approved).is_none());
+    }
+
+    #[test]
+    fn test_district_sets_are_sorted_and_nonempty() {
+        let sets = DistrictSets::from_sets(HashMap::from([(
+            "north".to_string(),
+            vec![
+                "PB001".to_string(),
+                "HR002".to_string(),
+                "PB001".to_string(),
+            ],
+        )]))
+        .unwrap();
+        assert_eq!(sets.get("north").unwrap(), ["HR002", "PB001"]);
+        assert!(sets.get("south").is_err());
+
+        let empty = HashMap::from([("none".to_string(), vec![" ".to_string()])]);
+        assert!(DistrictSets::from_sets(empty).is_err());
+    }
+
+    #[test]
+    fn test_salts_hide_values() {
+        assert_ne!(openings().root, openings().root);
+    }
+}
@@ -0,0 +1,301 @@
+//! Compact encoding of pinned metadata
+//!
+//! With METADATA_PAYLOAD_FORMAT=cbor-gzip, metadata pinned to IPFS (and the
+//! batch folder records pinned with it) is stored as gzip-compressed CBOR
+//! inside a small JSON envelope, so gateways still serve a JSON document:
+//!
+//! ```json
+//! { "format": "cbor+gzip", "version": 1, "payload": "<base64>" }
+//! ```
+//!
+//! Readers call [`decode`], which unwraps envelopes and passes plain JSON
+//! through unchanged, so both formats can coexist in one batch. Hashes are
+//! always computed over the decoded document.
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde_json::{Map, Number, Value};
+use std::io::{Read, Write};
+
+pub const ENVELOPE_FORMAT: &str = "cbor+gzip";
+pub const ENVELOPE_VERSION: u64 = 1;
+
+/// Refuse to inflate payloads beyond this size
+const MAX_DECODED_BYTES: u64 = 64 * 1024 * 1024;
+const MAX_DEPTH: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadFormat {
+    Json,
+    CborGzip,
+}
+
+impl PayloadFormat {
+    /// Format of newly pinned metadata (METADATA_PAYLOAD_FORMAT, default json)
+    pub fn from_env() -> Self {
+        match std::env::var("METADATA_PAYLOAD_FORMAT").as_deref() {
+            Ok("cbor-gzip") => Self::CborGzip,
+            _ => Self::Json,
+        }
+    }
+}
+
+/// Document to store for `value` in `format`
+pub fn encode(value: &Value, format: PayloadFormat) -> Result<Value> {
+    match format {
+        PayloadFormat::Json => Ok(value.clone()),
+        PayloadFormat::CborGzip => {
+            let mut cbor = Vec::new();
+            write_cbor(value, &mut cbor);
+
+            let mut gzip = GzEncoder::new(Vec::new(), Compression::best());
+            gzip.write_all(&cbor)
+                .context("Failed to compress payload")?;
+            let compressed = gzip.finish().context("Failed to compress payload")?;
+
+            Ok(serde_json::json!({
+                "format": ENVELOPE_FORMAT,
+                "version": ENVELOPE_VERSION,
+                "payload": BASE64.encode(compressed),
+            }))
+        }
+    }
+}
+
+/// Original document of a stored `value`, unwrapping a compact envelope
+pub fn decode(value: Value) -> Result<Value> {
+    let Some(format) = value.get("format").and_then(Value::as_str) else {
+        return Ok(value);
+    };
+    if format != ENVELOPE_FORMAT || value.get("payload").is_none() {
+        return Ok(value);
+    }
+    let version = value.get("version").and_then(Value::as_u64);
+    if version != Some(ENVELOPE_VERSION) {
+        anyhow::bail!(
+            "Unsupported {} envelope version {:?}",
+            ENVELOPE_FORMAT,
+            version
+        );
+    }
+
+    let payload = value["payload"]
+        .as_str()
+        .context("Envelope payload is not a string")?;
+    let compressed = BASE64
+        .decode(payload)
+        .context("Envelope payload is not valid base64")?;
+
+    let mut cbor = Vec::new();
+    GzDecoder::new(compressed.as_slice())
+        .take(MAX_DECODED_BYTES + 1)
+        .read_to_end(&mut cbor)
+        .context("Envelope payload is not valid gzip")?;
+    if cbor.len() as u64 > MAX_DECODED_BYTES {
+        anyhow::bail!(
+            "Envelope payload inflates beyond {} bytes",
+            MAX_DECODED_BYTES
+        );
+    }
+
+    let mut reader = CborReader {
+        data: &cbor,
+        pos: 0,
+    };
+    let decoded = reader.value(0)?;
+    if reader.pos != cbor.len() {
+        anyhow::bail!("Trailing bytes after CBOR payload");
+    }
+    Ok(decoded)
+}
+
+// ======================== CBOR ========================
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_NEGATIVE: u8 = 1;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const FALSE: u8 = 0xf4;
+const TRUE: u8 = 0xf5;
+const NULL: u8 = 0xf6;
+const FLOAT64: u8 = 0xfb;
+
+fn write_head(major: u8, arg: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+    if arg < 24 {
+        out.push(major | arg as u8);
+    } else if arg <= u8::MAX as u64 {
+        out.push(major | 24);
+        out.push(arg as u8);
+    } else if arg <= u16::MAX as u64 {
+        out.push(major | 25);
+        out.extend_from_slice(&(arg as u16).to_be_bytes());
+    } else if arg <= u32::MAX as u64 {
+        out.push(major | 26);
+        out.extend_from_slice(&(arg as u32).to_be_bytes());
+    } else {
+        out.push(major | 27);
+        out.extend_from_slice(&arg.to_be_bytes());
+    }
+}
+
+fn write_cbor(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Null => out.push(NULL),
+        Value::Bool(false) => out.push(FALSE),
+        Value::Bool(true) => out.push(TRUE),
+        Value::Number(n) => {
+            if let Some(u) = n.as_u64() {
+                write_head(MAJOR_UNSIGNED, u, out);
+            } else if let Some(i) = n.as_i64() {
+                write_head(MAJOR_NEGATIVE, (-1 - i) as u64, out);
+            } else {
+                out.push(FLOAT64);
+                out.extend_from_slice(&n.as_f64().unwrap_or(0.0).to_be_bytes());
+            }
+        }
+        Value::String(s) => {
+            write_head(MAJOR_TEXT, s.len() as u64, out);
+            out.extend_from_slice(s.as_bytes());
+        }
+        Value::Array(items) => {
+            write_head(MAJOR_ARRAY, items.len() as u64, out);
+            for item in items {
+                write_cbor(item, out);
+            }
+        }
+        Value::Object(map) => {
+            write_head(MAJOR_MAP, map.len() as u64, out);
+            for (key, item) in map {
+                write_head(MAJOR_TEXT, key.len() as u64, out);
+                out.extend_from_slice(key.as_bytes());
+                write_cbor(item, out);
+            }
+        }
+    }
+}
+
+/// Decoder for the CBOR subset written by [`write_cbor`]
+struct CborReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl CborReader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.data.len())
+            .context("Truncated CBOR payload")?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn arg(&mut self, info: u8) -> Result<u64> {
+        Ok(match info {
+            0..=23 => info as u64,
+            24 => self.take(1)?[0] as u64,
+            25 => u16::from_be_bytes(self.take(2)?.try_into()?) as u64,
+            26 => u32::from_be_bytes(self.take(4)?.try_into()?) as u64,
+            27 => u64::from_be_bytes(self.take(8)?.try_into()?),
+            _ => anyhow::bail!("Unsupported CBOR length encoding {}", info),
+        })
+    }
+
+    fn text(&mut self, len: u64) -> Result<String> {
+        let bytes = self.take(usize::try_from(len)?)?;
+        Ok(std::str::from_utf8(bytes)
+            .context("CBOR text is not UTF-8")?
+            .to_string())
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value> {
+        if depth > MAX_DEPTH {
+            anyhow::bail!("CBOR payload nested deeper than {}", MAX_DEPTH);
+        }
+        let initial = self.take(1)?[0];
+        match initial {
+            FALSE => return Ok(Value::Bool(false)),
+            TRUE => return Ok(Value::Bool(true)),
+            NULL => return Ok(Value::Null),
+            FLOAT64 => {
+                let f = f64::from_be_bytes(self.take(8)?.try_into()?);
+                return Number::from_f64(f)
+                    .map(Value::Number)
+                    .context("CBOR float is not finite");
+            }
+            _ => {}
+        }
+
+        let major = initial >> 5;
+        let arg = self.arg(initial & 0x1f)?;
+        // Every item takes at least one byte, which bounds untrusted lengths
+        let capacity = (arg as usize).min(self.data.len() - self.pos);
+        match major {
+            MAJOR_UNSIGNED => Ok(Value::Number(arg.into())),
+            MAJOR_NEGATIVE => {
+                let n = i64::try_from(arg).context("CBOR integer out of range")?;
+                Ok(Value::Number((-1 - n).into()))
+            }
+            MAJOR_TEXT => Ok(Value::String(self.text(arg)?)),
+            MAJOR_ARRAY => {
+                let mut items = Vec::with_capacity(capacity);
+                for _ in 0..arg {
+                    items.push(self.value(depth + 1)?);
+                }
+                Ok(Value::Array(items))
+            }
+            MAJOR_MAP => {
+                let mut map = Map::new();
+                for _ in 0..arg {
+                    let head = self.take(1)?[0];
+                    if head >> 5 != MAJOR_TEXT {
+                        anyhow::bail!("CBOR map key is not text");
+                    }
+                    let len = self.arg(head & 0x1f)?;
+                    let key = self.text(len)?;
+                    map.insert(key, self.value(depth + 1)?);
+                }
+                Ok(Value::Object(map))
+            }
+            _ => anyhow::bail!("Unsupported CBOR item 0x{:02x}", initial),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_compact_round_trip() {
+        let metadata = json!({
+            "batch_id": "BATCH-42",
+            "quantity_kg": 1250.5,
+            "offset": -300,
+            "large": u64::MAX,
+            "min": i64::MIN,
+            "verified": true,
+            "notes": null,
+            "stages": [{"name": "purchase", "lots": [1, 2, 3]}, "स्वीकृत"],
+        });
+
+        let stored = encode(&metadata, PayloadFormat::CborGzip).unwrap();
+        assert_eq!(stored["format"], ENVELOPE_FORMAT);
+        assert_eq!(decode(stored).unwrap(), metadata);
+    }
+
+    #[test]
+    fn test_plain_json_passes_through() {
+        let plain = json!({"format": "pdf", "pages": 3});
+        assert_eq!(decode(plain.clone()).unwrap(), plain);
+
+        let future = json!({"format": ENVELOPE_FORMAT, "version": 2, "payload": ""});
+        assert!(decode(future).is_err());
+    }
+}
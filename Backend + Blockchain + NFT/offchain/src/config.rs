@@ -1,3 +1,4 @@
+use anyhow::Context;
 use std::env;
 
 #[derive(Debug, Clone)]
@@ -33,7 +34,8 @@ impl Config {
     pub fn from_env() -> anyhow::Result<Self> {
         let port = env::var("PORT")
             .unwrap_or_else(|_| "3000".to_string())
-            .parse::<u16>()?;
+            .parse::<u16>()
+            .context("PORT must be a number between 0 and 65535")?;
 
         let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
 
@@ -0,0 +1,169 @@
+//! Confirmation depth of writes
+//!
+//! A receipt only says the transaction is in some block, which a reorg can
+//! still drop. With CONFIRMATIONS set above 1, writes wait until that many
+//! blocks (the inclusion block counts as one) are on top of the transaction
+//! before the API responds, re-reading the receipt on every poll so a
+//! transaction moved by a reorg is followed to its new block. Waiting gives
+//! up after CONFIRMATION_TIMEOUT_SECS (default 300).
+//!
+//! With CONFIRMATION_WAIT=false, writes respond as soon as they are mined
+//! and clients poll `GET /api/chain/tx/:tx_hash` until it reports
+//! `confirmed`.
+
+use crate::error::{format_tx_hash, ApiError, ApiResult};
+use crate::networks::EnvVars;
+use crate::state::AppState;
+use alloy::primitives::FixedBytes;
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Serialize;
+use std::time::Duration;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 300;
+/// Delay between block number polls while waiting
+pub const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+pub struct ConfirmationPolicy {
+    /// Blocks including the inclusion block, at least 1
+    pub confirmations: u64,
+    /// Hold responses until `confirmations` is reached
+    pub wait: bool,
+    pub timeout: Duration,
+}
+
+impl ConfirmationPolicy {
+    pub fn from_env(vars: &EnvVars) -> Result<Self> {
+        let confirmations = match vars.non_empty("CONFIRMATIONS") {
+            Some(value) => value
+                .trim()
+                .parse()
+                .context("CONFIRMATIONS must be a whole number of blocks")?,
+            _ => 1,
+        };
+        if confirmations == 0 {
+            bail!("CONFIRMATIONS must be at least 1");
+        }
+        let timeout_secs = vars
+            .get("CONFIRMATION_TIMEOUT_SECS")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+
+        Ok(Self {
+            confirmations,
+            wait: vars
+                .get("CONFIRMATION_WAIT")
+                .map(|v| !matches!(v.to_lowercase().as_str(), "false" | "0" | "off"))
+                .unwrap_or(true),
+            timeout: Duration::from_secs(timeout_secs),
+        })
+    }
+
+    /// Whether writes have to wait after the receipt arrives
+    pub fn waits(&self) -> bool {
+        self.wait && self.confirmations > 1
+    }
+}
+
+/// Blocks on top of and including `block`, given the latest block number
+pub fn confirmations_at(block: u64, latest: u64) -> u64 {
+    if latest < block {
+        0
+    } else {
+        latest - block + 1
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TxConfirmationStatus {
+    /// No receipt: not mined yet, or dropped by a reorg
+    Pending,
+    /// Mined, fewer than the required confirmations
+    Mined,
+    Confirmed,
+    /// Mined but reverted
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TxConfirmationView {
+    pub tx_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
+    pub status: TxConfirmationStatus,
+    pub block_number: Option<u64>,
+    pub confirmations: u64,
+    pub required_confirmations: u64,
+}
+
+/// Confirmation depth of a transaction
+pub async fn tx_status(
+    State(state): State<AppState>,
+    Path(tx_hash): Path<String>,
+) -> ApiResult<TxConfirmationView> {
+    let hash: FixedBytes<32> = tx_hash
+        .parse()
+        .map_err(|e| ApiError::invalid_hash("tx_hash", e))?;
+    let chain = state.chain();
+    let required = chain.confirmation_policy().confirmations;
+
+    let receipt = chain
+        .transaction_receipt(hash)
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+    let Some(receipt) = receipt else {
+        return Ok(Json(TxConfirmationView {
+            tx_hash: format_tx_hash(hash),
+            explorer_url: chain.explorer_url(hash),
+            status: TxConfirmationStatus::Pending,
+            block_number: None,
+            confirmations: 0,
+            required_confirmations: required,
+        }));
+    };
+
+    let latest = chain
+        .latest_block()
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+    let confirmations = receipt
+        .block_number
+        .map(|block| confirmations_at(block, latest))
+        .unwrap_or(0);
+    let status = if !receipt.status() {
+        TxConfirmationStatus::Failed
+    } else if confirmations >= required {
+        TxConfirmationStatus::Confirmed
+    } else {
+        TxConfirmationStatus::Mined
+    };
+
+    Ok(Json(TxConfirmationView {
+        tx_hash: format_tx_hash(hash),
+        explorer_url: chain.explorer_url(hash),
+        status,
+        block_number: receipt.block_number,
+        confirmations,
+        required_confirmations: required,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inclusion_block_counts_as_one() {
+        assert_eq!(confirmations_at(100, 100), 1);
+        assert_eq!(confirmations_at(100, 111), 12);
+        // Node behind the block the receipt came from
+        assert_eq!(confirmations_at(100, 99), 0);
+    }
+}
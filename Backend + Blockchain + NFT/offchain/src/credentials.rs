@@ -0,0 +1,504 @@
+//! Verifiable Credentials for verified farmers
+//!
+//! Buyers and lenders want an attestation they can carry away and check
+//! without our database. `POST /api/credentials/issue` lets an FPO attest
+//! that a registered farmer was verified by it on a given date; the result
+//! is a W3C Verifiable Credential (data model 1.1) in JWT form:
+//!
+//! - header `{"alg": "ES256K", "typ": "JWT", "kid": "<issuer>#controller"}`
+//! - claims `iss` (issuer DID), `sub` (farmer), `nbf`, `jti` and `vc`, the
+//!   credential of type `FarmerVerificationCredential` whose subject carries
+//!   `verifiedBy` (the FPO) and `verifiedOn` (the date)
+//!
+//! The issuer is `did:ethr:<address>` of CREDENTIAL_SIGNING_KEY (a
+//! secp256k1 private key); issuance is disabled without it. The JWT and
+//! the decoded credential are pinned to IPFS together and indexed in
+//! `data/credentials.json`.
+//!
+//! The subject is the farmer's DID when it is one (see
+//! [`crate::did_resolver`]) and `urn:farmer:0x<hash>` for a registry hash.
+//!
+//! `POST /api/credentials/verify` takes a JWT or the CID of a pinned
+//! credential. The signer's address is recovered from the signature and
+//! must be the issuer's did:ethr address, so credentials of other did:ethr
+//! issuers verify too; `trusted_issuer` tells whether it is ours.
+
+use crate::auth::{Principal, Role};
+use crate::did_resolver::{self, FarmerDid};
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+use alloy::primitives::Address;
+use alloy::signers::k256::ecdsa::{
+    signature::hazmat::PrehashSigner, RecoveryId, Signature, SigningKey, VerifyingKey,
+};
+use alloy::signers::local::PrivateKeySigner;
+use anyhow::{Context, Result};
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL, Engine};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+const CREDENTIALS_PATH: &str = "data/credentials.json";
+const CREDENTIALS_CONTEXT: &str = "https://www.w3.org/2018/credentials/v1";
+pub const CREDENTIAL_TYPE: &str = "FarmerVerificationCredential";
+const ALGORITHM: &str = "ES256K";
+
+/// Credential subject id of a farmer
+fn subject_id(farmer: &FarmerDid) -> String {
+    match farmer {
+        FarmerDid::Hash(_) => format!("urn:farmer:{}", farmer),
+        did => did.to_string(),
+    }
+}
+
+// ======================== SIGNING ========================
+
+pub struct CredentialIssuer {
+    key: SigningKey,
+    did: String,
+}
+
+impl CredentialIssuer {
+    fn new(signer: &PrivateKeySigner) -> Self {
+        Self {
+            key: signer.credential().clone(),
+            did: format!("did:ethr:{:#x}", signer.address()),
+        }
+    }
+
+    /// JWT of a credential that `fpo` verified `farmer` on `verified_on`
+    fn issue(
+        &self,
+        id: &str,
+        farmer: &FarmerDid,
+        fpo: &str,
+        verified_on: NaiveDate,
+        now: DateTime<Utc>,
+    ) -> Result<(String, Value)> {
+        let subject = subject_id(farmer);
+        let credential = json!({
+            "@context": [CREDENTIALS_CONTEXT],
+            "id": id,
+            "type": ["VerifiableCredential", CREDENTIAL_TYPE],
+            "issuer": self.did,
+            "issuanceDate": now.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            "credentialSubject": {
+                "id": subject,
+                "verifiedBy": fpo,
+                "verifiedOn": verified_on.to_string(),
+            },
+        });
+        let header = json!({
+            "alg": ALGORITHM,
+            "typ": "JWT",
+            "kid": format!("{}#controller", self.did),
+        });
+        let claims = json!({
+            "iss": self.did,
+            "sub": subject,
+            "nbf": now.timestamp(),
+            "jti": id,
+            "vc": credential,
+        });
+        let signing_input = format!(
+            "{}.{}",
+            BASE64URL.encode(header.to_string()),
+            BASE64URL.encode(claims.to_string())
+        );
+        let signature: Signature = self
+            .key
+            .sign_prehash(&Sha256::digest(signing_input.as_bytes()))
+            .context("Failed to sign credential")?;
+        Ok((
+            format!(
+                "{}.{}",
+                signing_input,
+                BASE64URL.encode(signature.to_bytes())
+            ),
+            credential,
+        ))
+    }
+}
+
+/// Outcome of checking a credential JWT
+#[derive(Debug, Serialize)]
+pub struct CredentialCheck {
+    pub valid: bool,
+    /// Issued by this deployment's key
+    pub trusted_issuer: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credential: Option<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+fn decode_segment(segment: &str, name: &str) -> Result<Value, String> {
+    let bytes = BASE64URL
+        .decode(segment)
+        .map_err(|_| format!("{} is not base64url", name))?;
+    serde_json::from_slice(&bytes).map_err(|_| format!("{} is not JSON", name))
+}
+
+/// Check the signature and claims of a credential JWT; `ours` is this
+/// deployment's issuer DID
+pub fn verify_jwt(jwt: &str, ours: Option<&str>, now: DateTime<Utc>) -> CredentialCheck {
+    let mut check = CredentialCheck {
+        valid: false,
+        trusted_issuer: false,
+        issuer: None,
+        credential: None,
+        errors: Vec::new(),
+    };
+    let parts: Vec<&str> = jwt.trim().split('.').collect();
+    let [header_segment, claims_segment, signature] = parts[..] else {
+        check.errors.push("not a JWT".to_string());
+        return check;
+    };
+    let (header, claims) = match (
+        decode_segment(header_segment, "header"),
+        decode_segment(claims_segment, "claims"),
+    ) {
+        (Ok(header), Ok(claims)) => (header, claims),
+        (header, claims) => {
+            check.errors.extend(header.err());
+            check.errors.extend(claims.err());
+            return check;
+        }
+    };
+    if header["alg"] != ALGORITHM {
+        check.errors.push(format!("alg must be {}", ALGORITHM));
+    }
+
+    let issuer = claims["iss"].as_str().unwrap_or_default().to_string();
+    let issuer_address = match issuer.parse::<FarmerDid>() {
+        Ok(FarmerDid::Ethr { address, .. }) => Some(address),
+        _ => {
+            check.errors.push("iss must be a did:ethr".to_string());
+            None
+        }
+    };
+    if let Some(address) = issuer_address {
+        if !signature_matches(header_segment, claims_segment, signature, address) {
+            check
+                .errors
+                .push("signature was not made by the issuer".to_string());
+        }
+    }
+
+    let credential = &claims["vc"];
+    let types = credential["type"].as_array().cloned().unwrap_or_default();
+    if !types.iter().any(|t| t == CREDENTIAL_TYPE) {
+        check
+            .errors
+            .push(format!("credential is not a {}", CREDENTIAL_TYPE));
+    }
+    if claims["sub"] != credential["credentialSubject"]["id"] {
+        check
+            .errors
+            .push("sub does not match the credential subject".to_string());
+    }
+    if claims["nbf"]
+        .as_i64()
+        .is_some_and(|nbf| nbf > now.timestamp())
+    {
+        check.errors.push("credential is not valid yet".to_string());
+    }
+
+    check.valid = check.errors.is_empty();
+    check.trusted_issuer = check.valid && ours == Some(issuer.as_str());
+    check.issuer = Some(issuer);
+    check.credential = Some(credential.clone());
+    check
+}
+
+/// Whether the ES256K `signature` was made by `issuer`'s key. r and s leave
+/// two candidate public keys; either may be the issuer's.
+fn signature_matches(header: &str, claims: &str, signature: &str, issuer: Address) -> bool {
+    let Some(signature) = BASE64URL
+        .decode(signature)
+        .ok()
+        .and_then(|sig| Signature::from_slice(&sig).ok())
+    else {
+        return false;
+    };
+    let prehash = Sha256::digest(format!("{}.{}", header, claims).as_bytes());
+    (0..2)
+        .filter_map(RecoveryId::from_byte)
+        .filter_map(|id| VerifyingKey::recover_from_prehash(&prehash, &signature, id).ok())
+        .any(|key| Address::from_public_key(&key) == issuer)
+}
+
+// ======================== STORE ========================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CredentialRecord {
+    /// `urn:uuid:` style identifier, the JWT's `jti`
+    pub id: String,
+    pub farmer_did: String,
+    pub fpo: String,
+    pub verified_on: String,
+    pub issuer: String,
+    pub credential_cid: String,
+    pub issued_at: String,
+}
+
+pub struct CredentialStore {
+    records: Mutex<Vec<CredentialRecord>>,
+    issuer: Option<CredentialIssuer>,
+}
+
+impl CredentialStore {
+    pub fn load() -> Result<Self> {
+        let records = match std::fs::read_to_string(CREDENTIALS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid JSON in {}", CREDENTIALS_PATH))?,
+            Err(_) => Vec::new(),
+        };
+        let issuer = match std::env::var("CREDENTIAL_SIGNING_KEY")
+            .ok()
+            .filter(|k| !k.is_empty())
+        {
+            Some(key) => {
+                let signer = key
+                    .trim()
+                    .parse::<PrivateKeySigner>()
+                    .context("CREDENTIAL_SIGNING_KEY is not a valid secp256k1 key")?;
+                let issuer = CredentialIssuer::new(&signer);
+                tracing::info!(issuer = %issuer.did, "Issuing farmer credentials");
+                Some(issuer)
+            }
+            None => None,
+        };
+        Ok(Self {
+            records: Mutex::new(records),
+            issuer,
+        })
+    }
+
+    fn save(records: &[CredentialRecord]) -> Result<()> {
+        std::fs::write(CREDENTIALS_PATH, serde_json::to_string_pretty(records)?)
+            .with_context(|| format!("Failed to write {}", CREDENTIALS_PATH))
+    }
+
+    fn issuer_did(&self) -> Option<&str> {
+        self.issuer.as_ref().map(|i| i.did.as_str())
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct IssueCredentialRequest {
+    pub farmer_did: String,
+    /// Day of the verification (YYYY-MM-DD), today by default
+    #[serde(default)]
+    pub verified_on: Option<String>,
+    /// Only read for admin callers; FPO accounts attest as themselves
+    #[serde(default)]
+    pub fpo: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IssueCredentialResponse {
+    #[serde(flatten)]
+    pub record: CredentialRecord,
+    pub jwt: String,
+    pub credential: Value,
+}
+
+fn attesting_fpo(principal: Option<&Principal>, named: Option<&str>) -> Result<String, ApiError> {
+    match principal {
+        Some(Principal::User(claims)) if claims.role == Role::Fpo => Ok(claims.sub.clone()),
+        Some(Principal::ApiKey { name, .. }) => Ok(name.clone()),
+        _ => named
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(str::to_string)
+            .ok_or_else(|| ApiError::bad_request("fpo is required")),
+    }
+}
+
+/// Issue and pin a credential attesting a farmer's verification
+pub async fn issue_credential(
+    State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
+    Json(payload): Json<IssueCredentialRequest>,
+) -> ApiResult<IssueCredentialResponse> {
+    let store = &state.credentials;
+    let issuer = store.issuer.as_ref().ok_or_else(|| {
+        ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Credential issuance is not configured (CREDENTIAL_SIGNING_KEY)",
+        )
+    })?;
+    let principal = principal.map(|Extension(p)| p);
+    let fpo = attesting_fpo(principal.as_ref(), payload.fpo.as_deref())?;
+
+    let farmer_did = did_resolver::canonical(&payload.farmer_did).map_err(ApiError::bad_request)?;
+    let farmer = state
+        .farmer_verification
+        .get_farmer_by_did(&farmer_did)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Farmer {} is not registered", farmer_did)))?;
+    if !farmer.verified {
+        return Err(ApiError::bad_request(format!(
+            "Farmer {} has not been verified",
+            farmer_did
+        )));
+    }
+    let now = Utc::now();
+    let verified_on = match payload.verified_on.as_deref() {
+        Some(day) => NaiveDate::parse_from_str(day, "%Y-%m-%d")
+            .map_err(|_| ApiError::bad_request("verified_on must be YYYY-MM-DD"))?,
+        None => now.date_naive(),
+    };
+    if verified_on > now.date_naive() {
+        return Err(ApiError::bad_request("verified_on lies in the future"));
+    }
+
+    let id = format!("urn:uuid:{}", uuid_v4());
+    let subject = farmer_did
+        .parse::<FarmerDid>()
+        .map_err(ApiError::bad_request)?;
+    let (jwt, credential) = issuer.issue(&id, &subject, &fpo, verified_on, now)?;
+    let credential_cid = state
+        .ipfs_client
+        .upload_json(&json!({
+            "type": "verifiable_credential",
+            "format": "jwt_vc",
+            "jwt": jwt,
+            "credential": credential,
+        }))
+        .await
+        .map_err(ApiError::ipfs_upload_failed)?;
+
+    let record = CredentialRecord {
+        id,
+        farmer_did,
+        fpo,
+        verified_on: verified_on.to_string(),
+        issuer: issuer.did.clone(),
+        credential_cid,
+        issued_at: now.to_rfc3339(),
+    };
+    let mut records = store.records.lock().await;
+    records.push(record.clone());
+    CredentialStore::save(&records)?;
+    tracing::info!(
+        credential = %record.id,
+        farmer_did = %record.farmer_did,
+        fpo = %record.fpo,
+        cid = %record.credential_cid,
+        "Issued farmer credential"
+    );
+
+    Ok(Json(IssueCredentialResponse {
+        record,
+        jwt,
+        credential,
+    }))
+}
+
+/// Random version 4 UUID
+fn uuid_v4() -> String {
+    let mut bytes = rand::random::<[u8; 16]>();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex::encode(bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyCredentialRequest {
+    #[serde(default)]
+    pub jwt: Option<String>,
+    /// CID of a credential pinned by [`issue_credential`]
+    #[serde(default)]
+    pub cid: Option<String>,
+}
+
+/// Check a credential given as JWT or by CID
+pub async fn verify_credential(
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyCredentialRequest>,
+) -> ApiResult<CredentialCheck> {
+    let jwt = match (payload.jwt, payload.cid) {
+        (Some(jwt), _) => jwt,
+        (None, Some(cid)) => {
+            let document = state
+                .ipfs_client
+                .fetch_json(&cid)
+                .await
+                .map_err(|e| ApiError::bad_request(format!("Cannot fetch {}: {:#}", cid, e)))?;
+            document["jwt"]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| ApiError::bad_request(format!("{} is not a credential", cid)))?
+        }
+        (None, None) => return Err(ApiError::bad_request("jwt or cid is required")),
+    };
+    Ok(Json(verify_jwt(
+        &jwt,
+        state.credentials.issuer_did(),
+        Utc::now(),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn farmer() -> FarmerDid {
+        format!("0x{}", "ab".repeat(32)).parse().unwrap()
+    }
+
+    #[test]
+    fn test_issued_credential_verifies_and_tampering_fails() {
+        let issuer = CredentialIssuer::new(&PrivateKeySigner::random());
+        let now = Utc::now();
+        let on = NaiveDate::from_ymd_opt(2025, 6, 2).unwrap();
+        let (jwt, credential) = issuer
+            .issue("urn:uuid:1", &farmer(), "fpo-nashik", on, now)
+            .unwrap();
+        assert_eq!(credential["credentialSubject"]["verifiedOn"], "2025-06-02");
+
+        let check = verify_jwt(&jwt, Some(&issuer.did), now);
+        assert!(check.valid, "{:?}", check.errors);
+        assert!(check.trusted_issuer);
+        assert_eq!(
+            check.credential.unwrap()["credentialSubject"]["id"],
+            format!("urn:farmer:0x{}", "ab".repeat(32))
+        );
+
+        // Another issuer's credential verifies, but is not ours
+        let other = CredentialIssuer::new(&PrivateKeySigner::random());
+        let check = verify_jwt(&jwt, Some(&other.did), now);
+        assert!(check.valid && !check.trusted_issuer);
+
+        let parts: Vec<&str> = jwt.split('.').collect();
+        let mut claims: Value =
+            serde_json::from_slice(&BASE64URL.decode(parts[1]).unwrap()).unwrap();
+        claims["vc"]["credentialSubject"]["verifiedBy"] = json!("fpo-other");
+        let forged = format!(
+            "{}.{}.{}",
+            parts[0],
+            BASE64URL.encode(claims.to_string()),
+            parts[2]
+        );
+        let check = verify_jwt(&forged, Some(&issuer.did), now);
+        assert!(!check.valid);
+        assert_eq!(check.errors, ["signature was not made by the issuer"]);
+    }
+}
@@ -0,0 +1,282 @@
+//! On-disk data format migrations
+//!
+//! JSON stores carry a top-level `format_version`; a file without one is
+//! version 0, the shape it had before versions were recorded. At startup,
+//! before any store is read, [`run`] upgrades every file in [`STORES`] step by
+//! step to the version this build reads. The original is first copied to
+//! `<file>.pre-migrate-v<N>-<timestamp>`, so a failed upgrade can be undone by
+//! hand. A file written by a newer build stops the start instead of being
+//! rewritten.
+//!
+//! To change a store's shape, append a step to its [`Store`]: step `i` turns
+//! version `i` into `i + 1`, and the store's loader writes
+//! [`Store::current_version`].
+
+use crate::did;
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Upgrades one version of a store's JSON to the next
+pub type Step = fn(&mut Map<String, Value>) -> Result<()>;
+
+/// A versioned JSON file
+pub struct Store {
+    pub path: &'static str,
+    pub steps: &'static [Step],
+}
+
+impl Store {
+    /// Version this build reads and writes
+    pub const fn current_version(&self) -> u32 {
+        self.steps.len() as u32
+    }
+}
+
+/// Legacy farmer registry, imported into SQLite on first start
+pub const FARMERS_DB: Store = Store {
+    path: crate::farmer_verification::LEGACY_JSON_PATH,
+    steps: &[farmers_v1],
+};
+
+/// Local ledger of FPO purchase blocks
+pub const LOCAL_LEDGER: Store = Store {
+    path: crate::local_blockchain::LocalBlockchain::BLOCKCHAIN_FILE,
+    steps: &[ledger_v1],
+};
+
+pub const STORES: &[&Store] = &[&FARMERS_DB, &LOCAL_LEDGER];
+
+/// Result of upgrading one file
+#[derive(Debug)]
+pub struct Migrated {
+    pub from: u32,
+    pub to: u32,
+    pub backup: PathBuf,
+}
+
+/// Upgrade every known store in place
+pub fn run() -> Result<()> {
+    for store in STORES {
+        if let Some(migrated) = migrate_file(store, Path::new(store.path))? {
+            tracing::warn!(
+                file = store.path,
+                from = migrated.from,
+                to = migrated.to,
+                backup = %migrated.backup.display(),
+                "Upgraded on-disk data format"
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Upgrade `path` to the store's current version; `None` if it is missing or
+/// already current
+pub fn migrate_file(store: &Store, path: &Path) -> Result<Option<Migrated>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut value: Value = serde_json::from_str(&content)
+        .with_context(|| format!("{} is not valid JSON", path.display()))?;
+    let Some(object) = value.as_object_mut() else {
+        bail!("{} does not hold a JSON object", path.display());
+    };
+
+    let from = match object.get("format_version") {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .with_context(|| format!("{} has an invalid format_version", path.display()))?,
+    };
+    let to = store.current_version();
+    if from == to {
+        return Ok(None);
+    }
+    if from > to {
+        bail!(
+            "{} has format version {}, but this build only reads up to {}; \
+             upgrade the server or restore an older copy",
+            path.display(),
+            from,
+            to
+        );
+    }
+
+    for (version, step) in store.steps.iter().enumerate().skip(from as usize) {
+        step(object).with_context(|| {
+            format!(
+                "Failed to upgrade {} from format version {}",
+                path.display(),
+                version
+            )
+        })?;
+    }
+    object.insert("format_version".into(), to.into());
+
+    let backup = PathBuf::from(format!(
+        "{}.pre-migrate-v{}-{}",
+        path.display(),
+        from,
+        chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+    ));
+    fs::copy(path, &backup)
+        .with_context(|| format!("Failed to back up {} before upgrading", path.display()))?;
+    fs::write(path, serde_json::to_string_pretty(&value)?)
+        .with_context(|| format!("Failed to write upgraded {}", path.display()))?;
+
+    Ok(Some(Migrated { from, to, backup }))
+}
+
+/// Fill `key` with `default` when an object lacks it
+fn fill(object: &mut Map<String, Value>, key: &str, default: Value) {
+    object.entry(key).or_insert(default);
+}
+
+/// Farmer entries and metadata get every field the registry now requires;
+/// a missing DID is derived from the mobile number
+fn farmers_v1(db: &mut Map<String, Value>) -> Result<()> {
+    let farmers = db
+        .entry("farmers")
+        .or_insert_with(|| json!([]))
+        .as_array_mut()
+        .context("farmers is not an array")?;
+    for (index, farmer) in farmers.iter_mut().enumerate() {
+        let farmer = farmer
+            .as_object_mut()
+            .with_context(|| format!("farmers[{}] is not an object", index))?;
+        let mobile = farmer
+            .get("mobile")
+            .and_then(Value::as_str)
+            .with_context(|| format!("farmers[{}] has no mobile number", index))?
+            .to_string();
+        fill(farmer, "farmer_did", did::farmer_did(&mobile).into());
+        for key in [
+            "name",
+            "location",
+            "state_code",
+            "district_code",
+            "crop",
+            "registration_date",
+            "ipfscid",
+        ] {
+            fill(farmer, key, "".into());
+        }
+        fill(farmer, "land_acres", 0.0.into());
+        fill(farmer, "verified", false.into());
+    }
+    let total = farmers.len();
+
+    let metadata = db
+        .entry("metadata")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .context("metadata is not an object")?;
+    fill(metadata, "version", "1.0".into());
+    fill(
+        metadata,
+        "last_updated",
+        chrono::Utc::now().to_rfc3339().into(),
+    );
+    fill(metadata, "total_farmers", total.into());
+    fill(
+        metadata,
+        "description",
+        "Farmer verification database".into(),
+    );
+    Ok(())
+}
+
+/// Blocks get their optional fields, and a missing batch index is rebuilt
+/// from the blocks in order
+fn ledger_v1(ledger: &mut Map<String, Value>) -> Result<()> {
+    let blocks = ledger
+        .entry("blocks")
+        .or_insert_with(|| json!([]))
+        .as_array_mut()
+        .context("blocks is not an array")?;
+    let mut history = Map::new();
+    for (index, block) in blocks.iter_mut().enumerate() {
+        let block = block
+            .as_object_mut()
+            .with_context(|| format!("blocks[{}] is not an object", index))?;
+        fill(block, "previous_block_hash", Value::Null);
+        fill(block, "transaction_data", json!({}));
+        fill(block, "version", 1.into());
+        if let (Some(batch_id), Some(hash)) = (block.get("batch_id"), block.get("block_hash")) {
+            let batch_id = batch_id.as_str().unwrap_or_default().to_string();
+            let hashes = history.entry(batch_id).or_insert_with(|| json!([]));
+            if let Some(hashes) = hashes.as_array_mut() {
+                hashes.push(hash.clone());
+            }
+        }
+    }
+    fill(ledger, "batch_history", Value::Object(history));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str, content: &Value) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ovc-migrate-test-{}", rand::random::<u64>()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, content.to_string()).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_unversioned_ledger_is_upgraded_with_backup() {
+        let path = temp_file(
+            "local_blockchain.json",
+            &json!({
+                "blocks": [
+                    {"block_id": "b1", "timestamp": "t", "transaction_type": "fpo_purchase",
+                     "batch_id": "BATCH-1", "farmer_did": "0x1", "block_hash": "h1"},
+                ]
+            }),
+        );
+
+        let migrated = migrate_file(&LOCAL_LEDGER, &path).unwrap().unwrap();
+        assert_eq!((migrated.from, migrated.to), (0, 1));
+        assert!(migrated.backup.exists());
+
+        let ledger: crate::local_blockchain::LocalBlockchain =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(ledger.format_version, LOCAL_LEDGER.current_version());
+        assert_eq!(ledger.blocks[0].version, 1);
+        assert_eq!(ledger.batch_history["BATCH-1"], vec!["h1".to_string()]);
+
+        // Already current: left alone
+        assert!(migrate_file(&LOCAL_LEDGER, &path).unwrap().is_none());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_farmers_db_gets_missing_fields_and_newer_versions_are_refused() {
+        let path = temp_file(
+            "farmers_db.json",
+            &json!({"farmers": [{"mobile": "9876543210", "name": "Rajesh Kumar"}]}),
+        );
+
+        migrate_file(&FARMERS_DB, &path).unwrap().unwrap();
+        let db: crate::farmer_verification::FarmerDatabase =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(db.farmers[0].farmer_did, did::farmer_did("9876543210"));
+        assert_eq!(db.metadata.total_farmers, 1);
+
+        fs::write(
+            &path,
+            json!({"format_version": 99, "farmers": []}).to_string(),
+        )
+        .unwrap();
+        assert!(migrate_file(&FARMERS_DB, &path).is_err());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,289 @@
+//! Graceful degradation per endpoint
+//!
+//! What endpoints do while a dependency is down (unreachable since the
+//! start, see [`crate::readiness`], or failing its calls):
+//!
+//! - Verification of SKUs, units and farmers (`/api/packaging/verify`,
+//!   `/api/packaging/verify/bulk`, `/api/packaging/verify-unit`,
+//!   `/verify/farmer`, `/api/farmer/verify`): a chain read that fails is
+//!   answered from the local event index, for the default network. A SKU or
+//!   farmer not indexed yet is still an error.
+//! - Contract writes are saved to the outbox and answered 202 with code
+//!   `write_queued` (see [`crate::outbox::Queued`]). Their metadata pin comes
+//!   first and fails with 503 while Pinata has not come up once.
+//! - Batch timeline and warehouse state: the on-chain part is returned, with
+//!   the IPFS metadata that could not be fetched listed in `warnings`.
+//! - Local endpoints (farmer registry, ledger, auth, admin) depend on neither.
+//!
+//! A response served in a degraded way names how in `X-Degraded`, e.g.
+//! `X-Degraded: local-index, ipfs-partial`. `GET /health` reports the
+//! dependencies that are down and answers 200 as long as the server runs.
+
+use crate::chain::{ChainClient, PackageOrigin};
+use crate::error::format_hash;
+use crate::indexer::{EventFilter, IndexedEvent};
+use crate::revert::ContractRevert;
+use crate::state::AppState;
+use alloy::primitives::FixedBytes;
+use anyhow::Result;
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+pub const DEGRADED_HEADER: HeaderName = HeaderName::from_static("x-degraded");
+
+/// Answered from the local event index instead of the chain
+pub const LOCAL_INDEX: &str = "local-index";
+/// A contract write was saved to the outbox
+pub const WRITE_QUEUED: &str = "write-queued";
+/// Some IPFS content could not be fetched
+pub const IPFS_PARTIAL: &str = "ipfs-partial";
+/// Reported by `/health` for each dependency that is down
+pub const CHAIN_DOWN: &str = "chain-down";
+pub const IPFS_DOWN: &str = "ipfs-down";
+
+/// Degradations of the request being served
+pub type Notes = Arc<Mutex<BTreeSet<&'static str>>>;
+
+/// Events scanned for a SKU or farmer in the local index
+const MAX_INDEXED_EVENTS: usize = 100;
+
+tokio::task_local! {
+    static NOTES: Notes;
+}
+
+/// Record that the current request is served degraded; a no-op outside one
+pub fn note(mode: &'static str) {
+    let _ = NOTES.try_with(|notes| notes.lock().unwrap_or_else(|e| e.into_inner()).insert(mode));
+}
+
+/// Notes of the current request, to carry into work done elsewhere
+pub fn current() -> Option<Notes> {
+    NOTES.try_with(Notes::clone).ok()
+}
+
+/// Run `future` noting into the request it was started by
+pub async fn run_with<F: Future>(notes: Option<Notes>, future: F) -> F::Output {
+    match notes {
+        Some(notes) => NOTES.scope(notes, future).await,
+        None => future.await,
+    }
+}
+
+/// Middleware reporting a request's degradations in `X-Degraded`
+pub async fn report_degradation(request: Request, next: Next) -> Response {
+    let notes = Notes::default();
+    let mut response = NOTES.scope(notes.clone(), next.run(request)).await;
+    let notes = notes.lock().unwrap_or_else(|e| e.into_inner());
+    if !notes.is_empty() {
+        let modes: Vec<&str> = notes.iter().copied().collect();
+        if let Ok(value) = HeaderValue::from_str(&modes.join(", ")) {
+            response.headers_mut().insert(DEGRADED_HEADER, value);
+        }
+    }
+    response
+}
+
+// ======================== LOCAL INDEX FALLBACKS ========================
+
+/// Whether the index can stand in for `chain`: it follows the default network
+fn indexes(state: &AppState, chain: &ChainClient) -> bool {
+    chain.network() == state.chain().network()
+}
+
+/// Packaging of a SKU from its indexed `SKUPackaged` event
+pub fn indexed_origin(events: &[IndexedEvent]) -> Option<PackageOrigin> {
+    let packaged = events.iter().find(|e| e.event == "SKUPackaged")?;
+    let parent = packaged.batch_hashes.first()?.parse().ok()?;
+    let root = packaged.fields["merkle_root"].as_str()?.parse().ok()?;
+    Some((parent, root, packaged.timestamp))
+}
+
+/// `(exists, crop hash, registered at)` from a farmer's indexed events; the
+/// crop hash follows the latest `FarmerUpdated`
+pub fn indexed_registration(events: &[IndexedEvent]) -> Option<(bool, FixedBytes<32>, u64)> {
+    let registered = events.iter().find(|e| e.event == "FarmerRegistered")?;
+    let latest = events
+        .iter()
+        .rfind(|e| e.event == "FarmerRegistered" || e.event == "FarmerUpdated")?;
+    let crop = latest.fields["crop_id_hash"].as_str()?.parse().ok()?;
+    Some((true, crop, registered.timestamp))
+}
+
+/// Look `id` up in the index after `chain` failed with `e`; `e` is returned
+/// if the index cannot answer
+async fn from_index<T>(
+    state: &AppState,
+    chain: &ChainClient,
+    id: FixedBytes<32>,
+    e: anyhow::Error,
+    pick: fn(&[IndexedEvent]) -> Option<T>,
+) -> Result<T> {
+    // A revert is an answer from the chain, not an outage
+    if ContractRevert::from_error(&e).is_some() || !indexes(state, chain) {
+        return Err(e);
+    }
+    chain.readiness().record_down(&e);
+    let events = match state
+        .events
+        .query(
+            &EventFilter::Subject(format_hash(id)),
+            None,
+            MAX_INDEXED_EVENTS,
+        )
+        .await
+    {
+        Ok(events) => events,
+        Err(index_error) => {
+            tracing::warn!(error = %format!("{:#}", index_error), "Local index lookup failed");
+            return Err(e);
+        }
+    };
+    match pick(&events) {
+        Some(found) => {
+            tracing::warn!(
+                subject = %format_hash(id),
+                error = %format!("{:#}", e),
+                "Chain read failed, answered from the local index"
+            );
+            note(LOCAL_INDEX);
+            Ok(found)
+        }
+        None => Err(e),
+    }
+}
+
+/// `verifyPackageOrigin`, else the SKU's indexed packaging
+pub async fn package_origin(
+    state: &AppState,
+    chain: &ChainClient,
+    sku_id: FixedBytes<32>,
+) -> Result<PackageOrigin> {
+    match chain.verify_package_origin(sku_id).await {
+        Ok(origin) => {
+            chain.readiness().record_up();
+            Ok(origin)
+        }
+        Err(e) => from_index(state, chain, sku_id, e, indexed_origin).await,
+    }
+}
+
+/// `verifyFarmer`, else the farmer's indexed registration
+pub async fn farmer_registration(
+    state: &AppState,
+    chain: &ChainClient,
+    farmer_did: FixedBytes<32>,
+) -> Result<(bool, FixedBytes<32>, u64)> {
+    match chain.verify_farmer(farmer_did).await {
+        Ok(farmer) => {
+            chain.readiness().record_up();
+            Ok(farmer)
+        }
+        Err(e) => from_index(state, chain, farmer_did, e, indexed_registration).await,
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct HealthReport {
+    /// "ok", or "degraded" while a dependency is down
+    pub status: &'static str,
+    /// `chain:<network>` and `ipfs` entries that are down
+    pub down: Vec<String>,
+}
+
+pub async fn health(State(state): State<AppState>) -> Json<HealthReport> {
+    let mut down: Vec<String> = state
+        .networks
+        .clients()
+        .iter()
+        .filter(|c| c.readiness().is_degraded())
+        .map(|c| format!("chain:{}", c.network()))
+        .collect();
+    if !down.is_empty() {
+        note(CHAIN_DOWN);
+    }
+    if state.ipfs_client.readiness().is_degraded() {
+        down.push("ipfs".to_string());
+        note(IPFS_DOWN);
+    }
+    Json(HealthReport {
+        status: if down.is_empty() { "ok" } else { "degraded" },
+        down,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(event: &str, timestamp: u64, fields: serde_json::Value) -> IndexedEvent {
+        IndexedEvent {
+            block_number: timestamp,
+            log_index: 0,
+            tx_hash: String::new(),
+            contract: String::new(),
+            event: event.to_string(),
+            timestamp,
+            subject: String::new(),
+            farmer_did: None,
+            metadata_cid: None,
+            batch_hashes: vec![format_hash(FixedBytes::<32>::repeat_byte(1))],
+            fields,
+        }
+    }
+
+    #[test]
+    fn test_indexed_events_answer_verification() {
+        let root = format_hash(FixedBytes::<32>::repeat_byte(2));
+        let sku = [
+            event("FraudDetected", 5, json!({})),
+            event("SKUPackaged", 3, json!({ "merkle_root": root })),
+        ];
+        let (parent, merkle_root, packaged_at) = indexed_origin(&sku).unwrap();
+        assert_eq!(parent, FixedBytes::repeat_byte(1));
+        assert_eq!(format_hash(merkle_root), root);
+        assert_eq!(packaged_at, 3);
+        assert!(indexed_origin(&sku[..1]).is_none());
+
+        let crop = |b: u8| json!({ "crop_id_hash": format_hash(FixedBytes::<32>::repeat_byte(b)) });
+        let farmer = [
+            event("FarmerRegistered", 10, crop(7)),
+            event("FarmerUpdated", 20, crop(9)),
+        ];
+        assert_eq!(
+            indexed_registration(&farmer),
+            Some((true, FixedBytes::repeat_byte(9), 10))
+        );
+        assert!(indexed_registration(&farmer[1..]).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_notes_reach_the_header_once() {
+        let notes = Notes::default();
+        NOTES
+            .scope(notes.clone(), async {
+                note(LOCAL_INDEX);
+                let carried = current();
+                tokio::spawn(run_with(carried, async { note(LOCAL_INDEX) }))
+                    .await
+                    .unwrap();
+                note(IPFS_PARTIAL);
+            })
+            .await;
+        let modes: Vec<&str> = notes.lock().unwrap().iter().copied().collect();
+        assert_eq!(modes, vec![IPFS_PARTIAL, LOCAL_INDEX]);
+        // Outside a request
+        note(WRITE_QUEUED);
+    }
+}
@@ -0,0 +1,537 @@
+//! Time-limited delegations for FPO field agents
+//!
+//! Field agents act on behalf of an FPO for a season. Instead of sharing the
+//! admin token or granting on-chain roles, an admin issues a delegation
+//! (delegator, delegate, scopes, expiry) and hands the agent its bearer token.
+//! Scoped endpoints accept the admin token or an active delegation carrying
+//! the scope, and the audit log records the delegation as the actor. A
+//! delegate only acts for its delegator: purchases naming another FPO are
+//! refused.
+//!
+//! Signed-in users (see [`crate::auth`]) pass when their role matches the
+//! scope. Tokens are returned once at creation; only their SHA-256 is stored in
+//! `data/delegations.json`. With DELEGATION_AUTH_REQUIRED=true, scoped
+//! endpoints reject requests without a bearer token.
+
+use crate::admin::require_admin;
+use crate::api_keys;
+use crate::auth::{self, Claims, Role};
+use crate::error::{ApiError, ApiResult};
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+const DELEGATIONS_PATH: &str = "data/delegations.json";
+pub(crate) const TOKEN_PREFIX: &str = "dlg_";
+/// Longest delegation an admin can issue (one season plus slack)
+const MAX_VALIDITY_DAYS: i64 = 366;
+
+// ======================== DELEGATIONS ========================
+
+/// Actions a delegation can authorize
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// `POST /api/fpo/purchase`
+    FpoPurchase,
+}
+
+impl Scope {
+    /// User role allowed to perform the scoped action itself
+    pub fn role(self) -> Role {
+        match self {
+            Scope::FpoPurchase => Role::Fpo,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DelegationStatus {
+    Active,
+    Expired,
+    Revoked,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Delegation {
+    pub id: u64,
+    /// FPO the agent acts for
+    pub delegator: String,
+    /// Field agent receiving the delegation
+    pub delegate: String,
+    pub scopes: Vec<Scope>,
+    pub issued_at: String,
+    pub expires_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<String>,
+    /// Hex SHA-256 of the bearer token
+    token_hash: String,
+}
+
+impl Delegation {
+    pub fn status(&self, now: DateTime<Utc>) -> DelegationStatus {
+        if self.revoked_at.is_some() {
+            return DelegationStatus::Revoked;
+        }
+        match DateTime::parse_from_rfc3339(&self.expires_at) {
+            Ok(expires_at) if now < expires_at => DelegationStatus::Active,
+            _ => DelegationStatus::Expired,
+        }
+    }
+
+    /// Check that the delegation may perform `scope` at `now`
+    pub fn authorize(&self, scope: Scope, now: DateTime<Utc>) -> Result<(), ApiError> {
+        match self.status(now) {
+            DelegationStatus::Active => {}
+            DelegationStatus::Expired => {
+                return Err(ApiError::unauthorized(format!(
+                    "Delegation {} expired at {}",
+                    self.id, self.expires_at
+                )))
+            }
+            DelegationStatus::Revoked => {
+                return Err(ApiError::unauthorized(format!(
+                    "Delegation {} has been revoked",
+                    self.id
+                )))
+            }
+        }
+        if !self.scopes.contains(&scope) {
+            return Err(ApiError::forbidden(format!(
+                "Delegation {} does not cover this action",
+                self.id
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Who made a request to a scoped endpoint
+#[derive(Debug, Clone)]
+pub enum Actor {
+    Admin,
+    Delegate(Delegation),
+    /// Auditor console account (see [`crate::auditor`])
+    Auditor(u64),
+    /// Signed-in user (see [`crate::auth`])
+    User(Claims),
+    /// Integration key (see [`crate::api_keys`])
+    ApiKey(u64),
+    Anonymous,
+}
+
+impl Actor {
+    /// Actor name written to the audit log and to submitted metadata
+    pub fn label(&self) -> String {
+        match self {
+            Actor::Admin => "admin".to_string(),
+            Actor::Delegate(d) => format!("delegate:{}", d.id),
+            Actor::Auditor(id) => format!("auditor:{}", id),
+            Actor::User(claims) => format!("user:{}", claims.sub),
+            Actor::ApiKey(id) => format!("api_key:{}", id),
+            Actor::Anonymous => "anonymous".to_string(),
+        }
+    }
+
+    /// Refuse a delegate acting for an FPO other than its delegator
+    pub fn check_fpo(&self, fpo_id: &str) -> Result<(), ApiError> {
+        match self {
+            Actor::Delegate(d) if d.delegator.trim() != fpo_id.trim() => {
+                Err(ApiError::forbidden(format!(
+                    "Delegation {} acts for FPO {}, not {}",
+                    d.id, d.delegator, fpo_id
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// ======================== STORE ========================
+
+/// Issued delegations plus the enforcement setting
+pub struct DelegationStore {
+    required: bool,
+    delegations: Mutex<Vec<Delegation>>,
+}
+
+impl DelegationStore {
+    pub fn load() -> Result<Self> {
+        let required = std::env::var("DELEGATION_AUTH_REQUIRED")
+            .map(|v| v == "true")
+            .unwrap_or(false);
+
+        let delegations = match std::fs::read_to_string(DELEGATIONS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid delegations file {}", DELEGATIONS_PATH))?,
+            Err(_) => Vec::new(),
+        };
+
+        Ok(Self {
+            required,
+            delegations: Mutex::new(delegations),
+        })
+    }
+
+    fn save(delegations: &[Delegation]) -> Result<()> {
+        std::fs::write(DELEGATIONS_PATH, serde_json::to_string_pretty(delegations)?)
+            .with_context(|| format!("Failed to write {}", DELEGATIONS_PATH))
+    }
+
+    /// Delegation issued with `token`, whatever its status
+    pub async fn find_by_token(&self, token: &str) -> Option<Delegation> {
+        if !token.starts_with(TOKEN_PREFIX) {
+            return None;
+        }
+        let token_hash = hash_token(token);
+        self.delegations
+            .lock()
+            .await
+            .iter()
+            .find(|d| d.token_hash == token_hash)
+            .cloned()
+    }
+
+    /// Active delegation issued with `token`; unknown, expired and revoked
+    /// tokens are rejected
+    pub async fn authenticate(
+        &self,
+        token: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Delegation, ApiError> {
+        let delegation = self.find_by_token(token).await.ok_or_else(|| {
+            tracing::warn!("Rejected request with unknown delegation token");
+            ApiError::unauthorized("Invalid bearer token")
+        })?;
+        match delegation.status(now) {
+            DelegationStatus::Active => Ok(delegation),
+            DelegationStatus::Expired => Err(ApiError::unauthorized(format!(
+                "Delegation {} expired at {}",
+                delegation.id, delegation.expires_at
+            ))),
+            DelegationStatus::Revoked => Err(ApiError::unauthorized(format!(
+                "Delegation {} has been revoked",
+                delegation.id
+            ))),
+        }
+    }
+}
+
+// ======================== AUTHORIZATION ========================
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
+
+/// Authorize a request to an endpoint covered by `scope`.
+///
+/// The admin token always passes, as do users and API keys whose role
+/// matches the scope; any other bearer token must belong to an active delegation carrying the
+/// scope. Requests without a token pass as
+/// anonymous unless DELEGATION_AUTH_REQUIRED is set.
+pub async fn require_scope(
+    state: &AppState,
+    headers: &HeaderMap,
+    scope: Scope,
+) -> Result<Actor, ApiError> {
+    if let Some(key) = api_keys::api_key_header(headers) {
+        let api_key = state
+            .api_keys
+            .authenticate(key)
+            .await
+            .ok_or_else(|| ApiError::unauthorized("Invalid, expired or revoked API key"))?;
+        if !state
+            .chain_roles
+            .key_scopes(api_key.id, &api_key.scopes)
+            .contains(&scope.role())
+        {
+            return Err(ApiError::forbidden(format!(
+                "API key {} does not cover this action",
+                api_key.id
+            )));
+        }
+        return Ok(Actor::ApiKey(api_key.id));
+    }
+
+    let Some(token) = bearer_token(headers) else {
+        if state.delegations.required {
+            return Err(ApiError::unauthorized("Missing bearer token"));
+        }
+        return Ok(Actor::Anonymous);
+    };
+
+    if state.admin_token.as_deref() == Some(token) {
+        return Ok(Actor::Admin);
+    }
+
+    if auth::is_jwt(token) {
+        let mut claims = state.auth.verify(token)?;
+        state.chain_roles.apply_to_claims(&mut claims)?;
+        if !claims.has_role(Role::Admin) && !claims.has_role(scope.role()) {
+            return Err(ApiError::forbidden(format!(
+                "Role {} cannot perform this action",
+                claims.role
+            )));
+        }
+        return Ok(Actor::User(claims));
+    }
+
+    let delegation = state
+        .delegations
+        .find_by_token(token)
+        .await
+        .ok_or_else(|| {
+            tracing::warn!(?scope, "Rejected request with unknown bearer token");
+            ApiError::unauthorized("Invalid bearer token")
+        })?;
+    delegation.authorize(scope, Utc::now())?;
+
+    tracing::info!(
+        delegation_id = delegation.id,
+        delegate = %delegation.delegate,
+        delegator = %delegation.delegator,
+        ?scope,
+        "Request authorized by delegation"
+    );
+    Ok(Actor::Delegate(delegation))
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct DelegationSummary {
+    pub id: u64,
+    pub delegator: String,
+    pub delegate: String,
+    pub scopes: Vec<Scope>,
+    pub issued_at: String,
+    pub expires_at: String,
+    pub revoked_at: Option<String>,
+    pub status: DelegationStatus,
+}
+
+impl From<&Delegation> for DelegationSummary {
+    fn from(d: &Delegation) -> Self {
+        Self {
+            id: d.id,
+            delegator: d.delegator.clone(),
+            delegate: d.delegate.clone(),
+            scopes: d.scopes.clone(),
+            issued_at: d.issued_at.clone(),
+            expires_at: d.expires_at.clone(),
+            revoked_at: d.revoked_at.clone(),
+            status: d.status(Utc::now()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateDelegationRequest {
+    pub delegator: String,
+    pub delegate: String,
+    pub scopes: Vec<Scope>,
+    /// RFC 3339, e.g. end of the procurement season
+    pub expires_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateDelegationResponse {
+    pub delegation: DelegationSummary,
+    /// Bearer token for the delegate; shown only once
+    pub token: String,
+}
+
+pub async fn create_delegation(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateDelegationRequest>,
+) -> ApiResult<CreateDelegationResponse> {
+    require_admin(&state, &headers)?;
+
+    if payload.delegator.trim().is_empty() || payload.delegate.trim().is_empty() {
+        return Err(ApiError::bad_request("delegator and delegate are required"));
+    }
+    if payload.scopes.is_empty() {
+        return Err(ApiError::bad_request("Provide at least one scope"));
+    }
+    let now = Utc::now();
+    let expires_at = DateTime::parse_from_rfc3339(&payload.expires_at)
+        .map_err(|e| ApiError::bad_request(format!("Invalid expires_at: {}", e)))?
+        .with_timezone(&Utc);
+    if expires_at <= now {
+        return Err(ApiError::bad_request("expires_at must be in the future"));
+    }
+    if expires_at > now + chrono::Duration::days(MAX_VALIDITY_DAYS) {
+        return Err(ApiError::bad_request(format!(
+            "Delegations may last at most {} days",
+            MAX_VALIDITY_DAYS
+        )));
+    }
+
+    let token = format!(
+        "{}{}",
+        TOKEN_PREFIX,
+        hex::encode(rand::random::<[u8; 32]>())
+    );
+    let mut delegations = state.delegations.delegations.lock().await;
+    let delegation = Delegation {
+        id: delegations.last().map(|d| d.id + 1).unwrap_or(1),
+        delegator: payload.delegator,
+        delegate: payload.delegate,
+        scopes: payload.scopes,
+        issued_at: now.to_rfc3339(),
+        expires_at: expires_at.to_rfc3339(),
+        revoked_at: None,
+        token_hash: hash_token(&token),
+    };
+    delegations.push(delegation.clone());
+    DelegationStore::save(&delegations)?;
+
+    tracing::info!(
+        delegation_id = delegation.id,
+        delegate = %delegation.delegate,
+        delegator = %delegation.delegator,
+        expires_at = %delegation.expires_at,
+        "Delegation issued"
+    );
+
+    Ok(Json(CreateDelegationResponse {
+        delegation: DelegationSummary::from(&delegation),
+        token,
+    }))
+}
+
+pub async fn list_delegations(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<DelegationSummary>> {
+    require_admin(&state, &headers)?;
+
+    let delegations: Vec<DelegationSummary> = state
+        .delegations
+        .delegations
+        .lock()
+        .await
+        .iter()
+        .map(DelegationSummary::from)
+        .collect();
+    Ok(Json(paginate("delegations", delegations, &params, |d| {
+        d.id
+    })?))
+}
+
+pub async fn revoke_delegation(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> ApiResult<DelegationSummary> {
+    require_admin(&state, &headers)?;
+
+    let mut delegations = state.delegations.delegations.lock().await;
+    let delegation = delegations
+        .iter_mut()
+        .find(|d| d.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("Delegation {} not found", id)))?;
+    if delegation.revoked_at.is_none() {
+        delegation.revoked_at = Some(Utc::now().to_rfc3339());
+        tracing::info!(delegation_id = id, "Delegation revoked");
+    }
+    let summary = DelegationSummary::from(&*delegation);
+    DelegationStore::save(&delegations)?;
+
+    Ok(Json(summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delegation(expires_at: &str) -> Delegation {
+        Delegation {
+            id: 1,
+            delegator: "FPO-001".to_string(),
+            delegate: "agent-7".to_string(),
+            scopes: vec![Scope::FpoPurchase],
+            issued_at: "2025-06-01T00:00:00Z".to_string(),
+            expires_at: expires_at.to_string(),
+            revoked_at: None,
+            token_hash: hash_token("dlg_test"),
+        }
+    }
+
+    #[test]
+    fn test_delegation_expiry_and_revocation() {
+        let now = DateTime::parse_from_rfc3339("2025-07-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let mut active = delegation("2025-10-31T23:59:59Z");
+        assert_eq!(active.status(now), DelegationStatus::Active);
+        assert!(active.authorize(Scope::FpoPurchase, now).is_ok());
+
+        let expired = delegation("2025-06-30T00:00:00Z");
+        assert_eq!(expired.status(now), DelegationStatus::Expired);
+        assert!(expired.authorize(Scope::FpoPurchase, now).is_err());
+
+        active.revoked_at = Some("2025-06-15T00:00:00Z".to_string());
+        assert_eq!(active.status(now), DelegationStatus::Revoked);
+        assert!(active.authorize(Scope::FpoPurchase, now).is_err());
+    }
+
+    #[test]
+    fn test_delegation_requires_scope() {
+        let now = DateTime::parse_from_rfc3339("2025-07-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut unscoped = delegation("2025-10-31T23:59:59Z");
+        unscoped.scopes.clear();
+        assert!(unscoped.authorize(Scope::FpoPurchase, now).is_err());
+    }
+
+    #[test]
+    fn test_delegates_act_only_for_their_fpo() {
+        let actor = Actor::Delegate(delegation("2025-10-31T23:59:59Z"));
+        assert!(actor.check_fpo("FPO-001").is_ok());
+        let other = actor.check_fpo("FPO-002").unwrap_err();
+        assert_eq!(other.status, axum::http::StatusCode::FORBIDDEN);
+        assert!(Actor::Admin.check_fpo("FPO-002").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_forged_and_lapsed_tokens_are_rejected() {
+        let now = DateTime::parse_from_rfc3339("2025-07-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let store = |delegation: Delegation| DelegationStore {
+            required: false,
+            delegations: Mutex::new(vec![delegation]),
+        };
+
+        let active = store(delegation("2025-10-31T23:59:59Z"));
+        assert_eq!(active.authenticate("dlg_test", now).await.unwrap().id, 1);
+        let forged = active.authenticate("dlg_x", now).await.unwrap_err();
+        assert_eq!(forged.status, axum::http::StatusCode::UNAUTHORIZED);
+
+        let expired = store(delegation("2025-06-30T00:00:00Z"));
+        let lapsed = expired.authenticate("dlg_test", now).await.unwrap_err();
+        assert_eq!(lapsed.status, axum::http::StatusCode::UNAUTHORIZED);
+    }
+}
@@ -1,5 +1,4 @@
 use crate::error::{ApiError, ApiResult};
-use crate::local_blockchain::LocalBlockchainClient;
 use crate::state::AppState;
 use axum::{extract::State, Json};
 use serde::{Deserialize, Serialize};
@@ -45,7 +44,7 @@ pub async fn verify_farmer(
     tracing::info!(farmer_did = %payload.farmer_did, "Verifying farmer in local blockchain");
 
     let exists = state
-        .blockchain_client
+        .local_ledger
         .verify_farmer(&payload.farmer_did)
         .await
         .map_err(|e| ApiError::internal_server_error(format!("Blockchain verify error: {}", e)))?;
@@ -126,7 +125,7 @@ pub async fn fpo_purchase(
 
     // 4) Add to local blockchain
     let receipt = state
-        .blockchain_client
+        .local_ledger
         .add_fpo_purchase(
             payload.batch_id.clone(),
             payload.farmer_did.clone(),
@@ -164,7 +163,7 @@ pub async fn get_blockchain_stats(
     State(state): State<AppState>,
 ) -> ApiResult<BlockchainStatsResponse> {
     let stats = state
-        .blockchain_client
+        .local_ledger
         .get_stats()
         .await
         .map_err(|e| ApiError::internal_server_error(format!("Stats error: {}", e)))?;
@@ -188,7 +187,7 @@ pub async fn get_batch_history(
     Json(payload): Json<BatchHistoryRequest>,
 ) -> ApiResult<BatchHistoryResponse> {
     let history = state
-        .blockchain_client
+        .local_ledger
         .get_batch_history(&payload.batch_id)
         .await
         .map_err(|e| ApiError::internal_server_error(format!("History error: {}", e)))?;
@@ -0,0 +1,100 @@
+//! Farmer DID derivation
+//!
+//! A farmer's DID is `0x` + hex(sha256(salt ‖ mobile)), over the 10-digit
+//! form of the mobile number (see [`crate::sms::normalize_mobile`]). The salt
+//! is FARMER_DID_SALT, empty by default, which gives the DIDs already in the
+//! registry. It is fixed per deployment: changing it changes every DID.
+//!
+//! The backend assigns DIDs only through [`farmer_did`]; clients get theirs
+//! from `POST /api/farmer/did/derive` instead of hashing the number
+//! themselves.
+
+use crate::error::{ApiError, ApiResult};
+use crate::sms::normalize_mobile;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+/// DID of `mobile` under `salt`
+pub fn derive_farmer_did(mobile: &str, salt: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(normalize_mobile(mobile).as_bytes());
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+/// Salt of this deployment (FARMER_DID_SALT)
+pub fn salt() -> &'static str {
+    static SALT: OnceLock<String> = OnceLock::new();
+    SALT.get_or_init(|| std::env::var("FARMER_DID_SALT").unwrap_or_default())
+}
+
+/// DID of `mobile` in this deployment
+pub fn farmer_did(mobile: &str) -> String {
+    derive_farmer_did(mobile, salt())
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct DeriveDidRequest {
+    pub mobile: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeriveDidResponse {
+    /// The number the DID was derived from, as stored in the registry
+    pub mobile: String,
+    pub farmer_did: String,
+}
+
+pub async fn derive_did(Json(payload): Json<DeriveDidRequest>) -> ApiResult<DeriveDidResponse> {
+    let mobile = normalize_mobile(&payload.mobile);
+    if mobile.len() != 10 {
+        return Err(ApiError::bad_request("mobile must be a 10-digit number"));
+    }
+    Ok(Json(DeriveDidResponse {
+        farmer_did: farmer_did(&mobile),
+        mobile,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::format_hash;
+    use alloy::primitives::FixedBytes;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn prop_did_round_trips_as_bytes32(mobile in "[6-9][0-9]{9}") {
+            let did = derive_farmer_did(&mobile, "");
+            prop_assert_eq!(&did, &derive_farmer_did(&format!("+91 {}", mobile), ""));
+            let parsed = did.parse::<FixedBytes<32>>().unwrap();
+            prop_assert_eq!(format_hash(parsed), did.clone());
+            let upper = format!("0x{}", did[2..].to_uppercase());
+            prop_assert_eq!(upper.parse::<FixedBytes<32>>().unwrap(), parsed);
+        }
+
+        #[test]
+        fn prop_distinct_mobiles_get_distinct_dids(a in "[6-9][0-9]{9}", b in "[6-9][0-9]{9}") {
+            prop_assume!(a != b);
+            prop_assert_ne!(derive_farmer_did(&a, ""), derive_farmer_did(&b, ""));
+        }
+    }
+
+    #[test]
+    fn test_unsalted_did_matches_the_registry() {
+        // Demo farmer of data/farmers_db.json
+        assert_eq!(
+            derive_farmer_did("9876543210", ""),
+            format!("0x{}", hex::encode(Sha256::digest(b"9876543210")))
+        );
+        assert_ne!(
+            derive_farmer_did("9876543210", "pepper"),
+            derive_farmer_did("9876543210", "")
+        );
+    }
+}
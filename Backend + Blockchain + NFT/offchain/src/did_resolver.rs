@@ -0,0 +1,363 @@
+//! W3C DIDs of farmers
+//!
+//! A farmer DID may be given in three forms:
+//!
+//! - `0x` + 64 hex digits, the hash the registry derives from a mobile number
+//!   (see [`crate::did`]); used as is on chain
+//! - `did:ethr:[network:]0x<address>`, an Ethereum account
+//! - `did:key:z<base58btc>`, an Ed25519 or secp256k1 public key
+//!
+//! The contract identifies farmers by bytes32, so a DID string is hashed
+//! (keccak256 of its canonical form) for chain calls and the event index;
+//! [`chain_did`] does this for every form. Canonical means a lowercase
+//! address for did:ethr and the key as given for did:key.
+//!
+//! `GET /api/did/:did` resolves did:ethr and did:key to their DID document,
+//! derived from the DID itself without a registry lookup, so credentials
+//! naming a farmer can be checked by external identity systems. A did:ethr
+//! network is a hex chain id or the name of a configured chain network;
+//! without one it is Ethereum mainnet.
+
+use crate::chain::hash_string;
+use crate::error::{format_hash, ApiError, ApiResult};
+use crate::state::AppState;
+use alloy::primitives::{Address, FixedBytes};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+const DID_CONTEXT: &str = "https://www.w3.org/ns/did/v1";
+const MULTIKEY_CONTEXT: &str = "https://w3id.org/security/multikey/v1";
+const SECP256K1_RECOVERY_CONTEXT: &str =
+    "https://w3id.org/security/suites/secp256k1recovery-2020/v2";
+
+const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Key type of a did:key, from its multicodec prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyType {
+    Ed25519,
+    Secp256k1,
+}
+
+/// A farmer identifier in one of the accepted forms
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FarmerDid {
+    Hash(FixedBytes<32>),
+    Ethr {
+        network: Option<String>,
+        address: Address,
+    },
+    Key {
+        key_type: KeyType,
+        /// `z` + base58btc of the multicodec-prefixed key
+        multibase: String,
+    },
+}
+
+impl FromStr for FarmerDid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(rest) = s.strip_prefix("did:ethr:") {
+            let (network, address) = match rest.rsplit_once(':') {
+                Some((network, address)) if !network.is_empty() && !network.contains(':') => {
+                    (Some(network.to_string()), address)
+                }
+                Some(_) => return Err("did:ethr takes at most one network".to_string()),
+                None => (None, rest),
+            };
+            let address = address
+                .parse::<Address>()
+                .map_err(|e| format!("did:ethr address: {}", e))?;
+            return Ok(FarmerDid::Ethr { network, address });
+        }
+        if let Some(multibase) = s.strip_prefix("did:key:") {
+            let key = multibase
+                .strip_prefix('z')
+                .and_then(decode_base58)
+                .ok_or("did:key must be base58btc multibase ('z' prefix)")?;
+            let key_type = match key.as_slice() {
+                [0xed, 0x01, rest @ ..] if rest.len() == 32 => KeyType::Ed25519,
+                [0xe7, 0x01, rest @ ..] if rest.len() == 33 => KeyType::Secp256k1,
+                _ => return Err("did:key must hold an Ed25519 or secp256k1 key".to_string()),
+            };
+            return Ok(FarmerDid::Key {
+                key_type,
+                multibase: multibase.to_string(),
+            });
+        }
+        if s.starts_with("did:") {
+            return Err("only did:ethr and did:key are supported".to_string());
+        }
+        s.parse::<FixedBytes<32>>()
+            .map(FarmerDid::Hash)
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl fmt::Display for FarmerDid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FarmerDid::Hash(hash) => f.write_str(&format_hash(*hash)),
+            FarmerDid::Ethr {
+                network: Some(network),
+                address,
+            } => write!(f, "did:ethr:{}:0x{}", network, hex::encode(address)),
+            FarmerDid::Ethr {
+                network: None,
+                address,
+            } => write!(f, "did:ethr:0x{}", hex::encode(address)),
+            FarmerDid::Key { multibase, .. } => write!(f, "did:key:{}", multibase),
+        }
+    }
+}
+
+impl FarmerDid {
+    /// bytes32 identifying the farmer in contract calls and events
+    pub fn chain_id(&self) -> FixedBytes<32> {
+        match self {
+            FarmerDid::Hash(hash) => *hash,
+            did => hash_string(&did.to_string()),
+        }
+    }
+}
+
+/// bytes32 of a farmer DID given in any accepted form
+pub fn chain_did(did: &str) -> Result<FixedBytes<32>, String> {
+    Ok(did.parse::<FarmerDid>()?.chain_id())
+}
+
+/// Canonical form of a farmer DID, as stored in the registry
+pub fn canonical(did: &str) -> Result<String, String> {
+    Ok(did.parse::<FarmerDid>()?.to_string())
+}
+
+/// Whether two DIDs name the same farmer
+pub fn same_farmer(a: &str, b: &str) -> bool {
+    match (chain_did(a), chain_did(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+fn decode_base58(s: &str) -> Option<Vec<u8>> {
+    // Little-endian base 256 digits of the number
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in s.bytes() {
+        let mut carry = BASE58_ALPHABET.iter().position(|&a| a == c)? as u32;
+        for byte in bytes.iter_mut() {
+            carry += *byte as u32 * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let leading_zeros = s.bytes().take_while(|&c| c == b'1').count();
+    bytes.extend(std::iter::repeat_n(0, leading_zeros));
+    bytes.reverse();
+    Some(bytes)
+}
+
+// ======================== DOCUMENTS ========================
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationMethod {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub controller: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key_multibase: Option<String>,
+    /// CAIP-10 account, for did:ethr
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blockchain_account_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidDocument {
+    #[serde(rename = "@context")]
+    pub context: Vec<&'static str>,
+    pub id: String,
+    pub verification_method: Vec<VerificationMethod>,
+    pub authentication: Vec<String>,
+    pub assertion_method: Vec<String>,
+}
+
+/// DID document of a did:ethr on `chain_id` or a did:key; `None` for a
+/// registry hash, which is not a DID
+pub fn document(did: &FarmerDid, chain_id: u64) -> Option<DidDocument> {
+    let id = did.to_string();
+    let (context, method) = match did {
+        FarmerDid::Hash(_) => return None,
+        FarmerDid::Ethr { address, .. } => (
+            SECP256K1_RECOVERY_CONTEXT,
+            VerificationMethod {
+                id: format!("{}#controller", id),
+                kind: "EcdsaSecp256k1RecoveryMethod2020",
+                controller: id.clone(),
+                public_key_multibase: None,
+                blockchain_account_id: Some(format!("eip155:{}:{}", chain_id, address)),
+            },
+        ),
+        FarmerDid::Key { multibase, .. } => (
+            MULTIKEY_CONTEXT,
+            VerificationMethod {
+                id: format!("{}#{}", id, multibase),
+                kind: "Multikey",
+                controller: id.clone(),
+                public_key_multibase: Some(multibase.clone()),
+                blockchain_account_id: None,
+            },
+        ),
+    };
+    Some(DidDocument {
+        context: vec![DID_CONTEXT, context],
+        authentication: vec![method.id.clone()],
+        assertion_method: vec![method.id.clone()],
+        verification_method: vec![method],
+        id,
+    })
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentMetadata {
+    /// bytes32 the contract knows the farmer by
+    pub chain_did: String,
+    /// Whether the farmer registry holds this DID
+    pub registered: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DidResolution {
+    pub did_document: DidDocument,
+    pub did_document_metadata: DocumentMetadata,
+}
+
+pub async fn resolve_did(
+    State(state): State<AppState>,
+    Path(did): Path<String>,
+) -> ApiResult<DidResolution> {
+    let did: FarmerDid = did.parse().map_err(ApiError::invalid_did)?;
+    let chain_id = match &did {
+        FarmerDid::Ethr {
+            network: Some(network),
+            ..
+        } => match network.strip_prefix("0x") {
+            Some(hex_id) => u64::from_str_radix(hex_id, 16)
+                .map_err(|_| ApiError::invalid_did("did:ethr chain id is not hex"))?,
+            None => state
+                .networks
+                .get(network)
+                .map(|client| client.chain_id())
+                .ok_or_else(|| {
+                    ApiError::not_found(format!("Chain network {} is not configured", network))
+                })?,
+        },
+        // did:ethr without a network is Ethereum mainnet
+        _ => 1,
+    };
+    let did_document = document(&did, chain_id).ok_or_else(|| {
+        ApiError::bad_request("A registry hash has no DID document; use did:ethr or did:key")
+    })?;
+    let registered = state
+        .farmer_verification
+        .is_did_registered(&did_document.id)
+        .await?;
+
+    Ok(Json(DidResolution {
+        did_document,
+        did_document_metadata: DocumentMetadata {
+            chain_did: format_hash(did.chain_id()),
+            registered,
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_did_forms_parse_to_canonical_strings_and_chain_ids() {
+        let hash = format_hash(FixedBytes::<32>::repeat_byte(0xab));
+        let parsed: FarmerDid = hash.to_uppercase().replace("0X", "0x").parse().unwrap();
+        assert_eq!(parsed.to_string(), hash);
+        assert_eq!(chain_did(&hash).unwrap(), FixedBytes::repeat_byte(0xab));
+
+        let ethr = "did:ethr:0xb9c5714089478a327f09197987f16f9e5d936e8a";
+        let checksummed = "did:ethr:0xB9C5714089478a327F09197987f16f9E5d936E8a";
+        assert_eq!(canonical(checksummed).unwrap(), ethr);
+        assert_eq!(chain_did(checksummed).unwrap(), hash_string(ethr));
+        assert!(same_farmer(ethr, checksummed));
+        assert_eq!(
+            canonical("did:ethr:sepolia:0xb9c5714089478a327f09197987f16f9e5d936e8a").unwrap(),
+            "did:ethr:sepolia:0xb9c5714089478a327f09197987f16f9e5d936e8a"
+        );
+
+        // Examples of the did:key specification
+        let ed25519: FarmerDid = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"
+            .parse()
+            .unwrap();
+        assert!(matches!(
+            ed25519,
+            FarmerDid::Key {
+                key_type: KeyType::Ed25519,
+                ..
+            }
+        ));
+        let secp: FarmerDid = "did:key:zQ3shokFTS3brHcDQrn82RUDfCZESWL1ZdCEJwekUDPQiYBme"
+            .parse()
+            .unwrap();
+        assert!(matches!(
+            secp,
+            FarmerDid::Key {
+                key_type: KeyType::Secp256k1,
+                ..
+            }
+        ));
+
+        assert!("did:web:example.com".parse::<FarmerDid>().is_err());
+        assert!("did:key:z6Mk".parse::<FarmerDid>().is_err());
+        assert!("did:ethr:a:b:0xb9c5714089478a327f09197987f16f9e5d936e8a"
+            .parse::<FarmerDid>()
+            .is_err());
+    }
+
+    #[test]
+    fn test_documents_reference_their_verification_method() {
+        let did: FarmerDid = "did:ethr:0xb9c5714089478a327f09197987f16f9e5d936e8a"
+            .parse()
+            .unwrap();
+        let doc = serde_json::to_value(document(&did, 11155111).unwrap()).unwrap();
+        assert_eq!(doc["@context"][0], DID_CONTEXT);
+        assert_eq!(
+            doc["verificationMethod"][0]["blockchainAccountId"],
+            "eip155:11155111:0xB9C5714089478a327F09197987f16f9E5d936E8a"
+        );
+        assert_eq!(doc["authentication"][0], doc["verificationMethod"][0]["id"]);
+
+        let key: FarmerDid = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"
+            .parse()
+            .unwrap();
+        let doc = document(&key, 1).unwrap();
+        assert_eq!(
+            doc.verification_method[0].id,
+            "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK#z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"
+        );
+        assert!(document(&FarmerDid::Hash(FixedBytes::ZERO), 1).is_none());
+    }
+}
@@ -5,10 +5,15 @@ use axum::{
 };
 use serde::Serialize;
 
+use crate::revert::ContractRevert;
+
 /// Unified error response structure for all API endpoints
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
     pub error: String,
+    /// Stable machine-readable code, set for decoded contract reverts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<&'static str>,
 }
 
 /// API error type that can be easily converted to an Axum response
@@ -16,6 +21,7 @@ pub struct ErrorResponse {
 pub struct ApiError {
     pub status: StatusCode,
     pub message: String,
+    pub code: Option<&'static str>,
 }
 
 impl ApiError {
@@ -23,6 +29,7 @@ impl ApiError {
         Self {
             status,
             message: message.into(),
+            code: None,
         }
     }
 
@@ -34,18 +41,76 @@ impl ApiError {
         Self::new(StatusCode::BAD_REQUEST, message)
     }
 
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, message)
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, message)
+    }
+
     #[allow(dead_code)]
     pub fn not_found(message: impl Into<String>) -> Self {
         Self::new(StatusCode::NOT_FOUND, message)
     }
 
     // Convenience constructors for common error cases
-    pub fn ipfs_upload_failed(e: impl std::fmt::Display) -> Self {
+    /// A tenant over its quota gets 403 rather than 500
+    pub fn ipfs_upload_failed(e: anyhow::Error) -> Self {
+        if let Some(quota) = Self::quota_exceeded(&e) {
+            return quota;
+        }
+        if let Some(degraded) = Self::degraded(&e) {
+            return degraded;
+        }
         Self::internal(format!("IPFS upload failed: {}", e))
     }
 
-    pub fn blockchain_failed(e: impl std::fmt::Display) -> Self {
-        Self::internal(format!("Blockchain transaction failed: {}", e))
+    fn quota_exceeded(e: &anyhow::Error) -> Option<Self> {
+        e.downcast_ref::<crate::metering::QuotaExceeded>()
+            .map(|quota| Self {
+                status: StatusCode::FORBIDDEN,
+                message: quota.to_string(),
+                code: Some("quota_exceeded"),
+            })
+    }
+
+    /// A dependency still warming up is 503, see [`crate::readiness`], and
+    /// a write kept for the outbox is 202
+    fn degraded(e: &anyhow::Error) -> Option<Self> {
+        if let Some(queued) = e.downcast_ref::<crate::outbox::Queued>() {
+            return Some(Self {
+                status: StatusCode::ACCEPTED,
+                message: queued.to_string(),
+                code: Some("write_queued"),
+            });
+        }
+        e.downcast_ref::<crate::readiness::Unavailable>()
+            .map(|unavailable| Self {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                message: unavailable.to_string(),
+                code: Some("dependency_unavailable"),
+            })
+    }
+
+    /// Contract reverts keep their own status and code, quota refusals are
+    /// 403 and writes saved to the outbox are 202; anything else is a
+    /// provider failure
+    pub fn blockchain_failed(e: anyhow::Error) -> Self {
+        if let Some(quota) = Self::quota_exceeded(&e) {
+            return quota;
+        }
+        if let Some(degraded) = Self::degraded(&e) {
+            return degraded;
+        }
+        match ContractRevert::from_error(&e) {
+            Some(revert) => Self {
+                status: revert.status,
+                message: revert.message,
+                code: Some(revert.code),
+            },
+            None => Self::internal(format!("Blockchain transaction failed: {}", e)),
+        }
     }
 
     pub fn internal_server_error(message: impl Into<String>) -> Self {
@@ -69,6 +134,7 @@ impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         let body = Json(ErrorResponse {
             error: self.message,
+            code: self.code,
         });
         (self.status, body).into_response()
     }
@@ -77,6 +143,9 @@ impl IntoResponse for ApiError {
 // Conversion from anyhow::Error for easy ? usage
 impl From<anyhow::Error> for ApiError {
     fn from(e: anyhow::Error) -> Self {
+        if let Some(degraded) = Self::degraded(&e) {
+            return degraded;
+        }
         Self::internal(e.to_string())
     }
 }
@@ -86,7 +155,15 @@ pub type ApiResult<T> = Result<Json<T>, ApiError>;
 
 /// Helper to build IPFS gateway URL
 pub fn ipfs_gateway_url(cid: &str) -> String {
-    format!("https://ipfs.io/ipfs/{}", cid)
+    match crate::tenant_storage::current_gateway() {
+        Some(gateway) => format!("{}/ipfs/{}", gateway, cid),
+        None => format!("https://ipfs.io/ipfs/{}", cid),
+    }
+}
+
+/// Helper to build a block explorer link for a transaction
+pub fn explorer_tx_url(base_url: &str, tx_hash: &str) -> String {
+    format!("{}/tx/{}", base_url.trim_end_matches('/'), tx_hash)
 }
 
 /// Helper to format transaction hash for response
@@ -0,0 +1,357 @@
+//! A/B content variants for the consumer trace page
+//!
+//! Experiments are configured in `data/experiments.json` (override with
+//! EXPERIMENTS_CONFIG_PATH). Each experiment covers an inclusive SKU range,
+//! optionally a single brand, and a set of weighted variants that replace
+//! the brand story and/or highlighted certifications.
+//!
+//! Assignment is deterministic: the same visitor (or, without a visitor ID,
+//! the same SKU) always lands in the same variant. Every trace page served
+//! with a variant counts as an exposure; the page reports conversions via
+//! `POST /api/public/experiments/convert`. Counters are kept in memory and
+//! flushed to `data/experiment_counters.json` at most every
+//! EXPERIMENT_FLUSH_SECS (default 30).
+
+use crate::admin::require_admin;
+use crate::chain::hash_string;
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{extract::State, http::HeaderMap, Json};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const DEFAULT_EXPERIMENTS_CONFIG_PATH: &str = "data/experiments.json";
+const COUNTERS_PATH: &str = "data/experiment_counters.json";
+const DEFAULT_FLUSH_SECS: u64 = 30;
+
+// ======================== CONFIGURATION ========================
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Variant {
+    pub id: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// Replaces the brand story (same placeholders)
+    #[serde(default)]
+    pub story: Option<String>,
+    /// Replaces the brand's highlighted certifications
+    #[serde(default)]
+    pub highlight_certifications: Option<Vec<String>>,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Experiment {
+    pub id: String,
+    /// Restrict to one brand; applies to every brand when absent
+    #[serde(default)]
+    pub brand: Option<String>,
+    /// First SKU of the range (inclusive, compared as strings)
+    pub sku_from: String,
+    /// Last SKU of the range (inclusive)
+    pub sku_to: String,
+    pub variants: Vec<Variant>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+impl Experiment {
+    pub fn covers(&self, sku_id: &str, brand: &str) -> bool {
+        self.active
+            && self.brand.as_deref().is_none_or(|b| b == brand)
+            && sku_id >= self.sku_from.as_str()
+            && sku_id <= self.sku_to.as_str()
+    }
+
+    /// Pick a variant for `unit` (visitor or SKU ID), stable across requests
+    pub fn assign(&self, unit: &str) -> Option<&Variant> {
+        let total: u64 = self.variants.iter().map(|v| v.weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let hash = hash_string(&format!("{}:{}", self.id, unit));
+        let mut bucket = u64::from_be_bytes(hash[..8].try_into().ok()?) % total;
+        for variant in &self.variants {
+            if bucket < variant.weight as u64 {
+                return Some(variant);
+            }
+            bucket -= variant.weight as u64;
+        }
+        None
+    }
+}
+
+// ======================== REGISTRY & COUNTERS ========================
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct VariantCounters {
+    pub exposures: u64,
+    pub conversions: u64,
+}
+
+/// experiment ID → variant ID → counters
+type CounterMap = BTreeMap<String, BTreeMap<String, VariantCounters>>;
+
+pub struct ExperimentRegistry {
+    experiments: Vec<Experiment>,
+    counters: Mutex<CounterMap>,
+    last_flush: Mutex<Instant>,
+    flush_interval: Duration,
+}
+
+impl ExperimentRegistry {
+    pub fn load() -> Result<Self> {
+        let path = std::env::var("EXPERIMENTS_CONFIG_PATH")
+            .unwrap_or_else(|_| DEFAULT_EXPERIMENTS_CONFIG_PATH.to_string());
+
+        let experiments: Vec<Experiment> = if std::path::Path::new(&path).exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read experiments config {}", path))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid experiments config {}", path))?
+        } else {
+            Vec::new()
+        };
+
+        let counters: CounterMap = match std::fs::read_to_string(COUNTERS_PATH) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "Ignoring unreadable experiment counters");
+                CounterMap::new()
+            }),
+            Err(_) => CounterMap::new(),
+        };
+
+        let flush_secs = std::env::var("EXPERIMENT_FLUSH_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_FLUSH_SECS);
+
+        tracing::info!(
+            experiments = experiments.len(),
+            "Trace page experiments loaded"
+        );
+
+        Ok(Self {
+            experiments,
+            counters: Mutex::new(counters),
+            last_flush: Mutex::new(Instant::now()),
+            flush_interval: Duration::from_secs(flush_secs),
+        })
+    }
+
+    /// First active experiment covering the SKU for this brand
+    pub fn find(&self, sku_id: &str, brand: &str) -> Option<&Experiment> {
+        self.experiments.iter().find(|e| e.covers(sku_id, brand))
+    }
+
+    pub fn get(&self, experiment_id: &str) -> Option<&Experiment> {
+        self.experiments.iter().find(|e| e.id == experiment_id)
+    }
+
+    pub fn record_exposure(&self, experiment_id: &str, variant_id: &str) {
+        self.bump(experiment_id, variant_id, |c| c.exposures += 1);
+    }
+
+    pub fn record_conversion(&self, experiment_id: &str, variant_id: &str) {
+        self.bump(experiment_id, variant_id, |c| c.conversions += 1);
+    }
+
+    fn bump(
+        &self,
+        experiment_id: &str,
+        variant_id: &str,
+        update: impl FnOnce(&mut VariantCounters),
+    ) {
+        let snapshot = {
+            let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+            update(
+                counters
+                    .entry(experiment_id.to_string())
+                    .or_default()
+                    .entry(variant_id.to_string())
+                    .or_default(),
+            );
+
+            let mut last_flush = self.last_flush.lock().unwrap_or_else(|e| e.into_inner());
+            if last_flush.elapsed() < self.flush_interval {
+                return;
+            }
+            *last_flush = Instant::now();
+            counters.clone()
+        };
+
+        if let Err(e) = Self::flush(&snapshot) {
+            tracing::warn!(error = %format!("{:#}", e), "Failed to persist experiment counters");
+        }
+    }
+
+    fn flush(counters: &CounterMap) -> Result<()> {
+        let json = serde_json::to_string_pretty(counters)?;
+        std::fs::write(COUNTERS_PATH, json)
+            .with_context(|| format!("Failed to write {}", COUNTERS_PATH))
+    }
+
+    pub fn counters(&self) -> CounterMap {
+        self.counters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct ConversionRequest {
+    pub experiment_id: String,
+    pub variant_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConversionResponse {
+    pub recorded: bool,
+}
+
+/// Record a conversion reported by the consumer trace page
+pub async fn record_conversion(
+    State(state): State<AppState>,
+    Json(payload): Json<ConversionRequest>,
+) -> ApiResult<ConversionResponse> {
+    let experiment = state
+        .experiments
+        .get(&payload.experiment_id)
+        .ok_or_else(|| ApiError::not_found("Unknown experiment"))?;
+    if !experiment
+        .variants
+        .iter()
+        .any(|v| v.id == payload.variant_id)
+    {
+        return Err(ApiError::bad_request("Unknown variant"));
+    }
+
+    state
+        .experiments
+        .record_conversion(&payload.experiment_id, &payload.variant_id);
+
+    Ok(Json(ConversionResponse { recorded: true }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct VariantStats {
+    pub variant_id: String,
+    pub exposures: u64,
+    pub conversions: u64,
+    pub conversion_rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExperimentStats {
+    pub experiment_id: String,
+    pub active: bool,
+    pub variants: Vec<VariantStats>,
+}
+
+/// Exposure and conversion counts per experiment variant (admin only)
+pub async fn experiment_analytics(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<Vec<ExperimentStats>> {
+    require_admin(&state, &headers)?;
+
+    let counters = state.experiments.counters();
+    let stats = state
+        .experiments
+        .experiments
+        .iter()
+        .map(|experiment| {
+            let recorded = counters.get(&experiment.id);
+            ExperimentStats {
+                experiment_id: experiment.id.clone(),
+                active: experiment.active,
+                variants: experiment
+                    .variants
+                    .iter()
+                    .map(|variant| {
+                        let c = recorded
+                            .and_then(|r| r.get(&variant.id))
+                            .copied()
+                            .unwrap_or_default();
+                        VariantStats {
+                            variant_id: variant.id.clone(),
+                            exposures: c.exposures,
+                            conversions: c.conversions,
+                            conversion_rate: if c.exposures > 0 {
+                                c.conversions as f64 / c.exposures as f64
+                            } else {
+                                0.0
+                            },
+                        }
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    Ok(Json(stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn experiment() -> Experiment {
+        Experiment {
+            id: "story-test".to_string(),
+            brand: Some("sarson-gold".to_string()),
+            sku_from: "SG-0001".to_string(),
+            sku_to: "SG-0500".to_string(),
+            variants: vec![
+                Variant {
+                    id: "control".to_string(),
+                    weight: 1,
+                    story: None,
+                    highlight_certifications: None,
+                },
+                Variant {
+                    id: "farmer-first".to_string(),
+                    weight: 3,
+                    story: Some("Grown in {location}".to_string()),
+                    highlight_certifications: None,
+                },
+            ],
+            active: true,
+        }
+    }
+
+    #[test]
+    fn test_covers_sku_range_and_brand() {
+        let e = experiment();
+        assert!(e.covers("SG-0001", "sarson-gold"));
+        assert!(e.covers("SG-0500", "sarson-gold"));
+        assert!(!e.covers("SG-0501", "sarson-gold"));
+        assert!(!e.covers("SG-0100", "kisan-pure"));
+    }
+
+    #[test]
+    fn test_assignment_is_deterministic_and_weighted() {
+        let e = experiment();
+        let first = e.assign("visitor-1").unwrap().id.clone();
+        assert_eq!(e.assign("visitor-1").unwrap().id, first);
+
+        let treated = (0..1000)
+            .filter(|i| e.assign(&format!("visitor-{}", i)).unwrap().id == "farmer-first")
+            .count();
+        assert!((650..850).contains(&treated), "got {}", treated);
+    }
+}
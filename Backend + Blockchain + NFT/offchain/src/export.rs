@@ -0,0 +1,268 @@
+//! Tabular exports as CSV or PDF
+//!
+//! Reports build a [`Table`] and render it in the format their recipients
+//! asked for. CSV follows RFC 4180. PDF output is a plain A4 landscape
+//! listing in the built-in Courier font, so no font files or PDF library are
+//! needed; cells are truncated to fit and non-ASCII characters replaced.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Pdf,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Pdf => "application/pdf",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    pub title: String,
+    /// Lines printed under the title in PDF output (period, generation time)
+    pub notes: Vec<String>,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+pub fn render(table: &Table, format: ExportFormat) -> Vec<u8> {
+    match format {
+        ExportFormat::Csv => to_csv(table),
+        ExportFormat::Pdf => to_pdf(table),
+    }
+}
+
+// ======================== CSV ========================
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn to_csv(table: &Table) -> Vec<u8> {
+    let mut out = String::new();
+    for row in std::iter::once(&table.columns).chain(&table.rows) {
+        let fields: Vec<String> = row.iter().map(|v| csv_field(v)).collect();
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    }
+    out.into_bytes()
+}
+
+// ======================== PDF ========================
+
+const PAGE_WIDTH: u32 = 842;
+const PAGE_HEIGHT: u32 = 595;
+const MARGIN: u32 = 36;
+const FONT_SIZE: u32 = 8;
+const LINE_HEIGHT: u32 = 10;
+/// Courier glyphs are 0.6 em wide
+const CHARS_PER_LINE: usize = ((PAGE_WIDTH - 2 * MARGIN) * 10 / (FONT_SIZE * 6)) as usize;
+const LINES_PER_PAGE: usize = ((PAGE_HEIGHT - 2 * MARGIN) / LINE_HEIGHT) as usize - 2;
+const MAX_COLUMN_WIDTH: usize = 32;
+
+/// Printable ASCII only; the standard fonts have no Unicode mapping
+fn pdf_text(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if (' '..='~').contains(&c) { c } else { '?' })
+        .collect()
+}
+
+fn fit(value: &str, width: usize) -> String {
+    let value = pdf_text(value);
+    if value.len() > width {
+        format!("{}~", &value[..width.saturating_sub(1)])
+    } else {
+        format!("{:<width$}", value, width = width)
+    }
+}
+
+/// Table laid out as fixed-width text lines
+fn text_lines(table: &Table) -> Vec<String> {
+    let widths: Vec<usize> = (0..table.columns.len())
+        .map(|i| {
+            std::iter::once(&table.columns)
+                .chain(&table.rows)
+                .filter_map(|row| row.get(i))
+                .map(|v| v.chars().count())
+                .max()
+                .unwrap_or(0)
+                .clamp(1, MAX_COLUMN_WIDTH)
+        })
+        .collect();
+    let line = |row: &[String]| -> String {
+        let cells: Vec<String> = widths
+            .iter()
+            .enumerate()
+            .map(|(i, w)| fit(row.get(i).map(String::as_str).unwrap_or(""), *w))
+            .collect();
+        let mut line = cells.join("  ").trim_end().to_string();
+        line.truncate(CHARS_PER_LINE);
+        line
+    };
+
+    let mut lines = vec![pdf_text(&table.title)];
+    lines.extend(table.notes.iter().map(|n| pdf_text(n)));
+    lines.push(String::new());
+    lines.push(line(&table.columns));
+    let rule = widths.iter().sum::<usize>() + 2 * widths.len().saturating_sub(1);
+    lines.push("-".repeat(rule.min(CHARS_PER_LINE)));
+    lines.extend(table.rows.iter().map(|row| line(row)));
+    lines
+}
+
+fn escape_pdf_string(line: &str) -> String {
+    line.replace('\\', "\\\\")
+        .replace('(', "\\(")
+        .replace(')', "\\)")
+}
+
+pub fn to_pdf(table: &Table) -> Vec<u8> {
+    let lines = text_lines(table);
+    let pages: Vec<&[String]> = lines.chunks(LINES_PER_PAGE).collect();
+    let page_count = pages.len();
+
+    // 1: catalog, 2: page tree, 3: font, then a page and a content stream per page
+    let mut objects: Vec<String> = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..page_count)
+                .map(|i| format!("{} 0 R", 4 + 2 * i))
+                .collect::<Vec<_>>()
+                .join(" "),
+            page_count
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+    ];
+    for (i, page) in pages.iter().enumerate() {
+        let mut stream = format!(
+            "BT /F1 {} Tf {} TL {} {} Td\n",
+            FONT_SIZE,
+            LINE_HEIGHT,
+            MARGIN,
+            PAGE_HEIGHT - MARGIN - FONT_SIZE
+        );
+        for line in page.iter() {
+            stream.push_str(&format!("({}) Tj T*\n", escape_pdf_string(line)));
+        }
+        stream.push_str(&format!(
+            "ET\nBT /F1 {} Tf {} {} Td (Page {} of {}) Tj ET\n",
+            FONT_SIZE,
+            MARGIN,
+            MARGIN / 2,
+            i + 1,
+            page_count
+        ));
+
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH,
+            PAGE_HEIGHT,
+            5 + 2 * i
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            stream.len(),
+            stream
+        ));
+    }
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let xref_offset = out.len();
+    out.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        out.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    out.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref_offset
+    ));
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(rows: usize) -> Table {
+        Table {
+            title: "Procurement by district".to_string(),
+            notes: vec!["Period: 2025-06-02 to 2025-06-09".to_string()],
+            columns: vec!["district".to_string(), "quantity_kg".to_string()],
+            rows: (0..rows)
+                .map(|i| vec![format!("D{:03}", i), format!("{}.0", i * 10)])
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_csv_quotes_special_fields() {
+        let mut t = table(0);
+        t.rows
+            .push(vec!["Pune, MH".to_string(), "say \"hi\"".to_string()]);
+        let csv = String::from_utf8(to_csv(&t)).unwrap();
+        assert_eq!(
+            csv,
+            "district,quantity_kg\r\n\"Pune, MH\",\"say \"\"hi\"\"\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_pdf_paginates_and_indexes_objects() {
+        let pdf = String::from_utf8(to_pdf(&table(120))).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("/Count 3 "));
+        assert!(pdf.contains("(Page 3 of 3)"));
+
+        // Every xref entry points at its object header
+        let xref = &pdf[pdf.find("xref\n").unwrap()..];
+        for (i, entry) in xref
+            .lines()
+            .skip(3)
+            .take_while(|l| l.ends_with(" n "))
+            .enumerate()
+        {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", i + 1)));
+        }
+    }
+
+    #[test]
+    fn test_pdf_text_is_escaped_and_fitted() {
+        assert_eq!(escape_pdf_string("a (b) \\"), "a \\(b\\) \\\\");
+        assert_eq!(fit("Bhāvnagar", 5), "Bh?v~");
+        assert_eq!(fit("ab", 4), "ab  ");
+    }
+}
@@ -0,0 +1,576 @@
+//! Bulk farmer registration
+//!
+//! `POST /api/farmer/register/bulk` takes the farmers an FPO onboards in one
+//! go, either as CSV (`Content-Type: text/csv`, first line naming the
+//! columns) or as a JSON array of rows. Columns follow [`FarmerEntry`]:
+//! `mobile`, `name`, `location`, `state_code`, `district_code`, `land_acres`
+//! and `crop` are required; `farmer_did` defaults to the DID the app derives
+//! from the mobile number, `registration_date` to today and `verified` to
+//! true.
+//!
+//! Each row is validated on its own and the valid ones are added to the
+//! farmer registry, so one bad line does not reject the file; the response
+//! reports every row's outcome. With `?on_chain=true` the farmers not yet on
+//! chain are also registered through `batchRegisterFarmers`, up to
+//! [`CHAIN_BATCH_SIZE`] per transaction.
+
+use crate::chain::{hash_string, ChainClient};
+use crate::did;
+use crate::did_resolver::{canonical, chain_did};
+use crate::error::{format_tx_hash, ApiError, ApiResult};
+use crate::farmer_verification::{FarmerEntry, FarmerVerificationService};
+use crate::sms::normalize_mobile;
+use crate::state::AppState;
+use alloy::primitives::FixedBytes;
+use anyhow::{bail, Result};
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const DEFAULT_BULK_REGISTER_MAX_ROWS: usize = 1000;
+
+/// Farmers per `batchRegisterFarmers` transaction
+pub const CHAIN_BATCH_SIZE: usize = 100;
+
+/// Concurrent `verifyFarmer` reads before registering on chain
+const CHAIN_LOOKUP_CONCURRENCY: usize = 16;
+
+/// CSV columns parsed as numbers and booleans
+const NUMERIC_COLUMNS: [&str; 1] = ["land_acres"];
+const BOOLEAN_COLUMNS: [&str; 1] = ["verified"];
+
+/// One row of the upload
+#[derive(Debug, Deserialize)]
+pub struct FarmerRow {
+    pub mobile: String,
+    #[serde(default)]
+    pub farmer_did: Option<String>,
+    pub name: String,
+    pub location: String,
+    pub state_code: String,
+    pub district_code: String,
+    pub land_acres: f64,
+    pub crop: String,
+    #[serde(default)]
+    pub registration_date: Option<String>,
+    #[serde(default)]
+    pub verified: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BulkRegisterParams {
+    /// Also register the farmers on chain
+    #[serde(default)]
+    pub on_chain: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RowStatus {
+    /// Added to the registry
+    Registered,
+    /// Replaced an entry with the same DID
+    Updated,
+    /// Rejected by validation; nothing was stored
+    Invalid,
+    /// Valid but could not be stored
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChainStatus {
+    Registered,
+    AlreadyRegistered,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChainRegistration {
+    pub status: ChainStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ChainRegistration {
+    fn failed(error: &anyhow::Error) -> Self {
+        Self {
+            status: ChainStatus::Failed,
+            tx_hash: None,
+            explorer_url: None,
+            error: Some(format!("{:#}", error)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkRegisterResult {
+    /// 1-based position among the uploaded rows
+    pub row: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mobile: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub farmer_did: Option<String>,
+    pub status: RowStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain: Option<ChainRegistration>,
+    /// DID and crop hash of a stored row, as registered on chain
+    #[serde(skip)]
+    chain_args: Option<(FixedBytes<32>, FixedBytes<32>)>,
+}
+
+impl BulkRegisterResult {
+    fn invalid(row: usize, error: impl Into<String>) -> Self {
+        Self {
+            row,
+            mobile: None,
+            farmer_did: None,
+            status: RowStatus::Invalid,
+            error: Some(error.into()),
+            chain: None,
+            chain_args: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ChainSummary {
+    pub registered: usize,
+    pub already_registered: usize,
+    pub failed: usize,
+    pub transactions: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkRegisterResponse {
+    pub total: usize,
+    pub registered: usize,
+    pub updated: usize,
+    pub invalid: usize,
+    pub failed: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_chain: Option<ChainSummary>,
+    pub results: Vec<BulkRegisterResult>,
+}
+
+/// Records of an RFC 4180 document; quoted fields may hold commas, line
+/// breaks and doubled quotes. Blank lines are skipped.
+pub fn parse_csv(text: &str) -> Result<Vec<Vec<String>>> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+
+    let mut end_record = |record: &mut Vec<String>, field: &mut String| {
+        record.push(std::mem::take(field));
+        let done = std::mem::take(record);
+        if done != [""] {
+            records.push(done);
+        }
+    };
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => end_record(&mut record, &mut field),
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        bail!("Unterminated quoted field");
+    }
+    end_record(&mut record, &mut field);
+    Ok(records)
+}
+
+/// Rows of a CSV upload keyed by its header line
+fn csv_rows(text: &str) -> Result<Vec<Result<FarmerRow, String>>> {
+    let mut records = parse_csv(text)?.into_iter();
+    let Some(header) = records.next() else {
+        return Ok(Vec::new());
+    };
+    let columns: Vec<String> = header
+        .iter()
+        .map(|name| name.trim().to_ascii_lowercase())
+        .collect();
+
+    Ok(records
+        .map(|fields| {
+            if fields.len() != columns.len() {
+                return Err(format!(
+                    "expected {} fields, got {}",
+                    columns.len(),
+                    fields.len()
+                ));
+            }
+            let mut row = Map::new();
+            for (column, field) in columns.iter().zip(fields) {
+                let field = field.trim();
+                if field.is_empty() {
+                    continue;
+                }
+                let value = if NUMERIC_COLUMNS.contains(&column.as_str()) {
+                    field.parse::<f64>().map(Value::from).ok()
+                } else if BOOLEAN_COLUMNS.contains(&column.as_str()) {
+                    field
+                        .to_ascii_lowercase()
+                        .parse::<bool>()
+                        .map(Value::from)
+                        .ok()
+                } else {
+                    None
+                };
+                row.insert(column.clone(), value.unwrap_or_else(|| field.into()));
+            }
+            serde_json::from_value(Value::Object(row)).map_err(|e| e.to_string())
+        })
+        .collect())
+}
+
+/// Rows of the request body, CSV or a JSON array depending on Content-Type
+fn parse_rows(headers: &HeaderMap, body: &str) -> Result<Vec<Result<FarmerRow, String>>> {
+    let is_csv = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("text/csv"))
+        .unwrap_or(false);
+    if is_csv {
+        return csv_rows(body);
+    }
+    let rows: Vec<Value> = serde_json::from_str(body)
+        .map_err(|e| anyhow::anyhow!("Expected a JSON array of farmers: {}", e))?;
+    Ok(rows
+        .into_iter()
+        .map(|row| serde_json::from_value(row).map_err(|e| e.to_string()))
+        .collect())
+}
+
+fn required(value: String, field: &str) -> Result<String, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(format!("{} must not be empty", field));
+    }
+    Ok(value.to_string())
+}
+
+/// Registry entry for a row, or why it is rejected
+fn validate(row: FarmerRow, today: &str) -> Result<FarmerEntry, String> {
+    let mobile = normalize_mobile(&row.mobile);
+    if mobile.len() != 10 {
+        return Err("mobile must be a 10-digit number".to_string());
+    }
+    let farmer_did = match row.farmer_did.as_deref().map(str::trim) {
+        Some(did) if !did.is_empty() => {
+            canonical(did).map_err(|e| format!("Invalid farmer DID: {}", e))?
+        }
+        _ => did::farmer_did(&mobile),
+    };
+    if !row.land_acres.is_finite() || row.land_acres <= 0.0 {
+        return Err("land_acres must be a positive number".to_string());
+    }
+
+    Ok(FarmerEntry {
+        mobile,
+        farmer_did,
+        name: required(row.name, "name")?,
+        location: required(row.location, "location")?,
+        state_code: required(row.state_code, "state_code")?.to_ascii_uppercase(),
+        district_code: required(row.district_code, "district_code")?.to_ascii_uppercase(),
+        land_acres: row.land_acres,
+        crop: required(row.crop, "crop")?,
+        verified: row.verified.unwrap_or(true),
+        registration_date: row
+            .registration_date
+            .filter(|date| !date.trim().is_empty())
+            .unwrap_or_else(|| today.to_string()),
+        ipfscid: String::new(),
+    })
+}
+
+/// Add or update a farmer, keeping the IPFS profile of an existing entry
+async fn store(service: &FarmerVerificationService, mut entry: FarmerEntry) -> Result<RowStatus> {
+    if let Some(did) = service.verify_mobile(&entry.mobile).await? {
+        if did != entry.farmer_did {
+            bail!("Mobile number is already registered to another farmer");
+        }
+    }
+    let status = match service.get_farmer_by_did(&entry.farmer_did).await? {
+        Some(existing) => {
+            entry.ipfscid = existing.ipfscid;
+            RowStatus::Updated
+        }
+        None => RowStatus::Registered,
+    };
+    service.add_farmer(&entry).await?;
+    Ok(status)
+}
+
+/// Validate and store every row, reporting each one
+pub async fn import_rows(
+    service: &FarmerVerificationService,
+    rows: Vec<Result<FarmerRow, String>>,
+) -> Vec<BulkRegisterResult> {
+    let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
+    let mut first_row: HashMap<String, usize> = HashMap::new();
+    let mut results = Vec::with_capacity(rows.len());
+
+    for (index, row) in rows.into_iter().enumerate() {
+        let row_number = index + 1;
+        let entry = match row.and_then(|row| validate(row, &today)) {
+            Ok(entry) => entry,
+            Err(e) => {
+                results.push(BulkRegisterResult::invalid(row_number, e));
+                continue;
+            }
+        };
+
+        let mut result = BulkRegisterResult::invalid(row_number, "");
+        result.mobile = Some(entry.mobile.clone());
+        result.farmer_did = Some(entry.farmer_did.clone());
+
+        let duplicate_of = [&entry.mobile, &entry.farmer_did]
+            .into_iter()
+            .find_map(|key| first_row.get(key.as_str()).copied());
+        if let Some(first) = duplicate_of {
+            result.error = Some(format!("Duplicate of row {}", first));
+            results.push(result);
+            continue;
+        }
+        first_row.insert(entry.mobile.clone(), row_number);
+        first_row.insert(entry.farmer_did.clone(), row_number);
+
+        let did = chain_did(&entry.farmer_did).ok();
+        let crop_id_hash = hash_string(&entry.crop);
+        match store(service, entry).await {
+            Ok(status) => {
+                result.status = status;
+                result.error = None;
+                result.chain_args = did.map(|did| (did, crop_id_hash));
+            }
+            Err(e) => {
+                result.status = RowStatus::Failed;
+                result.error = Some(format!("{:#}", e));
+            }
+        }
+        results.push(result);
+    }
+    results
+}
+
+/// Register the stored rows' farmers that are not on chain yet
+async fn register_on_chain(
+    chain: &Arc<ChainClient>,
+    results: &mut [BulkRegisterResult],
+) -> ChainSummary {
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(CHAIN_LOOKUP_CONCURRENCY));
+    let mut lookups = tokio::task::JoinSet::new();
+    for (index, result) in results.iter().enumerate() {
+        if let Some((did, _)) = result.chain_args {
+            let chain = chain.clone();
+            let semaphore = semaphore.clone();
+            lookups.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                (index, chain.verify_farmer(did).await)
+            });
+        }
+    }
+
+    let mut summary = ChainSummary::default();
+    let mut pending = Vec::new();
+    while let Some(joined) = lookups.join_next().await {
+        let (index, lookup) = match joined {
+            Ok(joined) => joined,
+            Err(e) => {
+                tracing::warn!(error = %e, "Farmer lookup task failed");
+                continue;
+            }
+        };
+        match lookup {
+            Ok((true, _, _)) => {
+                summary.already_registered += 1;
+                results[index].chain = Some(ChainRegistration {
+                    status: ChainStatus::AlreadyRegistered,
+                    tx_hash: None,
+                    explorer_url: None,
+                    error: None,
+                });
+            }
+            Ok(_) => pending.push(index),
+            Err(e) => {
+                summary.failed += 1;
+                results[index].chain = Some(ChainRegistration::failed(&e));
+            }
+        }
+    }
+    pending.sort_unstable();
+
+    for batch in pending.chunks(CHAIN_BATCH_SIZE) {
+        let (dids, crop_id_hashes): (Vec<_>, Vec<_>) = batch
+            .iter()
+            .filter_map(|&index| results[index].chain_args)
+            .unzip();
+        let outcome = chain.batch_register_farmers(dids, crop_id_hashes).await;
+        if outcome.is_ok() {
+            summary.transactions += 1;
+        }
+        for &index in batch {
+            results[index].chain = Some(match &outcome {
+                Ok(receipt) => {
+                    summary.registered += 1;
+                    ChainRegistration {
+                        status: ChainStatus::Registered,
+                        tx_hash: Some(format_tx_hash(receipt.transaction_hash)),
+                        explorer_url: chain.explorer_url(receipt.transaction_hash),
+                        error: None,
+                    }
+                }
+                Err(e) => {
+                    summary.failed += 1;
+                    ChainRegistration::failed(e)
+                }
+            });
+        }
+    }
+    summary
+}
+
+pub async fn register_farmers_bulk(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<BulkRegisterParams>,
+    body: String,
+) -> ApiResult<BulkRegisterResponse> {
+    let chain = match params.on_chain {
+        true => Some(state.networks.select(&headers)?),
+        false => None,
+    };
+    let max_rows = std::env::var("BULK_REGISTER_MAX_ROWS")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_BULK_REGISTER_MAX_ROWS);
+
+    let rows = parse_rows(&headers, &body).map_err(|e| ApiError::bad_request(e.to_string()))?;
+    if rows.is_empty() {
+        return Err(ApiError::bad_request("No farmer rows in the request"));
+    }
+    if rows.len() > max_rows {
+        return Err(ApiError::bad_request(format!(
+            "At most {} farmers can be registered per request, got {}",
+            max_rows,
+            rows.len()
+        )));
+    }
+
+    tracing::info!(
+        rows = rows.len(),
+        on_chain = params.on_chain,
+        "Bulk registering farmers"
+    );
+    let mut results = import_rows(&state.farmer_verification, rows).await;
+    let on_chain = match chain {
+        Some(chain) => Some(register_on_chain(&chain, &mut results).await),
+        None => None,
+    };
+
+    let count = |status| results.iter().filter(|r| r.status == status).count();
+    Ok(Json(BulkRegisterResponse {
+        total: results.len(),
+        registered: count(RowStatus::Registered),
+        updated: count(RowStatus::Updated),
+        invalid: count(RowStatus::Invalid),
+        failed: count(RowStatus::Failed),
+        on_chain,
+        results,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqliteConnectOptions;
+
+    #[test]
+    fn test_parse_csv_handles_quotes_and_blank_lines() {
+        let text = "\u{feff}a,b\r\n\"x, y\",\"say \"\"hi\"\"\"\n\n\"two\nlines\",\n";
+        assert_eq!(
+            parse_csv(text).unwrap(),
+            [
+                vec!["a", "b"],
+                vec!["x, y", "say \"hi\""],
+                vec!["two\nlines", ""]
+            ]
+        );
+        assert!(parse_csv("a,\"open").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_import_reports_each_row() {
+        let options = "sqlite::memory:".parse::<SqliteConnectOptions>().unwrap();
+        let service = FarmerVerificationService::connect(options, 1)
+            .await
+            .unwrap();
+        let csv = "Mobile,Name,Location,State_Code,District_Code,Land_Acres,Crop\n\
+                   +91 98765 43210,Asha,Latur,mh,mh025,2.5,soybean\n\
+                   9876543210,Asha again,Latur,MH,MH025,2.5,soybean\n\
+                   12345,Ravi,Akola,MH,MH001,1,soybean\n\
+                   9123456780,,Akola,MH,MH001,1,soybean\n\
+                   9123456789,Meena,Akola,MH,MH001,lots,soybean\n";
+        let results = import_rows(&service, csv_rows(csv).unwrap()).await;
+
+        let statuses: Vec<RowStatus> = results.iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                RowStatus::Registered,
+                RowStatus::Invalid,
+                RowStatus::Invalid,
+                RowStatus::Invalid,
+                RowStatus::Invalid
+            ]
+        );
+        assert_eq!(results[1].error.as_deref(), Some("Duplicate of row 1"));
+        assert_eq!(
+            results[0].farmer_did.as_deref(),
+            Some(did::farmer_did("9876543210").as_str())
+        );
+
+        let stored = service
+            .get_farmer_by_mobile("9876543210")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((stored.state_code.as_str(), stored.land_acres), ("MH", 2.5));
+        assert_eq!(service.total_farmers().await.unwrap(), 1);
+
+        // Re-importing the same sheet updates rather than duplicates
+        let again = import_rows(&service, csv_rows(csv).unwrap()).await;
+        assert_eq!(again[0].status, RowStatus::Updated);
+    }
+}
@@ -0,0 +1,165 @@
+//! Farmer profile edits
+//!
+//! `PATCH /api/farmer/:farmer_did` changes a farmer's `name`, `location`,
+//! `land_acres` or `crop` after onboarding. The updated profile (without the
+//! mobile number) is pinned to IPFS and becomes the farmer's `ipfscid`; with
+//! `"on_chain": true` its CID is also announced in a `FarmerUpdated` event
+//! via `updateFarmerMetadata`, which records the new crop hash.
+//!
+//! Every edit keeps the values it replaced, who made it and its CID and
+//! transaction in the registry: `GET /api/farmer/:farmer_did/profile/history`.
+//! Farmers may only edit and read their own profile.
+
+use crate::auth::{self, Principal};
+use crate::chain::hash_string;
+use crate::did_resolver::chain_did;
+use crate::error::{format_tx_hash, ipfs_gateway_url, ApiError, ApiResult};
+use crate::farmer_verification::{FarmerEntry, ProfileChange, ProfileUpdate};
+use crate::state::AppState;
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateProfileRequest {
+    #[serde(flatten)]
+    pub update: ProfileUpdate,
+    /// Also emit `FarmerUpdated` with the new profile CID
+    #[serde(default)]
+    pub on_chain: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UpdateProfileResponse {
+    pub farmer: FarmerEntry,
+    pub change: ProfileChange,
+    pub ipfs_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
+    /// Why `updateFarmerMetadata` failed; the registry edit is kept
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_error: Option<String>,
+}
+
+/// Name recorded as the author of an edit
+fn editor(principal: Option<&Principal>) -> String {
+    match principal {
+        Some(Principal::Admin) => "admin".to_string(),
+        Some(Principal::User(claims)) => format!("user:{}", claims.sub),
+        Some(Principal::Delegate(d)) => format!("delegate:{}", d.id),
+        Some(Principal::ApiKey { id, .. }) => format!("api_key:{}", id),
+        None => "anonymous".to_string(),
+    }
+}
+
+/// Public profile document pinned for an edit
+fn profile_metadata(farmer: &FarmerEntry, previous_cid: &str) -> Value {
+    json!({
+        "farmer_did": farmer.farmer_did,
+        "name": farmer.name,
+        "location": farmer.location,
+        "state_code": farmer.state_code,
+        "district_code": farmer.district_code,
+        "land_acres": farmer.land_acres,
+        "crop": farmer.crop,
+        "registration_date": farmer.registration_date,
+        "updated_at": chrono::Utc::now().to_rfc3339(),
+        "previous_cid": (!previous_cid.is_empty()).then_some(previous_cid),
+    })
+}
+
+pub async fn update_farmer_profile(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    principal: Option<Extension<Principal>>,
+    Path(farmer_did): Path<String>,
+    Json(payload): Json<UpdateProfileRequest>,
+) -> ApiResult<UpdateProfileResponse> {
+    let principal = principal.map(|Extension(p)| p);
+    auth::check_farmer_access(principal.as_ref(), Some(&farmer_did))?;
+    let chain = match payload.on_chain {
+        true => {
+            let did = chain_did(&farmer_did).map_err(ApiError::invalid_did)?;
+            Some((state.networks.select(&headers)?, did))
+        }
+        false => None,
+    };
+
+    let registry = &state.farmer_verification;
+    let farmer = registry
+        .get_farmer_by_did(&farmer_did)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Farmer {} not found", farmer_did)))?;
+    let mut diff = payload
+        .update
+        .apply(&farmer)
+        .map_err(|e| ApiError::bad_request(e.to_string()))?
+        .ok_or_else(|| ApiError::bad_request("No profile field changes"))?;
+
+    let cid = state
+        .ipfs_client
+        .upload_json(&profile_metadata(&diff.entry, &farmer.ipfscid))
+        .await
+        .map_err(ApiError::ipfs_upload_failed)?;
+    diff.entry.ipfscid = cid.clone();
+
+    let mut change = registry
+        .update_profile(&farmer, &diff, &editor(principal.as_ref()))
+        .await?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::CONFLICT,
+                "Profile was changed by another request; reload and retry",
+            )
+        })?;
+
+    let mut explorer_url = None;
+    let mut chain_error = None;
+    if let Some((chain, did)) = chain {
+        match chain
+            .update_farmer_metadata(did, hash_string(&diff.entry.crop), cid.clone())
+            .await
+        {
+            Ok(receipt) => {
+                let tx_hash = format_tx_hash(receipt.transaction_hash);
+                registry.record_profile_tx(change.id, &tx_hash).await?;
+                explorer_url = chain.explorer_url(receipt.transaction_hash);
+                change.tx_hash = Some(tx_hash);
+            }
+            Err(e) => {
+                tracing::error!(
+                    farmer_did = %farmer_did,
+                    error = %format!("{:#}", e),
+                    "Farmer profile saved but updateFarmerMetadata failed"
+                );
+                chain_error = Some(format!("{:#}", e));
+            }
+        }
+    }
+
+    Ok(Json(UpdateProfileResponse {
+        farmer: diff.entry,
+        change,
+        ipfs_url: ipfs_gateway_url(&cid),
+        explorer_url,
+        chain_error,
+    }))
+}
+
+pub async fn profile_history(
+    State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
+    Path(farmer_did): Path<String>,
+) -> ApiResult<Vec<ProfileChange>> {
+    auth::check_farmer_access(principal.as_deref(), Some(&farmer_did))?;
+    Ok(Json(
+        state
+            .farmer_verification
+            .profile_changes(&farmer_did)
+            .await?,
+    ))
+}
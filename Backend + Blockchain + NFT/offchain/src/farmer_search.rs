@@ -0,0 +1,64 @@
+//! Farmer listing for operators
+//!
+//! `GET /api/farmers` pages through the farmer registry ordered by DID, with
+//! optional filters: `state_code`, `district_code`, `crop`, `verified` and a
+//! land range `min_acres`/`max_acres` (inclusive). Pagination follows
+//! [`crate::pagination`]; the cursor is the DID of the last farmer returned.
+
+use crate::error::{ApiError, ApiResult};
+use crate::farmer_verification::{FarmerEntry, FarmerFilter};
+use crate::pagination::{decode_cursor, encode_cursor, Page, PageParams};
+use crate::state::AppState;
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+
+const CURSOR_SCOPE: &str = "farmers";
+
+pub async fn list_farmers(
+    State(state): State<AppState>,
+    Query(filter): Query<FarmerFilter>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<FarmerEntry>> {
+    for acres in [filter.min_acres, filter.max_acres].into_iter().flatten() {
+        if !acres.is_finite() || acres < 0.0 {
+            return Err(ApiError::bad_request(
+                "Land range must be a non-negative number of acres",
+            ));
+        }
+    }
+    if let (Some(min), Some(max)) = (filter.min_acres, filter.max_acres) {
+        if min > max {
+            return Err(ApiError::bad_request(
+                "`min_acres` must not be above `max_acres`",
+            ));
+        }
+    }
+
+    let limit = params.limit();
+    let after: Option<String> = params
+        .cursor
+        .as_deref()
+        .map(|cursor| decode_cursor(CURSOR_SCOPE, cursor))
+        .transpose()?;
+
+    let mut items = state
+        .farmer_verification
+        .search_farmers(&filter, after.as_deref(), limit + 1)
+        .await?;
+    let has_more = items.len() > limit;
+    items.truncate(limit);
+
+    let next_cursor = match (has_more, items.last()) {
+        (true, Some(last)) => Some(encode_cursor(CURSOR_SCOPE, &last.farmer_did)?),
+        _ => None,
+    };
+
+    Ok(Json(Page {
+        items,
+        next_cursor,
+        has_more,
+        limit,
+    }))
+}
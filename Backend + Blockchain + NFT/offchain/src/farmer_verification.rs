@@ -1,10 +1,23 @@
+use crate::analytics;
+use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::{Map, Value};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use sqlx::{QueryBuilder, Row, Sqlite};
 use std::fs;
 use std::path::Path;
+use std::time::Duration;
+
+/// SQLite database holding the farmer registry
+pub const DB_PATH: &str = "data/farmers.db";
+/// Legacy JSON registry, imported once into an empty database
+pub(crate) const LEGACY_JSON_PATH: &str = "data/farmers_db.json";
+
+const FARMER_COLUMNS: &str = "mobile, farmer_did, name, location, state_code, district_code, \
+     land_acres, crop, verified, registration_date, ipfscid";
 
 /// Farmer entry in the verification database
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct FarmerEntry {
     pub mobile: String,
     pub farmer_did: String,
@@ -19,7 +32,104 @@ pub struct FarmerEntry {
     pub ipfscid: String,
 }
 
-/// Metadata for the farmer database
+/// Editable profile fields; `None` leaves a field as it is
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProfileUpdate {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub land_acres: Option<f64>,
+    #[serde(default)]
+    pub crop: Option<String>,
+}
+
+/// A farmer with a [`ProfileUpdate`] applied, and the fields it changes
+#[derive(Debug, Clone)]
+pub struct ProfileDiff {
+    pub entry: FarmerEntry,
+    /// Replaced values of the changed fields
+    pub previous: Map<String, Value>,
+    pub updated: Map<String, Value>,
+}
+
+type TextField = fn(&mut FarmerEntry) -> &mut String;
+
+impl ProfileUpdate {
+    /// `farmer` with this update applied; `None` when no field changes
+    pub fn apply(&self, farmer: &FarmerEntry) -> Result<Option<ProfileDiff>> {
+        let mut diff = ProfileDiff {
+            entry: farmer.clone(),
+            previous: Map::new(),
+            updated: Map::new(),
+        };
+
+        let text_fields: [(&str, &Option<String>, TextField); 3] = [
+            ("name", &self.name, |f| &mut f.name),
+            ("location", &self.location, |f| &mut f.location),
+            ("crop", &self.crop, |f| &mut f.crop),
+        ];
+        for (field, value, slot) in text_fields {
+            let Some(value) = value.as_deref().map(str::trim) else {
+                continue;
+            };
+            if value.is_empty() {
+                bail!("{} must not be empty", field);
+            }
+            let current = slot(&mut diff.entry);
+            if current != value {
+                diff.previous.insert(field.into(), current.as_str().into());
+                diff.updated.insert(field.into(), value.into());
+                *current = value.to_string();
+            }
+        }
+
+        if let Some(acres) = self.land_acres {
+            if !acres.is_finite() || acres <= 0.0 {
+                bail!("land_acres must be a positive number");
+            }
+            if acres != diff.entry.land_acres {
+                diff.previous
+                    .insert("land_acres".into(), diff.entry.land_acres.into());
+                diff.updated.insert("land_acres".into(), acres.into());
+                diff.entry.land_acres = acres;
+            }
+        }
+
+        Ok((!diff.updated.is_empty()).then_some(diff))
+    }
+}
+
+/// One stored profile edit
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileChange {
+    pub id: i64,
+    pub farmer_did: String,
+    pub changed_at: String,
+    pub changed_by: String,
+    pub previous: Value,
+    pub updated: Value,
+    /// CID of the profile pinned with this edit
+    pub ipfscid: String,
+    /// `updateFarmerMetadata` transaction, when the edit was sent on chain
+    pub tx_hash: Option<String>,
+}
+
+/// Filters for listing farmers; every one given must match
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FarmerFilter {
+    pub state_code: Option<String>,
+    pub district_code: Option<String>,
+    pub crop: Option<String>,
+    pub verified: Option<bool>,
+    /// Inclusive
+    pub min_acres: Option<f64>,
+    /// Inclusive
+    pub max_acres: Option<f64>,
+}
+
+/// Metadata for the legacy JSON farmer database
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FarmerDbMetadata {
     pub version: String,
@@ -28,169 +138,403 @@ pub struct FarmerDbMetadata {
     pub description: String,
 }
 
-/// Complete farmer database structure
+/// Legacy JSON farmer database structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FarmerDatabase {
     pub farmers: Vec<FarmerEntry>,
     pub metadata: FarmerDbMetadata,
 }
 
-/// In-memory farmer verification service
+/// Farmer verification service backed by SQLite
+///
+/// Every call is a single statement against the pool, so concurrent handlers
+/// need no application-level lock and only the rows they touch are loaded.
 #[derive(Debug, Clone)]
 pub struct FarmerVerificationService {
-    mobile_to_did: HashMap<String, String>,
-    did_to_farmer: HashMap<String, FarmerEntry>,
+    pool: SqlitePool,
+    /// Read-only connections for reports (see [`crate::analytics`])
+    analytics: SqlitePool,
 }
 
 impl FarmerVerificationService {
-    /// Load the farmer database from a JSON file
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, anyhow::Error> {
-        let content = fs::read_to_string(path)?;
-        let db: FarmerDatabase = serde_json::from_str(&content)?;
-
-        let mut mobile_to_did = HashMap::new();
-        let mut did_to_farmer = HashMap::new();
-
-        for farmer in db.farmers {
-            mobile_to_did.insert(farmer.mobile.clone(), farmer.farmer_did.clone());
-            did_to_farmer.insert(farmer.farmer_did.clone(), farmer.clone());
+    /// Open `data/farmers.db`, apply migrations and import the legacy JSON
+    /// registry if the database is still empty
+    pub async fn open() -> Result<Self> {
+        if let Some(dir) = Path::new(DB_PATH).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let options = SqliteConnectOptions::new()
+            .filename(DB_PATH)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(Duration::from_secs(5));
+        let mut service = Self::connect(options.clone(), 8).await?;
+        service.analytics = analytics::open_pool(&options).await?;
+
+        if service.total_farmers().await? == 0 && Path::new(LEGACY_JSON_PATH).exists() {
+            let imported = service.import_json(LEGACY_JSON_PATH).await?;
+            tracing::info!(
+                imported,
+                from = LEGACY_JSON_PATH,
+                "Imported legacy farmer database; the JSON file is no longer read"
+            );
         }
 
+        Ok(service)
+    }
+
+    /// Connect with the given options and bring the schema up to date
+    pub async fn connect(options: SqliteConnectOptions, max_connections: u32) -> Result<Self> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await
+            .context("Failed to open farmer database")?;
+        sqlx::migrate!("./migrations/farmers")
+            .run(&pool)
+            .await
+            .context("Failed to migrate farmer database")?;
         Ok(Self {
-            mobile_to_did,
-            did_to_farmer,
+            analytics: pool.clone(),
+            pool,
         })
     }
 
-    /// Create a new service with empty database
-    pub fn new() -> Self {
-        Self {
-            mobile_to_did: HashMap::new(),
-            did_to_farmer: HashMap::new(),
+    /// Upsert every farmer from a JSON file in the legacy format
+    pub async fn import_json<P: AsRef<Path>>(&self, path: P) -> Result<usize> {
+        let content = fs::read_to_string(path)?;
+        let db: FarmerDatabase = serde_json::from_str(&content)?;
+
+        let mut tx = self.pool.begin().await?;
+        for farmer in &db.farmers {
+            upsert(&mut *tx, farmer).await?;
         }
+        tx.commit().await?;
+
+        Ok(db.farmers.len())
     }
 
     /// Verify if a mobile number exists and return the associated farmer DID
-    pub fn verify_mobile(&self, mobile: &str) -> Option<&String> {
-        self.mobile_to_did.get(mobile)
+    pub async fn verify_mobile(&self, mobile: &str) -> Result<Option<String>> {
+        Ok(
+            sqlx::query_scalar("SELECT farmer_did FROM farmers WHERE mobile = $1")
+                .bind(mobile)
+                .fetch_optional(&self.pool)
+                .await?,
+        )
     }
 
     /// Check if a farmer DID is registered
-    pub fn is_did_registered(&self, farmer_did: &str) -> bool {
-        self.did_to_farmer.contains_key(farmer_did)
+    pub async fn is_did_registered(&self, farmer_did: &str) -> Result<bool> {
+        Ok(self.get_farmer_by_did(farmer_did).await?.is_some())
     }
 
     /// Get farmer details by DID
-    pub fn get_farmer_by_did(&self, farmer_did: &str) -> Option<&FarmerEntry> {
-        self.did_to_farmer.get(farmer_did)
+    pub async fn get_farmer_by_did(&self, farmer_did: &str) -> Result<Option<FarmerEntry>> {
+        let sql = format!(
+            "SELECT {} FROM farmers WHERE farmer_did = $1",
+            FARMER_COLUMNS
+        );
+        Ok(sqlx::query_as(&sql)
+            .bind(farmer_did)
+            .fetch_optional(&self.pool)
+            .await?)
     }
 
     /// Get farmer details by mobile number
-    pub fn get_farmer_by_mobile(&self, mobile: &str) -> Option<&FarmerEntry> {
-        self.mobile_to_did
-            .get(mobile)
-            .and_then(|did| self.did_to_farmer.get(did))
+    pub async fn get_farmer_by_mobile(&self, mobile: &str) -> Result<Option<FarmerEntry>> {
+        let sql = format!("SELECT {} FROM farmers WHERE mobile = $1", FARMER_COLUMNS);
+        Ok(sqlx::query_as(&sql)
+            .bind(mobile)
+            .fetch_optional(&self.pool)
+            .await?)
     }
 
     /// Verify both mobile and DID match
-    pub fn verify_mobile_did_pair(&self, mobile: &str, farmer_did: &str) -> bool {
-        self.mobile_to_did
-            .get(mobile)
-            .map(|did| did == farmer_did)
-            .unwrap_or(false)
+    pub async fn verify_mobile_did_pair(&self, mobile: &str, farmer_did: &str) -> Result<bool> {
+        Ok(self.verify_mobile(mobile).await?.as_deref() == Some(farmer_did))
     }
 
-    /// Add a new farmer to the in-memory database
-    pub fn add_farmer(&mut self, farmer: FarmerEntry) {
-        self.mobile_to_did
-            .insert(farmer.mobile.clone(), farmer.farmer_did.clone());
-        self.did_to_farmer.insert(farmer.farmer_did.clone(), farmer);
+    /// Add a farmer, replacing any existing entry with the same DID
+    pub async fn add_farmer(&self, farmer: &FarmerEntry) -> Result<()> {
+        upsert(&self.pool, farmer).await
     }
 
     /// Check if a mobile number is verified
-    pub fn is_mobile_verified(&self, mobile: &str) -> bool {
-        self.get_farmer_by_mobile(mobile)
+    pub async fn is_mobile_verified(&self, mobile: &str) -> Result<bool> {
+        Ok(self
+            .get_farmer_by_mobile(mobile)
+            .await?
             .map(|farmer| farmer.verified)
-            .unwrap_or(false)
+            .unwrap_or(false))
     }
 
     /// Get total number of registered farmers
-    pub fn total_farmers(&self) -> usize {
-        self.did_to_farmer.len()
+    pub async fn total_farmers(&self) -> Result<usize> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM farmers")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count as usize)
     }
 
-    /// Get all registered mobile numbers
-    pub fn get_all_mobiles(&self) -> Vec<String> {
-        self.mobile_to_did.keys().cloned().collect()
+    /// All registered farmers, ordered by DID
+    pub async fn farmers(&self) -> Result<Vec<FarmerEntry>> {
+        let sql = format!("SELECT {} FROM farmers ORDER BY farmer_did", FARMER_COLUMNS);
+        Ok(sqlx::query_as(&sql).fetch_all(&self.pool).await?)
     }
 
-    /// Get all registered farmer DIDs
-    pub fn get_all_dids(&self) -> Vec<String> {
-        self.did_to_farmer.keys().cloned().collect()
+    /// [`Self::farmers`] from an analytics snapshot
+    pub async fn farmers_snapshot(&self) -> Result<Vec<FarmerEntry>> {
+        let sql = format!("SELECT {} FROM farmers ORDER BY farmer_did", FARMER_COLUMNS);
+        Ok(sqlx::query_as(&sql).fetch_all(&self.analytics).await?)
     }
 
-    /// Update IPFS CID for a farmer by mobile number
-    pub fn update_farmer_ipfscid_by_mobile(&mut self, mobile: &str, ipfscid: &str) -> Result<(), anyhow::Error> {
-        if let Some(farmer_did) = self.mobile_to_did.get(mobile).cloned() {
-            if let Some(farmer) = self.did_to_farmer.get_mut(&farmer_did) {
-                farmer.ipfscid = ipfscid.to_string();
-                tracing::info!(
-                    mobile = %mobile,
-                    farmer_did = %farmer_did,
-                    ipfscid = %ipfscid,
-                    "Updated farmer IPFS CID"
-                );
-                Ok(())
-            } else {
-                Err(anyhow::anyhow!("Farmer DID {} not found in database", farmer_did))
+    /// Up to `limit` farmers matching `filter` with a DID after `after`,
+    /// ordered by DID; codes and crop compare case-insensitively
+    pub async fn search_farmers(
+        &self,
+        filter: &FarmerFilter,
+        after: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<FarmerEntry>> {
+        let mut query = QueryBuilder::<Sqlite>::new(format!(
+            "SELECT {} FROM farmers WHERE 1 = 1",
+            FARMER_COLUMNS
+        ));
+        for (column, value) in [
+            ("state_code", &filter.state_code),
+            ("district_code", &filter.district_code),
+            ("crop", &filter.crop),
+        ] {
+            if let Some(value) = value {
+                query
+                    .push(format!(" AND {} = ", column))
+                    .push_bind(value.trim().to_string())
+                    .push(" COLLATE NOCASE");
             }
-        } else {
-            Err(anyhow::anyhow!("Mobile number {} not found in database", mobile))
         }
+        if let Some(verified) = filter.verified {
+            query.push(" AND verified = ").push_bind(verified);
+        }
+        if let Some(min) = filter.min_acres {
+            query.push(" AND land_acres >= ").push_bind(min);
+        }
+        if let Some(max) = filter.max_acres {
+            query.push(" AND land_acres <= ").push_bind(max);
+        }
+        if let Some(after) = after {
+            query
+                .push(" AND farmer_did > ")
+                .push_bind(after.to_string());
+        }
+        query
+            .push(" ORDER BY farmer_did LIMIT ")
+            .push_bind(limit as i64);
+
+        Ok(query.build_query_as().fetch_all(&self.pool).await?)
+    }
+
+    /// Update IPFS CID for a farmer by mobile number
+    pub async fn update_farmer_ipfscid_by_mobile(&self, mobile: &str, ipfscid: &str) -> Result<()> {
+        let farmer_did: Option<String> = sqlx::query_scalar(
+            "UPDATE farmers SET ipfscid = $1, updated_at = CURRENT_TIMESTAMP \
+             WHERE mobile = $2 RETURNING farmer_did",
+        )
+        .bind(ipfscid)
+        .bind(mobile)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let farmer_did = farmer_did
+            .ok_or_else(|| anyhow::anyhow!("Mobile number {} not found in database", mobile))?;
+        tracing::info!(
+            mobile = %mobile,
+            farmer_did = %farmer_did,
+            ipfscid = %ipfscid,
+            "Updated farmer IPFS CID"
+        );
+        Ok(())
     }
 
     /// Update IPFS CID for a farmer by DID
-    pub fn update_farmer_ipfscid_by_did(&mut self, farmer_did: &str, ipfscid: &str) -> Result<(), anyhow::Error> {
-        if let Some(farmer) = self.did_to_farmer.get_mut(farmer_did) {
-            farmer.ipfscid = ipfscid.to_string();
-            tracing::info!(
-                farmer_did = %farmer_did,
-                ipfscid = %ipfscid,
-                "Updated farmer IPFS CID"
-            );
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!("Farmer DID {} not found in database", farmer_did))
+    #[cfg(test)]
+    pub async fn update_farmer_ipfscid_by_did(
+        &self,
+        farmer_did: &str,
+        ipfscid: &str,
+    ) -> Result<()> {
+        let updated = sqlx::query(
+            "UPDATE farmers SET ipfscid = $1, updated_at = CURRENT_TIMESTAMP \
+             WHERE farmer_did = $2",
+        )
+        .bind(ipfscid)
+        .bind(farmer_did)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        if updated == 0 {
+            return Err(anyhow::anyhow!(
+                "Farmer DID {} not found in database",
+                farmer_did
+            ));
         }
+        tracing::info!(
+            farmer_did = %farmer_did,
+            ipfscid = %ipfscid,
+            "Updated farmer IPFS CID"
+        );
+        Ok(())
     }
 
-    /// Save the current database state to a JSON file
-    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), anyhow::Error> {
-        let farmers: Vec<FarmerEntry> = self.did_to_farmer.values().cloned().collect();
+    /// Store an edited profile and the values it replaced. Returns `None`
+    /// without writing when the stored profile no longer matches `current`,
+    /// i.e. another edit landed since it was read.
+    pub async fn update_profile(
+        &self,
+        current: &FarmerEntry,
+        diff: &ProfileDiff,
+        changed_by: &str,
+    ) -> Result<Option<ProfileChange>> {
+        let updated = &diff.entry;
+        let mut tx = self.pool.begin().await?;
+        let rows = sqlx::query(
+            "UPDATE farmers SET name = $1, location = $2, land_acres = $3, crop = $4, \
+             ipfscid = $5, updated_at = CURRENT_TIMESTAMP \
+             WHERE farmer_did = $6 AND name = $7 AND location = $8 AND land_acres = $9 \
+             AND crop = $10 AND ipfscid = $11",
+        )
+        .bind(&updated.name)
+        .bind(&updated.location)
+        .bind(updated.land_acres)
+        .bind(&updated.crop)
+        .bind(&updated.ipfscid)
+        .bind(&current.farmer_did)
+        .bind(&current.name)
+        .bind(&current.location)
+        .bind(current.land_acres)
+        .bind(&current.crop)
+        .bind(&current.ipfscid)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if rows == 0 {
+            return Ok(None);
+        }
 
-        let metadata = FarmerDbMetadata {
-            version: "1.0".to_string(),
-            last_updated: chrono::Utc::now().format("%Y-%m-%d").to_string(),
-            total_farmers: farmers.len(),
-            description: "Farmer verification database with SHA-256 hashed DIDs from mobile numbers".to_string(),
+        let mut change = ProfileChange {
+            id: 0,
+            farmer_did: current.farmer_did.clone(),
+            changed_at: chrono::Utc::now().to_rfc3339(),
+            changed_by: changed_by.to_string(),
+            previous: Value::Object(diff.previous.clone()),
+            updated: Value::Object(diff.updated.clone()),
+            ipfscid: updated.ipfscid.clone(),
+            tx_hash: None,
         };
+        change.id = sqlx::query_scalar(
+            "INSERT INTO farmer_profile_changes \
+             (farmer_did, changed_at, changed_by, previous, updated, ipfscid) \
+             VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+        )
+        .bind(&change.farmer_did)
+        .bind(&change.changed_at)
+        .bind(&change.changed_by)
+        .bind(change.previous.to_string())
+        .bind(change.updated.to_string())
+        .bind(&change.ipfscid)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        tracing::info!(
+            farmer_did = %change.farmer_did,
+            changed_by = %change.changed_by,
+            fields = ?diff.updated.keys().collect::<Vec<_>>(),
+            "Updated farmer profile"
+        );
+        Ok(Some(change))
+    }
 
-        let db = FarmerDatabase {
-            farmers,
-            metadata,
-        };
+    /// Attach the on-chain transaction of a profile edit
+    pub async fn record_profile_tx(&self, change_id: i64, tx_hash: &str) -> Result<()> {
+        sqlx::query("UPDATE farmer_profile_changes SET tx_hash = $1 WHERE id = $2")
+            .bind(tx_hash)
+            .bind(change_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
 
-        let json_content = serde_json::to_string_pretty(&db)?;
-        fs::write(path, json_content)?;
+    /// Profile edits of a farmer, oldest first
+    pub async fn profile_changes(&self, farmer_did: &str) -> Result<Vec<ProfileChange>> {
+        let rows = sqlx::query(
+            "SELECT id, farmer_did, changed_at, changed_by, previous, updated, ipfscid, tx_hash \
+             FROM farmer_profile_changes WHERE farmer_did = $1 ORDER BY id",
+        )
+        .bind(farmer_did)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(ProfileChange {
+                    id: row.try_get("id")?,
+                    farmer_did: row.try_get("farmer_did")?,
+                    changed_at: row.try_get("changed_at")?,
+                    changed_by: row.try_get("changed_by")?,
+                    previous: serde_json::from_str(row.try_get("previous")?)?,
+                    updated: serde_json::from_str(row.try_get("updated")?)?,
+                    ipfscid: row.try_get("ipfscid")?,
+                    tx_hash: row.try_get("tx_hash")?,
+                })
+            })
+            .collect()
+    }
 
+    /// Write a transactionally consistent copy of the database to `path`
+    pub async fn backup_to(&self, path: &Path) -> Result<()> {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        sqlx::query("VACUUM INTO $1")
+            .bind(path.to_string_lossy().into_owned())
+            .execute(&self.pool)
+            .await
+            .context("Failed to back up farmer database")?;
         Ok(())
     }
 }
 
-impl Default for FarmerVerificationService {
-    fn default() -> Self {
-        Self::new()
-    }
+async fn upsert<'e, E>(executor: E, farmer: &FarmerEntry) -> Result<()>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    let sql = format!(
+        "INSERT INTO farmers ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) \
+         ON CONFLICT (farmer_did) DO UPDATE SET \
+         mobile = excluded.mobile, name = excluded.name, location = excluded.location, \
+         state_code = excluded.state_code, district_code = excluded.district_code, \
+         land_acres = excluded.land_acres, crop = excluded.crop, verified = excluded.verified, \
+         registration_date = excluded.registration_date, ipfscid = excluded.ipfscid, \
+         updated_at = CURRENT_TIMESTAMP",
+        FARMER_COLUMNS
+    );
+    sqlx::query(&sql)
+        .bind(&farmer.mobile)
+        .bind(&farmer.farmer_did)
+        .bind(&farmer.name)
+        .bind(&farmer.location)
+        .bind(&farmer.state_code)
+        .bind(&farmer.district_code)
+        .bind(farmer.land_acres)
+        .bind(&farmer.crop)
+        .bind(farmer.verified)
+        .bind(&farmer.registration_date)
+        .bind(&farmer.ipfscid)
+        .execute(executor)
+        .await?;
+    Ok(())
 }
 
 /// Request structure for mobile verification endpoint
@@ -215,17 +559,16 @@ pub struct VerifyMobileResponse {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_new_service() {
-        let service = FarmerVerificationService::new();
-        assert_eq!(service.total_farmers(), 0);
+    async fn memory_service() -> FarmerVerificationService {
+        // One connection: every in-memory connection is a separate database
+        let options = "sqlite::memory:".parse::<SqliteConnectOptions>().unwrap();
+        FarmerVerificationService::connect(options, 1)
+            .await
+            .unwrap()
     }
 
-    #[test]
-    fn test_add_and_verify_farmer() {
-        let mut service = FarmerVerificationService::new();
-
-        let farmer = FarmerEntry {
+    fn test_farmer() -> FarmerEntry {
+        FarmerEntry {
             mobile: "9876543210".to_string(),
             farmer_did: "0x123abc".to_string(),
             name: "Test Farmer".to_string(),
@@ -237,22 +580,176 @@ mod tests {
             verified: true,
             registration_date: "2024-01-01".to_string(),
             ipfscid: "".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_new_service() {
+        let service = memory_service().await;
+        assert_eq!(service.total_farmers().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_add_and_verify_farmer() {
+        let service = memory_service().await;
+        service.add_farmer(&test_farmer()).await.unwrap();
+
+        assert_eq!(service.total_farmers().await.unwrap(), 1);
+        assert!(service.is_mobile_verified("9876543210").await.unwrap());
+        assert!(service
+            .verify_mobile_did_pair("9876543210", "0x123abc")
+            .await
+            .unwrap());
+
+        let retrieved = service
+            .get_farmer_by_mobile("9876543210")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(retrieved.name, "Test Farmer");
+        assert_eq!(retrieved.land_acres, 5.0);
+    }
+
+    #[tokio::test]
+    async fn test_profile_update_keeps_previous_values() {
+        let service = memory_service().await;
+        let farmer = test_farmer();
+        service.add_farmer(&farmer).await.unwrap();
+
+        let update = ProfileUpdate {
+            land_acres: Some(7.5),
+            crop: Some(" mustard ".to_string()),
+            name: Some("Test Farmer".to_string()),
+            ..Default::default()
+        };
+        let diff = update.apply(&farmer).unwrap().unwrap();
+        assert_eq!(diff.updated.len(), 2);
+        assert!(service
+            .update_profile(&farmer, &diff, "admin")
+            .await
+            .unwrap()
+            .is_some());
+
+        // A second edit based on the stale profile is refused
+        assert!(service
+            .update_profile(&farmer, &diff, "admin")
+            .await
+            .unwrap()
+            .is_none());
+
+        let changes = service.profile_changes("0x123abc").await.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes[0].previous,
+            serde_json::json!({ "crop": "wheat", "land_acres": 5.0 })
+        );
+        let stored = service
+            .get_farmer_by_did("0x123abc")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((stored.crop.as_str(), stored.land_acres), ("mustard", 7.5));
+
+        assert!(update.apply(&stored).unwrap().is_none());
+        let blank = ProfileUpdate {
+            name: Some(" ".to_string()),
+            ..Default::default()
         };
+        assert!(blank.apply(&stored).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_farmers_filters_and_pages_by_did() {
+        let service = memory_service().await;
+        for (i, (state, acres)) in [("PB", 2.0), ("pb", 6.0), ("HR", 4.0), ("PB", 9.0)]
+            .into_iter()
+            .enumerate()
+        {
+            service
+                .add_farmer(&FarmerEntry {
+                    mobile: format!("987654321{}", i),
+                    farmer_did: format!("0x{}", i),
+                    state_code: state.to_string(),
+                    land_acres: acres,
+                    ..test_farmer()
+                })
+                .await
+                .unwrap();
+        }
 
-        service.add_farmer(farmer.clone());
+        let filter = FarmerFilter {
+            state_code: Some("PB".to_string()),
+            min_acres: Some(2.0),
+            max_acres: Some(8.0),
+            ..Default::default()
+        };
+        let dids = |farmers: Vec<FarmerEntry>| -> Vec<String> {
+            farmers.into_iter().map(|f| f.farmer_did).collect()
+        };
+        let first = service.search_farmers(&filter, None, 1).await.unwrap();
+        assert_eq!(dids(first), vec!["0x0"]);
+        let rest = service
+            .search_farmers(&filter, Some("0x0"), 10)
+            .await
+            .unwrap();
+        assert_eq!(dids(rest), vec!["0x1"]);
+    }
 
-        assert_eq!(service.total_farmers(), 1);
-        assert!(service.is_mobile_verified("9876543210"));
-        assert!(service.verify_mobile_did_pair("9876543210", "0x123abc"));
+    #[tokio::test]
+    async fn test_verify_invalid_mobile() {
+        let service = memory_service().await;
+        assert!(!service.is_mobile_verified("0000000000").await.unwrap());
+        assert_eq!(service.verify_mobile("0000000000").await.unwrap(), None);
+    }
 
-        let retrieved = service.get_farmer_by_mobile("9876543210").unwrap();
-        assert_eq!(retrieved.name, "Test Farmer");
+    #[tokio::test]
+    async fn test_update_ipfscid() {
+        let service = memory_service().await;
+        service.add_farmer(&test_farmer()).await.unwrap();
+
+        service
+            .update_farmer_ipfscid_by_mobile("9876543210", "QmFarmer")
+            .await
+            .unwrap();
+        let farmer = service
+            .get_farmer_by_did("0x123abc")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(farmer.ipfscid, "QmFarmer");
+
+        assert!(service
+            .update_farmer_ipfscid_by_mobile("0000000000", "QmOther")
+            .await
+            .is_err());
+        assert!(service
+            .update_farmer_ipfscid_by_did("0xmissing", "QmOther")
+            .await
+            .is_err());
     }
 
-    #[test]
-    fn test_verify_invalid_mobile() {
-        let service = FarmerVerificationService::new();
-        assert!(!service.is_mobile_verified("0000000000"));
-        assert_eq!(service.verify_mobile("0000000000"), None);
+    #[tokio::test]
+    async fn test_backup_to_writes_readable_copy() {
+        // VACUUM INTO needs a file-backed source database
+        let dir = std::env::temp_dir().join(format!("ovc-farmers-test-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let options = SqliteConnectOptions::new()
+            .filename(dir.join("live.db"))
+            .create_if_missing(true);
+        let service = FarmerVerificationService::connect(options, 1)
+            .await
+            .unwrap();
+        service.add_farmer(&test_farmer()).await.unwrap();
+
+        let backup = dir.join("backup.db");
+        service.backup_to(&backup).await.unwrap();
+
+        let options = SqliteConnectOptions::new().filename(&backup);
+        let copy = FarmerVerificationService::connect(options, 1)
+            .await
+            .unwrap();
+        assert_eq!(copy.total_farmers().await.unwrap(), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
@@ -0,0 +1,253 @@
+//! Fault injection for resilience testing (development only)
+//!
+//! With FAULT_INJECTION=true, a request can ask for failures inside the
+//! clients it goes through, so the retry, outbox and pin deferral paths can
+//! be driven end to end without breaking Pinata or the RPC node:
+//!
+//! ```text
+//! X-Inject-Fault: pinata_timeout, nonce_error=6, slow_receipt
+//! ```
+//!
+//! - `pinata_timeout`: a Pinata pin hangs for FAULT_INJECTION_DELAY_MS
+//!   (default 30000) and then fails as timed out. A shorter
+//!   PIN_DEFERRAL_TIMEOUT_SECS defers the pin instead.
+//! - `nonce_error`: sending a transaction fails with "nonce too low", so the
+//!   transaction queue re-reads the nonce and retries. More failures than
+//!   TX_MAX_RETRIES send the write to the outbox.
+//! - `slow_receipt`: a transaction's receipt is withheld for the same delay.
+//!   A delay above TX_RECEIPT_TIMEOUT_SECS leads to fee-bumped replacements.
+//!
+//! `=n` makes a fault fire n times within the request (default once).
+//! FAULT_INJECTION_FAULTS applies the same list to requests without the
+//! header. Faults follow the request's writes into the transaction queue,
+//! but background jobs never see them. The server refuses to start with
+//! fault injection in production.
+
+use crate::config::Environment;
+use crate::error::ApiError;
+use crate::state::AppState;
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::{Request, State},
+    http::HeaderName,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub const FAULT_HEADER: HeaderName = HeaderName::from_static("x-inject-fault");
+const DEFAULT_DELAY_MS: u64 = 30_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fault {
+    PinataTimeout,
+    NonceError,
+    SlowReceipt,
+}
+
+impl Fault {
+    const ALL: [Fault; 3] = [Fault::PinataTimeout, Fault::NonceError, Fault::SlowReceipt];
+
+    fn name(self) -> &'static str {
+        match self {
+            Fault::PinataTimeout => "pinata_timeout",
+            Fault::NonceError => "nonce_error",
+            Fault::SlowReceipt => "slow_receipt",
+        }
+    }
+}
+
+/// Faults still to fire within one request
+#[derive(Debug, Clone)]
+pub struct FaultPlan {
+    remaining: Arc<Mutex<HashMap<Fault, u32>>>,
+    delay: Duration,
+}
+
+impl FaultPlan {
+    /// Parse a list such as `pinata_timeout, nonce_error=6`
+    pub fn parse(spec: &str, delay: Duration) -> Result<Self> {
+        let mut remaining = HashMap::new();
+        for item in spec.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let (name, times) = match item.split_once('=') {
+                Some((name, times)) => (
+                    name.trim(),
+                    times
+                        .trim()
+                        .parse::<u32>()
+                        .with_context(|| format!("Invalid count in fault '{}'", item))?,
+                ),
+                None => (item, 1),
+            };
+            let Some(fault) = Fault::ALL.into_iter().find(|f| f.name() == name) else {
+                bail!(
+                    "Unknown fault '{}' (expected one of: {})",
+                    name,
+                    Fault::ALL.map(Fault::name).join(", ")
+                );
+            };
+            *remaining.entry(fault).or_insert(0) += times;
+        }
+        Ok(Self {
+            remaining: Arc::new(Mutex::new(remaining)),
+            delay,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.remaining
+            .lock()
+            .map(|r| r.values().all(|n| *n == 0))
+            .unwrap_or(true)
+    }
+
+    /// Whether `fault` fires now, using up one occurrence
+    fn fire(&self, fault: Fault) -> bool {
+        let Ok(mut remaining) = self.remaining.lock() else {
+            return false;
+        };
+        match remaining.get_mut(&fault) {
+            Some(n) if *n > 0 => {
+                *n -= 1;
+                tracing::warn!(fault = fault.name(), left = *n, "Injecting fault");
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// FAULT_INJECTION settings
+#[derive(Debug, Clone)]
+pub struct FaultInjection {
+    /// FAULT_INJECTION_FAULTS, for requests without the header
+    default_faults: String,
+    delay: Duration,
+}
+
+impl FaultInjection {
+    /// `None` unless FAULT_INJECTION is on; an error in production
+    pub fn from_env() -> Result<Option<Self>> {
+        let enabled = std::env::var("FAULT_INJECTION")
+            .map(|v| matches!(v.to_lowercase().as_str(), "true" | "1" | "on"))
+            .unwrap_or(false);
+        if !enabled {
+            return Ok(None);
+        }
+        let environment = std::env::var("ENVIRONMENT").unwrap_or_default();
+        if Environment::from_str(&environment).is_production() {
+            bail!("FAULT_INJECTION must not be enabled in production");
+        }
+
+        let injection = Self {
+            default_faults: std::env::var("FAULT_INJECTION_FAULTS").unwrap_or_default(),
+            delay: Duration::from_millis(
+                std::env::var("FAULT_INJECTION_DELAY_MS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_DELAY_MS),
+            ),
+        };
+        // Fail at startup rather than on every request
+        FaultPlan::parse(&injection.default_faults, injection.delay)
+            .context("Invalid FAULT_INJECTION_FAULTS")?;
+        tracing::warn!(
+            default_faults = %injection.default_faults,
+            delay_ms = injection.delay.as_millis() as u64,
+            "Fault injection is enabled"
+        );
+        Ok(Some(injection))
+    }
+}
+
+tokio::task_local! {
+    static PLAN: FaultPlan;
+}
+
+/// Whether `fault` is injected into the current request's work now
+pub fn fire(fault: Fault) -> bool {
+    PLAN.try_with(|plan| plan.fire(fault)).unwrap_or(false)
+}
+
+/// How long a fired fault stalls
+pub fn delay() -> Duration {
+    PLAN.try_with(|plan| plan.delay).unwrap_or_default()
+}
+
+/// Faults of the current request, to carry into work done elsewhere
+pub fn current() -> Option<FaultPlan> {
+    PLAN.try_with(FaultPlan::clone).ok()
+}
+
+/// Run `future` with the faults of the request it was started by
+pub async fn run_with<F: Future>(plan: Option<FaultPlan>, future: F) -> F::Output {
+    match plan {
+        Some(plan) => PLAN.scope(plan, future).await,
+        None => future.await,
+    }
+}
+
+/// Middleware scoping the faults a request asks for to its handling
+pub async fn inject_faults(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(injection) = state.fault_injection.as_ref() else {
+        return next.run(request).await;
+    };
+    let spec = match request.headers().get(FAULT_HEADER) {
+        Some(value) => match value.to_str() {
+            Ok(spec) => spec.to_string(),
+            Err(_) => {
+                return ApiError::bad_request("Invalid X-Inject-Fault header").into_response()
+            }
+        },
+        None => injection.default_faults.clone(),
+    };
+
+    match FaultPlan::parse(&spec, injection.delay) {
+        Ok(plan) if plan.is_empty() => next.run(request).await,
+        Ok(plan) => PLAN.scope(plan, next.run(request)).await,
+        Err(e) => ApiError::bad_request(format!("{:#}", e)).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_counts_faults() {
+        let plan = FaultPlan::parse(" nonce_error=2, pinata_timeout ,", Duration::ZERO).unwrap();
+        assert!(plan.fire(Fault::NonceError));
+        assert!(plan.fire(Fault::NonceError));
+        assert!(!plan.fire(Fault::NonceError));
+        assert!(plan.fire(Fault::PinataTimeout));
+        assert!(!plan.fire(Fault::SlowReceipt));
+        assert!(plan.is_empty());
+
+        assert!(FaultPlan::parse("disk_full", Duration::ZERO).is_err());
+        assert!(FaultPlan::parse("nonce_error=many", Duration::ZERO).is_err());
+        assert!(FaultPlan::parse("", Duration::ZERO).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_faults_only_fire_inside_their_request() {
+        assert!(!fire(Fault::NonceError));
+
+        let plan = FaultPlan::parse("nonce_error", Duration::from_millis(5)).unwrap();
+        // Carried into other work, the plan's occurrences are shared
+        let carried = PLAN
+            .scope(plan, async {
+                assert_eq!(delay(), Duration::from_millis(5));
+                current()
+            })
+            .await;
+        assert!(run_with(carried.clone(), async { fire(Fault::NonceError) }).await);
+        assert!(!run_with(carried, async { fire(Fault::NonceError) }).await);
+    }
+}
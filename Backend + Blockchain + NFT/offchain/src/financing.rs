@@ -0,0 +1,626 @@
+//! Lender financing flags and double-financing checks
+//!
+//! When a lender finances a batch (a pledge against stored stock, or an
+//! advance paid to the farmer), the warehouse records a financing flag here.
+//! A batch can carry only one active flag of each kind; recording a second
+//! one is refused until the first is released.
+//!
+//! `GET /api/compliance/double-financing` cross-checks the flags against the
+//! FPO purchase and settlement records and lists:
+//!
+//! - `multiple_pledges` / `multiple_advances` - more than one active flag of
+//!   a kind on a batch (flags imported or recorded before this check existed)
+//! - `duplicate_payment` - two purchase records for the same farmer,
+//!   quantity and amount within a day, both with payable settlements
+//! - `advance_and_payment` - an active advance on a batch whose FPO payment
+//!   is not on hold, so the farmer is paid by both lender and FPO
+//!
+//! Banks and NBFCs sign in with the `lender` role and mark a lien on a
+//! stored batch with `POST /api/batches/:batch_id/lien`; the lien is a pledge
+//! flag in the lender's name. Processing a liened batch or packaging SKUs
+//! from it is refused until the lender releases the lien, so pledged stock
+//! cannot enter the product stream.
+//!
+//! Flags are kept in `data/financing_flags.json`.
+
+use crate::auth::{Principal, Role};
+use crate::batch_ledger;
+use crate::error::{ApiError, ApiResult};
+use crate::holds::{self, SettlementStatus};
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::Mutex;
+
+const FLAGS_PATH: &str = "data/financing_flags.json";
+/// Purchases this close together are treated as the same lot
+const DUPLICATE_WINDOW_HOURS: i64 = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinancingKind {
+    /// Stock pledged as loan collateral
+    Pledge,
+    /// Lender paid the farmer ahead of the FPO payment
+    PaymentAdvance,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinancingFlag {
+    pub id: u64,
+    pub batch_id: String,
+    pub kind: FinancingKind,
+    pub lender: String,
+    /// Loan account or sanction number at the lender
+    pub reference: String,
+    pub amount: f64,
+    pub recorded_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub released_at: Option<String>,
+}
+
+impl FinancingFlag {
+    pub fn is_active(&self) -> bool {
+        self.released_at.is_none()
+    }
+}
+
+pub struct FinancingStore {
+    flags: Mutex<Vec<FinancingFlag>>,
+}
+
+impl FinancingStore {
+    pub fn load() -> Result<Self> {
+        let flags = match std::fs::read_to_string(FLAGS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid financing flags file {}", FLAGS_PATH))?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Self {
+            flags: Mutex::new(flags),
+        })
+    }
+
+    fn save(flags: &[FinancingFlag]) -> Result<()> {
+        std::fs::write(FLAGS_PATH, serde_json::to_string_pretty(flags)?)
+            .with_context(|| format!("Failed to write {}", FLAGS_PATH))
+    }
+
+    /// Active pledge (lien) on a batch, if any
+    pub async fn active_lien(&self, batch_id: &str) -> Option<FinancingFlag> {
+        self.flags
+            .lock()
+            .await
+            .iter()
+            .find(|f| f.batch_id == batch_id && f.kind == FinancingKind::Pledge && f.is_active())
+            .cloned()
+    }
+
+    /// Refuse to move a liened batch further down the chain
+    pub async fn ensure_unencumbered(&self, batch_id: &str) -> Result<(), ApiError> {
+        match self.active_lien(batch_id).await {
+            Some(lien) => {
+                tracing::warn!(
+                    batch_id = %batch_id,
+                    lender = %lien.lender,
+                    flag_id = lien.id,
+                    "Refused to use liened batch"
+                );
+                Err(ApiError::new(
+                    StatusCode::CONFLICT,
+                    format!(
+                        "Batch {} is under lien to {} (ref {}) until released",
+                        batch_id, lien.lender, lien.reference
+                    ),
+                ))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+// ======================== CONFLICTS ========================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    MultiplePledges,
+    MultipleAdvances,
+    DuplicatePayment,
+    AdvanceAndPayment,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    pub batch_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub flag_ids: Vec<u64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub lenders: Vec<String>,
+    pub detail: String,
+}
+
+/// Purchase fields compared for duplicate payments
+#[derive(Debug, Clone)]
+pub struct PaidPurchase {
+    pub batch_id: String,
+    pub farmer_did: String,
+    pub quantity_kg: f64,
+    pub total_cost: f64,
+    pub paid_at: DateTime<Utc>,
+    /// Settlement allows the payment (clear or released)
+    pub payable: bool,
+}
+
+impl PaidPurchase {
+    pub fn from_record(batch_id: &str, record: &Value, status: SettlementStatus) -> Option<Self> {
+        let paid_at = record.get("timestamp")?.as_str()?;
+        Some(Self {
+            batch_id: batch_id.to_string(),
+            farmer_did: record
+                .pointer("/farmer_info/farmer_did")?
+                .as_str()?
+                .to_string(),
+            quantity_kg: record.pointer("/batch_info/quantity_kg")?.as_f64()?,
+            total_cost: record.pointer("/pricing/total_cost")?.as_f64()?,
+            paid_at: DateTime::parse_from_rfc3339(paid_at)
+                .ok()?
+                .with_timezone(&Utc),
+            payable: status != SettlementStatus::OnHold,
+        })
+    }
+}
+
+fn same_amount(a: f64, b: f64) -> bool {
+    (a - b).abs() < 0.005
+}
+
+/// Cross-check financing flags and purchases for double financing
+pub fn detect_conflicts(flags: &[FinancingFlag], purchases: &[PaidPurchase]) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+
+    let mut active: BTreeMap<(&str, FinancingKind), Vec<&FinancingFlag>> = BTreeMap::new();
+    for flag in flags.iter().filter(|f| f.is_active()) {
+        active
+            .entry((flag.batch_id.as_str(), flag.kind))
+            .or_default()
+            .push(flag);
+    }
+    for ((batch_id, kind), flags) in &active {
+        if flags.len() < 2 {
+            continue;
+        }
+        let (kind, noun) = match kind {
+            FinancingKind::Pledge => (ConflictKind::MultiplePledges, "pledges"),
+            FinancingKind::PaymentAdvance => (ConflictKind::MultipleAdvances, "payment advances"),
+        };
+        conflicts.push(Conflict {
+            kind,
+            batch_ids: vec![batch_id.to_string()],
+            flag_ids: flags.iter().map(|f| f.id).collect(),
+            lenders: flags.iter().map(|f| f.lender.clone()).collect(),
+            detail: format!(
+                "Batch {} has {} active {} totalling Rs {:.2}",
+                batch_id,
+                flags.len(),
+                noun,
+                flags.iter().map(|f| f.amount).sum::<f64>()
+            ),
+        });
+    }
+
+    let purchases_by_batch: HashMap<&str, &PaidPurchase> =
+        purchases.iter().map(|p| (p.batch_id.as_str(), p)).collect();
+    for ((batch_id, kind), flags) in &active {
+        if *kind != FinancingKind::PaymentAdvance {
+            continue;
+        }
+        if let Some(purchase) = purchases_by_batch.get(batch_id).filter(|p| p.payable) {
+            conflicts.push(Conflict {
+                kind: ConflictKind::AdvanceAndPayment,
+                batch_ids: vec![batch_id.to_string()],
+                flag_ids: flags.iter().map(|f| f.id).collect(),
+                lenders: flags.iter().map(|f| f.lender.clone()).collect(),
+                detail: format!(
+                    "Batch {} was advanced by a lender and its FPO payment of Rs {:.2} is not on hold",
+                    batch_id, purchase.total_cost
+                ),
+            });
+        }
+    }
+
+    let mut sorted: Vec<&PaidPurchase> = purchases.iter().filter(|p| p.payable).collect();
+    sorted.sort_by(|a, b| {
+        (a.farmer_did.as_str(), a.paid_at).cmp(&(b.farmer_did.as_str(), b.paid_at))
+    });
+    for (i, first) in sorted.iter().enumerate() {
+        for second in &sorted[i + 1..] {
+            if second.farmer_did != first.farmer_did
+                || second.paid_at - first.paid_at > chrono::Duration::hours(DUPLICATE_WINDOW_HOURS)
+            {
+                break;
+            }
+            if same_amount(first.quantity_kg, second.quantity_kg)
+                && same_amount(first.total_cost, second.total_cost)
+            {
+                conflicts.push(Conflict {
+                    kind: ConflictKind::DuplicatePayment,
+                    batch_ids: vec![first.batch_id.clone(), second.batch_id.clone()],
+                    flag_ids: Vec::new(),
+                    lenders: Vec::new(),
+                    detail: format!(
+                        "Farmer {} was paid Rs {:.2} for {:.1} kg twice within {} hours",
+                        first.farmer_did,
+                        first.total_cost,
+                        first.quantity_kg,
+                        DUPLICATE_WINDOW_HOURS
+                    ),
+                });
+            }
+        }
+    }
+
+    conflicts
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct RecordFinancingRequest {
+    pub batch_id: String,
+    pub kind: FinancingKind,
+    pub lender: String,
+    pub reference: String,
+    pub amount: f64,
+}
+
+impl FinancingStore {
+    /// Record a lender's financing of a batch, refusing a second active flag of the same kind
+    pub async fn record(&self, request: RecordFinancingRequest) -> Result<FinancingFlag, ApiError> {
+        if request.lender.trim().is_empty() || request.reference.trim().is_empty() {
+            return Err(ApiError::bad_request("lender and reference are required"));
+        }
+        if !(request.amount.is_finite() && request.amount > 0.0) {
+            return Err(ApiError::bad_request("amount must be positive"));
+        }
+        if batch_ledger::fpo_purchase(&request.batch_id)?.is_none() {
+            return Err(ApiError::not_found(format!(
+                "No FPO purchase for batch {}",
+                request.batch_id
+            )));
+        }
+
+        let mut flags = self.flags.lock().await;
+        if let Some(existing) = flags
+            .iter()
+            .find(|f| f.batch_id == request.batch_id && f.kind == request.kind && f.is_active())
+        {
+            tracing::warn!(
+                batch_id = %request.batch_id,
+                lender = %request.lender,
+                existing_flag = existing.id,
+                "Refused double financing of batch"
+            );
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                format!(
+                    "Batch {} is already financed by {} (flag {}, ref {})",
+                    request.batch_id, existing.lender, existing.id, existing.reference
+                ),
+            ));
+        }
+
+        let flag = FinancingFlag {
+            id: flags.last().map(|f| f.id + 1).unwrap_or(1),
+            batch_id: request.batch_id,
+            kind: request.kind,
+            lender: request.lender.trim().to_string(),
+            reference: request.reference.trim().to_string(),
+            amount: request.amount,
+            recorded_at: Utc::now().to_rfc3339(),
+            released_at: None,
+        };
+        flags.push(flag.clone());
+        Self::save(&flags)?;
+
+        tracing::info!(
+            flag_id = flag.id,
+            batch_id = %flag.batch_id,
+            lender = %flag.lender,
+            kind = ?flag.kind,
+            "Financing flag recorded"
+        );
+        Ok(flag)
+    }
+
+    /// Mark a financing flag repaid or withdrawn
+    pub async fn release(&self, id: u64) -> Result<FinancingFlag, ApiError> {
+        let mut flags = self.flags.lock().await;
+        let flag = flags
+            .iter_mut()
+            .find(|f| f.id == id)
+            .ok_or_else(|| ApiError::not_found(format!("Financing flag {} not found", id)))?;
+        if flag.released_at.is_none() {
+            flag.released_at = Some(Utc::now().to_rfc3339());
+            tracing::info!(flag_id = id, batch_id = %flag.batch_id, "Financing flag released");
+        }
+        let flag = flag.clone();
+        Self::save(&flags)?;
+        Ok(flag)
+    }
+}
+
+pub async fn record_financing(
+    State(state): State<AppState>,
+    Json(payload): Json<RecordFinancingRequest>,
+) -> ApiResult<FinancingFlag> {
+    state.financing.record(payload).await.map(Json)
+}
+
+pub async fn release_financing(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> ApiResult<FinancingFlag> {
+    state.financing.release(id).await.map(Json)
+}
+
+#[derive(Debug, Serialize)]
+pub struct DoubleFinancingReport {
+    pub checked_batches: usize,
+    pub active_flags: usize,
+    pub conflicts: Vec<Conflict>,
+}
+
+pub async fn double_financing(State(state): State<AppState>) -> ApiResult<DoubleFinancingReport> {
+    let mut purchases = Vec::new();
+    for (batch_id, record) in batch_ledger::all_purchases() {
+        let status = holds::settlement(&batch_id)?
+            .map(|s| s.status)
+            .unwrap_or_default();
+        if let Some(purchase) = PaidPurchase::from_record(&batch_id, &record, status) {
+            purchases.push(purchase);
+        }
+    }
+
+    let flags = state.financing.flags.lock().await.clone();
+    let conflicts = detect_conflicts(&flags, &purchases);
+    if !conflicts.is_empty() {
+        tracing::warn!(
+            conflicts = conflicts.len(),
+            "Double financing conflicts found"
+        );
+    }
+
+    Ok(Json(DoubleFinancingReport {
+        checked_batches: purchases.len(),
+        active_flags: flags.iter().filter(|f| f.is_active()).count(),
+        conflicts,
+    }))
+}
+
+// ======================== LIENS ========================
+
+/// Party a lien is recorded for: lender accounts and lender integration keys
+/// act in their own name, admins name the lender
+fn lien_party(principal: Option<&Principal>, named: Option<&str>) -> Result<String, ApiError> {
+    match principal {
+        Some(Principal::User(claims)) if claims.role == Role::Lender => Ok(claims.sub.clone()),
+        Some(Principal::ApiKey { name, .. }) => Ok(name.clone()),
+        _ => named
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .ok_or_else(|| ApiError::bad_request("lender is required")),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MarkLienRequest {
+    pub reference: String,
+    pub amount: f64,
+    /// Only read for admin callers
+    #[serde(default)]
+    pub lender: Option<String>,
+}
+
+pub async fn mark_lien(
+    State(state): State<AppState>,
+    Path(batch_id): Path<String>,
+    principal: Option<Extension<Principal>>,
+    Json(payload): Json<MarkLienRequest>,
+) -> ApiResult<FinancingFlag> {
+    let principal = principal.map(|Extension(p)| p);
+    let lender = lien_party(principal.as_ref(), payload.lender.as_deref())?;
+    let flag = state
+        .financing
+        .record(RecordFinancingRequest {
+            batch_id,
+            kind: FinancingKind::Pledge,
+            lender,
+            reference: payload.reference,
+            amount: payload.amount,
+        })
+        .await?;
+    Ok(Json(flag))
+}
+
+pub async fn release_lien(
+    State(state): State<AppState>,
+    Path(batch_id): Path<String>,
+    principal: Option<Extension<Principal>>,
+) -> ApiResult<FinancingFlag> {
+    let lien = state
+        .financing
+        .active_lien(&batch_id)
+        .await
+        .ok_or_else(|| ApiError::not_found(format!("Batch {} has no active lien", batch_id)))?;
+
+    let principal = principal.map(|Extension(p)| p);
+    if let Ok(caller) = lien_party(principal.as_ref(), None) {
+        if caller != lien.lender {
+            return Err(ApiError::forbidden(format!(
+                "Lien on batch {} belongs to {}",
+                batch_id, lien.lender
+            )));
+        }
+    }
+    if let Some(number) = state.receipts.receipt_under_lien(lien.id).await {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!(
+                "Lien was marked on warehouse receipt {}; release it there",
+                number
+            ),
+        ));
+    }
+
+    state.financing.release(lien.id).await.map(Json)
+}
+
+#[derive(Debug, Serialize)]
+pub struct LienStatus {
+    pub batch_id: String,
+    pub liened: bool,
+    pub lien: Option<FinancingFlag>,
+    /// Released liens, oldest first
+    pub history: Vec<FinancingFlag>,
+}
+
+pub async fn lien_status(
+    State(state): State<AppState>,
+    Path(batch_id): Path<String>,
+) -> ApiResult<LienStatus> {
+    let (active, history): (Vec<_>, Vec<_>) = state
+        .financing
+        .flags
+        .lock()
+        .await
+        .iter()
+        .filter(|f| f.batch_id == batch_id && f.kind == FinancingKind::Pledge)
+        .cloned()
+        .partition(FinancingFlag::is_active);
+    let lien = active.into_iter().next();
+    Ok(Json(LienStatus {
+        batch_id,
+        liened: lien.is_some(),
+        lien,
+        history,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flag(id: u64, batch_id: &str, kind: FinancingKind, lender: &str) -> FinancingFlag {
+        FinancingFlag {
+            id,
+            batch_id: batch_id.to_string(),
+            kind,
+            lender: lender.to_string(),
+            reference: format!("LN-{}", id),
+            amount: 50_000.0,
+            recorded_at: "2025-06-01T00:00:00Z".to_string(),
+            released_at: None,
+        }
+    }
+
+    fn purchase(batch_id: &str, farmer: &str, paid_at: &str, payable: bool) -> PaidPurchase {
+        PaidPurchase {
+            batch_id: batch_id.to_string(),
+            farmer_did: farmer.to_string(),
+            quantity_kg: 1000.0,
+            total_cost: 60_500.0,
+            paid_at: DateTime::parse_from_rfc3339(paid_at)
+                .unwrap()
+                .with_timezone(&Utc),
+            payable,
+        }
+    }
+
+    fn kinds(conflicts: &[Conflict]) -> Vec<(ConflictKind, Vec<String>)> {
+        conflicts
+            .iter()
+            .map(|c| (c.kind, c.batch_ids.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_flags_conflict_per_batch_and_kind() {
+        let mut released = flag(3, "b1", FinancingKind::Pledge, "Bank C");
+        released.released_at = Some("2025-06-02T00:00:00Z".to_string());
+        let flags = vec![
+            flag(1, "b1", FinancingKind::Pledge, "Bank A"),
+            flag(2, "b1", FinancingKind::Pledge, "Bank B"),
+            released,
+            flag(4, "b2", FinancingKind::Pledge, "Bank A"),
+            flag(5, "b2", FinancingKind::PaymentAdvance, "NBFC D"),
+        ];
+        let purchases = vec![purchase("b2", "f1", "2025-06-01T00:00:00Z", true)];
+
+        let conflicts = detect_conflicts(&flags, &purchases);
+        assert_eq!(
+            kinds(&conflicts),
+            vec![
+                (ConflictKind::MultiplePledges, vec!["b1".to_string()]),
+                (ConflictKind::AdvanceAndPayment, vec!["b2".to_string()]),
+            ]
+        );
+        assert_eq!(conflicts[0].flag_ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_duplicate_payments_need_payable_settlements_within_a_day() {
+        let purchases = vec![
+            purchase("b1", "f1", "2025-06-01T08:00:00Z", true),
+            purchase("b2", "f1", "2025-06-01T15:00:00Z", true),
+            purchase("b3", "f1", "2025-06-05T08:00:00Z", true),
+            purchase("b4", "f2", "2025-06-01T08:00:00Z", true),
+            purchase("b5", "f2", "2025-06-01T09:00:00Z", false),
+        ];
+        assert_eq!(
+            kinds(&detect_conflicts(&[], &purchases)),
+            vec![(
+                ConflictKind::DuplicatePayment,
+                vec!["b1".to_string(), "b2".to_string()]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_lien_party_uses_caller_identity() {
+        let lender = Principal::User(crate::auth::Claims {
+            sub: "sbi.agri.indore".to_string(),
+            role: Role::Lender,
+            did: None,
+            roles: Vec::new(),
+            iat: 0,
+            exp: 0,
+        });
+        assert_eq!(
+            lien_party(Some(&lender), Some("Other Bank")).unwrap(),
+            "sbi.agri.indore"
+        );
+        let key = Principal::ApiKey {
+            id: 3,
+            name: "nbfc-gateway".to_string(),
+            scopes: vec![Role::Lender],
+        };
+        assert_eq!(lien_party(Some(&key), None).unwrap(), "nbfc-gateway");
+        // Admins must name the lender
+        assert!(lien_party(Some(&Principal::Admin), None).is_err());
+        assert_eq!(
+            lien_party(Some(&Principal::Admin), Some(" HDFC ")).unwrap(),
+            "HDFC"
+        );
+    }
+}
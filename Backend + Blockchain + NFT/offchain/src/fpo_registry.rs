@@ -0,0 +1,374 @@
+//! Farmer Producer Organisations
+//!
+//! Purchases are recorded by FPOs, which are registered here with their
+//! registration number, district, payout wallet and member farmers.
+//! `POST /api/fpo/purchase` requires the `fpo_id` of a registered FPO and
+//! writes its name and registration number into the purchase metadata; an
+//! FPO that lists members may only buy from them.
+//!
+//! Admins manage the registry under `/api/admin/fpos`; FPO accounts read it
+//! through `GET /api/fpos`. Entries are kept in `data/fpos.json`.
+
+use crate::admin::require_admin;
+use crate::did_resolver;
+use crate::error::{ApiError, ApiResult};
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use alloy::primitives::Address;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+const FPOS_PATH: &str = "data/fpos.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fpo {
+    /// Lowercase slug, e.g. `nashik-oilseed-fpc`
+    pub id: String,
+    pub name: String,
+    /// Registration under the Companies Act or the state cooperative act
+    pub registration_number: String,
+    pub district: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wallet_address: Option<Address>,
+    /// Farmer DIDs, canonical
+    #[serde(default)]
+    pub members: Vec<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Fpo {
+    /// Whether `farmer_did` may sell to this FPO; one without a member list
+    /// buys from any registered farmer
+    pub fn admits(&self, farmer_did: &str) -> bool {
+        self.members.is_empty()
+            || self
+                .members
+                .iter()
+                .any(|m| did_resolver::same_farmer(m, farmer_did))
+    }
+
+    /// Entry embedded in purchase metadata
+    pub fn metadata_entry(&self) -> serde_json::Value {
+        serde_json::json!({
+            "fpo_id": self.id,
+            "name": self.name,
+            "registration_number": self.registration_number,
+            "district": self.district,
+        })
+    }
+}
+
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+}
+
+/// Trimmed value of a required text field
+fn required(value: &str, name: &str) -> Result<String, ApiError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(ApiError::bad_request(format!("{} is required", name)));
+    }
+    Ok(value.to_string())
+}
+
+/// Canonical, de-duplicated member DIDs
+fn canonical_members(members: &[String]) -> Result<Vec<String>, ApiError> {
+    let mut canonical: Vec<String> = Vec::with_capacity(members.len());
+    for member in members {
+        let did = did_resolver::canonical(member.trim())
+            .map_err(|e| ApiError::bad_request(format!("Member {}: {}", member, e)))?;
+        if !canonical.contains(&did) {
+            canonical.push(did);
+        }
+    }
+    Ok(canonical)
+}
+
+pub struct FpoRegistry {
+    fpos: Mutex<Vec<Fpo>>,
+}
+
+impl FpoRegistry {
+    pub fn load() -> Result<Self> {
+        let fpos = match std::fs::read_to_string(FPOS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid FPO registry {}", FPOS_PATH))?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Self {
+            fpos: Mutex::new(fpos),
+        })
+    }
+
+    fn save(fpos: &[Fpo]) -> Result<()> {
+        std::fs::write(FPOS_PATH, serde_json::to_string_pretty(fpos)?)
+            .with_context(|| format!("Failed to write {}", FPOS_PATH))
+    }
+
+    pub async fn get(&self, id: &str) -> Option<Fpo> {
+        self.fpos.lock().await.iter().find(|f| f.id == id).cloned()
+    }
+
+    /// Registered FPO `id`, as a request error when there is none
+    pub async fn require(&self, id: &str) -> Result<Fpo, ApiError> {
+        self.get(id.trim())
+            .await
+            .ok_or_else(|| ApiError::bad_request(format!("FPO {} is not registered", id)))
+    }
+}
+
+/// Members that are not in the farmer registry
+async fn unregistered(state: &AppState, members: &[String]) -> Result<Vec<String>, ApiError> {
+    let mut missing = Vec::new();
+    for member in members {
+        if !state.farmer_verification.is_did_registered(member).await? {
+            missing.push(member.clone());
+        }
+    }
+    Ok(missing)
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct CreateFpoRequest {
+    pub id: String,
+    pub name: String,
+    pub registration_number: String,
+    pub district: String,
+    #[serde(default)]
+    pub wallet_address: Option<Address>,
+    #[serde(default)]
+    pub members: Vec<String>,
+}
+
+/// Fields to change; `None` leaves a field as it is
+#[derive(Debug, Default, Deserialize)]
+pub struct UpdateFpoRequest {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub registration_number: Option<String>,
+    #[serde(default)]
+    pub district: Option<String>,
+    #[serde(default)]
+    pub wallet_address: Option<Address>,
+    /// Replaces the member list
+    #[serde(default)]
+    pub members: Option<Vec<String>>,
+}
+
+/// Registered fpos, ordered by ID
+pub async fn list_fpos(
+    State(state): State<AppState>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<Fpo>> {
+    let fpos = state.fpos.fpos.lock().await.clone();
+    Ok(Json(paginate("fpos", fpos, &params, |f| f.id.clone())?))
+}
+
+pub async fn get_fpo(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<Fpo> {
+    state
+        .fpos
+        .get(&id)
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("FPO {} not found", id)))
+}
+
+/// Register an FPO (admin only)
+pub async fn create_fpo(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateFpoRequest>,
+) -> ApiResult<Fpo> {
+    require_admin(&state, &headers)?;
+
+    let id = payload.id.trim().to_string();
+    if !valid_id(&id) {
+        return Err(ApiError::bad_request(
+            "id must be 1-64 lowercase letters, digits or hyphens",
+        ));
+    }
+    let members = canonical_members(&payload.members)?;
+    let missing = unregistered(&state, &members).await?;
+    if !missing.is_empty() {
+        return Err(ApiError::bad_request(format!(
+            "Members not registered as farmers: {}",
+            missing.join(", ")
+        )));
+    }
+    let now = Utc::now().to_rfc3339();
+    let fpo = Fpo {
+        id,
+        name: required(&payload.name, "name")?,
+        registration_number: required(&payload.registration_number, "registration_number")?,
+        district: required(&payload.district, "district")?,
+        wallet_address: payload.wallet_address,
+        members,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+
+    let mut fpos = state.fpos.fpos.lock().await;
+    if fpos.iter().any(|f| f.id == fpo.id) {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!("FPO {} already exists", fpo.id),
+        ));
+    }
+    if let Some(other) = fpos
+        .iter()
+        .find(|f| f.registration_number == fpo.registration_number)
+    {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!(
+                "Registration number {} belongs to FPO {}",
+                fpo.registration_number, other.id
+            ),
+        ));
+    }
+    fpos.push(fpo.clone());
+    FpoRegistry::save(&fpos)?;
+
+    tracing::info!(fpo_id = %fpo.id, members = fpo.members.len(), "FPO registered");
+    Ok(Json(fpo))
+}
+
+/// Change an FPO's details or members (admin only)
+pub async fn update_fpo(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateFpoRequest>,
+) -> ApiResult<Fpo> {
+    require_admin(&state, &headers)?;
+
+    let members = payload
+        .members
+        .as_deref()
+        .map(canonical_members)
+        .transpose()?;
+    if let Some(members) = &members {
+        let missing = unregistered(&state, members).await?;
+        if !missing.is_empty() {
+            return Err(ApiError::bad_request(format!(
+                "Members not registered as farmers: {}",
+                missing.join(", ")
+            )));
+        }
+    }
+
+    let mut fpos = state.fpos.fpos.lock().await;
+    if let Some(number) = &payload.registration_number {
+        if let Some(other) = fpos
+            .iter()
+            .find(|f| f.id != id && f.registration_number == number.trim())
+        {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                format!("Registration number {} belongs to FPO {}", number, other.id),
+            ));
+        }
+    }
+    let fpo = fpos
+        .iter_mut()
+        .find(|f| f.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("FPO {} not found", id)))?;
+    if let Some(name) = &payload.name {
+        fpo.name = required(name, "name")?;
+    }
+    if let Some(number) = &payload.registration_number {
+        fpo.registration_number = required(number, "registration_number")?;
+    }
+    if let Some(district) = &payload.district {
+        fpo.district = required(district, "district")?;
+    }
+    if payload.wallet_address.is_some() {
+        fpo.wallet_address = payload.wallet_address;
+    }
+    if let Some(members) = members {
+        fpo.members = members;
+    }
+    fpo.updated_at = Utc::now().to_rfc3339();
+    let fpo = fpo.clone();
+    FpoRegistry::save(&fpos)?;
+
+    tracing::info!(fpo_id = %fpo.id, "FPO updated");
+    Ok(Json(fpo))
+}
+
+/// Remove an FPO (admin only); purchases already recorded keep its details
+pub async fn delete_fpo(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<Fpo> {
+    require_admin(&state, &headers)?;
+
+    let mut fpos = state.fpos.fpos.lock().await;
+    let position = fpos
+        .iter()
+        .position(|f| f.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("FPO {} not found", id)))?;
+    let fpo = fpos.remove(position);
+    FpoRegistry::save(&fpos)?;
+
+    tracing::info!(fpo_id = %fpo.id, "FPO removed");
+    Ok(Json(fpo))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_members_are_canonical_and_admitted() {
+        let hash = format!("0x{}", "ab".repeat(32));
+        let ethr = "did:ethr:0xB9C5714089478a327F09197987f16f9E5d936E8a";
+        let members = canonical_members(&[
+            hash.to_uppercase().replacen("0X", "0x", 1),
+            ethr.to_string(),
+            hash.clone(),
+        ])
+        .unwrap();
+        assert_eq!(
+            members,
+            [
+                hash.clone(),
+                "did:ethr:0xb9c5714089478a327f09197987f16f9e5d936e8a".to_string()
+            ]
+        );
+        assert!(canonical_members(&["did:web:example.com".to_string()]).is_err());
+
+        let mut fpo = Fpo {
+            id: "nashik-fpc".to_string(),
+            name: "Nashik FPC".to_string(),
+            registration_number: "U01100MH2019PTC123456".to_string(),
+            district: "Nashik".to_string(),
+            wallet_address: None,
+            members: Vec::new(),
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+        assert!(fpo.admits(&format!("0x{}", "cd".repeat(32))));
+        fpo.members = members;
+        assert!(fpo.admits(ethr));
+        assert!(!fpo.admits(&format!("0x{}", "cd".repeat(32))));
+
+        assert!(valid_id("nashik-fpc") && !valid_id("Nashik FPC") && !valid_id(""));
+    }
+}
@@ -0,0 +1,235 @@
+//! Quality-grade taxonomies per crop
+//!
+//! Each crop has an ordered list of grades, best first. A grade has a code,
+//! the spellings accepted for it (`aliases`, matched case-insensitively), a
+//! numeric `score` (0-100) used by analytics and AI scoring, and a price
+//! premium applied to FPO purchases. Taxonomies are read from
+//! `data/grades.json` (override with GRADES_CONFIG_PATH):
+//!
+//! ```json
+//! { "groundnut": [
+//!     { "code": "A", "aliases": ["Grade-1", "Bold"], "score": 100, "premium_percent": 5 },
+//!     { "code": "FAQ", "score": 60 },
+//!     { "code": "C", "score": 40, "premium_percent": -5 } ] }
+//! ```
+//!
+//! Crops without an entry use the `default` entry, or the built-in A / B /
+//! FAQ / C scale when there is none. Purchases and warehouse receipts with a
+//! grade outside the crop's taxonomy are rejected, and the canonical code
+//! is stored.
+
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const DEFAULT_GRADES_CONFIG_PATH: &str = "data/grades.json";
+/// Taxonomy key used for crops without their own
+const DEFAULT_CROP: &str = "default";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Grade {
+    pub code: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    pub score: f64,
+    /// Added to the product cost of a purchase; negative for discounts
+    #[serde(default)]
+    pub premium_percent: f64,
+}
+
+impl Grade {
+    fn matches(&self, input: &str) -> bool {
+        self.code.eq_ignore_ascii_case(input)
+            || self.aliases.iter().any(|a| a.eq_ignore_ascii_case(input))
+    }
+}
+
+fn builtin_grades() -> Vec<Grade> {
+    let grade = |code: &str, aliases: &[&str], score: f64| Grade {
+        code: code.to_string(),
+        aliases: aliases.iter().map(|a| a.to_string()).collect(),
+        score,
+        premium_percent: 0.0,
+    };
+    vec![
+        grade("A", &["Grade-1", "Grade A", "1"], 100.0),
+        grade("B", &["Grade-2", "Grade B", "2"], 75.0),
+        grade("FAQ", &["Fair average quality"], 60.0),
+        grade("C", &["Grade-3", "Grade C", "3"], 40.0),
+    ]
+}
+
+/// Check one crop's grades: unique spellings, scores within 0-100 and
+/// ordered best first
+fn validate(crop: &str, grades: &[Grade]) -> Result<()> {
+    if grades.is_empty() {
+        bail!("Grades for {}: at least one grade is required", crop);
+    }
+    let mut seen: Vec<String> = Vec::new();
+    for grade in grades {
+        if !(0.0..=100.0).contains(&grade.score) {
+            bail!("Grades for {}: score of {} must be 0-100", crop, grade.code);
+        }
+        if !(-100.0..=100.0).contains(&grade.premium_percent) {
+            bail!(
+                "Grades for {}: premium of {} must be -100 to 100 percent",
+                crop,
+                grade.code
+            );
+        }
+        for spelling in std::iter::once(&grade.code).chain(&grade.aliases) {
+            let key = spelling.trim().to_lowercase();
+            if key.is_empty() || seen.contains(&key) {
+                bail!("Grades for {}: {:?} is empty or used twice", crop, spelling);
+            }
+            seen.push(key);
+        }
+    }
+    if grades.windows(2).any(|pair| pair[0].score < pair[1].score) {
+        bail!("Grades for {} must be listed best first", crop);
+    }
+    Ok(())
+}
+
+pub struct GradeTaxonomies {
+    crops: HashMap<String, Vec<Grade>>,
+}
+
+impl GradeTaxonomies {
+    pub fn load() -> Result<Self> {
+        let path = std::env::var("GRADES_CONFIG_PATH")
+            .unwrap_or_else(|_| DEFAULT_GRADES_CONFIG_PATH.to_string());
+
+        let configured: HashMap<String, Vec<Grade>> = if std::path::Path::new(&path).exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read grades {}", path))?;
+            serde_json::from_str(&content).with_context(|| format!("Invalid grades {}", path))?
+        } else {
+            HashMap::new()
+        };
+        Self::from_map(configured)
+    }
+
+    fn from_map(configured: HashMap<String, Vec<Grade>>) -> Result<Self> {
+        let mut crops = HashMap::new();
+        for (crop, grades) in configured {
+            validate(&crop, &grades)?;
+            crops.insert(crop.to_lowercase(), grades);
+        }
+        crops
+            .entry(DEFAULT_CROP.to_string())
+            .or_insert_with(builtin_grades);
+        Ok(Self { crops })
+    }
+
+    /// Grades of `crop`, best first
+    pub fn grades(&self, crop: &str) -> &[Grade] {
+        self.crops
+            .get(&crop.trim().to_lowercase())
+            .or_else(|| self.crops.get(DEFAULT_CROP))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Grade of `crop` spelled `input`, or a 400 listing the valid codes
+    pub fn resolve(&self, crop: &str, input: &str) -> Result<&Grade, ApiError> {
+        let grades = self.grades(crop);
+        grades
+            .iter()
+            .find(|g| g.matches(input.trim()))
+            .ok_or_else(|| {
+                ApiError::bad_request(format!(
+                    "Unknown quality_grade {:?} for {}; expected one of {}",
+                    input,
+                    crop,
+                    grades
+                        .iter()
+                        .map(|g| g.code.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            })
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct GradesQuery {
+    pub crop: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GradesView {
+    /// crop -> grades, best first
+    pub taxonomies: HashMap<String, Vec<Grade>>,
+}
+
+/// Grade taxonomies, of one crop with `?crop=`
+pub async fn list_grades(
+    State(state): State<AppState>,
+    Query(query): Query<GradesQuery>,
+) -> ApiResult<GradesView> {
+    let grades = &state.grades;
+    let taxonomies = match query.crop {
+        Some(crop) => HashMap::from([(crop.clone(), grades.grades(&crop).to_vec())]),
+        None => grades.crops.clone(),
+    };
+    Ok(Json(GradesView { taxonomies }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grades_resolve_per_crop() {
+        let groundnut = vec![
+            Grade {
+                code: "Bold".to_string(),
+                aliases: vec!["Grade-1".to_string()],
+                score: 90.0,
+                premium_percent: 5.0,
+            },
+            Grade {
+                code: "Java".to_string(),
+                aliases: Vec::new(),
+                score: 70.0,
+                premium_percent: 0.0,
+            },
+        ];
+        let taxonomies =
+            GradeTaxonomies::from_map(HashMap::from([("Groundnut".to_string(), groundnut)]))
+                .unwrap();
+
+        assert_eq!(
+            taxonomies.resolve("groundnut", "grade-1").unwrap().code,
+            "Bold"
+        );
+        assert!(taxonomies.resolve("groundnut", "FAQ").is_err());
+        // Other crops fall back to the built-in scale
+        assert_eq!(taxonomies.resolve("mustard", " faq ").unwrap().score, 60.0);
+
+        let unordered = vec![
+            Grade {
+                code: "B".to_string(),
+                aliases: Vec::new(),
+                score: 50.0,
+                premium_percent: 0.0,
+            },
+            Grade {
+                code: "A".to_string(),
+                aliases: vec!["b".to_string()],
+                score: 90.0,
+                premium_percent: 0.0,
+            },
+        ];
+        assert!(validate("soybean", &unordered).is_err());
+    }
+}
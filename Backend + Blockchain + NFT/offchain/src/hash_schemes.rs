@@ -0,0 +1,403 @@
+//! Registry of hash schemes (algorithm + canonicalization version)
+//!
+//! Every hash this backend stores or anchors is recorded together with the
+//! scheme that produced it, e.g. `keccak256/json/v1`. Verifiers dispatch on
+//! the recorded scheme, so introducing a new algorithm or canonical form never
+//! changes how historical records are checked, and an unknown scheme is an
+//! error rather than a silent mismatch.
+//!
+//! Canonicalizations:
+//! - `raw/v1`: the bytes as given (IDs, Merkle leaves and nodes)
+//! - `json/v1`: compact `serde_json` output, as used for AI score reveals
+//! - `json/v2`: compact JSON with recursively sorted keys and integral floats
+//!   written as integers, so `{"q": 1.0}` hashes the same as JavaScript's
+//!   `JSON.stringify({q: 1})`
+//!
+//! New JSON hashes (warehouse state, processing, fraud evidence, AI score
+//! reveals) use JSON_HASH_SCHEME (default `keccak256/json/v1`). Batch
+//! folders record the scheme of each hashed file in `hashes.json`.
+
+use crate::chain::hash_bytes;
+use crate::compact_payload;
+use crate::error::{format_hash, ApiError, ApiResult};
+use alloy::primitives::FixedBytes;
+use anyhow::{bail, Context, Result};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Per-folder index of hashed files, pinned with the batch records
+const FOLDER_HASHES_FILE: &str = "hashes.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Keccak256,
+    Sha256,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Canonicalization {
+    #[serde(rename = "raw/v1")]
+    RawV1,
+    #[serde(rename = "json/v1")]
+    JsonV1,
+    #[serde(rename = "json/v2")]
+    JsonV2,
+}
+
+/// Serialized as its ID, e.g. `"keccak256/json/v1"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum HashScheme {
+    /// Records written before schemes were tracked used this for IDs and trees
+    #[default]
+    #[serde(rename = "keccak256/raw/v1")]
+    Keccak256RawV1,
+    #[serde(rename = "keccak256/json/v1")]
+    Keccak256JsonV1,
+    #[serde(rename = "keccak256/json/v2")]
+    Keccak256JsonV2,
+    #[serde(rename = "sha256/raw/v1")]
+    Sha256RawV1,
+    #[serde(rename = "sha256/json/v2")]
+    Sha256JsonV2,
+}
+
+impl HashScheme {
+    pub const ALL: &'static [HashScheme] = &[
+        HashScheme::Keccak256RawV1,
+        HashScheme::Keccak256JsonV1,
+        HashScheme::Keccak256JsonV2,
+        HashScheme::Sha256RawV1,
+        HashScheme::Sha256JsonV2,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            HashScheme::Keccak256RawV1 => "keccak256/raw/v1",
+            HashScheme::Keccak256JsonV1 => "keccak256/json/v1",
+            HashScheme::Keccak256JsonV2 => "keccak256/json/v2",
+            HashScheme::Sha256RawV1 => "sha256/raw/v1",
+            HashScheme::Sha256JsonV2 => "sha256/json/v2",
+        }
+    }
+
+    pub fn parse(id: &str) -> Result<Self> {
+        match Self::ALL.iter().find(|s| s.id() == id) {
+            Some(scheme) => Ok(*scheme),
+            None => bail!("Unknown hash scheme '{}'", id),
+        }
+    }
+
+    /// Scheme for new JSON document hashes (JSON_HASH_SCHEME)
+    pub fn for_json() -> Result<Self> {
+        match std::env::var("JSON_HASH_SCHEME") {
+            Ok(id) if !id.is_empty() => Self::parse(&id),
+            _ => Ok(HashScheme::Keccak256JsonV1),
+        }
+    }
+
+    pub fn algorithm(self) -> HashAlgorithm {
+        match self {
+            HashScheme::Keccak256RawV1
+            | HashScheme::Keccak256JsonV1
+            | HashScheme::Keccak256JsonV2 => HashAlgorithm::Keccak256,
+            HashScheme::Sha256RawV1 | HashScheme::Sha256JsonV2 => HashAlgorithm::Sha256,
+        }
+    }
+
+    pub fn canonicalization(self) -> Canonicalization {
+        match self {
+            HashScheme::Keccak256RawV1 | HashScheme::Sha256RawV1 => Canonicalization::RawV1,
+            HashScheme::Keccak256JsonV1 => Canonicalization::JsonV1,
+            HashScheme::Keccak256JsonV2 | HashScheme::Sha256JsonV2 => Canonicalization::JsonV2,
+        }
+    }
+
+    pub fn hash_bytes(self, data: &[u8]) -> FixedBytes<32> {
+        match self.algorithm() {
+            HashAlgorithm::Keccak256 => hash_bytes(data),
+            HashAlgorithm::Sha256 => FixedBytes::from(<[u8; 32]>::from(Sha256::digest(data))),
+        }
+    }
+
+    /// Hash a JSON document under this scheme's canonicalization
+    pub fn hash_json(self, value: &Value) -> Result<FixedBytes<32>> {
+        let bytes = match self.canonicalization() {
+            Canonicalization::RawV1 => {
+                bail!("{} hashes raw bytes, not JSON documents", self.id())
+            }
+            Canonicalization::JsonV1 => serde_json::to_vec(value)?,
+            Canonicalization::JsonV2 => canonical_json_v2(value).into_bytes(),
+        };
+        Ok(self.hash_bytes(&bytes))
+    }
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Number(n) => match n.as_f64() {
+            Some(f) if n.is_f64() && f.fract() == 0.0 && f.abs() < 9_007_199_254_740_992.0 => {
+                out.push_str(&(f as i64).to_string())
+            }
+            _ => out.push_str(&n.to_string()),
+        },
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(item, out);
+            }
+            out.push('}');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// Canonical JSON v2 (see module docs)
+pub fn canonical_json_v2(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+/// A stored hash together with the scheme that produced it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashRecord {
+    pub scheme: HashScheme,
+    pub hash: FixedBytes<32>,
+}
+
+impl HashRecord {
+    /// Hash a JSON document under the configured JSON scheme
+    pub fn of_json(value: &Value) -> Result<Self> {
+        let scheme = HashScheme::for_json()?;
+        Ok(Self {
+            scheme,
+            hash: scheme.hash_json(value)?,
+        })
+    }
+}
+
+/// Record the hash of `file` in the folder's `hashes.json`
+pub fn record_folder_hash(folder: &str, file: &str, record: HashRecord) -> Result<()> {
+    let path = std::path::Path::new(folder).join(FOLDER_HASHES_FILE);
+    let mut hashes: BTreeMap<String, HashRecord> = match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content)
+            .with_context(|| format!("Invalid hash index {}", path.display()))?,
+        Err(_) => BTreeMap::new(),
+    };
+    hashes.insert(file.to_string(), record);
+    std::fs::write(&path, serde_json::to_string_pretty(&hashes)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct SchemeInfo {
+    pub id: &'static str,
+    pub algorithm: HashAlgorithm,
+    pub canonicalization: Canonicalization,
+}
+
+/// Every hash scheme this backend can verify
+pub async fn list_schemes() -> Json<Vec<SchemeInfo>> {
+    Json(
+        HashScheme::ALL
+            .iter()
+            .map(|scheme| SchemeInfo {
+                id: scheme.id(),
+                algorithm: scheme.algorithm(),
+                canonicalization: scheme.canonicalization(),
+            })
+            .collect(),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyHashRequest {
+    pub scheme: String,
+    pub hash: String,
+    /// Document for `json/*` schemes, plain or a compact envelope as
+    /// fetched from IPFS
+    #[serde(default)]
+    pub json: Option<Value>,
+    /// UTF-8 text for `raw/*` schemes
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyHashResponse {
+    pub scheme: &'static str,
+    pub expected_hash: String,
+    pub computed_hash: String,
+    pub matches: bool,
+}
+
+/// Recompute a recorded hash under the scheme it was recorded with
+pub async fn verify_hash(Json(payload): Json<VerifyHashRequest>) -> ApiResult<VerifyHashResponse> {
+    let scheme =
+        HashScheme::parse(&payload.scheme).map_err(|e| ApiError::bad_request(e.to_string()))?;
+    let expected: FixedBytes<32> = payload
+        .hash
+        .parse()
+        .map_err(|e| ApiError::invalid_hash("hash", e))?;
+
+    let json = payload
+        .json
+        .map(compact_payload::decode)
+        .transpose()
+        .map_err(|e| ApiError::bad_request(format!("{:#}", e)))?;
+
+    let computed = match (scheme.canonicalization(), &json, &payload.text) {
+        (Canonicalization::RawV1, _, Some(text)) => scheme.hash_bytes(text.as_bytes()),
+        (Canonicalization::JsonV1 | Canonicalization::JsonV2, Some(json), _) => scheme
+            .hash_json(json)
+            .map_err(|e| ApiError::bad_request(e.to_string()))?,
+        (Canonicalization::RawV1, _, None) => {
+            return Err(ApiError::bad_request(format!(
+                "{} requires `text`",
+                scheme.id()
+            )))
+        }
+        _ => {
+            return Err(ApiError::bad_request(format!(
+                "{} requires `json`",
+                scheme.id()
+            )))
+        }
+    };
+
+    Ok(Json(VerifyHashResponse {
+        scheme: scheme.id(),
+        expected_hash: format_hash(expected),
+        computed_hash: format_hash(computed),
+        matches: computed == expected,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    /// Arbitrary JSON documents; floats are multiples of 1/4 so they print
+    /// and parse back exactly
+    fn json_value() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::from),
+            any::<i64>().prop_map(Value::from),
+            any::<i32>().prop_map(|n| Value::from(n as f64 / 4.0)),
+            "\\PC{0,8}".prop_map(Value::from),
+        ];
+        leaf.prop_recursive(4, 32, 6, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..6).prop_map(Value::from),
+                prop::collection::btree_map("[a-z]{0,4}", inner, 0..6)
+                    .prop_map(|map| Value::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_canonical_json_round_trips(value in json_value()) {
+            let canonical = canonical_json_v2(&value);
+            let reparsed: Value = serde_json::from_str(&canonical).unwrap();
+            prop_assert_eq!(canonical_json_v2(&reparsed), canonical);
+            prop_assert_eq!(
+                HashScheme::Sha256JsonV2.hash_json(&reparsed).unwrap(),
+                HashScheme::Sha256JsonV2.hash_json(&value).unwrap()
+            );
+        }
+
+        #[test]
+        fn prop_canonical_json_ignores_key_order(
+            fields in prop::collection::btree_map("[a-z]{1,6}", json_value(), 1..8)
+        ) {
+            let entries: Vec<String> = fields
+                .iter()
+                .map(|(k, v)| format!("{}:{}", Value::from(k.as_str()), v))
+                .collect();
+            let forward = format!("{{{}}}", entries.join(","));
+            let reversed: Vec<&str> = entries.iter().rev().map(String::as_str).collect();
+            let backward = format!("{{{}}}", reversed.join(","));
+
+            let a: Value = serde_json::from_str(&forward).unwrap();
+            let b: Value = serde_json::from_str(&backward).unwrap();
+            prop_assert_eq!(canonical_json_v2(&a), canonical_json_v2(&b));
+            for scheme in [HashScheme::Keccak256JsonV2, HashScheme::Sha256JsonV2] {
+                prop_assert_eq!(scheme.hash_json(&a).unwrap(), scheme.hash_json(&b).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_ids_round_trip_through_serde() {
+        for scheme in HashScheme::ALL {
+            let encoded = serde_json::to_value(scheme).unwrap();
+            assert_eq!(encoded, json!(scheme.id()));
+            assert_eq!(HashScheme::parse(scheme.id()).unwrap(), *scheme);
+        }
+        assert!(HashScheme::parse("md5/raw/v1").is_err());
+        assert!(serde_json::from_value::<HashScheme>(json!("md5/raw/v1")).is_err());
+    }
+
+    #[test]
+    fn test_json_v1_matches_legacy_reveal_hash() {
+        let score = json!({"score": 87, "grade": "A"});
+        let legacy = hash_bytes(&serde_json::to_vec(&score).unwrap());
+        assert_eq!(
+            HashScheme::Keccak256JsonV1.hash_json(&score).unwrap(),
+            legacy
+        );
+    }
+
+    #[test]
+    fn test_canonical_json_v2() {
+        let value = json!({"b": [1.0, 2.5], "a": {"z": null, "y": "é"}});
+        assert_eq!(
+            canonical_json_v2(&value),
+            r#"{"a":{"y":"é","z":null},"b":[1,2.5]}"#
+        );
+        assert_eq!(
+            HashScheme::Sha256JsonV2
+                .hash_json(&json!({"q": 1.0}))
+                .unwrap(),
+            HashScheme::Sha256JsonV2
+                .hash_json(&json!({"q": 1}))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_raw_schemes_reject_json() {
+        assert!(HashScheme::Keccak256RawV1.hash_json(&json!({})).is_err());
+        assert_eq!(
+            HashScheme::Keccak256RawV1.hash_bytes(b"21"),
+            crate::chain::hash_string("21")
+        );
+    }
+}
@@ -0,0 +1,482 @@
+//! Thumbnails and EXIF of evidence images
+//!
+//! Every evidence photo is decoded once on upload (see
+//! [`crate::photo_evidence`]) to:
+//!
+//! - read its EXIF into an [`ExifSummary`]: capture time, GPS position,
+//!   camera make and model, orientation
+//! - render a JPEG thumbnail of at most [`THUMBNAIL_SIZE`] pixels a side,
+//!   turned upright by the EXIF orientation
+//! - strip metadata from the copy that is published: APP1 (EXIF, XMP) and
+//!   APP13 (IPTC) segments of a JPEG, `eXIf` and text chunks of a PNG,
+//!   `EXIF` and `XMP ` chunks of a WebP. The image data is copied as is, so
+//!   nothing is re-encoded; serial numbers, owner names, maker notes and
+//!   embedded previews go with the segments.
+//!
+//! EXIF is parsed here rather than with a crate: only a handful of tags of
+//! IFD0, the Exif IFD and the GPS IFD are read.
+
+use crate::perceptual_hash;
+use anyhow::{Context, Result};
+use chrono::{FixedOffset, NaiveDateTime};
+use image::{
+    codecs::jpeg::JpegEncoder, metadata::Orientation, DynamicImage, ImageDecoder, ImageReader,
+    Limits,
+};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+
+/// Larger images are refused rather than decoded
+const MAX_DIMENSION: u32 = 12_000;
+/// Longest side of a thumbnail
+pub const THUMBNAIL_SIZE: u32 = 320;
+const THUMBNAIL_QUALITY: u8 = 80;
+
+const TAG_MAKE: u16 = 0x010f;
+const TAG_MODEL: u16 = 0x0110;
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_OFFSET_TIME_ORIGINAL: u16 = 0x9011;
+const TAG_GPS_LATITUDE_REF: u16 = 1;
+const TAG_GPS_LATITUDE: u16 = 2;
+const TAG_GPS_LONGITUDE_REF: u16 = 3;
+const TAG_GPS_LONGITUDE: u16 = 4;
+
+/// Fields read from an image's EXIF
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExifSummary {
+    /// DateTimeOriginal, RFC 3339 when the camera recorded its UTC offset
+    /// and without an offset otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub captured_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub make: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<u16>,
+}
+
+// ======================== EXIF ========================
+
+struct Entry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    /// Offset of the entry's value field
+    field: usize,
+}
+
+/// A TIFF structure, the payload of an EXIF block
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let little_endian = match data.get(..4)? {
+            [b'I', b'I', 42, 0] => true,
+            [b'M', b'M', 0, 42] => false,
+            _ => return None,
+        };
+        Some(Self {
+            data,
+            little_endian,
+        })
+    }
+
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn ifd(&self, offset: usize) -> Vec<Entry> {
+        let count = self.u16_at(offset).unwrap_or(0) as usize;
+        (0..count)
+            .map_while(|i| {
+                let at = offset + 2 + i * 12;
+                Some(Entry {
+                    tag: self.u16_at(at)?,
+                    kind: self.u16_at(at + 2)?,
+                    count: self.u32_at(at + 4)?,
+                    field: at + 8,
+                })
+            })
+            .collect()
+    }
+
+    fn value(&self, entry: &Entry) -> Option<&'a [u8]> {
+        let unit = match entry.kind {
+            1 | 2 | 7 => 1,
+            3 => 2,
+            4 | 9 => 4,
+            5 | 10 => 8,
+            _ => return None,
+        };
+        let len = unit * entry.count as usize;
+        let start = if len <= 4 {
+            entry.field
+        } else {
+            self.u32_at(entry.field)? as usize
+        };
+        self.data.get(start..start.checked_add(len)?)
+    }
+
+    fn ascii(&self, entry: &Entry) -> Option<String> {
+        let text = String::from_utf8_lossy(self.value(entry)?);
+        let text = text.trim_end_matches('\0').trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    fn short(&self, entry: &Entry) -> Option<u16> {
+        (entry.kind == 3).then(|| self.u16_at(entry.field))?
+    }
+
+    fn offset(&self, entry: &Entry) -> Option<usize> {
+        (entry.kind == 4).then(|| self.u32_at(entry.field).map(|o| o as usize))?
+    }
+
+    /// Degrees from three RATIONALs of degrees, minutes and seconds
+    fn degrees(&self, entry: &Entry) -> Option<f64> {
+        if entry.kind != 5 || entry.count != 3 {
+            return None;
+        }
+        let start = self.u32_at(entry.field)? as usize;
+        let mut parts = (0..3).map(|i| {
+            let numerator = self.u32_at(start + i * 8)?;
+            let denominator = self.u32_at(start + i * 8 + 4)?;
+            (denominator != 0).then(|| f64::from(numerator) / f64::from(denominator))
+        });
+        let (d, m, s) = (parts.next()??, parts.next()??, parts.next()??);
+        Some(d + m / 60.0 + s / 3600.0)
+    }
+}
+
+/// Signed coordinate from a GPS value and its N/S or E/W reference
+fn coordinate(tiff: &Tiff, value: Option<&Entry>, reference: Option<&Entry>) -> Option<f64> {
+    let degrees = tiff.degrees(value?)?;
+    match tiff.ascii(reference?)?.as_str() {
+        "N" | "E" => Some(degrees),
+        "S" | "W" => Some(-degrees),
+        _ => None,
+    }
+}
+
+/// Capture time from EXIF `YYYY:MM:DD HH:MM:SS` and an optional `+HH:MM`
+fn capture_time(date_time: &str, offset: Option<&str>) -> Option<String> {
+    let local = NaiveDateTime::parse_from_str(date_time, "%Y:%m:%d %H:%M:%S").ok()?;
+    let offset = offset.and_then(|o| {
+        let sign = if o.starts_with('-') { -1 } else { 1 };
+        let (hours, minutes) = o.get(1..)?.split_once(':')?;
+        let seconds = hours.parse::<i32>().ok()? * 3600 + minutes.parse::<i32>().ok()? * 60;
+        FixedOffset::east_opt(sign * seconds)
+    });
+    Some(match offset {
+        Some(offset) => local
+            .and_local_timezone(offset)
+            .single()?
+            .to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        None => local.format("%Y-%m-%dT%H:%M:%S").to_string(),
+    })
+}
+
+/// Summary of an EXIF block, `None` when it holds none of the read tags
+pub fn parse_exif(block: &[u8]) -> Option<ExifSummary> {
+    let tiff = Tiff::new(block.strip_prefix(b"Exif\0\0").unwrap_or(block))?;
+    let ifd0 = tiff.ifd(tiff.u32_at(4)? as usize);
+    let find = |entries: &'_ [Entry], tag: u16| entries.iter().position(|e| e.tag == tag);
+
+    let mut summary = ExifSummary::default();
+    for entry in &ifd0 {
+        match entry.tag {
+            TAG_MAKE => summary.make = tiff.ascii(entry),
+            TAG_MODEL => summary.model = tiff.ascii(entry),
+            TAG_ORIENTATION => summary.orientation = tiff.short(entry),
+            _ => {}
+        }
+    }
+    let sub_ifd = |tag| {
+        find(&ifd0, tag)
+            .and_then(|i| tiff.offset(&ifd0[i]))
+            .map(|offset| tiff.ifd(offset))
+            .unwrap_or_default()
+    };
+
+    let exif = sub_ifd(TAG_EXIF_IFD);
+    let text = |tag| find(&exif, tag).and_then(|i| tiff.ascii(&exif[i]));
+    summary.captured_at = text(TAG_DATE_TIME_ORIGINAL)
+        .and_then(|dt| capture_time(&dt, text(TAG_OFFSET_TIME_ORIGINAL).as_deref()));
+
+    let gps = sub_ifd(TAG_GPS_IFD);
+    let gps_entry = |tag| find(&gps, tag).map(|i| &gps[i]);
+    summary.latitude = coordinate(
+        &tiff,
+        gps_entry(TAG_GPS_LATITUDE),
+        gps_entry(TAG_GPS_LATITUDE_REF),
+    );
+    summary.longitude = coordinate(
+        &tiff,
+        gps_entry(TAG_GPS_LONGITUDE),
+        gps_entry(TAG_GPS_LONGITUDE_REF),
+    );
+    if summary.latitude.is_none() || summary.longitude.is_none() {
+        summary.latitude = None;
+        summary.longitude = None;
+    }
+
+    (summary != ExifSummary::default()).then_some(summary)
+}
+
+// ======================== STRIPPING ========================
+
+fn strip_jpeg(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut out = bytes.get(..2)?.to_vec();
+    let mut at = 2;
+    loop {
+        let marker = *bytes.get(at..at + 2)?.get(1)?;
+        if bytes[at] != 0xff {
+            return None;
+        }
+        // Start of scan: the rest is entropy-coded data
+        if marker == 0xda {
+            out.extend_from_slice(&bytes[at..]);
+            return Some(out);
+        }
+        let len = u16::from_be_bytes(bytes.get(at + 2..at + 4)?.try_into().ok()?) as usize;
+        let end = at + 2 + len;
+        if !matches!(marker, 0xe1 | 0xed) {
+            out.extend_from_slice(bytes.get(at..end)?);
+        }
+        at = end;
+    }
+}
+
+fn strip_png(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut out = bytes.get(..8)?.to_vec();
+    let mut at = 8;
+    while at < bytes.len() {
+        let len = u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?) as usize;
+        let end = at.checked_add(12 + len)?;
+        let kind = bytes.get(at + 4..at + 8)?;
+        if !matches!(kind, b"eXIf" | b"tEXt" | b"zTXt" | b"iTXt") {
+            out.extend_from_slice(bytes.get(at..end)?);
+        }
+        at = end;
+    }
+    Some(out)
+}
+
+fn strip_webp(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut chunks = Vec::with_capacity(bytes.len());
+    let mut at = 12;
+    while at < bytes.len() {
+        let len = u32::from_le_bytes(bytes.get(at + 4..at + 8)?.try_into().ok()?) as usize;
+        let end = at.checked_add(8 + len + len % 2)?.min(bytes.len());
+        let chunk = bytes.get(at..end)?;
+        match &chunk[..4] {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" => {
+                let mut chunk = chunk.to_vec();
+                // Clear the EXIF and XMP flags
+                if let Some(flags) = chunk.get_mut(8) {
+                    *flags &= !0x0c;
+                }
+                chunks.extend_from_slice(&chunk);
+            }
+            _ => chunks.extend_from_slice(chunk),
+        }
+        at = end;
+    }
+    let mut out = b"RIFF".to_vec();
+    out.extend_from_slice(&(chunks.len() as u32 + 4).to_le_bytes());
+    out.extend_from_slice(b"WEBP");
+    out.extend_from_slice(&chunks);
+    Some(out)
+}
+
+/// Copy of an encoded image without its metadata segments; other formats
+/// and malformed files are returned unchanged
+pub fn strip_metadata(bytes: &[u8]) -> Vec<u8> {
+    let stripped = match bytes {
+        [0xff, 0xd8, ..] => strip_jpeg(bytes),
+        [0x89, b'P', b'N', b'G', ..] => strip_png(bytes),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => strip_webp(bytes),
+        _ => None,
+    };
+    stripped.unwrap_or_else(|| bytes.to_vec())
+}
+
+// ======================== PIPELINE ========================
+
+pub struct ProcessedImage {
+    pub exif: Option<ExifSummary>,
+    /// dHash of the image as stored, before orientation is applied
+    pub perceptual_hash: u64,
+    /// JPEG thumbnail
+    pub thumbnail: Vec<u8>,
+    /// The upload without metadata, for publication
+    pub stripped: Vec<u8>,
+}
+
+/// Decode an encoded JPEG, PNG or WebP image and derive everything kept of it
+pub fn process(bytes: &[u8]) -> Result<ProcessedImage> {
+    let mut reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .context("Failed to read image")?;
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder().context("Failed to read image")?;
+    let exif = decoder
+        .exif_metadata()
+        .ok()
+        .flatten()
+        .and_then(|block| parse_exif(&block));
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder).context("Failed to decode image")?;
+
+    let perceptual_hash = perceptual_hash::dhash(&image);
+    image.apply_orientation(orientation);
+    let mut thumbnail = Vec::new();
+    JpegEncoder::new_with_quality(&mut thumbnail, THUMBNAIL_QUALITY)
+        .encode_image(&image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgb8())
+        .context("Failed to encode thumbnail")?;
+
+    Ok(ProcessedImage {
+        exif,
+        perceptual_hash,
+        thumbnail,
+        stripped: strip_metadata(bytes),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Rgb, RgbImage};
+
+    /// Little-endian TIFF with Make, Orientation, capture time and a GPS fix
+    fn exif_block() -> Vec<u8> {
+        fn entry(out: &mut Vec<u8>, tag: u16, kind: u16, count: u32, value: u32) {
+            out.extend_from_slice(&tag.to_le_bytes());
+            out.extend_from_slice(&kind.to_le_bytes());
+            out.extend_from_slice(&count.to_le_bytes());
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        fn rationals(out: &mut Vec<u8>, values: [(u32, u32); 3]) {
+            for (n, d) in values {
+                out.extend_from_slice(&n.to_le_bytes());
+                out.extend_from_slice(&d.to_le_bytes());
+            }
+        }
+        // IFD0 at 8 (4 entries), Exif IFD at 62 (2), GPS IFD at 92 (4),
+        // then the values
+        let (exif_ifd, gps_ifd, data) = (62u32, 92u32, 146u32);
+        let mut out = b"II*\0".to_vec();
+        out.extend_from_slice(&8u32.to_le_bytes());
+
+        out.extend_from_slice(&4u16.to_le_bytes());
+        entry(&mut out, TAG_MAKE, 2, 6, data);
+        entry(&mut out, TAG_ORIENTATION, 3, 1, 6);
+        entry(&mut out, TAG_EXIF_IFD, 4, 1, exif_ifd);
+        entry(&mut out, TAG_GPS_IFD, 4, 1, gps_ifd);
+        out.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(out.len() as u32, exif_ifd);
+
+        out.extend_from_slice(&2u16.to_le_bytes());
+        entry(&mut out, TAG_DATE_TIME_ORIGINAL, 2, 20, data + 6);
+        entry(&mut out, TAG_OFFSET_TIME_ORIGINAL, 2, 7, data + 26);
+        out.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(out.len() as u32, gps_ifd);
+
+        out.extend_from_slice(&4u16.to_le_bytes());
+        entry(&mut out, TAG_GPS_LATITUDE_REF, 2, 2, u32::from(b'N'));
+        entry(&mut out, TAG_GPS_LATITUDE, 5, 3, data + 33);
+        entry(&mut out, TAG_GPS_LONGITUDE_REF, 2, 2, u32::from(b'E'));
+        entry(&mut out, TAG_GPS_LONGITUDE, 5, 3, data + 57);
+        out.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(out.len() as u32, data);
+
+        out.extend_from_slice(b"Canon\0");
+        out.extend_from_slice(b"2025:06:02 09:15:00\0");
+        out.extend_from_slice(b"+05:30\0");
+        rationals(&mut out, [(21, 1), (8, 1), (4488, 100)]);
+        rationals(&mut out, [(79, 1), (5, 1), (1752, 100)]);
+        out
+    }
+
+    fn jpeg_with_exif() -> Vec<u8> {
+        let image = RgbImage::from_fn(640, 480, |x, y| Rgb([x as u8, y as u8, 90]));
+        let mut jpeg = Cursor::new(Vec::new());
+        image.write_to(&mut jpeg, ImageFormat::Jpeg).unwrap();
+        let jpeg = jpeg.into_inner();
+
+        let mut payload = b"Exif\0\0".to_vec();
+        payload.extend_from_slice(&exif_block());
+        let mut out = jpeg[..2].to_vec();
+        out.extend_from_slice(&[0xff, 0xe1]);
+        out.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+        out.extend_from_slice(&payload);
+        out.extend_from_slice(&jpeg[2..]);
+        out
+    }
+
+    #[test]
+    fn test_parse_exif() {
+        let summary = parse_exif(&exif_block()).unwrap();
+        assert_eq!(summary.make.as_deref(), Some("Canon"));
+        assert_eq!(summary.orientation, Some(6));
+        assert_eq!(
+            summary.captured_at.as_deref(),
+            Some("2025-06-02T09:15:00+05:30")
+        );
+        assert!((summary.latitude.unwrap() - 21.1458).abs() < 1e-4);
+        assert!((summary.longitude.unwrap() - 79.0882).abs() < 1e-4);
+        assert_eq!(parse_exif(b"not a tiff"), None);
+        assert!(process(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_process_strips_exif_and_renders_upright_thumbnail() {
+        let jpeg = jpeg_with_exif();
+        let processed = process(&jpeg).unwrap();
+        assert_eq!(processed.exif.unwrap().make.as_deref(), Some("Canon"));
+
+        // Orientation 6 turns the landscape image upright as portrait
+        let thumbnail = image::load_from_memory(&processed.thumbnail).unwrap();
+        assert_eq!(
+            (thumbnail.width(), thumbnail.height()),
+            (240, THUMBNAIL_SIZE)
+        );
+
+        assert_eq!(
+            processed.stripped.len(),
+            jpeg.len() - exif_block().len() - 10
+        );
+        let stripped = process(&processed.stripped).unwrap();
+        assert!(stripped.exif.is_none());
+        assert_eq!(stripped.perceptual_hash, processed.perceptual_hash);
+    }
+}
@@ -0,0 +1,735 @@
+//! Chain event indexer
+//!
+//! The backend writes every supply-chain step to the contract, but the chain
+//! itself can only be searched by replaying logs. This background job follows
+//! the contract's events (farmer registrations, ownership transfers,
+//! warehouse and logistics updates, processing, packaging, fraud reports and
+//! AI scores) into `data/events.db`, so "what happened to batch X" is a single
+//! query:
+//!
+//! - `GET /api/events/batch/:batch_id` - events concerning a batch, including
+//!   processing outputs, its SKUs and fraud reports against them
+//! - `GET /api/events/farmer/:farmer_did` - registrations and purchases
+//! - `GET /api/events?from=&to=&event=` - by event timestamp (unix seconds)
+//!
+//! Only blocks with INDEXER_CONFIRMATIONS confirmations are indexed. The first
+//! run starts at INDEXER_START_BLOCK (set it to the deployment block for full
+//! history) or at the current head. During a blue/green migration the legacy
+//! contract is indexed too. The index is derived from the chain, so it is
+//! left out of snapshots and rebuilt after a restore, together with the
+//! summaries folded from it (see [`crate::read_models`]).
+
+use crate::analytics;
+use crate::chain::{hash_string, DecodedEvent, OilseedValueChain::OilseedValueChainEvents};
+use crate::did_resolver::chain_did;
+use crate::error::{format_hash, format_tx_hash, ApiError, ApiResult};
+use crate::pagination::{decode_cursor, encode_cursor, Page, PageParams};
+use crate::read_models::{self, ReadModel};
+use crate::state::AppState;
+use alloy::primitives::FixedBytes;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::sqlite::{
+    SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions, SqliteRow,
+};
+use sqlx::{QueryBuilder, Row, Sqlite};
+use std::time::Duration;
+
+/// SQLite database holding the event index
+pub const DB_PATH: &str = "data/events.db";
+const DEFAULT_INTERVAL_SECS: u64 = 15;
+const DEFAULT_CONFIRMATIONS: u64 = 3;
+const DEFAULT_MAX_BLOCK_RANGE: u64 = 5000;
+/// Events read at a time when folding an existing index into the read models
+const BACKFILL_PAGE: usize = 1000;
+
+// ======================== EVENTS ========================
+
+/// Contract event as stored in the index
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IndexedEvent {
+    pub block_number: u64,
+    pub log_index: u64,
+    pub tx_hash: String,
+    pub contract: String,
+    pub event: String,
+    /// Timestamp carried by the event (unix seconds)
+    pub timestamp: u64,
+    /// Primary indexed id: farmer DID, batch, warehouse, shipment or SKU hash
+    pub subject: String,
+    pub farmer_did: Option<String>,
+    pub metadata_cid: Option<String>,
+    /// Batches the event concerns
+    pub batch_hashes: Vec<String>,
+    /// Remaining event fields
+    pub fields: serde_json::Value,
+}
+
+/// Map a decoded contract event to its index entry; role changes are not
+/// supply-chain history and are skipped
+pub fn index_event(decoded: &DecodedEvent) -> Option<IndexedEvent> {
+    let hash = |h: &FixedBytes<32>| format_hash(h);
+    let (event, timestamp, subject, farmer_did, batch_hashes, metadata_cid, fields) =
+        match &decoded.event {
+            OilseedValueChainEvents::FarmerRegistered(e) => (
+                "FarmerRegistered",
+                e.timestamp,
+                hash(&e.farmerDID),
+                Some(hash(&e.farmerDID)),
+                vec![],
+                Some(e.metadataCID.clone()),
+                json!({ "crop_id_hash": hash(&e.cropIDHash) }),
+            ),
+            OilseedValueChainEvents::FarmerUpdated(e) => (
+                "FarmerUpdated",
+                e.timestamp,
+                hash(&e.farmerDID),
+                Some(hash(&e.farmerDID)),
+                vec![],
+                Some(e.metadataCID.clone()),
+                json!({ "crop_id_hash": hash(&e.cropIDHash) }),
+            ),
+            OilseedValueChainEvents::OwnershipTransfer(e) => (
+                "OwnershipTransfer",
+                e.timestamp,
+                hash(&e.batchHash),
+                Some(hash(&e.fromDID)),
+                vec![hash(&e.batchHash)],
+                Some(e.metadataCID.clone()),
+                json!({
+                    "to_address": e.toAddress.to_string(),
+                    "transfer_type": e.transferType,
+                }),
+            ),
+            OilseedValueChainEvents::WarehouseStateUpdated(e) => (
+                "WarehouseStateUpdated",
+                e.timestamp,
+                hash(&e.warehouseId),
+                None,
+                vec![],
+                Some(e.metadataCID.clone()),
+                json!({ "state_hash": hash(&e.stateHash) }),
+            ),
+            OilseedValueChainEvents::LogisticsMilestone(e) => (
+                "LogisticsMilestone",
+                e.timestamp,
+                hash(&e.shipmentId),
+                None,
+                vec![],
+                Some(e.metadataCID.clone()),
+                json!({
+                    "location_hash": hash(&e.locationHash),
+                    "is_delivered": e.isDelivered,
+                }),
+            ),
+            OilseedValueChainEvents::BatchProcessed(e) => (
+                "BatchProcessed",
+                e.timestamp,
+                hash(&e.inputBatchHash),
+                None,
+                std::iter::once(&e.inputBatchHash)
+                    .chain(&e.outputBatchHashes)
+                    .map(hash)
+                    .collect(),
+                Some(e.metadataCID.clone()),
+                json!({
+                    "transform_hash": hash(&e.transformHash),
+                    "output_batch_hashes": e.outputBatchHashes.iter().map(hash).collect::<Vec<_>>(),
+                }),
+            ),
+            OilseedValueChainEvents::SKUPackaged(e) => (
+                "SKUPackaged",
+                e.timestamp,
+                hash(&e.skuId),
+                None,
+                vec![hash(&e.parentBatchHash)],
+                Some(e.metadataCID.clone()),
+                json!({ "merkle_root": hash(&e.merkleRoot) }),
+            ),
+            // The parent batch is filled in from the SKU's packaging event
+            OilseedValueChainEvents::FraudDetected(e) => (
+                "FraudDetected",
+                e.timestamp,
+                hash(&e.skuId),
+                None,
+                vec![],
+                Some(e.evidenceCID.clone()),
+                json!({
+                    "reporter": e.reporter.to_string(),
+                    "evidence_hash": hash(&e.evidenceHash),
+                }),
+            ),
+            OilseedValueChainEvents::AIScoreCommitted(e) => (
+                "AIScoreCommitted",
+                e.timestamp,
+                hash(&e.batchHash),
+                None,
+                vec![hash(&e.batchHash)],
+                None,
+                json!({ "commit_hash": hash(&e.commitHash) }),
+            ),
+            OilseedValueChainEvents::AIScoreRevealed(e) => (
+                "AIScoreRevealed",
+                e.timestamp,
+                hash(&e.batchHash),
+                None,
+                vec![hash(&e.batchHash)],
+                Some(e.metadataCID.clone()),
+                json!({ "reveal_hash": hash(&e.revealHash) }),
+            ),
+            _ => return None,
+        };
+
+    Some(IndexedEvent {
+        block_number: decoded.position.block_number,
+        log_index: decoded.position.log_index,
+        tx_hash: format_tx_hash(decoded.position.tx_hash),
+        contract: decoded.contract.to_string(),
+        event: event.to_string(),
+        timestamp,
+        subject,
+        farmer_did,
+        metadata_cid,
+        batch_hashes,
+        fields,
+    })
+}
+
+// ======================== STORAGE ========================
+
+/// Which events a query returns
+#[derive(Debug, Clone)]
+pub enum EventFilter {
+    Batch(String),
+    Farmer(String),
+    /// Primary indexed id, e.g. a warehouse or shipment hash
+    Subject(String),
+    TimeRange {
+        from: Option<u64>,
+        to: Option<u64>,
+        event: Option<String>,
+    },
+}
+
+/// SQLite-backed event index plus the job's block cursor
+pub struct EventIndex {
+    pool: SqlitePool,
+    /// Read-only connections for reports and exports (see [`crate::analytics`])
+    analytics: SqlitePool,
+}
+
+impl EventIndex {
+    /// Open `data/events.db` and apply migrations
+    pub async fn open() -> Result<Self> {
+        if let Some(dir) = std::path::Path::new(DB_PATH).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let options = SqliteConnectOptions::new()
+            .filename(DB_PATH)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(Duration::from_secs(5));
+        let mut index = Self::connect(options.clone(), 4).await?;
+        index.analytics = analytics::open_pool(&options).await?;
+        Ok(index)
+    }
+
+    /// Connect with the given options and bring the schema up to date
+    pub async fn connect(options: SqliteConnectOptions, max_connections: u32) -> Result<Self> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await
+            .context("Failed to open event index")?;
+        sqlx::migrate!("./migrations/events")
+            .run(&pool)
+            .await
+            .context("Failed to migrate event index")?;
+        let index = Self {
+            analytics: pool.clone(),
+            pool,
+        };
+        index
+            .backfill_read_models()
+            .await
+            .context("Failed to build read models")?;
+        Ok(index)
+    }
+
+    /// Fold the events of an index built before the read models into them
+    async fn backfill_read_models(&self) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        if !read_models::is_empty(&mut tx).await? {
+            return Ok(());
+        }
+        let mut after = (-1i64, -1i64);
+        let mut folded = 0;
+        loop {
+            let events = sqlx::query(
+                "SELECT * FROM chain_events WHERE (block_number, log_index) > ($1, $2) \
+                 ORDER BY block_number, log_index LIMIT $3",
+            )
+            .bind(after.0)
+            .bind(after.1)
+            .bind(BACKFILL_PAGE as i64)
+            .fetch_all(&mut *tx)
+            .await?
+            .iter()
+            .map(row_to_event)
+            .collect::<Result<Vec<_>>>()?;
+            let Some(last) = events.last() else {
+                break;
+            };
+            after = (last.block_number as i64, last.log_index as i64);
+            for event in &events {
+                read_models::apply(&mut tx, event).await?;
+            }
+            folded += events.len();
+        }
+        tx.commit().await?;
+        if folded > 0 {
+            tracing::info!(events = folded, "Built read models from the event index");
+        }
+        Ok(())
+    }
+
+    /// Read model stored for `id` (batch or SKU hash, or farmer DID)
+    pub async fn read_model<M: ReadModel>(&self, id: &str) -> Result<Option<M>> {
+        read_models::load(&mut *self.pool.acquire().await?, id).await
+    }
+
+    /// First block not yet indexed
+    pub async fn next_block(&self) -> Result<Option<u64>> {
+        let next: Option<i64> =
+            sqlx::query_scalar("SELECT next_block FROM indexer_cursor WHERE id = 1")
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(next.map(|n| n as u64))
+    }
+
+    pub async fn total_events(&self) -> Result<u64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM chain_events")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count as u64)
+    }
+
+    /// Store the events of a block range, fold them into the read models and
+    /// advance the cursor atomically
+    pub async fn store(&self, events: Vec<IndexedEvent>, next_block: u64) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for mut event in events {
+            if event.event == "FraudDetected" {
+                event.batch_hashes = sqlx::query_scalar(
+                    "SELECT b.batch_hash FROM chain_events e \
+                     JOIN chain_event_batches b \
+                       ON b.block_number = e.block_number AND b.log_index = e.log_index \
+                     WHERE e.event = 'SKUPackaged' AND e.subject = $1",
+                )
+                .bind(&event.subject)
+                .fetch_all(&mut *tx)
+                .await?;
+            }
+
+            sqlx::query(
+                "INSERT OR REPLACE INTO chain_events (block_number, log_index, tx_hash, \
+                 contract, event, timestamp, subject, farmer_did, metadata_cid, batch_hashes, \
+                 fields) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+            )
+            .bind(event.block_number as i64)
+            .bind(event.log_index as i64)
+            .bind(&event.tx_hash)
+            .bind(&event.contract)
+            .bind(&event.event)
+            .bind(event.timestamp as i64)
+            .bind(&event.subject)
+            .bind(&event.farmer_did)
+            .bind(&event.metadata_cid)
+            .bind(serde_json::to_string(&event.batch_hashes)?)
+            .bind(event.fields.to_string())
+            .execute(&mut *tx)
+            .await?;
+
+            for batch_hash in &event.batch_hashes {
+                sqlx::query(
+                    "INSERT OR IGNORE INTO chain_event_batches (batch_hash, block_number, \
+                     log_index) VALUES ($1, $2, $3)",
+                )
+                .bind(batch_hash)
+                .bind(event.block_number as i64)
+                .bind(event.log_index as i64)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            read_models::apply(&mut tx, &event).await?;
+        }
+
+        sqlx::query(
+            "INSERT INTO indexer_cursor (id, next_block) VALUES (1, $1) \
+             ON CONFLICT (id) DO UPDATE SET next_block = excluded.next_block",
+        )
+        .bind(next_block as i64)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Events matching `filter` in chain order, after the `(block, log index)`
+    /// position `after`
+    pub async fn query(
+        &self,
+        filter: &EventFilter,
+        after: Option<(u64, u64)>,
+        limit: usize,
+    ) -> Result<Vec<IndexedEvent>> {
+        query_events(&self.pool, filter, after, limit).await
+    }
+
+    /// [`Self::query`] from an analytics snapshot
+    pub async fn query_snapshot(
+        &self,
+        filter: &EventFilter,
+        after: Option<(u64, u64)>,
+        limit: usize,
+    ) -> Result<Vec<IndexedEvent>> {
+        query_events(&self.analytics, filter, after, limit).await
+    }
+}
+
+async fn query_events(
+    pool: &SqlitePool,
+    filter: &EventFilter,
+    after: Option<(u64, u64)>,
+    limit: usize,
+) -> Result<Vec<IndexedEvent>> {
+    let mut query = QueryBuilder::<Sqlite>::new("SELECT e.* FROM chain_events e ");
+    match filter {
+        EventFilter::Batch(batch_hash) => {
+            query
+                .push(
+                    "JOIN chain_event_batches b \
+                     ON b.block_number = e.block_number AND b.log_index = e.log_index \
+                     WHERE b.batch_hash = ",
+                )
+                .push_bind(batch_hash.clone());
+        }
+        EventFilter::Farmer(farmer_did) => {
+            query
+                .push("WHERE e.farmer_did = ")
+                .push_bind(farmer_did.clone());
+        }
+        EventFilter::Subject(subject) => {
+            query.push("WHERE e.subject = ").push_bind(subject.clone());
+        }
+        EventFilter::TimeRange { from, to, event } => {
+            query.push("WHERE 1 = 1");
+            if let Some(from) = from {
+                query.push(" AND e.timestamp >= ").push_bind(*from as i64);
+            }
+            if let Some(to) = to {
+                query.push(" AND e.timestamp <= ").push_bind(*to as i64);
+            }
+            if let Some(event) = event {
+                query.push(" AND e.event = ").push_bind(event.clone());
+            }
+        }
+    }
+    if let Some((block_number, log_index)) = after {
+        query
+            .push(" AND (e.block_number, e.log_index) > (")
+            .push_bind(block_number as i64)
+            .push(", ")
+            .push_bind(log_index as i64)
+            .push(")");
+    }
+    query
+        .push(" ORDER BY e.block_number, e.log_index LIMIT ")
+        .push_bind(limit as i64);
+
+    query
+        .build()
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(row_to_event)
+        .collect()
+}
+
+fn row_to_event(row: &SqliteRow) -> Result<IndexedEvent> {
+    Ok(IndexedEvent {
+        block_number: row.try_get::<i64, _>("block_number")? as u64,
+        log_index: row.try_get::<i64, _>("log_index")? as u64,
+        tx_hash: row.try_get("tx_hash")?,
+        contract: row.try_get("contract")?,
+        event: row.try_get("event")?,
+        timestamp: row.try_get::<i64, _>("timestamp")? as u64,
+        subject: row.try_get("subject")?,
+        farmer_did: row.try_get("farmer_did")?,
+        metadata_cid: row.try_get("metadata_cid")?,
+        batch_hashes: serde_json::from_str(row.try_get("batch_hashes")?)?,
+        fields: serde_json::from_str(row.try_get("fields")?)?,
+    })
+}
+
+// ======================== INDEXER JOB ========================
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Outcome of indexing one block range
+#[derive(Debug, Clone, Copy)]
+pub struct IndexRun {
+    pub from_block: u64,
+    pub to_block: u64,
+    pub events: usize,
+    /// No confirmed blocks are left to index
+    pub caught_up: bool,
+}
+
+/// Index the next range of confirmed blocks, if any
+pub async fn run_once(state: &AppState) -> Result<Option<IndexRun>> {
+    let confirmations = env_u64("INDEXER_CONFIRMATIONS", DEFAULT_CONFIRMATIONS);
+    let max_range = env_u64("INDEXER_MAX_BLOCK_RANGE", DEFAULT_MAX_BLOCK_RANGE).max(1);
+
+    let latest = state.chain().latest_block().await?;
+    let Some(confirmed) = latest.checked_sub(confirmations) else {
+        return Ok(None);
+    };
+    let from_block = match state.events.next_block().await? {
+        Some(block) => block,
+        None => env_u64("INDEXER_START_BLOCK", confirmed),
+    };
+    if from_block > confirmed {
+        return Ok(None);
+    }
+    let to_block = confirmed.min(from_block + max_range - 1);
+
+    let decoded = state.chain().decoded_events(from_block, to_block).await?;
+    // Role changes are not indexed but keep API permissions in step
+    state
+        .chain_roles
+        .apply_events(&decoded, state.chain().write_contract_address())?;
+    let events: Vec<IndexedEvent> = decoded.iter().filter_map(index_event).collect();
+    let count = events.len();
+    state.events.store(events, to_block + 1).await?;
+
+    Ok(Some(IndexRun {
+        from_block,
+        to_block,
+        events: count,
+        caught_up: to_block == confirmed,
+    }))
+}
+
+/// Start the indexer; it catches up range by range, then polls for new blocks
+pub fn spawn(state: AppState) {
+    let interval =
+        Duration::from_secs(env_u64("INDEXER_INTERVAL_SECS", DEFAULT_INTERVAL_SECS).max(1));
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            loop {
+                match run_once(&state).await {
+                    Ok(Some(run)) => {
+                        tracing::debug!(
+                            from_block = run.from_block,
+                            to_block = run.to_block,
+                            events = run.events,
+                            "Indexed contract events"
+                        );
+                        if run.caught_up {
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        tracing::error!(error = %format!("{:#}", e), "Event indexer failed");
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct TimeRangeParams {
+    /// Unix seconds, inclusive
+    pub from: Option<u64>,
+    /// Unix seconds, inclusive
+    pub to: Option<u64>,
+    /// Event name, e.g. `OwnershipTransfer`
+    pub event: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IndexerStatus {
+    pub next_block: Option<u64>,
+    pub total_events: u64,
+}
+
+pub(crate) async fn query_page(
+    state: &AppState,
+    scope: &str,
+    filter: EventFilter,
+    params: &PageParams,
+) -> ApiResult<Page<IndexedEvent>> {
+    let limit = params.limit();
+    let after: Option<(u64, u64)> = params
+        .cursor
+        .as_deref()
+        .map(|cursor| decode_cursor(scope, cursor))
+        .transpose()?;
+
+    let mut items = state.events.query(&filter, after, limit + 1).await?;
+    let has_more = items.len() > limit;
+    items.truncate(limit);
+
+    let next_cursor = match (has_more, items.last()) {
+        (true, Some(last)) => Some(encode_cursor(scope, &(last.block_number, last.log_index))?),
+        _ => None,
+    };
+
+    Ok(Json(Page {
+        items,
+        next_cursor,
+        has_more,
+        limit,
+    }))
+}
+
+pub async fn events_by_batch(
+    State(state): State<AppState>,
+    Path(batch_id): Path<String>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<IndexedEvent>> {
+    let filter = EventFilter::Batch(format_hash(hash_string(&batch_id)));
+    query_page(&state, "events/batch", filter, &params).await
+}
+
+pub async fn events_by_farmer(
+    State(state): State<AppState>,
+    Path(farmer_did): Path<String>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<IndexedEvent>> {
+    let farmer_did = chain_did(&farmer_did).map_err(ApiError::invalid_did)?;
+    let filter = EventFilter::Farmer(format_hash(farmer_did));
+    query_page(&state, "events/farmer", filter, &params).await
+}
+
+pub async fn events_by_time(
+    State(state): State<AppState>,
+    Query(range): Query<TimeRangeParams>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<IndexedEvent>> {
+    if let (Some(from), Some(to)) = (range.from, range.to) {
+        if from > to {
+            return Err(ApiError::bad_request("`from` must not be after `to`"));
+        }
+    }
+    let filter = EventFilter::TimeRange {
+        from: range.from,
+        to: range.to,
+        event: range.event,
+    };
+    query_page(&state, "events", filter, &params).await
+}
+
+pub async fn indexer_status(State(state): State<AppState>) -> ApiResult<IndexerStatus> {
+    Ok(Json(IndexerStatus {
+        next_block: state.events.next_block().await?,
+        total_events: state.events.total_events().await?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::{EventRef, OilseedValueChain};
+    use alloy::primitives::Address;
+
+    fn decoded(block_number: u64, event: OilseedValueChainEvents) -> DecodedEvent {
+        DecodedEvent {
+            position: EventRef {
+                block_number,
+                tx_hash: FixedBytes::repeat_byte(block_number as u8),
+                log_index: 0,
+            },
+            contract: Address::ZERO,
+            event,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_query_follows_skus_to_fraud_reports() {
+        let options = "sqlite::memory:".parse::<SqliteConnectOptions>().unwrap();
+        let index = EventIndex::connect(options, 1).await.unwrap();
+
+        let batch = hash_string("BATCH-1");
+        let sku = hash_string("SKU-1");
+        let events = [
+            decoded(
+                10,
+                OilseedValueChainEvents::SKUPackaged(OilseedValueChain::SKUPackaged {
+                    skuId: sku,
+                    parentBatchHash: batch,
+                    merkleRoot: FixedBytes::ZERO,
+                    timestamp: 1_700_000_000,
+                    metadataCID: "QmSku".to_string(),
+                }),
+            ),
+            decoded(
+                11,
+                OilseedValueChainEvents::FraudDetected(OilseedValueChain::FraudDetected {
+                    skuId: sku,
+                    reporter: Address::ZERO,
+                    evidenceHash: FixedBytes::ZERO,
+                    timestamp: 1_700_000_100,
+                    evidenceCID: "QmEvidence".to_string(),
+                }),
+            ),
+        ];
+        let indexed: Vec<IndexedEvent> = events.iter().filter_map(index_event).collect();
+        index.store(indexed, 12).await.unwrap();
+        assert_eq!(index.next_block().await.unwrap(), Some(12));
+
+        let by_batch = index
+            .query(&EventFilter::Batch(format_hash(batch)), None, 10)
+            .await
+            .unwrap();
+        let names: Vec<&str> = by_batch.iter().map(|e| e.event.as_str()).collect();
+        assert_eq!(names, ["SKUPackaged", "FraudDetected"]);
+
+        let after_first = index
+            .query(&EventFilter::Batch(format_hash(batch)), Some((10, 0)), 10)
+            .await
+            .unwrap();
+        assert_eq!(after_first.len(), 1);
+
+        let range = EventFilter::TimeRange {
+            from: Some(1_700_000_050),
+            to: None,
+            event: None,
+        };
+        let in_range = index.query(&range, None, 10).await.unwrap();
+        assert_eq!(in_range.len(), 1);
+        assert_eq!(in_range[0].metadata_cid.as_deref(), Some("QmEvidence"));
+    }
+}
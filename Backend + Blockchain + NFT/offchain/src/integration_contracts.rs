@@ -0,0 +1,242 @@
+//! Provider-side contract tests for third-party gateway integrations
+//!
+//! Each file in `fixtures/integrations/` records one interaction a gateway
+//! has with us: the HTTP request exactly as the provider sends it and the
+//! part of our response it relies on. The test replays every fixture
+//! through the extractors and reply builders of the production handlers,
+//! with the farmer directory and webhook secret supplied by the fixture, so
+//! a change to field names, encodings or reply framing fails here before a
+//! gateway breaks in the field.
+//!
+//! Covered endpoints: inbound SMS (`/api/sms/inbound`), USSD sessions
+//! (`/api/ussd/session`) and IVR steps (`/api/ivr/step`). Capturing a new
+//! provider quirk only takes a fixture:
+//!
+//! ```json
+//! {
+//!   "provider": "africastalking",
+//!   "description": "first dial shows the main menu",
+//!   "given": { "webhook_secret": "s3cret", "farmers": [] },
+//!   "request": {
+//!     "method": "POST",
+//!     "uri": "/api/ussd/session",
+//!     "headers": {
+//!       "content-type": "application/x-www-form-urlencoded",
+//!       "x-webhook-secret": "s3cret"
+//!     },
+//!     "body": "sessionId=ATUid_1&phoneNumber=%2B919876543210&text="
+//!   },
+//!   "response": { "status": 200, "body": "CON Oilseed Value Chain\n..." }
+//! }
+//! ```
+//!
+//! String bodies are sent and compared as text, anything else as JSON. A
+//! response without `body` only pins the status.
+
+use crate::error::ApiError;
+use crate::farmer_verification::FarmerEntry;
+use crate::sms::{self, check_webhook_secret, normalize_mobile, InboundSms, SmsQuery, SmsReply};
+use crate::ussd::{self, navigate, parse_path, IvrRequest, IvrResponse, UssdRequest};
+use axum::{
+    body::{to_bytes, Body},
+    extract::State,
+    http::{HeaderMap, Request},
+    response::Response,
+    routing::post,
+    Form, Json, Router,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tower::ServiceExt;
+
+const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/integrations");
+
+#[derive(Debug, Deserialize)]
+struct Interaction {
+    provider: String,
+    description: String,
+    #[serde(default)]
+    given: Given,
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+/// Our side of the interaction
+#[derive(Debug, Default, Deserialize)]
+struct Given {
+    /// SMS_WEBHOOK_SECRET; every webhook is rejected when absent
+    #[serde(default)]
+    webhook_secret: Option<String>,
+    /// Farmer verification database
+    #[serde(default)]
+    farmers: Vec<FarmerEntry>,
+}
+
+impl Given {
+    fn caller(&self, phone: &str) -> Option<&FarmerEntry> {
+        let mobile = normalize_mobile(phone);
+        self.farmers.iter().find(|f| f.mobile == mobile)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    /// Path and query string
+    uri: String,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    body: Value,
+}
+
+impl RecordedRequest {
+    fn build(self) -> Request<Body> {
+        let mut request = Request::builder()
+            .method(self.method.as_str())
+            .uri(self.uri);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let body = match self.body {
+            Value::Null => Body::empty(),
+            Value::String(text) => Body::from(text),
+            json => Body::from(json.to_string()),
+        };
+        request.body(body).expect("Invalid recorded request")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    #[serde(default)]
+    body: Option<Value>,
+}
+
+impl RecordedResponse {
+    /// Mismatch between the recorded and the actual response, if any
+    async fn mismatch(&self, response: Response) -> Option<String> {
+        let status = response.status().as_u16();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.ok()?;
+        if status != self.status {
+            return Some(format!(
+                "expected status {}, got {} ({})",
+                self.status,
+                status,
+                String::from_utf8_lossy(&bytes)
+            ));
+        }
+
+        let matches = match &self.body {
+            None => true,
+            Some(Value::String(text)) => bytes == text.as_bytes(),
+            Some(json) => serde_json::from_slice::<Value>(&bytes).ok().as_ref() == Some(json),
+        };
+        (!matches).then(|| {
+            format!(
+                "expected body {}, got {}",
+                self.body.as_ref().map(Value::to_string).unwrap_or_default(),
+                String::from_utf8_lossy(&bytes)
+            )
+        })
+    }
+}
+
+// ======================== GATEWAY HANDLERS ========================
+//
+// Same extractors and reply builders as `sms::inbound_sms`,
+// `ussd::ussd_session` and `ussd::ivr_step`; only the farmer lookups and
+// the outgoing SMS are replaced by the fixture.
+
+fn gateway_router(given: Given) -> Router {
+    Router::new()
+        .route("/api/sms/inbound", post(inbound_sms))
+        .route("/api/ussd/session", post(ussd_session))
+        .route("/api/ivr/step", post(ivr_step))
+        .with_state(Arc::new(given))
+}
+
+async fn inbound_sms(
+    State(given): State<Arc<Given>>,
+    headers: HeaderMap,
+    Form(payload): Form<InboundSms>,
+) -> Result<Json<SmsReply>, ApiError> {
+    check_webhook_secret(given.webhook_secret.as_deref(), &headers)?;
+
+    let query = SmsQuery::parse(&payload.body);
+    let message = sms::reply_to(given.caller(&payload.from), &query)?;
+    Ok(Json(SmsReply {
+        to: payload.from,
+        message,
+        delivered: false,
+    }))
+}
+
+async fn ussd_session(
+    State(given): State<Arc<Given>>,
+    headers: HeaderMap,
+    Form(payload): Form<UssdRequest>,
+) -> Result<String, ApiError> {
+    check_webhook_secret(given.webhook_secret.as_deref(), &headers)?;
+
+    let path = parse_path(&payload.text);
+    let step = navigate(&path, given.caller(&payload.phone_number), &given.farmers);
+    Ok(ussd::ussd_text(&step))
+}
+
+async fn ivr_step(
+    State(given): State<Arc<Given>>,
+    headers: HeaderMap,
+    Json(payload): Json<IvrRequest>,
+) -> Result<Json<IvrResponse>, ApiError> {
+    check_webhook_secret(given.webhook_secret.as_deref(), &headers)?;
+
+    let path = payload.path();
+    let step = navigate(&path, given.caller(&payload.caller), &given.farmers);
+    Ok(Json(IvrResponse::new(&path, step)))
+}
+
+fn load_fixtures() -> Vec<(String, Interaction)> {
+    let mut fixtures: Vec<(String, Interaction)> = std::fs::read_dir(FIXTURES_DIR)
+        .expect("Missing fixtures/integrations")
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .map(|path| {
+            let content = std::fs::read_to_string(&path).unwrap();
+            let interaction = serde_json::from_str(&content)
+                .unwrap_or_else(|e| panic!("Invalid fixture {}: {}", path.display(), e));
+            (
+                path.file_name().unwrap().to_string_lossy().to_string(),
+                interaction,
+            )
+        })
+        .collect();
+    fixtures.sort_by(|a, b| a.0.cmp(&b.0));
+    fixtures
+}
+
+#[tokio::test]
+async fn test_gateway_interactions_match_recorded_contracts() {
+    let fixtures = load_fixtures();
+    assert!(!fixtures.is_empty(), "No fixtures in {}", FIXTURES_DIR);
+
+    let mut failures = Vec::new();
+    for (file, interaction) in fixtures {
+        let router = gateway_router(interaction.given);
+        let response = router
+            .oneshot(interaction.request.build())
+            .await
+            .expect("Router is infallible");
+        if let Some(mismatch) = interaction.response.mismatch(response).await {
+            failures.push(format!(
+                "{} [{}] {}: {}",
+                file, interaction.provider, interaction.description, mismatch
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
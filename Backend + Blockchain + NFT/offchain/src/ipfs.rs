@@ -1,8 +1,16 @@
-use anyhow::{Context, Result};
+use crate::cid_provenance::{self, CidProvenance};
+use crate::compact_payload::{self, PayloadFormat};
+use crate::faults::{self, Fault};
+use crate::metering::{self, Metric};
+use crate::readiness::Readiness;
+use crate::slowlog::{self, SlowOperation};
+use crate::tenant_storage::{self, TenantStorage};
+use anyhow::{bail, Context, Result};
 use reqwest::Client;
 use serde_json::Value;
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
 use reqwest::multipart::{Form, Part};
 
 /// IPFS client for uploading files and folders to Pinata
@@ -11,6 +19,12 @@ pub struct IpfsClient {
     client: Client,
     api_key: String,
     api_secret: String,
+    /// Per-tenant Pinata accounts and gateways
+    tenants: Arc<TenantStorage>,
+    /// Pins deferred behind placeholder CIDs
+    provenance: Arc<CidProvenance>,
+    /// Pinata answered; pins wait for it, see [`crate::readiness`]
+    readiness: Arc<Readiness>,
 }
 
 impl IpfsClient {
@@ -25,12 +39,87 @@ impl IpfsClient {
             client: Client::new(),
             api_key,
             api_secret,
+            tenants: Arc::new(TenantStorage::load()?),
+            provenance: Arc::new(CidProvenance::load()?),
+            readiness: Arc::new(Readiness::default()),
         })
     }
 
-    /// Upload a single JSON object to IPFS
+    pub fn readiness(&self) -> Arc<Readiness> {
+        self.readiness.clone()
+    }
+
+    /// Check that Pinata answers. Rejected keys count as reachable: pins
+    /// then fail with Pinata's own error rather than waiting.
+    pub async fn probe(&self) -> Result<()> {
+        let response = self.client
+            .get("https://api.pinata.cloud/data/testAuthentication")
+            .header("pinata_api_key", &self.api_key)
+            .header("pinata_secret_api_key", &self.api_secret)
+            .timeout(std::time::Duration::from_secs(10))
+            .send()
+            .await
+            .context("Failed to reach Pinata")?;
+        if matches!(response.status().as_u16(), 401 | 403) {
+            tracing::error!(status = %response.status(), "Pinata rejected PINATA_API_KEY/PINATA_API_SECRET");
+            return Ok(());
+        }
+        response
+            .error_for_status()
+            .context("Pinata authentication test failed")?;
+        Ok(())
+    }
+
+    pub fn tenants(&self) -> &TenantStorage {
+        &self.tenants
+    }
+
+    pub fn provenance(&self) -> &CidProvenance {
+        &self.provenance
+    }
+
+    /// Pin `form` of `bytes` bytes with the current tenant's Pinata keys,
+    /// metering it against the tenant's quota
+    async fn pin(&self, form: Form, bytes: u64, detail: &str) -> Result<Value> {
+        self.readiness.check("IPFS (Pinata)")?;
+        let tenant = metering::admit_current(Metric::Bytes, bytes)?;
+        let (api_key, api_secret) = self
+            .tenants
+            .credentials(&tenant)
+            .unwrap_or((&self.api_key, &self.api_secret));
+
+        if faults::fire(Fault::PinataTimeout) {
+            tokio::time::sleep(faults::delay()).await;
+            bail!("Failed to send request to Pinata: operation timed out (injected fault)");
+        }
+        let request = self.client
+            .post("https://api.pinata.cloud/pinning/pinFileToIPFS")
+            .header("pinata_api_key", api_key)
+            .header("pinata_secret_api_key", api_secret)
+            .multipart(form)
+            .send();
+        let resp = match slowlog::observe(SlowOperation::IpfsUpload, detail, request).await {
+            Ok(resp) => {
+                self.readiness.record_up();
+                resp
+            }
+            Err(e) => {
+                let e = anyhow::Error::new(e).context("Failed to send request to Pinata");
+                self.readiness.record_down(&e);
+                return Err(e);
+            }
+        };
+
+        let response_json: Value = resp.json().await
+            .context("Failed to parse Pinata response")?;
+        metering::global().record_upload(&tenant, bytes);
+        Ok(response_json)
+    }
+
+    /// Upload a single JSON object to IPFS, in the configured payload format
     pub async fn upload_json(&self, data: &Value) -> Result<String> {
-        let json_bytes = serde_json::to_vec_pretty(data)
+        let stored = compact_payload::encode(data, PayloadFormat::from_env())?;
+        let json_bytes = serde_json::to_vec_pretty(&stored)
             .context("Failed to serialize JSON data")?;
 
         self.upload_bytes(json_bytes, "data.json").await
@@ -38,20 +127,11 @@ impl IpfsClient {
 
     /// Upload raw bytes to IPFS with a filename
     pub async fn upload_bytes(&self, data: Vec<u8>, filename: &str) -> Result<String> {
+        let bytes = data.len() as u64;
         let form = Form::new()
             .part("file", Part::bytes(data).file_name(filename.to_string()));
 
-        let resp = self.client
-            .post("https://api.pinata.cloud/pinning/pinFileToIPFS")
-            .header("pinata_api_key", &self.api_key)
-            .header("pinata_secret_api_key", &self.api_secret)
-            .multipart(form)
-            .send()
-            .await
-            .context("Failed to send request to Pinata")?;
-
-        let response_json: Value = resp.json().await
-            .context("Failed to parse Pinata response")?;
+        let response_json = self.pin(form, bytes, filename).await?;
 
         let ipfs_hash = response_json["IpfsHash"]
             .as_str()
@@ -60,6 +140,68 @@ impl IpfsClient {
         Ok(ipfs_hash.to_string())
     }
 
+    /// Fetch a JSON document through the public IPFS gateway, decoding
+    /// compact payloads
+    pub async fn fetch_json(&self, cid: &str) -> Result<Value> {
+        let cid = &self.provenance.resolve(cid).await?;
+        let value = self.client
+            .get(crate::error::ipfs_gateway_url(cid))
+            .timeout(std::time::Duration::from_secs(15))
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch {} from IPFS gateway", cid))?
+            .error_for_status()
+            .with_context(|| format!("IPFS gateway rejected {}", cid))?
+            .json()
+            .await
+            .with_context(|| format!("{} is not valid JSON", cid))?;
+
+        compact_payload::decode(value).with_context(|| format!("{} has an invalid payload", cid))
+    }
+
+    /// Fetch raw content through the public IPFS gateway
+    pub async fn fetch_bytes(&self, cid: &str) -> Result<Vec<u8>> {
+        let cid = &self.provenance.resolve(cid).await?;
+        let bytes = self.client
+            .get(crate::error::ipfs_gateway_url(cid))
+            .timeout(std::time::Duration::from_secs(300))
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch {} from IPFS gateway", cid))?
+            .error_for_status()
+            .with_context(|| format!("IPFS gateway rejected {}", cid))?
+            .bytes()
+            .await
+            .with_context(|| format!("Failed to read {} from IPFS gateway", cid))?;
+
+        Ok(bytes.to_vec())
+    }
+
+    /// CID of the most recently pinned file whose name contains `name`
+    pub async fn latest_pin(&self, name: &str) -> Result<Option<String>> {
+        let response_json: Value = self.client
+            .get("https://api.pinata.cloud/data/pinList")
+            .header("pinata_api_key", &self.api_key)
+            .header("pinata_secret_api_key", &self.api_secret)
+            .query(&[
+                ("status", "pinned"),
+                ("metadata[name]", name),
+                ("pageLimit", "1"),
+            ])
+            .send()
+            .await
+            .context("Failed to query Pinata pin list")?
+            .error_for_status()
+            .context("Pinata rejected pin list query")?
+            .json()
+            .await
+            .context("Failed to parse Pinata pin list")?;
+
+        Ok(response_json["rows"][0]["ipfs_pin_hash"]
+            .as_str()
+            .map(str::to_string))
+    }
+
     /// Write JSON data to a file within a batch folder
     pub fn write_json_to_folder(&self, folder_path: &str, filename: &str, data: &Value) -> Result<()> {
         // Create folder if it doesn't exist
@@ -67,7 +209,8 @@ impl IpfsClient {
             .with_context(|| format!("Failed to create directory: {}", folder_path))?;
 
         let file_path = Path::new(folder_path).join(filename);
-        let json_content = serde_json::to_string_pretty(data)
+        let stored = compact_payload::encode(data, PayloadFormat::from_env())?;
+        let json_content = serde_json::to_string_pretty(&stored)
             .context("Failed to serialize JSON data")?;
 
         fs::write(&file_path, json_content)
@@ -79,41 +222,15 @@ impl IpfsClient {
     /// Upload an entire folder (recursive) to IPFS and get ONE CID
     pub async fn upload_folder(&self, folder_path: &str) -> Result<String> {
         let mut form = Form::new();
+        let mut total_bytes = 0u64;
 
-        // Walk through all files in the folder
-        for entry in walkdir::WalkDir::new(folder_path) {
-            let entry = entry
-                .with_context(|| format!("Failed to read directory entry in: {}", folder_path))?;
-            let path = entry.path();
-
-            if path.is_file() {
-                let bytes = fs::read(path)
-                    .with_context(|| format!("Failed to read file: {}", path.display()))?;
-
-                let rel_path = path.strip_prefix(folder_path)
-                    .with_context(|| format!("Failed to get relative path for: {}", path.display()))?
-                    .to_string_lossy()
-                    .to_string();
-
-                // Pinata expects file names for directory uploads to maintain structure
-                form = form.part(
-                    "file",
-                    Part::bytes(bytes).file_name(rel_path)
-                );
-            }
+        for (rel_path, bytes) in folder_files(folder_path)? {
+            total_bytes += bytes.len() as u64;
+            // Pinata expects file names for directory uploads to maintain structure
+            form = form.part("file", Part::bytes(bytes).file_name(rel_path));
         }
 
-        let resp = self.client
-            .post("https://api.pinata.cloud/pinning/pinFileToIPFS")
-            .header("pinata_api_key", &self.api_key)
-            .header("pinata_secret_api_key", &self.api_secret)
-            .multipart(form)
-            .send()
-            .await
-            .context("Failed to send folder upload request to Pinata")?;
-
-        let response_json: Value = resp.json().await
-            .context("Failed to parse Pinata folder upload response")?;
+        let response_json = self.pin(form, total_bytes, folder_path).await?;
 
         let ipfs_hash = response_json["IpfsHash"]
             .as_str()
@@ -121,6 +238,52 @@ impl IpfsClient {
 
         Ok(ipfs_hash.to_string())
     }
+
+    /// Upload a folder whose CID is about to be written on chain. When
+    /// Pinata is slower than PIN_DEFERRAL_TIMEOUT_SECS, the pin is deferred
+    /// and a placeholder carrying the folder's content hash is returned
+    /// instead (see [`crate::cid_provenance`]).
+    pub async fn upload_folder_or_defer(&self, folder_path: &str) -> Result<String> {
+        let Some(timeout) = cid_provenance::deferral_timeout() else {
+            return self.upload_folder(folder_path).await;
+        };
+        if !timeout.is_zero() {
+            match tokio::time::timeout(timeout, self.upload_folder(folder_path)).await {
+                Ok(result) => return result,
+                Err(_) => tracing::warn!(
+                    folder = %folder_path,
+                    timeout_secs = timeout.as_secs(),
+                    "Pinata is slow, deferring pin"
+                ),
+            }
+        }
+        self.provenance
+            .defer(folder_path, &tenant_storage::current_tenant())
+            .await
+    }
+}
+
+/// Every file under `folder_path` (recursive) with its path relative to the
+/// folder, as sent in a folder upload
+pub fn folder_files(folder_path: &str) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut files = Vec::new();
+    for entry in walkdir::WalkDir::new(folder_path) {
+        let entry =
+            entry.with_context(|| format!("Failed to read directory entry in: {}", folder_path))?;
+        let path = entry.path();
+
+        if path.is_file() {
+            let bytes =
+                fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+            let rel_path = path
+                .strip_prefix(folder_path)
+                .with_context(|| format!("Failed to get relative path for: {}", path.display()))?
+                .to_string_lossy()
+                .to_string();
+            files.push((rel_path, bytes));
+        }
+    }
+    Ok(files)
 }
 
 
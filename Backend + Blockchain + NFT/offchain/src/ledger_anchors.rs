@@ -0,0 +1,341 @@
+//! Anchoring of the local ledger to the primary chain
+//!
+//! Villages without connectivity or a funded signer record purchases in the
+//! hash-chained local ledger (`local_blockchain.json`) instead of the
+//! contract. Every LEDGER_ANCHOR_INTERVAL_SECS the ledger anchor job builds
+//! a Merkle tree over the blocks added since the last anchor (see
+//! [`Block::leaf`]) and commits the root with `updateWarehouseState` under
+//! the ledger's own warehouse id, so a single transaction covers any number
+//! of local blocks. The anchored block hashes are pinned to IPFS as the
+//! state's metadata CID. The backend signer needs ROLE_WAREHOUSE.
+//!
+//! Anchors are stored in `data/ledger_anchors.json`. An inclusion proof
+//! links a local block to the transaction that committed its root.
+
+use crate::admin::require_admin;
+use crate::chain::hash_string;
+use crate::error::{format_hash, format_tx_hash, ApiError, ApiResult};
+use crate::hash_schemes::HashScheme;
+use crate::local_blockchain::Block;
+use crate::merkle::{merkle_proof, merkle_root, MERKLE_HASH_SCHEME, TREE_SCHEME};
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use alloy::primitives::FixedBytes;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+const ANCHORS_FILE: &str = "data/ledger_anchors.json";
+const DEFAULT_LEDGER_ID: &str = "local-ledger";
+const DEFAULT_INTERVAL_SECS: u64 = 3600;
+const DEFAULT_MAX_BLOCKS: usize = 1000;
+
+// ======================== STORAGE ========================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerAnchor {
+    pub id: u64,
+    /// Ledger position of the first anchored block
+    pub first_block: usize,
+    pub block_hashes: Vec<String>,
+    pub root: FixedBytes<32>,
+    /// Warehouse id the root was committed under
+    pub warehouse_id: FixedBytes<32>,
+    pub manifest_cid: String,
+    pub tx_hash: FixedBytes<32>,
+    pub block_number: Option<u64>,
+    pub anchored_at: String,
+    #[serde(default)]
+    pub hash_scheme: HashScheme,
+}
+
+impl LedgerAnchor {
+    /// Ledger position of the first block after this anchor
+    fn next_block(&self) -> usize {
+        self.first_block + self.block_hashes.len()
+    }
+}
+
+pub struct LedgerAnchorStore {
+    anchors: Mutex<Vec<LedgerAnchor>>,
+}
+
+impl LedgerAnchorStore {
+    pub fn load() -> Result<Self> {
+        let anchors = if std::path::Path::new(ANCHORS_FILE).exists() {
+            let content = std::fs::read_to_string(ANCHORS_FILE)
+                .with_context(|| format!("Failed to read {}", ANCHORS_FILE))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid ledger anchors {}", ANCHORS_FILE))?
+        } else {
+            Vec::new()
+        };
+        Ok(Self {
+            anchors: Mutex::new(anchors),
+        })
+    }
+
+    fn save(anchors: &[LedgerAnchor]) -> Result<()> {
+        std::fs::create_dir_all("data").context("Failed to create data directory")?;
+        std::fs::write(ANCHORS_FILE, serde_json::to_string_pretty(anchors)?)
+            .with_context(|| format!("Failed to write {}", ANCHORS_FILE))
+    }
+}
+
+/// Anchor covering ledger position `position`
+fn covering_anchor(anchors: &[LedgerAnchor], position: usize) -> Option<&LedgerAnchor> {
+    anchors
+        .iter()
+        .find(|a| a.first_block <= position && position < a.next_block())
+}
+
+/// Warehouse id the ledger's roots are committed under
+pub fn ledger_warehouse_id() -> FixedBytes<32> {
+    let ledger_id =
+        std::env::var("LEDGER_ANCHOR_ID").unwrap_or_else(|_| DEFAULT_LEDGER_ID.to_string());
+    hash_string(&ledger_id)
+}
+
+// ======================== ANCHOR JOB ========================
+
+/// Anchor the ledger blocks added since the last anchor; returns the new
+/// anchor, if any
+pub async fn run_once(state: &AppState) -> Result<Option<LedgerAnchor>> {
+    let max_blocks = std::env::var("LEDGER_ANCHOR_MAX_BLOCKS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_BLOCKS)
+        .max(1);
+
+    let mut anchors = state.ledger_anchors.anchors.lock().await;
+    let first_block = anchors.last().map(LedgerAnchor::next_block).unwrap_or(0);
+
+    let blocks: Vec<Block> = state
+        .local_ledger
+        .blocks_from(first_block)
+        .await
+        .into_iter()
+        .take(max_blocks)
+        .collect();
+    if blocks.is_empty() {
+        return Ok(None);
+    }
+
+    let leaves: Vec<FixedBytes<32>> = blocks.iter().map(Block::leaf).collect();
+    let root = merkle_root(&leaves);
+    let block_hashes: Vec<String> = blocks.iter().map(|b| b.block_hash.clone()).collect();
+
+    let manifest = serde_json::json!({
+        "type": "ledger_anchor",
+        "first_block": first_block,
+        "block_hashes": block_hashes,
+        "root": root,
+        "tree_scheme": TREE_SCHEME,
+    });
+    let manifest_cid = state
+        .ipfs_client
+        .upload_json(&manifest)
+        .await
+        .context("Failed to pin ledger anchor manifest")?;
+
+    let warehouse_id = ledger_warehouse_id();
+    let receipt = state
+        .chain()
+        .update_warehouse_state(warehouse_id, root, manifest_cid.clone())
+        .await?;
+
+    let anchor = LedgerAnchor {
+        id: anchors.last().map(|a| a.id + 1).unwrap_or(1),
+        first_block,
+        block_hashes,
+        root,
+        warehouse_id,
+        manifest_cid,
+        tx_hash: receipt.transaction_hash,
+        block_number: receipt.block_number,
+        anchored_at: chrono::Utc::now().to_rfc3339(),
+        hash_scheme: MERKLE_HASH_SCHEME,
+    };
+    anchors.push(anchor.clone());
+    LedgerAnchorStore::save(&anchors)?;
+
+    Ok(Some(anchor))
+}
+
+/// Start the periodic ledger anchor job
+pub fn spawn(state: AppState) {
+    let interval = std::env::var("LEDGER_ANCHOR_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+    if interval == 0 {
+        tracing::info!("LEDGER_ANCHOR_INTERVAL_SECS is 0, local ledger anchoring disabled");
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+        loop {
+            ticker.tick().await;
+            match run_once(&state).await {
+                Ok(Some(anchor)) => tracing::info!(
+                    id = anchor.id,
+                    blocks = anchor.block_hashes.len(),
+                    "Ledger anchor committed"
+                ),
+                Ok(None) => tracing::debug!("Ledger anchor job found no new blocks"),
+                Err(e) => tracing::error!(error = %format!("{:#}", e), "Ledger anchor job failed"),
+            }
+        }
+    });
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct LedgerAnchorSummary {
+    pub id: u64,
+    pub first_block: usize,
+    pub block_count: usize,
+    pub root: String,
+    pub warehouse_id: String,
+    pub manifest_cid: String,
+    pub tx_hash: String,
+    pub block_number: Option<u64>,
+    pub anchored_at: String,
+    pub hash_scheme: HashScheme,
+}
+
+impl From<&LedgerAnchor> for LedgerAnchorSummary {
+    fn from(anchor: &LedgerAnchor) -> Self {
+        Self {
+            id: anchor.id,
+            first_block: anchor.first_block,
+            block_count: anchor.block_hashes.len(),
+            root: format_hash(anchor.root),
+            warehouse_id: format_hash(anchor.warehouse_id),
+            manifest_cid: anchor.manifest_cid.clone(),
+            tx_hash: format_tx_hash(anchor.tx_hash),
+            block_number: anchor.block_number,
+            anchored_at: anchor.anchored_at.clone(),
+            hash_scheme: anchor.hash_scheme,
+        }
+    }
+}
+
+pub async fn list_ledger_anchors(
+    State(state): State<AppState>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<LedgerAnchorSummary>> {
+    let summaries: Vec<LedgerAnchorSummary> = state
+        .ledger_anchors
+        .anchors
+        .lock()
+        .await
+        .iter()
+        .map(LedgerAnchorSummary::from)
+        .collect();
+
+    Ok(Json(paginate("ledger_anchors", summaries, &params, |a| {
+        a.id
+    })?))
+}
+
+#[derive(Debug, Serialize)]
+pub struct LedgerInclusionProof {
+    pub block: Block,
+    pub leaf: FixedBytes<32>,
+    pub proof: Vec<FixedBytes<32>>,
+    pub anchor: LedgerAnchorSummary,
+}
+
+pub async fn ledger_inclusion_proof(
+    State(state): State<AppState>,
+    Path(block_hash): Path<String>,
+) -> ApiResult<LedgerInclusionProof> {
+    let (position, block) = state
+        .local_ledger
+        .find_block(&block_hash)
+        .await
+        .ok_or_else(|| ApiError::not_found("Block not found in the local ledger"))?;
+
+    let anchor = {
+        let anchors = state.ledger_anchors.anchors.lock().await;
+        covering_anchor(&anchors, position)
+            .cloned()
+            .ok_or_else(|| ApiError::not_found("Block not anchored yet"))?
+    };
+    if anchor.hash_scheme != MERKLE_HASH_SCHEME {
+        return Err(ApiError::internal(format!(
+            "Ledger anchor {} uses hash scheme {}, which this build cannot prove",
+            anchor.id,
+            anchor.hash_scheme.id()
+        )));
+    }
+
+    let leaves: Vec<FixedBytes<32>> = anchor
+        .block_hashes
+        .iter()
+        .map(|hash| crate::merkle::leaf(hash.as_bytes()))
+        .collect();
+    let proof = merkle_proof(&leaves, position - anchor.first_block);
+
+    Ok(Json(LedgerInclusionProof {
+        leaf: block.leaf(),
+        block,
+        proof,
+        anchor: LedgerAnchorSummary::from(&anchor),
+    }))
+}
+
+/// Run the ledger anchor job immediately (admin only)
+pub async fn trigger_ledger_anchor(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<Option<LedgerAnchorSummary>> {
+    require_admin(&state, &headers)?;
+
+    let anchor = run_once(&state)
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+
+    Ok(Json(anchor.as_ref().map(LedgerAnchorSummary::from)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn anchor(id: u64, first_block: usize, blocks: usize) -> LedgerAnchor {
+        LedgerAnchor {
+            id,
+            first_block,
+            block_hashes: (0..blocks)
+                .map(|i| format!("0x{:02x}", first_block + i))
+                .collect(),
+            root: FixedBytes::ZERO,
+            warehouse_id: ledger_warehouse_id(),
+            manifest_cid: String::new(),
+            tx_hash: FixedBytes::ZERO,
+            block_number: None,
+            anchored_at: String::new(),
+            hash_scheme: MERKLE_HASH_SCHEME,
+        }
+    }
+
+    #[test]
+    fn test_block_resolves_to_covering_anchor() {
+        let anchors = vec![anchor(1, 0, 3), anchor(2, 3, 2)];
+
+        assert_eq!(covering_anchor(&anchors, 0).map(|a| a.id), Some(1));
+        assert_eq!(covering_anchor(&anchors, 3).map(|a| a.id), Some(2));
+        assert_eq!(covering_anchor(&anchors, 4).map(|a| a.id), Some(2));
+        assert!(covering_anchor(&anchors, 5).is_none());
+    }
+}
@@ -1,9 +1,96 @@
+pub mod acceptance;
+pub mod admin;
+pub mod analytics;
+pub mod anchoring;
+pub mod api_keys;
+pub mod audit;
+pub mod auditor;
+pub mod auth;
+pub mod batch_ledger;
+pub mod business_calendar;
 pub mod chain;
+pub mod chain_roles;
+pub mod cid_provenance;
+pub mod cold_chain;
+pub mod commitments;
+pub mod compact_payload;
 pub mod config;
+pub mod confirmations;
+pub mod credentials;
+pub mod data_migrations;
+pub mod degradation;
+pub mod delegation;
+pub mod demo_handlers;
+pub mod did;
+pub mod did_resolver;
 pub mod error;
+pub mod experiments;
+pub mod export;
+pub mod farmer_import;
+pub mod farmer_profile;
+pub mod farmer_search;
 pub mod farmer_verification;
+pub mod faults;
+pub mod financing;
+pub mod fpo_registry;
+pub mod grades;
+pub mod hash_schemes;
+pub mod holds;
+pub mod image_metadata;
+pub mod indexer;
+#[cfg(test)]
+mod integration_contracts;
 pub mod ipfs;
+pub mod ledger_anchors;
+pub mod local_blockchain;
+pub mod logging;
+pub mod merkle;
+pub mod metering;
+pub mod networks;
+pub mod nft;
+pub mod nft_metadata;
+pub mod notifications;
+pub mod object_storage;
+pub mod otp;
+pub mod outbox;
+pub mod pagination;
+pub mod perceptual_hash;
+pub mod photo_evidence;
+pub mod processor_registry;
+pub mod public_stats;
+pub mod public_trace;
+pub mod read_models;
+pub mod readiness;
+pub mod reference_data;
+pub mod remote_signer;
+pub mod reports;
+pub mod revert;
+pub mod response_shaping;
+pub mod response_signing;
 pub mod routes;
+pub mod samples;
+pub mod schemes;
+pub mod seals;
+pub mod share;
+pub mod shelf_life;
+pub mod signers;
+pub mod sku_units;
+pub mod slowlog;
+pub mod sms;
+pub mod snapshots;
 pub mod state;
+pub mod stock_rotation;
 pub mod supply_chain_handlers;
+pub mod tenant_storage;
+pub mod timeline;
+pub mod timezones;
+pub mod transport;
+pub mod tx_queue;
+pub mod ussd;
+pub mod video_evidence;
+pub mod wallet;
+pub mod warehouse_receipts;
+pub mod warehouse_registry;
+pub mod warehouse_transfers;
+pub mod workflow_replay;
 pub mod workflows;
@@ -2,14 +2,15 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
-use chrono::{DateTime, Utc};
+use chrono::Utc;
 use sha2::{Digest, Sha256};
+use alloy::primitives::FixedBytes;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
     pub block_id: String,
     pub previous_block_hash: Option<String>,
-    pub timestamp: DateTime<Utc>,
+    pub timestamp: String,
     pub transaction_type: String,
     pub batch_id: String,
     pub farmer_did: String,
@@ -18,8 +19,18 @@ pub struct Block {
     pub version: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Block {
+    /// Merkle leaf of the block when its ledger is anchored on-chain
+    pub fn leaf(&self) -> FixedBytes<32> {
+        crate::merkle::leaf(self.block_hash.as_bytes())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LocalBlockchain {
+    /// See [`crate::data_migrations::LOCAL_LEDGER`]
+    #[serde(default)]
+    pub format_version: u32,
     pub blocks: Vec<Block>,
     pub batch_history: HashMap<String, Vec<String>>, // batch_id -> [block_hashes]
 }
@@ -33,10 +44,11 @@ pub struct BlockReceipt {
 }
 
 impl LocalBlockchain {
-    const BLOCKCHAIN_FILE: &'static str = "local_blockchain.json";
+    pub(crate) const BLOCKCHAIN_FILE: &'static str = "local_blockchain.json";
 
     pub fn new() -> Self {
         Self {
+            format_version: crate::data_migrations::LOCAL_LEDGER.current_version(),
             blocks: Vec::new(),
             batch_history: HashMap::new(),
         }
@@ -89,7 +101,7 @@ impl LocalBlockchain {
         let is_update = previous_block_hash.is_some();
 
         let block_id = Self::generate_block_id();
-        let timestamp = Utc::now();
+        let timestamp = Utc::now().to_rfc3339();
 
         // Create block data for hashing
         let block_content = serde_json::json!({
@@ -124,7 +136,7 @@ impl LocalBlockchain {
         // Update batch history
         self.batch_history
             .entry(batch_id.clone())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(block_hash.clone());
 
         // Save to file
@@ -161,24 +173,16 @@ impl LocalBlockchain {
         }
     }
 
-    pub fn get_latest_block_for_batch(&self, batch_id: &str) -> Option<&Block> {
-        self.get_batch_history(batch_id).last().copied()
-    }
-
     pub fn verify_farmer_exists(&self, farmer_did: &str) -> bool {
         self.blocks.iter().any(|block| block.farmer_did == farmer_did)
     }
 
-    pub fn get_block_by_hash(&self, block_hash: &str) -> Option<&Block> {
-        self.blocks.iter().find(|b| b.block_hash == block_hash)
-    }
-
     pub fn get_blockchain_stats(&self) -> serde_json::Value {
         serde_json::json!({
             "total_blocks": self.blocks.len(),
             "unique_batches": self.batch_history.len(),
             "unique_farmers": self.blocks.iter().map(|b| &b.farmer_did).collect::<std::collections::HashSet<_>>().len(),
-            "last_block_time": self.blocks.last().map(|b| b.timestamp),
+            "last_block_time": self.blocks.last().map(|b| &b.timestamp),
             "blockchain_file": Self::BLOCKCHAIN_FILE
         })
     }
@@ -226,4 +230,20 @@ impl LocalBlockchainClient {
         let blockchain = self.blockchain.read().await;
         Ok(blockchain.get_blockchain_stats())
     }
+
+    /// Blocks from position `start` on, in ledger order
+    pub async fn blocks_from(&self, start: usize) -> Vec<Block> {
+        let blockchain = self.blockchain.read().await;
+        blockchain.blocks.iter().skip(start).cloned().collect()
+    }
+
+    /// Block with `block_hash` and its position in the ledger
+    pub async fn find_block(&self, block_hash: &str) -> Option<(usize, Block)> {
+        let blockchain = self.blockchain.read().await;
+        let position = blockchain
+            .blocks
+            .iter()
+            .position(|b| b.block_hash == block_hash)?;
+        Some((position, blockchain.blocks[position].clone()))
+    }
 }
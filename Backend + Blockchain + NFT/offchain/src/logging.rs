@@ -0,0 +1,159 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing_subscriber::{
+    layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry,
+};
+
+/// Filter used when RUST_LOG is not set
+pub const DEFAULT_FILTER: &str = "offchain=debug,tower_http=debug,axum::rejection=trace";
+
+type FilterHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Handle for changing the active log filter while the service is running
+#[derive(Clone)]
+pub struct LogControl {
+    handle: FilterHandle,
+    base_filter: String,
+    default_revert: Option<Duration>,
+    active_filter: Arc<Mutex<String>>,
+    revert_task: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl LogControl {
+    /// Install the global tracing subscriber with a reloadable EnvFilter
+    pub fn init() -> Self {
+        let base_filter = std::env::var("RUST_LOG").unwrap_or_else(|_| DEFAULT_FILTER.to_string());
+        let env_filter =
+            EnvFilter::try_new(&base_filter).unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+
+        // 0 disables automatic reversion
+        let default_revert = std::env::var("LOG_LEVEL_REVERT_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(900);
+        let default_revert = (default_revert > 0).then(|| Duration::from_secs(default_revert));
+
+        let (filter_layer, handle) = reload::Layer::new(env_filter);
+
+        tracing_subscriber::registry()
+            .with(filter_layer)
+            .with(tracing_subscriber::fmt::layer())
+            .init();
+
+        Self {
+            handle,
+            active_filter: Arc::new(Mutex::new(base_filter.clone())),
+            base_filter,
+            default_revert,
+            revert_task: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Filter the service was started with
+    pub fn base_filter(&self) -> &str {
+        &self.base_filter
+    }
+
+    /// How long a runtime override stays active when the caller does not say
+    pub fn default_revert(&self) -> Option<Duration> {
+        self.default_revert
+    }
+
+    /// Filter currently applied
+    pub async fn active_filter(&self) -> String {
+        self.active_filter.lock().await.clone()
+    }
+
+    /// Apply a new filter, optionally reverting to the base filter after `revert_after`
+    pub async fn apply(&self, directives: &str, revert_after: Option<Duration>) -> Result<()> {
+        let filter = EnvFilter::try_new(directives)
+            .with_context(|| format!("Invalid log filter '{}'", directives))?;
+        self.handle
+            .reload(filter)
+            .context("Failed to reload log filter")?;
+        *self.active_filter.lock().await = directives.to_string();
+
+        let mut revert_task = self.revert_task.lock().await;
+        if let Some(task) = revert_task.take() {
+            task.abort();
+        }
+
+        if let Some(delay) = revert_after {
+            let control = self.clone();
+            *revert_task = Some(tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                if let Err(e) = control.reset().await {
+                    tracing::error!(error = %e, "Failed to revert log filter");
+                } else {
+                    tracing::info!(filter = %control.base_filter, "Log filter reverted");
+                }
+            }));
+        }
+
+        tracing::info!(filter = %directives, revert_after_secs = ?revert_after.map(|d| d.as_secs()), "Log filter updated");
+        Ok(())
+    }
+
+    /// Restore the filter the service was started with
+    pub async fn reset(&self) -> Result<()> {
+        let filter = EnvFilter::try_new(&self.base_filter)
+            .unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+        self.handle
+            .reload(filter)
+            .context("Failed to reload log filter")?;
+        *self.active_filter.lock().await = self.base_filter.clone();
+        Ok(())
+    }
+}
+
+/// Build filter directives from an optional base and per-target levels.
+///
+/// Per-target entries are appended after the base so they take precedence
+/// over any directive for the same target.
+pub fn build_directives(base: Option<&str>, targets: &BTreeMap<String, String>) -> String {
+    let mut parts: Vec<String> = base
+        .map(|b| {
+            b.split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    for (target, level) in targets {
+        parts.retain(|p| p.split('=').next() != Some(target.as_str()));
+        parts.push(format!("{}={}", target, level));
+    }
+
+    parts.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_levels_override_base() {
+        let mut targets = BTreeMap::new();
+        targets.insert("offchain::chain".to_string(), "trace".to_string());
+        targets.insert("tower_http".to_string(), "warn".to_string());
+
+        let directives = build_directives(Some("offchain=debug, tower_http=debug"), &targets);
+        assert_eq!(
+            directives,
+            "offchain=debug,offchain::chain=trace,tower_http=warn"
+        );
+    }
+
+    #[test]
+    fn test_empty_base() {
+        let mut targets = BTreeMap::new();
+        targets.insert("offchain".to_string(), "info".to_string());
+        assert_eq!(build_directives(None, &targets), "offchain=info");
+    }
+}
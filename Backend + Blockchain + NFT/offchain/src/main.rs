@@ -1,19 +1,102 @@
 use axum::{routing::get, Router};
 use hyper::server::conn::http1;
 use hyper_util::rt::TokioIo;
-use std::time::Duration;
 use tower::Service;
 use tower_http::cors::{Any, CorsLayer};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod acceptance;
+mod admin;
+mod analytics;
+mod anchoring;
+mod api_keys;
+mod audit;
+mod auditor;
+mod auth;
+mod batch_ledger;
+mod business_calendar;
 mod chain;
+mod chain_roles;
+mod cid_provenance;
+mod cold_chain;
+mod commitments;
+mod compact_payload;
 mod config;
+mod confirmations;
+mod credentials;
+mod data_migrations;
+mod degradation;
+mod delegation;
+mod demo_handlers;
+mod did;
+mod did_resolver;
 mod error;
+mod experiments;
+mod export;
+mod farmer_import;
+mod farmer_profile;
+mod farmer_search;
 mod farmer_verification;
+mod faults;
+mod financing;
+mod fpo_registry;
+mod grades;
+mod hash_schemes;
+mod holds;
+mod image_metadata;
+mod indexer;
 mod ipfs;
+mod ledger_anchors;
+mod local_blockchain;
+mod logging;
+mod merkle;
+mod metering;
+mod networks;
+mod nft;
+mod nft_metadata;
+mod notifications;
+mod object_storage;
+mod otp;
+mod outbox;
+mod pagination;
+mod perceptual_hash;
+mod photo_evidence;
+mod processor_registry;
+mod public_stats;
+mod public_trace;
+mod read_models;
+mod readiness;
+mod reference_data;
+mod remote_signer;
+mod reports;
+mod revert;
+mod response_shaping;
+mod response_signing;
 mod routes;
+mod samples;
+mod schemes;
+mod seals;
+mod share;
+mod shelf_life;
+mod signers;
+mod sku_units;
+mod slowlog;
+mod sms;
+mod snapshots;
 mod state;
+mod stock_rotation;
 mod supply_chain_handlers;
+mod tenant_storage;
+mod timeline;
+mod timezones;
+mod transport;
+mod tx_queue;
+mod ussd;
+mod video_evidence;
+mod wallet;
+mod warehouse_receipts;
+mod warehouse_registry;
+mod warehouse_transfers;
+mod workflow_replay;
 mod workflows;
 
 use config::Config;
@@ -21,17 +104,22 @@ use state::AppState;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing/logging
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "offchain=debug,tower_http=debug,axum::rejection=trace".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    // Load environment variables first so RUST_LOG and LOG_LEVEL_REVERT_SECS
+    // in .env apply to the logger
+    networks::load_dotenv();
+
+    // Initialize tracing/logging (filter can be adjusted at runtime via the admin API)
+    let log_control = logging::LogControl::init();
 
-    // Load environment variables
-    dotenvy::dotenv().ok();
+    // `offchain restore [CID]` rebuilds data/ from an IPFS snapshot instead of serving
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("restore") {
+        return snapshots::restore_command(args.get(2).cloned(), log_control).await;
+    }
+    // `offchain replay-workflow <trace.json>` re-executes a recorded workflow run
+    if args.get(1).map(String::as_str) == Some("replay-workflow") {
+        return workflow_replay::replay_command(args.get(2).cloned()).await;
+    }
 
     // Load configuration
     let config = Config::from_env()?;
@@ -42,11 +130,47 @@ async fn main() -> anyhow::Result<()> {
         config.address()
     );
 
+    // Upgrade stores written by older versions before anything reads them
+    data_migrations::run()?;
+
     // Initialize application state (blockchain + IPFS clients)
     tracing::info!("Initializing application state...");
-    let app_state = AppState::from_env().await?;
+    let app_state = AppState::from_env(log_control).await?;
     tracing::info!("Application state initialized successfully");
 
+    // Keep retrying a chain RPC or Pinata that was unreachable at startup
+    readiness::spawn(app_state.clone());
+
+    // Periodically anchor contract events to the public L1 (if configured)
+    anchoring::spawn(app_state.clone());
+
+    // Periodically commit a Merkle root of new local ledger blocks on-chain
+    ledger_anchors::spawn(app_state.clone());
+
+    // Pin batch folders whose CIDs were deferred behind placeholders
+    cid_provenance::spawn(app_state.clone());
+
+    // Periodically pin an encrypted snapshot of data/ to IPFS (if a key is set)
+    snapshots::spawn(app_state.clone());
+
+    // Publish a Merkle digest of each finished day's audit log
+    audit::spawn(app_state.clone());
+
+    // Follow contract events into the local event index
+    indexer::spawn(app_state.clone());
+
+    // Generate and deliver scheduled reports for finished periods
+    reports::spawn(app_state.clone());
+
+    // Retry chain writes saved to the outbox after failing
+    outbox::spawn(app_state.clone());
+
+    // Watch the signer balance and alert before writes run out of gas
+    wallet::spawn(app_state.clone());
+
+    // Remind custodians before counter-samples may be disposed of
+    samples::spawn(app_state.clone());
+
     // Configure CORS
     let cors = if config.environment.is_production() {
         // In production, restrict CORS to specific origins
@@ -63,8 +187,47 @@ async fn main() -> anyhow::Result<()> {
     // Build the application router
     let app = Router::new()
         .route("/", get(root))
-        .route("/health", get(health_check))
-        .merge(routes::configure_routes(app_state))
+        .route(
+            "/health",
+            get(degradation::health).with_state(app_state.clone()),
+        )
+        .merge(routes::configure_routes(app_state.clone()))
+        // Innermost so fallbacks taken by handlers reach X-Degraded
+        .layer(axum::middleware::from_fn(degradation::report_degradation))
+        // Dev-only failures asked for by X-Inject-Fault, scoped to the handler
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            faults::inject_faults,
+        ))
+        // Inside authentication so the caller's tenant zone is known
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            timezones::localize_timestamps,
+        ))
+        // Uploads of a tenant's requests go to its own Pinata account
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            tenant_storage::scope_tenant,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            audit::record_mutations,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            api_keys::validate_key,
+        ))
+        // Resolve bearer tokens and API keys before the audit log records the actor
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            auth::authenticate,
+        ))
+        .layer(axum::middleware::from_fn(response_shaping::sparse_fieldsets))
+        // Outside field selection so the signature covers the body as sent
+        .layer(axum::middleware::from_fn_with_state(
+            app_state,
+            response_signing::sign_public_responses,
+        ))
         .layer(cors)
         .layer(tower_http::trace::TraceLayer::new_for_http());
 
@@ -79,30 +242,220 @@ async fn main() -> anyhow::Result<()> {
     tracing::info!("");
     tracing::info!("📋 API Endpoints:");
     tracing::info!("");
+    tracing::info!("🔐 AUTHENTICATION (JWT with role claim):");
+    tracing::info!("  - POST /api/auth/login            - Sign in, receive a bearer token");
+    tracing::info!("  - GET  /api/auth/me               - Claims of the current token");
+    tracing::info!("  - Integrations send X-API-Key instead (see /api/admin/api-keys)");
+    tracing::info!("");
     tracing::info!("🔄 WORKFLOW ORCHESTRATION:");
-    tracing::info!("  - POST /api/workflow/execute      - Execute complete supply chain workflow");
+    tracing::info!("  - POST /api/workflow/execute      - Start complete supply chain workflow (202 + workflow_id)");
+    tracing::info!("  - GET  /api/workflow/status/:id   - Workflow job progress, tx hashes and errors");
+    tracing::info!("  - GET  /api/workflow/stream/:id   - Server-sent events as workflow stages progress");
+    tracing::info!("  - POST /api/workflow/resume/:id   - Resume a failed workflow from its last completed stage");
     tracing::info!("  - POST /api/workflow/verify-sku   - Verify SKU traceability");
     tracing::info!("  - POST /api/workflow/verify-farmer - Verify farmer registration");
     tracing::info!("");
     tracing::info!("📱 MOBILE VERIFICATION:");
     tracing::info!("  - POST /api/verification/mobile   - Verify mobile number and get farmer DID");
     tracing::info!("  - POST /api/verification/farmer-by-did - Get farmer details by DID");
+    tracing::info!("  - POST /api/verification/otp/send - Text a one-time code to a mobile number");
+    tracing::info!("  - POST /api/verification/otp/verify - Check the code, get a proof token for registration");
+    tracing::info!("");
+    tracing::info!("📟 FEATURE PHONE CHANNELS:");
+    tracing::info!("  - POST /api/sms/inbound           - Inbound SMS webhook (STATUS/PAYMENT)");
+    tracing::info!("  - POST /api/ussd/session          - USSD gateway menu");
+    tracing::info!("  - POST /api/ivr/step              - IVR gateway menu step");
+    tracing::info!("");
+    tracing::info!("🌐 PUBLIC (consumer facing):");
+    tracing::info!("  - GET  /api/public/trace/:sku_id  - Branded consumer trace (?brand=&visitor=)");
+    tracing::info!("  - POST /api/public/experiments/convert - Record trace page conversion");
+    tracing::info!("  - GET  /api/public/stats          - Program transparency statistics");
+    tracing::info!("  - GET  /api/reference-data        - Enum codes with display labels (?lang=hi)");
+    tracing::info!("  - GET  /api/grades                - Quality-grade taxonomies per crop (?crop=)");
+    tracing::info!("  - GET  /api/shelf-life/rules      - Shelf-life rules per crop, process and package type");
+    tracing::info!("  - POST /api/public/proofs/district - Prove SKU comes from approved districts");
+    tracing::info!("  - POST /api/public/proofs/district/verify - Verify a district proof");
+    tracing::info!("  - GET  /.well-known/jwks.json     - Key signing public verification responses");
+    tracing::info!("  - GET  /api/anchors               - L1 anchors of contract events");
+    tracing::info!("  - GET  /api/anchors/proof/:tx_hash - Inclusion proof linking an event to its L1 anchor");
+    tracing::info!("  - GET  /api/cids/provenance       - Placeholder CIDs of deferred pins and their pinned CIDs");
+    tracing::info!("  - GET  /api/cids/provenance/:placeholder - Provenance of one placeholder CID");
+    tracing::info!("  - POST /api/ledger/fpo-purchase   - Record an FPO purchase in the local ledger");
+    tracing::info!("  - POST /api/ledger/verify-farmer  - Check a farmer has local ledger blocks");
+    tracing::info!("  - POST /api/ledger/batch-history  - Local ledger blocks of a batch");
+    tracing::info!("  - GET  /api/ledger/stats          - Local ledger statistics");
+    tracing::info!("  - GET  /api/ledger/anchors        - On-chain anchors of the local ledger");
+    tracing::info!("  - GET  /api/ledger/proof/:block_hash - Inclusion proof linking a local block to its anchor");
+    tracing::info!("  - GET  /api/hashes/schemes        - Supported hash schemes");
+    tracing::info!("  - GET  /api/audit/digests         - Daily Merkle digests of API mutations");
+    tracing::info!("  - GET  /api/audit/proof/:day/:seq - Inclusion proof of an audit entry");
+    tracing::info!("  - POST /api/hashes/verify         - Recompute a hash under its recorded scheme");
+    tracing::info!("  - GET  /api/calendars/:state_code - Business days, holidays and cutoff of a state (?from=&days=)");
+    tracing::info!("  - GET  /api/events                - Indexed contract events (?from=&to=&event=)");
+    tracing::info!("  - GET  /api/events/batch/:batch_id - Events concerning a batch and its SKUs");
+    tracing::info!("  - GET  /api/events/farmer/:farmer_did - Registrations and purchases of a farmer");
+    tracing::info!("  - GET  /api/events/status         - Event indexer progress");
+    tracing::info!("  - GET  /api/batches/:id/summary   - Batch stage, custody, outputs and SKUs from the index");
+    tracing::info!("  - GET  /api/skus/:id/summary      - SKU packaging and fraud reports from the index");
+    tracing::info!("  - GET  /api/farmer/:did/summary   - Farmer registration and purchases from the index");
+    tracing::info!("  - GET  /api/trace/batch/:batch_id - Chronological batch timeline with metadata");
+    tracing::info!("  - GET  /api/shared/documents      - Documents of a shared batch (?token=)");
+    tracing::info!("  - GET  /api/shared/events         - Events of a shared batch (?token=)");
+    tracing::info!("");
+    tracing::info!("🔍 AUDITOR CONSOLE (read-only auditor token):");
+    tracing::info!("  - POST /api/auditor/verify        - Verify invitation email, receive token");
+    tracing::info!("  - GET  /api/auditor/me            - Auditor account");
+    tracing::info!("  - GET  /api/auditor/queries       - Saved event queries");
+    tracing::info!("  - POST /api/auditor/queries       - Save an event query");
+    tracing::info!("  - DELETE /api/auditor/queries/:id - Delete a saved query");
+    tracing::info!("  - GET  /api/auditor/queries/:id/run - Run a saved query (paginated)");
+    tracing::info!("  - POST /api/auditor/queries/:id/export - Export all matching events");
+    tracing::info!("  - GET  /api/auditor/exports       - Export history");
     tracing::info!("");
     tracing::info!("🔗 INDIVIDUAL SUPPLY CHAIN STAGES:");
     tracing::info!("  - POST /api/farmer/register       - Register a new farmer");
+    tracing::info!("  - POST /api/farmer/register/bulk  - Register farmers from CSV or JSON rows");
     tracing::info!("  - POST /api/farmer/verify         - Verify farmer registration");
-    tracing::info!("  - POST /api/fpo/purchase          - Record FPO purchase");
-    tracing::info!("  - POST /api/warehouse/update      - Update warehouse state");
+    tracing::info!("  - POST /api/farmer/did/derive     - Farmer DID of a mobile number");
+    tracing::info!("  - GET  /api/did/:did              - DID document of a did:ethr or did:key farmer");
+    tracing::info!("  - POST /api/credentials/issue     - Verifiable Credential of a farmer's verification");
+    tracing::info!("  - POST /api/credentials/verify    - Check a farmer credential JWT or CID");
+    tracing::info!("  - GET  /api/farmers               - Search farmers (?state_code=&district_code=&crop=&verified=&min_acres=&max_acres=)");
+    tracing::info!("  - PATCH /api/farmer/:farmer_did   - Update a farmer's profile");
+    tracing::info!("  - GET  /api/farmer/:farmer_did/profile/history - Previous values of a farmer's profile");
+    tracing::info!("  - POST /api/fpo/purchase          - Record FPO purchase (fpo_id of a registered FPO)");
+    tracing::info!("  - GET  /api/fpos                  - Registered FPOs");
+    tracing::info!("  - POST /api/ownership/transfer    - Record a later custody change (warehouse, processor, retail)");
+    tracing::info!("  - POST /api/samples               - Record where a counter-sample is kept");
+    tracing::info!("  - GET  /api/samples/batch/:id     - Counter-samples of a batch");
+    tracing::info!("  - POST /api/samples/:id/dispose   - Dispose of a sample after retention");
+    tracing::info!("  - POST /api/admin/samples/:id/hold - Hold a sample for a quality dispute");
+    tracing::info!("  - POST /api/warehouse/update      - Update warehouse state (registered warehouse_id)");
     tracing::info!("  - POST /api/warehouse/batch-update - Batch update warehouses");
-    tracing::info!("  - POST /api/logistics/record      - Record logistics milestone");
-    tracing::info!("  - POST /api/processing/batch      - Process a batch");
+    tracing::info!("  - GET  /api/warehouse/:warehouse_id - On-chain warehouse state + metadata");
+    tracing::info!("  - GET  /api/warehouses            - Registered warehouses (location, capacity, licences)");
+    tracing::info!("  - GET  /api/warehouses/:id/dispatch-order - FIFO/FEFO dispatch order, aging stock, bypass alerts");
+    tracing::info!("  - POST /api/warehouse/receipts    - Issue electronic warehouse receipt (eNWR)");
+    tracing::info!("  - GET  /api/warehouse/receipts    - List receipts (?batch_id, ?holder, ?status)");
+    tracing::info!("  - GET  /api/warehouse/receipts/:number - Receipt with endorsements and anchors");
+    tracing::info!("  - POST /api/warehouse/receipts/:number/transfer - Endorse receipt to a new holder");
+    tracing::info!("  - POST /api/warehouse/receipts/:number/pledge - Mark lien for a lender");
+    tracing::info!("  - POST /api/warehouse/receipts/:number/release - Release or invoke the lien");
+    tracing::info!("  - POST /api/warehouse/receipts/:number/close - Close on delivery of goods");
+    tracing::info!("  - POST /api/warehouse/transfer    - Move stock between warehouses with its receipts");
+    tracing::info!("  - GET  /api/warehouse/transfers   - List transfers (?batch_id, ?warehouse_id)");
+    tracing::info!("  - GET  /api/logistics/transporters - Registered transporters");
+    tracing::info!("  - GET  /api/logistics/vehicles    - Registered vehicles");
+    tracing::info!("  - GET  /api/logistics/drivers     - Registered drivers");
+    tracing::info!("  - POST /api/logistics/assign      - Assign a vehicle and driver to a shipment");
+    tracing::info!("  - GET  /api/logistics/assignments/:id - Carrier and handovers of a shipment");
+    tracing::info!("  - POST /api/logistics/record      - Record logistics milestone (assigned shipments)");
+    tracing::info!("  - POST /api/logistics/batch-record - Record many milestones in one tx");
+    tracing::info!("  - POST /api/logistics/seals       - Issue seals to a shipment at dispatch");
+    tracing::info!("  - GET  /api/logistics/seals/:id   - Seals and checkpoint checks of a shipment");
+    tracing::info!("  - GET  /api/admin/seals/exceptions - Shipments with missing or mismatched seals");
+    tracing::info!("  - POST /api/logistics/readings    - In-transit temperature sensor readings");
+    tracing::info!("  - GET  /api/logistics/cold-chain/:id - Temperature exposure, gaps and spoilage risk");
+    tracing::info!("  - POST /api/processing/acceptance - Accept or reject an incoming lot");
+    tracing::info!("  - GET  /api/processing/acceptance/:id - Latest acceptance decision of a batch");
+    tracing::info!("  - POST /api/processing/batch      - Process a batch (accepted lots, registered capable processor)");
+    tracing::info!("  - GET  /api/processors            - Registered processors (licence, capabilities, capacity)");
+    tracing::info!("  - GET  /api/quality/stats         - Lot acceptance and grade drift per FPO/farmer");
     tracing::info!("  - POST /api/packaging/sku         - Create a new SKU");
     tracing::info!("  - POST /api/packaging/verify      - Verify SKU origin");
+    tracing::info!("  - POST /api/packaging/verify/bulk - Verify many SKUs in one request");
+    tracing::info!("  - GET  /api/packaging/unit-proof  - Merkle proof of a retail unit (?sku_id=&unit_id=)");
+    tracing::info!("  - POST /api/packaging/verify-unit - Verify a retail unit against the on-chain root");
+    tracing::info!("  - GET  /api/networks              - Configured chain networks (select with X-Chain-Network)");
+    tracing::info!("  - GET  /api/readiness             - Whether each chain network and IPFS is up yet");
+    tracing::info!("  - GET  /health                    - \"ok\" or \"degraded\" with the dependencies down (see X-Degraded)");
+    tracing::info!("  - POST /api/nft/mint              - Mint the ERC-721 token of a packaged SKU");
     tracing::info!("  - POST /api/fraud/report          - Report fraud");
+    tracing::info!("  - POST /api/evidence/photos       - Upload an attested photo (procurement: FPO, acceptance: processor, fraud: anyone)");
+    tracing::info!("  - GET  /api/evidence/photos       - Search photos by capture time, position and camera");
+    tracing::info!("  - GET  /api/evidence/photos/:cid  - Photo attestation record");
+    tracing::info!("  - GET  /api/admin/evidence/similar - Photos resembling photos of other batches or fraud cases");
+    tracing::info!("  - POST /api/evidence/videos/uploads - Start a chunked evidence video upload");
+    tracing::info!("  - PUT  /api/evidence/videos/uploads/:id/chunks/:n - Upload one video chunk");
+    tracing::info!("  - GET  /api/evidence/videos/uploads/:id - Missing chunks of a video upload");
+    tracing::info!("  - POST /api/evidence/videos/uploads/:id/complete - Assemble and pin the video");
+    tracing::info!("  - GET  /api/evidence/videos/:cid  - Evidence video record");
+    tracing::info!("  - GET  /api/evidence/objects/:cid/url - Signed download URL of stored evidence");
     tracing::info!("  - POST /api/ai/commit             - Commit AI score");
     tracing::info!("  - POST /api/ai/reveal             - Reveal AI score");
+    tracing::info!("  - GET  /api/ai/score/:batch_id    - AI score commit/reveal status");
+    tracing::info!("  - POST /api/lab/results           - Record lab results and run payment hold rules");
+    tracing::info!("  - GET  /api/settlements/:batch_id - Settlement status and hold reasons of a batch");
+    tracing::info!("  - GET  /api/farmer/:farmer_did/settlements - Settlement status of a farmer's batches");
+    tracing::info!("  - POST /api/compliance/financing  - Record a lender pledge or advance on a batch");
+    tracing::info!("  - POST /api/compliance/financing/:id/release - Release a financing flag");
+    tracing::info!("  - GET  /api/compliance/double-financing - Double-pledged and double-paid batches");
+    tracing::info!("  - POST /api/batches/:batch_id/lien - Mark a lender lien (blocks processing/packaging)");
+    tracing::info!("  - GET  /api/batches/:batch_id/lien - Lien status and history of a batch");
+    tracing::info!("  - POST /api/batches/:batch_id/lien/release - Release the caller's lien");
     tracing::info!("  - POST /api/ipfs/upload           - Upload data to IPFS");
     tracing::info!("");
+    tracing::info!("🛠️  ADMIN (requires ADMIN_API_TOKEN or an admin user):");
+    tracing::info!("  - GET  /api/admin/users           - User accounts");
+    tracing::info!("  - POST /api/admin/users           - Create a user with a role");
+    tracing::info!("  - POST /api/admin/users/:id/disable - Disable a user account");
+    tracing::info!("  - GET  /api/admin/api-keys        - Integration API keys");
+    tracing::info!("  - POST /api/admin/fpos            - Register an FPO (PUT/DELETE /api/admin/fpos/:id)");
+    tracing::info!("  - POST /api/admin/warehouses      - Register a warehouse (PUT/DELETE /api/admin/warehouses/:id)");
+    tracing::info!("  - POST /api/admin/processors      - Register a processor (PUT/DELETE /api/admin/processors/:id)");
+    tracing::info!("  - PUT  /api/admin/transporters/:id - Register a transporter (also /vehicles/:reg, /drivers/:id)");
+    tracing::info!("  - POST /api/admin/api-keys        - Issue an API key with role scopes");
+    tracing::info!("  - POST /api/admin/api-keys/:id/rotate - Rotate a key (old key valid for a grace period)");
+    tracing::info!("  - POST /api/admin/api-keys/:id/revoke - Revoke an API key");
+    tracing::info!("  - GET  /api/admin/log-level       - Show active log filter");
+    tracing::info!("  - PUT  /api/admin/log-level       - Adjust log filter at runtime");
+    tracing::info!("  - GET  /api/admin/slowlog         - Slow IPFS uploads and receipt waits");
+    tracing::info!("  - GET  /api/admin/usage           - Bytes pinned and transactions per tenant against quota (?month=)");
+    tracing::info!("  - GET  /api/usage                 - The calling API key's usage and quota");
+    tracing::info!("  - GET  /api/admin/tx-queue        - Queued signer transactions (nonces, retries, replacements)");
+    tracing::info!("  - GET  /api/chain/tx/:hash        - Confirmation depth of a transaction");
+    tracing::info!("  - GET  /api/admin/wallet          - Signer balances and low-funds state");
+    tracing::info!("  - GET  /api/admin/signers         - Signer addresses per on-chain role");
+    tracing::info!("  - POST /api/admin/signers/reload  - Rotate signer keys from SIGNER_KEYS_PATH");
+    tracing::info!("  - POST /api/admin/chain/reload    - Apply RPC, contract and signer changes from .env");
+    tracing::info!("  - GET  /api/admin/outbox          - Failed chain writes awaiting retry (?status=)");
+    tracing::info!("  - POST /api/admin/outbox/:id/requeue - Retry an outbox entry now");
+    tracing::info!("  - POST /api/admin/roles/grant     - Grant on-chain roles to an account");
+    tracing::info!("  - POST /api/admin/roles/revoke    - Revoke on-chain roles from an account");
+    tracing::info!("  - GET  /api/admin/delegations     - Field agent delegations");
+    tracing::info!("  - POST /api/admin/delegations     - Delegate scopes to a field agent until an expiry");
+    tracing::info!("  - POST /api/admin/delegations/:id/revoke - Revoke a delegation");
+    tracing::info!("  - POST /api/share                 - Issue an expiring share link for one batch");
+    tracing::info!("  - GET  /api/admin/shares          - Issued share links and their usage");
+    tracing::info!("  - POST /api/admin/shares/:id/revoke - Revoke a share link");
+    tracing::info!("  - GET  /api/admin/auditors        - Auditor accounts");
+    tracing::info!("  - POST /api/admin/auditors        - Invite an auditor by email");
+    tracing::info!("  - POST /api/admin/auditors/:id/disable - Disable an auditor account");
+    tracing::info!("  - GET  /api/admin/roles/check     - On-chain roles of an account (?account=&role=)");
+    tracing::info!("  - GET  /api/admin/chain-roles     - Principals bound to on-chain roles");
+    tracing::info!("  - PUT  /api/admin/chain-roles/bindings - Bind a user or API key to an address");
+    tracing::info!("  - POST /api/notifications/send    - Send SMS/WhatsApp notification");
+    tracing::info!("  - GET  /api/analytics/experiments - Trace page A/B exposures and conversions");
+    tracing::info!("  - POST /api/commitments/batch/:batch_id - Commit to batch attributes");
+    tracing::info!("  - POST /api/admin/anchors/run     - Anchor new events to L1 now");
+    tracing::info!("  - POST /api/admin/ledger/anchor/run - Anchor new local ledger blocks now");
+    tracing::info!("  - GET  /api/admin/snapshots       - Encrypted IPFS snapshots of data/");
+    tracing::info!("  - POST /api/admin/snapshots/run   - Take a snapshot now");
+    tracing::info!("  - GET  /api/admin/workflow/trace/:id - Latest recorded trace of a workflow job");
+    tracing::info!("  - POST /api/admin/audit/digest/run - Publish pending audit digests now");
+    tracing::info!("  - GET  /api/admin/hold-rules      - Configured payment hold rules");
+    tracing::info!("  - POST /api/admin/settlements/:batch_id/release - Release payment holds on a batch");
+    tracing::info!("  - GET  /api/admin/schemes         - Incentive schemes and budget utilization");
+    tracing::info!("  - GET  /api/admin/schemes/:id/entitlements - Bonus entitlements per purchase (?farmer_did=)");
+    tracing::info!("  - POST /api/admin/schemes/:id/claims - Export payable entitlements as a claim file");
+    tracing::info!("  - GET  /api/admin/schemes/:id/claims - Claim files of a scheme");
+    tracing::info!("  - GET  /api/admin/scheme-claims/:id/download - Download a claim file");
+    tracing::info!("  - GET  /api/admin/reports         - Report schedules and their last runs");
+    tracing::info!("  - POST /api/admin/reports/:id/run - Generate and deliver a report now");
+    tracing::info!("  - GET  /api/admin/reports/runs    - Report run history (?schedule_id=)");
+    tracing::info!("  - GET  /api/admin/reports/runs/:id/download - Download a generated report");
+    tracing::info!("");
+    tracing::info!("✂️  Append ?fields=a,b.c to any JSON endpoint for sparse responses");
     tracing::info!("📚 See WORKFLOW.md for complete integration guide");
     tracing::info!("💡 Use /api/workflow/execute for end-to-end automation");
     tracing::info!("");
@@ -140,7 +493,7 @@ async fn main() -> anyhow::Result<()> {
                     async move {
                         tower_service.call(request).await.map_err(|err| {
                             tracing::error!("Service error: {:?}", err);
-                            std::io::Error::new(std::io::ErrorKind::Other, err)
+                            std::io::Error::other(err)
                         })
                     }
                 },
@@ -158,7 +511,3 @@ async fn main() -> anyhow::Result<()> {
 async fn root() -> &'static str {
     "Oilseed Value Chain Backend API v0.1.0 - All systems operational"
 }
-
-async fn health_check() -> &'static str {
-    "OK"
-}
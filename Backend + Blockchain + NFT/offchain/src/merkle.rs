@@ -0,0 +1,152 @@
+//! Pairwise Merkle trees shared by commitments, anchors, audit digests and
+//! SKU units
+//!
+//! Leaves and inner nodes are domain separated (`0x00 || data` for leaves,
+//! `0x01 || lo || hi` for nodes) and every pair is sorted before hashing, so
+//! a proof is just the list of sibling hashes and can be checked on-chain
+//! without position bits. An odd node at the end of a level is promoted
+//! unchanged.
+
+use crate::hash_schemes::HashScheme;
+use alloy::primitives::FixedBytes;
+
+/// Scheme of every leaf and node hash in the trees built here
+pub const MERKLE_HASH_SCHEME: HashScheme = HashScheme::Keccak256RawV1;
+
+/// Identifier of the tree construction, recorded next to published roots
+pub const TREE_SCHEME: &str = "keccak256-sorted-pair-merkle/v1";
+
+pub const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// Domain-separated leaf hash of `data`
+pub fn leaf(data: &[u8]) -> FixedBytes<32> {
+    let mut prefixed = Vec::with_capacity(data.len() + 1);
+    prefixed.push(LEAF_PREFIX);
+    prefixed.extend_from_slice(data);
+    MERKLE_HASH_SCHEME.hash_bytes(&prefixed)
+}
+
+fn hash_pair(a: &FixedBytes<32>, b: &FixedBytes<32>) -> FixedBytes<32> {
+    let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+    let mut data = vec![NODE_PREFIX];
+    data.extend_from_slice(lo.as_slice());
+    data.extend_from_slice(hi.as_slice());
+    MERKLE_HASH_SCHEME.hash_bytes(&data)
+}
+
+fn next_level(level: &[FixedBytes<32>]) -> Vec<FixedBytes<32>> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [a, b] => hash_pair(a, b),
+            // Odd node is promoted unchanged
+            [a] => *a,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+pub fn merkle_root(leaves: &[FixedBytes<32>]) -> FixedBytes<32> {
+    if leaves.is_empty() {
+        return FixedBytes::ZERO;
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level[0]
+}
+
+/// Sibling path from leaf `index` to the root
+pub fn merkle_proof(leaves: &[FixedBytes<32>], mut index: usize) -> Vec<FixedBytes<32>> {
+    let mut proof = Vec::new();
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        let sibling = index ^ 1;
+        if sibling < level.len() {
+            proof.push(level[sibling]);
+        }
+        index /= 2;
+        level = next_level(&level);
+    }
+    proof
+}
+
+pub fn verify_merkle_proof(
+    leaf: FixedBytes<32>,
+    proof: &[FixedBytes<32>],
+    root: FixedBytes<32>,
+) -> bool {
+    proof
+        .iter()
+        .fold(leaf, |acc, sibling| hash_pair(&acc, sibling))
+        == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::hash_string;
+    use proptest::prelude::*;
+
+    fn leaves() -> impl Strategy<Value = Vec<FixedBytes<32>>> {
+        prop::collection::vec(prop::collection::vec(any::<u8>(), 0..16), 1..40)
+            .prop_map(|items| items.iter().map(|data| leaf(data)).collect())
+    }
+
+    proptest! {
+        #[test]
+        fn prop_every_proof_verifies(leaves in leaves()) {
+            let root = merkle_root(&leaves);
+            for (i, leaf) in leaves.iter().enumerate() {
+                prop_assert!(verify_merkle_proof(*leaf, &merkle_proof(&leaves, i), root));
+            }
+        }
+
+        #[test]
+        fn prop_tampered_leaf_fails(
+            leaves in leaves(),
+            index in any::<prop::sample::Index>(),
+            data in prop::collection::vec(any::<u8>(), 0..16),
+        ) {
+            let i = index.index(leaves.len());
+            let forged = leaf(&data);
+            prop_assume!(!leaves.contains(&forged));
+            let root = merkle_root(&leaves);
+            prop_assert!(!verify_merkle_proof(forged, &merkle_proof(&leaves, i), root));
+        }
+
+        #[test]
+        fn prop_swapping_siblings_keeps_root(leaves in leaves(), pair in any::<prop::sample::Index>()) {
+            // Pairs are sorted before hashing, so order within a pair is irrelevant
+            prop_assume!(leaves.len() >= 2);
+            let i = pair.index(leaves.len() / 2) * 2;
+            let mut swapped = leaves.clone();
+            swapped.swap(i, i + 1);
+            prop_assert_eq!(merkle_root(&swapped), merkle_root(&leaves));
+        }
+    }
+
+    #[test]
+    fn test_every_leaf_proves_against_root() {
+        let leaves: Vec<FixedBytes<32>> = (0..5).map(|i| hash_string(&i.to_string())).collect();
+        let root = merkle_root(&leaves);
+        for (i, leaf) in leaves.iter().enumerate() {
+            assert!(verify_merkle_proof(*leaf, &merkle_proof(&leaves, i), root));
+        }
+        assert!(!verify_merkle_proof(
+            hash_string("x"),
+            &merkle_proof(&leaves, 0),
+            root
+        ));
+    }
+
+    #[test]
+    fn test_single_leaf_is_its_own_root() {
+        let only = leaf(b"UNIT-1");
+        assert_eq!(merkle_root(&[only]), only);
+        assert!(merkle_proof(&[only], 0).is_empty());
+        assert_eq!(merkle_root(&[]), FixedBytes::ZERO);
+    }
+}
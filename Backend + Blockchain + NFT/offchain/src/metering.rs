@@ -0,0 +1,452 @@
+//! Usage metering and quotas
+//!
+//! Bytes pinned to IPFS and transactions sent are counted per tenant (the
+//! API key name of the request, see [`crate::tenant_storage`]) and calendar
+//! month (UTC) in `data/usage.json`; work done outside an API key's request
+//! is counted as `platform`. Transactions also record the gas they used.
+//!
+//! Monthly limits are read from `data/quotas.json` (override with
+//! QUOTAS_CONFIG_PATH). `default` applies to every API key, `tenants`
+//! replaces it for one, and `platform` is never limited:
+//!
+//! ```json
+//! { "default": { "bytes": { "soft": 1000000000, "hard": 2000000000 },
+//!                "transactions": { "soft": 4000, "hard": 5000 } },
+//!   "tenants": { "export-desk-dubai": { "transactions": { "hard": 20000 } } } }
+//! ```
+//!
+//! Past the soft limit a warning is logged once a month and responses to the
+//! tenant carry an `X-Quota-Warning` header; an upload or transaction that
+//! would pass the hard limit is refused with 403 and code `quota_exceeded`.
+//! Admins see every tenant at `GET /api/admin/usage`, an API key its own at
+//! `GET /api/usage` (both take `?month=YYYY-MM`, default the current month).
+
+use crate::admin::require_admin;
+use crate::auth::Principal;
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+use crate::tenant_storage::{self, PLATFORM};
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Extension, Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Mutex, OnceLock};
+
+const DEFAULT_QUOTAS_PATH: &str = "data/quotas.json";
+const USAGE_PATH: &str = "data/usage.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    Bytes,
+    Transactions,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Limits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub soft: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hard: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Quota {
+    #[serde(default)]
+    pub bytes: Limits,
+    #[serde(default)]
+    pub transactions: Limits,
+}
+
+impl Quota {
+    fn limits(&self, metric: Metric) -> Limits {
+        match metric {
+            Metric::Bytes => self.bytes,
+            Metric::Transactions => self.transactions,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct QuotasFile {
+    #[serde(default)]
+    default: Quota,
+    #[serde(default)]
+    tenants: HashMap<String, Quota>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub tenant: String,
+    /// `YYYY-MM`
+    pub month: String,
+    #[serde(default)]
+    pub uploads: u64,
+    #[serde(default)]
+    pub bytes: u64,
+    #[serde(default)]
+    pub transactions: u64,
+    #[serde(default)]
+    pub gas_used: u64,
+    /// Metrics whose soft limit was passed this month
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warned: Vec<Metric>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_activity_at: Option<String>,
+}
+
+impl UsageRecord {
+    fn value(&self, metric: Metric) -> u64 {
+        match metric {
+            Metric::Bytes => self.bytes,
+            Metric::Transactions => self.transactions,
+        }
+    }
+}
+
+/// Upload or transaction refused because it would pass a hard limit
+#[derive(Debug)]
+pub struct QuotaExceeded {
+    pub tenant: String,
+    pub month: String,
+    pub metric: Metric,
+    pub limit: u64,
+}
+
+impl std::fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let metric = match self.metric {
+            Metric::Bytes => "storage quota of",
+            Metric::Transactions => "transaction quota of",
+        };
+        write!(
+            f,
+            "Monthly {} {} for {} exhausted (limit {})",
+            metric, self.tenant, self.month, self.limit
+        )
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+fn current_month() -> String {
+    Utc::now().format("%Y-%m").to_string()
+}
+
+pub struct Metering {
+    default: Quota,
+    tenants: HashMap<String, Quota>,
+    usage: Mutex<Vec<UsageRecord>>,
+}
+
+impl Metering {
+    fn load() -> Result<Self> {
+        let path = std::env::var("QUOTAS_CONFIG_PATH")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| DEFAULT_QUOTAS_PATH.to_string());
+        let file: QuotasFile = if std::path::Path::new(&path).exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read quotas {}", path))?;
+            serde_json::from_str(&content).with_context(|| format!("Invalid quotas {}", path))?
+        } else {
+            QuotasFile::default()
+        };
+        let usage = match std::fs::read_to_string(USAGE_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid usage file {}", USAGE_PATH))?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Self {
+            default: file.default,
+            tenants: file.tenants,
+            usage: Mutex::new(usage),
+        })
+    }
+
+    fn save(usage: &[UsageRecord]) -> Result<()> {
+        std::fs::write(USAGE_PATH, serde_json::to_string_pretty(usage)?)
+            .with_context(|| format!("Failed to write {}", USAGE_PATH))
+    }
+
+    pub fn quota(&self, tenant: &str) -> Quota {
+        if tenant == PLATFORM {
+            return Quota::default();
+        }
+        self.tenants.get(tenant).copied().unwrap_or(self.default)
+    }
+
+    fn usage(&self, tenant: &str, month: &str) -> UsageRecord {
+        self.usage
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|u| u.tenant == tenant && u.month == month)
+            .cloned()
+            .unwrap_or_else(|| UsageRecord {
+                tenant: tenant.to_string(),
+                month: month.to_string(),
+                ..Default::default()
+            })
+    }
+
+    /// Refuse `amount` more of `metric` if it would pass the hard limit
+    pub fn admit(&self, tenant: &str, metric: Metric, amount: u64) -> Result<(), QuotaExceeded> {
+        let Some(limit) = self.quota(tenant).limits(metric).hard else {
+            return Ok(());
+        };
+        let month = current_month();
+        let used = self.usage(tenant, &month).value(metric);
+        if used.saturating_add(amount) > limit {
+            tracing::warn!(tenant = %tenant, ?metric, used, limit, "Hard quota reached");
+            return Err(QuotaExceeded {
+                tenant: tenant.to_string(),
+                month,
+                metric,
+                limit,
+            });
+        }
+        Ok(())
+    }
+
+    fn record(&self, tenant: &str, update: impl FnOnce(&mut UsageRecord)) {
+        let month = current_month();
+        let quota = self.quota(tenant);
+        let mut usage = self.usage.lock().unwrap_or_else(|e| e.into_inner());
+        let index = match usage
+            .iter()
+            .position(|u| u.tenant == tenant && u.month == month)
+        {
+            Some(index) => index,
+            None => {
+                usage.push(UsageRecord {
+                    tenant: tenant.to_string(),
+                    month,
+                    ..Default::default()
+                });
+                usage.len() - 1
+            }
+        };
+        let record = &mut usage[index];
+        update(record);
+        record.last_activity_at = Some(Utc::now().to_rfc3339());
+
+        for metric in soft_limits_passed(&quota, record) {
+            if !record.warned.contains(&metric) {
+                record.warned.push(metric);
+                tracing::warn!(
+                    tenant = %tenant,
+                    ?metric,
+                    used = record.value(metric),
+                    "Soft quota passed"
+                );
+            }
+        }
+        if let Err(e) = Self::save(&usage) {
+            tracing::warn!(error = %format!("{:#}", e), "Failed to save usage");
+        }
+    }
+
+    /// Meter a completed upload of `bytes`
+    pub fn record_upload(&self, tenant: &str, bytes: u64) {
+        self.record(tenant, |u| {
+            u.uploads += 1;
+            u.bytes += bytes;
+        });
+    }
+
+    /// Meter a mined transaction
+    pub fn record_transaction(&self, tenant: &str, gas_used: u64) {
+        self.record(tenant, |u| {
+            u.transactions += 1;
+            u.gas_used += gas_used;
+        });
+    }
+
+    /// `X-Quota-Warning` value for a tenant past a soft limit this month
+    pub fn warning(&self, tenant: &str) -> Option<String> {
+        let quota = self.quota(tenant);
+        let usage = self.usage(tenant, &current_month());
+        let passed: Vec<String> = soft_limits_passed(&quota, &usage)
+            .into_iter()
+            .map(|metric| {
+                let limits = quota.limits(metric);
+                format!(
+                    "{:?} {} of {}",
+                    metric,
+                    usage.value(metric),
+                    limits.hard.or(limits.soft).unwrap_or_default()
+                )
+                .to_lowercase()
+            })
+            .collect();
+        (!passed.is_empty()).then(|| passed.join(", "))
+    }
+}
+
+fn soft_limits_passed(quota: &Quota, usage: &UsageRecord) -> Vec<Metric> {
+    [Metric::Bytes, Metric::Transactions]
+        .into_iter()
+        .filter(|metric| {
+            quota
+                .limits(*metric)
+                .soft
+                .is_some_and(|soft| usage.value(*metric) >= soft)
+        })
+        .collect()
+}
+
+static METERING: OnceLock<Metering> = OnceLock::new();
+
+/// Load quotas and usage; run once at startup so a bad file stops it
+pub fn init() -> Result<()> {
+    let metering = Metering::load()?;
+    let _ = METERING.set(metering);
+    Ok(())
+}
+
+/// Process-wide meter shared by the IPFS and chain clients
+pub fn global() -> &'static Metering {
+    METERING.get_or_init(|| {
+        Metering::load().unwrap_or_else(|e| {
+            tracing::warn!(error = %format!("{:#}", e), "Usage metering starts empty");
+            Metering {
+                default: Quota::default(),
+                tenants: HashMap::new(),
+                usage: Mutex::new(Vec::new()),
+            }
+        })
+    })
+}
+
+/// Tenant of the current request, checked against the hard limit of `metric`
+pub fn admit_current(metric: Metric, amount: u64) -> Result<String, QuotaExceeded> {
+    let tenant = tenant_storage::current_tenant();
+    global().admit(&tenant, metric, amount)?;
+    Ok(tenant)
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    #[serde(default)]
+    pub month: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TenantUsageView {
+    #[serde(flatten)]
+    pub usage: UsageRecord,
+    pub quota: Quota,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageView {
+    pub month: String,
+    pub tenants: Vec<TenantUsageView>,
+}
+
+fn month_param(query: UsageQuery) -> Result<String, ApiError> {
+    let month = query.month.unwrap_or_else(current_month);
+    if chrono::NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d").is_err() {
+        return Err(ApiError::bad_request("month must be YYYY-MM"));
+    }
+    Ok(month)
+}
+
+fn view(metering: &Metering, tenant: &str, month: &str) -> TenantUsageView {
+    TenantUsageView {
+        usage: metering.usage(tenant, month),
+        quota: metering.quota(tenant),
+    }
+}
+
+/// Usage of every tenant in a month (admin only)
+pub async fn all_usage(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<UsageQuery>,
+) -> ApiResult<UsageView> {
+    require_admin(&state, &headers)?;
+    let month = month_param(query)?;
+
+    let metering = global();
+    let mut tenants: BTreeSet<String> = metering.tenants.keys().cloned().collect();
+    tenants.insert(PLATFORM.to_string());
+    for record in metering
+        .usage
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+    {
+        if record.month == month {
+            tenants.insert(record.tenant.clone());
+        }
+    }
+    Ok(Json(UsageView {
+        tenants: tenants
+            .iter()
+            .map(|tenant| view(metering, tenant, &month))
+            .collect(),
+        month,
+    }))
+}
+
+/// Usage of the calling API key
+pub async fn own_usage(
+    principal: Option<Extension<Principal>>,
+    Query(query): Query<UsageQuery>,
+) -> ApiResult<TenantUsageView> {
+    let Some(Extension(Principal::ApiKey { name, .. })) = principal else {
+        return Err(ApiError::unauthorized("Send an API key to see its usage"));
+    };
+    let month = month_param(query)?;
+    Ok(Json(view(global(), &name, &month)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_soft_and_hard_limits() {
+        let metering = Metering {
+            default: Quota {
+                bytes: Limits::default(),
+                transactions: Limits {
+                    soft: Some(8),
+                    hard: Some(10),
+                },
+            },
+            tenants: HashMap::new(),
+            usage: Mutex::new(vec![UsageRecord {
+                tenant: "export-desk".to_string(),
+                month: current_month(),
+                transactions: 9,
+                ..Default::default()
+            }]),
+        };
+
+        assert!(metering
+            .admit("export-desk", Metric::Transactions, 1)
+            .is_ok());
+        assert!(metering
+            .admit("export-desk", Metric::Transactions, 2)
+            .is_err());
+        assert!(metering
+            .admit("export-desk", Metric::Bytes, u64::MAX)
+            .is_ok());
+        assert!(metering.admit(PLATFORM, Metric::Transactions, 100).is_ok());
+        assert_eq!(
+            metering.warning("export-desk").as_deref(),
+            Some("transactions 9 of 10")
+        );
+        assert_eq!(metering.warning("other-key"), None);
+    }
+}
@@ -0,0 +1,376 @@
+//! Named chain networks
+//!
+//! The unsuffixed chain variables (RPC_URL, CHAIN_ID, CONTRACT_ADDRESS, ...)
+//! configure the default network, named by CHAIN_NETWORK (default
+//! "default"). CHAIN_NETWORKS lists further networks, each configured by the
+//! same variables with the network's name as suffix:
+//!
+//! ```text
+//! CHAIN_NETWORK=amoy
+//! CHAIN_NETWORKS=polygon
+//! RPC_URL_POLYGON=https://polygon-rpc.com
+//! CHAIN_ID_POLYGON=137
+//! CONTRACT_ADDRESS_POLYGON=0x...
+//! ```
+//!
+//! Signer keys, gas and confirmation settings are shared. Each network has
+//! its own transaction queue and outbox (`data/tx_queue.<network>.json`,
+//! `data/outbox.<network>.db`), and the outbox worker retries all of them.
+//!
+//! Supply chain endpoints and the tx-queue and outbox admin views act on
+//! the network named by the `X-Chain-Network` header, else on the default.
+//! Local records (batch folders, the event index, anchoring) are not split
+//! by network and follow the default network. `GET /api/networks` lists
+//! the configured networks.
+//!
+//! `POST /api/admin/chain/reload` applies changed chain settings without a
+//! restart, e.g. after a contract redeploy. It parses `.env` again and
+//! rebuilds the client of every network with its RPC_URL, contract
+//! addresses, signer keys, gas and confirmation settings, validates them
+//! like at startup and swaps them in together. Nothing changes if any
+//! network fails. As at startup, variables set in the process environment
+//! take precedence over the file; a variable removed from the file is
+//! unset. The process environment itself is never modified, so
+//! CHAIN_NETWORK, SIGNER_KEYS_PATH and the remote signer settings keep their
+//! startup values. Each network keeps its transaction queue entries and
+//! outbox, and writes already submitted finish with the settings they
+//! started with.
+
+use crate::admin::require_admin;
+use crate::chain::{ChainClient, ChainConfig};
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::State,
+    http::{HeaderMap, HeaderName},
+    Json,
+};
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock, RwLock};
+use tokio::sync::Mutex;
+
+pub const NETWORK_HEADER: HeaderName = HeaderName::from_static("x-chain-network");
+const DEFAULT_NETWORK: &str = "default";
+
+/// Chain variables a named network sets with its own suffix
+pub const PER_NETWORK_VARS: &[&str] = &[
+    "RPC_URL",
+    "CHAIN_ID",
+    "CONTRACT_ADDRESS",
+    "LEGACY_CONTRACT_ADDRESS",
+    "CONTRACT_WRITE_CUTOVER",
+    "NFT_CONTRACT_ADDRESS",
+    "PAYMENTS_CONTRACT_ADDRESS",
+    "EXPLORER_BASE_URL",
+];
+
+/// Variables of the process environment before `.env` was loaded
+static PROCESS_VARS: OnceLock<HashSet<String>> = OnceLock::new();
+
+/// Load `.env` without overriding the process environment, remembering
+/// which variables the process set itself so a reload keeps them ahead of
+/// the file
+pub fn load_dotenv() {
+    PROCESS_VARS.get_or_init(|| {
+        std::env::vars_os()
+            .filter_map(|(name, _)| name.into_string().ok())
+            .collect()
+    });
+    dotenvy::dotenv().ok();
+}
+
+/// Where the chain variables are read from
+#[derive(Debug, Default)]
+pub struct EnvVars {
+    /// `.env` as parsed by a reload; `None` reads the process environment
+    file: Option<HashMap<String, String>>,
+}
+
+impl EnvVars {
+    /// The process environment, with `.env` as loaded at startup
+    pub fn process() -> Self {
+        Self::default()
+    }
+
+    /// The current `.env` under the variables the process set itself
+    pub fn reread() -> Result<Self> {
+        let mut file = HashMap::new();
+        match dotenvy::dotenv_iter() {
+            Ok(iter) => {
+                for item in iter {
+                    let (name, value) = item.context("Invalid .env")?;
+                    file.insert(name, value);
+                }
+            }
+            Err(e) if e.not_found() => {}
+            Err(e) => return Err(e).context("Failed to read .env"),
+        }
+        Ok(Self::with_file(file))
+    }
+
+    fn with_file(file: HashMap<String, String>) -> Self {
+        Self { file: Some(file) }
+    }
+
+    /// Value of `name`, unset when neither source has it
+    pub fn get(&self, name: &str) -> Option<String> {
+        match &self.file {
+            Some(file) if !from_process(name) => file.get(name).cloned(),
+            _ => std::env::var(name).ok(),
+        }
+    }
+
+    /// [`Self::get`], counting an empty value as unset
+    pub fn non_empty(&self, name: &str) -> Option<String> {
+        self.get(name).filter(|v| !v.trim().is_empty())
+    }
+}
+
+/// Whether the process environment set `name` before `.env` was loaded;
+/// without [`load_dotenv`], whether it is set at all
+fn from_process(name: &str) -> bool {
+    match PROCESS_VARS.get() {
+        Some(names) => names.contains(name),
+        None => std::env::var_os(name).is_some(),
+    }
+}
+
+/// Name of the network the unsuffixed variables configure
+pub fn default_network() -> String {
+    std::env::var("CHAIN_NETWORK")
+        .ok()
+        .map(|n| n.trim().to_lowercase())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| DEFAULT_NETWORK.to_string())
+}
+
+/// Suffix of a network's variables, e.g. "POLYGON_AMOY" for "polygon-amoy"
+pub fn var_suffix(network: &str) -> String {
+    network.to_uppercase().replace('-', "_")
+}
+
+/// Per-network copy of a data file; the default network keeps `path`
+pub fn data_path(network: &str, path: &str) -> String {
+    if network == default_network() {
+        return path.to_string();
+    }
+    match path.rsplit_once('.') {
+        Some((stem, extension)) => format!("{}.{}.{}", stem, network, extension),
+        None => format!("{}.{}", path, network),
+    }
+}
+
+/// Names listed in CHAIN_NETWORKS, lowercased and without duplicates
+fn parse_names(list: &str, default: &str) -> Result<Vec<String>> {
+    let mut names: Vec<String> = Vec::new();
+    for name in list.split(',').map(|n| n.trim().to_lowercase()) {
+        if name.is_empty() || name == default || names.contains(&name) {
+            continue;
+        }
+        if !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            bail!("Invalid network name '{}' in CHAIN_NETWORKS", name);
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+/// Chain clients by network name
+pub struct ChainNetworks {
+    /// Default network first; replaced as a whole by a reload
+    clients: RwLock<Vec<Arc<ChainClient>>>,
+    /// Held while a reload builds the new clients
+    reloading: Mutex<()>,
+}
+
+impl ChainNetworks {
+    /// `default` plus a client per network in CHAIN_NETWORKS
+    pub async fn from_env(default: Arc<ChainClient>) -> Result<Self> {
+        let list = std::env::var("CHAIN_NETWORKS").unwrap_or_default();
+        let mut clients = vec![default];
+        for name in parse_names(&list, clients[0].network())? {
+            let config = ChainConfig::for_network(&name, &EnvVars::process())?;
+            let client = ChainClient::start(config).await?;
+            tracing::info!(network = %name, chain_id = client.chain_id(), "Chain network configured");
+            clients.push(Arc::new(client));
+        }
+        Ok(Self {
+            clients: RwLock::new(clients),
+            reloading: Mutex::new(()),
+        })
+    }
+
+    /// Current clients, default first
+    pub fn clients(&self) -> Vec<Arc<ChainClient>> {
+        self.clients
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Client of the default network
+    pub fn default_client(&self) -> Arc<ChainClient> {
+        self.clients.read().unwrap_or_else(|e| e.into_inner())[0].clone()
+    }
+
+    pub fn get(&self, network: &str) -> Option<Arc<ChainClient>> {
+        self.clients()
+            .into_iter()
+            .find(|c| c.network().eq_ignore_ascii_case(network))
+    }
+
+    /// Client of the network named by `X-Chain-Network`, else the default
+    pub fn select(&self, headers: &HeaderMap) -> Result<Arc<ChainClient>, ApiError> {
+        let Some(value) = headers.get(NETWORK_HEADER) else {
+            return Ok(self.default_client());
+        };
+        let name = value
+            .to_str()
+            .map_err(|_| ApiError::bad_request("Invalid X-Chain-Network header"))?
+            .trim();
+        self.get(name).ok_or_else(|| {
+            let known: Vec<String> = self
+                .clients()
+                .iter()
+                .map(|c| c.network().to_string())
+                .collect();
+            ApiError::bad_request(format!(
+                "Unknown network '{}' (configured: {})",
+                name,
+                known.join(", ")
+            ))
+        })
+    }
+
+    /// Parse `.env` again, rebuild every network's client and swap them in
+    /// once all of them are valid
+    pub async fn reload(&self) -> Result<()> {
+        let _reloading = self.reloading.lock().await;
+        let vars = EnvVars::reread()?;
+
+        let current = self.clients();
+        let default = current[0]
+            .reconnect(ChainConfig::from_env(&vars)?)
+            .await
+            .context("Default network")?;
+        let mut clients = vec![Arc::new(default)];
+        let list = vars.get("CHAIN_NETWORKS").unwrap_or_default();
+        for name in parse_names(&list, clients[0].network())? {
+            let previous = current[1..].iter().find(|c| c.network() == name);
+            let config = ChainConfig::for_network(&name, &vars)?;
+            let client = ChainClient::connect(config, previous.map(Arc::as_ref))
+                .await
+                .with_context(|| format!("Network '{}'", name))?;
+            clients.push(Arc::new(client));
+        }
+
+        for client in &clients {
+            tracing::info!(
+                network = %client.network(),
+                chain_id = client.chain_id(),
+                contract_address = ?client.write_contract_address(),
+                "Chain network reloaded"
+            );
+        }
+        *self.clients.write().unwrap_or_else(|e| e.into_inner()) = clients;
+        Ok(())
+    }
+
+    fn views(&self) -> Vec<NetworkView> {
+        self.clients()
+            .iter()
+            .enumerate()
+            .map(|(i, client)| NetworkView {
+                name: client.network().to_string(),
+                chain_id: client.chain_id(),
+                contract_address: format!("{:?}", client.write_contract_address()),
+                explorer_base_url: client.explorer_base_url().map(str::to_string),
+                default: i == 0,
+            })
+            .collect()
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct NetworkView {
+    pub name: String,
+    pub chain_id: u64,
+    pub contract_address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_base_url: Option<String>,
+    pub default: bool,
+}
+
+/// Configured networks, default first
+pub async fn list_networks(State(state): State<AppState>) -> ApiResult<Vec<NetworkView>> {
+    Ok(Json(state.networks.views()))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReloadChainResponse {
+    pub networks: Vec<NetworkView>,
+    pub reloaded_at: String,
+}
+
+/// Apply changed chain settings from `.env` without a restart (admin only)
+pub async fn reload_chain(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<ReloadChainResponse> {
+    require_admin(&state, &headers)?;
+
+    state
+        .networks
+        .reload()
+        .await
+        .map_err(|e| ApiError::bad_request(format!("Chain reload failed: {:#}", e)))?;
+    Ok(Json(ReloadChainResponse {
+        networks: state.networks.views(),
+        reloaded_at: Utc::now().to_rfc3339(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_names_and_data_paths() {
+        assert_eq!(
+            parse_names(" Polygon, amoy,polygon,,mainnet-pilot", "amoy").unwrap(),
+            vec!["polygon", "mainnet-pilot"]
+        );
+        assert!(parse_names("polygon mainnet", "default").is_err());
+
+        assert_eq!(var_suffix("mainnet-pilot"), "MAINNET_PILOT");
+        assert_eq!(
+            data_path("polygon", "data/tx_queue.json"),
+            "data/tx_queue.polygon.json"
+        );
+        assert_eq!(
+            data_path(&default_network(), "data/outbox.db"),
+            "data/outbox.db"
+        );
+    }
+
+    #[test]
+    fn test_reload_vars_come_from_the_file() {
+        let vars = EnvVars::with_file(HashMap::from([(
+            "RPC_URL_TEST_RELOAD".to_string(),
+            "http://localhost:8545".to_string(),
+        )]));
+        assert_eq!(
+            vars.get("RPC_URL_TEST_RELOAD").as_deref(),
+            Some("http://localhost:8545")
+        );
+        assert_eq!(EnvVars::process().get("RPC_URL_TEST_RELOAD"), None);
+    }
+}
@@ -0,0 +1,104 @@
+//! ERC-721 tokens for packaged SKUs
+//!
+//! `POST /api/nft/mint` mints one SkuNFT token per SKU (contract at
+//! NFT_CONTRACT_ADDRESS, see `contracts/SkuNFT.sol`). The token URI points
+//! to ERC-721 metadata built by [`crate::nft_metadata`], which links the
+//! batch folder anchored by the SKU's `SKUPackaged` event, so the SKU must
+//! be packaged, indexed and pinned first. Tokens go
+//! to `recipient`, or stay with the backend's default signer, which must be
+//! an authorized minter.
+
+use crate::chain::hash_string;
+use crate::error::{format_hash, format_tx_hash, ApiError, ApiResult};
+use crate::indexer::EventFilter;
+use crate::nft_metadata;
+use crate::state::AppState;
+use alloy::primitives::Address;
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize)]
+pub struct MintSkuNftRequest {
+    pub sku_id: String,
+    /// Address receiving the token (default: the backend signer)
+    #[serde(default)]
+    pub recipient: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MintSkuNftResponse {
+    pub sku_id: String,
+    pub token_id: String,
+    pub token_uri: String,
+    pub contract: String,
+    pub tx_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
+}
+
+pub async fn mint_sku_nft(
+    State(state): State<AppState>,
+    Json(payload): Json<MintSkuNftRequest>,
+) -> ApiResult<MintSkuNftResponse> {
+    let recipient: Option<Address> = payload
+        .recipient
+        .as_deref()
+        .map(|r| {
+            r.parse()
+                .map_err(|e| ApiError::bad_request(format!("Invalid recipient address: {}", e)))
+        })
+        .transpose()?;
+    let sku_hash = hash_string(&payload.sku_id);
+
+    let contract = state
+        .chain()
+        .nft_address()
+        .ok_or_else(|| ApiError::bad_request("SKU NFTs are not configured"))?;
+    if let Some(token_id) = state
+        .chain()
+        .sku_token(sku_hash)
+        .await
+        .map_err(ApiError::blockchain_failed)?
+    {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!("SKU {} is already minted as token {}", payload.sku_id, token_id),
+        ));
+    }
+
+    let packaged = state
+        .events
+        .query(&EventFilter::Subject(format_hash(sku_hash)), None, 100)
+        .await
+        .map_err(|e| ApiError::internal(format!("Event index query failed: {:#}", e)))?
+        .into_iter()
+        .find(|event| event.event == "SKUPackaged")
+        .ok_or_else(|| ApiError::not_found("SKU has no indexed packaging event yet"))?;
+    let cid = packaged
+        .metadata_cid
+        .filter(|cid| !cid.is_empty())
+        .ok_or_else(|| ApiError::not_found("SKU packaging event has no metadata CID"))?;
+    // A deferred pin must land before the URI can point at real content
+    let cid = state
+        .ipfs_client
+        .provenance()
+        .resolve(&cid)
+        .await
+        .map_err(|e| ApiError::new(StatusCode::CONFLICT, format!("{:#}", e)))?;
+    let token_uri = nft_metadata::pin_token_uri(&state, &payload.sku_id, &cid).await?;
+
+    let (token_id, receipt) = state
+        .chain()
+        .mint_sku_nft(sku_hash, recipient, token_uri.clone())
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+
+    Ok(Json(MintSkuNftResponse {
+        sku_id: payload.sku_id,
+        token_id: token_id.to_string(),
+        token_uri,
+        contract: format!("{:?}", contract),
+        tx_hash: format_tx_hash(receipt.transaction_hash),
+        explorer_url: state.chain().explorer_url(receipt.transaction_hash),
+    }))
+}
@@ -0,0 +1,290 @@
+//! ERC-721 metadata for SKU tokens
+//!
+//! Marketplaces and wallets render a token from the JSON its `tokenURI`
+//! points to, following the ERC-721 metadata / OpenSea schema (`name`,
+//! `description`, `image`, `external_url`, `attributes`). Our batch records
+//! are free-form, so before minting, [`pin_token_uri`] folds the packaging,
+//! processing, FPO purchase and farmer records of a SKU into that schema,
+//! pins it and returns `ipfs://<cid>` as the token URI.
+//!
+//! The document is always pinned as plain JSON, whatever
+//! METADATA_PAYLOAD_FORMAT says, since marketplaces cannot read our compact
+//! envelope. The batch folder stays linked under `properties`.
+
+use crate::batch_ledger::{self, SkuRecord};
+use crate::error::ApiError;
+use crate::farmer_verification::FarmerEntry;
+use crate::notifications::trace_url;
+use crate::state::AppState;
+use anyhow::Context;
+use serde_json::{json, Value};
+
+/// Everything a SKU's token metadata is built from
+#[derive(Debug, Clone)]
+pub struct TokenSources {
+    pub sku_id: String,
+    pub sku: SkuRecord,
+    pub processing: Option<Value>,
+    pub purchase: Option<Value>,
+    pub farmer: Option<FarmerEntry>,
+    /// CID of the batch folder anchored on chain
+    pub batch_folder_cid: String,
+}
+
+impl TokenSources {
+    /// First string found at `pointer` in packaging, processing, then purchase records
+    fn field(&self, pointer: &str) -> Option<&str> {
+        [
+            Some(&self.sku.packaging),
+            self.processing.as_ref(),
+            self.purchase.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(|record| {
+            record
+                .pointer(pointer)
+                .and_then(Value::as_str)
+                .filter(|v| !v.is_empty())
+        })
+    }
+
+    fn purchase_field(&self, pointer: &str) -> Option<&Value> {
+        self.purchase.as_ref().and_then(|p| p.pointer(pointer))
+    }
+
+    /// Image CID: the packaging artwork, else the first procurement photo
+    fn image_cid(&self) -> Option<&str> {
+        ["/image_cid", "/image"]
+            .iter()
+            .find_map(|pointer| self.sku.packaging.pointer(pointer).and_then(Value::as_str))
+            .or_else(|| {
+                self.purchase_field("/photos/0/image_cid")
+                    .and_then(Value::as_str)
+            })
+            .map(|cid| cid.trim_start_matches("ipfs://"))
+            .filter(|cid| !cid.is_empty())
+    }
+
+    /// Harvest date as unix seconds: an explicit `harvest_date`, else the purchase date
+    fn harvest_timestamp(&self) -> Option<i64> {
+        self.field("/harvest_date")
+            .or_else(|| self.purchase_field("/timestamp").and_then(Value::as_str))
+            .and_then(parse_date)
+    }
+}
+
+/// Unix seconds of an RFC 3339 timestamp or a `YYYY-MM-DD` date
+fn parse_date(value: &str) -> Option<i64> {
+    if let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(timestamp.timestamp());
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|time| time.and_utc().timestamp())
+}
+
+fn trait_value(trait_type: &str, value: impl Into<Value>) -> Value {
+    json!({ "trait_type": trait_type, "value": value.into() })
+}
+
+/// ERC-721 metadata document for a SKU
+pub fn build(sources: &TokenSources) -> Value {
+    let crop = sources
+        .purchase_field("/farmer_info/crop_type")
+        .and_then(Value::as_str)
+        .or_else(|| sources.farmer.as_ref().map(|f| f.crop.as_str()));
+    let grade = sources
+        .purchase_field("/batch_info/quality_grade")
+        .and_then(Value::as_str);
+
+    let name = sources
+        .field("/product_name")
+        .or_else(|| sources.sku.packaging.get("name").and_then(Value::as_str))
+        .map(String::from)
+        .unwrap_or_else(|| match crop {
+            Some(crop) => format!("{} oil · {}", crop, sources.sku_id),
+            None => format!("Oilseed SKU {}", sources.sku_id),
+        });
+    let description = sources
+        .sku
+        .packaging
+        .get("description")
+        .and_then(Value::as_str)
+        .map(String::from)
+        .unwrap_or_else(|| {
+            format!(
+                "Packaged from batch {} and traced on chain from the FPO purchase to this pack.",
+                sources.sku.batch_id
+            )
+        });
+
+    let mut attributes = Vec::new();
+    if let Some(grade) = grade {
+        attributes.push(trait_value("Quality Grade", grade));
+    }
+    if let Some(farmer) = &sources.farmer {
+        attributes.push(trait_value(
+            "Origin District",
+            farmer.district_code.as_str(),
+        ));
+        attributes.push(trait_value("Origin State", farmer.location.as_str()));
+    }
+    if let Some(harvested) = sources.harvest_timestamp() {
+        attributes.push(json!({
+            "display_type": "date",
+            "trait_type": "Harvest Date",
+            "value": harvested,
+        }));
+    }
+    if let Some(crop) = crop {
+        attributes.push(trait_value("Crop", crop));
+    }
+    if let Some(package_type) = sources.field("/package_type") {
+        attributes.push(trait_value("Package Type", package_type));
+    }
+    if let Some(quantity) = sources
+        .purchase_field("/batch_info/quantity_kg")
+        .and_then(Value::as_f64)
+    {
+        attributes.push(json!({
+            "display_type": "number",
+            "trait_type": "Batch Quantity (kg)",
+            "value": quantity,
+        }));
+    }
+    attributes.push(trait_value("Batch", sources.sku.batch_id.as_str()));
+
+    let mut metadata = json!({
+        "name": name,
+        "description": description,
+        "external_url": trace_url(&sources.sku_id),
+        "attributes": attributes,
+        "properties": {
+            "sku_id": sources.sku_id,
+            "batch_id": sources.sku.batch_id,
+            "batch_folder": format!("ipfs://{}", sources.batch_folder_cid),
+        },
+    });
+    if let Some(cid) = sources.image_cid() {
+        metadata["image"] = json!(format!("ipfs://{}", cid));
+    }
+    metadata
+}
+
+/// Collect the off-chain records of `sku_id`, whose batch folder is pinned
+/// as `batch_folder_cid`
+pub async fn gather(
+    state: &AppState,
+    sku_id: &str,
+    batch_folder_cid: &str,
+) -> Result<TokenSources, ApiError> {
+    let sku = batch_ledger::find_sku(sku_id)?
+        .ok_or_else(|| ApiError::not_found("SKU packaging record not found"))?;
+    let purchase = batch_ledger::fpo_purchase(&sku.batch_id)?;
+    let processing = batch_ledger::batch_records(&sku.batch_id)?
+        .into_iter()
+        .find(|(name, _)| name == "processing.json")
+        .map(|(_, record)| record);
+
+    let farmer_did = purchase
+        .as_ref()
+        .and_then(|p| p.pointer("/farmer_info/farmer_did"))
+        .and_then(Value::as_str);
+    let farmer = match farmer_did {
+        Some(did) => state.farmer_verification.get_farmer_by_did(did).await?,
+        None => None,
+    };
+
+    Ok(TokenSources {
+        sku_id: sku_id.to_string(),
+        sku,
+        processing,
+        purchase,
+        farmer,
+        batch_folder_cid: batch_folder_cid.to_string(),
+    })
+}
+
+/// Build and pin the token metadata of `sku_id`; returns its token URI
+pub async fn pin_token_uri(
+    state: &AppState,
+    sku_id: &str,
+    batch_folder_cid: &str,
+) -> Result<String, ApiError> {
+    let metadata = build(&gather(state, sku_id, batch_folder_cid).await?);
+    let bytes = serde_json::to_vec_pretty(&metadata)
+        .context("Failed to serialize token metadata")
+        .map_err(ApiError::ipfs_upload_failed)?;
+    let cid = state
+        .ipfs_client
+        .upload_bytes(bytes, "metadata.json")
+        .await
+        .map_err(ApiError::ipfs_upload_failed)?;
+    Ok(format!("ipfs://{}", cid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources(packaging: Value) -> TokenSources {
+        TokenSources {
+            sku_id: "SKU-7".to_string(),
+            sku: SkuRecord {
+                batch_id: "BATCH-1".to_string(),
+                packaging,
+            },
+            processing: Some(json!({"harvest_date": "2025-02-10"})),
+            purchase: Some(json!({
+                "batch_info": {"quality_grade": "A", "quantity_kg": 500.0},
+                "farmer_info": {"farmer_did": "did:farmer:1", "crop_type": "Mustard"},
+                "timestamp": "2025-03-01T10:00:00+00:00",
+                "photos": [{"image_cid": "QmPhoto"}],
+            })),
+            farmer: None,
+            batch_folder_cid: "QmFolder".to_string(),
+        }
+    }
+
+    fn attribute<'a>(metadata: &'a Value, trait_type: &str) -> Option<&'a Value> {
+        metadata["attributes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|a| a["trait_type"] == trait_type)
+            .map(|a| &a["value"])
+    }
+
+    #[test]
+    fn test_metadata_follows_erc721_schema() {
+        let metadata = build(&sources(json!({"package_type": "1L bottle"})));
+
+        assert_eq!(metadata["name"], "Mustard oil · SKU-7");
+        assert_eq!(metadata["image"], "ipfs://QmPhoto");
+        assert_eq!(metadata["properties"]["batch_folder"], "ipfs://QmFolder");
+        assert_eq!(attribute(&metadata, "Quality Grade"), Some(&json!("A")));
+        assert_eq!(
+            attribute(&metadata, "Package Type"),
+            Some(&json!("1L bottle"))
+        );
+        // Explicit harvest date wins over the purchase date
+        assert_eq!(
+            attribute(&metadata, "Harvest Date"),
+            Some(&json!(1739145600))
+        );
+    }
+
+    #[test]
+    fn test_packaging_overrides_defaults() {
+        let metadata = build(&sources(json!({
+            "product_name": "Cold-pressed Mustard Oil",
+            "image_cid": "ipfs://QmLabel",
+        })));
+
+        assert_eq!(metadata["name"], "Cold-pressed Mustard Oil");
+        assert_eq!(metadata["image"], "ipfs://QmLabel");
+        assert!(attribute(&metadata, "Origin District").is_none());
+    }
+}
@@ -0,0 +1,439 @@
+//! Outbound notifications to FPO staff, traders and farmers
+//!
+//! A notification names a pre-approved template plus its parameters and is
+//! delivered over one channel:
+//!
+//! - `sms`: plain text through the SMS provider (see [`crate::sms::SmsClient`])
+//! - `whatsapp`: WhatsApp Business Cloud API template message, with the SKU
+//!   QR code as header image and the consumer trace link as URL button
+//!
+//! Auditor invitations and scheduled reports are sent by email through
+//! [`EmailClient`].
+//!
+//! Channels without credentials run in dry-run mode and only log.
+
+use crate::admin::require_admin;
+use crate::error::{ApiError, ApiResult};
+use crate::sms::{normalize_mobile, SmsClient};
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{extract::State, http::HeaderMap, Json};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    Sms,
+    Whatsapp,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Notification {
+    pub channel: Channel,
+    pub to: String,
+    /// Template name registered with the provider
+    pub template: String,
+    #[serde(default = "default_language")]
+    pub language: String,
+    /// Positional body parameters ({{1}}, {{2}}, ...)
+    #[serde(default)]
+    pub params: Vec<String>,
+    /// SKU whose trace link and QR code are attached
+    #[serde(default)]
+    pub sku_id: Option<String>,
+    /// Overrides the generated QR image for the SKU
+    #[serde(default)]
+    pub qr_image_url: Option<String>,
+}
+
+fn default_language() -> String {
+    "en".to_string()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryReport {
+    pub channel: Channel,
+    pub to: String,
+    pub delivered: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_message_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace_url: Option<String>,
+}
+
+// ======================== LINKS ========================
+
+/// Public consumer trace page for a SKU (PUBLIC_TRACE_BASE_URL)
+pub fn trace_url(sku_id: &str) -> String {
+    let base = std::env::var("PUBLIC_TRACE_BASE_URL")
+        .unwrap_or_else(|_| "https://oilseed-valuechain.gov.in/trace".to_string());
+    format!("{}/{}", base.trim_end_matches('/'), sku_id)
+}
+
+/// QR code image encoding `data` (QR_IMAGE_BASE_URL is a renderer taking `?data=`)
+pub fn qr_image_url(data: &str) -> String {
+    let base = std::env::var("QR_IMAGE_BASE_URL")
+        .unwrap_or_else(|_| "https://api.qrserver.com/v1/create-qr-code/?size=512x512".to_string());
+    let separator = if base.contains('?') { '&' } else { '?' };
+    let encoded: String = data
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    format!("{}{}data={}", base, separator, encoded)
+}
+
+// ======================== WHATSAPP ========================
+
+/// WhatsApp Business Cloud API client
+#[derive(Debug, Clone)]
+pub struct WhatsAppClient {
+    client: Client,
+    api_url: String,
+    phone_number_id: Option<String>,
+    access_token: Option<String>,
+    country_code: String,
+}
+
+impl WhatsAppClient {
+    pub fn from_env() -> Self {
+        let phone_number_id = std::env::var("WHATSAPP_PHONE_NUMBER_ID")
+            .ok()
+            .filter(|v| !v.is_empty());
+        let access_token = std::env::var("WHATSAPP_ACCESS_TOKEN")
+            .ok()
+            .filter(|v| !v.is_empty());
+        if phone_number_id.is_none() || access_token.is_none() {
+            tracing::warn!("WhatsApp credentials not set, WhatsApp messages will only be logged");
+        }
+
+        Self {
+            client: Client::new(),
+            api_url: std::env::var("WHATSAPP_API_URL")
+                .unwrap_or_else(|_| "https://graph.facebook.com/v19.0".to_string()),
+            phone_number_id,
+            access_token,
+            country_code: std::env::var("WHATSAPP_DEFAULT_COUNTRY_CODE")
+                .unwrap_or_else(|_| "91".to_string()),
+        }
+    }
+
+    /// Build the Cloud API template message body
+    pub fn template_payload(
+        &self,
+        to: &str,
+        notification: &Notification,
+        image_url: Option<&str>,
+        button_path: Option<&str>,
+    ) -> Value {
+        let mut components = Vec::new();
+
+        if let Some(image_url) = image_url {
+            components.push(json!({
+                "type": "header",
+                "parameters": [{ "type": "image", "image": { "link": image_url } }]
+            }));
+        }
+        if !notification.params.is_empty() {
+            let parameters: Vec<Value> = notification
+                .params
+                .iter()
+                .map(|p| json!({ "type": "text", "text": p }))
+                .collect();
+            components.push(json!({ "type": "body", "parameters": parameters }));
+        }
+        if let Some(button_path) = button_path {
+            components.push(json!({
+                "type": "button",
+                "sub_type": "url",
+                "index": "0",
+                "parameters": [{ "type": "text", "text": button_path }]
+            }));
+        }
+
+        json!({
+            "messaging_product": "whatsapp",
+            "to": to,
+            "type": "template",
+            "template": {
+                "name": notification.template,
+                "language": { "code": notification.language },
+                "components": components
+            }
+        })
+    }
+
+    /// Send a template message; returns the provider message ID when delivered
+    pub async fn send_template(
+        &self,
+        notification: &Notification,
+        image_url: Option<&str>,
+        button_path: Option<&str>,
+    ) -> Result<Option<String>> {
+        let to = format!(
+            "{}{}",
+            self.country_code,
+            normalize_mobile(&notification.to)
+        );
+        let payload = self.template_payload(&to, notification, image_url, button_path);
+
+        let (Some(phone_number_id), Some(access_token)) =
+            (&self.phone_number_id, &self.access_token)
+        else {
+            tracing::info!(to = %to, payload = %payload, "WhatsApp dry run");
+            return Ok(None);
+        };
+
+        let response: Value = self
+            .client
+            .post(format!("{}/{}/messages", self.api_url, phone_number_id))
+            .bearer_auth(access_token)
+            .json(&payload)
+            .send()
+            .await
+            .context("Failed to reach WhatsApp API")?
+            .error_for_status()
+            .context("WhatsApp API rejected message")?
+            .json()
+            .await
+            .context("Failed to parse WhatsApp API response")?;
+
+        let message_id = response
+            .pointer("/messages/0/id")
+            .and_then(|v| v.as_str())
+            .map(String::from);
+
+        tracing::info!(to = %to, template = %notification.template, ?message_id, "WhatsApp message sent");
+        Ok(message_id)
+    }
+}
+
+// ======================== EMAIL ========================
+
+/// File attached to an email
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+/// Transactional email provider accepting a JSON `{to, from, subject, text,
+/// attachments}` POST, attachment content base64 encoded
+#[derive(Debug, Clone)]
+pub struct EmailClient {
+    client: Client,
+    endpoint: Option<String>,
+    api_key: Option<String>,
+    from: String,
+}
+
+impl EmailClient {
+    pub fn from_env() -> Self {
+        let endpoint = std::env::var("EMAIL_PROVIDER_URL")
+            .ok()
+            .filter(|v| !v.is_empty());
+        if endpoint.is_none() {
+            tracing::warn!("EMAIL_PROVIDER_URL not set, outbound email will only be logged");
+        }
+
+        Self {
+            client: Client::new(),
+            endpoint,
+            api_key: std::env::var("EMAIL_API_KEY").ok(),
+            from: std::env::var("EMAIL_FROM")
+                .unwrap_or_else(|_| "no-reply@oilseed-valuechain.gov.in".to_string()),
+        }
+    }
+
+    /// Send a plain text email; returns whether it was handed to the provider
+    pub async fn send(
+        &self,
+        to: &str,
+        subject: &str,
+        text: &str,
+        attachments: &[Attachment],
+    ) -> Result<bool> {
+        let Some(endpoint) = &self.endpoint else {
+            let files: Vec<&str> = attachments.iter().map(|a| a.filename.as_str()).collect();
+            tracing::info!(to = %to, subject = %subject, text = %text, ?files, "Email dry run");
+            return Ok(false);
+        };
+
+        let attachments: Vec<Value> = attachments
+            .iter()
+            .map(|a| {
+                json!({
+                    "filename": a.filename,
+                    "content_type": a.content_type,
+                    "content": BASE64.encode(&a.content),
+                })
+            })
+            .collect();
+        let mut request = self.client.post(endpoint).json(&json!({
+            "to": to,
+            "from": self.from,
+            "subject": subject,
+            "text": text,
+            "attachments": attachments,
+        }));
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        request
+            .send()
+            .await
+            .context("Failed to reach email provider")?
+            .error_for_status()
+            .context("Email provider rejected message")?;
+
+        tracing::info!(to = %to, subject = %subject, "Email sent");
+        Ok(true)
+    }
+}
+
+// ======================== DISPATCH ========================
+
+pub struct NotificationService {
+    sms: Arc<SmsClient>,
+    whatsapp: WhatsAppClient,
+    email: EmailClient,
+}
+
+impl NotificationService {
+    pub fn new(sms: Arc<SmsClient>, whatsapp: WhatsAppClient, email: EmailClient) -> Self {
+        Self {
+            sms,
+            whatsapp,
+            email,
+        }
+    }
+
+    /// Email goes to addresses rather than mobiles, so it is used directly by
+    /// auditor invitations and scheduled reports instead of [`Self::send`]
+    pub fn email(&self) -> &EmailClient {
+        &self.email
+    }
+
+    pub async fn send(&self, notification: &Notification) -> Result<DeliveryReport> {
+        let trace = notification.sku_id.as_deref().map(trace_url);
+
+        let (delivered, provider_message_id) = match notification.channel {
+            Channel::Sms => {
+                let mut text = notification.params.join(" ");
+                if let Some(trace) = &trace {
+                    text.push_str(&format!(" Trace: {}", trace));
+                }
+                let text = if text.trim().is_empty() {
+                    notification.template.clone()
+                } else {
+                    text.trim().to_string()
+                };
+                (self.sms.send(&notification.to, &text).await?, None)
+            }
+            Channel::Whatsapp => {
+                let image = notification
+                    .qr_image_url
+                    .clone()
+                    .or_else(|| trace.as_deref().map(qr_image_url));
+                let message_id = self
+                    .whatsapp
+                    .send_template(
+                        notification,
+                        image.as_deref(),
+                        notification.sku_id.as_deref(),
+                    )
+                    .await?;
+                (message_id.is_some(), message_id)
+            }
+        };
+
+        Ok(DeliveryReport {
+            channel: notification.channel,
+            to: notification.to.clone(),
+            delivered,
+            provider_message_id,
+            trace_url: trace,
+        })
+    }
+}
+
+/// Send a single notification (admin only)
+pub async fn send_notification(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<Notification>,
+) -> ApiResult<DeliveryReport> {
+    require_admin(&state, &headers)?;
+
+    if normalize_mobile(&payload.to).len() != 10 {
+        return Err(ApiError::bad_request(format!(
+            "Recipient {} is not a valid mobile number",
+            payload.to
+        )));
+    }
+
+    let report = state
+        .notifications
+        .send(&payload)
+        .await
+        .map_err(|e| ApiError::internal(format!("Notification failed: {:#}", e)))?;
+
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_whatsapp_template_payload() {
+        let client = WhatsAppClient {
+            client: Client::new(),
+            api_url: String::new(),
+            phone_number_id: None,
+            access_token: None,
+            country_code: "91".to_string(),
+        };
+        let notification = Notification {
+            channel: Channel::Whatsapp,
+            to: "9876543210".to_string(),
+            template: "sku_dispatched".to_string(),
+            language: "en".to_string(),
+            params: vec!["SKU-0001".to_string()],
+            sku_id: Some("SKU-0001".to_string()),
+            qr_image_url: None,
+        };
+
+        let payload = client.template_payload(
+            "919876543210",
+            &notification,
+            Some("https://qr.example/img.png"),
+            Some("SKU-0001"),
+        );
+
+        assert_eq!(payload["template"]["name"], "sku_dispatched");
+        let components = payload["template"]["components"].as_array().unwrap();
+        assert_eq!(components.len(), 3);
+        assert_eq!(
+            components[0]["parameters"][0]["image"]["link"],
+            "https://qr.example/img.png"
+        );
+        assert_eq!(components[2]["sub_type"], "url");
+    }
+
+    #[test]
+    fn test_qr_image_url_encodes_data() {
+        let url = qr_image_url("https://x.in/trace/SKU 1");
+        assert!(url.ends_with("data=https%3A%2F%2Fx.in%2Ftrace%2FSKU%201"));
+    }
+}
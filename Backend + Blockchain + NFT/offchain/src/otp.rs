@@ -0,0 +1,430 @@
+//! One-time passcodes proving ownership of a mobile number
+//!
+//! `POST /api/verification/otp/send` texts a 6-digit code through the SMS
+//! provider (see [`crate::sms::SmsProvider`]); `POST /api/verification/otp/verify`
+//! checks it and returns a short-lived proof token. Farmer registration with a
+//! mobile number consumes such a proof when OTP_REQUIRED=true, so a number is
+//! only linked to a farmer DID after its owner has received the code.
+//!
+//! Codes expire after OTP_TTL_SECS (default 5 minutes), allow OTP_MAX_ATTEMPTS
+//! wrong guesses (default 5) and can be resent after OTP_RESEND_SECS (default
+//! 60), also after a lockout. Proofs last OTP_PROOF_TTL_SECS (default 15
+//! minutes) and are single use. Codes and proofs are held in memory only,
+//! keyed with a per-process secret, so a restart invalidates them.
+
+use crate::error::{ApiError, ApiResult};
+use crate::sms::normalize_mobile;
+use crate::state::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+const PROOF_PREFIX: &str = "otp_";
+
+fn env_secs(name: &str, default: i64) -> Duration {
+    let secs = std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default);
+    Duration::seconds(secs.max(1))
+}
+
+/// Why a code was not accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeRejection {
+    Expired,
+    /// Too many wrong guesses; a new code is needed
+    Locked,
+    Mismatch {
+        remaining: u32,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Challenge {
+    code_hash: String,
+    sent_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    failed_attempts: u32,
+}
+
+impl Challenge {
+    fn check(
+        &mut self,
+        code_hash: &str,
+        now: DateTime<Utc>,
+        max_attempts: u32,
+    ) -> Result<(), CodeRejection> {
+        if now >= self.expires_at {
+            return Err(CodeRejection::Expired);
+        }
+        if self.failed_attempts >= max_attempts {
+            return Err(CodeRejection::Locked);
+        }
+        if self.code_hash != code_hash {
+            self.failed_attempts += 1;
+            return Err(match max_attempts - self.failed_attempts {
+                0 => CodeRejection::Locked,
+                remaining => CodeRejection::Mismatch { remaining },
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Proof {
+    mobile: String,
+    expires_at: DateTime<Utc>,
+}
+
+pub struct OtpService {
+    ttl: Duration,
+    resend_after: Duration,
+    proof_ttl: Duration,
+    max_attempts: u32,
+    /// Registration with a mobile number needs a proof token
+    pub required: bool,
+    key: [u8; 32],
+    challenges: Mutex<HashMap<String, Challenge>>,
+    proofs: Mutex<HashMap<String, Proof>>,
+}
+
+impl OtpService {
+    pub fn from_env() -> Self {
+        Self {
+            ttl: env_secs("OTP_TTL_SECS", 300),
+            resend_after: env_secs("OTP_RESEND_SECS", 60),
+            proof_ttl: env_secs("OTP_PROOF_TTL_SECS", 900),
+            max_attempts: std::env::var("OTP_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5u32)
+                .max(1),
+            required: std::env::var("OTP_REQUIRED")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            key: rand::random(),
+            challenges: Mutex::new(HashMap::new()),
+            proofs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn hash(&self, parts: &[&str]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.key);
+        for part in parts {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        hex::encode(hasher.finalize())
+    }
+
+    /// Store a new challenge for `mobile`, unless the previous one, locked or
+    /// not, was sent less than `resend_after` ago
+    async fn open_challenge(
+        &self,
+        mobile: &str,
+        code: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let mut challenges = self.challenges.lock().await;
+        challenges.retain(|_, c| now < c.expires_at || now < c.sent_at + self.resend_after);
+        if let Some(existing) = challenges.get(mobile) {
+            let wait = existing.sent_at + self.resend_after - now;
+            if wait > Duration::zero() {
+                return Err(ApiError::new(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    format!(
+                        "Wait {} seconds before requesting a new code",
+                        wait.num_seconds() + 1
+                    ),
+                ));
+            }
+        }
+        challenges.insert(
+            mobile.to_string(),
+            Challenge {
+                code_hash: self.hash(&[mobile, code]),
+                sent_at: now,
+                expires_at: now + self.ttl,
+                failed_attempts: 0,
+            },
+        );
+        Ok(())
+    }
+
+    /// Check `code` against the pending challenge of `mobile`, consuming it
+    /// on success. Expired and locked challenges stay until they can be
+    /// replaced, so they keep holding back a resend.
+    async fn check_code(
+        &self,
+        mobile: &str,
+        code: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), ApiError> {
+        let mut challenges = self.challenges.lock().await;
+        let challenge = challenges.get_mut(mobile).ok_or_else(|| {
+            ApiError::bad_request("No pending code for this number, request a new one")
+        })?;
+        let code_hash = self.hash(&[mobile, code]);
+        match challenge.check(&code_hash, now, self.max_attempts) {
+            Ok(()) => {
+                challenges.remove(mobile);
+                Ok(())
+            }
+            Err(CodeRejection::Mismatch { remaining }) => {
+                tracing::warn!(mobile = %mask_mobile(mobile), remaining, "Wrong OTP");
+                Err(ApiError::bad_request(format!(
+                    "Incorrect code, {} attempts left",
+                    remaining
+                )))
+            }
+            Err(CodeRejection::Expired) => {
+                Err(ApiError::bad_request("Code expired, request a new one"))
+            }
+            Err(CodeRejection::Locked) => {
+                tracing::warn!(mobile = %mask_mobile(mobile), "OTP locked after too many attempts");
+                Err(ApiError::new(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    "Too many incorrect attempts, request a new code",
+                ))
+            }
+        }
+    }
+
+    /// Consume a proof token issued for `mobile`
+    pub async fn consume_proof(&self, token: &str, mobile: &str) -> bool {
+        let mut proofs = self.proofs.lock().await;
+        let key = self.hash(&[token]);
+        match proofs.get(&key) {
+            Some(proof) if proof.mobile == normalize_mobile(mobile) => {
+                let valid = Utc::now() < proof.expires_at;
+                proofs.remove(&key);
+                valid
+            }
+            _ => false,
+        }
+    }
+}
+
+fn mask_mobile(mobile: &str) -> String {
+    let visible = mobile.len().saturating_sub(4);
+    format!("{}{}", "*".repeat(visible), &mobile[visible..])
+}
+
+fn parse_mobile(raw: &str) -> Result<String, ApiError> {
+    let mobile = normalize_mobile(raw);
+    if mobile.len() != 10 {
+        return Err(ApiError::bad_request("mobile must be a 10-digit number"));
+    }
+    Ok(mobile)
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct SendOtpRequest {
+    pub mobile: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SendOtpResponse {
+    pub mobile: String,
+    pub expires_in_secs: i64,
+    pub resend_after_secs: i64,
+    /// False when the SMS provider is in dry-run mode
+    pub delivered: bool,
+}
+
+pub async fn send_otp(
+    State(state): State<AppState>,
+    Json(payload): Json<SendOtpRequest>,
+) -> ApiResult<SendOtpResponse> {
+    let otp = &state.otp;
+    let mobile = parse_mobile(&payload.mobile)?;
+    let now = Utc::now();
+
+    let code = format!("{:06}", rand::thread_rng().gen_range(0..1_000_000));
+    otp.open_challenge(&mobile, &code, now).await?;
+
+    let message = format!(
+        "{} is your Oilseed Value Chain verification code. It expires in {} minutes. Do not share it.",
+        code,
+        otp.ttl.num_minutes().max(1)
+    );
+    let delivered = match state.sms_client.send(&mobile, &message).await {
+        Ok(delivered) => delivered,
+        Err(e) => {
+            otp.challenges.lock().await.remove(&mobile);
+            tracing::error!(mobile = %mask_mobile(&mobile), error = %format!("{:#}", e), "OTP delivery failed");
+            return Err(ApiError::new(
+                StatusCode::BAD_GATEWAY,
+                "Could not send the verification code, try again later",
+            ));
+        }
+    };
+
+    tracing::info!(mobile = %mask_mobile(&mobile), delivered, "OTP sent");
+    Ok(Json(SendOtpResponse {
+        mobile: mask_mobile(&mobile),
+        expires_in_secs: otp.ttl.num_seconds(),
+        resend_after_secs: otp.resend_after.num_seconds(),
+        delivered,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyOtpRequest {
+    pub mobile: String,
+    pub code: String,
+    /// DID the caller wants to link; checked against the farmer database
+    #[serde(default)]
+    pub farmer_did: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyOtpResponse {
+    pub verified: bool,
+    /// Farmer registered with this number, if any
+    pub farmer_did: Option<String>,
+    /// Whether `farmer_did` from the request matches the registered farmer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub did_matches: Option<bool>,
+    /// Single-use proof of ownership for farmer registration
+    pub proof_token: String,
+    pub proof_expires_at: String,
+}
+
+pub async fn verify_otp(
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyOtpRequest>,
+) -> ApiResult<VerifyOtpResponse> {
+    let otp = &state.otp;
+    let mobile = parse_mobile(&payload.mobile)?;
+    let now = Utc::now();
+
+    otp.check_code(&mobile, payload.code.trim(), now).await?;
+
+    let farmer_did = state
+        .farmer_verification
+        .get_farmer_by_mobile(&mobile)
+        .await?
+        .map(|f| f.farmer_did);
+    let did_matches = payload
+        .farmer_did
+        .as_ref()
+        .map(|did| farmer_did.as_ref() == Some(did));
+
+    let proof_token = format!(
+        "{}{}",
+        PROOF_PREFIX,
+        hex::encode(rand::random::<[u8; 24]>())
+    );
+    let proof_expires_at = now + otp.proof_ttl;
+    {
+        let mut proofs = otp.proofs.lock().await;
+        proofs.retain(|_, p| now < p.expires_at);
+        proofs.insert(
+            otp.hash(&[&proof_token]),
+            Proof {
+                mobile: mobile.clone(),
+                expires_at: proof_expires_at,
+            },
+        );
+    }
+
+    tracing::info!(mobile = %mask_mobile(&mobile), ?did_matches, "Mobile ownership verified by OTP");
+    Ok(Json(VerifyOtpResponse {
+        verified: true,
+        farmer_did,
+        did_matches,
+        proof_token,
+        proof_expires_at: proof_expires_at.to_rfc3339(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn challenge(now: DateTime<Utc>) -> Challenge {
+        Challenge {
+            code_hash: "right".to_string(),
+            sent_at: now,
+            expires_at: now + Duration::minutes(5),
+            failed_attempts: 0,
+        }
+    }
+
+    #[test]
+    fn test_attempt_limit_locks_challenge() {
+        let now = Utc::now();
+        let mut c = challenge(now);
+        assert_eq!(
+            c.check("wrong", now, 3),
+            Err(CodeRejection::Mismatch { remaining: 2 })
+        );
+        assert_eq!(
+            c.check("wrong", now, 3),
+            Err(CodeRejection::Mismatch { remaining: 1 })
+        );
+        assert_eq!(c.check("wrong", now, 3), Err(CodeRejection::Locked));
+        // Even the right code is refused once locked
+        assert_eq!(c.check("right", now, 3), Err(CodeRejection::Locked));
+    }
+
+    #[test]
+    fn test_code_expires() {
+        let now = Utc::now();
+        let mut c = challenge(now);
+        assert_eq!(c.check("right", now, 5), Ok(()));
+        assert_eq!(
+            c.check("right", now + Duration::minutes(6), 5),
+            Err(CodeRejection::Expired)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lockout_does_not_skip_resend_cooldown() {
+        let otp = OtpService {
+            ttl: Duration::minutes(5),
+            resend_after: Duration::seconds(60),
+            proof_ttl: Duration::minutes(15),
+            max_attempts: 2,
+            required: false,
+            key: [7; 32],
+            challenges: Mutex::new(HashMap::new()),
+            proofs: Mutex::new(HashMap::new()),
+        };
+        let now = Utc::now();
+        otp.open_challenge("9876543210", "123456", now)
+            .await
+            .unwrap();
+        assert!(otp.check_code("9876543210", "000000", now).await.is_err());
+        let locked = otp.check_code("9876543210", "000000", now).await;
+        assert_eq!(locked.unwrap_err().status, StatusCode::TOO_MANY_REQUESTS);
+
+        let resend = otp
+            .open_challenge("9876543210", "654321", now + Duration::seconds(5))
+            .await;
+        assert_eq!(resend.unwrap_err().status, StatusCode::TOO_MANY_REQUESTS);
+        let locked = otp.check_code("9876543210", "123456", now).await;
+        assert_eq!(locked.unwrap_err().status, StatusCode::TOO_MANY_REQUESTS);
+
+        let later = now + Duration::seconds(61);
+        otp.open_challenge("9876543210", "654321", later)
+            .await
+            .unwrap();
+        assert!(otp.check_code("9876543210", "654321", later).await.is_ok());
+    }
+
+    #[test]
+    fn test_mask_mobile() {
+        assert_eq!(mask_mobile("9876543210"), "******3210");
+    }
+}
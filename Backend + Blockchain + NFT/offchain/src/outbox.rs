@@ -0,0 +1,471 @@
+//! Outbox for failed chain writes
+//!
+//! Handlers pin metadata to IPFS before the contract call, so a write that
+//! fails after the transaction queue's own retries (see [`crate::tx_queue`])
+//! would leave the CID pinned but never recorded on-chain. Such writes are
+//! saved with their calldata in `data/outbox.db`, and a background worker
+//! sends them again every OUTBOX_RETRY_SECS (default 60), backing off per
+//! entry from one minute up to six hours. After OUTBOX_MAX_ATTEMPTS (default
+//! 8) an entry is abandoned until an admin requeues it:
+//!
+//! - `GET /api/admin/outbox?status=pending` - entries, newest first
+//! - `POST /api/admin/outbox/:id/requeue` - retry an entry now
+//!
+//! The handler answers 202 with code `write_queued` and the entry id (see
+//! [`Queued`]); a write the outbox completes reaches the local views through
+//! the event indexer. Writes made while the RPC has been unreachable since
+//! the start are saved here without being sent first.
+
+use crate::admin::require_admin;
+use crate::chain::ChainClient;
+use crate::error::{format_tx_hash, ApiError, ApiResult};
+use crate::pagination::{decode_cursor, encode_cursor, Page, PageParams};
+use crate::state::AppState;
+use alloy::{
+    network::TransactionBuilder,
+    primitives::{Address, Bytes},
+    rpc::types::TransactionRequest,
+};
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use std::time::Duration;
+
+/// SQLite database holding the default network's outbox
+pub const DB_PATH: &str = "data/outbox.db";
+const DEFAULT_RETRY_SECS: u64 = 60;
+const DEFAULT_MAX_ATTEMPTS: i64 = 8;
+const FIRST_RETRY_SECS: i64 = 60;
+const MAX_RETRY_SECS: i64 = 6 * 3600;
+/// Entries sent per worker pass
+const BATCH_SIZE: i64 = 20;
+const CURSOR_SCOPE: &str = "outbox";
+
+pub const PENDING: &str = "pending";
+pub const DONE: &str = "done";
+pub const ABANDONED: &str = "abandoned";
+
+/// A write saved to the outbox instead of completing
+#[derive(Debug)]
+pub struct Queued {
+    pub label: String,
+    pub id: i64,
+    pub reason: String,
+}
+
+impl std::fmt::Display for Queued {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} transaction could not be sent ({}); saved as outbox entry {} and retried in the background",
+            self.label, self.reason, self.id
+        )
+    }
+}
+
+impl std::error::Error for Queued {}
+
+/// Seconds to wait after the `attempts`-th failed attempt
+fn retry_delay(attempts: i64) -> i64 {
+    let doublings = (attempts - 1).clamp(0, 20) as u32;
+    (FIRST_RETRY_SECS << doublings).min(MAX_RETRY_SECS)
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub label: String,
+    pub to_address: Option<String>,
+    pub calldata: String,
+    pub status: String,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    /// Unix seconds
+    pub next_attempt_at: i64,
+    pub tx_hash: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl OutboxEntry {
+    /// Transaction to send again; nonce and fees are set by the queue
+    pub fn request(&self) -> Result<TransactionRequest> {
+        let input: Bytes = self
+            .calldata
+            .parse()
+            .with_context(|| format!("Outbox entry {}: invalid calldata", self.id))?;
+        let mut request = TransactionRequest::default().with_input(input);
+        if let Some(to) = &self.to_address {
+            let to: Address = to
+                .parse()
+                .with_context(|| format!("Outbox entry {}: invalid address", self.id))?;
+            request = request.with_to(to);
+        }
+        Ok(request)
+    }
+}
+
+pub struct Outbox {
+    pool: SqlitePool,
+}
+
+impl Outbox {
+    /// Open the outbox database at `path` and apply migrations
+    pub async fn open(path: &str) -> Result<Self> {
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .busy_timeout(Duration::from_secs(5));
+        Self::connect(options).await
+    }
+
+    pub async fn connect(options: SqliteConnectOptions) -> Result<Self> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .context("Failed to open outbox")?;
+        sqlx::migrate!("./migrations/outbox")
+            .run(&pool)
+            .await
+            .context("Failed to migrate outbox")?;
+        Ok(Self { pool })
+    }
+
+    /// Save a write that failed with `error`; returns the entry ID
+    pub async fn record(
+        &self,
+        label: &str,
+        request: &TransactionRequest,
+        error: &str,
+    ) -> Result<i64> {
+        let now = Utc::now();
+        let calldata = TransactionBuilder::input(request)
+            .cloned()
+            .unwrap_or_default();
+        let id = sqlx::query_scalar(
+            "INSERT INTO outbox (label, to_address, calldata, status, attempts, last_error, \
+             next_attempt_at, created_at, updated_at) \
+             VALUES ($1, $2, $3, $4, 1, $5, $6, $7, $7) RETURNING id",
+        )
+        .bind(label)
+        .bind(TransactionBuilder::to(request).map(|to| format!("{:?}", to)))
+        .bind(calldata.to_string())
+        .bind(PENDING)
+        .bind(error)
+        .bind(now.timestamp() + retry_delay(1))
+        .bind(now.to_rfc3339())
+        .fetch_one(&self.pool)
+        .await
+        .context("Failed to record outbox entry")?;
+        Ok(id)
+    }
+
+    /// Pending entries whose next attempt is due, oldest first
+    pub async fn due(&self, now: i64) -> Result<Vec<OutboxEntry>> {
+        Ok(sqlx::query_as(
+            "SELECT * FROM outbox WHERE status = $1 AND next_attempt_at <= $2 \
+             ORDER BY id LIMIT $3",
+        )
+        .bind(PENDING)
+        .bind(now)
+        .bind(BATCH_SIZE)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    /// Entries newest first, starting below `before` when given
+    pub async fn list(
+        &self,
+        status: Option<&str>,
+        before: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<OutboxEntry>> {
+        Ok(sqlx::query_as(
+            "SELECT * FROM outbox WHERE ($1 IS NULL OR status = $1) \
+             AND ($2 IS NULL OR id < $2) ORDER BY id DESC LIMIT $3",
+        )
+        .bind(status)
+        .bind(before)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    pub async fn mark_done(&self, id: i64, tx_hash: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE outbox SET status = $1, tx_hash = $2, attempts = attempts + 1, \
+             updated_at = $3 WHERE id = $4",
+        )
+        .bind(DONE)
+        .bind(tx_hash)
+        .bind(Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Count a failed attempt, abandoning the entry after `max_attempts`
+    pub async fn mark_failed(
+        &self,
+        entry: &OutboxEntry,
+        error: &str,
+        max_attempts: i64,
+    ) -> Result<()> {
+        let now = Utc::now();
+        let attempts = entry.attempts + 1;
+        let status = if attempts >= max_attempts {
+            ABANDONED
+        } else {
+            PENDING
+        };
+        sqlx::query(
+            "UPDATE outbox SET status = $1, attempts = $2, last_error = $3, \
+             next_attempt_at = $4, updated_at = $5 WHERE id = $6",
+        )
+        .bind(status)
+        .bind(attempts)
+        .bind(error)
+        .bind(now.timestamp() + retry_delay(attempts))
+        .bind(now.to_rfc3339())
+        .bind(entry.id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Make an entry that has not been sent due now with a fresh attempt
+    /// count; false if there is no such entry
+    pub async fn requeue(&self, id: i64) -> Result<bool> {
+        let now = Utc::now();
+        let updated = sqlx::query(
+            "UPDATE outbox SET status = $1, attempts = 0, next_attempt_at = $2, \
+             updated_at = $3 WHERE id = $4 AND status != $5",
+        )
+        .bind(PENDING)
+        .bind(now.timestamp())
+        .bind(now.to_rfc3339())
+        .bind(id)
+        .bind(DONE)
+        .execute(&self.pool)
+        .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+}
+
+// ======================== WORKER ========================
+
+fn max_attempts() -> i64 {
+    std::env::var("OUTBOX_MAX_ATTEMPTS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_ATTEMPTS)
+        .max(1)
+}
+
+/// Send every due entry of every network once; returns how many were mined
+pub async fn run_once(state: &AppState) -> Result<usize> {
+    let mut mined = 0;
+    for chain in state.networks.clients() {
+        // One unreachable network must not hold up the others
+        match run_network(&chain).await {
+            Ok(count) => mined += count,
+            Err(e) => {
+                tracing::error!(network = %chain.network(), error = %format!("{:#}", e), "Outbox pass failed")
+            }
+        }
+    }
+    Ok(mined)
+}
+
+async fn run_network(chain: &ChainClient) -> Result<usize> {
+    let outbox = chain.outbox();
+    let max_attempts = max_attempts();
+    let mut mined = 0;
+
+    for entry in outbox.due(Utc::now().timestamp()).await? {
+        let sent = match entry.request() {
+            Ok(request) => chain.tx_queue().submit(&entry.label, request).await,
+            Err(e) => Err(e),
+        };
+        match sent {
+            Ok(receipt) => {
+                let tx_hash = format_tx_hash(receipt.transaction_hash);
+                tracing::info!(id = entry.id, label = %entry.label, tx_hash = %tx_hash, "Outbox entry written on-chain");
+                outbox.mark_done(entry.id, &tx_hash).await?;
+                mined += 1;
+            }
+            Err(e) => {
+                let error = format!("{:#}", e);
+                tracing::warn!(id = entry.id, label = %entry.label, attempts = entry.attempts + 1, error = %error, "Outbox entry failed again");
+                outbox.mark_failed(&entry, &error, max_attempts).await?;
+            }
+        }
+    }
+
+    Ok(mined)
+}
+
+pub fn spawn(state: AppState) {
+    let secs = std::env::var("OUTBOX_RETRY_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_RETRY_SECS);
+    let interval = Duration::from_secs(secs.max(10));
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = run_once(&state).await {
+                tracing::error!(error = %format!("{:#}", e), "Outbox pass failed");
+            }
+        }
+    });
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct OutboxParams {
+    pub status: Option<String>,
+}
+
+/// Outbox entries, newest first (admin only)
+pub async fn list_entries(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<OutboxParams>,
+    Query(page): Query<PageParams>,
+) -> ApiResult<Page<OutboxEntry>> {
+    require_admin(&state, &headers)?;
+    if let Some(status) = params.status.as_deref() {
+        if ![PENDING, DONE, ABANDONED].contains(&status) {
+            return Err(ApiError::bad_request(
+                "status must be pending, done or abandoned",
+            ));
+        }
+    }
+
+    let limit = page.limit();
+    let before: Option<i64> = page
+        .cursor
+        .as_deref()
+        .map(|cursor| decode_cursor(CURSOR_SCOPE, cursor))
+        .transpose()?;
+
+    let mut items = state
+        .networks
+        .select(&headers)?
+        .outbox()
+        .list(params.status.as_deref(), before, limit as i64 + 1)
+        .await
+        .map_err(ApiError::from)?;
+    let has_more = items.len() > limit;
+    items.truncate(limit);
+
+    let next_cursor = match (has_more, items.last()) {
+        (true, Some(last)) => Some(encode_cursor(CURSOR_SCOPE, &last.id)?),
+        _ => None,
+    };
+
+    Ok(Json(Page {
+        items,
+        next_cursor,
+        has_more,
+        limit,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct RequeueResponse {
+    pub id: i64,
+    pub status: &'static str,
+}
+
+/// Retry a pending or abandoned entry on the next worker pass (admin only)
+pub async fn requeue_entry(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> ApiResult<RequeueResponse> {
+    require_admin(&state, &headers)?;
+
+    let requeued = state
+        .networks
+        .select(&headers)?
+        .outbox()
+        .requeue(id)
+        .await
+        .map_err(ApiError::from)?;
+    if !requeued {
+        return Err(ApiError::not_found(format!(
+            "No outbox entry {} awaiting a retry",
+            id
+        )));
+    }
+    tracing::info!(id, "Outbox entry requeued");
+
+    Ok(Json(RequeueResponse {
+        id,
+        status: PENDING,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_cap() {
+        assert_eq!(retry_delay(1), 60);
+        assert_eq!(retry_delay(3), 240);
+        assert_eq!(retry_delay(50), MAX_RETRY_SECS);
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_round_trip() {
+        let options = "sqlite::memory:".parse::<SqliteConnectOptions>().unwrap();
+        let outbox = Outbox::connect(options).await.unwrap();
+
+        let to = Address::repeat_byte(0x11);
+        let request = TransactionRequest::default()
+            .with_to(to)
+            .with_input(Bytes::from(vec![0xde, 0xad]));
+        let id = outbox
+            .record("fpoPurchase", &request, "timeout")
+            .await
+            .unwrap();
+
+        // Not due until the first retry delay has passed
+        let now = Utc::now().timestamp();
+        assert!(outbox.due(now).await.unwrap().is_empty());
+        let due = outbox.due(now + retry_delay(1)).await.unwrap();
+        assert_eq!(due.len(), 1);
+        let resent = due[0].request().unwrap();
+        assert_eq!(TransactionBuilder::to(&resent), Some(to));
+        assert_eq!(
+            TransactionBuilder::input(&resent).unwrap().to_vec(),
+            vec![0xde, 0xad]
+        );
+
+        outbox.mark_failed(&due[0], "timeout", 2).await.unwrap();
+        let entry = &outbox.list(None, None, 10).await.unwrap()[0];
+        assert_eq!((entry.status.as_str(), entry.attempts), (ABANDONED, 2));
+
+        assert!(outbox.requeue(id).await.unwrap());
+        assert_eq!(outbox.due(now).await.unwrap().len(), 1);
+        outbox.mark_done(id, "0xabc").await.unwrap();
+        assert!(!outbox.requeue(id).await.unwrap());
+    }
+}
@@ -0,0 +1,181 @@
+//! Cursor-based pagination shared by all list endpoints
+//!
+//! List endpoints accept `?cursor=<opaque>&limit=<n>` and return a [`Page`].
+//! Items are ordered by a unique, immutable sort key chosen by the endpoint;
+//! the cursor encodes the key of the last item returned, so a page boundary
+//! stays correct when new items are inserted concurrently (unlike offsets).
+//!
+//! Cursors are bound to the endpoint's scope and rejected elsewhere.
+//!
+//! A few lists are returned whole on purpose: admin-maintained configuration
+//! that stays small (networks, experiments, hold rules, report schedules,
+//! schemes, auditor accounts) and per-farmer histories that are already
+//! scoped to one record (settlements, profile changes).
+
+use crate::error::ApiError;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+pub const MAX_PAGE_SIZE: usize = 200;
+
+const CURSOR_VERSION: u8 = 1;
+
+/// Query parameters accepted by every paginated endpoint
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PageParams {
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl PageParams {
+    /// Requested page size clamped to `1..=MAX_PAGE_SIZE`
+    pub fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+}
+
+/// One page of results
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+    pub limit: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CursorPayload<K> {
+    v: u8,
+    s: String,
+    k: K,
+}
+
+/// Encode the sort key of the last item on a page as an opaque cursor
+pub fn encode_cursor<K: Serialize>(scope: &str, key: &K) -> Result<String, ApiError> {
+    let payload = CursorPayload {
+        v: CURSOR_VERSION,
+        s: scope.to_string(),
+        k: key,
+    };
+    let json = serde_json::to_vec(&payload).map_err(ApiError::json_failed)?;
+    Ok(hex::encode(json))
+}
+
+/// Decode a cursor produced by [`encode_cursor`] for the same scope
+pub fn decode_cursor<K: DeserializeOwned>(scope: &str, cursor: &str) -> Result<K, ApiError> {
+    let invalid = || ApiError::bad_request("Invalid or expired pagination cursor");
+
+    let bytes = hex::decode(cursor).map_err(|_| invalid())?;
+    let payload: CursorPayload<K> = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+
+    if payload.v != CURSOR_VERSION || payload.s != scope {
+        return Err(invalid());
+    }
+
+    Ok(payload.k)
+}
+
+/// Sort `items` by `key` and return the page following `params.cursor`
+pub fn paginate<T, K, F>(
+    scope: &str,
+    mut items: Vec<T>,
+    params: &PageParams,
+    key: F,
+) -> Result<Page<T>, ApiError>
+where
+    K: Ord + Serialize + DeserializeOwned,
+    F: Fn(&T) -> K,
+{
+    let limit = params.limit();
+    let after: Option<K> = params
+        .cursor
+        .as_deref()
+        .map(|cursor| decode_cursor(scope, cursor))
+        .transpose()?;
+
+    items.sort_by_key(|item| key(item));
+
+    let mut page: Vec<T> = items
+        .into_iter()
+        .filter(|item| after.as_ref().is_none_or(|after| key(item) > *after))
+        .take(limit + 1)
+        .collect();
+
+    let has_more = page.len() > limit;
+    page.truncate(limit);
+
+    let next_cursor = match (has_more, page.last()) {
+        (true, Some(last)) => Some(encode_cursor(scope, &key(last))?),
+        _ => None,
+    };
+
+    Ok(Page {
+        items: page,
+        next_cursor,
+        has_more,
+        limit,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(cursor: Option<String>, limit: usize) -> PageParams {
+        PageParams {
+            cursor,
+            limit: Some(limit),
+        }
+    }
+
+    #[test]
+    fn test_walks_all_pages_in_key_order() {
+        let items = vec![5u64, 1, 4, 2, 3];
+
+        let first = paginate("test", items.clone(), &params(None, 2), |i| *i).unwrap();
+        assert_eq!(first.items, vec![1, 2]);
+        assert!(first.has_more);
+
+        let second =
+            paginate("test", items.clone(), &params(first.next_cursor, 2), |i| *i).unwrap();
+        assert_eq!(second.items, vec![3, 4]);
+
+        let third = paginate("test", items, &params(second.next_cursor, 2), |i| *i).unwrap();
+        assert_eq!(third.items, vec![5]);
+        assert!(!third.has_more);
+        assert!(third.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_cursor_survives_concurrent_insert() {
+        let first = paginate("test", vec![10u64, 20, 30], &params(None, 2), |i| *i).unwrap();
+
+        // An item inserted before the cursor must not shift the next page
+        let second = paginate(
+            "test",
+            vec![5u64, 10, 20, 30],
+            &params(first.next_cursor, 2),
+            |i| *i,
+        )
+        .unwrap();
+        assert_eq!(second.items, vec![30]);
+    }
+
+    #[test]
+    fn test_cursor_rejected_for_other_scope() {
+        let cursor = encode_cursor("farmers", &"abc").unwrap();
+        assert!(decode_cursor::<String>("events", &cursor).is_err());
+        assert!(decode_cursor::<String>("farmers", "not-hex").is_err());
+    }
+
+    #[test]
+    fn test_limit_is_capped() {
+        assert_eq!(params(None, 10_000).limit(), MAX_PAGE_SIZE);
+        assert_eq!(params(None, 0).limit(), 1);
+        assert_eq!(PageParams::default().limit(), DEFAULT_PAGE_SIZE);
+    }
+}
@@ -0,0 +1,78 @@
+//! Perceptual hashes of evidence photos
+//!
+//! A 64-bit difference hash (dHash): the image is reduced to 9x8 grayscale
+//! and each bit records whether a pixel is brighter than its right-hand
+//! neighbour. Re-encoding, resizing, light crops and brightness changes move
+//! only a few bits, so a reused photo stays within a small Hamming distance
+//! of the original while its byte hash changes completely.
+
+use image::{imageops::FilterType, DynamicImage};
+
+/// dHash of a decoded image
+pub fn dhash(image: &DynamicImage) -> u64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+/// Number of differing bits
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+pub fn to_hex(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+pub fn from_hex(hex: &str) -> Option<u64> {
+    u64::from_str_radix(hex, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Rgb, RgbImage};
+
+    fn encode(image: &RgbImage, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut bytes, format).unwrap();
+        bytes.into_inner()
+    }
+
+    fn decoded_dhash(bytes: &[u8]) -> u64 {
+        dhash(&image::load_from_memory(bytes).unwrap())
+    }
+
+    fn scene(width: u32, height: u32, shift: u8) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| {
+            let v = ((x * 255 / width) as u8).wrapping_mul(3) ^ ((y * 255 / height) as u8);
+            Rgb([v.saturating_add(shift), v / 2, 255 - v])
+        })
+    }
+
+    #[test]
+    fn test_reencoded_photo_stays_close() {
+        let original = decoded_dhash(&encode(&scene(320, 240, 0), ImageFormat::Png));
+        // Resized, brightened and re-encoded as JPEG
+        let reused = decoded_dhash(&encode(&scene(640, 480, 12), ImageFormat::Jpeg));
+        assert!(distance(original, reused) <= 6);
+
+        let other = RgbImage::from_fn(320, 240, |x, y| {
+            let v = ((x + 2 * y) % 97) as u8 * 2;
+            Rgb([v, v, v])
+        });
+        let unrelated = decoded_dhash(&encode(&other, ImageFormat::Png));
+        assert!(distance(original, unrelated) > 10);
+
+        assert_eq!(from_hex(&to_hex(original)), Some(original));
+    }
+}
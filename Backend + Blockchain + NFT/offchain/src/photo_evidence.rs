@@ -0,0 +1,999 @@
+//! Device-attested evidence photos
+//!
+//! Procurement and fraud photos are uploaded with the capture metadata the
+//! device recorded: capture time, GPS fix, device ID and, where the capture
+//! app supports it, an on-device signature. The image is pinned to IPFS next
+//! to an attestation document, and purchases and fraud reports reference it
+//! by image CID (`photos`), which puts the image hash into the metadata whose
+//! hash goes on-chain. A photo swapped later no longer matches that hash.
+//!
+//! Signing devices are enrolled with a secp256k1 key in `data/device_keys.json`
+//! (override with DEVICE_KEYS_PATH), `{ "tab-017": "0x<address>" }`. They
+//! sign [`attestation_message`] with EIP-191 (`personal_sign`). A signature
+//! from an unenrolled device, or one that does not recover to the enrolled
+//! address, is rejected; unsigned photos are accepted as `unsigned` unless
+//! PHOTO_REQUIRE_SIGNATURE is set.
+//!
+//! Capture times must lie within PHOTO_MAX_AGE_HOURS (default 72) of the
+//! upload, and an image can only be submitted once. Procurement photos are
+//! uploaded by FPOs (or field agents delegated to purchase) and acceptance
+//! photos by processors; only fraud photos, which come with consumer
+//! reports, can be uploaded without signing in. Records are kept in
+//! `data/photo_evidence.json`.
+//!
+//! Every image is also indexed by its perceptual hash (see
+//! [`crate::perceptual_hash`]). An upload within PHOTO_SIMILARITY_DISTANCE
+//! bits (default 6) of a photo filed for another batch or SKU, or for the
+//! other purpose, is accepted but flagged with `similar_to`; flagged photos
+//! are listed at `GET /api/admin/evidence/similar`.
+//!
+//! High-resolution images go to object storage when it is configured (see
+//! [`crate::object_storage`]); `image_cid` is then their pointer's CID.
+//!
+//! The published copy has its EXIF stripped and comes with a thumbnail (see
+//! [`crate::image_metadata`]); `image_hash` stays the hash of the upload,
+//! which the device attested, and `published_hash` is that of the copy.
+//! EXIF capture time and GPS are kept in `exif` and checked against the
+//! attestation: a photo taken more than PHOTO_EXIF_MAX_SKEW_MINUTES (default
+//! 30) apart from it, or more than PHOTO_EXIF_MAX_DISTANCE_M (default 1000)
+//! away, is accepted but flagged with `exif_mismatches`. EXIF times without
+//! an offset are read in the attestation's offset.
+//! `GET /api/evidence/photos` searches photos by purpose, record, capture
+//! time, position and camera.
+
+use crate::admin::require_admin;
+use crate::auth::{Principal, Role};
+use crate::chain::hash_bytes;
+use crate::delegation::Scope;
+use crate::error::{format_hash, ipfs_gateway_url, ApiError, ApiResult};
+use crate::image_metadata::{self, ExifSummary};
+use crate::object_storage;
+use crate::pagination::{paginate, Page, PageParams};
+use crate::perceptual_hash;
+use crate::state::AppState;
+use alloy::primitives::{Address, PrimitiveSignature};
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Extension, Json,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+const PHOTOS_PATH: &str = "data/photo_evidence.json";
+const DEFAULT_DEVICE_KEYS_PATH: &str = "data/device_keys.json";
+const DEFAULT_MAX_AGE_HOURS: i64 = 72;
+const DEFAULT_EXIF_MAX_SKEW_MINUTES: i64 = 30;
+const DEFAULT_EXIF_MAX_DISTANCE_M: f64 = 1000.0;
+const EARTH_RADIUS_M: f64 = 6_371_000.0;
+/// Device clocks may run slightly ahead of the server
+const CLOCK_SKEW_MINUTES: i64 = 5;
+/// Largest decoded image accepted
+pub const MAX_PHOTO_BYTES: usize = 8 * 1024 * 1024;
+/// Photos referenced by one purchase or fraud report
+const MAX_PHOTOS_PER_RECORD: usize = 10;
+const DEFAULT_SIMILARITY_DISTANCE: u32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhotoPurpose {
+    /// Referenced by an FPO purchase; `reference` is the batch ID
+    Procurement,
+    /// Referenced by a fraud report; `reference` is the SKU ID
+    Fraud,
+    /// Referenced by a processor's lot acceptance; `reference` is the batch ID
+    Acceptance,
+}
+
+impl PhotoPurpose {
+    /// Whether `principal` may file photos for this purpose
+    fn check_uploader(self, principal: Option<&Principal>) -> Result<(), ApiError> {
+        let roles: &'static [Role] = match self {
+            PhotoPurpose::Fraud => return Ok(()),
+            PhotoPurpose::Procurement => &[Role::Fpo],
+            PhotoPurpose::Acceptance => &[Role::Processor],
+        };
+        match principal {
+            Some(p) if p.admits(roles) => Ok(()),
+            Some(p) if self == PhotoPurpose::Procurement && p.holds_scope(Scope::FpoPurchase) => {
+                Ok(())
+            }
+            Some(_) => Err(ApiError::forbidden(format!(
+                "Requires role {} to upload {:?} photos",
+                roles[0], self
+            ))),
+            None => Err(ApiError::unauthorized(
+                "Sign in to upload procurement or acceptance photos",
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureAttestation {
+    /// RFC 3339 capture time from the device clock
+    pub captured_at: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accuracy_m: Option<f64>,
+    pub device_id: String,
+    /// keccak256 of the image computed on the device
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image_hash: Option<String>,
+    /// EIP-191 signature of [`attestation_message`] by the device key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttestationLevel {
+    /// Signed on an enrolled device
+    Signed,
+    /// Capture metadata only, as reported by the client
+    Unsigned,
+}
+
+/// Text signed on the device: coordinates with six decimals
+pub fn attestation_message(image_hash: &str, attestation: &CaptureAttestation) -> String {
+    format!(
+        "oilseed-photo/v1\nimage:{}\ncaptured_at:{}\nlat:{:.6}\nlon:{:.6}\ndevice:{}",
+        image_hash.to_lowercase(),
+        attestation.captured_at,
+        attestation.latitude,
+        attestation.longitude,
+        attestation.device_id
+    )
+}
+
+#[derive(Debug, Clone)]
+struct PhotoPolicy {
+    require_signature: bool,
+    max_age: Duration,
+    similarity_distance: u32,
+    exif_max_skew: Duration,
+    exif_max_distance_m: f64,
+}
+
+/// Great-circle distance in metres
+fn distance_m((lat_a, lon_a): (f64, f64), (lat_b, lon_b): (f64, f64)) -> f64 {
+    let (lat_a, lat_b) = (lat_a.to_radians(), lat_b.to_radians());
+    let dlat = lat_b - lat_a;
+    let dlon = (lon_b - lon_a).to_radians();
+    let h = (dlat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_M * h.sqrt().asin()
+}
+
+/// Where the EXIF of a photo contradicts its capture attestation
+fn exif_mismatches(
+    exif: &ExifSummary,
+    attestation: &CaptureAttestation,
+    policy: &PhotoPolicy,
+) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    let attested = DateTime::parse_from_rfc3339(&attestation.captured_at).ok();
+    let exif_time = exif.captured_at.as_deref().and_then(|t| {
+        DateTime::parse_from_rfc3339(t).ok().or_else(|| {
+            let local = NaiveDateTime::parse_from_str(t, "%Y-%m-%dT%H:%M:%S").ok()?;
+            local.and_local_timezone(*attested?.offset()).single()
+        })
+    });
+    if let (Some(exif_time), Some(attested)) = (exif_time, attested) {
+        let skew = (exif_time - attested).abs();
+        if skew > policy.exif_max_skew {
+            mismatches.push(format!(
+                "EXIF capture time is {} minutes from captured_at",
+                skew.num_minutes()
+            ));
+        }
+    }
+    if let (Some(latitude), Some(longitude)) = (exif.latitude, exif.longitude) {
+        let distance = distance_m(
+            (latitude, longitude),
+            (attestation.latitude, attestation.longitude),
+        );
+        if distance > policy.exif_max_distance_m {
+            mismatches.push(format!(
+                "EXIF position is {:.0} m from the attested position",
+                distance
+            ));
+        }
+    }
+    mismatches
+}
+
+/// Check capture metadata against the received image
+fn verify_attestation(
+    attestation: &CaptureAttestation,
+    image_hash: &str,
+    enrolled: Option<Address>,
+    policy: &PhotoPolicy,
+    now: DateTime<Utc>,
+) -> Result<AttestationLevel, String> {
+    if attestation.device_id.trim().is_empty() {
+        return Err("device_id is required".to_string());
+    }
+    if !(-90.0..=90.0).contains(&attestation.latitude)
+        || !(-180.0..=180.0).contains(&attestation.longitude)
+    {
+        return Err("GPS coordinates are out of range".to_string());
+    }
+
+    let captured_at = DateTime::parse_from_rfc3339(&attestation.captured_at)
+        .map_err(|_| "captured_at must be an RFC 3339 timestamp".to_string())?
+        .with_timezone(&Utc);
+    if captured_at > now + Duration::minutes(CLOCK_SKEW_MINUTES) {
+        return Err("captured_at is in the future".to_string());
+    }
+    if now - captured_at > policy.max_age {
+        return Err(format!(
+            "Photo was captured more than {} hours ago",
+            policy.max_age.num_hours()
+        ));
+    }
+
+    if let Some(device_hash) = &attestation.image_hash {
+        if !device_hash.eq_ignore_ascii_case(image_hash) {
+            return Err("Image does not match the hash computed on the device".to_string());
+        }
+    }
+
+    let Some(signature) = &attestation.signature else {
+        if policy.require_signature {
+            return Err("A device signature is required".to_string());
+        }
+        return Ok(AttestationLevel::Unsigned);
+    };
+    let Some(enrolled) = enrolled else {
+        return Err(format!(
+            "Device {} is not enrolled for signed capture",
+            attestation.device_id
+        ));
+    };
+    let signer = signature
+        .parse::<PrimitiveSignature>()
+        .map_err(|_| "signature is not a valid 65-byte hex signature".to_string())?
+        .recover_address_from_msg(attestation_message(image_hash, attestation))
+        .map_err(|_| "signature could not be recovered".to_string())?;
+    if signer != enrolled {
+        return Err(format!(
+            "signature was not made by the key enrolled for device {}",
+            attestation.device_id
+        ));
+    }
+    Ok(AttestationLevel::Signed)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhotoEvidence {
+    pub image_cid: String,
+    /// keccak256 of the image bytes
+    pub image_hash: String,
+    pub content_type: String,
+    pub size_bytes: usize,
+    pub purpose: PhotoPurpose,
+    pub reference: String,
+    pub attestation: CaptureAttestation,
+    pub level: AttestationLevel,
+    /// Attestation document pinned next to the image
+    pub attestation_cid: String,
+    pub received_at: String,
+    /// dHash as 16 hex digits
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub perceptual_hash: Option<String>,
+    /// Earlier photos of another batch, SKU or purpose that look the same
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub similar_to: Vec<SimilarPhoto>,
+    /// Key in object storage, when the image is kept there
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_key: Option<String>,
+    /// keccak256 of the published copy, when metadata was stripped from it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail_cid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exif: Option<ExifSummary>,
+    /// Where the EXIF contradicts the attestation
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exif_mismatches: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarPhoto {
+    pub image_cid: String,
+    pub purpose: PhotoPurpose,
+    pub reference: String,
+    /// Differing bits of the perceptual hashes
+    pub distance: u32,
+}
+
+/// Photos filed for something else whose perceptual hash is within
+/// `max_distance` of `hash`, closest first
+fn similar_photos(
+    photos: &[PhotoEvidence],
+    hash: u64,
+    purpose: PhotoPurpose,
+    reference: &str,
+    max_distance: u32,
+) -> Vec<SimilarPhoto> {
+    let mut similar: Vec<SimilarPhoto> = photos
+        .iter()
+        .filter(|p| p.reference != reference || p.purpose != purpose)
+        .filter_map(|p| {
+            let other = p
+                .perceptual_hash
+                .as_deref()
+                .and_then(perceptual_hash::from_hex)?;
+            let distance = perceptual_hash::distance(hash, other);
+            (distance <= max_distance).then(|| SimilarPhoto {
+                image_cid: p.image_cid.clone(),
+                purpose: p.purpose,
+                reference: p.reference.clone(),
+                distance,
+            })
+        })
+        .collect();
+    similar.sort_by_key(|s| s.distance);
+    similar
+}
+
+impl PhotoEvidence {
+    /// Entry embedded in purchase and fraud metadata
+    pub fn metadata_entry(&self) -> serde_json::Value {
+        serde_json::json!({
+            "image_cid": self.image_cid,
+            "image_hash": self.image_hash,
+            "thumbnail_cid": self.thumbnail_cid,
+            "perceptual_hash": self.perceptual_hash,
+            "attestation_cid": self.attestation_cid,
+            "attestation": self.level,
+            "captured_at": self.attestation.captured_at,
+            "latitude": self.attestation.latitude,
+            "longitude": self.attestation.longitude,
+            "device_id": self.attestation.device_id,
+        })
+    }
+}
+
+pub struct PhotoEvidenceStore {
+    photos: Mutex<Vec<PhotoEvidence>>,
+    devices: HashMap<String, Address>,
+    policy: PhotoPolicy,
+}
+
+impl PhotoEvidenceStore {
+    pub fn load() -> Result<Self> {
+        let photos = match std::fs::read_to_string(PHOTOS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid photo evidence file {}", PHOTOS_PATH))?,
+            Err(_) => Vec::new(),
+        };
+
+        let path = std::env::var("DEVICE_KEYS_PATH")
+            .unwrap_or_else(|_| DEFAULT_DEVICE_KEYS_PATH.to_string());
+        let devices: HashMap<String, String> = if std::path::Path::new(&path).exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read device keys {}", path))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid device keys {}", path))?
+        } else {
+            HashMap::new()
+        };
+        let devices = devices
+            .into_iter()
+            .map(|(device, address)| {
+                let address = address
+                    .parse()
+                    .with_context(|| format!("Device {}: invalid address in {}", device, path))?;
+                Ok((device, address))
+            })
+            .collect::<Result<_>>()?;
+
+        let max_age_hours = std::env::var("PHOTO_MAX_AGE_HOURS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_AGE_HOURS);
+
+        Ok(Self {
+            photos: Mutex::new(photos),
+            devices,
+            policy: PhotoPolicy {
+                require_signature: std::env::var("PHOTO_REQUIRE_SIGNATURE")
+                    .map(|v| v == "true" || v == "1")
+                    .unwrap_or(false),
+                max_age: Duration::hours(max_age_hours),
+                similarity_distance: std::env::var("PHOTO_SIMILARITY_DISTANCE")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_SIMILARITY_DISTANCE),
+                exif_max_skew: Duration::minutes(
+                    std::env::var("PHOTO_EXIF_MAX_SKEW_MINUTES")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(DEFAULT_EXIF_MAX_SKEW_MINUTES),
+                ),
+                exif_max_distance_m: std::env::var("PHOTO_EXIF_MAX_DISTANCE_M")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(DEFAULT_EXIF_MAX_DISTANCE_M),
+            },
+        })
+    }
+
+    /// Object storage key of the image pinned as `image_cid`
+    pub async fn object_key(&self, image_cid: &str) -> Option<String> {
+        self.photos
+            .lock()
+            .await
+            .iter()
+            .find(|p| p.image_cid == image_cid)
+            .and_then(|p| p.object_key.clone())
+    }
+
+    fn save(photos: &[PhotoEvidence]) -> Result<()> {
+        std::fs::write(PHOTOS_PATH, serde_json::to_string_pretty(photos)?)
+            .with_context(|| format!("Failed to write {}", PHOTOS_PATH))
+    }
+
+    /// Photos referenced by `image_cids`, which must all have been uploaded
+    /// for `purpose` and `reference`
+    pub async fn resolve(
+        &self,
+        image_cids: &[String],
+        purpose: PhotoPurpose,
+        reference: &str,
+    ) -> Result<Vec<PhotoEvidence>, ApiError> {
+        if image_cids.len() > MAX_PHOTOS_PER_RECORD {
+            return Err(ApiError::bad_request(format!(
+                "At most {} photos per record",
+                MAX_PHOTOS_PER_RECORD
+            )));
+        }
+        let photos = self.photos.lock().await;
+        image_cids
+            .iter()
+            .map(|cid| {
+                let photo = photos
+                    .iter()
+                    .find(|p| &p.image_cid == cid)
+                    .ok_or_else(|| ApiError::bad_request(format!("Unknown photo {}", cid)))?;
+                if photo.purpose != purpose || photo.reference != reference {
+                    return Err(ApiError::bad_request(format!(
+                        "Photo {} was not captured for {}",
+                        cid, reference
+                    )));
+                }
+                Ok(photo.clone())
+            })
+            .collect()
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct UploadPhotoRequest {
+    pub purpose: PhotoPurpose,
+    /// Batch ID for procurement, SKU ID for fraud
+    pub reference: String,
+    pub image_base64: String,
+    #[serde(default)]
+    pub content_type: Option<String>,
+    pub attestation: CaptureAttestation,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PhotoEvidenceResponse {
+    #[serde(flatten)]
+    pub photo: PhotoEvidence,
+    pub ipfs_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
+}
+
+impl From<PhotoEvidence> for PhotoEvidenceResponse {
+    fn from(photo: PhotoEvidence) -> Self {
+        Self {
+            ipfs_url: ipfs_gateway_url(&photo.image_cid),
+            thumbnail_url: photo.thumbnail_cid.as_deref().map(ipfs_gateway_url),
+            photo,
+        }
+    }
+}
+
+/// Verify a photo's capture attestation and pin both to IPFS
+pub async fn upload_photo(
+    State(state): State<AppState>,
+    principal: Option<Extension<Principal>>,
+    Json(payload): Json<UploadPhotoRequest>,
+) -> ApiResult<PhotoEvidenceResponse> {
+    let principal = principal.map(|Extension(p)| p);
+    payload.purpose.check_uploader(principal.as_ref())?;
+    let store = &state.photos;
+    if payload.reference.trim().is_empty() {
+        return Err(ApiError::bad_request("reference is required"));
+    }
+    let image = BASE64
+        .decode(payload.image_base64.trim())
+        .map_err(|_| ApiError::bad_request("image_base64 is not valid base64"))?;
+    if image.is_empty() || image.len() > MAX_PHOTO_BYTES {
+        return Err(ApiError::bad_request(format!(
+            "Image must be between 1 byte and {} MB",
+            MAX_PHOTO_BYTES / (1024 * 1024)
+        )));
+    }
+
+    let image_hash = format_hash(hash_bytes(&image));
+    if store
+        .photos
+        .lock()
+        .await
+        .iter()
+        .any(|p| p.image_hash == image_hash)
+    {
+        return Err(ApiError::bad_request(
+            "This image has already been submitted",
+        ));
+    }
+
+    let level = verify_attestation(
+        &payload.attestation,
+        &image_hash,
+        store.devices.get(&payload.attestation.device_id).copied(),
+        &store.policy,
+        Utc::now(),
+    )
+    .map_err(ApiError::bad_request)?;
+
+    let decoded = image.clone();
+    let processed = tokio::task::spawn_blocking(move || image_metadata::process(&decoded))
+        .await
+        .map_err(|e| ApiError::internal(format!("Image processing failed: {}", e)))?
+        .map_err(|e| ApiError::bad_request(format!("Unsupported image: {:#}", e)))?;
+    let phash = processed.perceptual_hash;
+    let similar_to = similar_photos(
+        &store.photos.lock().await,
+        phash,
+        payload.purpose,
+        &payload.reference,
+        store.policy.similarity_distance,
+    );
+    if !similar_to.is_empty() {
+        tracing::warn!(
+            reference = %payload.reference,
+            matches = ?similar_to.iter().map(|s| &s.image_cid).collect::<Vec<_>>(),
+            "Evidence photo resembles photos filed for other records"
+        );
+    }
+
+    let exif_mismatches = processed
+        .exif
+        .as_ref()
+        .map(|exif| exif_mismatches(exif, &payload.attestation, &store.policy))
+        .unwrap_or_default();
+    if !exif_mismatches.is_empty() {
+        tracing::warn!(
+            reference = %payload.reference,
+            mismatches = ?exif_mismatches,
+            "Evidence photo EXIF contradicts its capture attestation"
+        );
+    }
+
+    tracing::info!(
+        purpose = ?payload.purpose,
+        reference = %payload.reference,
+        device_id = %payload.attestation.device_id,
+        level = ?level,
+        "Accepting evidence photo"
+    );
+
+    let content_type = payload
+        .content_type
+        .unwrap_or_else(|| "image/jpeg".to_string());
+    let extension = content_type.strip_prefix("image/").unwrap_or("bin");
+    let size_bytes = image.len();
+    let published_hash =
+        (processed.stripped != image).then(|| format_hash(hash_bytes(&processed.stripped)));
+    let (image_cid, object_key) = object_storage::store_evidence(
+        &state,
+        processed.stripped,
+        published_hash.as_deref().unwrap_or(&image_hash),
+        &content_type,
+        extension,
+        "photos",
+    )
+    .await?;
+    let thumbnail_cid = state
+        .ipfs_client
+        .upload_bytes(
+            processed.thumbnail,
+            &format!("{}-thumb.jpg", &image_hash[2..18]),
+        )
+        .await
+        .map_err(ApiError::ipfs_upload_failed)?;
+
+    let document = serde_json::json!({
+        "type": "photo_attestation",
+        "image_cid": image_cid,
+        "image_hash": image_hash,
+        "published_hash": published_hash,
+        "thumbnail_cid": thumbnail_cid,
+        "exif": processed.exif,
+        "purpose": payload.purpose,
+        "reference": payload.reference,
+        "attestation": payload.attestation,
+        "level": level,
+        "perceptual_hash": perceptual_hash::to_hex(phash),
+        "message": attestation_message(&image_hash, &payload.attestation),
+    });
+    let attestation_cid = state
+        .ipfs_client
+        .upload_json(&document)
+        .await
+        .map_err(ApiError::ipfs_upload_failed)?;
+
+    let photo = PhotoEvidence {
+        image_cid,
+        image_hash,
+        content_type,
+        size_bytes,
+        purpose: payload.purpose,
+        reference: payload.reference,
+        attestation: payload.attestation,
+        level,
+        attestation_cid,
+        received_at: Utc::now().to_rfc3339(),
+        perceptual_hash: Some(perceptual_hash::to_hex(phash)),
+        similar_to,
+        object_key,
+        published_hash,
+        thumbnail_cid: Some(thumbnail_cid),
+        exif: processed.exif,
+        exif_mismatches,
+    };
+
+    let mut photos = store.photos.lock().await;
+    photos.push(photo.clone());
+    PhotoEvidenceStore::save(&photos).map_err(ApiError::from)?;
+
+    Ok(Json(photo.into()))
+}
+
+pub async fn get_photo(
+    State(state): State<AppState>,
+    Path(image_cid): Path<String>,
+) -> ApiResult<PhotoEvidenceResponse> {
+    let photo = state
+        .photos
+        .photos
+        .lock()
+        .await
+        .iter()
+        .find(|p| p.image_cid == image_cid)
+        .cloned()
+        .ok_or_else(|| ApiError::not_found(format!("Photo {} not found", image_cid)))?;
+
+    Ok(Json(photo.into()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PhotoSearchQuery {
+    #[serde(default)]
+    pub purpose: Option<PhotoPurpose>,
+    #[serde(default)]
+    pub reference: Option<String>,
+    /// Capture window, RFC 3339
+    #[serde(default)]
+    pub from: Option<String>,
+    #[serde(default)]
+    pub to: Option<String>,
+    /// Centre and radius of a position search
+    #[serde(default)]
+    pub lat: Option<f64>,
+    #[serde(default)]
+    pub lon: Option<f64>,
+    #[serde(default)]
+    pub radius_km: Option<f64>,
+    /// Camera make or model, case-insensitive substring
+    #[serde(default)]
+    pub camera: Option<String>,
+    /// Only photos whose EXIF contradicts their attestation
+    #[serde(default)]
+    pub mismatched: Option<bool>,
+}
+
+/// Capture time and position of a photo: EXIF where it has them, the
+/// attestation otherwise
+fn capture_point(photo: &PhotoEvidence) -> (Option<DateTime<Utc>>, (f64, f64)) {
+    let exif = photo.exif.as_ref();
+    let captured_at = exif
+        .and_then(|e| e.captured_at.as_deref())
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .or_else(|| DateTime::parse_from_rfc3339(&photo.attestation.captured_at).ok())
+        .map(|t| t.with_timezone(&Utc));
+    let position = exif
+        .and_then(|e| e.latitude.zip(e.longitude))
+        .unwrap_or((photo.attestation.latitude, photo.attestation.longitude));
+    (captured_at, position)
+}
+
+fn parse_bound(value: Option<&str>, name: &str) -> Result<Option<DateTime<Utc>>, ApiError> {
+    value
+        .map(|v| {
+            DateTime::parse_from_rfc3339(v)
+                .map(|t| t.with_timezone(&Utc))
+                .map_err(|_| ApiError::bad_request(format!("{} must be RFC 3339", name)))
+        })
+        .transpose()
+}
+
+/// Search photos by their extracted capture metadata
+pub async fn search_photos(
+    State(state): State<AppState>,
+    Query(query): Query<PhotoSearchQuery>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<PhotoEvidenceResponse>> {
+    let from = parse_bound(query.from.as_deref(), "from")?;
+    let to = parse_bound(query.to.as_deref(), "to")?;
+    let near = match (query.lat, query.lon) {
+        (Some(lat), Some(lon)) => Some(((lat, lon), query.radius_km.unwrap_or(1.0) * 1000.0)),
+        (None, None) => None,
+        _ => return Err(ApiError::bad_request("lat and lon go together")),
+    };
+    let camera = query.camera.as_deref().map(str::to_lowercase);
+
+    let photos: Vec<PhotoEvidenceResponse> = state
+        .photos
+        .photos
+        .lock()
+        .await
+        .iter()
+        .filter(|p| query.purpose.is_none_or(|purpose| p.purpose == purpose))
+        .filter(|p| query.reference.as_ref().is_none_or(|r| &p.reference == r))
+        .filter(|p| query.mismatched != Some(true) || !p.exif_mismatches.is_empty())
+        .filter(|p| {
+            camera.as_ref().is_none_or(|camera| {
+                p.exif.as_ref().is_some_and(|e| {
+                    [&e.make, &e.model]
+                        .into_iter()
+                        .flatten()
+                        .any(|name| name.to_lowercase().contains(camera))
+                })
+            })
+        })
+        .filter(|p| {
+            let (captured_at, position) = capture_point(p);
+            let in_window = match captured_at {
+                Some(t) => from.is_none_or(|from| t >= from) && to.is_none_or(|to| t <= to),
+                None => from.is_none() && to.is_none(),
+            };
+            in_window && near.is_none_or(|(centre, radius)| distance_m(centre, position) <= radius)
+        })
+        .cloned()
+        .map(PhotoEvidenceResponse::from)
+        .collect();
+
+    Ok(Json(paginate("evidence-photos", photos, &params, |p| {
+        (p.photo.received_at.clone(), p.photo.image_cid.clone())
+    })?))
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimilarPhotosView {
+    /// Newest first
+    pub photos: Vec<PhotoEvidence>,
+}
+
+/// Photos flagged as resembling photos of other records (admin only)
+pub async fn list_similar(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<SimilarPhotosView> {
+    require_admin(&state, &headers)?;
+
+    let photos = state
+        .photos
+        .photos
+        .lock()
+        .await
+        .iter()
+        .rev()
+        .filter(|p| !p.similar_to.is_empty())
+        .cloned()
+        .collect();
+    Ok(Json(SimilarPhotosView { photos }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::{local::PrivateKeySigner, SignerSync};
+
+    fn attestation(captured_at: &str) -> CaptureAttestation {
+        CaptureAttestation {
+            captured_at: captured_at.to_string(),
+            latitude: 21.1458,
+            longitude: 79.0882,
+            accuracy_m: Some(8.0),
+            device_id: "tab-017".to_string(),
+            image_hash: None,
+            signature: None,
+        }
+    }
+
+    fn policy() -> PhotoPolicy {
+        PhotoPolicy {
+            require_signature: false,
+            max_age: Duration::hours(72),
+            similarity_distance: 6,
+            exif_max_skew: Duration::minutes(30),
+            exif_max_distance_m: 1000.0,
+        }
+    }
+
+    #[test]
+    fn test_capture_metadata_checks() {
+        let now = DateTime::parse_from_rfc3339("2025-06-02T10:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let hash = format_hash(hash_bytes(b"photo"));
+
+        let fresh = attestation("2025-06-02T09:00:00+05:30");
+        assert_eq!(
+            verify_attestation(&fresh, &hash, None, &policy(), now),
+            Ok(AttestationLevel::Unsigned)
+        );
+        assert!(verify_attestation(
+            &attestation("2025-05-20T09:00:00Z"),
+            &hash,
+            None,
+            &policy(),
+            now
+        )
+        .is_err());
+        assert!(verify_attestation(
+            &attestation("2025-06-02T11:00:00Z"),
+            &hash,
+            None,
+            &policy(),
+            now
+        )
+        .is_err());
+
+        let mut swapped = fresh.clone();
+        swapped.image_hash = Some(format_hash(hash_bytes(b"other photo")));
+        assert!(verify_attestation(&swapped, &hash, None, &policy(), now).is_err());
+
+        let strict = PhotoPolicy {
+            require_signature: true,
+            ..policy()
+        };
+        assert!(verify_attestation(&fresh, &hash, None, &strict, now).is_err());
+    }
+
+    #[test]
+    fn test_device_signature_must_match_enrolled_key() {
+        let now = Utc::now();
+        let device = PrivateKeySigner::random();
+        let hash = format_hash(hash_bytes(b"photo"));
+        let mut signed = attestation(&now.to_rfc3339());
+        let signature = device
+            .sign_message_sync(attestation_message(&hash, &signed).as_bytes())
+            .unwrap();
+        signed.signature = Some(hex::encode(signature.as_bytes()));
+
+        assert_eq!(
+            verify_attestation(&signed, &hash, Some(device.address()), &policy(), now),
+            Ok(AttestationLevel::Signed)
+        );
+        // Unenrolled device, another device's key, or a moved GPS fix
+        assert!(verify_attestation(&signed, &hash, None, &policy(), now).is_err());
+        let other = PrivateKeySigner::random().address();
+        assert!(verify_attestation(&signed, &hash, Some(other), &policy(), now).is_err());
+        signed.latitude += 0.01;
+        assert!(
+            verify_attestation(&signed, &hash, Some(device.address()), &policy(), now).is_err()
+        );
+    }
+
+    #[test]
+    fn test_exif_checked_against_attestation() {
+        let attested = attestation("2025-06-02T09:00:00+05:30");
+        let exif = |captured_at: &str, latitude: f64| ExifSummary {
+            captured_at: Some(captured_at.to_string()),
+            latitude: Some(latitude),
+            longitude: Some(79.0882),
+            ..Default::default()
+        };
+        // Without an offset the EXIF time is read in the attestation's
+        assert!(
+            exif_mismatches(&exif("2025-06-02T09:20:00", 21.15), &attested, &policy()).is_empty()
+        );
+
+        let mismatches = exif_mismatches(
+            &exif("2025-06-01T09:00:00+05:30", 21.3),
+            &attested,
+            &policy(),
+        );
+        assert_eq!(
+            mismatches,
+            [
+                "EXIF capture time is 1440 minutes from captured_at",
+                "EXIF position is 17146 m from the attested position"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_only_fraud_photos_are_anonymous() {
+        let user = |role| {
+            Principal::User(crate::auth::Claims {
+                sub: "u".to_string(),
+                role,
+                did: None,
+                roles: Vec::new(),
+                iat: 0,
+                exp: 0,
+            })
+        };
+        assert!(PhotoPurpose::Fraud.check_uploader(None).is_ok());
+        assert!(PhotoPurpose::Procurement.check_uploader(None).is_err());
+        assert!(PhotoPurpose::Procurement
+            .check_uploader(Some(&user(Role::Fpo)))
+            .is_ok());
+        assert!(PhotoPurpose::Procurement
+            .check_uploader(Some(&user(Role::Farmer)))
+            .is_err());
+        assert!(PhotoPurpose::Acceptance
+            .check_uploader(Some(&user(Role::Processor)))
+            .is_ok());
+        assert!(PhotoPurpose::Acceptance
+            .check_uploader(Some(&user(Role::Fpo)))
+            .is_err());
+    }
+
+    #[test]
+    fn test_similar_photos_skip_same_record() {
+        let photo = |cid: &str, reference: &str, hash: u64| PhotoEvidence {
+            image_cid: cid.to_string(),
+            image_hash: String::new(),
+            content_type: "image/jpeg".to_string(),
+            size_bytes: 1,
+            purpose: PhotoPurpose::Procurement,
+            reference: reference.to_string(),
+            attestation: attestation("2025-06-02T09:00:00Z"),
+            level: AttestationLevel::Unsigned,
+            attestation_cid: String::new(),
+            received_at: String::new(),
+            perceptual_hash: Some(perceptual_hash::to_hex(hash)),
+            similar_to: Vec::new(),
+            object_key: None,
+            published_hash: None,
+            thumbnail_cid: None,
+            exif: None,
+            exif_mismatches: Vec::new(),
+        };
+        let photos = [
+            photo("QmSame", "BATCH-1", 0xff00),
+            photo("QmOther", "BATCH-2", 0xff03),
+            photo("QmFar", "BATCH-3", 0x00ff),
+        ];
+
+        let similar = similar_photos(&photos, 0xff01, PhotoPurpose::Procurement, "BATCH-1", 6);
+        assert_eq!(similar.len(), 1);
+        assert_eq!(
+            (similar[0].image_cid.as_str(), similar[0].distance),
+            ("QmOther", 1)
+        );
+
+        // A fraud photo matching a procurement photo of the same batch is flagged
+        let similar = similar_photos(&photos, 0xff00, PhotoPurpose::Fraud, "BATCH-1", 6);
+        assert_eq!(similar[0].image_cid, "QmSame");
+    }
+}
@@ -0,0 +1,342 @@
+//! Processor master data
+//!
+//! Processing facilities are registered with their licence, the processes
+//! they can run (e.g. `cold press`, `expeller`, `solvent extraction`) and
+//! their daily capacity. `POST /api/processing/batch` and the workflow's
+//! processing stage name the facility through `processor_id`; the
+//! `process_type` must be one of its capabilities, and the registry entry is
+//! written into `processing.json`.
+//!
+//! Admins manage the registry under `/api/admin/processors`; anyone can read
+//! it through `GET /api/processors`. Entries are kept in
+//! `data/processors.json`.
+
+use crate::admin::require_admin;
+use crate::error::{ApiError, ApiResult};
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use crate::warehouse_registry::{validate_licence, Licence};
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+const PROCESSORS_PATH: &str = "data/processors.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Processor {
+    /// Facility ID
+    pub id: String,
+    pub name: String,
+    pub licence: Licence,
+    /// Normalised process names, e.g. `cold press`
+    pub capabilities: Vec<String>,
+    pub capacity_tonnes_per_day: f64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Processor {
+    pub fn supports(&self, process_type: &str) -> bool {
+        let process_type = normalize_capability(process_type);
+        self.capabilities.contains(&process_type)
+    }
+
+    /// Entry written into `processing.json`
+    pub fn metadata_entry(&self) -> serde_json::Value {
+        serde_json::json!({
+            "processor_id": self.id,
+            "name": self.name,
+            "licence": self.licence,
+            "capabilities": self.capabilities,
+            "capacity_tonnes_per_day": self.capacity_tonnes_per_day,
+        })
+    }
+}
+
+/// Lowercase with `-`/`_` and repeated spaces folded, so `Cold-Press`
+/// matches `cold press`
+fn normalize_capability(name: &str) -> String {
+    name.split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn validate_capabilities(capabilities: &[String]) -> Result<Vec<String>, ApiError> {
+    let mut checked: Vec<String> = Vec::with_capacity(capabilities.len());
+    for capability in capabilities {
+        let capability = normalize_capability(capability);
+        if capability.is_empty() {
+            return Err(ApiError::bad_request("Capabilities must not be empty"));
+        }
+        if !checked.contains(&capability) {
+            checked.push(capability);
+        }
+    }
+    if checked.is_empty() {
+        return Err(ApiError::bad_request(
+            "A processor needs at least one capability",
+        ));
+    }
+    Ok(checked)
+}
+
+fn validate_capacity(capacity_tonnes_per_day: f64) -> Result<(), ApiError> {
+    if !(capacity_tonnes_per_day.is_finite() && capacity_tonnes_per_day > 0.0) {
+        return Err(ApiError::bad_request(
+            "capacity_tonnes_per_day must be positive",
+        ));
+    }
+    Ok(())
+}
+
+pub struct ProcessorRegistry {
+    processors: Mutex<Vec<Processor>>,
+}
+
+impl ProcessorRegistry {
+    pub fn load() -> Result<Self> {
+        let processors = match std::fs::read_to_string(PROCESSORS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid processor registry {}", PROCESSORS_PATH))?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Self {
+            processors: Mutex::new(processors),
+        })
+    }
+
+    fn save(processors: &[Processor]) -> Result<()> {
+        std::fs::write(PROCESSORS_PATH, serde_json::to_string_pretty(processors)?)
+            .with_context(|| format!("Failed to write {}", PROCESSORS_PATH))
+    }
+
+    pub async fn get(&self, id: &str) -> Option<Processor> {
+        self.processors
+            .lock()
+            .await
+            .iter()
+            .find(|p| p.id == id)
+            .cloned()
+    }
+
+    /// Registered processor `id` able to run `process_type`, as a request
+    /// error otherwise
+    pub async fn require_capable(
+        &self,
+        id: &str,
+        process_type: &str,
+    ) -> Result<Processor, ApiError> {
+        let processor = self
+            .get(id.trim())
+            .await
+            .ok_or_else(|| ApiError::bad_request(format!("Processor {} is not registered", id)))?;
+        if !processor.supports(process_type) {
+            return Err(ApiError::bad_request(format!(
+                "Processor {} is not registered for {} (capabilities: {})",
+                processor.id,
+                process_type,
+                processor.capabilities.join(", ")
+            )));
+        }
+        Ok(processor)
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct CreateProcessorRequest {
+    pub id: String,
+    pub name: String,
+    pub licence: Licence,
+    pub capabilities: Vec<String>,
+    pub capacity_tonnes_per_day: f64,
+}
+
+/// Fields to change; `None` leaves a field as it is
+#[derive(Debug, Default, Deserialize)]
+pub struct UpdateProcessorRequest {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub licence: Option<Licence>,
+    /// Replaces the capability list
+    #[serde(default)]
+    pub capabilities: Option<Vec<String>>,
+    #[serde(default)]
+    pub capacity_tonnes_per_day: Option<f64>,
+}
+
+/// Registered processors, ordered by ID
+pub async fn list_processors(
+    State(state): State<AppState>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<Processor>> {
+    let processors = state.processors.processors.lock().await.clone();
+    Ok(Json(paginate("processors", processors, &params, |p| {
+        p.id.clone()
+    })?))
+}
+
+pub async fn get_processor(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<Processor> {
+    state
+        .processors
+        .get(&id)
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Processor {} not found", id)))
+}
+
+/// Register a processing facility (admin only)
+pub async fn create_processor(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateProcessorRequest>,
+) -> ApiResult<Processor> {
+    require_admin(&state, &headers)?;
+
+    let id = payload.id.trim().to_string();
+    let name = payload.name.trim().to_string();
+    if id.is_empty() || name.is_empty() {
+        return Err(ApiError::bad_request("id and name are required"));
+    }
+    validate_capacity(payload.capacity_tonnes_per_day)?;
+    let now = Utc::now().to_rfc3339();
+    let processor = Processor {
+        id,
+        name,
+        licence: validate_licence(&payload.licence)?,
+        capabilities: validate_capabilities(&payload.capabilities)?,
+        capacity_tonnes_per_day: payload.capacity_tonnes_per_day,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+
+    let mut processors = state.processors.processors.lock().await;
+    if processors.iter().any(|p| p.id == processor.id) {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!("Processor {} already exists", processor.id),
+        ));
+    }
+    processors.push(processor.clone());
+    ProcessorRegistry::save(&processors)?;
+
+    tracing::info!(processor_id = %processor.id, "Processor registered");
+    Ok(Json(processor))
+}
+
+/// Change a processor's details, licence or capabilities (admin only)
+pub async fn update_processor(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateProcessorRequest>,
+) -> ApiResult<Processor> {
+    require_admin(&state, &headers)?;
+
+    let licence = payload.licence.as_ref().map(validate_licence).transpose()?;
+    let capabilities = payload
+        .capabilities
+        .as_deref()
+        .map(validate_capabilities)
+        .transpose()?;
+    if let Some(capacity) = payload.capacity_tonnes_per_day {
+        validate_capacity(capacity)?;
+    }
+
+    let mut processors = state.processors.processors.lock().await;
+    let processor = processors
+        .iter_mut()
+        .find(|p| p.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("Processor {} not found", id)))?;
+
+    if let Some(name) = &payload.name {
+        if name.trim().is_empty() {
+            return Err(ApiError::bad_request("name is required"));
+        }
+        processor.name = name.trim().to_string();
+    }
+    if let Some(licence) = licence {
+        processor.licence = licence;
+    }
+    if let Some(capabilities) = capabilities {
+        processor.capabilities = capabilities;
+    }
+    if let Some(capacity) = payload.capacity_tonnes_per_day {
+        processor.capacity_tonnes_per_day = capacity;
+    }
+    processor.updated_at = Utc::now().to_rfc3339();
+    let processor = processor.clone();
+    ProcessorRegistry::save(&processors)?;
+
+    tracing::info!(processor_id = %processor.id, "Processor updated");
+    Ok(Json(processor))
+}
+
+/// Remove a processor (admin only); batches already processed keep its
+/// details in their metadata
+pub async fn delete_processor(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<Processor> {
+    require_admin(&state, &headers)?;
+
+    let mut processors = state.processors.processors.lock().await;
+    let position = processors
+        .iter()
+        .position(|p| p.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("Processor {} not found", id)))?;
+    let processor = processors.remove(position);
+    ProcessorRegistry::save(&processors)?;
+
+    tracing::info!(processor_id = %processor.id, "Processor removed");
+    Ok(Json(processor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_type_matches_normalised_capabilities() {
+        let capabilities = validate_capabilities(&[
+            " Cold-Press ".to_string(),
+            "solvent_extraction".to_string(),
+            "cold press".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(capabilities, ["cold press", "solvent extraction"]);
+        assert!(validate_capabilities(&[]).is_err());
+        assert!(validate_capabilities(&["  ".to_string()]).is_err());
+
+        let processor = Processor {
+            id: "PROC-1".to_string(),
+            name: "Malwa Oil Mills".to_string(),
+            licence: Licence {
+                authority: "FSSAI".to_string(),
+                number: "10019022008765".to_string(),
+                valid_until: None,
+            },
+            capabilities,
+            capacity_tonnes_per_day: 40.0,
+            created_at: String::new(),
+            updated_at: String::new(),
+        };
+        assert!(processor.supports("COLD PRESS"));
+        assert!(processor.supports("Solvent-Extraction"));
+        assert!(!processor.supports("expeller"));
+    }
+}
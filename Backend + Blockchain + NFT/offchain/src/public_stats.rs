@@ -0,0 +1,263 @@
+//! Program-level transparency statistics
+//!
+//! `GET /api/public/stats` is unauthenticated and feeds the public
+//! transparency dashboard. Figures are computed from the farmer database and
+//! the batch ledger, then cached in memory for PUBLIC_STATS_TTL_SECS
+//! (default one hour) and advertised with a matching `Cache-Control` so CDNs
+//! can absorb the traffic.
+
+use crate::batch_ledger;
+use crate::error::ApiError;
+use crate::farmer_verification::FarmerEntry;
+use crate::state::AppState;
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const DEFAULT_TTL_SECS: u64 = 3600;
+
+/// Minimum Support Price in INR per quintal (Kharif 2024-25, Rabi 2025-26)
+const MSP_PER_QUINTAL: &[(&str, f64)] = &[
+    ("mustard", 5950.0),
+    ("rapeseed", 5950.0),
+    ("groundnut", 6783.0),
+    ("sunflower", 7280.0),
+    ("soybean", 4892.0),
+    ("sesame", 9267.0),
+    ("safflower", 5940.0),
+    ("niger", 8717.0),
+];
+
+fn msp_per_kg(crop: &str) -> Option<f64> {
+    let crop = crop.to_lowercase();
+    MSP_PER_QUINTAL
+        .iter()
+        .find(|(name, _)| *name == crop)
+        .map(|(_, msp)| msp / 100.0)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CropPriceStats {
+    pub crop: String,
+    pub tonnes: f64,
+    /// Quantity-weighted average price paid to farmers
+    pub avg_price_per_kg: f64,
+    pub msp_per_kg: Option<f64>,
+    /// Average price relative to MSP, in percent (positive = above MSP)
+    pub premium_over_msp_pct: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicStats {
+    pub farmers_onboarded: usize,
+    pub farmers_verified: usize,
+    pub districts_covered: usize,
+    pub states_covered: usize,
+    pub batches_traced: usize,
+    pub tonnes_traced: f64,
+    /// Quantity-weighted premium over MSP across crops that have an MSP
+    pub avg_premium_over_msp_pct: Option<f64>,
+    pub crops: Vec<CropPriceStats>,
+    pub generated_at: String,
+}
+
+/// Aggregate program statistics from farmer entries and FPO purchase records
+pub fn compute_stats<'a>(
+    farmers: impl Iterator<Item = &'a FarmerEntry>,
+    purchases: &[(String, Value)],
+) -> PublicStats {
+    let mut farmers_onboarded = 0;
+    let mut farmers_verified = 0;
+    let mut districts = HashSet::new();
+    let mut states = HashSet::new();
+    for farmer in farmers {
+        farmers_onboarded += 1;
+        if farmer.verified {
+            farmers_verified += 1;
+        }
+        if !farmer.district_code.is_empty() {
+            districts.insert(farmer.district_code.as_str());
+        }
+        if !farmer.state_code.is_empty() {
+            states.insert(farmer.state_code.as_str());
+        }
+    }
+
+    // crop → (quantity kg, amount paid)
+    let mut by_crop: BTreeMap<String, (f64, f64)> = BTreeMap::new();
+    for (_, purchase) in purchases {
+        let quantity = purchase
+            .pointer("/batch_info/quantity_kg")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0);
+        let price = purchase
+            .pointer("/pricing/price_per_kg")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.0);
+        let crop = purchase
+            .pointer("/farmer_info/crop_type")
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown")
+            .to_lowercase();
+
+        let entry = by_crop.entry(crop).or_default();
+        entry.0 += quantity;
+        entry.1 += quantity * price;
+    }
+
+    let mut msp_quantity = 0.0;
+    let mut weighted_premium = 0.0;
+    let crops: Vec<CropPriceStats> = by_crop
+        .into_iter()
+        .map(|(crop, (quantity, paid))| {
+            let avg_price_per_kg = if quantity > 0.0 { paid / quantity } else { 0.0 };
+            let msp = msp_per_kg(&crop);
+            let premium = msp.map(|msp| (avg_price_per_kg - msp) / msp * 100.0);
+            if let Some(premium) = premium {
+                msp_quantity += quantity;
+                weighted_premium += premium * quantity;
+            }
+            CropPriceStats {
+                crop,
+                tonnes: quantity / 1000.0,
+                avg_price_per_kg,
+                msp_per_kg: msp,
+                premium_over_msp_pct: premium,
+            }
+        })
+        .collect();
+
+    PublicStats {
+        farmers_onboarded,
+        farmers_verified,
+        districts_covered: districts.len(),
+        states_covered: states.len(),
+        batches_traced: purchases.len(),
+        tonnes_traced: crops.iter().map(|c| c.tonnes).sum(),
+        avg_premium_over_msp_pct: (msp_quantity > 0.0).then(|| weighted_premium / msp_quantity),
+        crops,
+        generated_at: chrono::Utc::now().to_rfc3339(),
+    }
+}
+
+/// Last computed statistics and when they were computed
+#[derive(Default)]
+pub struct StatsCache {
+    entry: Mutex<Option<(Instant, PublicStats)>>,
+}
+
+impl StatsCache {
+    fn ttl() -> Duration {
+        let secs = std::env::var("PUBLIC_STATS_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+        Duration::from_secs(secs)
+    }
+
+    fn fresh(&self, ttl: Duration) -> Option<PublicStats> {
+        let entry = self.entry.lock().unwrap_or_else(|e| e.into_inner());
+        entry
+            .as_ref()
+            .filter(|(computed_at, _)| computed_at.elapsed() < ttl)
+            .map(|(_, stats)| stats.clone())
+    }
+
+    fn store(&self, stats: PublicStats) {
+        *self.entry.lock().unwrap_or_else(|e| e.into_inner()) = Some((Instant::now(), stats));
+    }
+}
+
+pub async fn get_public_stats(State(state): State<AppState>) -> Result<Response, ApiError> {
+    let ttl = StatsCache::ttl();
+
+    let stats = match state.public_stats.fresh(ttl) {
+        Some(stats) => stats,
+        None => {
+            let purchases = tokio::task::spawn_blocking(batch_ledger::all_purchases)
+                .await
+                .map_err(|e| ApiError::internal(format!("Stats task failed: {}", e)))?;
+            let farmers = state.farmer_verification.farmers_snapshot().await?;
+            let stats = compute_stats(farmers.iter(), &purchases);
+            tracing::info!(
+                farmers = stats.farmers_onboarded,
+                batches = stats.batches_traced,
+                "Recomputed public statistics"
+            );
+            state.public_stats.store(stats.clone());
+            stats
+        }
+    };
+
+    let cache_control = format!("public, max-age={}", ttl.as_secs());
+    Ok(([(header::CACHE_CONTROL, cache_control)], Json(stats)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn farmer(did: &str, district: &str, verified: bool) -> FarmerEntry {
+        FarmerEntry {
+            mobile: String::new(),
+            farmer_did: did.to_string(),
+            name: String::new(),
+            location: String::new(),
+            state_code: "PB".to_string(),
+            district_code: district.to_string(),
+            land_acres: 1.0,
+            crop: "mustard".to_string(),
+            verified,
+            registration_date: String::new(),
+            ipfscid: String::new(),
+        }
+    }
+
+    fn purchase(crop: &str, quantity_kg: f64, price_per_kg: f64) -> (String, Value) {
+        (
+            "b".to_string(),
+            json!({
+                "batch_info": { "quantity_kg": quantity_kg },
+                "farmer_info": { "crop_type": crop },
+                "pricing": { "price_per_kg": price_per_kg }
+            }),
+        )
+    }
+
+    #[test]
+    fn test_compute_stats() {
+        let farmers = [
+            farmer("a", "PB001", true),
+            farmer("b", "PB001", false),
+            farmer("c", "PB002", true),
+        ];
+        let purchases = [
+            purchase("Mustard", 1000.0, 59.5),
+            purchase("mustard", 1000.0, 65.45),
+            purchase("castor", 500.0, 40.0),
+        ];
+
+        let stats = compute_stats(farmers.iter(), &purchases);
+        assert_eq!(stats.farmers_onboarded, 3);
+        assert_eq!(stats.farmers_verified, 2);
+        assert_eq!(stats.districts_covered, 2);
+        assert_eq!(stats.batches_traced, 3);
+        assert!((stats.tonnes_traced - 2.5).abs() < 1e-9);
+
+        // Mustard averaged 62.475/kg against an MSP of 59.50/kg
+        let premium = stats.avg_premium_over_msp_pct.unwrap();
+        assert!((premium - 5.0).abs() < 1e-9);
+
+        let castor = stats.crops.iter().find(|c| c.crop == "castor").unwrap();
+        assert!(castor.premium_over_msp_pct.is_none());
+    }
+}
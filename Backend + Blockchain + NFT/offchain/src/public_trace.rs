@@ -0,0 +1,424 @@
+//! Consumer trace page data with per-brand presentation
+//!
+//! `GET /api/public/trace/:sku_id?brand=X` combines the on-chain package
+//! origin with the off-chain batch records and returns it together with the
+//! brand's presentation metadata, so several oil brands can run their own
+//! consumer experience off one backend.
+//!
+//! Brands are configured in `data/brands.json` (override with
+//! BRAND_CONFIG_PATH). Without `?brand=` the configured default brand is used.
+
+use crate::batch_ledger;
+use crate::chain::hash_string;
+use crate::error::{format_hash, ipfs_gateway_url, ApiError, ApiResult};
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// ======================== BRAND CONFIGURATION ========================
+
+const DEFAULT_BRAND_CONFIG_PATH: &str = "data/brands.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrandProfile {
+    pub display_name: String,
+    #[serde(default)]
+    pub logo_cid: Option<String>,
+    #[serde(default)]
+    pub accent_color: Option<String>,
+    /// Story shown on the trace page; `{crop}`, `{grade}`, `{location}` and
+    /// `{batch_id}` are filled in from the trace
+    pub story: String,
+    /// Certification IDs to feature, in display order
+    #[serde(default)]
+    pub highlight_certifications: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BrandRegistry {
+    pub default_brand: String,
+    pub brands: HashMap<String, BrandProfile>,
+}
+
+impl BrandRegistry {
+    /// Load brand profiles, falling back to a neutral program brand when no file exists
+    pub fn load() -> Result<Self> {
+        let path = std::env::var("BRAND_CONFIG_PATH")
+            .unwrap_or_else(|_| DEFAULT_BRAND_CONFIG_PATH.to_string());
+
+        if !std::path::Path::new(&path).exists() {
+            tracing::warn!(path = %path, "Brand config not found, using default brand only");
+            return Ok(Self::fallback());
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read brand config {}", path))?;
+        let registry: Self = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid brand config {}", path))?;
+
+        if !registry.brands.contains_key(&registry.default_brand) {
+            anyhow::bail!(
+                "Default brand '{}' is not defined in {}",
+                registry.default_brand,
+                path
+            );
+        }
+
+        tracing::info!(brands = registry.brands.len(), "Brand profiles loaded");
+        Ok(registry)
+    }
+
+    fn fallback() -> Self {
+        let profile = BrandProfile {
+            display_name: "Oilseed Value Chain".to_string(),
+            logo_cid: None,
+            accent_color: None,
+            story: "This {crop} oil was traced from an FPO purchase in {location} to your pack."
+                .to_string(),
+            highlight_certifications: vec![
+                "blockchain_verified".to_string(),
+                "farmer_verified".to_string(),
+            ],
+        };
+        Self {
+            default_brand: "default".to_string(),
+            brands: HashMap::from([("default".to_string(), profile)]),
+        }
+    }
+
+    /// Resolve a requested brand (or the default) to its ID and profile
+    pub fn resolve(&self, brand: Option<&str>) -> Option<(&str, &BrandProfile)> {
+        let id = brand.unwrap_or(&self.default_brand);
+        self.brands
+            .get_key_value(id)
+            .map(|(id, profile)| (id.as_str(), profile))
+    }
+}
+
+// ======================== TRACE ASSEMBLY ========================
+
+/// Certifications the trace can establish, with consumer-facing labels
+const CERTIFICATIONS: &[(&str, &str)] = &[
+    ("blockchain_verified", "Origin recorded on blockchain"),
+    ("farmer_verified", "Sourced from a verified farmer"),
+    ("ai_quality_scored", "Independently quality scored"),
+    ("organic", "Organic grade produce"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceBatch {
+    pub batch_id: String,
+    pub crop_type: Option<String>,
+    pub quality_grade: Option<String>,
+    pub quantity_kg: Option<f64>,
+    pub purchased_at: Option<String>,
+    pub farmer_location: Option<String>,
+    pub farmer_verified: bool,
+    pub stage: &'static str,
+    /// `batch_stage` code of `stage`
+    pub stage_code: &'static str,
+    pub ai_scored: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsumerTrace {
+    pub sku_id: String,
+    pub verified: bool,
+    pub packaged_at: u64,
+    pub parent_batch_hash: String,
+    pub merkle_root: String,
+    pub package_type: Option<String>,
+    pub expiry_date: Option<String>,
+    pub batch: Option<TraceBatch>,
+}
+
+impl ConsumerTrace {
+    /// IDs of all certifications this trace establishes
+    pub fn achieved_certifications(&self) -> Vec<&'static str> {
+        let batch = self.batch.as_ref();
+        CERTIFICATIONS
+            .iter()
+            .map(|(id, _)| *id)
+            .filter(|id| match *id {
+                "blockchain_verified" => self.verified,
+                "farmer_verified" => batch.is_some_and(|b| b.farmer_verified),
+                "ai_quality_scored" => batch.is_some_and(|b| b.ai_scored),
+                "organic" => batch
+                    .and_then(|b| b.quality_grade.as_deref())
+                    .is_some_and(|g| g.eq_ignore_ascii_case("organic")),
+                _ => false,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Certification {
+    pub id: String,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Branding {
+    pub brand: String,
+    pub display_name: String,
+    pub logo_cid: Option<String>,
+    pub logo_url: Option<String>,
+    pub accent_color: Option<String>,
+    pub story: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicTraceResponse {
+    pub trace: ConsumerTrace,
+    pub branding: Branding,
+    /// Brand-highlighted certifications the trace actually establishes
+    pub certifications: Vec<Certification>,
+    /// Content variant served, when the SKU is part of an experiment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub experiment: Option<ExperimentAssignment>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExperimentAssignment {
+    pub experiment_id: String,
+    pub variant_id: String,
+}
+
+/// Fill story placeholders from the trace; unknown values read as generic text
+pub fn render_story(template: &str, trace: &ConsumerTrace) -> String {
+    let batch = trace.batch.as_ref();
+    let value = |field: Option<&str>, fallback: &str| field.unwrap_or(fallback).to_string();
+
+    template
+        .replace(
+            "{crop}",
+            &value(batch.and_then(|b| b.crop_type.as_deref()), "oilseed"),
+        )
+        .replace(
+            "{grade}",
+            &value(batch.and_then(|b| b.quality_grade.as_deref()), "graded"),
+        )
+        .replace(
+            "{location}",
+            &value(batch.and_then(|b| b.farmer_location.as_deref()), "India"),
+        )
+        .replace(
+            "{batch_id}",
+            &value(batch.map(|b| b.batch_id.as_str()), "unknown"),
+        )
+}
+
+/// Highlighted certifications achieved by the trace, in highlight order
+pub fn highlighted_certifications(
+    highlights: &[String],
+    trace: &ConsumerTrace,
+) -> Vec<Certification> {
+    let achieved = trace.achieved_certifications();
+    highlights
+        .iter()
+        .filter(|id| achieved.contains(&id.as_str()))
+        .filter_map(|id| CERTIFICATIONS.iter().find(|(known, _)| known == id))
+        .map(|(id, label)| Certification {
+            id: id.to_string(),
+            label: label.to_string(),
+        })
+        .collect()
+}
+
+async fn build_trace(state: &AppState, sku_id: &str) -> Result<ConsumerTrace, ApiError> {
+    let (parent_batch_hash, merkle_root, packaged_at) = state
+        .chain()
+        .verify_package_origin(hash_string(sku_id))
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+
+    // Only trust the off-chain folder if it is the batch recorded on chain
+    let record = batch_ledger::find_sku(sku_id)
+        .map_err(ApiError::from)?
+        .filter(|record| hash_string(&record.batch_id) == parent_batch_hash);
+    let packaging_field = |name: &str| {
+        record
+            .as_ref()
+            .and_then(|r| r.packaging.get(name))
+            .and_then(|v| v.as_str())
+            .map(String::from)
+    };
+
+    let batch = match &record {
+        Some(record) => {
+            let status = batch_ledger::batch_status(&record.batch_id)
+                .map_err(ApiError::from)?
+                .ok_or_else(|| ApiError::not_found("Batch records not found"))?;
+
+            let farmer = match &status.farmer_did {
+                Some(did) => state.farmer_verification.get_farmer_by_did(did).await?,
+                None => None,
+            };
+
+            Some(TraceBatch {
+                batch_id: status.batch_id.clone(),
+                crop_type: status.crop_type.clone(),
+                quality_grade: status.quality_grade.clone(),
+                quantity_kg: status.quantity_kg,
+                purchased_at: status.purchased_at.clone(),
+                farmer_location: farmer.as_ref().map(|f| f.location.clone()),
+                farmer_verified: farmer.as_ref().is_some_and(|f| f.verified),
+                stage: status.stage_label(),
+                stage_code: status.stage(),
+                ai_scored: status.ai_scored,
+            })
+        }
+        None => None,
+    };
+
+    Ok(ConsumerTrace {
+        sku_id: sku_id.to_string(),
+        verified: packaged_at > 0,
+        packaged_at,
+        parent_batch_hash: format_hash(parent_batch_hash),
+        merkle_root: format_hash(merkle_root),
+        package_type: packaging_field("package_type"),
+        expiry_date: packaging_field("expiry_date"),
+        batch,
+    })
+}
+
+// ======================== HANDLER ========================
+
+#[derive(Debug, Deserialize)]
+pub struct PublicTraceQuery {
+    #[serde(default)]
+    pub brand: Option<String>,
+    /// Stable anonymous visitor ID used for experiment assignment
+    #[serde(default)]
+    pub visitor: Option<String>,
+}
+
+pub async fn get_public_trace(
+    State(state): State<AppState>,
+    Path(sku_id): Path<String>,
+    Query(query): Query<PublicTraceQuery>,
+) -> ApiResult<PublicTraceResponse> {
+    let (brand_id, profile) = state
+        .brands
+        .resolve(query.brand.as_deref())
+        .ok_or_else(|| {
+            ApiError::not_found(format!(
+                "Unknown brand '{}'",
+                query.brand.as_deref().unwrap_or_default()
+            ))
+        })?;
+
+    let trace = build_trace(&state, &sku_id).await?;
+    if !trace.verified {
+        return Err(ApiError::not_found(format!("SKU {} not found", sku_id)));
+    }
+
+    let mut story = &profile.story;
+    let mut highlights = &profile.highlight_certifications;
+    let mut experiment = None;
+
+    if let Some(active) = state.experiments.find(&sku_id, brand_id) {
+        let unit = query.visitor.as_deref().unwrap_or(&sku_id);
+        if let Some(variant) = active.assign(unit) {
+            story = variant.story.as_ref().unwrap_or(story);
+            highlights = variant
+                .highlight_certifications
+                .as_ref()
+                .unwrap_or(highlights);
+            state.experiments.record_exposure(&active.id, &variant.id);
+            experiment = Some(ExperimentAssignment {
+                experiment_id: active.id.clone(),
+                variant_id: variant.id.clone(),
+            });
+        }
+    }
+
+    let branding = Branding {
+        brand: brand_id.to_string(),
+        display_name: profile.display_name.clone(),
+        logo_cid: profile.logo_cid.clone(),
+        logo_url: profile.logo_cid.as_deref().map(ipfs_gateway_url),
+        accent_color: profile.accent_color.clone(),
+        story: render_story(story, &trace),
+    };
+    let certifications = highlighted_certifications(highlights, &trace);
+
+    Ok(Json(PublicTraceResponse {
+        trace,
+        branding,
+        certifications,
+        experiment,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace() -> ConsumerTrace {
+        ConsumerTrace {
+            sku_id: "SKU-0001".to_string(),
+            verified: true,
+            packaged_at: 1_700_000_000,
+            parent_batch_hash: String::new(),
+            merkle_root: String::new(),
+            package_type: None,
+            expiry_date: None,
+            batch: Some(TraceBatch {
+                batch_id: "21".to_string(),
+                crop_type: Some("mustard".to_string()),
+                quality_grade: Some("organic".to_string()),
+                quantity_kg: Some(100.0),
+                purchased_at: None,
+                farmer_location: Some("Punjab".to_string()),
+                farmer_verified: false,
+                stage: "Packaged",
+                stage_code: "packaged",
+                ai_scored: true,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_render_story_placeholders() {
+        let story = render_story("{grade} {crop} from {location}, lot {batch_id}", &trace());
+        assert_eq!(story, "organic mustard from Punjab, lot 21");
+
+        let mut unknown = trace();
+        unknown.batch = None;
+        assert_eq!(
+            render_story("{crop} from {location}", &unknown),
+            "oilseed from India"
+        );
+    }
+
+    #[test]
+    fn test_only_achieved_highlights_in_brand_order() {
+        let highlights = vec![
+            "organic".to_string(),
+            "farmer_verified".to_string(),
+            "blockchain_verified".to_string(),
+            "fssai".to_string(),
+        ];
+
+        let ids: Vec<String> = highlighted_certifications(&highlights, &trace())
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(ids, vec!["organic", "blockchain_verified"]);
+    }
+
+    #[test]
+    fn test_resolve_brand() {
+        let registry = BrandRegistry::fallback();
+        assert_eq!(registry.resolve(None).unwrap().0, "default");
+        assert!(registry.resolve(Some("other")).is_none());
+    }
+}
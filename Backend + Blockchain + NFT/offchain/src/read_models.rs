@@ -0,0 +1,511 @@
+//! Read models of batches, SKUs and farmers
+//!
+//! Every event the indexer stores is folded, in the same transaction, into
+//! summaries kept next to it in `data/events.db`, so they never disagree with
+//! the indexed history:
+//!
+//! - `GET /api/batches/:batch_id/summary` - custody stage, farmer, custodian,
+//!   processing inputs and outputs, SKUs, fraud reports and AI score state
+//! - `GET /api/skus/:sku_id/summary` - parent batch, Merkle root, packaging
+//!   time and fraud reports
+//! - `GET /api/farmer/:farmer_did/summary` - registration, crop, profile CID
+//!   and FPO purchases
+//!
+//! Each is a primary-key lookup. An index built before the summaries existed
+//! is folded into them once when it is opened (see
+//! [`crate::indexer::EventIndex::connect`]).
+
+use crate::chain::hash_string;
+use crate::did_resolver::chain_did;
+use crate::error::{format_hash, ApiError, ApiResult};
+use crate::indexer::IndexedEvent;
+use crate::state::AppState;
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sqlx::SqliteConnection;
+
+/// `transferType` of the FPO purchase, the first custody change of a batch
+const TRANSFER_FPO_PURCHASE: u64 = 1;
+
+/// A summary folded from the events concerning one id
+pub trait ReadModel: Serialize + DeserializeOwned {
+    const TABLE: &'static str;
+
+    fn new(id: &str) -> Self;
+
+    fn fold(&mut self, event: &IndexedEvent);
+}
+
+// ======================== MODELS ========================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStage {
+    Purchased,
+    InWarehouse,
+    AtProcessor,
+    AtRetail,
+    /// Transformed into output batches
+    Processed,
+    /// Created by processing another batch
+    ProcessingOutput,
+    Packaged,
+}
+
+impl BatchStage {
+    fn from_transfer_type(code: u64) -> Option<Self> {
+        match code {
+            1 => Some(BatchStage::Purchased),
+            2 => Some(BatchStage::InWarehouse),
+            3 => Some(BatchStage::AtProcessor),
+            4 => Some(BatchStage::AtRetail),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchStatus {
+    pub batch_hash: String,
+    /// `None` until a custody, processing or packaging event
+    pub stage: Option<BatchStage>,
+    /// Farmer the FPO bought the batch from
+    pub farmer_did: Option<String>,
+    /// Address of the latest custodian
+    pub custodian: Option<String>,
+    /// Input batch, for a processing output
+    pub parent_batch_hash: Option<String>,
+    pub output_batch_hashes: Vec<String>,
+    pub sku_ids: Vec<String>,
+    pub fraud_reports: u64,
+    /// "committed" or "revealed"
+    pub ai_score: Option<String>,
+    /// CID of the latest custody or processing record
+    pub metadata_cid: Option<String>,
+    pub events: u64,
+    pub first_event_at: u64,
+    pub last_event: String,
+    pub last_event_at: u64,
+}
+
+impl ReadModel for BatchStatus {
+    const TABLE: &'static str = "batch_status";
+
+    fn new(id: &str) -> Self {
+        BatchStatus {
+            batch_hash: id.to_string(),
+            stage: None,
+            farmer_did: None,
+            custodian: None,
+            parent_batch_hash: None,
+            output_batch_hashes: Vec::new(),
+            sku_ids: Vec::new(),
+            fraud_reports: 0,
+            ai_score: None,
+            metadata_cid: None,
+            events: 0,
+            first_event_at: 0,
+            last_event: String::new(),
+            last_event_at: 0,
+        }
+    }
+
+    fn fold(&mut self, event: &IndexedEvent) {
+        if self.events == 0 {
+            self.first_event_at = event.timestamp;
+        }
+        self.events += 1;
+        self.last_event = event.event.clone();
+        self.last_event_at = event.timestamp;
+
+        match event.event.as_str() {
+            "OwnershipTransfer" => {
+                let code = event.fields["transfer_type"].as_u64().unwrap_or_default();
+                if code == TRANSFER_FPO_PURCHASE {
+                    self.farmer_did = event.farmer_did.clone();
+                }
+                self.stage = BatchStage::from_transfer_type(code).or(self.stage);
+                self.custodian = event.fields["to_address"].as_str().map(String::from);
+                self.metadata_cid = event.metadata_cid.clone();
+            }
+            "BatchProcessed" if event.subject == self.batch_hash => {
+                self.stage = Some(BatchStage::Processed);
+                self.output_batch_hashes =
+                    serde_json::from_value(event.fields["output_batch_hashes"].clone())
+                        .unwrap_or_default();
+                self.metadata_cid = event.metadata_cid.clone();
+            }
+            "BatchProcessed" => {
+                self.stage = Some(BatchStage::ProcessingOutput);
+                self.parent_batch_hash = Some(event.subject.clone());
+                self.metadata_cid = event.metadata_cid.clone();
+            }
+            "SKUPackaged" => {
+                self.stage = Some(BatchStage::Packaged);
+                if !self.sku_ids.contains(&event.subject) {
+                    self.sku_ids.push(event.subject.clone());
+                }
+            }
+            "FraudDetected" => self.fraud_reports += 1,
+            "AIScoreCommitted" => self.ai_score = Some("committed".to_string()),
+            "AIScoreRevealed" => self.ai_score = Some("revealed".to_string()),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SkuSummary {
+    pub sku_id: String,
+    pub parent_batch_hash: Option<String>,
+    pub merkle_root: Option<String>,
+    pub packaged_at: Option<u64>,
+    pub metadata_cid: Option<String>,
+    pub fraud_reports: u64,
+    pub last_fraud_at: Option<u64>,
+    pub evidence_cids: Vec<String>,
+}
+
+impl ReadModel for SkuSummary {
+    const TABLE: &'static str = "sku_summary";
+
+    fn new(id: &str) -> Self {
+        SkuSummary {
+            sku_id: id.to_string(),
+            parent_batch_hash: None,
+            merkle_root: None,
+            packaged_at: None,
+            metadata_cid: None,
+            fraud_reports: 0,
+            last_fraud_at: None,
+            evidence_cids: Vec::new(),
+        }
+    }
+
+    fn fold(&mut self, event: &IndexedEvent) {
+        match event.event.as_str() {
+            "SKUPackaged" => {
+                self.parent_batch_hash = event.batch_hashes.first().cloned();
+                self.merkle_root = event.fields["merkle_root"].as_str().map(String::from);
+                self.packaged_at = Some(event.timestamp);
+                self.metadata_cid = event.metadata_cid.clone();
+            }
+            "FraudDetected" => {
+                self.fraud_reports += 1;
+                self.last_fraud_at = Some(event.timestamp);
+                self.evidence_cids.extend(event.metadata_cid.clone());
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FarmerSummary {
+    pub farmer_did: String,
+    pub registered_at: Option<u64>,
+    /// Crop hash of the latest registration or update
+    pub crop_id_hash: Option<String>,
+    /// Latest profile CID
+    pub metadata_cid: Option<String>,
+    pub profile_updates: u64,
+    /// Batches the FPO bought from the farmer, in chain order
+    pub purchased_batches: Vec<String>,
+    pub last_purchase_at: Option<u64>,
+}
+
+impl ReadModel for FarmerSummary {
+    const TABLE: &'static str = "farmer_summary";
+
+    fn new(id: &str) -> Self {
+        FarmerSummary {
+            farmer_did: id.to_string(),
+            registered_at: None,
+            crop_id_hash: None,
+            metadata_cid: None,
+            profile_updates: 0,
+            purchased_batches: Vec::new(),
+            last_purchase_at: None,
+        }
+    }
+
+    fn fold(&mut self, event: &IndexedEvent) {
+        match event.event.as_str() {
+            "FarmerRegistered" | "FarmerUpdated" => {
+                if event.event == "FarmerRegistered" {
+                    self.registered_at = Some(event.timestamp);
+                } else {
+                    self.profile_updates += 1;
+                }
+                self.crop_id_hash = event.fields["crop_id_hash"].as_str().map(String::from);
+                self.metadata_cid = event.metadata_cid.clone();
+            }
+            "OwnershipTransfer" => {
+                if !self.purchased_batches.contains(&event.subject) {
+                    self.purchased_batches.push(event.subject.clone());
+                }
+                self.last_purchase_at = Some(event.timestamp);
+            }
+            _ => {}
+        }
+    }
+}
+
+// ======================== STORAGE ========================
+
+/// Fold `event` into the model stored for `id`, unless it already has it
+async fn update<M: ReadModel>(
+    conn: &mut SqliteConnection,
+    id: &str,
+    event: &IndexedEvent,
+) -> Result<()> {
+    let sql = format!(
+        "SELECT model, block_number, log_index FROM {} WHERE id = $1",
+        M::TABLE
+    );
+    let stored: Option<(String, i64, i64)> = sqlx::query_as(&sql)
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    let mut model = match stored {
+        Some((_, block_number, log_index))
+            if (block_number as u64, log_index as u64) >= (event.block_number, event.log_index) =>
+        {
+            return Ok(());
+        }
+        Some((model, ..)) => serde_json::from_str(&model)?,
+        None => M::new(id),
+    };
+    model.fold(event);
+
+    let sql = format!(
+        "INSERT INTO {} (id, block_number, log_index, model) VALUES ($1, $2, $3, $4) \
+         ON CONFLICT (id) DO UPDATE SET block_number = excluded.block_number, \
+         log_index = excluded.log_index, model = excluded.model",
+        M::TABLE
+    );
+    sqlx::query(&sql)
+        .bind(id)
+        .bind(event.block_number as i64)
+        .bind(event.log_index as i64)
+        .bind(serde_json::to_string(&model)?)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Fold a stored event into every model it concerns
+pub(crate) async fn apply(conn: &mut SqliteConnection, event: &IndexedEvent) -> Result<()> {
+    match event.event.as_str() {
+        "SKUPackaged" | "FraudDetected" => {
+            update::<SkuSummary>(conn, &event.subject, event).await?;
+        }
+        "FarmerRegistered" | "FarmerUpdated" => {
+            update::<FarmerSummary>(conn, &event.subject, event).await?;
+        }
+        "OwnershipTransfer"
+            if event.fields["transfer_type"].as_u64() == Some(TRANSFER_FPO_PURCHASE) =>
+        {
+            if let Some(farmer_did) = &event.farmer_did {
+                update::<FarmerSummary>(conn, farmer_did, event).await?;
+            }
+        }
+        _ => {}
+    }
+    for batch_hash in &event.batch_hashes {
+        update::<BatchStatus>(conn, batch_hash, event).await?;
+    }
+    Ok(())
+}
+
+/// The model stored for `id`
+pub(crate) async fn load<M: ReadModel>(conn: &mut SqliteConnection, id: &str) -> Result<Option<M>> {
+    let sql = format!("SELECT model FROM {} WHERE id = $1", M::TABLE);
+    let model: Option<String> = sqlx::query_scalar(&sql)
+        .bind(id)
+        .fetch_optional(conn)
+        .await?;
+    Ok(model.map(|m| serde_json::from_str(&m)).transpose()?)
+}
+
+/// No model has been stored yet
+pub(crate) async fn is_empty(conn: &mut SqliteConnection) -> Result<bool> {
+    let any: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM batch_status) OR EXISTS (SELECT 1 FROM sku_summary) \
+         OR EXISTS (SELECT 1 FROM farmer_summary)",
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(!any)
+}
+
+// ======================== HANDLERS ========================
+
+pub async fn batch_summary(
+    State(state): State<AppState>,
+    Path(batch_id): Path<String>,
+) -> ApiResult<BatchStatus> {
+    let batch_hash = format_hash(hash_string(&batch_id));
+    state
+        .events
+        .read_model(&batch_hash)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("No indexed events for batch {}", batch_id)))
+}
+
+pub async fn sku_summary(
+    State(state): State<AppState>,
+    Path(sku_id): Path<String>,
+) -> ApiResult<SkuSummary> {
+    let sku_hash = format_hash(hash_string(&sku_id));
+    state
+        .events
+        .read_model(&sku_hash)
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("No indexed events for SKU {}", sku_id)))
+}
+
+pub async fn farmer_summary(
+    State(state): State<AppState>,
+    Path(farmer_did): Path<String>,
+) -> ApiResult<FarmerSummary> {
+    let did = chain_did(&farmer_did).map_err(ApiError::invalid_did)?;
+    state
+        .events
+        .read_model(&format_hash(did))
+        .await?
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("No indexed events for farmer {}", farmer_did)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(
+        position: u64,
+        event: &str,
+        subject: &str,
+        batch_hashes: &[&str],
+        fields: serde_json::Value,
+    ) -> IndexedEvent {
+        IndexedEvent {
+            block_number: position,
+            log_index: 0,
+            tx_hash: String::new(),
+            contract: String::new(),
+            event: event.to_string(),
+            timestamp: 1_700_000_000 + position,
+            subject: subject.to_string(),
+            farmer_did: Some("0xfarmer".to_string()),
+            metadata_cid: Some(format!("Qm{}", position)),
+            batch_hashes: batch_hashes.iter().map(|b| b.to_string()).collect(),
+            fields,
+        }
+    }
+
+    #[test]
+    fn test_batch_status_follows_the_lifecycle() {
+        let events = [
+            event(
+                1,
+                "OwnershipTransfer",
+                "0xb1",
+                &["0xb1"],
+                json!({"transfer_type": 1, "to_address": "0xfpo"}),
+            ),
+            event(
+                2,
+                "BatchProcessed",
+                "0xb1",
+                &["0xb1", "0xb2"],
+                json!({"output_batch_hashes": ["0xb2"]}),
+            ),
+            event(3, "SKUPackaged", "0xs1", &["0xb2"], json!({})),
+            event(4, "FraudDetected", "0xs1", &["0xb2"], json!({})),
+        ];
+
+        let mut input = BatchStatus::new("0xb1");
+        let mut output = BatchStatus::new("0xb2");
+        for e in &events {
+            if e.batch_hashes.contains(&input.batch_hash) {
+                input.fold(e);
+            }
+            if e.batch_hashes.contains(&output.batch_hash) {
+                output.fold(e);
+            }
+        }
+
+        assert_eq!(input.stage, Some(BatchStage::Processed));
+        assert_eq!(input.farmer_did.as_deref(), Some("0xfarmer"));
+        assert_eq!(input.custodian.as_deref(), Some("0xfpo"));
+        assert_eq!(input.output_batch_hashes, vec!["0xb2".to_string()]);
+        assert_eq!(input.events, 2);
+
+        assert_eq!(output.stage, Some(BatchStage::Packaged));
+        assert_eq!(output.parent_batch_hash.as_deref(), Some("0xb1"));
+        assert_eq!(output.sku_ids, vec!["0xs1".to_string()]);
+        assert_eq!(output.fraud_reports, 1);
+        assert_eq!(output.first_event_at, 1_700_000_002);
+        assert_eq!(output.last_event, "FraudDetected");
+    }
+
+    #[tokio::test]
+    async fn test_stored_events_update_models_once() {
+        use crate::indexer::EventIndex;
+        use sqlx::sqlite::SqliteConnectOptions;
+
+        let options = "sqlite::memory:".parse::<SqliteConnectOptions>().unwrap();
+        let index = EventIndex::connect(options, 1).await.unwrap();
+        let events = vec![
+            event(
+                1,
+                "FarmerRegistered",
+                "0xfarmer",
+                &[],
+                json!({"crop_id_hash": "0xc1"}),
+            ),
+            event(
+                2,
+                "OwnershipTransfer",
+                "0xb1",
+                &["0xb1"],
+                json!({"transfer_type": 1, "to_address": "0xfpo"}),
+            ),
+            event(
+                3,
+                "SKUPackaged",
+                "0xs1",
+                &["0xb1"],
+                json!({"merkle_root": "0xr"}),
+            ),
+        ];
+        index.store(events.clone(), 4).await.unwrap();
+        // Indexed again, e.g. from an earlier INDEXER_START_BLOCK
+        index.store(events, 4).await.unwrap();
+
+        let farmer: FarmerSummary = index.read_model("0xfarmer").await.unwrap().unwrap();
+        assert_eq!(farmer.registered_at, Some(1_700_000_001));
+        assert_eq!(farmer.purchased_batches, vec!["0xb1".to_string()]);
+
+        let batch: BatchStatus = index.read_model("0xb1").await.unwrap().unwrap();
+        assert_eq!(batch.events, 2);
+        assert_eq!(batch.stage, Some(BatchStage::Packaged));
+
+        let sku: SkuSummary = index.read_model("0xs1").await.unwrap().unwrap();
+        assert_eq!(sku.parent_batch_hash.as_deref(), Some("0xb1"));
+        assert_eq!(sku.merkle_root.as_deref(), Some("0xr"));
+        assert!(index
+            .read_model::<SkuSummary>("0xs2")
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,274 @@
+//! Startup warmup of the chain and IPFS dependencies
+//!
+//! The server starts serving once its local state is loaded, even if the RPC
+//! node or Pinata are briefly unreachable:
+//!
+//! - A chain client whose RPC does not answer the startup validation is kept
+//!   but not ready (see [`crate::chain::ChainClient::start`]). An RPC that
+//!   answers with the wrong chain or no contract code still fails the start.
+//! - IPFS is ready once Pinata answers its authentication test.
+//!
+//! [`spawn`] retries each dependency that is not ready with exponential
+//! backoff, from WARMUP_RETRY_BASE_MS (default 1000) doubling up to
+//! WARMUP_RETRY_MAX_SECS (default 60). Until then, contract writes go to the
+//! outbox and pins fail with [`Unavailable`], which handlers answer with 503;
+//! reads and local endpoints are served as usual (see [`crate::degradation`]).
+//! A chain reload swaps in validated clients, which are ready at once.
+//!
+//! After the start, calls that fail and succeed again mark a dependency down
+//! and up; that only changes what is reported. `GET /api/readiness` reports
+//! each dependency.
+
+use crate::error::ApiResult;
+use crate::state::AppState;
+use anyhow::Result;
+use axum::{extract::State, Json};
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const DEFAULT_RETRY_BASE_MS: u64 = 1000;
+const DEFAULT_RETRY_MAX_SECS: u64 = 60;
+
+/// A dependency that has not come up since the start
+#[derive(Debug)]
+pub struct Unavailable {
+    pub dependency: String,
+}
+
+impl std::fmt::Display for Unavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is not reachable yet; the server keeps retrying, try again shortly",
+            self.dependency
+        )
+    }
+}
+
+impl std::error::Error for Unavailable {}
+
+/// Warmup progress of one dependency
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DependencyStatus {
+    pub ready: bool,
+    /// Failed attempts so far
+    pub attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ready_at: Option<String>,
+    /// Since when its calls have been failing, after the start
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub down_since: Option<String>,
+}
+
+/// Whether a client's dependency is up; shared by the client's clones
+#[derive(Debug, Default)]
+pub struct Readiness {
+    ready: AtomicBool,
+    status: Mutex<DependencyStatus>,
+}
+
+impl Readiness {
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+
+    /// `Err` while `dependency` is not ready
+    pub fn check(&self, dependency: &str) -> Result<(), Unavailable> {
+        match self.is_ready() {
+            true => Ok(()),
+            false => Err(Unavailable {
+                dependency: dependency.to_string(),
+            }),
+        }
+    }
+
+    pub fn mark_ready(&self) {
+        let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+        if !status.ready {
+            status.ready = true;
+            status.ready_at = Some(Utc::now().to_rfc3339());
+        }
+        self.ready.store(true, Ordering::Release);
+    }
+
+    /// Record a failed attempt; returns the number of attempts so far
+    pub fn record_failure(&self, error: &anyhow::Error) -> u32 {
+        let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+        status.attempts += 1;
+        status.last_error = Some(format!("{:#}", error));
+        status.attempts
+    }
+
+    /// Record a failed call after the start
+    pub fn record_down(&self, error: &anyhow::Error) {
+        let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+        status
+            .down_since
+            .get_or_insert_with(|| Utc::now().to_rfc3339());
+        status.last_error = Some(format!("{:#}", error));
+    }
+
+    /// Record a successful call
+    pub fn record_up(&self) {
+        let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+        status.down_since = None;
+    }
+
+    /// Not ready yet, or its last call failed
+    pub fn is_degraded(&self) -> bool {
+        !self.is_ready() || self.status().down_since.is_some()
+    }
+
+    pub fn status(&self) -> DependencyStatus {
+        self.status
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Delay after a failed attempt: `base` doubled per earlier failure, capped
+/// at `max`
+pub fn backoff(attempt: u32, base: Duration, max: Duration) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(max)
+}
+
+/// Run `probe` until it succeeds, then mark `readiness` ready
+async fn warm_up<F, Fut>(dependency: String, readiness: Arc<Readiness>, mut probe: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let base = Duration::from_millis(env_u64("WARMUP_RETRY_BASE_MS", DEFAULT_RETRY_BASE_MS).max(1));
+    let max =
+        Duration::from_secs(env_u64("WARMUP_RETRY_MAX_SECS", DEFAULT_RETRY_MAX_SECS)).max(base);
+    loop {
+        match probe().await {
+            Ok(()) => {
+                readiness.mark_ready();
+                tracing::info!(dependency = %dependency, "Dependency is up");
+                return;
+            }
+            Err(e) => {
+                let attempt = readiness.record_failure(&e);
+                let delay = backoff(attempt, base, max);
+                tracing::warn!(
+                    dependency = %dependency,
+                    attempt,
+                    retry_in_ms = delay.as_millis() as u64,
+                    error = %format!("{:#}", e),
+                    "Dependency not ready"
+                );
+                tokio::time::sleep(delay).await;
+            }
+        }
+    }
+}
+
+/// Retry every chain network and IPFS that is not ready yet
+pub fn spawn(state: AppState) {
+    for client in state.networks.clients() {
+        if client.readiness().is_ready() {
+            continue;
+        }
+        let networks = state.networks.clone();
+        let dependency = format!("chain:{}", client.network());
+        tokio::spawn(warm_up(dependency, client.readiness(), move || {
+            let networks = networks.clone();
+            let client = client.clone();
+            async move {
+                // A reload replaced the client with a validated one
+                if !networks.clients().iter().any(|c| Arc::ptr_eq(c, &client)) {
+                    return Ok(());
+                }
+                client.validate().await
+            }
+        }));
+    }
+
+    let ipfs = state.ipfs_client.clone();
+    if !ipfs.readiness().is_ready() {
+        tokio::spawn(warm_up("ipfs".to_string(), ipfs.readiness(), move || {
+            let ipfs = ipfs.clone();
+            async move { ipfs.probe().await }
+        }));
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessResponse {
+    /// Every dependency is up
+    pub ready: bool,
+    /// By network name
+    pub chain: BTreeMap<String, DependencyStatus>,
+    pub ipfs: DependencyStatus,
+}
+
+/// Warmup status of the chain networks and IPFS
+pub async fn readiness(State(state): State<AppState>) -> ApiResult<ReadinessResponse> {
+    let chain: BTreeMap<String, DependencyStatus> = state
+        .networks
+        .clients()
+        .iter()
+        .map(|c| (c.network().to_string(), c.readiness().status()))
+        .collect();
+    let ipfs = state.ipfs_client.readiness().status();
+    Ok(Json(ReadinessResponse {
+        ready: ipfs.ready && chain.values().all(|s| s.ready),
+        chain,
+        ipfs,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let base = Duration::from_millis(500);
+        let max = Duration::from_secs(5);
+        let delays: Vec<u64> = (1..=6)
+            .map(|attempt| backoff(attempt, base, max).as_millis() as u64)
+            .collect();
+        assert_eq!(delays, vec![500, 1000, 2000, 4000, 5000, 5000]);
+        assert_eq!(backoff(u32::MAX, base, max), max);
+    }
+
+    #[test]
+    fn test_readiness_records_attempts_until_ready() {
+        let readiness = Readiness::default();
+        assert!(readiness.check("ipfs").is_err());
+        assert_eq!(readiness.record_failure(&anyhow::anyhow!("refused")), 1);
+        assert_eq!(readiness.record_failure(&anyhow::anyhow!("refused")), 2);
+
+        readiness.mark_ready();
+        assert!(readiness.check("ipfs").is_ok());
+        let status = readiness.status();
+        assert_eq!(status.attempts, 2);
+        assert!(status.ready_at.is_some());
+        assert!(!readiness.is_degraded());
+
+        readiness.record_down(&anyhow::anyhow!("timed out"));
+        assert!(readiness.is_degraded());
+        readiness.record_up();
+        assert!(!readiness.is_degraded());
+    }
+}
@@ -0,0 +1,290 @@
+//! Reference data: enum codes and their display labels
+//!
+//! API payloads carry codes (`"A"`, `"tractor"`, `"fpo_purchase"`); frontends
+//! fetch the labels to show from `GET /api/reference-data?lang=hi`. English
+//! labels are built in ([`ENUMS`]); other languages are read from
+//! `data/labels.json` (override with LABELS_CONFIG_PATH):
+//!
+//! ```json
+//! { "hi": { "transport_method": { "tractor": "ट्रैक्टर", "truck": "ट्रक" } } }
+//! ```
+//!
+//! A label missing from a translation falls back to English. Without `lang`
+//! the first language of the Accept-Language header that has labels is used.
+
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+const DEFAULT_LABELS_CONFIG_PATH: &str = "data/labels.json";
+const ENGLISH: &str = "en";
+
+type Labels = &'static [(&'static str, &'static str)];
+
+/// Every enum served, as (name, [(code, English label)])
+pub const ENUMS: [(&str, Labels); 7] = [
+    (
+        "quality_grade",
+        &[
+            ("A", "Grade A"),
+            ("B", "Grade B"),
+            ("FAQ", "Fair average quality"),
+            ("C", "Grade C"),
+            ("organic", "Organic"),
+            ("conventional", "Conventional"),
+        ],
+    ),
+    (
+        "transport_method",
+        &[
+            ("tractor", "Tractor"),
+            ("bullock", "Bullock cart"),
+            ("pickup", "Pickup"),
+            ("truck", "Truck"),
+        ],
+    ),
+    (
+        "workflow_stage",
+        &[
+            ("farmer_registration", "Farmer registration"),
+            ("fpo_purchase", "FPO purchase"),
+            ("warehouse_storage", "Warehouse storage"),
+            ("logistics", "Logistics"),
+            ("processing", "Processing"),
+            ("packaging", "Packaging"),
+            ("ai_scoring", "AI quality scoring"),
+        ],
+    ),
+    (
+        "batch_stage",
+        &[
+            ("purchased", "Purchased by FPO"),
+            ("processed", "Processed"),
+            ("quality_scored", "Quality scored"),
+            ("packaged", "Packaged"),
+        ],
+    ),
+    (
+        "settlement_status",
+        &[
+            ("clear", "Clear"),
+            ("on_hold", "On hold"),
+            ("released", "Released"),
+        ],
+    ),
+    (
+        "receipt_status",
+        &[
+            ("active", "Active"),
+            ("pledged", "Pledged"),
+            ("closed", "Closed"),
+        ],
+    ),
+    (
+        "role",
+        &[
+            ("farmer", "Farmer"),
+            ("fpo", "FPO"),
+            ("warehouse", "Warehouse"),
+            ("processor", "Processor"),
+            ("lender", "Lender"),
+            ("admin", "Administrator"),
+        ],
+    ),
+];
+
+fn enum_labels(name: &str) -> Option<Labels> {
+    ENUMS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|(_, labels)| *labels)
+}
+
+/// English label of a code, or the code itself if unknown
+pub fn english_label(name: &str, code: &'static str) -> &'static str {
+    enum_labels(name)
+        .and_then(|labels| labels.iter().find(|(c, _)| *c == code))
+        .map(|(_, label)| *label)
+        .unwrap_or(code)
+}
+
+/// language -> enum -> code -> label
+type Translations = HashMap<String, HashMap<String, HashMap<String, String>>>;
+
+pub struct LabelCatalog {
+    translations: Translations,
+}
+
+impl LabelCatalog {
+    pub fn load() -> Result<Self> {
+        let path = std::env::var("LABELS_CONFIG_PATH")
+            .unwrap_or_else(|_| DEFAULT_LABELS_CONFIG_PATH.to_string());
+
+        let translations: Translations = if std::path::Path::new(&path).exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read labels {}", path))?;
+            serde_json::from_str(&content).with_context(|| format!("Invalid labels {}", path))?
+        } else {
+            HashMap::new()
+        };
+
+        for (language, enums) in &translations {
+            for (name, labels) in enums {
+                let Some(known) = enum_labels(name) else {
+                    bail!("Labels {}: unknown enum {}", language, name);
+                };
+                if let Some(code) = labels.keys().find(|c| !known.iter().any(|(k, _)| k == c)) {
+                    bail!("Labels {}: unknown {} code {}", language, name, code);
+                }
+            }
+        }
+
+        Ok(Self { translations })
+    }
+
+    pub fn languages(&self) -> Vec<String> {
+        let mut languages: Vec<String> = self.translations.keys().cloned().collect();
+        languages.push(ENGLISH.to_string());
+        languages.sort();
+        languages.dedup();
+        languages
+    }
+
+    fn supports(&self, language: &str) -> bool {
+        language == ENGLISH || self.translations.contains_key(language)
+    }
+
+    /// Labels of every enum in `language`, falling back to English
+    pub fn localized(&self, language: &str) -> BTreeMap<&'static str, Vec<EnumValue>> {
+        let translated = self.translations.get(language);
+        ENUMS
+            .iter()
+            .map(|(name, labels)| {
+                let names = translated.and_then(|t| t.get(*name));
+                let values = labels
+                    .iter()
+                    .map(|(code, english)| EnumValue {
+                        code,
+                        label: names
+                            .and_then(|n| n.get(*code))
+                            .cloned()
+                            .unwrap_or_else(|| english.to_string()),
+                        label_en: english,
+                    })
+                    .collect();
+                (*name, values)
+            })
+            .collect()
+    }
+}
+
+/// First Accept-Language tag with labels, e.g. "hi" from "hi-IN,hi;q=0.9"
+fn preferred_language(headers: &HeaderMap, catalog: &LabelCatalog) -> Option<String> {
+    headers
+        .get(header::ACCEPT_LANGUAGE)?
+        .to_str()
+        .ok()?
+        .split(',')
+        .filter_map(|tag| tag.split(';').next())
+        .map(|tag| tag.trim().split('-').next().unwrap_or("").to_lowercase())
+        .find(|tag| catalog.supports(tag))
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct ReferenceDataParams {
+    pub lang: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EnumValue {
+    pub code: &'static str,
+    pub label: String,
+    pub label_en: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReferenceData {
+    pub language: String,
+    pub languages: Vec<String>,
+    pub enums: BTreeMap<&'static str, Vec<EnumValue>>,
+}
+
+/// Display labels of every enum code in one language
+pub async fn get_reference_data(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<ReferenceDataParams>,
+) -> ApiResult<ReferenceData> {
+    let catalog = &state.labels;
+    let language = match params.lang {
+        Some(lang) => {
+            let lang = lang.trim().to_lowercase();
+            if !catalog.supports(&lang) {
+                return Err(ApiError::bad_request(format!(
+                    "No labels for language {}; available: {}",
+                    lang,
+                    catalog.languages().join(", ")
+                )));
+            }
+            lang
+        }
+        None => preferred_language(&headers, catalog).unwrap_or_else(|| ENGLISH.to_string()),
+    };
+
+    Ok(Json(ReferenceData {
+        enums: catalog.localized(&language),
+        languages: catalog.languages(),
+        language,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflows::STAGES;
+
+    #[test]
+    fn test_workflow_stage_codes_match_workflow() {
+        let codes: Vec<&str> = enum_labels("workflow_stage")
+            .unwrap()
+            .iter()
+            .map(|(code, _)| *code)
+            .collect();
+        assert_eq!(codes, STAGES);
+    }
+
+    #[test]
+    fn test_missing_translation_falls_back_to_english() {
+        let translations = HashMap::from([(
+            "hi".to_string(),
+            HashMap::from([(
+                "transport_method".to_string(),
+                HashMap::from([("truck".to_string(), "ट्रक".to_string())]),
+            )]),
+        )]);
+        let catalog = LabelCatalog { translations };
+
+        let labels = catalog.localized("hi");
+        let transport = &labels["transport_method"];
+        let label = |code: &str| &transport.iter().find(|v| v.code == code).unwrap().label;
+        assert_eq!(label("truck"), "ट्रक");
+        assert_eq!(label("tractor"), "Tractor");
+        assert_eq!(catalog.languages(), vec!["en", "hi"]);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT_LANGUAGE, "mr-IN,hi;q=0.8".parse().unwrap());
+        assert_eq!(
+            preferred_language(&headers, &catalog).as_deref(),
+            Some("hi")
+        );
+    }
+}
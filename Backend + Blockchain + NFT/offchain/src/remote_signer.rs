@@ -0,0 +1,138 @@
+//! Signing through a remote signer
+//!
+//! Production keys live in a KMS or HSM rather than in `.env`. Any signer
+//! key (PRIVATE_KEY, PRIVATE_KEY_<ROLE> or an entry of SIGNER_KEYS_PATH) may
+//! be given as `remote:0x<address>` instead of a private key; transactions
+//! for that address are then signed by the JSON-RPC endpoint at
+//! REMOTE_SIGNER_URL with `eth_signTransaction`, and only the signed bytes
+//! come back. This is the interface of Web3Signer (which keeps keys in AWS
+//! KMS, Azure Key Vault, HashiCorp Vault or a YubiHSM), of Clef, and of the
+//! small signing proxies usually put in front of AWS or GCP KMS.
+//!
+//! REMOTE_SIGNER_TOKEN, when set, is sent as a bearer token. Startup
+//! validation checks that the signer serves every remote address through
+//! `eth_accounts`.
+
+use alloy::{
+    primitives::{Address, Bytes},
+    rpc::types::{TransactionInput, TransactionRequest},
+};
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct RemoteSigner {
+    url: String,
+    token: Option<String>,
+    http: Client,
+}
+
+impl RemoteSigner {
+    /// `None` when REMOTE_SIGNER_URL is not set
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(url) = std::env::var("REMOTE_SIGNER_URL")
+            .ok()
+            .filter(|v| !v.is_empty())
+        else {
+            return Ok(None);
+        };
+        url.parse::<reqwest::Url>()
+            .with_context(|| format!("REMOTE_SIGNER_URL '{}' is not a valid URL", url))?;
+
+        Ok(Some(Self {
+            url,
+            token: std::env::var("REMOTE_SIGNER_TOKEN")
+                .ok()
+                .filter(|v| !v.is_empty()),
+            http: Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .context("Failed to build remote signer client")?,
+        }))
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let mut request = self.http.post(&self.url).json(&json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        }));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response: Value = request
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .with_context(|| format!("Remote signer {} request failed", method))?
+            .json()
+            .await
+            .with_context(|| format!("Remote signer {} returned invalid JSON", method))?;
+
+        if let Some(error) = response.get("error") {
+            bail!("Remote signer {} failed: {}", method, error);
+        }
+        response
+            .get("result")
+            .cloned()
+            .ok_or_else(|| anyhow!("Remote signer {} returned no result", method))
+    }
+
+    /// Addresses the signer holds keys for
+    pub async fn accounts(&self) -> Result<Vec<Address>> {
+        let result = self.call("eth_accounts", json!([])).await?;
+        serde_json::from_value(result).context("Remote signer eth_accounts returned no addresses")
+    }
+
+    /// Signed, encoded transaction ready for eth_sendRawTransaction
+    pub async fn sign_transaction(&self, tx: &TransactionRequest) -> Result<Bytes> {
+        let mut tx = tx.clone();
+        // Signers differ on which of the two names they read
+        if let Some(input) = tx.input.input().cloned() {
+            tx.input = TransactionInput::both(input);
+        }
+        let result = self.call("eth_signTransaction", json!([tx])).await?;
+        raw_transaction(&result)
+    }
+}
+
+/// Raw bytes from an eth_signTransaction result: a hex string (Web3Signer)
+/// or an object with a `raw` field (Clef, geth)
+fn raw_transaction(result: &Value) -> Result<Bytes> {
+    let raw = match result {
+        Value::String(raw) => raw,
+        Value::Object(fields) => fields
+            .get("raw")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("Remote signer result has no raw transaction"))?,
+        _ => bail!("Unexpected remote signer result {}", result),
+    };
+    let raw: Bytes = raw
+        .parse()
+        .context("Remote signer returned a malformed transaction")?;
+    if raw.is_empty() {
+        bail!("Remote signer returned an empty transaction");
+    }
+    Ok(raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_transaction_from_either_result_shape() {
+        let web3signer = json!("0x02f86b0180");
+        let clef = json!({ "raw": "0x02f86b0180", "tx": { "nonce": "0x0" } });
+        assert_eq!(
+            raw_transaction(&web3signer).unwrap(),
+            raw_transaction(&clef).unwrap()
+        );
+        assert!(raw_transaction(&json!({ "tx": {} })).is_err());
+        assert!(raw_transaction(&json!("0x")).is_err());
+    }
+}
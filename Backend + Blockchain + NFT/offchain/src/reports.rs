@@ -0,0 +1,999 @@
+//! Scheduled reports
+//!
+//! Schedules are read from `data/reports.json` (override with
+//! REPORTS_CONFIG_PATH):
+//!
+//! ```json
+//! [{ "id": "weekly-procurement", "kind": "procurement_summary",
+//!    "cadence": "weekly", "format": "pdf",
+//!    "recipients": [{ "channel": "email", "to": "dao@agri.gov.in" },
+//!                   { "channel": "whatsapp", "to": "+919800000001" }] }]
+//! ```
+//!
+//! - `procurement_summary` - FPO purchases per farmer district
+//! - `fraud_summary` - fraud reports per SKU
+//!
+//! Weekly reports cover the previous Monday-to-Sunday week and monthly
+//! reports the previous calendar month, in the business calendar of the
+//! schedule's `calendar` state code (see [`crate::business_calendar`]).
+//! Purchases count on their effective business day, so one made after the
+//! cutoff on the last day of a period falls into the next report. Every
+//! REPORTS_CHECK_SECS (default 1 hour) the job generates, on business days
+//! only, each report whose latest period has no run yet, stores the file under `data/reports/` and delivers it: email
+//! recipients get the file attached, SMS and WhatsApp recipients a one-line
+//! summary (WhatsApp template `scheduled_report` unless overridden). Runs
+//! and their per-recipient delivery results are kept in
+//! `data/report_runs.json`.
+
+use crate::admin::require_admin;
+use crate::batch_ledger;
+use crate::business_calendar::BusinessCalendar;
+use crate::chain::hash_string;
+use crate::error::{format_hash, ApiError, ApiResult};
+use crate::export::{self, ExportFormat, Table};
+use crate::indexer::{EventFilter, IndexedEvent};
+use crate::notifications::{Attachment, Channel, Notification};
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+const DEFAULT_REPORTS_CONFIG_PATH: &str = "data/reports.json";
+const RUNS_PATH: &str = "data/report_runs.json";
+const REPORTS_DIR: &str = "data/reports";
+const DEFAULT_CHECK_INTERVAL_SECS: u64 = 3600;
+const DEFAULT_WHATSAPP_TEMPLATE: &str = "scheduled_report";
+/// Fraud events read per report
+const MAX_FRAUD_EVENTS: usize = 10_000;
+const UNKNOWN_DISTRICT: &str = "unknown";
+
+// ======================== CONFIGURATION ========================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportKind {
+    ProcurementSummary,
+    FraudSummary,
+}
+
+impl ReportKind {
+    fn title(self) -> &'static str {
+        match self {
+            ReportKind::ProcurementSummary => "Procurement summary by district",
+            ReportKind::FraudSummary => "Fraud summary",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cadence {
+    Weekly,
+    Monthly,
+}
+
+impl Cadence {
+    /// Latest complete period before `today` as `[start, end)`
+    pub fn last_period(self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        match self {
+            Cadence::Weekly => {
+                let end =
+                    today - ChronoDuration::days(today.weekday().num_days_from_monday() as i64);
+                (end - ChronoDuration::days(7), end)
+            }
+            Cadence::Monthly => {
+                let end = today.with_day(1).unwrap_or(today);
+                let start = if end.month() == 1 {
+                    NaiveDate::from_ymd_opt(end.year() - 1, 12, 1)
+                } else {
+                    NaiveDate::from_ymd_opt(end.year(), end.month() - 1, 1)
+                };
+                (start.unwrap_or(end), end)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryChannel {
+    Email,
+    Sms,
+    Whatsapp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recipient {
+    pub channel: DeliveryChannel,
+    /// Email address or mobile number
+    pub to: String,
+    /// WhatsApp template; defaults to `scheduled_report`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportSchedule {
+    pub id: String,
+    pub kind: ReportKind,
+    pub cadence: Cadence,
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub recipients: Vec<Recipient>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+    /// State code of the business calendar; the default calendar if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calendar: Option<String>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+// ======================== RUNS ========================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryResult {
+    pub channel: DeliveryChannel,
+    pub to: String,
+    pub delivered: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportRun {
+    pub id: u64,
+    pub schedule_id: String,
+    pub kind: ReportKind,
+    /// First day of the period
+    pub period_start: String,
+    /// Day after the period
+    pub period_end: String,
+    pub format: ExportFormat,
+    /// File name under `data/reports/`
+    pub file: String,
+    pub rows: usize,
+    pub sha256: String,
+    pub summary: String,
+    /// "schedule" or "manual"
+    pub trigger: String,
+    pub generated_at: String,
+    pub deliveries: Vec<DeliveryResult>,
+}
+
+pub struct ReportStore {
+    schedules: Vec<ReportSchedule>,
+    runs: Mutex<Vec<ReportRun>>,
+}
+
+impl ReportStore {
+    pub fn load() -> Result<Self> {
+        let path = std::env::var("REPORTS_CONFIG_PATH")
+            .unwrap_or_else(|_| DEFAULT_REPORTS_CONFIG_PATH.to_string());
+
+        let schedules: Vec<ReportSchedule> = if std::path::Path::new(&path).exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read reports config {}", path))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid reports config {}", path))?
+        } else {
+            Vec::new()
+        };
+
+        let mut ids = HashSet::new();
+        for schedule in &schedules {
+            // Schedule IDs end up in report file names
+            if !batch_ledger::is_valid_batch_id(&schedule.id) {
+                bail!("Invalid report schedule ID {:?}", schedule.id);
+            }
+            if !ids.insert(schedule.id.as_str()) {
+                bail!("Duplicate report schedule ID {}", schedule.id);
+            }
+        }
+
+        let runs = match std::fs::read_to_string(RUNS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid report runs file {}", RUNS_PATH))?,
+            Err(_) => Vec::new(),
+        };
+
+        Ok(Self {
+            schedules,
+            runs: Mutex::new(runs),
+        })
+    }
+
+    fn save(runs: &[ReportRun]) -> Result<()> {
+        std::fs::write(RUNS_PATH, serde_json::to_string_pretty(runs)?)
+            .with_context(|| format!("Failed to write {}", RUNS_PATH))
+    }
+
+    pub fn schedules(&self) -> &[ReportSchedule] {
+        &self.schedules
+    }
+}
+
+// ======================== AGGREGATION ========================
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DistrictProcurement {
+    pub district: String,
+    pub purchases: usize,
+    pub farmers: usize,
+    pub quantity_kg: f64,
+    pub total_cost: f64,
+    /// Quantity of purchases recorded with a grade score
+    pub graded_kg: f64,
+    /// Sum of grade score times quantity over those purchases
+    pub grade_score_kg: f64,
+}
+
+impl DistrictProcurement {
+    /// Quantity-weighted grade score (see [`crate::grades`])
+    pub fn avg_grade_score(&self) -> Option<f64> {
+        (self.graded_kg > 0.0).then(|| self.grade_score_kg / self.graded_kg)
+    }
+}
+
+fn purchase_day(purchase: &Value, calendar: &BusinessCalendar) -> Option<NaiveDate> {
+    let timestamp = purchase.get("timestamp")?.as_str()?;
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|t| calendar.effective_date(t.with_timezone(&Utc)))
+}
+
+fn purchase_farmer(purchase: &Value) -> Option<&str> {
+    purchase
+        .pointer("/farmer_info/farmer_did")
+        .and_then(|v| v.as_str())
+}
+
+fn district_of<'a>(districts: &'a HashMap<String, String>, farmer_did: Option<&str>) -> &'a str {
+    farmer_did
+        .and_then(|did| districts.get(did))
+        .map(String::as_str)
+        .filter(|d| !d.is_empty())
+        .unwrap_or(UNKNOWN_DISTRICT)
+}
+
+/// Purchases whose effective business day is in `[start, end)` grouped by
+/// the farmer's district
+pub fn procurement_by_district(
+    purchases: &[(String, Value)],
+    districts: &HashMap<String, String>,
+    calendar: &BusinessCalendar,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Vec<DistrictProcurement> {
+    let mut groups: BTreeMap<&str, (DistrictProcurement, HashSet<&str>)> = BTreeMap::new();
+
+    for (_, purchase) in purchases {
+        if !purchase_day(purchase, calendar).is_some_and(|day| day >= start && day < end) {
+            continue;
+        }
+        let farmer = purchase_farmer(purchase);
+        let district = district_of(districts, farmer);
+        let number = |pointer: &str| {
+            purchase
+                .pointer(pointer)
+                .and_then(|v| v.as_f64())
+                .unwrap_or(0.0)
+        };
+
+        let (row, farmers) = groups.entry(district).or_default();
+        row.purchases += 1;
+        row.quantity_kg += number("/batch_info/quantity_kg");
+        row.total_cost += number("/pricing/total_cost");
+        if let Some(score) = purchase
+            .pointer("/batch_info/grade_score")
+            .and_then(|v| v.as_f64())
+        {
+            row.graded_kg += number("/batch_info/quantity_kg");
+            row.grade_score_kg += score * number("/batch_info/quantity_kg");
+        }
+        if let Some(farmer) = farmer {
+            farmers.insert(farmer);
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(district, (row, farmers))| DistrictProcurement {
+            district: district.to_string(),
+            farmers: farmers.len(),
+            ..row
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkuFraud {
+    /// SKU ID when packaged through this backend, else the on-chain SKU hash
+    pub sku: String,
+    pub batch_id: Option<String>,
+    pub district: String,
+    pub reports: usize,
+    pub reporters: usize,
+    pub first_reported: u64,
+    pub last_reported: u64,
+}
+
+/// Fraud reports grouped by SKU, most reported first
+///
+/// `skus` maps SKU hashes to `(sku_id, batch_id)` and `batch_districts` maps
+/// batch IDs to the supplying farmer's district.
+pub fn fraud_by_sku(
+    events: &[IndexedEvent],
+    skus: &HashMap<String, (String, String)>,
+    batch_districts: &HashMap<String, String>,
+) -> Vec<SkuFraud> {
+    let mut groups: HashMap<&str, (SkuFraud, HashSet<&str>)> = HashMap::new();
+
+    for event in events.iter().filter(|e| e.event == "FraudDetected") {
+        let (row, reporters) = groups.entry(event.subject.as_str()).or_insert_with(|| {
+            let sku = skus.get(&event.subject);
+            let batch_id = sku.map(|(_, batch_id)| batch_id.clone());
+            let district = batch_id
+                .as_ref()
+                .and_then(|b| batch_districts.get(b))
+                .cloned()
+                .unwrap_or_else(|| UNKNOWN_DISTRICT.to_string());
+            (
+                SkuFraud {
+                    sku: sku
+                        .map(|(sku_id, _)| sku_id.clone())
+                        .unwrap_or_else(|| event.subject.clone()),
+                    batch_id,
+                    district,
+                    reports: 0,
+                    reporters: 0,
+                    first_reported: event.timestamp,
+                    last_reported: event.timestamp,
+                },
+                HashSet::new(),
+            )
+        });
+        row.reports += 1;
+        row.first_reported = row.first_reported.min(event.timestamp);
+        row.last_reported = row.last_reported.max(event.timestamp);
+        if let Some(reporter) = event.fields.get("reporter").and_then(|v| v.as_str()) {
+            reporters.insert(reporter);
+        }
+    }
+
+    let mut rows: Vec<SkuFraud> = groups
+        .into_values()
+        .map(|(row, reporters)| SkuFraud {
+            reporters: reporters.len(),
+            ..row
+        })
+        .collect();
+    rows.sort_by(|a, b| b.reports.cmp(&a.reports).then_with(|| a.sku.cmp(&b.sku)));
+    rows
+}
+
+// ======================== TABLES ========================
+
+fn period_label(start: NaiveDate, end: NaiveDate) -> String {
+    format!(
+        "{} to {}",
+        start.format("%Y-%m-%d"),
+        (end - ChronoDuration::days(1)).format("%Y-%m-%d")
+    )
+}
+
+fn notes(start: NaiveDate, end: NaiveDate) -> Vec<String> {
+    vec![
+        format!("Period: {} (business days)", period_label(start, end)),
+        format!("Generated: {}", Utc::now().format("%Y-%m-%d %H:%M UTC")),
+    ]
+}
+
+fn unix_time_label(secs: u64) -> String {
+    DateTime::<Utc>::from_timestamp(secs as i64, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// Table plus a one-line summary for SMS and WhatsApp
+fn procurement_table(
+    rows: &[DistrictProcurement],
+    start: NaiveDate,
+    end: NaiveDate,
+) -> (Table, String) {
+    let purchases: usize = rows.iter().map(|r| r.purchases).sum();
+    let farmers: usize = rows.iter().map(|r| r.farmers).sum();
+    let quantity: f64 = rows.iter().map(|r| r.quantity_kg).sum();
+    let cost: f64 = rows.iter().map(|r| r.total_cost).sum();
+    let avg = |cost: f64, qty: f64| if qty > 0.0 { cost / qty } else { 0.0 };
+    let grade = |score: Option<f64>| score.map(|s| format!("{:.1}", s)).unwrap_or_default();
+    let all = DistrictProcurement {
+        graded_kg: rows.iter().map(|r| r.graded_kg).sum(),
+        grade_score_kg: rows.iter().map(|r| r.grade_score_kg).sum(),
+        ..Default::default()
+    };
+
+    let mut table_rows: Vec<Vec<String>> = rows
+        .iter()
+        .map(|r| {
+            vec![
+                r.district.clone(),
+                r.purchases.to_string(),
+                r.farmers.to_string(),
+                format!("{:.1}", r.quantity_kg),
+                format!("{:.2}", r.total_cost),
+                format!("{:.2}", avg(r.total_cost, r.quantity_kg)),
+                grade(r.avg_grade_score()),
+            ]
+        })
+        .collect();
+    table_rows.push(vec![
+        "TOTAL".to_string(),
+        purchases.to_string(),
+        farmers.to_string(),
+        format!("{:.1}", quantity),
+        format!("{:.2}", cost),
+        format!("{:.2}", avg(cost, quantity)),
+        grade(all.avg_grade_score()),
+    ]);
+
+    let summary = format!(
+        "{} purchases, {:.1} kg, Rs {:.2} across {} districts",
+        purchases,
+        quantity,
+        cost,
+        rows.len()
+    );
+    let table = Table {
+        title: ReportKind::ProcurementSummary.title().to_string(),
+        notes: notes(start, end),
+        columns: [
+            "district",
+            "purchases",
+            "farmers",
+            "quantity_kg",
+            "total_cost",
+            "avg_price_per_kg",
+            "avg_grade_score",
+        ]
+        .map(String::from)
+        .to_vec(),
+        rows: table_rows,
+    };
+    (table, summary)
+}
+
+fn fraud_table(rows: &[SkuFraud], start: NaiveDate, end: NaiveDate) -> (Table, String) {
+    let reports: usize = rows.iter().map(|r| r.reports).sum();
+    let summary = format!("{} fraud reports against {} SKUs", reports, rows.len());
+    let table = Table {
+        title: ReportKind::FraudSummary.title().to_string(),
+        notes: notes(start, end),
+        columns: [
+            "sku",
+            "batch_id",
+            "district",
+            "reports",
+            "reporters",
+            "first_reported",
+            "last_reported",
+        ]
+        .map(String::from)
+        .to_vec(),
+        rows: rows
+            .iter()
+            .map(|r| {
+                vec![
+                    r.sku.clone(),
+                    r.batch_id.clone().unwrap_or_default(),
+                    r.district.clone(),
+                    r.reports.to_string(),
+                    r.reporters.to_string(),
+                    unix_time_label(r.first_reported),
+                    unix_time_label(r.last_reported),
+                ]
+            })
+            .collect(),
+    };
+    (table, summary)
+}
+
+// ======================== GENERATION ========================
+
+fn schedule_calendar<'a>(state: &'a AppState, schedule: &ReportSchedule) -> &'a BusinessCalendar {
+    state.calendars.for_state(schedule.calendar.as_deref())
+}
+
+async fn generate(
+    state: &AppState,
+    kind: ReportKind,
+    calendar: &BusinessCalendar,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<(Table, String)> {
+    let districts: HashMap<String, String> = state
+        .farmer_verification
+        .farmers_snapshot()
+        .await?
+        .into_iter()
+        .map(|f| (f.farmer_did, f.district_code))
+        .collect();
+    let purchases = batch_ledger::all_purchases();
+
+    match kind {
+        ReportKind::ProcurementSummary => {
+            let rows = procurement_by_district(&purchases, &districts, calendar, start, end);
+            Ok(procurement_table(&rows, start, end))
+        }
+        ReportKind::FraudSummary => {
+            let day_start = |day| calendar.day_start(day).timestamp().max(0) as u64;
+            let filter = EventFilter::TimeRange {
+                from: Some(day_start(start)),
+                to: Some(day_start(end).saturating_sub(1)),
+                event: Some("FraudDetected".to_string()),
+            };
+            let events = state
+                .events
+                .query_snapshot(&filter, None, MAX_FRAUD_EVENTS)
+                .await?;
+            if events.len() == MAX_FRAUD_EVENTS {
+                tracing::warn!(limit = MAX_FRAUD_EVENTS, "Fraud summary truncated");
+            }
+
+            let skus: HashMap<String, (String, String)> = batch_ledger::all_skus()
+                .into_iter()
+                .map(|(sku_id, batch_id)| (format_hash(hash_string(&sku_id)), (sku_id, batch_id)))
+                .collect();
+            let batch_districts: HashMap<String, String> = purchases
+                .iter()
+                .map(|(batch_id, purchase)| {
+                    let district = district_of(&districts, purchase_farmer(purchase));
+                    (batch_id.clone(), district.to_string())
+                })
+                .collect();
+
+            let rows = fraud_by_sku(&events, &skus, &batch_districts);
+            Ok(fraud_table(&rows, start, end))
+        }
+    }
+}
+
+async fn deliver(
+    state: &AppState,
+    recipient: &Recipient,
+    subject: &str,
+    summary: &str,
+    attachment: &Attachment,
+) -> Result<bool> {
+    match recipient.channel {
+        DeliveryChannel::Email => {
+            let text = format!("{}\n\nThe full report is attached.", summary);
+            state
+                .notifications
+                .email()
+                .send(
+                    &recipient.to,
+                    subject,
+                    &text,
+                    std::slice::from_ref(attachment),
+                )
+                .await
+        }
+        DeliveryChannel::Sms => {
+            let text = format!("{}: {}", subject, summary);
+            state.sms_client.send(&recipient.to, &text).await
+        }
+        DeliveryChannel::Whatsapp => {
+            let notification = Notification {
+                channel: Channel::Whatsapp,
+                to: recipient.to.clone(),
+                template: recipient
+                    .template
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_WHATSAPP_TEMPLATE.to_string()),
+                language: "en".to_string(),
+                params: vec![subject.to_string(), summary.to_string()],
+                sku_id: None,
+                qr_image_url: None,
+            };
+            Ok(state.notifications.send(&notification).await?.delivered)
+        }
+    }
+}
+
+/// Generate, store and deliver one report for `[start, end)`
+async fn run_schedule(
+    state: &AppState,
+    schedule: &ReportSchedule,
+    start: NaiveDate,
+    end: NaiveDate,
+    trigger: &str,
+) -> Result<ReportRun> {
+    let calendar = schedule_calendar(state, schedule);
+    let (table, summary) = generate(state, schedule.kind, calendar, start, end).await?;
+    let body = export::render(&table, schedule.format);
+    let subject = format!("{} ({})", table.title, period_label(start, end));
+
+    // Reruns of a period get their own file
+    let generated_at = Utc::now();
+    let file = format!(
+        "{}_{}_{}.{}",
+        schedule.id,
+        start.format("%Y-%m-%d"),
+        generated_at.format("%Y%m%dT%H%M%S"),
+        schedule.format.extension()
+    );
+    std::fs::create_dir_all(REPORTS_DIR)
+        .with_context(|| format!("Failed to create {}", REPORTS_DIR))?;
+    let path = std::path::Path::new(REPORTS_DIR).join(&file);
+    std::fs::write(&path, &body).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let attachment = Attachment {
+        filename: file.clone(),
+        content_type: schedule.format.content_type().to_string(),
+        content: body.clone(),
+    };
+    let mut deliveries = Vec::with_capacity(schedule.recipients.len());
+    for recipient in &schedule.recipients {
+        let result = deliver(state, recipient, &subject, &summary, &attachment).await;
+        if let Err(e) = &result {
+            tracing::warn!(
+                schedule_id = %schedule.id,
+                to = %recipient.to,
+                error = %format!("{:#}", e),
+                "Report delivery failed"
+            );
+        }
+        deliveries.push(DeliveryResult {
+            channel: recipient.channel,
+            to: recipient.to.clone(),
+            delivered: result.as_ref().is_ok_and(|d| *d),
+            error: result.err().map(|e| format!("{:#}", e)),
+        });
+    }
+
+    let mut runs = state.reports.runs.lock().await;
+    let run = ReportRun {
+        id: runs.last().map(|r| r.id + 1).unwrap_or(1),
+        schedule_id: schedule.id.clone(),
+        kind: schedule.kind,
+        period_start: start.format("%Y-%m-%d").to_string(),
+        period_end: end.format("%Y-%m-%d").to_string(),
+        format: schedule.format,
+        file,
+        rows: table.rows.len(),
+        sha256: hex::encode(Sha256::digest(&body)),
+        summary,
+        trigger: trigger.to_string(),
+        generated_at: generated_at.to_rfc3339(),
+        deliveries,
+    };
+    runs.push(run.clone());
+    ReportStore::save(&runs)?;
+
+    Ok(run)
+}
+
+/// Generate every active report whose latest period has not run yet
+pub async fn run_once(state: &AppState) -> Result<Vec<ReportRun>> {
+    let now = Utc::now();
+    let mut generated = Vec::new();
+
+    for schedule in state.reports.schedules().iter().filter(|s| s.active) {
+        let calendar = schedule_calendar(state, schedule);
+        let today = calendar.local_date(now);
+        if !calendar.is_business_day(today) {
+            continue;
+        }
+        let (start, end) = schedule.cadence.last_period(today);
+        let period_start = start.format("%Y-%m-%d").to_string();
+        let done = state
+            .reports
+            .runs
+            .lock()
+            .await
+            .iter()
+            .any(|r| r.schedule_id == schedule.id && r.period_start == period_start);
+        if done {
+            continue;
+        }
+
+        match run_schedule(state, schedule, start, end, "schedule").await {
+            Ok(run) => generated.push(run),
+            Err(e) => tracing::error!(
+                schedule_id = %schedule.id,
+                error = %format!("{:#}", e),
+                "Scheduled report failed"
+            ),
+        }
+    }
+
+    Ok(generated)
+}
+
+pub fn spawn(state: AppState) {
+    if !state.reports.schedules().iter().any(|s| s.active) {
+        tracing::info!("No active report schedules; report job not started");
+        return;
+    }
+    let secs = std::env::var("REPORTS_CHECK_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_CHECK_INTERVAL_SECS);
+    let interval = Duration::from_secs(secs.max(60));
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match run_once(&state).await {
+                Ok(runs) => {
+                    for run in runs {
+                        tracing::info!(
+                            schedule_id = %run.schedule_id,
+                            period_start = %run.period_start,
+                            rows = run.rows,
+                            delivered = run.deliveries.iter().filter(|d| d.delivered).count(),
+                            "Scheduled report generated"
+                        );
+                    }
+                }
+                Err(e) => tracing::error!(error = %format!("{:#}", e), "Report job failed"),
+            }
+        }
+    });
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct ScheduleStatus {
+    #[serde(flatten)]
+    pub schedule: ReportSchedule,
+    /// Period the job will generate next if it has not run yet
+    pub current_period_start: String,
+    pub last_run: Option<ReportRun>,
+}
+
+pub async fn list_report_schedules(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<Vec<ScheduleStatus>> {
+    require_admin(&state, &headers)?;
+
+    let now = Utc::now();
+    let runs = state.reports.runs.lock().await;
+    Ok(Json(
+        state
+            .reports
+            .schedules()
+            .iter()
+            .map(|schedule| ScheduleStatus {
+                current_period_start: schedule
+                    .cadence
+                    .last_period(schedule_calendar(&state, schedule).local_date(now))
+                    .0
+                    .format("%Y-%m-%d")
+                    .to_string(),
+                last_run: runs
+                    .iter()
+                    .rev()
+                    .find(|r| r.schedule_id == schedule.id)
+                    .cloned(),
+                schedule: schedule.clone(),
+            })
+            .collect(),
+    ))
+}
+
+/// Generate a report for its latest period now, even if it already ran
+pub async fn run_report(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> ApiResult<ReportRun> {
+    require_admin(&state, &headers)?;
+
+    let schedule = state
+        .reports
+        .schedules()
+        .iter()
+        .find(|s| s.id == id)
+        .cloned()
+        .ok_or_else(|| ApiError::not_found(format!("Report schedule {} not found", id)))?;
+    let today = schedule_calendar(&state, &schedule).local_date(Utc::now());
+    let (start, end) = schedule.cadence.last_period(today);
+    let run = run_schedule(&state, &schedule, start, end, "manual").await?;
+
+    tracing::info!(schedule_id = %run.schedule_id, run_id = run.id, "Report run on demand");
+    Ok(Json(run))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RunHistoryParams {
+    #[serde(default)]
+    pub schedule_id: Option<String>,
+}
+
+/// Run history, newest first
+pub async fn list_report_runs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<RunHistoryParams>,
+    Query(page): Query<PageParams>,
+) -> ApiResult<Page<ReportRun>> {
+    require_admin(&state, &headers)?;
+
+    let runs: Vec<ReportRun> = state
+        .reports
+        .runs
+        .lock()
+        .await
+        .iter()
+        .filter(|r| {
+            params
+                .schedule_id
+                .as_deref()
+                .is_none_or(|id| r.schedule_id == id)
+        })
+        .cloned()
+        .collect();
+    Ok(Json(paginate("report-runs", runs, &page, |r| {
+        Reverse(r.id)
+    })?))
+}
+
+pub async fn download_report(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+
+    let run = state
+        .reports
+        .runs
+        .lock()
+        .await
+        .iter()
+        .find(|r| r.id == id)
+        .cloned()
+        .ok_or_else(|| ApiError::not_found(format!("Report run {} not found", id)))?;
+    let path = std::path::Path::new(REPORTS_DIR).join(&run.file);
+    let body = std::fs::read(&path)
+        .map_err(|e| ApiError::not_found(format!("Report file {} unavailable: {}", run.file, e)))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, run.format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", run.file),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn day(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_last_period() {
+        // 2025-06-11 is a Wednesday
+        assert_eq!(
+            Cadence::Weekly.last_period(day("2025-06-11")),
+            (day("2025-06-02"), day("2025-06-09"))
+        );
+        assert_eq!(
+            Cadence::Weekly.last_period(day("2025-06-09")),
+            (day("2025-06-02"), day("2025-06-09"))
+        );
+        assert_eq!(
+            Cadence::Monthly.last_period(day("2025-01-15")),
+            (day("2024-12-01"), day("2025-01-01"))
+        );
+    }
+
+    #[test]
+    fn test_procurement_groups_by_district_within_period() {
+        let purchase = |did: &str, qty: f64, cost: f64, ts: &str| {
+            (
+                "b".to_string(),
+                json!({
+                    "farmer_info": { "farmer_did": did },
+                    "batch_info": { "quantity_kg": qty },
+                    "pricing": { "total_cost": cost },
+                    "timestamp": ts,
+                }),
+            )
+        };
+        let purchases = vec![
+            purchase("0xa", 100.0, 6000.0, "2025-06-02T08:00:00Z"),
+            purchase("0xa", 50.0, 3000.0, "2025-06-08T23:59:00Z"),
+            purchase("0xb", 20.0, 1000.0, "2025-06-05T10:00:00+05:30"),
+            purchase("0xc", 10.0, 500.0, "2025-06-04T10:00:00Z"),
+            // Outside the week
+            purchase("0xa", 999.0, 1.0, "2025-06-09T00:00:00Z"),
+        ];
+        let districts = HashMap::from([
+            ("0xa".to_string(), "D01".to_string()),
+            ("0xb".to_string(), "D02".to_string()),
+        ]);
+
+        let calendar = BusinessCalendar::default();
+        let rows = procurement_by_district(
+            &purchases,
+            &districts,
+            &calendar,
+            day("2025-06-02"),
+            day("2025-06-09"),
+        );
+        let d01 = &rows[0];
+        assert_eq!(
+            (d01.district.as_str(), d01.purchases, d01.farmers),
+            ("D01", 2, 1)
+        );
+        assert_eq!(d01.quantity_kg, 150.0);
+        assert_eq!(rows[1].district, "D02");
+        assert_eq!(rows[2].district, UNKNOWN_DISTRICT);
+        assert_eq!(rows.len(), 3);
+    }
+
+    #[test]
+    fn test_fraud_groups_by_sku() {
+        let event = |sku: &str, reporter: &str, ts: u64| IndexedEvent {
+            block_number: ts,
+            log_index: 0,
+            tx_hash: String::new(),
+            contract: String::new(),
+            event: "FraudDetected".to_string(),
+            timestamp: ts,
+            subject: sku.to_string(),
+            farmer_did: None,
+            metadata_cid: None,
+            batch_hashes: vec![],
+            fields: json!({ "reporter": reporter }),
+        };
+        let events = vec![
+            event("0x01", "0xr1", 30),
+            event("0x02", "0xr1", 10),
+            event("0x01", "0xr2", 20),
+            event("0x01", "0xr1", 40),
+        ];
+        let skus = HashMap::from([("0x01".to_string(), ("SKU-1".to_string(), "B1".to_string()))]);
+        let batch_districts = HashMap::from([("B1".to_string(), "D01".to_string())]);
+
+        let rows = fraud_by_sku(&events, &skus, &batch_districts);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].sku, "SKU-1");
+        assert_eq!(rows[0].district, "D01");
+        assert_eq!((rows[0].reports, rows[0].reporters), (3, 2));
+        assert_eq!((rows[0].first_reported, rows[0].last_reported), (20, 40));
+        assert_eq!(rows[1].sku, "0x02");
+        assert_eq!(rows[1].district, UNKNOWN_DISTRICT);
+    }
+}
@@ -0,0 +1,158 @@
+//! Response shaping
+//!
+//! Implements JSON:API-style sparse fieldsets for every JSON endpoint.
+//! Clients pass `?fields=` with a comma-separated list of field paths and
+//! receive only those fields:
+//!
+//! ```text
+//! GET /api/...?fields=sku_id,verified,trace.farmer.name
+//! ```
+//!
+//! Dotted paths select nested fields. Arrays are shaped element by element,
+//! so `fields=events.tx_hash` keeps only `tx_hash` in each event.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Query, Request},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// Largest response body the shaping layer will buffer
+const MAX_SHAPED_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Requested fields as a tree; an empty subtree selects the whole value
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FieldSet(BTreeMap<String, FieldSet>);
+
+impl FieldSet {
+    /// Parse a `fields` parameter such as `a,b.c,b.d`
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut root = FieldSet::default();
+
+        for path in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let mut node = &mut root;
+            for segment in path.split('.').filter(|s| !s.is_empty()) {
+                node = node.0.entry(segment.to_string()).or_default();
+            }
+        }
+
+        (!root.0.is_empty()).then_some(root)
+    }
+
+    /// Keep only the selected fields of `value`
+    pub fn apply(&self, value: Value) -> Value {
+        if self.0.is_empty() {
+            return value;
+        }
+
+        match value {
+            Value::Object(object) => {
+                let mut shaped = Map::new();
+                for (key, field) in object {
+                    if let Some(subset) = self.0.get(&key) {
+                        shaped.insert(key, subset.apply(field));
+                    }
+                }
+                Value::Object(shaped)
+            }
+            Value::Array(items) => Value::Array(items.into_iter().map(|v| self.apply(v)).collect()),
+            other => other,
+        }
+    }
+}
+
+/// Middleware applying `?fields=` to successful JSON responses
+pub async fn sparse_fieldsets(request: Request, next: Next) -> Response {
+    let field_set = Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .ok()
+        .and_then(|Query(params)| params.get("fields").and_then(|f| FieldSet::parse(f)));
+
+    let response = next.run(request).await;
+
+    let Some(field_set) = field_set else {
+        return response;
+    };
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("application/json"))
+        .unwrap_or(false);
+
+    if !response.status().is_success() || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_SHAPED_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to buffer response for field selection");
+            return crate::error::ApiError::internal("Failed to shape response").into_response();
+        }
+    };
+
+    let value = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(value) => value,
+        Err(_) => return Response::from_parts(parts, Body::from(bytes)),
+    };
+
+    let shaped = match serde_json::to_vec(&field_set.apply(value)) {
+        Ok(shaped) => shaped,
+        Err(e) => return crate::error::ApiError::json_failed(e).into_response(),
+    };
+
+    parts
+        .headers
+        .insert(header::CONTENT_LENGTH, HeaderValue::from(shaped.len()));
+
+    Response::from_parts(parts, Body::from(shaped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_selects_top_level_and_nested_fields() {
+        let fields = FieldSet::parse("sku_id, trace.farmer.name").unwrap();
+        let value = json!({
+            "sku_id": "SKU-1",
+            "merkle_root": "0xabc",
+            "trace": {
+                "farmer": { "name": "Rajesh", "mobile": "9876543210" },
+                "fpo": { "batch_id": "B1" }
+            }
+        });
+
+        assert_eq!(
+            fields.apply(value),
+            json!({ "sku_id": "SKU-1", "trace": { "farmer": { "name": "Rajesh" } } })
+        );
+    }
+
+    #[test]
+    fn test_shapes_each_array_element() {
+        let fields = FieldSet::parse("events.tx_hash").unwrap();
+        let value = json!({ "events": [
+            { "tx_hash": "0x1", "cid": "Qm1" },
+            { "tx_hash": "0x2", "cid": "Qm2" }
+        ]});
+
+        assert_eq!(
+            fields.apply(value),
+            json!({ "events": [{ "tx_hash": "0x1" }, { "tx_hash": "0x2" }] })
+        );
+    }
+
+    #[test]
+    fn test_empty_spec_is_ignored() {
+        assert!(FieldSet::parse(" , ").is_none());
+    }
+}
@@ -0,0 +1,244 @@
+//! Signed responses for public verification endpoints
+//!
+//! Consumer apps, retailers and caches pass trace and verification results
+//! along, so a response has to prove its origin on its own. With
+//! RESPONSE_SIGNING_KEY set (a secp256k1 private key), successful JSON
+//! responses of the public verification endpoints (see [`SIGNED_PATHS`])
+//! carry a detached JWS (RFC 7515 appendix F) in `X-Response-Signature`:
+//!
+//! ```text
+//! X-Response-Signature: <base64url(protected header)>..<base64url(signature)>
+//! ```
+//!
+//! The payload is the body in canonical JSON v2 (sorted keys, see
+//! [`canonical_json_v2`]), so re-serializing the body does not break the
+//! signature. The algorithm is ES256K (RFC 8812) and the header's `kid` is
+//! the signer's Ethereum address; the public key is published as a JWK set
+//! at `GET /.well-known/jwks.json`.
+//!
+//! To verify, rebuild `header + "." + base64url(canonical body)` and check
+//! the signature against the published key.
+
+use crate::error::{ApiError, ApiResult};
+use crate::hash_schemes::canonical_json_v2;
+use crate::state::AppState;
+use alloy::signers::k256::ecdsa::{signature::hazmat::PrehashSigner, Signature, SigningKey};
+use alloy::signers::local::PrivateKeySigner;
+use anyhow::{Context, Result};
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL, Engine};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+pub const SIGNATURE_HEADER: HeaderName = HeaderName::from_static("x-response-signature");
+pub const ALGORITHM: &str = "ES256K";
+
+/// Path prefixes whose responses are signed
+pub const SIGNED_PATHS: &[&str] = &[
+    "/api/public/",
+    "/api/packaging/verify",
+    "/api/workflow/verify-sku",
+    "/api/hashes/verify",
+    "/api/anchors",
+    "/api/ledger/anchors",
+    "/api/ledger/proof/",
+    "/api/cids/provenance",
+];
+
+/// Largest response body the signing layer will buffer
+const MAX_SIGNED_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+pub struct ResponseSigner {
+    key: SigningKey,
+    kid: String,
+}
+
+impl ResponseSigner {
+    /// Load RESPONSE_SIGNING_KEY; `None` when responses are not signed
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(key) = std::env::var("RESPONSE_SIGNING_KEY")
+            .ok()
+            .filter(|k| !k.is_empty())
+        else {
+            return Ok(None);
+        };
+        let signer = key
+            .trim()
+            .parse::<PrivateKeySigner>()
+            .context("RESPONSE_SIGNING_KEY is not a valid secp256k1 key")?;
+
+        let response_signer = Self::new(&signer);
+        tracing::info!(kid = %response_signer.kid, "Signing public verification responses");
+        Ok(Some(response_signer))
+    }
+
+    fn new(signer: &PrivateKeySigner) -> Self {
+        Self {
+            key: signer.credential().clone(),
+            kid: format!("{:?}", signer.address()),
+        }
+    }
+
+    /// Public key as a JWK
+    pub fn jwk(&self) -> Value {
+        let point = self.key.verifying_key().to_encoded_point(false);
+        json!({
+            "kty": "EC",
+            "crv": "secp256k1",
+            "alg": ALGORITHM,
+            "use": "sig",
+            "kid": self.kid,
+            "x": point.x().map(|x| BASE64URL.encode(x)),
+            "y": point.y().map(|y| BASE64URL.encode(y)),
+        })
+    }
+
+    /// Protected header of every signature
+    fn protected_header(&self) -> String {
+        let header = json!({
+            "alg": ALGORITHM,
+            "kid": self.kid,
+            "cty": "json/v2",
+        });
+        BASE64URL.encode(header.to_string())
+    }
+
+    /// Detached JWS over the canonical form of `body`
+    pub fn sign(&self, body: &Value) -> Result<String> {
+        let protected = self.protected_header();
+        let signing_input = signing_input(&protected, body);
+        let signature: Signature = self
+            .key
+            .sign_prehash(&Sha256::digest(signing_input.as_bytes()))
+            .context("Failed to sign response")?;
+        Ok(format!(
+            "{}..{}",
+            protected,
+            BASE64URL.encode(signature.to_bytes())
+        ))
+    }
+}
+
+/// JWS signing input for a detached canonical JSON payload
+fn signing_input(protected: &str, body: &Value) -> String {
+    format!(
+        "{}.{}",
+        protected,
+        BASE64URL.encode(canonical_json_v2(body))
+    )
+}
+
+fn is_signed_path(path: &str) -> bool {
+    SIGNED_PATHS.iter().any(|prefix| path.starts_with(prefix))
+}
+
+/// Middleware adding `X-Response-Signature` to public verification responses
+pub async fn sign_public_responses(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let signed_path = is_signed_path(request.uri().path());
+    let response = next.run(request).await;
+
+    let Some(signer) = state.response_signer.as_ref().filter(|_| signed_path) else {
+        return response;
+    };
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("application/json"))
+        .unwrap_or(false);
+    if !response.status().is_success() || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_SIGNED_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to buffer response for signing");
+            return ApiError::internal("Failed to sign response").into_response();
+        }
+    };
+
+    let signature = serde_json::from_slice::<Value>(&bytes)
+        .context("Response body is not JSON")
+        .and_then(|value| signer.sign(&value))
+        .and_then(|jws| HeaderValue::from_str(&jws).context("Invalid signature header"));
+    match signature {
+        Ok(value) => {
+            parts.headers.insert(SIGNATURE_HEADER, value);
+        }
+        Err(e) => tracing::error!(error = %format!("{:#}", e), "Failed to sign response"),
+    }
+
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+// ======================== HANDLERS ========================
+
+/// JWK set with the response signing key (empty when signing is disabled)
+pub async fn get_jwks(State(state): State<AppState>) -> ApiResult<Value> {
+    let keys: Vec<Value> = state
+        .response_signer
+        .iter()
+        .map(|signer| signer.jwk())
+        .collect();
+    Ok(Json(json!({ "keys": keys })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::k256::ecdsa::{signature::hazmat::PrehashVerifier, VerifyingKey};
+    use alloy::signers::k256::EncodedPoint;
+
+    #[test]
+    fn test_signature_verifies_against_published_key() {
+        let signer = ResponseSigner::new(&PrivateKeySigner::random());
+        let body = json!({"sku_id": "SKU-1", "verified": true, "batch": {"grade": "A"}});
+        let jws = signer.sign(&body).unwrap();
+
+        let (protected, signature) = jws.split_once("..").unwrap();
+        let header: Value = serde_json::from_slice(&BASE64URL.decode(protected).unwrap()).unwrap();
+        assert_eq!(header["alg"], ALGORITHM);
+        assert_eq!(header["kid"], signer.kid);
+
+        // Verify with nothing but the JWK and a re-serialized body
+        let jwk = signer.jwk();
+        let coordinate = |c: &str| BASE64URL.decode(jwk[c].as_str().unwrap()).unwrap();
+        let point = EncodedPoint::from_affine_coordinates(
+            coordinate("x").as_slice().into(),
+            coordinate("y").as_slice().into(),
+            false,
+        );
+        let key = VerifyingKey::from_encoded_point(&point).unwrap();
+        let signature = Signature::from_slice(&BASE64URL.decode(signature).unwrap()).unwrap();
+        let reordered: Value =
+            serde_json::from_str(r#"{"batch":{"grade":"A"},"verified":true,"sku_id":"SKU-1"}"#)
+                .unwrap();
+        let digest = Sha256::digest(signing_input(protected, &reordered).as_bytes());
+        assert!(key.verify_prehash(&digest, &signature).is_ok());
+
+        let tampered = json!({"sku_id": "SKU-1", "verified": false, "batch": {"grade": "A"}});
+        let digest = Sha256::digest(signing_input(protected, &tampered).as_bytes());
+        assert!(key.verify_prehash(&digest, &signature).is_err());
+    }
+
+    #[test]
+    fn test_only_public_verification_paths_are_signed() {
+        assert!(is_signed_path("/api/public/trace/SKU-1"));
+        assert!(is_signed_path("/api/packaging/verify/bulk"));
+        assert!(!is_signed_path("/api/packaging/create"));
+        assert!(!is_signed_path("/api/admin/anchors/run"));
+    }
+}
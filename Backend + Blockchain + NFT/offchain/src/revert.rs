@@ -0,0 +1,146 @@
+//! Contract revert reasons
+//!
+//! A reverted call reaches the backend as an RPC error whose `data` holds the
+//! ABI-encoded reason. The contract's custom errors (see `nyx.sol`) are
+//! mapped to an HTTP status and a stable error code clients can match on;
+//! `require` messages and panics come back as `CONTRACT_REVERT` with the
+//! decoded reason.
+
+use crate::chain::OilseedValueChain::OilseedValueChainErrors;
+use alloy::{primitives::Bytes, sol_types::SolInterface, transports::TransportError};
+use axum::http::StatusCode;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractRevert {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ContractRevert {
+    fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// Decode the revert data of a failed call
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        use OilseedValueChainErrors as E;
+        use StatusCode as S;
+
+        if let Ok(error) = OilseedValueChainErrors::abi_decode(data, true) {
+            let (status, code, message) = match error {
+                E::Unauthorized(_) => (
+                    S::FORBIDDEN,
+                    "ROLE_MISSING",
+                    "The backend signer lacks the contract role for this call",
+                ),
+                E::AlreadyRegistered(_) => (
+                    S::CONFLICT,
+                    "FARMER_EXISTS",
+                    "Farmer is already registered on-chain",
+                ),
+                E::FarmerNotRegistered(_) => (
+                    S::UNPROCESSABLE_ENTITY,
+                    "FARMER_NOT_REGISTERED",
+                    "Farmer is not registered on-chain",
+                ),
+                E::SKUAlreadyExists(_) => (S::CONFLICT, "SKU_EXISTS", "SKU already exists"),
+                E::SKUNotFound(_) => (S::NOT_FOUND, "SKU_NOT_FOUND", "SKU not found on-chain"),
+                E::AlreadyCommitted(_) => (
+                    S::CONFLICT,
+                    "SCORE_ALREADY_COMMITTED",
+                    "An AI score is already committed for this batch",
+                ),
+                E::NotCommitted(_) => (
+                    S::CONFLICT,
+                    "SCORE_NOT_COMMITTED",
+                    "No AI score has been committed for this batch",
+                ),
+                E::AlreadyRevealed(_) => (
+                    S::CONFLICT,
+                    "SCORE_ALREADY_REVEALED",
+                    "The AI score for this batch is already revealed",
+                ),
+                E::InvalidReveal(_) => (
+                    S::UNPROCESSABLE_ENTITY,
+                    "INVALID_REVEAL",
+                    "Reveal hash and nonce do not match the commitment",
+                ),
+                E::LengthMismatch(_) => (
+                    S::BAD_REQUEST,
+                    "LENGTH_MISMATCH",
+                    "Batch arrays differ in length",
+                ),
+                E::RevealTooEarly(_) => (
+                    S::CONFLICT,
+                    "REVEAL_TOO_EARLY",
+                    "The reveal window has not opened yet",
+                ),
+                E::RevealTooLate(_) => (
+                    S::CONFLICT,
+                    "REVEAL_TOO_LATE",
+                    "The reveal window has closed",
+                ),
+                E::InvalidTransferType(_) => (
+                    S::BAD_REQUEST,
+                    "INVALID_TRANSFER_TYPE",
+                    "Transfer type must be warehouse, processor or retail",
+                ),
+            };
+            return Some(Self::new(status, code, message));
+        }
+
+        alloy::sol_types::decode_revert_reason(data).map(|reason| {
+            Self::new(
+                S::UNPROCESSABLE_ENTITY,
+                "CONTRACT_REVERT",
+                format!("Contract reverted: {}", reason),
+            )
+        })
+    }
+
+    /// Revert behind an error from a contract call or transaction send, if
+    /// the node returned its data
+    pub fn from_error(error: &anyhow::Error) -> Option<Self> {
+        error.chain().find_map(|cause| {
+            let transport =
+                cause
+                    .downcast_ref::<TransportError>()
+                    .or_else(|| match cause.downcast_ref::<alloy::contract::Error>() {
+                        Some(alloy::contract::Error::TransportError(e)) => Some(e),
+                        _ => None,
+                    })?;
+            let data: Bytes = transport.as_error_resp()?.as_revert_data()?;
+            Self::decode(&data)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain::OilseedValueChain::SKUAlreadyExists;
+    use alloy::sol_types::{Revert, SolError};
+
+    #[test]
+    fn test_decode_custom_errors_and_require_strings() {
+        let revert = ContractRevert::decode(&SKUAlreadyExists {}.abi_encode()).unwrap();
+        assert_eq!(
+            (revert.status, revert.code),
+            (StatusCode::CONFLICT, "SKU_EXISTS")
+        );
+
+        let reason = Revert {
+            reason: "quantity must be positive".to_string(),
+        };
+        let revert = ContractRevert::decode(&reason.abi_encode()).unwrap();
+        assert_eq!(revert.code, "CONTRACT_REVERT");
+        assert!(revert.message.ends_with("quantity must be positive"));
+
+        assert_eq!(ContractRevert::decode(&[0xde, 0xad, 0xbe, 0xef]), None);
+    }
+}
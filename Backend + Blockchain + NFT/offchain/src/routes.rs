@@ -1,14 +1,120 @@
+use crate::acceptance;
+use crate::admin;
+use crate::anchoring;
+use crate::api_keys;
+use crate::audit;
+use crate::auditor;
+use crate::auth::{self, restrict, restrict_scoped, Role};
+use crate::business_calendar;
+use crate::chain_roles;
+use crate::cid_provenance;
+use crate::cold_chain;
+use crate::commitments;
+use crate::confirmations;
+use crate::credentials;
+use crate::delegation::{self, Scope};
+use crate::demo_handlers;
+use crate::did;
+use crate::did_resolver;
+use crate::experiments;
+use crate::farmer_import;
+use crate::farmer_profile;
+use crate::farmer_search;
+use crate::financing;
+use crate::fpo_registry;
+use crate::grades;
+use crate::hash_schemes;
+use crate::holds;
+use crate::indexer;
+use crate::ledger_anchors;
+use crate::metering;
+use crate::networks;
+use crate::nft;
+use crate::notifications;
+use crate::object_storage;
+use crate::otp;
+use crate::outbox;
+use crate::photo_evidence;
+use crate::processor_registry;
+use crate::public_stats;
+use crate::public_trace;
+use crate::read_models;
+use crate::readiness;
+use crate::reference_data;
+use crate::reports;
+use crate::response_signing;
+use crate::samples;
+use crate::schemes;
+use crate::seals;
+use crate::share;
+use crate::shelf_life;
+use crate::signers;
+use crate::sku_units;
+use crate::sms;
+use crate::snapshots;
+use crate::stock_rotation;
 use crate::supply_chain_handlers;
+use crate::timeline;
+use crate::transport;
+use crate::tx_queue;
+use crate::ussd;
+use crate::video_evidence;
+use crate::wallet;
+use crate::warehouse_receipts;
+use crate::warehouse_registry;
+use crate::warehouse_transfers;
+use crate::workflow_replay;
 use crate::workflows;
-use axum::{routing::post, Router};
+use axum::{
+    extract::DefaultBodyLimit,
+    routing::{delete, get, patch, post, put},
+    Router,
+};
+
+/// Any signed-in user
+const ALL_ROLES: [Role; 6] = [
+    Role::Farmer,
+    Role::Fpo,
+    Role::Warehouse,
+    Role::Processor,
+    Role::Lender,
+    Role::Admin,
+];
 
 pub fn configure_routes(state: crate::state::AppState) -> Router {
     Router::new()
+        // ==================== AUTH ROUTES ====================
+        .route("/api/auth/login", post(auth::login))
+        .route("/api/auth/me", get(auth::current_user))
         // ==================== WORKFLOW ROUTES ====================
         // Complete end-to-end workflow
         .route(
             "/api/workflow/execute",
-            post(workflows::http_handlers::execute_workflow),
+            restrict(
+                post(workflows::http_handlers::execute_workflow),
+                &[Role::Admin],
+            ),
+        )
+        .route(
+            "/api/workflow/status/:id",
+            restrict(
+                get(workflows::http_handlers::workflow_status),
+                &[Role::Admin],
+            ),
+        )
+        .route(
+            "/api/workflow/stream/:id",
+            restrict(
+                get(workflows::http_handlers::workflow_stream),
+                &[Role::Admin],
+            ),
+        )
+        .route(
+            "/api/workflow/resume/:id",
+            restrict(
+                post(workflows::http_handlers::resume_workflow),
+                &[Role::Admin],
+            ),
         )
         .route(
             "/api/workflow/verify-sku",
@@ -27,69 +133,697 @@ pub fn configure_routes(state: crate::state::AppState) -> Router {
             "/api/verification/farmer-by-did",
             post(supply_chain_handlers::get_farmer_by_did),
         )
+        .route("/api/verification/otp/send", post(otp::send_otp))
+        .route("/api/verification/otp/verify", post(otp::verify_otp))
+        // ==================== FEATURE PHONE ROUTES ====================
+        .route("/api/sms/inbound", post(sms::inbound_sms))
+        .route("/api/ussd/session", post(ussd::ussd_session))
+        .route("/api/ivr/step", post(ussd::ivr_step))
+        // ==================== PUBLIC ROUTES ====================
+        .route(
+            "/api/public/trace/:sku_id",
+            get(public_trace::get_public_trace),
+        )
+        .route(
+            "/api/public/experiments/convert",
+            post(experiments::record_conversion),
+        )
+        .route("/api/public/stats", get(public_stats::get_public_stats))
+        .route(
+            "/api/reference-data",
+            get(reference_data::get_reference_data),
+        )
+        .route("/api/grades", get(grades::list_grades))
+        .route("/api/shelf-life/rules", get(shelf_life::list_rules))
+        .route(
+            "/api/public/proofs/district",
+            post(commitments::district_proof),
+        )
+        .route(
+            "/api/public/proofs/district/verify",
+            post(commitments::verify_district_proof),
+        )
+        .route(
+            "/.well-known/jwks.json",
+            get(response_signing::get_jwks),
+        )
+        .route("/api/anchors", get(anchoring::list_anchors))
+        .route(
+            "/api/anchors/proof/:tx_hash",
+            get(anchoring::inclusion_proof),
+        )
+        .route("/api/cids/provenance", get(cid_provenance::list_provenance))
+        .route(
+            "/api/cids/provenance/:placeholder",
+            get(cid_provenance::get_provenance),
+        )
+        .route(
+            "/api/ledger/fpo-purchase",
+            restrict(post(demo_handlers::fpo_purchase), &[Role::Fpo]),
+        )
+        .route(
+            "/api/ledger/verify-farmer",
+            post(demo_handlers::verify_farmer),
+        )
+        .route(
+            "/api/ledger/batch-history",
+            post(demo_handlers::get_batch_history),
+        )
+        .route("/api/ledger/stats", get(demo_handlers::get_blockchain_stats))
+        .route("/api/ledger/anchors", get(ledger_anchors::list_ledger_anchors))
+        .route(
+            "/api/ledger/proof/:block_hash",
+            get(ledger_anchors::ledger_inclusion_proof),
+        )
+        .route("/api/hashes/schemes", get(hash_schemes::list_schemes))
+        .route("/api/hashes/verify", post(hash_schemes::verify_hash))
+        .route(
+            "/api/calendars/:state_code",
+            get(business_calendar::get_calendar),
+        )
+        .route("/api/audit/digests", get(audit::list_digests))
+        .route("/api/audit/proof/:day/:seq", get(audit::entry_proof))
+        .route("/api/events", get(indexer::events_by_time))
+        .route("/api/events/batch/:batch_id", get(indexer::events_by_batch))
+        .route(
+            "/api/events/farmer/:farmer_did",
+            get(indexer::events_by_farmer),
+        )
+        .route("/api/events/status", get(indexer::indexer_status))
+        .route(
+            "/api/batches/:batch_id/summary",
+            get(read_models::batch_summary),
+        )
+        .route("/api/skus/:sku_id/summary", get(read_models::sku_summary))
+        .route(
+            "/api/farmer/:farmer_did/summary",
+            get(read_models::farmer_summary),
+        )
+        .route(
+            "/api/trace/batch/:batch_id",
+            get(timeline::get_batch_timeline),
+        )
+        .route("/api/shared/documents", get(share::shared_documents))
+        .route("/api/shared/events", get(share::shared_events))
+        // ==================== AUDITOR CONSOLE ROUTES ====================
+        .route("/api/auditor/verify", post(auditor::verify_auditor))
+        .route("/api/auditor/me", get(auditor::auditor_profile))
+        .route(
+            "/api/auditor/queries",
+            get(auditor::list_queries).post(auditor::save_query),
+        )
+        .route("/api/auditor/queries/:id", delete(auditor::delete_query))
+        .route("/api/auditor/queries/:id/run", get(auditor::run_query))
+        .route(
+            "/api/auditor/queries/:id/export",
+            post(auditor::export_query),
+        )
+        .route("/api/auditor/exports", get(auditor::list_exports))
         // ==================== DEMO ROUTES ====================
         .route("/verify/farmer", post(supply_chain_handlers::verify_farmer))
-        .route("/fpo/purchase", post(supply_chain_handlers::fpo_purchase))
+        .route(
+            "/fpo/purchase",
+            restrict_scoped(
+                post(supply_chain_handlers::fpo_purchase),
+                &[Role::Fpo],
+                Scope::FpoPurchase,
+            ),
+        )
+        .route(
+            "/api/samples",
+            restrict(
+                post(samples::record_sample),
+                &[Role::Fpo, Role::Warehouse],
+            ),
+        )
+        .route(
+            "/api/samples/batch/:batch_id",
+            get(samples::list_batch_samples),
+        )
+        .route(
+            "/api/samples/:sample_id/dispose",
+            restrict(
+                post(samples::dispose_sample),
+                &[Role::Fpo, Role::Warehouse],
+            ),
+        )
+        .route(
+            "/api/admin/samples/:sample_id/hold",
+            post(samples::hold_sample),
+        )
         // ==================== SUPPLY CHAIN ROUTES ====================
         // Stage 1: Farmer Registration
         .route(
             "/api/farmer/register",
-            post(supply_chain_handlers::register_farmer),
+            restrict(post(supply_chain_handlers::register_farmer), &[Role::Fpo]),
+        )
+        .route(
+            "/api/farmer/register/bulk",
+            restrict(post(farmer_import::register_farmers_bulk), &[Role::Fpo]),
         )
         .route(
             "/api/farmer/verify",
             post(supply_chain_handlers::verify_farmer),
         )
+        .route("/api/farmer/did/derive", post(did::derive_did))
+        .route("/api/did/:did", get(did_resolver::resolve_did))
+        .route(
+            "/api/credentials/issue",
+            restrict(post(credentials::issue_credential), &[Role::Fpo]),
+        )
+        .route(
+            "/api/credentials/verify",
+            post(credentials::verify_credential),
+        )
+        .route(
+            "/api/farmers",
+            restrict(get(farmer_search::list_farmers), &[Role::Fpo]),
+        )
+        .route(
+            "/api/farmer/:farmer_did",
+            restrict(
+                patch(farmer_profile::update_farmer_profile),
+                &[Role::Fpo, Role::Farmer],
+            ),
+        )
+        .route(
+            "/api/farmer/:farmer_did/profile/history",
+            restrict(
+                get(farmer_profile::profile_history),
+                &[Role::Fpo, Role::Farmer],
+            ),
+        )
         // Stage 2: FPO Purchase
         .route(
             "/api/fpo/purchase",
-            post(supply_chain_handlers::fpo_purchase),
+            restrict_scoped(
+                post(supply_chain_handlers::fpo_purchase),
+                &[Role::Fpo],
+                Scope::FpoPurchase,
+            ),
+        )
+        .route(
+            "/api/fpos",
+            restrict(get(fpo_registry::list_fpos), &[Role::Fpo]),
+        )
+        .route(
+            "/api/fpos/:id",
+            restrict(get(fpo_registry::get_fpo), &[Role::Fpo]),
+        )
+        .route(
+            "/api/ownership/transfer",
+            restrict(
+                post(supply_chain_handlers::record_ownership_transfer),
+                &[Role::Fpo, Role::Warehouse, Role::Processor],
+            ),
         )
         // Stage 3: Warehouse Storage
         .route(
             "/api/warehouse/update",
-            post(supply_chain_handlers::update_warehouse_state),
+            restrict(
+                post(supply_chain_handlers::update_warehouse_state),
+                &[Role::Warehouse],
+            ),
         )
         .route(
             "/api/warehouse/batch-update",
-            post(supply_chain_handlers::batch_update_warehouse),
+            restrict(
+                post(supply_chain_handlers::batch_update_warehouse),
+                &[Role::Warehouse],
+            ),
+        )
+        .route(
+            "/api/warehouse/:warehouse_id",
+            get(supply_chain_handlers::get_warehouse_state),
+        )
+        .route("/api/warehouses", get(warehouse_registry::list_warehouses))
+        .route(
+            "/api/warehouses/:id",
+            get(warehouse_registry::get_warehouse),
+        )
+        .route(
+            "/api/warehouses/:id/dispatch-order",
+            restrict(
+                get(stock_rotation::dispatch_plan),
+                &[Role::Fpo, Role::Warehouse],
+            ),
+        )
+        .route(
+            "/api/warehouse/receipts",
+            restrict(
+                get(warehouse_receipts::list_receipts),
+                &[Role::Warehouse, Role::Fpo],
+            )
+            .merge(restrict(
+                post(warehouse_receipts::issue_receipt),
+                &[Role::Warehouse],
+            )),
+        )
+        .route(
+            "/api/warehouse/receipts/:number",
+            restrict(
+                get(warehouse_receipts::get_receipt),
+                &[Role::Warehouse, Role::Fpo],
+            ),
+        )
+        .route(
+            "/api/warehouse/receipts/:number/transfer",
+            restrict(
+                post(warehouse_receipts::transfer_receipt),
+                &[Role::Warehouse],
+            ),
+        )
+        .route(
+            "/api/warehouse/receipts/:number/pledge",
+            restrict(
+                post(warehouse_receipts::pledge_receipt),
+                &[Role::Warehouse],
+            ),
+        )
+        .route(
+            "/api/warehouse/receipts/:number/release",
+            restrict(
+                post(warehouse_receipts::release_receipt_lien),
+                &[Role::Warehouse],
+            ),
+        )
+        .route(
+            "/api/warehouse/receipts/:number/close",
+            restrict(
+                post(warehouse_receipts::close_receipt),
+                &[Role::Warehouse],
+            ),
+        )
+        .route(
+            "/api/warehouse/transfer",
+            restrict(
+                post(warehouse_transfers::transfer_stock),
+                &[Role::Warehouse],
+            ),
+        )
+        .route(
+            "/api/warehouse/transfers",
+            restrict(
+                get(warehouse_transfers::list_transfers),
+                &[Role::Warehouse, Role::Fpo],
+            ),
         )
         // Stage 4: Logistics Tracking
+        .route(
+            "/api/logistics/transporters",
+            restrict(
+                get(transport::list_transporters),
+                &[Role::Fpo, Role::Warehouse, Role::Processor],
+            ),
+        )
+        .route(
+            "/api/logistics/vehicles",
+            restrict(
+                get(transport::list_vehicles),
+                &[Role::Fpo, Role::Warehouse, Role::Processor],
+            ),
+        )
+        .route(
+            "/api/logistics/drivers",
+            restrict(
+                get(transport::list_drivers),
+                &[Role::Fpo, Role::Warehouse, Role::Processor],
+            ),
+        )
+        .route(
+            "/api/logistics/assign",
+            restrict(
+                post(transport::assign_shipment),
+                &[Role::Fpo, Role::Warehouse, Role::Processor],
+            ),
+        )
+        .route(
+            "/api/logistics/assignments/:shipment_id",
+            get(transport::get_assignment),
+        )
         .route(
             "/api/logistics/record",
-            post(supply_chain_handlers::record_logistics),
+            restrict(
+                post(supply_chain_handlers::record_logistics),
+                &[Role::Warehouse, Role::Processor],
+            ),
+        )
+        .route(
+            "/api/logistics/batch-record",
+            restrict(
+                post(supply_chain_handlers::batch_record_logistics),
+                &[Role::Warehouse, Role::Processor],
+            ),
+        )
+        .route(
+            "/api/logistics/seals",
+            restrict(
+                post(seals::issue_seals),
+                &[Role::Fpo, Role::Warehouse, Role::Processor],
+            ),
+        )
+        .route(
+            "/api/logistics/seals/:shipment_id",
+            get(seals::get_shipment_seals),
+        )
+        .route("/api/admin/seals/exceptions", get(seals::list_exceptions))
+        .route(
+            "/api/logistics/readings",
+            restrict(
+                post(cold_chain::record_readings),
+                &[Role::Warehouse, Role::Processor],
+            ),
+        )
+        .route(
+            "/api/logistics/cold-chain/:shipment_id",
+            get(cold_chain::cold_chain_report),
         )
         // Stage 5: Processing
         .route(
             "/api/processing/batch",
-            post(supply_chain_handlers::process_batch),
+            restrict(
+                post(supply_chain_handlers::process_batch),
+                &[Role::Processor],
+            ),
+        )
+        .route("/api/processors", get(processor_registry::list_processors))
+        .route(
+            "/api/processors/:id",
+            get(processor_registry::get_processor),
+        )
+        .route(
+            "/api/processing/acceptance",
+            restrict(post(acceptance::record_acceptance), &[Role::Processor]),
+        )
+        .route(
+            "/api/processing/acceptance/:batch_id",
+            get(acceptance::get_acceptance),
         )
+        .route("/api/quality/stats", get(acceptance::get_quality_stats))
         // Stage 6: Packaging
         .route(
             "/api/packaging/sku",
-            post(supply_chain_handlers::create_sku),
+            restrict(post(supply_chain_handlers::create_sku), &[Role::Processor]),
         )
         .route(
             "/api/packaging/verify",
             post(supply_chain_handlers::verify_sku),
         )
+        .route(
+            "/api/packaging/verify/bulk",
+            post(supply_chain_handlers::verify_sku_bulk),
+        )
+        .route("/api/packaging/unit-proof", get(sku_units::unit_proof))
+        .route("/api/packaging/verify-unit", post(sku_units::verify_unit))
+        .route("/api/networks", get(networks::list_networks))
+        .route("/api/readiness", get(readiness::readiness))
+        .route(
+            "/api/nft/mint",
+            restrict(post(nft::mint_sku_nft), &[Role::Processor]),
+        )
         // Stage 7: Fraud Reporting
         .route(
             "/api/fraud/report",
             post(supply_chain_handlers::report_fraud),
         )
+        // Base64 images are a third larger than the decoded limit
+        .route(
+            "/api/evidence/photos",
+            post(photo_evidence::upload_photo)
+                .layer(DefaultBodyLimit::max(
+                    photo_evidence::MAX_PHOTO_BYTES / 3 * 4 + 64 * 1024,
+                ))
+                .get(photo_evidence::search_photos),
+        )
+        .route(
+            "/api/evidence/photos/:image_cid",
+            get(photo_evidence::get_photo),
+        )
+        .route(
+            "/api/admin/evidence/similar",
+            get(photo_evidence::list_similar),
+        )
+        .route(
+            "/api/evidence/videos/uploads",
+            restrict(
+                post(video_evidence::create_upload),
+                &[Role::Fpo, Role::Processor],
+            ),
+        )
+        .route(
+            "/api/evidence/videos/uploads/:upload_id",
+            restrict(
+                get(video_evidence::upload_status),
+                &[Role::Fpo, Role::Processor],
+            ),
+        )
+        .route(
+            "/api/evidence/videos/uploads/:upload_id/chunks/:index",
+            restrict(
+                put(video_evidence::put_chunk).layer(DefaultBodyLimit::max(
+                    video_evidence::CHUNK_SIZE as usize + 1024,
+                )),
+                &[Role::Fpo, Role::Processor],
+            ),
+        )
+        .route(
+            "/api/evidence/videos/uploads/:upload_id/complete",
+            restrict(
+                post(video_evidence::complete_upload),
+                &[Role::Fpo, Role::Processor],
+            ),
+        )
+        .route(
+            "/api/evidence/videos/:video_cid",
+            get(video_evidence::get_video),
+        )
+        .route(
+            "/api/evidence/objects/:cid/url",
+            get(object_storage::signed_url),
+        )
         // Stage 8: AI Scoring
         .route(
             "/api/ai/commit",
-            post(supply_chain_handlers::commit_ai_score),
+            restrict(
+                post(supply_chain_handlers::commit_ai_score),
+                &[Role::Fpo, Role::Processor],
+            ),
         )
         .route(
             "/api/ai/reveal",
-            post(supply_chain_handlers::reveal_ai_score),
+            restrict(
+                post(supply_chain_handlers::reveal_ai_score),
+                &[Role::Fpo, Role::Processor],
+            ),
+        )
+        .route(
+            "/api/ai/score/:batch_id",
+            get(supply_chain_handlers::get_ai_score),
+        )
+        // Stage 9: Lab Results and Payment Holds
+        .route(
+            "/api/lab/results",
+            restrict(
+                post(supply_chain_handlers::record_lab_result),
+                &[Role::Fpo, Role::Processor],
+            ),
+        )
+        .route(
+            "/api/settlements/:batch_id",
+            restrict(get(holds::get_settlement), &[Role::Fpo, Role::Farmer]),
+        )
+        .route(
+            "/api/farmer/:farmer_did/settlements",
+            restrict(get(holds::farmer_settlements), &[Role::Fpo, Role::Farmer]),
+        )
+        // Stage 10: Financing and Compliance
+        .route(
+            "/api/compliance/financing",
+            restrict(post(financing::record_financing), &[Role::Warehouse]),
+        )
+        .route(
+            "/api/compliance/financing/:id/release",
+            restrict(post(financing::release_financing), &[Role::Warehouse]),
+        )
+        .route(
+            "/api/batches/:batch_id/lien",
+            restrict(
+                get(financing::lien_status),
+                &[Role::Lender, Role::Warehouse, Role::Fpo, Role::Processor],
+            )
+            .merge(restrict(post(financing::mark_lien), &[Role::Lender])),
+        )
+        .route(
+            "/api/batches/:batch_id/lien/release",
+            restrict(post(financing::release_lien), &[Role::Lender]),
+        )
+        .route(
+            "/api/compliance/double-financing",
+            restrict(
+                get(financing::double_financing),
+                &[Role::Warehouse, Role::Fpo],
+            ),
         )
         // ==================== IPFS ROUTES ====================
-        .route("/api/ipfs/upload", post(crate::ipfs::upload_to_ipfs))
-        .route("/api/farmer/ipfs/upload", post(supply_chain_handlers::upload_farmer_ipfs_data))
+        .route(
+            "/api/ipfs/upload",
+            restrict(post(crate::ipfs::upload_to_ipfs), &ALL_ROLES),
+        )
+        .route(
+            "/api/farmer/ipfs/upload",
+            restrict(
+                post(supply_chain_handlers::upload_farmer_ipfs_data),
+                &[Role::Fpo, Role::Farmer],
+            ),
+        )
+        // ==================== ADMIN ROUTES ====================
+        .route(
+            "/api/admin/users",
+            get(auth::list_users).post(auth::create_user),
+        )
+        .route("/api/admin/users/:id/disable", post(auth::disable_user))
+        .route(
+            "/api/admin/api-keys",
+            get(api_keys::list_api_keys).post(api_keys::create_api_key),
+        )
+        .route(
+            "/api/admin/api-keys/:id/rotate",
+            post(api_keys::rotate_api_key),
+        )
+        .route(
+            "/api/admin/api-keys/:id/revoke",
+            post(api_keys::revoke_api_key),
+        )
+        .route(
+            "/api/admin/log-level",
+            get(admin::get_log_level).put(admin::set_log_level),
+        )
+        .route(
+            "/api/admin/slowlog",
+            get(admin::get_slowlog).delete(admin::clear_slowlog),
+        )
+        .route("/api/admin/usage", get(metering::all_usage))
+        .route("/api/usage", get(metering::own_usage))
+        .route("/api/admin/tx-queue", get(tx_queue::list_transactions))
+        .route("/api/chain/tx/:tx_hash", get(confirmations::tx_status))
+        .route("/api/admin/wallet", get(wallet::wallet_status))
+        .route("/api/admin/signers", get(signers::list_signers))
+        .route("/api/admin/signers/reload", post(signers::reload_signers))
+        .route("/api/admin/chain/reload", post(networks::reload_chain))
+        .route("/api/admin/outbox", get(outbox::list_entries))
+        .route(
+            "/api/admin/outbox/:id/requeue",
+            post(outbox::requeue_entry),
+        )
+        .route("/api/admin/roles/grant", post(admin::grant_role))
+        .route("/api/admin/roles/revoke", post(admin::revoke_role))
+        .route("/api/admin/roles/check", get(admin::check_role))
+        .route("/api/admin/chain-roles", get(chain_roles::list_bindings))
+        .route(
+            "/api/admin/chain-roles/bindings",
+            put(chain_roles::bind_principal),
+        )
+        .route("/api/admin/fpos", post(fpo_registry::create_fpo))
+        .route(
+            "/api/admin/transporters/:id",
+            put(transport::put_transporter).delete(transport::delete_transporter),
+        )
+        .route(
+            "/api/admin/vehicles/:registration_number",
+            put(transport::put_vehicle).delete(transport::delete_vehicle),
+        )
+        .route(
+            "/api/admin/drivers/:id",
+            put(transport::put_driver).delete(transport::delete_driver),
+        )
+        .route(
+            "/api/admin/warehouses",
+            post(warehouse_registry::create_warehouse),
+        )
+        .route(
+            "/api/admin/warehouses/:id",
+            put(warehouse_registry::update_warehouse).delete(warehouse_registry::delete_warehouse),
+        )
+        .route(
+            "/api/admin/processors",
+            post(processor_registry::create_processor),
+        )
+        .route(
+            "/api/admin/processors/:id",
+            put(processor_registry::update_processor).delete(processor_registry::delete_processor),
+        )
+        .route(
+            "/api/admin/fpos/:id",
+            put(fpo_registry::update_fpo).delete(fpo_registry::delete_fpo),
+        )
+        .route(
+            "/api/admin/delegations",
+            get(delegation::list_delegations).post(delegation::create_delegation),
+        )
+        .route(
+            "/api/admin/delegations/:id/revoke",
+            post(delegation::revoke_delegation),
+        )
+        .route("/api/share", post(share::create_share))
+        .route("/api/admin/shares", get(share::list_shares))
+        .route("/api/admin/shares/:id/revoke", post(share::revoke_share))
+        .route(
+            "/api/admin/auditors",
+            get(auditor::list_auditors).post(auditor::invite_auditor),
+        )
+        .route(
+            "/api/admin/auditors/:id/disable",
+            post(auditor::disable_auditor),
+        )
+        .route(
+            "/api/notifications/send",
+            post(notifications::send_notification),
+        )
+        .route(
+            "/api/analytics/experiments",
+            get(experiments::experiment_analytics),
+        )
+        .route(
+            "/api/commitments/batch/:batch_id",
+            post(commitments::create_batch_commitment),
+        )
+        .route("/api/admin/anchors/run", post(anchoring::trigger_anchor))
+        .route(
+            "/api/admin/ledger/anchor/run",
+            post(ledger_anchors::trigger_ledger_anchor),
+        )
+        .route("/api/admin/snapshots", get(snapshots::list_snapshots))
+        .route("/api/admin/snapshots/run", post(snapshots::trigger_snapshot))
+        .route(
+            "/api/admin/workflow/trace/:id",
+            get(workflow_replay::get_trace),
+        )
+        .route("/api/admin/audit/digest/run", post(audit::trigger_digest))
+        .route("/api/admin/hold-rules", get(holds::list_hold_rules))
+        .route(
+            "/api/admin/settlements/:batch_id/release",
+            post(holds::release_settlement),
+        )
+        .route("/api/admin/schemes", get(schemes::list_schemes))
+        .route(
+            "/api/admin/schemes/:id/entitlements",
+            get(schemes::list_entitlements),
+        )
+        .route(
+            "/api/admin/schemes/:id/claims",
+            get(schemes::list_claims).post(schemes::create_claim),
+        )
+        .route(
+            "/api/admin/scheme-claims/:id/download",
+            get(schemes::download_claim),
+        )
+        .route("/api/admin/reports", get(reports::list_report_schedules))
+        .route("/api/admin/reports/runs", get(reports::list_report_runs))
+        .route(
+            "/api/admin/reports/runs/:id/download",
+            get(reports::download_report),
+        )
+        .route("/api/admin/reports/:id/run", post(reports::run_report))
         // Add state to all routes
         .with_state(state)
 }
@@ -0,0 +1,402 @@
+//! Counter-sample retention
+//!
+//! A physical counter-sample is kept from each purchase so disputes about
+//! delivered quality can be settled against it. `POST /api/samples` records
+//! where it is stored (location and container) and how long it is retained
+//! (`retention_days`, default SAMPLE_RETENTION_DAYS or 90). Samples of a
+//! batch are listed at `GET /api/samples/batch/:batch_id` and in the batch
+//! timeline.
+//!
+//! SAMPLE_REMINDER_DAYS (default 7) before the retention period ends, a
+//! reminder is logged and sent by SMS to the custodian, once per sample. A
+//! sample cannot be disposed of before its period ends, nor while an admin
+//! holds it for a dispute (`POST /api/admin/samples/:sample_id/hold`).
+//! Records are kept in `data/counter_samples.json`.
+
+use crate::admin::require_admin;
+use crate::batch_ledger;
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+const SAMPLES_PATH: &str = "data/counter_samples.json";
+const DEFAULT_RETENTION_DAYS: i64 = 90;
+const DEFAULT_REMINDER_DAYS: i64 = 7;
+/// Longest retention accepted, about five years
+const MAX_RETENTION_DAYS: i64 = 5 * 366;
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(3600);
+
+fn env_days(name: &str, default: i64) -> i64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|d: &i64| *d >= 0)
+        .unwrap_or(default)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SampleStatus {
+    Retained,
+    Disposed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CounterSample {
+    pub sample_id: String,
+    pub batch_id: String,
+    /// Store room, lab or warehouse holding the sample
+    pub location: String,
+    pub container_id: String,
+    pub retention_days: i64,
+    pub collected_at: String,
+    /// End of the retention period
+    pub dispose_after: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custodian_phone: Option<String>,
+    pub status: SampleStatus,
+    /// Kept past its retention period for a dispute
+    #[serde(default)]
+    pub on_hold: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hold_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reminder_sent_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disposed_at: Option<String>,
+}
+
+impl CounterSample {
+    fn dispose_after(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.dispose_after)
+            .ok()
+            .map(|t| t.with_timezone(&Utc))
+    }
+
+    /// Retained, not reminded yet, and within `lead` of its disposal date
+    fn due_for_reminder(&self, now: DateTime<Utc>, lead: Duration) -> bool {
+        self.status == SampleStatus::Retained
+            && !self.on_hold
+            && self.reminder_sent_at.is_none()
+            && self.dispose_after().is_some_and(|end| now + lead >= end)
+    }
+}
+
+pub struct SampleStore {
+    samples: Mutex<Vec<CounterSample>>,
+    retention_days: i64,
+    reminder_days: i64,
+}
+
+impl SampleStore {
+    pub fn load() -> Result<Self> {
+        let samples = match std::fs::read_to_string(SAMPLES_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid counter-sample file {}", SAMPLES_PATH))?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Self {
+            samples: Mutex::new(samples),
+            retention_days: env_days("SAMPLE_RETENTION_DAYS", DEFAULT_RETENTION_DAYS),
+            reminder_days: env_days("SAMPLE_REMINDER_DAYS", DEFAULT_REMINDER_DAYS),
+        })
+    }
+
+    fn save(samples: &[CounterSample]) -> Result<()> {
+        std::fs::write(SAMPLES_PATH, serde_json::to_string_pretty(samples)?)
+            .with_context(|| format!("Failed to write {}", SAMPLES_PATH))
+    }
+
+    /// Samples kept from `batch_id`, oldest first
+    pub async fn for_batch(&self, batch_id: &str) -> Vec<CounterSample> {
+        self.samples
+            .lock()
+            .await
+            .iter()
+            .filter(|s| s.batch_id == batch_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Send the reminders that are due; returns how many were sent
+    pub async fn send_reminders(&self, state: &AppState) -> Result<usize> {
+        let now = Utc::now();
+        let lead = Duration::days(self.reminder_days);
+        let due: Vec<CounterSample> = self
+            .samples
+            .lock()
+            .await
+            .iter()
+            .filter(|s| s.due_for_reminder(now, lead))
+            .cloned()
+            .collect();
+
+        for sample in &due {
+            tracing::warn!(
+                sample_id = %sample.sample_id,
+                batch_id = %sample.batch_id,
+                location = %sample.location,
+                dispose_after = %sample.dispose_after,
+                "Counter-sample retention period ending"
+            );
+            if let Some(phone) = &sample.custodian_phone {
+                let text = format!(
+                    "Counter-sample {} of batch {} (container {} at {}) may be disposed of after {}.",
+                    sample.sample_id,
+                    sample.batch_id,
+                    sample.container_id,
+                    sample.location,
+                    &sample.dispose_after[..10.min(sample.dispose_after.len())]
+                );
+                if let Err(e) = state.sms_client.send(phone, &text).await {
+                    tracing::warn!(sample_id = %sample.sample_id, error = %e, "Sample reminder SMS failed");
+                }
+            }
+        }
+
+        if !due.is_empty() {
+            let mut samples = self.samples.lock().await;
+            for sample in samples.iter_mut() {
+                if due.iter().any(|d| d.sample_id == sample.sample_id) {
+                    sample.reminder_sent_at = Some(now.to_rfc3339());
+                }
+            }
+            Self::save(&samples)?;
+        }
+        Ok(due.len())
+    }
+}
+
+/// Check hourly for samples nearing the end of their retention period
+pub fn spawn(state: AppState) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            match state.samples.send_reminders(&state).await {
+                Ok(0) => {}
+                Ok(sent) => tracing::info!(sent, "Counter-sample reminders sent"),
+                Err(e) => {
+                    tracing::warn!(error = %format!("{:#}", e), "Counter-sample reminder check failed")
+                }
+            }
+        }
+    });
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct RecordSampleRequest {
+    pub batch_id: String,
+    pub location: String,
+    pub container_id: String,
+    #[serde(default)]
+    pub retention_days: Option<i64>,
+    /// RFC 3339; defaults to now
+    #[serde(default)]
+    pub collected_at: Option<String>,
+    #[serde(default)]
+    pub custodian_phone: Option<String>,
+}
+
+/// Record where a counter-sample of a batch is kept
+pub async fn record_sample(
+    State(state): State<AppState>,
+    Json(payload): Json<RecordSampleRequest>,
+) -> ApiResult<CounterSample> {
+    if !batch_ledger::is_valid_batch_id(&payload.batch_id) {
+        return Err(ApiError::bad_request("Invalid batch ID"));
+    }
+    if batch_ledger::batch_records(&payload.batch_id)?.is_empty() {
+        return Err(ApiError::not_found(format!(
+            "No records for batch {}",
+            payload.batch_id
+        )));
+    }
+    let location = payload.location.trim();
+    let container_id = payload.container_id.trim();
+    if location.is_empty() || container_id.is_empty() {
+        return Err(ApiError::bad_request(
+            "location and container_id are required",
+        ));
+    }
+    let store = &state.samples;
+    let retention_days = payload.retention_days.unwrap_or(store.retention_days);
+    if !(1..=MAX_RETENTION_DAYS).contains(&retention_days) {
+        return Err(ApiError::bad_request(format!(
+            "retention_days must be between 1 and {}",
+            MAX_RETENTION_DAYS
+        )));
+    }
+    let collected_at = match &payload.collected_at {
+        Some(time) => DateTime::parse_from_rfc3339(time)
+            .map_err(|_| ApiError::bad_request("collected_at must be an RFC 3339 timestamp"))?
+            .with_timezone(&Utc),
+        None => Utc::now(),
+    };
+
+    let mut samples = store.samples.lock().await;
+    if samples
+        .iter()
+        .any(|s| s.container_id == container_id && s.status == SampleStatus::Retained)
+    {
+        return Err(ApiError::bad_request(format!(
+            "Container {} already holds a retained sample",
+            container_id
+        )));
+    }
+    let sample = CounterSample {
+        sample_id: format!("CS-{}", hex::encode(rand::random::<[u8; 6]>())),
+        batch_id: payload.batch_id,
+        location: location.to_string(),
+        container_id: container_id.to_string(),
+        retention_days,
+        collected_at: collected_at.to_rfc3339(),
+        dispose_after: (collected_at + Duration::days(retention_days)).to_rfc3339(),
+        custodian_phone: payload.custodian_phone.filter(|p| !p.trim().is_empty()),
+        status: SampleStatus::Retained,
+        on_hold: false,
+        hold_reason: None,
+        reminder_sent_at: None,
+        disposed_at: None,
+    };
+    samples.push(sample.clone());
+    SampleStore::save(&samples)?;
+
+    tracing::info!(
+        sample_id = %sample.sample_id,
+        batch_id = %sample.batch_id,
+        container_id = %sample.container_id,
+        "Counter-sample recorded"
+    );
+    Ok(Json(sample))
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchSamplesView {
+    pub batch_id: String,
+    pub samples: Vec<CounterSample>,
+}
+
+pub async fn list_batch_samples(
+    State(state): State<AppState>,
+    Path(batch_id): Path<String>,
+) -> ApiResult<BatchSamplesView> {
+    let samples = state.samples.for_batch(&batch_id).await;
+    Ok(Json(BatchSamplesView { batch_id, samples }))
+}
+
+/// Mark a sample disposed of once its retention period is over
+pub async fn dispose_sample(
+    State(state): State<AppState>,
+    Path(sample_id): Path<String>,
+) -> ApiResult<CounterSample> {
+    let mut samples = state.samples.samples.lock().await;
+    let sample = samples
+        .iter_mut()
+        .find(|s| s.sample_id == sample_id)
+        .ok_or_else(|| ApiError::not_found(format!("Counter-sample {} not found", sample_id)))?;
+
+    if sample.status == SampleStatus::Disposed {
+        return Err(ApiError::bad_request("Sample was already disposed of"));
+    }
+    if sample.on_hold {
+        return Err(ApiError::forbidden(format!(
+            "Sample is held for a dispute: {}",
+            sample.hold_reason.as_deref().unwrap_or("no reason given")
+        )));
+    }
+    if sample.dispose_after().is_some_and(|end| Utc::now() < end) {
+        return Err(ApiError::bad_request(format!(
+            "Sample must be retained until {}",
+            sample.dispose_after
+        )));
+    }
+    sample.status = SampleStatus::Disposed;
+    sample.disposed_at = Some(Utc::now().to_rfc3339());
+    let sample = sample.clone();
+    SampleStore::save(&samples)?;
+
+    tracing::info!(sample_id = %sample.sample_id, batch_id = %sample.batch_id, "Counter-sample disposed");
+    Ok(Json(sample))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HoldSampleRequest {
+    pub hold: bool,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Keep a sample past its retention period while a dispute is open, or
+/// release it (admin only)
+pub async fn hold_sample(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(sample_id): Path<String>,
+    Json(payload): Json<HoldSampleRequest>,
+) -> ApiResult<CounterSample> {
+    require_admin(&state, &headers)?;
+
+    let mut samples = state.samples.samples.lock().await;
+    let sample = samples
+        .iter_mut()
+        .find(|s| s.sample_id == sample_id)
+        .ok_or_else(|| ApiError::not_found(format!("Counter-sample {} not found", sample_id)))?;
+    if sample.status == SampleStatus::Disposed {
+        return Err(ApiError::bad_request("Sample was already disposed of"));
+    }
+    sample.on_hold = payload.hold;
+    sample.hold_reason = payload.reason.filter(|_| payload.hold);
+    let sample = sample.clone();
+    SampleStore::save(&samples)?;
+
+    tracing::info!(sample_id = %sample.sample_id, hold = sample.on_hold, "Counter-sample hold updated");
+    Ok(Json(sample))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reminder_due_once_before_disposal() {
+        let collected = DateTime::parse_from_rfc3339("2025-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let mut sample = CounterSample {
+            sample_id: "CS-1".to_string(),
+            batch_id: "B-1".to_string(),
+            location: "FPO store room".to_string(),
+            container_id: "JAR-7".to_string(),
+            retention_days: 90,
+            collected_at: collected.to_rfc3339(),
+            dispose_after: (collected + Duration::days(90)).to_rfc3339(),
+            custodian_phone: None,
+            status: SampleStatus::Retained,
+            on_hold: false,
+            hold_reason: None,
+            reminder_sent_at: None,
+            disposed_at: None,
+        };
+        let lead = Duration::days(7);
+
+        assert!(!sample.due_for_reminder(collected + Duration::days(82), lead));
+        assert!(sample.due_for_reminder(collected + Duration::days(84), lead));
+
+        sample.on_hold = true;
+        assert!(!sample.due_for_reminder(collected + Duration::days(84), lead));
+        sample.on_hold = false;
+        sample.reminder_sent_at = Some(collected.to_rfc3339());
+        assert!(!sample.due_for_reminder(collected + Duration::days(84), lead));
+    }
+}
@@ -0,0 +1,846 @@
+//! Incentive schemes paying quality bonuses on FPO purchases
+//!
+//! Schemes are read from `data/schemes.json` (override with
+//! SCHEMES_CONFIG_PATH):
+//!
+//! ```json
+//! [{ "id": "nmeo-grade-a-2025", "name": "NMEO-OP grade A bonus",
+//!    "authority": "NMEO-OP", "start_date": "2025-04-01", "end_date": "2026-03-31",
+//!    "eligibility": { "grades": ["A"], "crops": ["groundnut"], "min_quantity_kg": 100 },
+//!    "bonus": { "per_kg": 2.0, "max_per_purchase": 5000 },
+//!    "budget": 1000000, "max_per_farmer": 20000,
+//!    "claim_format": { "format": "csv", "columns": [
+//!      { "header": "BENEFICIARY_ID", "field": "farmer_did" },
+//!      { "header": "BONUS_INR", "field": "bonus" }] } }]
+//! ```
+//!
+//! Entitlements are derived from the FPO purchase records on every request,
+//! in purchase order: the bonus formula is applied to each eligible purchase,
+//! then capped per purchase, per farmer and by the remaining budget, so
+//! early purchases are funded first. Purchases whose payment is on hold (see
+//! [`crate::holds`]) keep their share of the budget but are left out of claim
+//! files until released. Claim files are stored under `data/scheme_claims/`
+//! and recorded in `data/scheme_claims.json`; claimed purchases are not
+//! claimed again.
+
+use crate::admin::require_admin;
+use crate::batch_ledger;
+use crate::error::{ApiError, ApiResult};
+use crate::export::{self, ExportFormat, Table};
+use crate::holds::{self, SettlementStatus};
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use tokio::sync::Mutex;
+
+const DEFAULT_SCHEMES_CONFIG_PATH: &str = "data/schemes.json";
+const CLAIMS_PATH: &str = "data/scheme_claims.json";
+const CLAIMS_DIR: &str = "data/scheme_claims";
+
+// ======================== CONFIGURATION ========================
+
+/// Which purchases a scheme covers; empty lists match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Eligibility {
+    /// Quality grades, compared case-insensitively
+    #[serde(default)]
+    pub grades: Vec<String>,
+    #[serde(default)]
+    pub crops: Vec<String>,
+    /// Farmer district codes
+    #[serde(default)]
+    pub districts: Vec<String>,
+    #[serde(default)]
+    pub min_quantity_kg: Option<f64>,
+}
+
+/// Bonus per purchase: `per_kg * quantity + percent_of_value% * value + flat`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BonusFormula {
+    #[serde(default)]
+    pub per_kg: f64,
+    #[serde(default)]
+    pub percent_of_value: f64,
+    #[serde(default)]
+    pub flat: f64,
+    #[serde(default)]
+    pub max_per_purchase: Option<f64>,
+}
+
+impl BonusFormula {
+    pub fn compute(&self, quantity_kg: f64, value: f64) -> f64 {
+        let bonus = self.per_kg * quantity_kg + self.percent_of_value / 100.0 * value + self.flat;
+        let bonus = match self.max_per_purchase {
+            Some(max) => bonus.min(max),
+            None => bonus,
+        };
+        round_rupees(bonus.max(0.0))
+    }
+}
+
+/// Fields a claim file column can carry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimField {
+    ClaimId,
+    SchemeId,
+    BatchId,
+    FarmerDid,
+    District,
+    CropType,
+    QualityGrade,
+    QuantityKg,
+    PurchaseValue,
+    PurchaseDate,
+    Bonus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimColumn {
+    pub header: String,
+    pub field: ClaimField,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimFormat {
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Columns in the order the authority expects
+    #[serde(default = "default_claim_columns")]
+    pub columns: Vec<ClaimColumn>,
+}
+
+impl Default for ClaimFormat {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::Csv,
+            columns: default_claim_columns(),
+        }
+    }
+}
+
+fn default_claim_columns() -> Vec<ClaimColumn> {
+    [
+        ("claim_id", ClaimField::ClaimId),
+        ("batch_id", ClaimField::BatchId),
+        ("farmer_did", ClaimField::FarmerDid),
+        ("district", ClaimField::District),
+        ("crop_type", ClaimField::CropType),
+        ("quality_grade", ClaimField::QualityGrade),
+        ("quantity_kg", ClaimField::QuantityKg),
+        ("purchase_value", ClaimField::PurchaseValue),
+        ("purchase_date", ClaimField::PurchaseDate),
+        ("bonus", ClaimField::Bonus),
+    ]
+    .into_iter()
+    .map(|(header, field)| ClaimColumn {
+        header: header.to_string(),
+        field,
+    })
+    .collect()
+}
+
+fn default_active() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scheme {
+    pub id: String,
+    pub name: String,
+    /// Body that pays the claims
+    pub authority: String,
+    /// First and last purchase day covered (YYYY-MM-DD, inclusive)
+    pub start_date: String,
+    pub end_date: String,
+    #[serde(default)]
+    pub eligibility: Eligibility,
+    pub bonus: BonusFormula,
+    /// Total bonus the scheme can pay
+    pub budget: f64,
+    #[serde(default)]
+    pub max_per_farmer: Option<f64>,
+    #[serde(default)]
+    pub claim_format: ClaimFormat,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn matches_any(allowed: &[String], value: &str) -> bool {
+    allowed.is_empty() || allowed.iter().any(|a| a.eq_ignore_ascii_case(value))
+}
+
+impl Scheme {
+    pub fn is_eligible(&self, purchase: &Purchase) -> bool {
+        let rules = &self.eligibility;
+        // ISO dates order lexicographically
+        let day = purchase.date.format("%Y-%m-%d").to_string();
+        (self.start_date.as_str()..=self.end_date.as_str()).contains(&day.as_str())
+            && matches_any(&rules.grades, &purchase.quality_grade)
+            && matches_any(&rules.crops, &purchase.crop_type)
+            && matches_any(&rules.districts, &purchase.district)
+            && rules
+                .min_quantity_kg
+                .is_none_or(|min| purchase.quantity_kg >= min)
+    }
+}
+
+// ======================== ENTITLEMENTS ========================
+
+/// Fields of an FPO purchase record the schemes look at
+#[derive(Debug, Clone, PartialEq)]
+pub struct Purchase {
+    pub batch_id: String,
+    pub farmer_did: String,
+    pub district: String,
+    pub crop_type: String,
+    pub quality_grade: String,
+    pub quantity_kg: f64,
+    /// Product cost, excluding transport
+    pub value: f64,
+    pub timestamp: String,
+    pub date: NaiveDate,
+}
+
+impl Purchase {
+    /// Read a purchase record; `None` if it lacks a farmer or timestamp
+    pub fn from_record(
+        batch_id: &str,
+        record: &Value,
+        districts: &HashMap<String, String>,
+    ) -> Option<Self> {
+        let text = |pointer: &str| record.pointer(pointer).and_then(|v| v.as_str());
+        let number = |pointer: &str| record.pointer(pointer).and_then(|v| v.as_f64());
+
+        let farmer_did = text("/farmer_info/farmer_did")?.to_string();
+        let timestamp = text("/timestamp")?.to_string();
+        let date = DateTime::parse_from_rfc3339(&timestamp)
+            .ok()?
+            .with_timezone(&Utc)
+            .date_naive();
+        let quantity_kg = number("/batch_info/quantity_kg").unwrap_or(0.0);
+        let value = number("/pricing/product_cost")
+            .unwrap_or_else(|| quantity_kg * number("/pricing/price_per_kg").unwrap_or(0.0));
+
+        Some(Self {
+            batch_id: batch_id.to_string(),
+            district: districts.get(&farmer_did).cloned().unwrap_or_default(),
+            farmer_did,
+            crop_type: text("/farmer_info/crop_type")
+                .unwrap_or_default()
+                .to_string(),
+            quality_grade: text("/batch_info/quality_grade")
+                .unwrap_or_default()
+                .to_string(),
+            quantity_kg,
+            value,
+            timestamp,
+            date,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntitlementStatus {
+    /// Ready to be claimed
+    Payable,
+    /// Payment of the purchase is on hold
+    OnHold,
+    Claimed,
+    /// Caps left nothing to pay
+    Exhausted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Entitlement {
+    pub scheme_id: String,
+    pub batch_id: String,
+    pub farmer_did: String,
+    pub district: String,
+    pub crop_type: String,
+    pub quality_grade: String,
+    pub quantity_kg: f64,
+    pub purchase_value: f64,
+    pub purchase_date: String,
+    /// Bonus from the formula before farmer and budget caps
+    pub formula_bonus: f64,
+    pub bonus: f64,
+    /// Cap that reduced the bonus, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capped_by: Option<&'static str>,
+    pub status: EntitlementStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub claim_id: Option<u64>,
+}
+
+fn round_rupees(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+/// Entitlements of `scheme` over `purchases`, in purchase order
+///
+/// `on_hold` holds batch IDs whose payment is on hold and `claimed` maps
+/// batch IDs to the claim that included them.
+pub fn entitlements(
+    scheme: &Scheme,
+    purchases: &[Purchase],
+    on_hold: &HashSet<String>,
+    claimed: &HashMap<String, u64>,
+) -> Vec<Entitlement> {
+    let mut eligible: Vec<&Purchase> = purchases.iter().filter(|p| scheme.is_eligible(p)).collect();
+    eligible.sort_by(|a, b| {
+        a.timestamp
+            .cmp(&b.timestamp)
+            .then_with(|| a.batch_id.cmp(&b.batch_id))
+    });
+
+    let mut remaining_budget = scheme.budget.max(0.0);
+    let mut paid_per_farmer: HashMap<&str, f64> = HashMap::new();
+    let mut result = Vec::with_capacity(eligible.len());
+
+    for purchase in eligible {
+        let formula_bonus = scheme.bonus.compute(purchase.quantity_kg, purchase.value);
+        let mut bonus = formula_bonus;
+        let mut capped_by = None;
+
+        let paid = paid_per_farmer.entry(&purchase.farmer_did).or_default();
+        if let Some(max) = scheme.max_per_farmer {
+            let room = (max - *paid).max(0.0);
+            if bonus > room {
+                bonus = room;
+                capped_by = Some("max_per_farmer");
+            }
+        }
+        if bonus > remaining_budget {
+            bonus = remaining_budget;
+            capped_by = Some("budget");
+        }
+        let bonus = round_rupees(bonus);
+        *paid += bonus;
+        remaining_budget = round_rupees(remaining_budget - bonus);
+
+        let claim_id = claimed.get(&purchase.batch_id).copied();
+        let status = if claim_id.is_some() {
+            EntitlementStatus::Claimed
+        } else if bonus <= 0.0 {
+            EntitlementStatus::Exhausted
+        } else if on_hold.contains(&purchase.batch_id) {
+            EntitlementStatus::OnHold
+        } else {
+            EntitlementStatus::Payable
+        };
+
+        result.push(Entitlement {
+            scheme_id: scheme.id.clone(),
+            batch_id: purchase.batch_id.clone(),
+            farmer_did: purchase.farmer_did.clone(),
+            district: purchase.district.clone(),
+            crop_type: purchase.crop_type.clone(),
+            quality_grade: purchase.quality_grade.clone(),
+            quantity_kg: purchase.quantity_kg,
+            purchase_value: purchase.value,
+            purchase_date: purchase.date.format("%Y-%m-%d").to_string(),
+            formula_bonus,
+            bonus,
+            capped_by,
+            status,
+            claim_id,
+        });
+    }
+
+    result
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Utilization {
+    pub budget: f64,
+    pub eligible_purchases: usize,
+    /// Bonus committed to eligible purchases, whatever their status
+    pub committed: f64,
+    pub claimed: f64,
+    pub payable: f64,
+    pub on_hold: f64,
+    pub remaining: f64,
+    /// Committed share of the budget, 0-100
+    pub utilization_pct: f64,
+}
+
+pub fn utilization(scheme: &Scheme, entitlements: &[Entitlement]) -> Utilization {
+    let sum = |status: EntitlementStatus| {
+        round_rupees(
+            entitlements
+                .iter()
+                .filter(|e| e.status == status)
+                .map(|e| e.bonus)
+                .sum(),
+        )
+    };
+    let committed = round_rupees(entitlements.iter().map(|e| e.bonus).sum());
+    let budget = scheme.budget.max(0.0);
+
+    Utilization {
+        budget,
+        eligible_purchases: entitlements.len(),
+        committed,
+        claimed: sum(EntitlementStatus::Claimed),
+        payable: sum(EntitlementStatus::Payable),
+        on_hold: sum(EntitlementStatus::OnHold),
+        remaining: round_rupees((budget - committed).max(0.0)),
+        utilization_pct: if budget > 0.0 {
+            round_rupees(committed / budget * 100.0)
+        } else {
+            0.0
+        },
+    }
+}
+
+// ======================== CLAIMS ========================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClaimFile {
+    pub id: u64,
+    pub scheme_id: String,
+    pub authority: String,
+    pub format: ExportFormat,
+    /// File name under `data/scheme_claims/`
+    pub file: String,
+    pub batch_ids: Vec<String>,
+    pub total_bonus: f64,
+    pub sha256: String,
+    pub created_at: String,
+}
+
+fn claim_value(field: ClaimField, claim_id: u64, e: &Entitlement) -> String {
+    match field {
+        ClaimField::ClaimId => claim_id.to_string(),
+        ClaimField::SchemeId => e.scheme_id.clone(),
+        ClaimField::BatchId => e.batch_id.clone(),
+        ClaimField::FarmerDid => e.farmer_did.clone(),
+        ClaimField::District => e.district.clone(),
+        ClaimField::CropType => e.crop_type.clone(),
+        ClaimField::QualityGrade => e.quality_grade.clone(),
+        ClaimField::QuantityKg => format!("{:.2}", e.quantity_kg),
+        ClaimField::PurchaseValue => format!("{:.2}", e.purchase_value),
+        ClaimField::PurchaseDate => e.purchase_date.clone(),
+        ClaimField::Bonus => format!("{:.2}", e.bonus),
+    }
+}
+
+/// Claim file table in the scheme's column layout
+pub fn claim_table(scheme: &Scheme, claim_id: u64, payable: &[Entitlement]) -> Table {
+    let columns = &scheme.claim_format.columns;
+    let total: f64 = payable.iter().map(|e| e.bonus).sum();
+    Table {
+        title: format!("{} - claim {}", scheme.name, claim_id),
+        notes: vec![
+            format!("Authority: {}", scheme.authority),
+            format!(
+                "{} purchases, total bonus Rs {:.2}",
+                payable.len(),
+                round_rupees(total)
+            ),
+        ],
+        columns: columns.iter().map(|c| c.header.clone()).collect(),
+        rows: payable
+            .iter()
+            .map(|e| {
+                columns
+                    .iter()
+                    .map(|c| claim_value(c.field, claim_id, e))
+                    .collect()
+            })
+            .collect(),
+    }
+}
+
+// ======================== REGISTRY ========================
+
+pub struct SchemeRegistry {
+    schemes: Vec<Scheme>,
+    claims: Mutex<Vec<ClaimFile>>,
+}
+
+impl SchemeRegistry {
+    pub fn load() -> Result<Self> {
+        let path = std::env::var("SCHEMES_CONFIG_PATH")
+            .unwrap_or_else(|_| DEFAULT_SCHEMES_CONFIG_PATH.to_string());
+
+        let schemes: Vec<Scheme> = if std::path::Path::new(&path).exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read schemes config {}", path))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid schemes config {}", path))?
+        } else {
+            Vec::new()
+        };
+
+        let mut ids = HashSet::new();
+        for scheme in &schemes {
+            // Scheme IDs end up in claim file names
+            if !batch_ledger::is_valid_batch_id(&scheme.id) {
+                bail!("Invalid scheme ID {:?}", scheme.id);
+            }
+            if !ids.insert(scheme.id.as_str()) {
+                bail!("Duplicate scheme ID {}", scheme.id);
+            }
+            for date in [&scheme.start_date, &scheme.end_date] {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .with_context(|| format!("Invalid date {:?} in scheme {}", date, scheme.id))?;
+            }
+            if scheme.end_date < scheme.start_date {
+                bail!("Scheme {} ends before it starts", scheme.id);
+            }
+        }
+
+        let claims = match std::fs::read_to_string(CLAIMS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid scheme claims file {}", CLAIMS_PATH))?,
+            Err(_) => Vec::new(),
+        };
+
+        Ok(Self {
+            schemes,
+            claims: Mutex::new(claims),
+        })
+    }
+
+    fn save(claims: &[ClaimFile]) -> Result<()> {
+        std::fs::write(CLAIMS_PATH, serde_json::to_string_pretty(claims)?)
+            .with_context(|| format!("Failed to write {}", CLAIMS_PATH))
+    }
+
+    fn scheme(&self, id: &str) -> Result<&Scheme, ApiError> {
+        self.schemes
+            .iter()
+            .find(|s| s.id == id)
+            .ok_or_else(|| ApiError::not_found(format!("Scheme {} not found", id)))
+    }
+}
+
+/// Current entitlements of a scheme
+async fn scheme_entitlements(
+    state: &AppState,
+    scheme: &Scheme,
+    claims: &[ClaimFile],
+) -> Result<Vec<Entitlement>> {
+    let districts: HashMap<String, String> = state
+        .farmer_verification
+        .farmers_snapshot()
+        .await?
+        .into_iter()
+        .map(|f| (f.farmer_did, f.district_code))
+        .collect();
+    let purchases: Vec<Purchase> = batch_ledger::all_purchases()
+        .iter()
+        .filter_map(|(batch_id, record)| Purchase::from_record(batch_id, record, &districts))
+        .collect();
+
+    let mut on_hold = HashSet::new();
+    for purchase in &purchases {
+        if holds::settlement(&purchase.batch_id)?
+            .is_some_and(|s| s.status == SettlementStatus::OnHold)
+        {
+            on_hold.insert(purchase.batch_id.clone());
+        }
+    }
+    let claimed: HashMap<String, u64> = claims
+        .iter()
+        .filter(|c| c.scheme_id == scheme.id)
+        .flat_map(|c| c.batch_ids.iter().map(move |b| (b.clone(), c.id)))
+        .collect();
+
+    Ok(entitlements(scheme, &purchases, &on_hold, &claimed))
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct SchemeStatus {
+    #[serde(flatten)]
+    pub scheme: Scheme,
+    pub utilization: Utilization,
+}
+
+pub async fn list_schemes(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<Vec<SchemeStatus>> {
+    require_admin(&state, &headers)?;
+
+    let claims = state.schemes.claims.lock().await.clone();
+    let mut statuses = Vec::with_capacity(state.schemes.schemes.len());
+    for scheme in &state.schemes.schemes {
+        let entitlements = scheme_entitlements(&state, scheme, &claims).await?;
+        statuses.push(SchemeStatus {
+            utilization: utilization(scheme, &entitlements),
+            scheme: scheme.clone(),
+        });
+    }
+    Ok(Json(statuses))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EntitlementParams {
+    #[serde(default)]
+    pub farmer_did: Option<String>,
+}
+
+pub async fn list_entitlements(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Query(params): Query<EntitlementParams>,
+    Query(page): Query<PageParams>,
+) -> ApiResult<Page<Entitlement>> {
+    require_admin(&state, &headers)?;
+
+    let scheme = state.schemes.scheme(&id)?;
+    let claims = state.schemes.claims.lock().await.clone();
+    let entitlements: Vec<Entitlement> = scheme_entitlements(&state, scheme, &claims)
+        .await?
+        .into_iter()
+        .filter(|e| {
+            params
+                .farmer_did
+                .as_deref()
+                .is_none_or(|did| e.farmer_did == did)
+        })
+        .collect();
+    let scope = format!("schemes/{}/entitlements", scheme.id);
+    Ok(Json(paginate(&scope, entitlements, &page, |e| {
+        e.batch_id.clone()
+    })?))
+}
+
+/// Export every payable, unclaimed entitlement as a claim file
+pub async fn create_claim(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> ApiResult<ClaimFile> {
+    require_admin(&state, &headers)?;
+
+    let scheme = state.schemes.scheme(&id)?;
+    if !scheme.active {
+        return Err(ApiError::bad_request(format!(
+            "Scheme {} is not active",
+            scheme.id
+        )));
+    }
+
+    // Held for the whole export so two claims never include the same purchase
+    let mut claims = state.schemes.claims.lock().await;
+    let payable: Vec<Entitlement> = scheme_entitlements(&state, scheme, &claims)
+        .await?
+        .into_iter()
+        .filter(|e| e.status == EntitlementStatus::Payable)
+        .collect();
+    if payable.is_empty() {
+        return Err(ApiError::bad_request(format!(
+            "Scheme {} has no payable entitlements to claim",
+            scheme.id
+        )));
+    }
+
+    let claim_id = claims.last().map(|c| c.id + 1).unwrap_or(1);
+    let format = scheme.claim_format.format;
+    let body = export::render(&claim_table(scheme, claim_id, &payable), format);
+    let created_at = Utc::now();
+    let file = format!(
+        "{}_claim_{}_{}.{}",
+        scheme.id,
+        claim_id,
+        created_at.format("%Y%m%d"),
+        format.extension()
+    );
+    std::fs::create_dir_all(CLAIMS_DIR)
+        .with_context(|| format!("Failed to create {}", CLAIMS_DIR))?;
+    let path = std::path::Path::new(CLAIMS_DIR).join(&file);
+    std::fs::write(&path, &body).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    let claim = ClaimFile {
+        id: claim_id,
+        scheme_id: scheme.id.clone(),
+        authority: scheme.authority.clone(),
+        format,
+        file,
+        batch_ids: payable.iter().map(|e| e.batch_id.clone()).collect(),
+        total_bonus: round_rupees(payable.iter().map(|e| e.bonus).sum()),
+        sha256: hex::encode(Sha256::digest(&body)),
+        created_at: created_at.to_rfc3339(),
+    };
+    claims.push(claim.clone());
+    SchemeRegistry::save(&claims)?;
+
+    tracing::info!(
+        scheme_id = %claim.scheme_id,
+        claim_id = claim.id,
+        purchases = claim.batch_ids.len(),
+        total_bonus = claim.total_bonus,
+        "Scheme claim file exported"
+    );
+    Ok(Json(claim))
+}
+
+/// Claim files of a scheme, newest first
+pub async fn list_claims(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<ClaimFile>> {
+    require_admin(&state, &headers)?;
+
+    state.schemes.scheme(&id)?;
+    let claims: Vec<ClaimFile> = state
+        .schemes
+        .claims
+        .lock()
+        .await
+        .iter()
+        .filter(|c| c.scheme_id == id)
+        .cloned()
+        .collect();
+    let scope = format!("schemes/{}/claims", id);
+    Ok(Json(paginate(&scope, claims, &params, |c| Reverse(c.id))?))
+}
+
+pub async fn download_claim(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> Result<Response, ApiError> {
+    require_admin(&state, &headers)?;
+
+    let claim = state
+        .schemes
+        .claims
+        .lock()
+        .await
+        .iter()
+        .find(|c| c.id == id)
+        .cloned()
+        .ok_or_else(|| ApiError::not_found(format!("Claim {} not found", id)))?;
+    let path = std::path::Path::new(CLAIMS_DIR).join(&claim.file);
+    let body = std::fs::read(&path).map_err(|e| {
+        ApiError::not_found(format!("Claim file {} unavailable: {}", claim.file, e))
+    })?;
+
+    Ok((
+        [
+            (
+                header::CONTENT_TYPE,
+                claim.format.content_type().to_string(),
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", claim.file),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheme() -> Scheme {
+        serde_json::from_value(serde_json::json!({
+            "id": "grade-a",
+            "name": "Grade A bonus",
+            "authority": "NMEO-OP",
+            "start_date": "2025-04-01",
+            "end_date": "2026-03-31",
+            "eligibility": { "grades": ["A"], "min_quantity_kg": 100 },
+            "bonus": { "per_kg": 2.0, "max_per_purchase": 1500 },
+            "budget": 2500,
+            "max_per_farmer": 1800
+        }))
+        .unwrap()
+    }
+
+    fn purchase(batch_id: &str, farmer: &str, grade: &str, kg: f64, timestamp: &str) -> Purchase {
+        Purchase {
+            batch_id: batch_id.to_string(),
+            farmer_did: farmer.to_string(),
+            district: "D01".to_string(),
+            crop_type: "groundnut".to_string(),
+            quality_grade: grade.to_string(),
+            quantity_kg: kg,
+            value: kg * 60.0,
+            timestamp: timestamp.to_string(),
+            date: NaiveDate::parse_from_str(&timestamp[..10], "%Y-%m-%d").unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_caps_apply_in_purchase_order() {
+        let purchases = vec![
+            purchase("b3", "f2", "A", 1000.0, "2025-06-03T00:00:00Z"),
+            purchase("b1", "f1", "a", 1000.0, "2025-06-01T00:00:00Z"),
+            purchase("b2", "f1", "A", 200.0, "2025-06-02T00:00:00Z"),
+            purchase("b4", "f3", "B", 1000.0, "2025-06-04T00:00:00Z"),
+            purchase("b5", "f3", "A", 50.0, "2025-06-05T00:00:00Z"),
+            purchase("b6", "f3", "A", 500.0, "2025-03-01T00:00:00Z"),
+        ];
+        let result = entitlements(&scheme(), &purchases, &HashSet::new(), &HashMap::new());
+        let rows: Vec<(&str, f64, Option<&str>)> = result
+            .iter()
+            .map(|e| (e.batch_id.as_str(), e.bonus, e.capped_by))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                // 2000 from the formula, capped per purchase
+                ("b1", 1500.0, None),
+                // 400 from the formula, farmer f1 has 300 left
+                ("b2", 300.0, Some("max_per_farmer")),
+                // Budget has 700 left
+                ("b3", 700.0, Some("budget")),
+            ]
+        );
+        let usage = utilization(&scheme(), &result);
+        assert_eq!((usage.committed, usage.remaining), (2500.0, 0.0));
+    }
+
+    #[test]
+    fn test_held_and_claimed_purchases_are_not_payable() {
+        let purchases = vec![
+            purchase("b1", "f1", "A", 100.0, "2025-06-01T00:00:00Z"),
+            purchase("b2", "f2", "A", 100.0, "2025-06-02T00:00:00Z"),
+            purchase("b3", "f3", "A", 100.0, "2025-06-03T00:00:00Z"),
+        ];
+        let on_hold = HashSet::from(["b2".to_string()]);
+        let claimed = HashMap::from([("b1".to_string(), 7)]);
+        let result = entitlements(&scheme(), &purchases, &on_hold, &claimed);
+        let statuses: Vec<EntitlementStatus> = result.iter().map(|e| e.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                EntitlementStatus::Claimed,
+                EntitlementStatus::OnHold,
+                EntitlementStatus::Payable
+            ]
+        );
+
+        let table = claim_table(&scheme(), 8, &result[2..]);
+        assert_eq!(table.columns[0], "claim_id");
+        assert_eq!(table.rows[0][0], "8");
+        assert_eq!(table.rows[0].last().unwrap(), "200.00");
+    }
+}
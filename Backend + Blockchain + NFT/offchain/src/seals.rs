@@ -0,0 +1,348 @@
+//! Seals and tamper tags on shipments
+//!
+//! Seals are issued to a shipment at dispatch (`POST /api/logistics/seals`).
+//! Each gets a random tag, and its QR code carries [`Seal::qr_payload`], so
+//! a seal reprinted with the same number does not scan as the original.
+//!
+//! Logistics milestones for a sealed shipment list the QR payloads scanned
+//! at the checkpoint in `seals`. A milestone that leaves out an issued seal,
+//! scans a seal that was not issued to the shipment or carries a wrong tag,
+//! or sends no seal confirmation at all is recorded as an exception and the
+//! shipment is marked `exception`. The milestone itself is still recorded.
+//! The delivery milestone closes the shipment. Exceptions are listed at
+//! `GET /api/admin/seals/exceptions` for fraud review. Records are kept in
+//! `data/seals.json`.
+
+use crate::admin::require_admin;
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+const SEALS_PATH: &str = "data/seals.json";
+const QR_PREFIX: &str = "oilseed-seal";
+/// Seals on one shipment
+const MAX_SEALS: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Seal {
+    pub seal_id: String,
+    /// Random tag printed into the QR code
+    pub tag: String,
+}
+
+impl Seal {
+    /// Content of the seal's QR code
+    pub fn qr_payload(&self) -> String {
+        format!("{}:{}:{}", QR_PREFIX, self.seal_id, self.tag)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShipmentSealStatus {
+    InTransit,
+    Delivered,
+    /// A checkpoint reported missing or mismatched seals
+    Exception,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SealCheck {
+    pub location: String,
+    pub is_delivered: bool,
+    pub checked_at: String,
+    /// No seal confirmation was sent for this milestone
+    #[serde(default)]
+    pub unconfirmed: bool,
+    /// Issued seals that were not scanned
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing: Vec<String>,
+    /// Scans that match no issued seal, or carry the wrong tag
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mismatched: Vec<String>,
+}
+
+impl SealCheck {
+    pub fn intact(&self) -> bool {
+        !self.unconfirmed && self.missing.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+/// Compare the scans of a checkpoint with the seals issued at dispatch
+fn check_seals(issued: &[Seal], scanned: Option<&[String]>) -> (bool, Vec<String>, Vec<String>) {
+    let Some(scanned) = scanned else {
+        let missing = issued.iter().map(|s| s.seal_id.clone()).collect();
+        return (true, missing, Vec::new());
+    };
+    let scanned: Vec<&str> = scanned.iter().map(|s| s.trim()).collect();
+    let missing = issued
+        .iter()
+        .filter(|seal| !scanned.contains(&seal.qr_payload().as_str()))
+        .map(|seal| seal.seal_id.clone())
+        .collect();
+    let mismatched = scanned
+        .iter()
+        .filter(|scan| !issued.iter().any(|seal| seal.qr_payload() == **scan))
+        .map(|scan| scan.to_string())
+        .collect();
+    (false, missing, mismatched)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShipmentSeals {
+    pub shipment_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<String>,
+    pub seals: Vec<Seal>,
+    pub status: ShipmentSealStatus,
+    pub issued_at: String,
+    pub checks: Vec<SealCheck>,
+}
+
+pub struct SealStore {
+    shipments: Mutex<Vec<ShipmentSeals>>,
+}
+
+impl SealStore {
+    pub fn load() -> Result<Self> {
+        let shipments = match std::fs::read_to_string(SEALS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid seals file {}", SEALS_PATH))?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Self {
+            shipments: Mutex::new(shipments),
+        })
+    }
+
+    fn save(shipments: &[ShipmentSeals]) -> Result<()> {
+        std::fs::write(SEALS_PATH, serde_json::to_string_pretty(shipments)?)
+            .with_context(|| format!("Failed to write {}", SEALS_PATH))
+    }
+
+    /// Check the seals of a logistics milestone. `None` when no seals were
+    /// issued to the shipment.
+    pub async fn record_checkpoint(
+        &self,
+        shipment_id: &str,
+        location: &str,
+        is_delivered: bool,
+        scanned: Option<&[String]>,
+    ) -> Result<Option<SealCheck>> {
+        let mut shipments = self.shipments.lock().await;
+        let Some(shipment) = shipments.iter_mut().find(|s| s.shipment_id == shipment_id) else {
+            return Ok(None);
+        };
+
+        let (unconfirmed, missing, mismatched) = check_seals(&shipment.seals, scanned);
+        let check = SealCheck {
+            location: location.to_string(),
+            is_delivered,
+            checked_at: Utc::now().to_rfc3339(),
+            unconfirmed,
+            missing,
+            mismatched,
+        };
+        if !check.intact() {
+            tracing::warn!(
+                shipment_id,
+                location,
+                missing = ?check.missing,
+                mismatched = ?check.mismatched,
+                unconfirmed = check.unconfirmed,
+                "Seal exception on shipment"
+            );
+            shipment.status = ShipmentSealStatus::Exception;
+        } else if is_delivered && shipment.status == ShipmentSealStatus::InTransit {
+            shipment.status = ShipmentSealStatus::Delivered;
+        }
+        shipment.checks.push(check.clone());
+        Self::save(&shipments)?;
+        Ok(Some(check))
+    }
+
+    /// Shipments with a seal exception, for fraud review
+    pub async fn exceptions(&self) -> Vec<ShipmentSeals> {
+        self.shipments
+            .lock()
+            .await
+            .iter()
+            .filter(|s| s.status == ShipmentSealStatus::Exception)
+            .cloned()
+            .collect()
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct IssueSealsRequest {
+    pub shipment_id: String,
+    #[serde(default)]
+    pub batch_id: Option<String>,
+    /// Numbers printed on the physical seals
+    pub seal_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IssuedSeal {
+    pub seal_id: String,
+    pub qr_payload: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IssueSealsResponse {
+    pub shipment_id: String,
+    pub seals: Vec<IssuedSeal>,
+}
+
+/// Assign seals to a shipment at dispatch
+pub async fn issue_seals(
+    State(state): State<AppState>,
+    Json(payload): Json<IssueSealsRequest>,
+) -> ApiResult<IssueSealsResponse> {
+    if payload.shipment_id.trim().is_empty() {
+        return Err(ApiError::bad_request("shipment_id is required"));
+    }
+    if payload.seal_ids.is_empty() || payload.seal_ids.len() > MAX_SEALS {
+        return Err(ApiError::bad_request(format!(
+            "Between 1 and {} seals per shipment",
+            MAX_SEALS
+        )));
+    }
+    let seal_ids: Vec<&str> = payload.seal_ids.iter().map(|id| id.trim()).collect();
+    if seal_ids.iter().any(|id| id.is_empty() || id.contains(':')) {
+        return Err(ApiError::bad_request(
+            "Seal IDs must be non-empty and must not contain ':'",
+        ));
+    }
+
+    let mut shipments = state.seals.shipments.lock().await;
+    if shipments
+        .iter()
+        .any(|s| s.shipment_id == payload.shipment_id)
+    {
+        return Err(ApiError::bad_request(format!(
+            "Seals were already issued for shipment {}",
+            payload.shipment_id
+        )));
+    }
+    // Seals are single use
+    if let Some((_, reused)) = seal_ids.iter().enumerate().find(|(i, id)| {
+        seal_ids[..*i].contains(id)
+            || shipments
+                .iter()
+                .any(|s| s.seals.iter().any(|seal| seal.seal_id == **id))
+    }) {
+        return Err(ApiError::bad_request(format!(
+            "Seal {} has already been used",
+            reused
+        )));
+    }
+
+    let seals: Vec<Seal> = seal_ids
+        .iter()
+        .map(|id| Seal {
+            seal_id: id.to_string(),
+            tag: hex::encode(rand::random::<[u8; 8]>()),
+        })
+        .collect();
+    shipments.push(ShipmentSeals {
+        shipment_id: payload.shipment_id.clone(),
+        batch_id: payload.batch_id,
+        seals: seals.clone(),
+        status: ShipmentSealStatus::InTransit,
+        issued_at: Utc::now().to_rfc3339(),
+        checks: Vec::new(),
+    });
+    SealStore::save(&shipments)?;
+
+    tracing::info!(
+        shipment_id = %payload.shipment_id,
+        count = seals.len(),
+        "Issued shipment seals"
+    );
+    Ok(Json(IssueSealsResponse {
+        shipment_id: payload.shipment_id,
+        seals: seals
+            .iter()
+            .map(|seal| IssuedSeal {
+                seal_id: seal.seal_id.clone(),
+                qr_payload: seal.qr_payload(),
+            })
+            .collect(),
+    }))
+}
+
+pub async fn get_shipment_seals(
+    State(state): State<AppState>,
+    Path(shipment_id): Path<String>,
+) -> ApiResult<ShipmentSeals> {
+    state
+        .seals
+        .shipments
+        .lock()
+        .await
+        .iter()
+        .find(|s| s.shipment_id == shipment_id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("No seals issued for {}", shipment_id)))
+}
+
+#[derive(Debug, Serialize)]
+pub struct SealExceptionsView {
+    pub shipments: Vec<ShipmentSeals>,
+}
+
+/// Shipments with missing or mismatched seal confirmations (admin only)
+pub async fn list_exceptions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<SealExceptionsView> {
+    require_admin(&state, &headers)?;
+    Ok(Json(SealExceptionsView {
+        shipments: state.seals.exceptions().await,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_scans() {
+        let seal = |id: &str, tag: &str| Seal {
+            seal_id: id.to_string(),
+            tag: tag.to_string(),
+        };
+        let issued = [seal("S-1", "aa"), seal("S-2", "bb")];
+        let scans = |s: &[&str]| s.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let ok = scans(&["oilseed-seal:S-2:bb", " oilseed-seal:S-1:aa"]);
+        assert_eq!(check_seals(&issued, Some(&ok)), (false, vec![], vec![]));
+
+        // S-2 reprinted with a different tag
+        let forged = scans(&["oilseed-seal:S-1:aa", "oilseed-seal:S-2:cc"]);
+        assert_eq!(
+            check_seals(&issued, Some(&forged)),
+            (
+                false,
+                vec!["S-2".to_string()],
+                vec!["oilseed-seal:S-2:cc".to_string()]
+            )
+        );
+
+        let (unconfirmed, missing, _) = check_seals(&issued, None);
+        assert!(unconfirmed);
+        assert_eq!(missing.len(), 2);
+    }
+}
@@ -0,0 +1,452 @@
+//! Per-batch share links for external labs and auditors
+//!
+//! An admin issues a share token for one batch (`POST /api/share`) with the
+//! scopes the recipient needs and an expiry. The recipient reads that batch's
+//! documents and events through `/api/shared/*`, passing the token as
+//! `?token=` or a bearer token, without an account and without access to any
+//! other batch.
+//!
+//! Like delegations, tokens are returned once and only their SHA-256 is kept
+//! in `data/shares.json`, along with the recipient and access statistics.
+
+use crate::admin::require_admin;
+use crate::batch_ledger;
+use crate::chain::hash_string;
+use crate::error::{format_hash, ApiError, ApiResult};
+use crate::indexer::{self, EventFilter, IndexedEvent};
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::Mutex;
+
+const SHARES_PATH: &str = "data/shares.json";
+const TOKEN_PREFIX: &str = "shr_";
+/// Longest a share link can stay valid (one audit cycle)
+const MAX_VALIDITY_DAYS: i64 = 90;
+
+// ======================== SHARES ========================
+
+/// Data a share token gives access to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareScope {
+    /// JSON records in the batch folder (`GET /api/shared/documents`)
+    Documents,
+    /// Indexed contract events of the batch (`GET /api/shared/events`)
+    Events,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareStatus {
+    Active,
+    Expired,
+    Revoked,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Share {
+    pub id: u64,
+    pub batch_id: String,
+    /// Lab or auditor the link was issued to
+    pub recipient: String,
+    pub scopes: Vec<ShareScope>,
+    pub issued_at: String,
+    pub expires_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<String>,
+    #[serde(default)]
+    pub access_count: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_accessed_at: Option<String>,
+    /// Hex SHA-256 of the share token
+    token_hash: String,
+}
+
+impl Share {
+    pub fn status(&self, now: DateTime<Utc>) -> ShareStatus {
+        if self.revoked_at.is_some() {
+            return ShareStatus::Revoked;
+        }
+        match DateTime::parse_from_rfc3339(&self.expires_at) {
+            Ok(expires_at) if now < expires_at => ShareStatus::Active,
+            _ => ShareStatus::Expired,
+        }
+    }
+
+    /// Check that the share may read `scope` at `now`
+    pub fn authorize(&self, scope: ShareScope, now: DateTime<Utc>) -> Result<(), ApiError> {
+        match self.status(now) {
+            ShareStatus::Active => {}
+            ShareStatus::Expired => {
+                return Err(ApiError::unauthorized(format!(
+                    "Share link expired at {}",
+                    self.expires_at
+                )))
+            }
+            ShareStatus::Revoked => {
+                return Err(ApiError::unauthorized("Share link has been revoked"))
+            }
+        }
+        if !self.scopes.contains(&scope) {
+            return Err(ApiError::forbidden(
+                "Share link does not grant access to this data",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+// ======================== STORE ========================
+
+pub struct ShareStore {
+    shares: Mutex<Vec<Share>>,
+}
+
+impl ShareStore {
+    pub fn load() -> Result<Self> {
+        let shares = match std::fs::read_to_string(SHARES_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid shares file {}", SHARES_PATH))?,
+            Err(_) => Vec::new(),
+        };
+
+        Ok(Self {
+            shares: Mutex::new(shares),
+        })
+    }
+
+    fn save(shares: &[Share]) -> Result<()> {
+        std::fs::write(SHARES_PATH, serde_json::to_string_pretty(shares)?)
+            .with_context(|| format!("Failed to write {}", SHARES_PATH))
+    }
+
+    /// Authorize `token` for `scope` and record the access
+    async fn access(&self, token: &str, scope: ShareScope) -> Result<Share, ApiError> {
+        let token_hash = hash_token(token);
+        let mut shares = self.shares.lock().await;
+        let share = shares
+            .iter_mut()
+            .find(|s| token.starts_with(TOKEN_PREFIX) && s.token_hash == token_hash)
+            .ok_or_else(|| {
+                tracing::warn!(?scope, "Rejected request with unknown share token");
+                ApiError::unauthorized("Invalid share token")
+            })?;
+
+        let now = Utc::now();
+        share.authorize(scope, now)?;
+        share.access_count += 1;
+        share.last_accessed_at = Some(now.to_rfc3339());
+        let share = share.clone();
+        Self::save(&shares)?;
+
+        tracing::info!(
+            share_id = share.id,
+            batch_id = %share.batch_id,
+            recipient = %share.recipient,
+            ?scope,
+            "Shared batch data accessed"
+        );
+        Ok(share)
+    }
+}
+
+// ======================== SHARED ACCESS ========================
+
+#[derive(Debug, Default, Deserialize)]
+pub struct TokenParams {
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Share token from `?token=` or the bearer header
+fn share_token<'a>(params: &'a TokenParams, headers: &'a HeaderMap) -> Result<&'a str, ApiError> {
+    params
+        .token
+        .as_deref()
+        .or_else(|| {
+            headers
+                .get(axum::http::header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+        })
+        .ok_or_else(|| ApiError::unauthorized("Missing share token"))
+}
+
+#[derive(Debug, Serialize)]
+pub struct SharedDocument {
+    pub name: String,
+    pub content: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SharedDocuments {
+    pub batch_id: String,
+    pub expires_at: String,
+    pub documents: Vec<SharedDocument>,
+}
+
+pub async fn shared_documents(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<TokenParams>,
+) -> ApiResult<SharedDocuments> {
+    let token = share_token(&params, &headers)?;
+    let share = state.shares.access(token, ShareScope::Documents).await?;
+
+    let documents = batch_ledger::batch_records(&share.batch_id)?
+        .into_iter()
+        .map(|(name, content)| SharedDocument { name, content })
+        .collect();
+
+    Ok(Json(SharedDocuments {
+        batch_id: share.batch_id,
+        expires_at: share.expires_at,
+        documents,
+    }))
+}
+
+pub async fn shared_events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<TokenParams>,
+    Query(page): Query<PageParams>,
+) -> ApiResult<Page<IndexedEvent>> {
+    let token = share_token(&params, &headers)?;
+    let share = state.shares.access(token, ShareScope::Events).await?;
+
+    let filter = EventFilter::Batch(format_hash(hash_string(&share.batch_id)));
+    // Cursors are bound to the share, so they cannot be replayed with another link
+    let scope = format!("shared/events/{}", share.id);
+    indexer::query_page(&state, &scope, filter, &page).await
+}
+
+// ======================== ADMIN HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct ShareSummary {
+    pub id: u64,
+    pub batch_id: String,
+    pub recipient: String,
+    pub scopes: Vec<ShareScope>,
+    pub issued_at: String,
+    pub expires_at: String,
+    pub revoked_at: Option<String>,
+    pub access_count: u64,
+    pub last_accessed_at: Option<String>,
+    pub status: ShareStatus,
+}
+
+impl From<&Share> for ShareSummary {
+    fn from(s: &Share) -> Self {
+        Self {
+            id: s.id,
+            batch_id: s.batch_id.clone(),
+            recipient: s.recipient.clone(),
+            scopes: s.scopes.clone(),
+            issued_at: s.issued_at.clone(),
+            expires_at: s.expires_at.clone(),
+            revoked_at: s.revoked_at.clone(),
+            access_count: s.access_count,
+            last_accessed_at: s.last_accessed_at.clone(),
+            status: s.status(Utc::now()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateShareRequest {
+    pub batch_id: String,
+    pub recipient: String,
+    /// Defaults to documents and events
+    #[serde(default)]
+    pub scopes: Vec<ShareScope>,
+    /// RFC 3339
+    pub expires_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateShareResponse {
+    pub share: ShareSummary,
+    /// Share token for the recipient; shown only once
+    pub token: String,
+    /// Relative links embedding the token, one per scope
+    pub links: Vec<String>,
+}
+
+pub async fn create_share(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateShareRequest>,
+) -> ApiResult<CreateShareResponse> {
+    require_admin(&state, &headers)?;
+
+    if !batch_ledger::is_valid_batch_id(&payload.batch_id) {
+        return Err(ApiError::bad_request("Invalid batch ID"));
+    }
+    if !batch_ledger::batch_path(&payload.batch_id).is_dir() {
+        return Err(ApiError::not_found(format!(
+            "Batch {} not found",
+            payload.batch_id
+        )));
+    }
+    if payload.recipient.trim().is_empty() {
+        return Err(ApiError::bad_request("recipient is required"));
+    }
+    let scopes = if payload.scopes.is_empty() {
+        vec![ShareScope::Documents, ShareScope::Events]
+    } else {
+        payload.scopes
+    };
+    let now = Utc::now();
+    let expires_at = DateTime::parse_from_rfc3339(&payload.expires_at)
+        .map_err(|e| ApiError::bad_request(format!("Invalid expires_at: {}", e)))?
+        .with_timezone(&Utc);
+    if expires_at <= now {
+        return Err(ApiError::bad_request("expires_at must be in the future"));
+    }
+    if expires_at > now + chrono::Duration::days(MAX_VALIDITY_DAYS) {
+        return Err(ApiError::bad_request(format!(
+            "Share links may last at most {} days",
+            MAX_VALIDITY_DAYS
+        )));
+    }
+
+    let token = format!(
+        "{}{}",
+        TOKEN_PREFIX,
+        hex::encode(rand::random::<[u8; 32]>())
+    );
+    let mut shares = state.shares.shares.lock().await;
+    let share = Share {
+        id: shares.last().map(|s| s.id + 1).unwrap_or(1),
+        batch_id: payload.batch_id,
+        recipient: payload.recipient,
+        scopes,
+        issued_at: now.to_rfc3339(),
+        expires_at: expires_at.to_rfc3339(),
+        revoked_at: None,
+        access_count: 0,
+        last_accessed_at: None,
+        token_hash: hash_token(&token),
+    };
+    shares.push(share.clone());
+    ShareStore::save(&shares)?;
+
+    tracing::info!(
+        share_id = share.id,
+        batch_id = %share.batch_id,
+        recipient = %share.recipient,
+        expires_at = %share.expires_at,
+        "Share link issued"
+    );
+
+    let links = share
+        .scopes
+        .iter()
+        .map(|scope| match scope {
+            ShareScope::Documents => format!("/api/shared/documents?token={}", token),
+            ShareScope::Events => format!("/api/shared/events?token={}", token),
+        })
+        .collect();
+
+    Ok(Json(CreateShareResponse {
+        share: ShareSummary::from(&share),
+        token,
+        links,
+    }))
+}
+
+pub async fn list_shares(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<ShareSummary>> {
+    require_admin(&state, &headers)?;
+
+    let shares: Vec<ShareSummary> = state
+        .shares
+        .shares
+        .lock()
+        .await
+        .iter()
+        .map(ShareSummary::from)
+        .collect();
+    Ok(Json(paginate("shares", shares, &params, |s| s.id)?))
+}
+
+pub async fn revoke_share(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<u64>,
+) -> ApiResult<ShareSummary> {
+    require_admin(&state, &headers)?;
+
+    let mut shares = state.shares.shares.lock().await;
+    let share = shares
+        .iter_mut()
+        .find(|s| s.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("Share {} not found", id)))?;
+    if share.revoked_at.is_none() {
+        share.revoked_at = Some(Utc::now().to_rfc3339());
+        tracing::info!(share_id = id, "Share link revoked");
+    }
+    let summary = ShareSummary::from(&*share);
+    ShareStore::save(&shares)?;
+
+    Ok(Json(summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn share(expires_at: &str, scopes: Vec<ShareScope>) -> Share {
+        Share {
+            id: 1,
+            batch_id: "23322".to_string(),
+            recipient: "NABL lab".to_string(),
+            scopes,
+            issued_at: "2025-06-01T00:00:00Z".to_string(),
+            expires_at: expires_at.to_string(),
+            revoked_at: None,
+            access_count: 0,
+            last_accessed_at: None,
+            token_hash: hash_token("shr_test"),
+        }
+    }
+
+    #[test]
+    fn test_share_scopes_expiry_and_revocation() {
+        let now = DateTime::parse_from_rfc3339("2025-07-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let mut documents_only = share("2025-07-15T00:00:00Z", vec![ShareScope::Documents]);
+        assert!(documents_only.authorize(ShareScope::Documents, now).is_ok());
+        assert!(documents_only.authorize(ShareScope::Events, now).is_err());
+
+        documents_only.revoked_at = Some("2025-06-20T00:00:00Z".to_string());
+        assert_eq!(documents_only.status(now), ShareStatus::Revoked);
+        assert!(documents_only
+            .authorize(ShareScope::Documents, now)
+            .is_err());
+
+        let expired = share("2025-06-30T00:00:00Z", vec![ShareScope::Events]);
+        assert_eq!(expired.status(now), ShareStatus::Expired);
+        assert!(expired.authorize(ShareScope::Events, now).is_err());
+    }
+}
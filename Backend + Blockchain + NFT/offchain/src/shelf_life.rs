@@ -0,0 +1,363 @@
+//! Shelf life of packaged products
+//!
+//! Packaging stamps every SKU with an expiry date derived from a shelf-life
+//! rule for the product. Rules are read from `data/shelf_life.json`
+//! (override with SHELF_LIFE_CONFIG_PATH); each names any of `crop`,
+//! `process_type` and `package_type` and the rule naming the most of them
+//! that match wins, earlier rules breaking ties:
+//!
+//! ```json
+//! [ { "crop": "groundnut", "process_type": "expeller", "package_type": "bottle",
+//!     "shelf_life_days": 270, "max_storage_temp_c": 30 },
+//!   { "crop": "groundnut", "shelf_life_days": 180 },
+//!   { "shelf_life_days": 120, "cold_chain_reduction_percent": 40 } ]
+//! ```
+//!
+//! Products without a matching rule keep `expiry_months` of the packaging
+//! request (30-day months), or DEFAULT_SHELF_LIFE_DAYS.
+//!
+//! The shelf life is cut when the source batch was mishandled: by
+//! `cold_chain_reduction_percent` (default 30) when the cold-chain report of
+//! its shipment rates the spoilage risk high, half of it for medium, and by
+//! `storage_reduction_percent` (default 20) when the warehouse reported a
+//! temperature or humidity above the rule's limits. Cuts compound.
+
+use crate::cold_chain::RiskLevel;
+use crate::error::ApiResult;
+use crate::state::AppState;
+use anyhow::{bail, Context, Result};
+use axum::{extract::State, Json};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_SHELF_LIFE_CONFIG_PATH: &str = "data/shelf_life.json";
+/// Shelf life of products without a rule or `expiry_months`
+const DEFAULT_SHELF_LIFE_DAYS: u32 = 180;
+
+fn default_cold_chain_reduction() -> f64 {
+    30.0
+}
+
+fn default_storage_reduction() -> f64 {
+    20.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShelfLifeRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crop: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package_type: Option<String>,
+    pub shelf_life_days: u32,
+    /// Warehouse temperature above which storage counts as a breach
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_storage_temp_c: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_storage_humidity_percent: Option<f64>,
+    /// Cut for a high cold-chain risk; a medium risk takes half
+    #[serde(default = "default_cold_chain_reduction")]
+    pub cold_chain_reduction_percent: f64,
+    #[serde(default = "default_storage_reduction")]
+    pub storage_reduction_percent: f64,
+}
+
+impl ShelfLifeRule {
+    /// Rule of a product without a configured one
+    fn fallback(shelf_life_days: u32) -> Self {
+        Self {
+            crop: None,
+            process_type: None,
+            package_type: None,
+            shelf_life_days,
+            max_storage_temp_c: None,
+            max_storage_humidity_percent: None,
+            cold_chain_reduction_percent: default_cold_chain_reduction(),
+            storage_reduction_percent: default_storage_reduction(),
+        }
+    }
+
+    fn keys(&self) -> [&Option<String>; 3] {
+        [&self.crop, &self.process_type, &self.package_type]
+    }
+
+    /// Number of keys named by the rule, or `None` when one of them differs
+    fn specificity(&self, product: [&str; 3]) -> Option<usize> {
+        let mut named = 0;
+        for (key, value) in self.keys().into_iter().zip(product) {
+            if let Some(key) = key {
+                if !key.trim().eq_ignore_ascii_case(value.trim()) {
+                    return None;
+                }
+                named += 1;
+            }
+        }
+        Some(named)
+    }
+}
+
+fn validate(index: usize, rule: &ShelfLifeRule) -> Result<()> {
+    if rule.shelf_life_days == 0 {
+        bail!(
+            "Shelf-life rule {}: shelf_life_days must be positive",
+            index
+        );
+    }
+    for percent in [
+        rule.cold_chain_reduction_percent,
+        rule.storage_reduction_percent,
+    ] {
+        if !(0.0..=100.0).contains(&percent) {
+            bail!(
+                "Shelf-life rule {}: reductions must be 0-100 percent",
+                index
+            );
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreachKind {
+    ColdChain,
+    Storage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Breach {
+    pub kind: BreachKind,
+    pub detail: String,
+    pub reduction_percent: f64,
+}
+
+/// What is known of the handling of the source batch
+#[derive(Debug, Clone, Default)]
+pub struct SourceConditions {
+    /// Spoilage risk of the shipment's cold-chain report
+    pub cold_chain_risk: Option<RiskLevel>,
+    pub storage_temp_c: Option<f64>,
+    pub storage_humidity_percent: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShelfLife {
+    /// Rule applied; `None` for the `expiry_months` or default fallback
+    pub rule: Option<ShelfLifeRule>,
+    pub base_days: u32,
+    pub days: u32,
+    pub breaches: Vec<Breach>,
+}
+
+impl ShelfLife {
+    pub fn expiry(&self, packaged_at: DateTime<Utc>) -> DateTime<Utc> {
+        packaged_at + Duration::days(self.days as i64)
+    }
+}
+
+fn breaches(rule: &ShelfLifeRule, conditions: &SourceConditions) -> Vec<Breach> {
+    let mut breaches = Vec::new();
+    let cold_chain_cut = match conditions.cold_chain_risk {
+        Some(RiskLevel::High) => rule.cold_chain_reduction_percent,
+        Some(RiskLevel::Medium) => rule.cold_chain_reduction_percent / 2.0,
+        _ => 0.0,
+    };
+    if cold_chain_cut > 0.0 {
+        breaches.push(Breach {
+            kind: BreachKind::ColdChain,
+            detail: format!(
+                "{:?} spoilage risk in transit",
+                conditions.cold_chain_risk.unwrap_or(RiskLevel::Low)
+            ),
+            reduction_percent: cold_chain_cut,
+        });
+    }
+
+    let mut storage = Vec::new();
+    if let (Some(value), Some(limit)) = (conditions.storage_temp_c, rule.max_storage_temp_c) {
+        if value > limit {
+            storage.push(format!("stored at {} °C, above {} °C", value, limit));
+        }
+    }
+    if let (Some(value), Some(limit)) = (
+        conditions.storage_humidity_percent,
+        rule.max_storage_humidity_percent,
+    ) {
+        if value > limit {
+            storage.push(format!("stored at {}% humidity, above {}%", value, limit));
+        }
+    }
+    if !storage.is_empty() && rule.storage_reduction_percent > 0.0 {
+        breaches.push(Breach {
+            kind: BreachKind::Storage,
+            detail: storage.join("; "),
+            reduction_percent: rule.storage_reduction_percent,
+        });
+    }
+    breaches
+}
+
+pub struct ShelfLifeRules {
+    rules: Vec<ShelfLifeRule>,
+}
+
+impl ShelfLifeRules {
+    pub fn load() -> Result<Self> {
+        let path = std::env::var("SHELF_LIFE_CONFIG_PATH")
+            .unwrap_or_else(|_| DEFAULT_SHELF_LIFE_CONFIG_PATH.to_string());
+
+        let rules: Vec<ShelfLifeRule> = if std::path::Path::new(&path).exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read shelf-life rules {}", path))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid shelf-life rules {}", path))?
+        } else {
+            Vec::new()
+        };
+        Self::from_rules(rules)
+    }
+
+    fn from_rules(rules: Vec<ShelfLifeRule>) -> Result<Self> {
+        for (index, rule) in rules.iter().enumerate() {
+            validate(index, rule)?;
+        }
+        Ok(Self { rules })
+    }
+
+    /// Most specific rule matching the product
+    pub fn rule_for(
+        &self,
+        crop: &str,
+        process_type: &str,
+        package_type: &str,
+    ) -> Option<&ShelfLifeRule> {
+        let product = [crop, process_type, package_type];
+        let mut best: Option<(usize, &ShelfLifeRule)> = None;
+        for rule in &self.rules {
+            if let Some(specificity) = rule.specificity(product) {
+                if best.is_none_or(|(b, _)| specificity > b) {
+                    best = Some((specificity, rule));
+                }
+            }
+        }
+        best.map(|(_, rule)| rule)
+    }
+
+    /// Shelf life of a product packaged from a source batch handled under
+    /// `conditions`; `fallback_days` applies when no rule matches
+    pub fn compute(
+        &self,
+        product: [&str; 3],
+        fallback_days: Option<u32>,
+        conditions: &SourceConditions,
+    ) -> ShelfLife {
+        let [crop, process_type, package_type] = product;
+        let matched = self.rule_for(crop, process_type, package_type).cloned();
+        let rule = matched.clone().unwrap_or_else(|| {
+            ShelfLifeRule::fallback(fallback_days.unwrap_or(DEFAULT_SHELF_LIFE_DAYS))
+        });
+
+        let breaches = breaches(&rule, conditions);
+        let remaining = breaches
+            .iter()
+            .fold(1.0, |share, b| share * (1.0 - b.reduction_percent / 100.0));
+        ShelfLife {
+            rule: matched,
+            base_days: rule.shelf_life_days,
+            days: (rule.shelf_life_days as f64 * remaining).floor() as u32,
+            breaches,
+        }
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct ShelfLifeRulesView {
+    pub rules: Vec<ShelfLifeRule>,
+    pub default_days: u32,
+}
+
+/// Configured shelf-life rules, in matching order
+pub async fn list_rules(State(state): State<AppState>) -> ApiResult<ShelfLifeRulesView> {
+    Ok(Json(ShelfLifeRulesView {
+        rules: state.shelf_life.rules.clone(),
+        default_days: DEFAULT_SHELF_LIFE_DAYS,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> ShelfLifeRules {
+        let rules = serde_json::from_value(serde_json::json!([
+            { "crop": "Groundnut", "process_type": "expeller", "package_type": "bottle",
+              "shelf_life_days": 270, "max_storage_temp_c": 30 },
+            { "crop": "groundnut", "shelf_life_days": 180 },
+            { "package_type": "bottle", "shelf_life_days": 200 },
+            { "shelf_life_days": 120, "cold_chain_reduction_percent": 40 }
+        ]))
+        .unwrap();
+        ShelfLifeRules::from_rules(rules).unwrap()
+    }
+
+    #[test]
+    fn test_most_specific_rule_wins() {
+        let rules = rules();
+        let days = |product| rules.compute(product, Some(360), &SourceConditions::default());
+
+        assert_eq!(days(["groundnut", "expeller", "bottle"]).days, 270);
+        // Crop and package rules name one key each; the earlier one wins
+        assert_eq!(days(["groundnut", "solvent", "bottle"]).days, 180);
+        assert_eq!(days(["mustard", "expeller", "bottle"]).days, 200);
+        assert_eq!(days(["mustard", "expeller", "pouch"]).days, 120);
+
+        let unruled = ShelfLifeRules::from_rules(Vec::new()).unwrap();
+        let fallback = unruled.compute(["soy", "", ""], Some(360), &SourceConditions::default());
+        assert!(fallback.rule.is_none());
+        assert_eq!(fallback.days, 360);
+        assert_eq!(
+            unruled
+                .compute(["soy", "", ""], None, &SourceConditions::default())
+                .days,
+            DEFAULT_SHELF_LIFE_DAYS
+        );
+        assert!(ShelfLifeRules::from_rules(vec![ShelfLifeRule::fallback(0)]).is_err());
+    }
+
+    #[test]
+    fn test_breaches_shorten_shelf_life() {
+        let rules = rules();
+        let hot = SourceConditions {
+            cold_chain_risk: Some(RiskLevel::High),
+            storage_temp_c: Some(34.0),
+            storage_humidity_percent: Some(60.0),
+        };
+
+        // 270 days less 30% for transit and 20% for storage
+        let life = rules.compute(["groundnut", "expeller", "bottle"], None, &hot);
+        assert_eq!(life.base_days, 270);
+        assert_eq!(life.days, 151);
+        let kinds: Vec<BreachKind> = life.breaches.iter().map(|b| b.kind).collect();
+        assert_eq!(kinds, [BreachKind::ColdChain, BreachKind::Storage]);
+
+        // No storage limits; a medium risk takes half of the 40% cut
+        let medium = SourceConditions {
+            cold_chain_risk: Some(RiskLevel::Medium),
+            ..hot
+        };
+        let life = rules.compute(["mustard", "expeller", "pouch"], None, &medium);
+        assert_eq!(life.days, 96);
+        assert_eq!(life.breaches.len(), 1);
+
+        let packaged = DateTime::parse_from_rfc3339("2025-03-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            life.expiry(packaged).to_rfc3339(),
+            "2025-06-05T00:00:00+00:00"
+        );
+    }
+}
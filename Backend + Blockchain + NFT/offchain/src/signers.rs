@@ -0,0 +1,432 @@
+//! Signing keys per on-chain role
+//!
+//! The contract checks a role for every write (FPO for purchases, WAREHOUSE
+//! for storage updates, AI_ORACLE for scores, ...). Each role can have its
+//! own key in PRIVATE_KEY_<ROLE> (e.g. PRIVATE_KEY_AI_ORACLE); roles without
+//! one, and writes no role guards, are signed with PRIVATE_KEY.
+//!
+//! Keys can also be kept in a JSON file named by SIGNER_KEYS_PATH, which
+//! takes precedence over the environment:
+//!
+//! ```json
+//! { "default": "0x...", "FPO": "0x...", "AI_ORACLE": "0x..." }
+//! ```
+//!
+//! Any key may instead name an account of the remote signer, as
+//! `remote:0x<address>` (see [`crate::remote_signer`]), so no private key
+//! has to be stored with the backend.
+//!
+//! `POST /api/admin/signers/reload` re-reads the file and swaps the keys
+//! without a restart. Transactions already being sent keep the key they
+//! started with, so replacements stay at the old key's nonce. The new
+//! address needs its role granted on-chain before it can write.
+
+use crate::admin::require_admin;
+use crate::chain::roles;
+use crate::error::{ApiError, ApiResult};
+use crate::networks::EnvVars;
+use crate::remote_signer::RemoteSigner;
+use crate::state::AppState;
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use anyhow::{bail, Context, Result};
+use axum::{extract::State, http::HeaderMap, Json};
+use chrono::Utc;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+
+/// Key name of PRIVATE_KEY in the keys file
+const DEFAULT_KEY: &str = "default";
+/// Prefix of keys held by the remote signer
+const REMOTE_PREFIX: &str = "remote:";
+
+/// (role bit, private key)
+type RoleKeys = Vec<(u64, String)>;
+
+/// Role the contract requires for the write `label`; `None` when any
+/// account may send it
+pub fn role_for(label: &str) -> Option<u64> {
+    match label {
+        "grantRole" | "revokeRole" => Some(roles::ADMIN),
+        "registerFarmer"
+        | "batchRegisterFarmers"
+        | "updateFarmerMetadata"
+        | "fpoPurchase"
+        | "recordOwnershipTransfer" => Some(roles::FPO),
+        "updateWarehouseState" | "batchUpdateWarehouse" => Some(roles::WAREHOUSE),
+        "recordLogistics" | "batchRecordLogistics" => Some(roles::LOGISTICS),
+        "processBatch" => Some(roles::PROCESSOR),
+        "createSKU" => Some(roles::PACKAGER),
+        "commitAIScore" | "revealAIScore" => Some(roles::AI_ORACLE),
+        _ => None,
+    }
+}
+
+/// Roles the backend may hold a dedicated key for
+fn keyed_roles() -> impl Iterator<Item = &'static (&'static str, u64)> {
+    roles::ALL.iter().filter(|(_, bit)| *bit != roles::FARMER)
+}
+
+/// PRIVATE_KEY_<ROLE> variables that are set, as (role bit, key)
+pub fn role_keys_from_env(vars: &EnvVars) -> RoleKeys {
+    keyed_roles()
+        .filter_map(|(name, bit)| {
+            vars.non_empty(&format!("PRIVATE_KEY_{}", name))
+                .map(|key| (*bit, key))
+        })
+        .collect()
+}
+
+/// Key that signs a transaction
+#[derive(Clone)]
+pub enum TxSigner {
+    Local(PrivateKeySigner),
+    /// Account of the remote signer
+    Remote {
+        address: Address,
+        signer: Arc<RemoteSigner>,
+    },
+}
+
+impl TxSigner {
+    pub fn address(&self) -> Address {
+        match self {
+            TxSigner::Local(key) => key.address(),
+            TxSigner::Remote { address, .. } => *address,
+        }
+    }
+}
+
+/// Check the form of a key without building the signer; used by
+/// [`crate::chain::ChainConfig::validate_format`]
+pub fn check_key(name: &str, key: &str) -> Result<(), String> {
+    match key.trim().strip_prefix(REMOTE_PREFIX) {
+        Some(address) if address.trim().parse::<Address>().is_err() => Err(format!(
+            "{} must be remote:0x followed by the 40 hex characters of the signer's address",
+            name
+        )),
+        Some(_) => Ok(()),
+        None if key.trim().parse::<PrivateKeySigner>().is_err() => Err(format!(
+            "{} is not a valid secp256k1 key (expected 64 hex characters, optional 0x prefix, or remote:0x<address>)",
+            name
+        )),
+        None => Ok(()),
+    }
+}
+
+fn parse_key(name: &str, key: &str, remote: Option<&Arc<RemoteSigner>>) -> Result<TxSigner> {
+    check_key(name, key).map_err(anyhow::Error::msg)?;
+    let key = key.trim();
+    match key.strip_prefix(REMOTE_PREFIX) {
+        Some(address) => Ok(TxSigner::Remote {
+            address: address.trim().parse()?,
+            signer: remote
+                .with_context(|| {
+                    format!(
+                        "{} names a remote key but REMOTE_SIGNER_URL is not set",
+                        name
+                    )
+                })?
+                .clone(),
+        }),
+        None => Ok(TxSigner::Local(key.parse()?)),
+    }
+}
+
+#[derive(Clone)]
+struct SignerKeys {
+    default: TxSigner,
+    by_role: HashMap<u64, TxSigner>,
+}
+
+impl SignerKeys {
+    fn parse(
+        default: &str,
+        role_keys: &[(u64, String)],
+        remote: Option<&Arc<RemoteSigner>>,
+    ) -> Result<Self> {
+        let mut by_role = HashMap::new();
+        for (bit, key) in role_keys {
+            let name = format!("PRIVATE_KEY_{}", roles::names(*bit).join("|"));
+            by_role.insert(*bit, parse_key(&name, key, remote)?);
+        }
+        Ok(Self {
+            default: parse_key("PRIVATE_KEY", default, remote)?,
+            by_role,
+        })
+    }
+
+    fn for_role(&self, role: Option<u64>) -> &TxSigner {
+        role.and_then(|bit| self.by_role.get(&bit))
+            .unwrap_or(&self.default)
+    }
+
+    fn view(&self) -> Vec<SignerView> {
+        let mut by_address: BTreeMap<Address, SignerView> = BTreeMap::new();
+        let mut add = |signer: &TxSigner, role: &'static str| {
+            by_address
+                .entry(signer.address())
+                .or_insert_with(|| SignerView {
+                    address: signer.address(),
+                    roles: Vec::new(),
+                    remote: matches!(signer, TxSigner::Remote { .. }),
+                })
+                .roles
+                .push(role)
+        };
+        add(&self.default, DEFAULT_KEY);
+        for (name, bit) in keyed_roles() {
+            if let Some(signer) = self.by_role.get(bit) {
+                add(signer, name);
+            }
+        }
+        by_address.into_values().collect()
+    }
+}
+
+/// (default key, role keys) from the keys file
+fn read_keys_file(path: &str) -> Result<(Option<String>, RoleKeys)> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read signer keys {}", path))?;
+    let entries: HashMap<String, String> =
+        serde_json::from_str(&content).with_context(|| format!("Invalid signer keys {}", path))?;
+
+    let mut default = None;
+    let mut role_keys = Vec::new();
+    for (name, key) in entries {
+        if name.eq_ignore_ascii_case(DEFAULT_KEY) {
+            default = Some(key);
+            continue;
+        }
+        let bit = roles::from_names(&[&name]).with_context(|| format!("Signer keys {}", path))?;
+        if bit == roles::FARMER {
+            bail!("Signer keys {}: the backend does not sign as FARMER", path);
+        }
+        role_keys.push((bit, key));
+    }
+    Ok((default, role_keys))
+}
+
+/// Environment keys with the keys file, if any, laid over them
+fn resolve(
+    env_default: &str,
+    env_role_keys: &[(u64, String)],
+    keys_path: Option<&str>,
+    remote: Option<&Arc<RemoteSigner>>,
+) -> Result<SignerKeys> {
+    let Some(path) = keys_path else {
+        return SignerKeys::parse(env_default, env_role_keys, remote);
+    };
+    let (default, file_keys) = read_keys_file(path)?;
+    let mut role_keys: RoleKeys = env_role_keys
+        .iter()
+        .filter(|(bit, _)| !file_keys.iter().any(|(b, _)| b == bit))
+        .cloned()
+        .collect();
+    role_keys.extend(file_keys);
+    SignerKeys::parse(
+        default.as_deref().unwrap_or(env_default),
+        &role_keys,
+        remote,
+    )
+}
+
+/// Remote addresses of `keys` the remote signer does not hold
+async fn missing_remote_keys(keys: &SignerKeys, remote: &RemoteSigner) -> Result<Vec<Address>> {
+    let wanted: Vec<Address> = keys
+        .view()
+        .into_iter()
+        .filter(|s| s.remote)
+        .map(|s| s.address)
+        .collect();
+    if wanted.is_empty() {
+        return Ok(Vec::new());
+    }
+    let accounts = remote.accounts().await?;
+    Ok(wanted
+        .into_iter()
+        .filter(|address| !accounts.contains(address))
+        .collect())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SignerView {
+    pub address: Address,
+    /// Roles signed with this key; "default" for PRIVATE_KEY
+    pub roles: Vec<&'static str>,
+    /// Signed by the remote signer
+    pub remote: bool,
+}
+
+pub struct SignerSet {
+    env_default: String,
+    env_role_keys: RoleKeys,
+    keys_path: Option<String>,
+    remote: Option<Arc<RemoteSigner>>,
+    keys: RwLock<SignerKeys>,
+}
+
+impl SignerSet {
+    /// Keys from the environment, overridden by SIGNER_KEYS_PATH when set
+    pub fn load(default: &str, role_keys: &[(u64, String)]) -> Result<Self> {
+        let keys_path = std::env::var("SIGNER_KEYS_PATH")
+            .ok()
+            .filter(|v| !v.is_empty());
+        let remote = RemoteSigner::from_env()?.map(Arc::new);
+        let keys = resolve(default, role_keys, keys_path.as_deref(), remote.as_ref())?;
+        Ok(Self {
+            env_default: default.to_string(),
+            env_role_keys: role_keys.to_vec(),
+            keys_path,
+            remote,
+            keys: RwLock::new(keys),
+        })
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, SignerKeys> {
+        self.keys.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Key that signs the write `label`
+    pub fn for_label(&self, label: &str) -> TxSigner {
+        self.read().for_role(role_for(label)).clone()
+    }
+
+    /// Key that signs writes guarded by `role`
+    pub fn for_role(&self, role: u64) -> TxSigner {
+        self.read().for_role(Some(role)).clone()
+    }
+
+    /// PRIVATE_KEY, for writes no role guards
+    pub fn default_signer(&self) -> TxSigner {
+        self.read().default.clone()
+    }
+
+    /// Distinct signer addresses with the roles they sign for
+    pub fn signers(&self) -> Vec<SignerView> {
+        self.read().view()
+    }
+
+    /// Problems with the remote keys in use, for startup validation
+    pub async fn remote_problems(&self) -> Vec<String> {
+        let Some(remote) = &self.remote else {
+            return Vec::new();
+        };
+        let keys = self.read().clone();
+        match missing_remote_keys(&keys, remote).await {
+            Ok(missing) => missing
+                .into_iter()
+                .map(|address| {
+                    format!(
+                        "Remote signer at REMOTE_SIGNER_URL holds no key for {:?}",
+                        address
+                    )
+                })
+                .collect(),
+            Err(e) => vec![format!("Could not list remote signer accounts: {:#}", e)],
+        }
+    }
+
+    /// Re-read the keys file and swap in its keys
+    pub async fn reload(&self) -> Result<Vec<SignerView>> {
+        if self.keys_path.is_none() {
+            bail!(
+                "SIGNER_KEYS_PATH is not set; keys from the environment need a restart to change"
+            );
+        }
+        let keys = resolve(
+            &self.env_default,
+            &self.env_role_keys,
+            self.keys_path.as_deref(),
+            self.remote.as_ref(),
+        )?;
+        // A rotated-in remote key has to exist before writes are routed to it
+        if let Some(remote) = &self.remote {
+            let missing = missing_remote_keys(&keys, remote).await?;
+            if !missing.is_empty() {
+                bail!("Remote signer holds no key for {:?}", missing);
+            }
+        }
+        let view = keys.view();
+        *self.keys.write().unwrap_or_else(|e| e.into_inner()) = keys;
+        Ok(view)
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct SignersView {
+    pub signers: Vec<SignerView>,
+}
+
+/// Signer addresses and the roles they sign for (admin only)
+pub async fn list_signers(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<SignersView> {
+    require_admin(&state, &headers)?;
+    Ok(Json(SignersView {
+        signers: state.chain().signers().signers(),
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReloadSignersResponse {
+    pub signers: Vec<SignerView>,
+    pub reloaded_at: String,
+}
+
+/// Rotate keys by re-reading SIGNER_KEYS_PATH (admin only)
+pub async fn reload_signers(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<ReloadSignersResponse> {
+    require_admin(&state, &headers)?;
+
+    let signers = state
+        .chain()
+        .signers()
+        .reload()
+        .await
+        .map_err(|e| ApiError::bad_request(format!("{:#}", e)))?;
+    for signer in &signers {
+        tracing::info!(address = ?signer.address, roles = ?signer.roles, "Signer key loaded");
+    }
+    Ok(Json(ReloadSignersResponse {
+        signers,
+        reloaded_at: Utc::now().to_rfc3339(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_1: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const KEY_2: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+    #[test]
+    fn test_writes_use_their_role_key() {
+        let keys =
+            SignerKeys::parse(KEY_1, &[(roles::AI_ORACLE, KEY_2.to_string())], None).unwrap();
+        let default = keys.default.address();
+        let oracle = keys.by_role[&roles::AI_ORACLE].address();
+        assert_ne!(default, oracle);
+
+        assert_eq!(keys.for_role(role_for("commitAIScore")).address(), oracle);
+        // No dedicated key: falls back to PRIVATE_KEY
+        assert_eq!(keys.for_role(role_for("fpoPurchase")).address(), default);
+        assert_eq!(keys.for_role(role_for("reportFraud")).address(), default);
+
+        let view = keys.view();
+        assert_eq!(view.len(), 2);
+        assert!(view.iter().any(|s| s.roles == ["AI_ORACLE"]));
+
+        assert!(SignerKeys::parse(KEY_1, &[(roles::FPO, "0x12".to_string())], None).is_err());
+        // Remote keys need REMOTE_SIGNER_URL
+        let remote = format!("remote:{:?}", oracle);
+        assert!(check_key("PRIVATE_KEY", &remote).is_ok());
+        assert!(SignerKeys::parse(&remote, &[], None).is_err());
+    }
+}
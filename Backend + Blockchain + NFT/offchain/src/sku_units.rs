@@ -0,0 +1,297 @@
+//! Merkle trees over the retail units of a SKU
+//!
+//! `createSKU` records a Merkle root over the SKU's unit IDs (see
+//! [`crate::merkle`]). The unit IDs are kept in `data/sku-units/<sku_id>.json`
+//! so a proof can be produced for any single unit later:
+//!
+//! - `GET /api/packaging/unit-proof?sku_id=&unit_id=` - sibling path of a unit
+//! - `POST /api/packaging/verify-unit` - check a unit (and optionally a proof
+//!   held by the verifier) against the on-chain `merkleRoot`
+//!
+//! SKUs packaged before unit trees were stored carry a flat keccak over the
+//! concatenated unit hashes; no proofs can be produced for them.
+
+use crate::batch_ledger;
+use crate::chain::hash_string;
+use crate::degradation;
+use crate::error::{format_hash, ApiError, ApiResult};
+use crate::hash_schemes::HashScheme;
+use crate::merkle::{
+    self, merkle_proof, merkle_root, verify_merkle_proof, MERKLE_HASH_SCHEME, TREE_SCHEME,
+};
+use crate::state::AppState;
+use alloy::primitives::FixedBytes;
+use anyhow::{bail, Context, Result};
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+const UNITS_DIR: &str = "data/sku-units";
+/// Units accepted per SKU
+const MAX_UNITS_PER_SKU: usize = 100_000;
+
+/// Leaf of a unit in its SKU's tree
+pub fn unit_leaf(unit_id: &str) -> FixedBytes<32> {
+    merkle::leaf(unit_id.as_bytes())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnitTree {
+    pub sku_id: String,
+    pub parent_batch_id: String,
+    pub tree_scheme: String,
+    pub hash_scheme: HashScheme,
+    pub root: FixedBytes<32>,
+    /// Leaf order of the tree
+    pub unit_ids: Vec<String>,
+    pub created_at: String,
+}
+
+impl UnitTree {
+    /// Build the tree of a SKU, rejecting empty, blank or duplicate unit IDs
+    pub fn build(sku_id: &str, parent_batch_id: &str, unit_ids: Vec<String>) -> Result<Self> {
+        if !batch_ledger::is_valid_batch_id(sku_id) {
+            bail!("Invalid SKU ID {}", sku_id);
+        }
+        if unit_ids.is_empty() {
+            bail!("unit_ids must not be empty");
+        }
+        if unit_ids.len() > MAX_UNITS_PER_SKU {
+            bail!(
+                "At most {} units per SKU, got {}",
+                MAX_UNITS_PER_SKU,
+                unit_ids.len()
+            );
+        }
+        let mut seen = std::collections::HashSet::with_capacity(unit_ids.len());
+        for unit_id in &unit_ids {
+            if unit_id.trim().is_empty() {
+                bail!("unit_ids must not be blank");
+            }
+            if !seen.insert(unit_id.as_str()) {
+                bail!("Duplicate unit ID {}", unit_id);
+            }
+        }
+
+        let mut tree = Self {
+            sku_id: sku_id.to_string(),
+            parent_batch_id: parent_batch_id.to_string(),
+            tree_scheme: TREE_SCHEME.to_string(),
+            hash_scheme: MERKLE_HASH_SCHEME,
+            root: FixedBytes::ZERO,
+            unit_ids,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+        tree.root = merkle_root(&tree.leaves());
+        Ok(tree)
+    }
+
+    pub fn leaves(&self) -> Vec<FixedBytes<32>> {
+        self.unit_ids.iter().map(|id| unit_leaf(id)).collect()
+    }
+
+    /// Sibling path of `unit_id`, if it is part of the SKU
+    pub fn proof(&self, unit_id: &str) -> Option<Vec<FixedBytes<32>>> {
+        let index = self.unit_ids.iter().position(|id| id == unit_id)?;
+        Some(merkle_proof(&self.leaves(), index))
+    }
+
+    fn path(sku_id: &str) -> std::path::PathBuf {
+        std::path::Path::new(UNITS_DIR).join(format!("{}.json", sku_id))
+    }
+
+    pub fn load(sku_id: &str) -> Result<Option<Self>> {
+        if !batch_ledger::is_valid_batch_id(sku_id) {
+            return Ok(None);
+        }
+        let path = Self::path(sku_id);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .with_context(|| format!("Invalid unit tree {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        std::fs::create_dir_all(UNITS_DIR)
+            .with_context(|| format!("Failed to create {}", UNITS_DIR))?;
+        let path = Self::path(&self.sku_id);
+        std::fs::write(&path, serde_json::to_string(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct UnitProofParams {
+    pub sku_id: String,
+    pub unit_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnitProofResponse {
+    pub sku_id: String,
+    pub unit_id: String,
+    pub leaf: String,
+    /// Sibling hashes from the leaf up; pairs are sorted before hashing
+    pub proof: Vec<String>,
+    pub merkle_root: String,
+    /// Root recorded by `createSKU`; equals `merkle_root` for SKUs with stored trees
+    pub onchain_merkle_root: String,
+    pub anchored: bool,
+    pub tree_scheme: String,
+    pub hash_scheme: HashScheme,
+}
+
+pub async fn unit_proof(
+    State(state): State<AppState>,
+    Query(params): Query<UnitProofParams>,
+) -> ApiResult<UnitProofResponse> {
+    let tree = UnitTree::load(&params.sku_id)?.ok_or_else(|| {
+        ApiError::not_found(format!(
+            "No unit tree stored for SKU {} (packaged before unit proofs?)",
+            params.sku_id
+        ))
+    })?;
+    let proof = tree.proof(&params.unit_id).ok_or_else(|| {
+        ApiError::not_found(format!(
+            "Unit {} is not part of SKU {}",
+            params.unit_id, params.sku_id
+        ))
+    })?;
+
+    let (_, onchain_root, _) = state
+        .chain()
+        .verify_package_origin(hash_string(&tree.sku_id))
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+
+    Ok(Json(UnitProofResponse {
+        leaf: format_hash(unit_leaf(&params.unit_id)),
+        proof: proof.iter().map(format_hash).collect(),
+        merkle_root: format_hash(tree.root),
+        onchain_merkle_root: format_hash(onchain_root),
+        anchored: onchain_root == tree.root,
+        tree_scheme: tree.tree_scheme,
+        hash_scheme: tree.hash_scheme,
+        sku_id: tree.sku_id,
+        unit_id: params.unit_id,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyUnitRequest {
+    pub sku_id: String,
+    pub unit_id: String,
+    /// Proof held by the verifier (e.g. printed on the unit); the stored tree
+    /// is used when omitted
+    #[serde(default)]
+    pub proof: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyUnitResponse {
+    pub sku_id: String,
+    pub unit_id: String,
+    /// The unit belongs to a SKU recorded on chain
+    pub valid: bool,
+    pub packaged_at: u64,
+    pub parent_batch_hash: String,
+    pub merkle_root: String,
+    pub leaf: String,
+    /// "provided" or "stored"
+    pub proof_source: &'static str,
+}
+
+pub async fn verify_unit(
+    State(state): State<AppState>,
+    Json(payload): Json<VerifyUnitRequest>,
+) -> ApiResult<VerifyUnitResponse> {
+    let (proof, proof_source) = match &payload.proof {
+        Some(proof) => {
+            let proof = proof
+                .iter()
+                .map(|h| h.parse::<FixedBytes<32>>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| ApiError::invalid_hash("proof", e))?;
+            (Some(proof), "provided")
+        }
+        None => {
+            let tree = UnitTree::load(&payload.sku_id)?.ok_or_else(|| {
+                ApiError::not_found(format!(
+                    "No unit tree stored for SKU {}; provide a proof",
+                    payload.sku_id
+                ))
+            })?;
+            (tree.proof(&payload.unit_id), "stored")
+        }
+    };
+
+    let (parent_batch_hash, onchain_root, packaged_at) =
+        degradation::package_origin(&state, &state.chain(), hash_string(&payload.sku_id))
+            .await
+            .map_err(ApiError::blockchain_failed)?;
+
+    let leaf = unit_leaf(&payload.unit_id);
+    let valid = packaged_at > 0
+        && proof.is_some_and(|proof| verify_merkle_proof(leaf, &proof, onchain_root));
+
+    tracing::info!(
+        sku_id = %payload.sku_id,
+        unit_id = %payload.unit_id,
+        valid,
+        proof_source,
+        "Unit verification"
+    );
+
+    Ok(Json(VerifyUnitResponse {
+        sku_id: payload.sku_id,
+        unit_id: payload.unit_id,
+        valid,
+        packaged_at,
+        parent_batch_hash: format_hash(parent_batch_hash),
+        merkle_root: format_hash(onchain_root),
+        leaf: format_hash(leaf),
+        proof_source,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn units(n: usize) -> Vec<String> {
+        (1..=n).map(|i| format!("UNIT-{:04}", i)).collect()
+    }
+
+    #[test]
+    fn test_every_unit_proves_against_root() {
+        let tree = UnitTree::build("SKU-1", "21", units(7)).unwrap();
+        for unit_id in &tree.unit_ids {
+            let proof = tree.proof(unit_id).unwrap();
+            assert!(verify_merkle_proof(unit_leaf(unit_id), &proof, tree.root));
+        }
+        assert!(tree.proof("UNIT-9999").is_none());
+
+        let proof = tree.proof("UNIT-0001").unwrap();
+        assert!(!verify_merkle_proof(
+            unit_leaf("UNIT-0002"),
+            &proof,
+            tree.root
+        ));
+    }
+
+    #[test]
+    fn test_build_rejects_bad_unit_lists() {
+        assert!(UnitTree::build("SKU-1", "21", vec![]).is_err());
+        assert!(UnitTree::build("SKU-1", "21", vec!["A".into(), "A".into()]).is_err());
+        assert!(UnitTree::build("SKU-1", "21", vec![" ".into()]).is_err());
+        assert!(UnitTree::build("../SKU", "21", units(2)).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+//! Slow operation log
+//!
+//! Records IPFS uploads and transaction receipt waits that exceed their
+//! latency threshold into a bounded in-memory ring buffer, together with
+//! the tracing span context that was active at the time. Exposed through
+//! `GET /api/admin/slowlog` for diagnosing field performance without a
+//! full tracing backend.
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing_subscriber::fmt::{format::DefaultFields, FormattedFields};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Registry;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlowOperation {
+    IpfsUpload,
+    ReceiptWait,
+}
+
+impl SlowOperation {
+    fn env_key(&self) -> &'static str {
+        match self {
+            SlowOperation::IpfsUpload => "SLOWLOG_IPFS_UPLOAD_MS",
+            SlowOperation::ReceiptWait => "SLOWLOG_RECEIPT_WAIT_MS",
+        }
+    }
+
+    fn default_threshold(&self) -> Duration {
+        match self {
+            SlowOperation::IpfsUpload => Duration::from_secs(10),
+            SlowOperation::ReceiptWait => Duration::from_secs(60),
+        }
+    }
+
+    pub fn threshold(&self) -> Duration {
+        std::env::var(self.env_key())
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .map(Duration::from_millis)
+            .unwrap_or_else(|| self.default_threshold())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SlowLogEntry {
+    pub operation: SlowOperation,
+    pub detail: String,
+    pub duration_ms: u64,
+    pub threshold_ms: u64,
+    pub recorded_at: String,
+    /// Enclosing spans from root to leaf, e.g. `request{method=POST uri=/api/fpo/purchase}`
+    pub span_context: Vec<String>,
+}
+
+pub struct SlowLog {
+    capacity: usize,
+    entries: Mutex<VecDeque<SlowLogEntry>>,
+}
+
+impl SlowLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn push(&self, entry: SlowLogEntry) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Most recent entries first
+    pub fn recent(&self, limit: usize) -> Vec<SlowLogEntry> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().rev().take(limit).cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// Process-wide slow log shared by the IPFS and chain clients
+pub fn global() -> &'static SlowLog {
+    static SLOW_LOG: OnceLock<SlowLog> = OnceLock::new();
+    SLOW_LOG.get_or_init(|| {
+        let capacity = std::env::var("SLOWLOG_CAPACITY")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .unwrap_or(200);
+        SlowLog::new(capacity)
+    })
+}
+
+/// Await `fut`, recording it in the slow log if it exceeds the threshold for `operation`
+pub async fn observe<F: Future>(
+    operation: SlowOperation,
+    detail: impl Into<String>,
+    fut: F,
+) -> F::Output {
+    let started = Instant::now();
+    let output = fut.await;
+    let elapsed = started.elapsed();
+    let threshold = operation.threshold();
+
+    if elapsed > threshold {
+        let detail = detail.into();
+        tracing::warn!(
+            ?operation,
+            detail = %detail,
+            duration_ms = elapsed.as_millis() as u64,
+            threshold_ms = threshold.as_millis() as u64,
+            "Slow operation"
+        );
+        global().push(SlowLogEntry {
+            operation,
+            detail,
+            duration_ms: elapsed.as_millis() as u64,
+            threshold_ms: threshold.as_millis() as u64,
+            recorded_at: chrono::Utc::now().to_rfc3339(),
+            span_context: current_span_context(),
+        });
+    }
+
+    output
+}
+
+/// Names and recorded fields of the spans enclosing the current one
+fn current_span_context() -> Vec<String> {
+    let Some(id) = tracing::Span::current().id() else {
+        return Vec::new();
+    };
+
+    tracing::dispatcher::get_default(|dispatch| {
+        let Some(registry) = dispatch.downcast_ref::<Registry>() else {
+            return Vec::new();
+        };
+        let Some(span) = registry.span(&id) else {
+            return Vec::new();
+        };
+
+        span.scope()
+            .from_root()
+            .map(|s| {
+                let extensions = s.extensions();
+                match extensions.get::<FormattedFields<DefaultFields>>() {
+                    Some(fields) if !fields.is_empty() => format!("{}{{{}}}", s.name(), fields),
+                    _ => s.name().to_string(),
+                }
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(detail: &str) -> SlowLogEntry {
+        SlowLogEntry {
+            operation: SlowOperation::IpfsUpload,
+            detail: detail.to_string(),
+            duration_ms: 11_000,
+            threshold_ms: 10_000,
+            recorded_at: String::new(),
+            span_context: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_ring_buffer_drops_oldest() {
+        let log = SlowLog::new(2);
+        log.push(entry("a"));
+        log.push(entry("b"));
+        log.push(entry("c"));
+
+        let recent: Vec<String> = log.recent(10).into_iter().map(|e| e.detail).collect();
+        assert_eq!(recent, vec!["c", "b"]);
+    }
+}
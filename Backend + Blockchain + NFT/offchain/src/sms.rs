@@ -0,0 +1,460 @@
+//! Farmer-facing SMS channel
+//!
+//! Inbound messages from the SMS gateway arrive at `POST /api/sms/inbound`.
+//! The sender's number is matched against the farmer verification database
+//! and simple keyword queries are answered from the batch ledger:
+//!
+//! - `STATUS <batch_id>` → latest stage reached by one of the farmer's batches
+//! - `PAYMENT`           → most recent FPO purchase payments and any payment holds
+//! - anything else       → usage help
+
+use crate::batch_ledger;
+use crate::error::{ApiError, ApiResult};
+use crate::farmer_verification::FarmerEntry;
+use crate::holds::SettlementStatus;
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{extract::State, http::HeaderMap, Form, Json};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+// ======================== OUTBOUND PROVIDER ========================
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Gateway that delivers outbound text messages
+///
+/// `to` is the number as given by the caller; providers normalize it to the
+/// form their API expects.
+pub trait SmsProvider: std::fmt::Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn send<'a>(&'a self, client: &'a Client, to: &'a str, message: &'a str)
+        -> BoxFuture<'a, Result<()>>;
+}
+
+/// Generic JSON gateway: `POST {to, from, message}` with an optional bearer key
+#[derive(Debug)]
+pub struct HttpSmsProvider {
+    pub endpoint: String,
+    pub api_key: Option<String>,
+    pub sender_id: String,
+}
+
+impl SmsProvider for HttpSmsProvider {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    fn send<'a>(
+        &'a self,
+        client: &'a Client,
+        to: &'a str,
+        message: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut request = client.post(&self.endpoint).json(&serde_json::json!({
+                "to": to,
+                "from": self.sender_id,
+                "message": message,
+            }));
+            if let Some(api_key) = &self.api_key {
+                request = request.bearer_auth(api_key);
+            }
+            request
+                .send()
+                .await
+                .context("Failed to reach SMS provider")?
+                .error_for_status()
+                .context("SMS provider rejected message")?;
+            Ok(())
+        })
+    }
+}
+
+/// MSG91 `v2/sendsms`; DLT template ID is required for Indian traffic
+#[derive(Debug)]
+pub struct Msg91Provider {
+    pub auth_key: String,
+    pub sender_id: String,
+    pub route: String,
+    pub dlt_template_id: Option<String>,
+    pub country_code: String,
+}
+
+impl SmsProvider for Msg91Provider {
+    fn name(&self) -> &'static str {
+        "msg91"
+    }
+
+    fn send<'a>(
+        &'a self,
+        client: &'a Client,
+        to: &'a str,
+        message: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut body = serde_json::json!({
+                "sender": self.sender_id,
+                "route": self.route,
+                "country": self.country_code,
+                "sms": [{ "message": message, "to": [normalize_mobile(to)] }],
+            });
+            if let Some(template_id) = &self.dlt_template_id {
+                body["DLT_TE_ID"] = serde_json::Value::String(template_id.clone());
+            }
+            client
+                .post("https://api.msg91.com/api/v2/sendsms")
+                .header("authkey", &self.auth_key)
+                .json(&body)
+                .send()
+                .await
+                .context("Failed to reach MSG91")?
+                .error_for_status()
+                .context("MSG91 rejected message")?;
+            Ok(())
+        })
+    }
+}
+
+/// Twilio Programmable Messaging
+#[derive(Debug)]
+pub struct TwilioProvider {
+    pub account_sid: String,
+    pub auth_token: String,
+    /// Sending number in E.164 form or a messaging service SID
+    pub from: String,
+    pub country_code: String,
+}
+
+impl SmsProvider for TwilioProvider {
+    fn name(&self) -> &'static str {
+        "twilio"
+    }
+
+    fn send<'a>(
+        &'a self,
+        client: &'a Client,
+        to: &'a str,
+        message: &'a str,
+    ) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let url = format!(
+                "https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json",
+                self.account_sid
+            );
+            let to = format!("+{}{}", self.country_code, normalize_mobile(to));
+            let sender = if self.from.starts_with("MG") {
+                "MessagingServiceSid"
+            } else {
+                "From"
+            };
+            client
+                .post(url)
+                .basic_auth(&self.account_sid, Some(&self.auth_token))
+                .form(&[("To", to.as_str()), (sender, &self.from), ("Body", message)])
+                .send()
+                .await
+                .context("Failed to reach Twilio")?
+                .error_for_status()
+                .context("Twilio rejected message")?;
+            Ok(())
+        })
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+/// Outbound SMS through the configured [`SmsProvider`]
+///
+/// SMS_PROVIDER selects `http` (SMS_PROVIDER_URL), `msg91` or `twilio`;
+/// without a usable configuration the client runs in dry-run mode and only
+/// logs messages.
+#[derive(Debug, Clone)]
+pub struct SmsClient {
+    client: Client,
+    provider: Option<Arc<dyn SmsProvider>>,
+}
+
+impl SmsClient {
+    pub fn from_env() -> Self {
+        let sender_id = env_var("SMS_SENDER_ID").unwrap_or_else(|| "OILSED".to_string());
+        let country_code = env_var("SMS_COUNTRY_CODE").unwrap_or_else(|| "91".to_string());
+
+        let provider: Option<Arc<dyn SmsProvider>> =
+            match env_var("SMS_PROVIDER").as_deref().unwrap_or("http") {
+                "msg91" => env_var("MSG91_AUTH_KEY").map(|auth_key| {
+                    Arc::new(Msg91Provider {
+                        auth_key,
+                        sender_id,
+                        route: env_var("MSG91_ROUTE").unwrap_or_else(|| "4".to_string()),
+                        dlt_template_id: env_var("MSG91_DLT_TEMPLATE_ID"),
+                        country_code,
+                    }) as Arc<dyn SmsProvider>
+                }),
+                "twilio" => match (
+                    env_var("TWILIO_ACCOUNT_SID"),
+                    env_var("TWILIO_AUTH_TOKEN"),
+                    env_var("TWILIO_FROM"),
+                ) {
+                    (Some(account_sid), Some(auth_token), Some(from)) => {
+                        Some(Arc::new(TwilioProvider {
+                            account_sid,
+                            auth_token,
+                            from,
+                            country_code,
+                        }) as Arc<dyn SmsProvider>)
+                    }
+                    _ => None,
+                },
+                "http" => env_var("SMS_PROVIDER_URL").map(|endpoint| {
+                    Arc::new(HttpSmsProvider {
+                        endpoint,
+                        api_key: env_var("SMS_API_KEY"),
+                        sender_id,
+                    }) as Arc<dyn SmsProvider>
+                }),
+                other => {
+                    tracing::warn!(provider = %other, "Unknown SMS_PROVIDER");
+                    None
+                }
+            };
+        match &provider {
+            Some(provider) => tracing::info!(provider = provider.name(), "SMS provider configured"),
+            None => tracing::warn!("SMS provider not configured, outbound SMS will only be logged"),
+        }
+
+        Self::new(provider)
+    }
+
+    pub fn new(provider: Option<Arc<dyn SmsProvider>>) -> Self {
+        Self {
+            client: Client::new(),
+            provider,
+        }
+    }
+
+    /// Send a text message; returns whether it was handed to the provider
+    pub async fn send(&self, to: &str, message: &str) -> Result<bool> {
+        let Some(provider) = &self.provider else {
+            tracing::info!(to = %to, message = %message, "SMS dry run");
+            return Ok(false);
+        };
+
+        provider.send(&self.client, to, message).await?;
+
+        tracing::info!(to = %to, provider = provider.name(), "SMS sent");
+        Ok(true)
+    }
+}
+
+/// Reduce a phone number to the 10-digit form stored in the farmer database
+pub fn normalize_mobile(raw: &str) -> String {
+    let digits: String = raw.chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.len() > 10 {
+        digits[digits.len() - 10..].to_string()
+    } else {
+        digits
+    }
+}
+
+/// Shared-secret check for inbound gateway webhooks (SMS_WEBHOOK_SECRET)
+pub fn verify_webhook_secret(headers: &HeaderMap) -> Result<(), ApiError> {
+    check_webhook_secret(env_var("SMS_WEBHOOK_SECRET").as_deref(), headers)
+}
+
+/// Check the `x-webhook-secret` header against `expected`; every webhook is
+/// rejected when no secret is configured. The secret is never read from the
+/// query string, which ends up in access and audit logs.
+pub fn check_webhook_secret(expected: Option<&str>, headers: &HeaderMap) -> Result<(), ApiError> {
+    let Some(expected) = expected else {
+        tracing::warn!("Rejected gateway webhook, SMS_WEBHOOK_SECRET is not set");
+        return Err(ApiError::unauthorized("Gateway webhooks are not configured"));
+    };
+
+    let provided = headers
+        .get("x-webhook-secret")
+        .and_then(|v| v.to_str().ok());
+
+    if provided != Some(expected) {
+        tracing::warn!("Rejected gateway webhook with invalid secret");
+        return Err(ApiError::unauthorized("Invalid webhook secret"));
+    }
+
+    Ok(())
+}
+
+// ======================== QUERY PARSING ========================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SmsQuery {
+    Status { batch_id: String },
+    Payment,
+    Help,
+}
+
+impl SmsQuery {
+    pub fn parse(text: &str) -> Self {
+        let mut words = text.split_whitespace();
+        let keyword = words.next().unwrap_or_default().to_uppercase();
+
+        match keyword.as_str() {
+            "STATUS" => match words.next() {
+                Some(batch_id) => SmsQuery::Status {
+                    batch_id: batch_id.to_string(),
+                },
+                None => SmsQuery::Help,
+            },
+            "PAYMENT" | "PAY" => SmsQuery::Payment,
+            _ => SmsQuery::Help,
+        }
+    }
+}
+
+const HELP_TEXT: &str = "Send STATUS <batch id> for batch progress or PAYMENT for recent payments.";
+
+/// Answer a query on behalf of a verified farmer
+pub fn answer_query(farmer: &FarmerEntry, query: &SmsQuery) -> Result<String> {
+    match query {
+        SmsQuery::Status { batch_id } => {
+            let status = batch_ledger::batch_status(batch_id)?;
+            Ok(match status {
+                Some(status)
+                    if status.farmer_did.as_deref() == Some(farmer.farmer_did.as_str()) =>
+                {
+                    let mut reply = format!("Batch {}: {}", status.batch_id, status.stage_label());
+                    if let (Some(quantity), Some(grade)) =
+                        (status.quantity_kg, &status.quality_grade)
+                    {
+                        reply.push_str(&format!(", {:.0} kg {}", quantity, grade));
+                    }
+                    if status.packaged_skus > 0 {
+                        reply.push_str(&format!(", {} SKUs packed", status.packaged_skus));
+                    }
+                    reply.push('.');
+                    reply
+                }
+                // Do not reveal whether batches of other farmers exist
+                _ => format!("No batch {} found for your number.", batch_id),
+            })
+        }
+        SmsQuery::Payment => {
+            let payments = batch_ledger::payments_for_farmer(&farmer.farmer_did)?;
+            Ok(match payments.first() {
+                Some(latest) => {
+                    let total: f64 = payments.iter().map(|p| p.total_cost).sum();
+                    let mut reply = format!(
+                        "Last payment: Rs {:.2} for batch {} ({:.0} kg) on {}. {} batch(es), total Rs {:.2}.",
+                        latest.total_cost,
+                        latest.batch_id,
+                        latest.quantity_kg,
+                        latest.timestamp.get(..10).unwrap_or(&latest.timestamp),
+                        payments.len(),
+                        total
+                    );
+                    for held in payments
+                        .iter()
+                        .filter(|p| p.settlement == SettlementStatus::OnHold)
+                    {
+                        reply.push_str(&format!(
+                            " Batch {} ON HOLD: {}.",
+                            held.batch_id,
+                            held.hold_reasons.join("; ")
+                        ));
+                    }
+                    reply
+                }
+                None => "No payments recorded for your number yet.".to_string(),
+            })
+        }
+        SmsQuery::Help => Ok(HELP_TEXT.to_string()),
+    }
+}
+
+// ======================== INBOUND WEBHOOK ========================
+
+const UNREGISTERED_REPLY: &str =
+    "This number is not registered. Please contact your FPO to register.";
+
+/// Reply to an inbound query from `farmer` (`None` for unknown numbers)
+pub fn reply_to(farmer: Option<&FarmerEntry>, query: &SmsQuery) -> Result<String> {
+    match farmer {
+        Some(farmer) => answer_query(farmer, query),
+        None => Ok(UNREGISTERED_REPLY.to_string()),
+    }
+}
+
+/// Inbound message as posted by the gateway (Twilio and generic field names)
+#[derive(Debug, Deserialize)]
+pub struct InboundSms {
+    #[serde(alias = "From", alias = "sender", alias = "mobile")]
+    pub from: String,
+    #[serde(alias = "Body", alias = "message", alias = "text")]
+    pub body: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SmsReply {
+    pub to: String,
+    pub message: String,
+    pub delivered: bool,
+}
+
+pub async fn inbound_sms(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(payload): Form<InboundSms>,
+) -> ApiResult<SmsReply> {
+    verify_webhook_secret(&headers)?;
+
+    let mobile = normalize_mobile(&payload.from);
+    let query = SmsQuery::parse(&payload.body);
+    tracing::info!(mobile = %mobile, ?query, "Inbound SMS");
+
+    let farmer = state.farmer_verification.get_farmer_by_mobile(&mobile).await?;
+
+    let message = reply_to(farmer.as_ref(), &query).map_err(ApiError::from)?;
+
+    let delivered = match state.sms_client.send(&payload.from, &message).await {
+        Ok(delivered) => delivered,
+        Err(e) => {
+            tracing::error!(mobile = %mobile, error = %format!("{:#}", e), "Failed to send SMS reply");
+            false
+        }
+    };
+
+    Ok(Json(SmsReply {
+        to: payload.from,
+        message,
+        delivered,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_queries() {
+        assert_eq!(
+            SmsQuery::parse("status B-21"),
+            SmsQuery::Status {
+                batch_id: "B-21".to_string()
+            }
+        );
+        assert_eq!(SmsQuery::parse("  PAYMENT "), SmsQuery::Payment);
+        assert_eq!(SmsQuery::parse("STATUS"), SmsQuery::Help);
+        assert_eq!(SmsQuery::parse("hello"), SmsQuery::Help);
+    }
+
+    #[test]
+    fn test_normalize_mobile() {
+        assert_eq!(normalize_mobile("+91 98765-43210"), "9876543210");
+        assert_eq!(normalize_mobile("9876543210"), "9876543210");
+    }
+}
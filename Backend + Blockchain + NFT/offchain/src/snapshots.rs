@@ -0,0 +1,497 @@
+//! Encrypted state snapshots on IPFS for disaster recovery
+//!
+//! Every SNAPSHOT_INTERVAL_SECS (default 4 hours) the whole `data/` directory
+//! (farmer database, batch records, commitment openings, anchors and their
+//! cursor, experiment counters) is packed into a tar.gz, encrypted with
+//! AES-256-GCM under SNAPSHOT_ENCRYPTION_KEY and pinned to IPFS. The chain
+//! height at snapshot time is stored inside the archive, so the worst case
+//! loss is one interval of off-chain writes. Contract events after that
+//! height are reported on restore; they are not replayed into the store,
+//! since the chain holds only their hashes.
+//!
+//! Restore on a fresh host (same .env, including the encryption key):
+//!
+//! ```text
+//! cargo run --release -- restore          # latest snapshot pinned on Pinata
+//! cargo run --release -- restore <CID>    # a specific snapshot
+//! ```
+//!
+//! The command moves any existing `data/` aside to `data.pre-restore-<ts>/`,
+//! unpacks the snapshot, lists the contract events emitted after the
+//! snapshot in `data/restore_report.json` and loads the application state to
+//! check it. The off-chain records of the reported events have to be
+//! re-submitted by their senders. The anchor job resumes from its snapshotted cursor on the next
+//! start, so events after the snapshot are anchored as usual. The event
+//! index is not snapshotted; it is rebuilt from INDEXER_START_BLOCK.
+
+use crate::admin::require_admin;
+use crate::chain::{ChainClient, EventRef};
+use crate::error::{ApiError, ApiResult};
+use crate::farmer_verification;
+use crate::indexer;
+use crate::ipfs::IpfsClient;
+use crate::logging::LogControl;
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Nonce,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+const DATA_DIR: &str = "data";
+const MANIFEST_PATH: &str = "data/snapshots.json";
+/// Written into the archive root; not part of the live data directory
+const SNAPSHOT_INFO_FILE: &str = ".snapshot.json";
+const REPORT_PATH: &str = "data/restore_report.json";
+/// Pin names start with this prefix so the latest snapshot can be found by name
+const PIN_NAME_PREFIX: &str = "ovc-snapshot";
+const SNAPSHOT_MAGIC: &[u8; 8] = b"OVCSNAP1";
+const NONCE_LEN: usize = 12;
+const DEFAULT_INTERVAL_SECS: u64 = 4 * 3600;
+const REPORT_WINDOW_BLOCKS: u64 = 5000;
+
+// ======================== ENCRYPTION ========================
+
+/// Parse a 32-byte AES key from 64 hex characters
+pub fn parse_key(hex_key: &str) -> Result<[u8; 32]> {
+    let bytes = hex::decode(hex_key.trim().trim_start_matches("0x"))
+        .context("SNAPSHOT_ENCRYPTION_KEY is not valid hex")?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow!("SNAPSHOT_ENCRYPTION_KEY must be 32 bytes (64 hex characters)"))
+}
+
+/// MAGIC || nonce || AES-256-GCM ciphertext
+pub fn encrypt(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>> {
+    let cipher = Aes256Gcm::new(key.into());
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let ciphertext = cipher
+        .encrypt(&Nonce::from(nonce), plaintext)
+        .map_err(|_| anyhow!("Snapshot encryption failed"))?;
+
+    let mut blob = Vec::with_capacity(SNAPSHOT_MAGIC.len() + NONCE_LEN + ciphertext.len());
+    blob.extend_from_slice(SNAPSHOT_MAGIC);
+    blob.extend_from_slice(&nonce);
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+}
+
+pub fn decrypt(key: &[u8; 32], blob: &[u8]) -> Result<Vec<u8>> {
+    let body = blob
+        .strip_prefix(SNAPSHOT_MAGIC.as_slice())
+        .context("Not a snapshot (bad magic)")?;
+    if body.len() < NONCE_LEN {
+        bail!("Snapshot is truncated");
+    }
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    let nonce: [u8; NONCE_LEN] = nonce.try_into()?;
+
+    Aes256Gcm::new(key.into())
+        .decrypt(&Nonce::from(nonce), ciphertext)
+        .map_err(|_| anyhow!("Snapshot decryption failed (wrong key or corrupted data)"))
+}
+
+// ======================== ARCHIVE ========================
+
+/// Stored as `.snapshot.json` inside the archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub created_at: String,
+    /// Latest primary-chain block when the snapshot was taken
+    pub chain_block: u64,
+}
+
+/// Archive `data_dir`; the live SQLite farmer database (and its WAL files)
+/// is replaced by `farmer_db`, a consistent copy taken with `VACUUM INTO`.
+/// The event index is left out, the indexer rebuilds it from the chain.
+fn pack(data_dir: &Path, info: &SnapshotInfo, farmer_db: Option<&Path>) -> Result<Vec<u8>> {
+    let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+
+    let info_json = serde_json::to_vec_pretty(info)?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(info_json.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    archive.append_data(&mut header, SNAPSHOT_INFO_FILE, info_json.as_slice())?;
+
+    let file_name = |path: &'static str| {
+        Path::new(path)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or_default()
+    };
+    let db_name = file_name(farmer_verification::DB_PATH);
+    let events_db_name = file_name(indexer::DB_PATH);
+    for entry in walkdir::WalkDir::new(data_dir).min_depth(1) {
+        let entry = entry.with_context(|| format!("Failed to archive {}", data_dir.display()))?;
+        let name = entry.path().strip_prefix(data_dir)?;
+        let name_str = name.to_string_lossy();
+        if name_str.starts_with(events_db_name)
+            || (farmer_db.is_some() && name_str.starts_with(db_name))
+        {
+            continue;
+        }
+        if entry.file_type().is_dir() {
+            archive.append_dir(name, entry.path())?;
+        } else {
+            archive.append_path_with_name(entry.path(), name)?;
+        }
+    }
+    if let Some(farmer_db) = farmer_db {
+        archive.append_path_with_name(farmer_db, db_name)?;
+    }
+
+    Ok(archive.into_inner()?.finish()?)
+}
+
+fn unpack(archive: &[u8], data_dir: &Path) -> Result<SnapshotInfo> {
+    std::fs::create_dir_all(data_dir)
+        .with_context(|| format!("Failed to create {}", data_dir.display()))?;
+    tar::Archive::new(GzDecoder::new(archive))
+        .unpack(data_dir)
+        .with_context(|| format!("Failed to unpack snapshot into {}", data_dir.display()))?;
+
+    let info_path = data_dir.join(SNAPSHOT_INFO_FILE);
+    let info = serde_json::from_slice(&std::fs::read(&info_path)?)
+        .context("Snapshot has no valid .snapshot.json")?;
+    std::fs::remove_file(&info_path)?;
+    Ok(info)
+}
+
+// ======================== STORE ========================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotRecord {
+    pub cid: String,
+    pub created_at: String,
+    pub chain_block: u64,
+    pub size_bytes: usize,
+}
+
+/// Encryption key plus the snapshots pinned from this host
+pub struct SnapshotStore {
+    key: Option<[u8; 32]>,
+    records: Mutex<Vec<SnapshotRecord>>,
+}
+
+impl SnapshotStore {
+    pub fn load() -> Result<Self> {
+        let key = match std::env::var("SNAPSHOT_ENCRYPTION_KEY") {
+            Ok(hex_key) if !hex_key.is_empty() => Some(parse_key(&hex_key)?),
+            _ => None,
+        };
+
+        let records = match std::fs::read_to_string(MANIFEST_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid snapshot manifest {}", MANIFEST_PATH))?,
+            Err(_) => Vec::new(),
+        };
+
+        Ok(Self {
+            key,
+            records: Mutex::new(records),
+        })
+    }
+}
+
+// ======================== SNAPSHOT JOB ========================
+
+/// Archive, encrypt and pin `data/`; returns the new snapshot record
+pub async fn run_once(state: &AppState) -> Result<SnapshotRecord> {
+    let key = state
+        .snapshots
+        .key
+        .context("SNAPSHOT_ENCRYPTION_KEY is not set")?;
+    // Also serialises concurrent runs
+    let mut records = state.snapshots.records.lock().await;
+
+    let info = SnapshotInfo {
+        created_at: chrono::Utc::now().to_rfc3339(),
+        chain_block: state.chain().latest_block().await?,
+    };
+
+    // The farmer database is written concurrently; archive a consistent copy
+    let farmer_db = std::env::temp_dir().join(format!("{}-farmers.db", PIN_NAME_PREFIX));
+    state.farmer_verification.backup_to(&farmer_db).await?;
+    let blob = {
+        let info = info.clone();
+        let farmer_db = farmer_db.clone();
+        tokio::task::spawn_blocking(move || {
+            encrypt(&key, &pack(Path::new(DATA_DIR), &info, Some(&farmer_db))?)
+        })
+        .await
+        .context("Snapshot task failed")?
+    };
+    let _ = std::fs::remove_file(&farmer_db);
+    let blob = blob?;
+    let size_bytes = blob.len();
+
+    let filename = format!(
+        "{}-{}.bin",
+        PIN_NAME_PREFIX,
+        chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+    );
+    let cid = state
+        .ipfs_client
+        .upload_bytes(blob, &filename)
+        .await
+        .context("Failed to pin snapshot")?;
+
+    let record = SnapshotRecord {
+        cid,
+        created_at: info.created_at,
+        chain_block: info.chain_block,
+        size_bytes,
+    };
+    records.push(record.clone());
+    std::fs::write(MANIFEST_PATH, serde_json::to_string_pretty(&*records)?)
+        .with_context(|| format!("Failed to write {}", MANIFEST_PATH))?;
+
+    Ok(record)
+}
+
+/// Start the periodic snapshot job if an encryption key is configured
+pub fn spawn(state: AppState) {
+    if state.snapshots.key.is_none() {
+        tracing::warn!("SNAPSHOT_ENCRYPTION_KEY not set, IPFS snapshots disabled");
+        return;
+    }
+    let secs = std::env::var("SNAPSHOT_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+    let interval = Duration::from_secs(secs.max(60));
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick fires immediately; wait a full interval after startup
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            match run_once(&state).await {
+                Ok(record) => tracing::info!(
+                    cid = %record.cid,
+                    chain_block = record.chain_block,
+                    size_bytes = record.size_bytes,
+                    "Snapshot pinned to IPFS"
+                ),
+                Err(e) => tracing::error!(error = %format!("{:#}", e), "Snapshot job failed"),
+            }
+        }
+    });
+}
+
+// ======================== RESTORE ========================
+
+#[derive(Debug, Serialize)]
+struct RestoreReport {
+    snapshot_cid: String,
+    snapshot_created_at: String,
+    snapshot_chain_block: u64,
+    /// Last block scanned for events
+    reported_to_block: u64,
+    restored_at: String,
+    /// Contract events newer than the snapshot; their off-chain records may
+    /// need to be re-submitted
+    events_after_snapshot: Vec<EventRef>,
+}
+
+/// `restore [CID]`: rebuild `data/` from a snapshot, then report the contract
+/// events emitted since
+pub async fn restore_command(cid: Option<String>, log_control: LogControl) -> Result<()> {
+    let key = parse_key(
+        &std::env::var("SNAPSHOT_ENCRYPTION_KEY").context("SNAPSHOT_ENCRYPTION_KEY not set")?,
+    )?;
+    let ipfs = IpfsClient::from_env()?;
+
+    let cid = match cid {
+        Some(cid) => cid,
+        None => ipfs
+            .latest_pin(PIN_NAME_PREFIX)
+            .await?
+            .context("No snapshot pinned on Pinata")?,
+    };
+    tracing::info!(cid = %cid, "Restoring snapshot");
+
+    let blob = ipfs.fetch_bytes(&cid).await?;
+    let archive = decrypt(&key, &blob)?;
+
+    let data_dir = Path::new(DATA_DIR);
+    if data_dir.exists() {
+        let backup = format!(
+            "{}.pre-restore-{}",
+            DATA_DIR,
+            chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+        );
+        std::fs::rename(data_dir, &backup)
+            .with_context(|| format!("Failed to move existing {} aside", DATA_DIR))?;
+        tracing::warn!(backup = %backup, "Existing data directory moved aside");
+    }
+    let info = unpack(&archive, data_dir)?;
+    tracing::info!(
+        created_at = %info.created_at,
+        chain_block = info.chain_block,
+        "Snapshot unpacked"
+    );
+
+    // Report everything the contract emitted since the snapshot
+    let chain = ChainClient::from_env().await?;
+    let latest = chain.latest_block().await?;
+    let mut events = Vec::new();
+    let mut from_block = info.chain_block + 1;
+    while from_block <= latest {
+        let to_block = latest.min(from_block + REPORT_WINDOW_BLOCKS - 1);
+        events.extend(chain.contract_events(from_block, to_block).await?);
+        from_block = to_block + 1;
+    }
+
+    let report = RestoreReport {
+        snapshot_cid: cid,
+        snapshot_created_at: info.created_at,
+        snapshot_chain_block: info.chain_block,
+        reported_to_block: latest,
+        restored_at: chrono::Utc::now().to_rfc3339(),
+        events_after_snapshot: events,
+    };
+    std::fs::write(REPORT_PATH, serde_json::to_string_pretty(&report)?)
+        .with_context(|| format!("Failed to write {}", REPORT_PATH))?;
+
+    // Make sure the restored data loads before anyone starts the server on it
+    let state = AppState::from_env(log_control).await?;
+    tracing::info!(
+        farmers = state.farmer_verification.total_farmers().await?,
+        events_after_snapshot = report.events_after_snapshot.len(),
+        report = REPORT_PATH,
+        "Restore complete"
+    );
+
+    Ok(())
+}
+
+// ======================== HANDLERS ========================
+
+/// Snapshots pinned from this host, oldest first (admin only)
+pub async fn list_snapshots(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<SnapshotRecord>> {
+    require_admin(&state, &headers)?;
+
+    let records = state.snapshots.records.lock().await.clone();
+    Ok(Json(paginate("snapshots", records, &params, |r| {
+        (r.created_at.clone(), r.cid.clone())
+    })?))
+}
+
+/// Take a snapshot immediately (admin only)
+pub async fn trigger_snapshot(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<SnapshotRecord> {
+    require_admin(&state, &headers)?;
+
+    if state.snapshots.key.is_none() {
+        return Err(ApiError::bad_request("SNAPSHOT_ENCRYPTION_KEY is not set"));
+    }
+
+    let record = run_once(&state)
+        .await
+        .map_err(|e| ApiError::internal(format!("Snapshot failed: {:#}", e)))?;
+
+    Ok(Json(record))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_roundtrip_and_tamper_detection() {
+        let key = parse_key(&"11".repeat(32)).unwrap();
+        let blob = encrypt(&key, b"farmers_db").unwrap();
+        assert!(blob.starts_with(SNAPSHOT_MAGIC));
+        assert_eq!(decrypt(&key, &blob).unwrap(), b"farmers_db");
+
+        let mut tampered = blob.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(decrypt(&key, &tampered).is_err());
+
+        let other = parse_key(&"22".repeat(32)).unwrap();
+        assert!(decrypt(&other, &blob).is_err());
+    }
+
+    #[test]
+    fn test_parse_key_rejects_wrong_length() {
+        assert!(parse_key("abcd").is_err());
+        assert!(parse_key(&format!("0x{}", "ab".repeat(32))).is_ok());
+    }
+
+    #[test]
+    fn test_pack_unpack_roundtrip() {
+        let root =
+            std::env::temp_dir().join(format!("ovc-snapshot-test-{}", rand::random::<u64>()));
+        let source = root.join("source");
+        std::fs::create_dir_all(source.join("BATCH-1")).unwrap();
+        std::fs::write(source.join("farmers_db.json"), "{}").unwrap();
+        std::fs::write(source.join("BATCH-1/fpo_purchase.json"), "[]").unwrap();
+
+        let info = SnapshotInfo {
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            chain_block: 42,
+        };
+        let archive = pack(&source, &info, None).unwrap();
+
+        let target = root.join("target");
+        let restored = unpack(&archive, &target).unwrap();
+        assert_eq!(restored.chain_block, 42);
+        assert_eq!(
+            std::fs::read_to_string(target.join("BATCH-1/fpo_purchase.json")).unwrap(),
+            "[]"
+        );
+        assert!(!target.join(SNAPSHOT_INFO_FILE).exists());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_pack_replaces_live_farmer_db_with_copy() {
+        let root =
+            std::env::temp_dir().join(format!("ovc-snapshot-test-{}", rand::random::<u64>()));
+        let source = root.join("source");
+        std::fs::create_dir_all(&source).unwrap();
+        std::fs::write(source.join("farmers.db"), "live").unwrap();
+        std::fs::write(source.join("farmers.db-wal"), "wal").unwrap();
+        std::fs::write(source.join("events.db"), "index").unwrap();
+        let copy = root.join("copy.db");
+        std::fs::write(&copy, "consistent").unwrap();
+
+        let info = SnapshotInfo {
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            chain_block: 7,
+        };
+        let archive = pack(&source, &info, Some(&copy)).unwrap();
+
+        let target = root.join("target");
+        unpack(&archive, &target).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(target.join("farmers.db")).unwrap(),
+            "consistent"
+        );
+        assert!(!target.join("farmers.db-wal").exists());
+        assert!(!target.join("events.db").exists());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
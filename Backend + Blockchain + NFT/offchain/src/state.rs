@@ -1,52 +1,255 @@
-use crate::chain::ChainClient;
+use crate::acceptance::AcceptanceStore;
+use crate::anchoring::AnchorStore;
+use crate::api_keys::ApiKeyStore;
+use crate::audit::AuditLog;
+use crate::auditor::AuditorStore;
+use crate::auth::AuthService;
+use crate::business_calendar::CalendarRegistry;
+use crate::cold_chain::ColdChainStore;
+use crate::chain::{AnchorClient, ChainClient, ChainConfig};
+use crate::commitments::DistrictSets;
+use crate::chain_roles::ChainRoleStore;
+use crate::credentials::CredentialStore;
+use crate::delegation::DelegationStore;
+use crate::experiments::ExperimentRegistry;
 use crate::farmer_verification::FarmerVerificationService;
+use crate::faults::FaultInjection;
+use crate::financing::FinancingStore;
+use crate::fpo_registry::FpoRegistry;
+use crate::grades::GradeTaxonomies;
+use crate::holds::HoldEngine;
+use crate::indexer::EventIndex;
 use crate::ipfs::IpfsClient;
+use crate::ledger_anchors::LedgerAnchorStore;
+use crate::local_blockchain::LocalBlockchainClient;
+use crate::logging::LogControl;
+use crate::metering;
+use crate::networks::{ChainNetworks, EnvVars};
+use crate::notifications::{EmailClient, NotificationService, WhatsAppClient};
+use crate::object_storage::ObjectStorage;
+use crate::otp::OtpService;
+use crate::photo_evidence::PhotoEvidenceStore;
+use crate::processor_registry::ProcessorRegistry;
+use crate::public_stats::StatsCache;
+use crate::public_trace::BrandRegistry;
+use crate::reference_data::LabelCatalog;
+use crate::reports::ReportStore;
+use crate::response_signing::ResponseSigner;
+use crate::samples::SampleStore;
+use crate::schemes::SchemeRegistry;
+use crate::seals::SealStore;
+use crate::share::ShareStore;
+use crate::shelf_life::ShelfLifeRules;
+use crate::sms::SmsClient;
+use crate::snapshots::SnapshotStore;
+use crate::timezones::TimezoneConfig;
+use crate::transport::TransportRegistry;
+use crate::video_evidence::VideoEvidenceStore;
+use crate::wallet::WalletMonitor;
+use crate::warehouse_receipts::ReceiptStore;
+use crate::warehouse_registry::WarehouseRegistry;
+use crate::warehouse_transfers::TransferStore;
+use crate::workflows::WorkflowJobStore;
 use anyhow::Result;
 use std::sync::Arc;
-use tokio::sync::Mutex;
 
 /// Unified application state containing all shared clients and configuration
 #[derive(Clone)]
 pub struct AppState {
-    pub blockchain_client: Arc<ChainClient>,
+    /// Every configured network, for handlers that target a named chain;
+    /// see [`AppState::chain`] for the default one
+    pub networks: Arc<ChainNetworks>,
     pub ipfs_client: Arc<IpfsClient>,
-    pub farmer_verification: Arc<Mutex<FarmerVerificationService>>,
+    pub farmer_verification: Arc<FarmerVerificationService>,
+    pub sms_client: Arc<SmsClient>,
+    pub notifications: Arc<NotificationService>,
+    pub brands: Arc<BrandRegistry>,
+    pub experiments: Arc<ExperimentRegistry>,
+    pub public_stats: Arc<StatsCache>,
+    pub anchor_client: Option<Arc<AnchorClient>>,
+    pub anchors: Arc<AnchorStore>,
+    pub local_ledger: LocalBlockchainClient,
+    pub ledger_anchors: Arc<LedgerAnchorStore>,
+    pub response_signer: Option<Arc<ResponseSigner>>,
+    pub fault_injection: Option<Arc<FaultInjection>>,
+    pub snapshots: Arc<SnapshotStore>,
+    pub audit: Arc<AuditLog>,
+    pub events: Arc<EventIndex>,
+    pub delegations: Arc<DelegationStore>,
+    pub shares: Arc<ShareStore>,
+    pub auditors: Arc<AuditorStore>,
+    pub calendars: Arc<CalendarRegistry>,
+    pub reports: Arc<ReportStore>,
+    pub holds: Arc<HoldEngine>,
+    pub schemes: Arc<SchemeRegistry>,
+    pub financing: Arc<FinancingStore>,
+    pub receipts: Arc<ReceiptStore>,
+    pub transfers: Arc<TransferStore>,
+    pub photos: Arc<PhotoEvidenceStore>,
+    pub videos: Arc<VideoEvidenceStore>,
+    pub fpos: Arc<FpoRegistry>,
+    pub warehouses: Arc<WarehouseRegistry>,
+    pub transport: Arc<TransportRegistry>,
+    pub processors: Arc<ProcessorRegistry>,
+    pub credentials: Arc<CredentialStore>,
+    pub object_storage: Option<Arc<ObjectStorage>>,
+    pub seals: Arc<SealStore>,
+    pub cold_chain: Arc<ColdChainStore>,
+    pub wallet: Arc<WalletMonitor>,
+    pub grades: Arc<GradeTaxonomies>,
+    pub shelf_life: Arc<ShelfLifeRules>,
+    pub samples: Arc<SampleStore>,
+    pub acceptance: Arc<AcceptanceStore>,
+    pub chain_roles: Arc<ChainRoleStore>,
+    pub otp: Arc<OtpService>,
+    pub auth: Arc<AuthService>,
+    pub api_keys: Arc<ApiKeyStore>,
+    pub workflow_jobs: Arc<WorkflowJobStore>,
+    pub timezones: Arc<TimezoneConfig>,
+    pub labels: Arc<LabelCatalog>,
+    pub district_sets: Arc<DistrictSets>,
+    pub log_control: LogControl,
+    pub admin_token: Option<String>,
 }
 
 impl AppState {
     /// Create new AppState from environment variables
-    pub async fn from_env() -> Result<Self> {
+    pub async fn from_env(log_control: LogControl) -> Result<Self> {
         tracing::info!("Initializing application state from environment");
 
-        let chain_client = ChainClient::from_env().await?;
+        // Before the clients, which meter every upload and transaction
+        metering::init()?;
+
+        let chain_client = Arc::new(ChainClient::start(ChainConfig::from_env(&EnvVars::process())?).await?);
+        let networks = ChainNetworks::from_env(chain_client).await?;
         tracing::info!("Chain client initialized successfully");
 
         let ipfs_client = IpfsClient::from_env()?;
         tracing::info!("IPFS client initialized successfully");
 
-        // Load farmer verification database
-        let farmer_verification = match FarmerVerificationService::from_file("data/farmers_db.json")
-        {
-            Ok(service) => {
-                tracing::info!(
-                    "Farmer verification service loaded with {} farmers",
-                    service.total_farmers()
-                );
-                service
-            }
-            Err(e) => {
-                tracing::warn!(
-                    "Failed to load farmer database: {}. Using empty database.",
-                    e
-                );
-                FarmerVerificationService::new()
-            }
-        };
+        // Open farmer verification database
+        let farmer_verification = FarmerVerificationService::open().await?;
+        tracing::info!(
+            "Farmer verification service opened with {} farmers",
+            farmer_verification.total_farmers().await?
+        );
+
+        let sms_client = Arc::new(SmsClient::from_env());
+        let notifications = NotificationService::new(
+            sms_client.clone(),
+            WhatsAppClient::from_env(),
+            EmailClient::from_env(),
+        );
+
+        let brands = BrandRegistry::load()?;
+        let experiments = ExperimentRegistry::load()?;
+        let anchor_client = AnchorClient::from_env()?;
+        let anchors = AnchorStore::load()?;
+        let local_ledger = LocalBlockchainClient::new().await?;
+        let ledger_anchors = LedgerAnchorStore::load()?;
+        let response_signer = ResponseSigner::from_env()?;
+        let fault_injection = FaultInjection::from_env()?;
+        let snapshots = SnapshotStore::load()?;
+        let audit = AuditLog::load()?;
+        let events = EventIndex::open().await?;
+        let delegations = DelegationStore::load()?;
+        let shares = ShareStore::load()?;
+        let auditors = AuditorStore::load()?;
+        let calendars = CalendarRegistry::load()?;
+        let reports = ReportStore::load()?;
+        let holds = HoldEngine::load()?;
+        let schemes = SchemeRegistry::load()?;
+        let financing = FinancingStore::load()?;
+        let receipts = ReceiptStore::load()?;
+        let transfers = TransferStore::load()?;
+        let photos = PhotoEvidenceStore::load()?;
+        let videos = VideoEvidenceStore::load()?;
+        let object_storage = ObjectStorage::from_env()?;
+        let credentials = CredentialStore::load()?;
+        let fpos = FpoRegistry::load()?;
+        let warehouses = WarehouseRegistry::load()?;
+        let transport = TransportRegistry::load()?;
+        let processors = ProcessorRegistry::load()?;
+        let seals = SealStore::load()?;
+        let cold_chain = ColdChainStore::load()?;
+        let wallet = WalletMonitor::from_env()?;
+        let grades = GradeTaxonomies::load()?;
+        let shelf_life = ShelfLifeRules::load()?;
+        let samples = SampleStore::load()?;
+        let acceptance = AcceptanceStore::load()?;
+        let chain_roles = ChainRoleStore::load()?;
+        let auth = AuthService::load()?;
+        let api_keys = ApiKeyStore::load()?;
+        let workflow_jobs = WorkflowJobStore::load()?;
+        let timezones = TimezoneConfig::load()?;
+        let labels = LabelCatalog::load()?;
+        let district_sets = DistrictSets::load()?;
+
+        let admin_token = std::env::var("ADMIN_API_TOKEN")
+            .ok()
+            .filter(|t| !t.is_empty());
+        if admin_token.is_none() {
+            tracing::warn!("ADMIN_API_TOKEN not set, admin endpoints are disabled");
+        }
 
         Ok(Self {
-            blockchain_client: Arc::new(chain_client),
+            networks: Arc::new(networks),
             ipfs_client: Arc::new(ipfs_client),
-            farmer_verification: Arc::new(Mutex::new(farmer_verification)),
+            farmer_verification: Arc::new(farmer_verification),
+            sms_client,
+            notifications: Arc::new(notifications),
+            brands: Arc::new(brands),
+            experiments: Arc::new(experiments),
+            public_stats: Arc::new(StatsCache::default()),
+            anchor_client: anchor_client.map(Arc::new),
+            anchors: Arc::new(anchors),
+            local_ledger,
+            ledger_anchors: Arc::new(ledger_anchors),
+            response_signer: response_signer.map(Arc::new),
+            fault_injection: fault_injection.map(Arc::new),
+            snapshots: Arc::new(snapshots),
+            audit: Arc::new(audit),
+            events: Arc::new(events),
+            delegations: Arc::new(delegations),
+            shares: Arc::new(shares),
+            auditors: Arc::new(auditors),
+            calendars: Arc::new(calendars),
+            reports: Arc::new(reports),
+            holds: Arc::new(holds),
+            schemes: Arc::new(schemes),
+            financing: Arc::new(financing),
+            receipts: Arc::new(receipts),
+            transfers: Arc::new(transfers),
+            photos: Arc::new(photos),
+            videos: Arc::new(videos),
+            credentials: Arc::new(credentials),
+            fpos: Arc::new(fpos),
+            warehouses: Arc::new(warehouses),
+            transport: Arc::new(transport),
+            processors: Arc::new(processors),
+            object_storage: object_storage.map(Arc::new),
+            seals: Arc::new(seals),
+            cold_chain: Arc::new(cold_chain),
+            wallet: Arc::new(wallet),
+            grades: Arc::new(grades),
+            shelf_life: Arc::new(shelf_life),
+            samples: Arc::new(samples),
+            acceptance: Arc::new(acceptance),
+            chain_roles: Arc::new(chain_roles),
+            otp: Arc::new(OtpService::from_env()),
+            auth: Arc::new(auth),
+            api_keys: Arc::new(api_keys),
+            workflow_jobs: Arc::new(workflow_jobs),
+            timezones: Arc::new(timezones),
+            labels: Arc::new(labels),
+            district_sets: Arc::new(district_sets),
+            log_control,
+            admin_token,
         })
     }
+
+    /// Client of the default network, current as of the last chain reload
+    pub fn chain(&self) -> Arc<ChainClient> {
+        self.networks.default_client()
+    }
 }
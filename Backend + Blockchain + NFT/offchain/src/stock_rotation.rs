@@ -0,0 +1,397 @@
+//! Stock aging and dispatch order
+//!
+//! A warehouse's stock is the lots of its open warehouse receipts (see
+//! [`crate::warehouse_receipts`]). `GET /api/warehouses/:id/dispatch-order`
+//! ranks them per commodity for dispatch, first in first out by the date
+//! the stock entered storage (kept across warehouse transfers) or, with
+//! `?strategy=fefo`, first expired first out by the receipt's validity. Lots under lien are listed apart as `held`;
+//! lots older than STOCK_AGING_DAYS (default 90) are flagged `aging`.
+//!
+//! Closing a receipt on delivery dispatches its lot; receipts closed by a
+//! transfer are not dispatches. When older lots of the same commodity were
+//! already in the warehouse and free to go at that moment the dispatch
+//! bypassed them: it is logged on close and reported in `bypass_alerts` for
+//! dispatches in the last `?alert_days=` (default 30).
+
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+use crate::warehouse_receipts::{ReceiptStatus, WarehouseReceipt};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const DEFAULT_AGING_DAYS: i64 = 90;
+const DEFAULT_ALERT_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DispatchStrategy {
+    #[default]
+    Fifo,
+    Fefo,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StockLot {
+    pub receipt_number: String,
+    pub batch_id: String,
+    pub commodity: String,
+    pub quantity_kg: f64,
+    pub quality_grade: String,
+    pub issued_at: String,
+    pub stored_since: String,
+    pub valid_until: String,
+    pub age_days: i64,
+    /// Negative once the receipt has lapsed
+    pub days_to_expiry: i64,
+    pub aging: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommodityQueue {
+    pub commodity: String,
+    pub quantity_kg: f64,
+    /// Dispatch order, first lot first
+    pub lots: Vec<StockLot>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BypassAlert {
+    pub dispatched: String,
+    pub dispatched_at: String,
+    pub commodity: String,
+    /// Receipt numbers of the older lots left behind, oldest first
+    pub bypassed: Vec<String>,
+    /// Age of the oldest of them when the dispatch happened
+    pub oldest_age_days: i64,
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// Whether a lien kept the lot from being dispatched at `at`
+fn held_at(receipt: &WarehouseReceipt, at: DateTime<Utc>) -> bool {
+    receipt.lien.as_ref().is_some_and(|lien| {
+        parse_time(&lien.marked_at).is_some_and(|marked| marked <= at)
+            && lien
+                .released_at
+                .as_deref()
+                .and_then(parse_time)
+                .is_none_or(|released| released > at)
+    })
+}
+
+fn same_commodity(a: &WarehouseReceipt, b: &WarehouseReceipt) -> bool {
+    a.commodity.trim().eq_ignore_ascii_case(b.commodity.trim())
+}
+
+fn lot(receipt: &WarehouseReceipt, now: DateTime<Utc>, aging_days: i64) -> StockLot {
+    let age_days = parse_time(receipt.stored_since())
+        .map(|since| (now - since).num_days())
+        .unwrap_or_default();
+    let days_to_expiry = NaiveDate::parse_from_str(&receipt.valid_until, "%Y-%m-%d")
+        .map(|until| (until - now.date_naive()).num_days())
+        .unwrap_or_default();
+    StockLot {
+        receipt_number: receipt.receipt_number.clone(),
+        batch_id: receipt.batch_id.clone(),
+        commodity: receipt.commodity.clone(),
+        quantity_kg: receipt.quantity_kg,
+        quality_grade: receipt.quality_grade.clone(),
+        issued_at: receipt.issued_at.clone(),
+        stored_since: receipt.stored_since().to_string(),
+        valid_until: receipt.valid_until.clone(),
+        age_days,
+        days_to_expiry,
+        aging: age_days > aging_days,
+    }
+}
+
+/// Open lots of `receipts` ranked per commodity, and the lots under lien
+pub fn dispatch_order(
+    receipts: &[&WarehouseReceipt],
+    strategy: DispatchStrategy,
+    now: DateTime<Utc>,
+    aging_days: i64,
+) -> (Vec<CommodityQueue>, Vec<StockLot>) {
+    let mut queues: BTreeMap<String, Vec<&WarehouseReceipt>> = BTreeMap::new();
+    let mut held = Vec::new();
+    for receipt in receipts {
+        match receipt.status {
+            ReceiptStatus::Active => queues
+                .entry(receipt.commodity.trim().to_lowercase())
+                .or_default()
+                .push(receipt),
+            ReceiptStatus::Pledged => held.push(lot(receipt, now, aging_days)),
+            ReceiptStatus::Closed => {}
+        }
+    }
+
+    let queues = queues
+        .into_iter()
+        .map(|(commodity, mut lots)| {
+            // RFC 3339 times from the same clock and ISO dates sort as strings
+            match strategy {
+                DispatchStrategy::Fifo => lots
+                    .sort_by(|a, b| a.stored_since().cmp(b.stored_since()).then(a.id.cmp(&b.id))),
+                DispatchStrategy::Fefo => lots.sort_by(|a, b| {
+                    a.valid_until
+                        .cmp(&b.valid_until)
+                        .then(a.stored_since().cmp(b.stored_since()))
+                        .then(a.id.cmp(&b.id))
+                }),
+            }
+            let lots: Vec<StockLot> = lots.iter().map(|r| lot(r, now, aging_days)).collect();
+            CommodityQueue {
+                commodity,
+                quantity_kg: lots.iter().map(|l| l.quantity_kg).sum(),
+                lots,
+            }
+        })
+        .collect();
+    (queues, held)
+}
+
+/// Older lots of the same commodity that were in the warehouse and free to go
+/// when `dispatched` was dispatched
+pub fn bypassed_by(
+    dispatched: &WarehouseReceipt,
+    receipts: &[&WarehouseReceipt],
+) -> Option<BypassAlert> {
+    let closed_at = parse_time(dispatched.dispatched_at()?)?;
+    let stored_since = parse_time(dispatched.stored_since())?;
+
+    let mut older: Vec<(DateTime<Utc>, &WarehouseReceipt)> = receipts
+        .iter()
+        .filter(|r| r.id != dispatched.id && same_commodity(r, dispatched))
+        .filter(|r| parse_time(&r.issued_at).is_some_and(|issued| issued <= closed_at))
+        .filter_map(|r| Some((parse_time(r.stored_since())?, *r)))
+        .filter(|(stored, r)| {
+            *stored < stored_since
+                && r.closed_at
+                    .as_deref()
+                    .and_then(parse_time)
+                    .is_none_or(|closed| closed > closed_at)
+                && !held_at(r, closed_at)
+        })
+        .collect();
+    older.sort_by_key(|(stored, _)| *stored);
+
+    let (oldest, _) = older.first()?;
+    Some(BypassAlert {
+        dispatched: dispatched.receipt_number.clone(),
+        dispatched_at: closed_at.to_rfc3339(),
+        commodity: dispatched.commodity.clone(),
+        bypassed: older
+            .iter()
+            .map(|(_, r)| r.receipt_number.clone())
+            .collect(),
+        oldest_age_days: (closed_at - *oldest).num_days(),
+    })
+}
+
+fn aging_days() -> i64 {
+    std::env::var("STOCK_AGING_DAYS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|days: &i64| *days > 0)
+        .unwrap_or(DEFAULT_AGING_DAYS)
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct DispatchOrderQuery {
+    #[serde(default)]
+    pub strategy: DispatchStrategy,
+    #[serde(default)]
+    pub commodity: Option<String>,
+    #[serde(default)]
+    pub alert_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DispatchPlan {
+    pub warehouse_id: String,
+    pub strategy: DispatchStrategy,
+    pub generated_at: String,
+    pub aging_days: i64,
+    pub queues: Vec<CommodityQueue>,
+    /// Lots under lien, not dispatchable until it is released
+    pub held: Vec<StockLot>,
+    pub aging_lots: usize,
+    pub bypass_alerts: Vec<BypassAlert>,
+}
+
+/// Recommended dispatch order of a warehouse's stock and recent FIFO bypasses
+pub async fn dispatch_plan(
+    State(state): State<AppState>,
+    Path(warehouse_id): Path<String>,
+    Query(query): Query<DispatchOrderQuery>,
+) -> ApiResult<DispatchPlan> {
+    if state.warehouses.get(&warehouse_id).await.is_none() {
+        return Err(ApiError::not_found(format!(
+            "Warehouse {} not found",
+            warehouse_id
+        )));
+    }
+    let alert_days = query.alert_days.unwrap_or(DEFAULT_ALERT_DAYS);
+    if alert_days < 0 {
+        return Err(ApiError::bad_request("alert_days must not be negative"));
+    }
+
+    let now = Utc::now();
+    let aging_days = aging_days();
+    let stored = state.receipts.at_warehouse(&warehouse_id).await;
+    let receipts: Vec<&WarehouseReceipt> = stored
+        .iter()
+        .filter(|r| {
+            query
+                .commodity
+                .as_deref()
+                .is_none_or(|c| r.commodity.trim().eq_ignore_ascii_case(c.trim()))
+        })
+        .collect();
+
+    let (queues, held) = dispatch_order(&receipts, query.strategy, now, aging_days);
+    let since = now - Duration::days(alert_days);
+    let mut bypass_alerts: Vec<BypassAlert> = receipts
+        .iter()
+        .filter(|r| {
+            r.dispatched_at()
+                .and_then(parse_time)
+                .is_some_and(|closed| closed >= since)
+        })
+        .filter_map(|r| bypassed_by(r, &receipts))
+        .collect();
+    bypass_alerts.sort_by(|a, b| b.dispatched_at.cmp(&a.dispatched_at));
+
+    let aging_lots = queues
+        .iter()
+        .flat_map(|q| &q.lots)
+        .chain(&held)
+        .filter(|l| l.aging)
+        .count();
+    Ok(Json(DispatchPlan {
+        warehouse_id,
+        strategy: query.strategy,
+        generated_at: now.to_rfc3339(),
+        aging_days,
+        queues,
+        held,
+        aging_lots,
+        bypass_alerts,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::warehouse_receipts::{ClosedReason, ReceiptLien};
+
+    fn receipt(id: u64, issued_at: &str, valid_until: &str) -> WarehouseReceipt {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "receipt_number": format!("ENWR-{}", id),
+            "batch_id": format!("B{}", id),
+            "warehouse_id": "WH-1",
+            "depositor": "FPO-1",
+            "holder": "FPO-1",
+            "commodity": "Soybean",
+            "quantity_kg": 1000.0,
+            "quality_grade": "A",
+            "issued_at": issued_at,
+            "valid_until": valid_until,
+            "status": "active",
+        }))
+        .unwrap()
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        parse_time(time).unwrap()
+    }
+
+    #[test]
+    fn test_fifo_and_fefo_order() {
+        let old = receipt(1, "2025-01-10T00:00:00Z", "2025-09-30");
+        let new = receipt(2, "2025-03-01T00:00:00Z", "2025-06-30");
+        let mut pledged = receipt(3, "2024-12-01T00:00:00Z", "2025-05-31");
+        pledged.status = ReceiptStatus::Pledged;
+        let receipts = [&new, &old, &pledged];
+        let now = at("2025-05-01T00:00:00Z");
+
+        let (queues, held) = dispatch_order(&receipts, DispatchStrategy::Fifo, now, 90);
+        assert_eq!(queues.len(), 1);
+        assert_eq!(queues[0].commodity, "soybean");
+        assert_eq!(queues[0].quantity_kg, 2000.0);
+        let order: Vec<&str> = queues[0]
+            .lots
+            .iter()
+            .map(|l| l.receipt_number.as_str())
+            .collect();
+        assert_eq!(order, ["ENWR-1", "ENWR-2"]);
+        assert!(queues[0].lots[0].aging && !queues[0].lots[1].aging);
+        assert_eq!(queues[0].lots[1].days_to_expiry, 60);
+        assert_eq!(held.len(), 1);
+
+        let (queues, _) = dispatch_order(&receipts, DispatchStrategy::Fefo, now, 90);
+        assert_eq!(queues[0].lots[0].receipt_number, "ENWR-2");
+    }
+
+    #[test]
+    fn test_dispatch_bypassing_older_free_lots_is_flagged() {
+        let old = receipt(1, "2025-01-10T00:00:00Z", "2025-09-30");
+        let mut pledged = receipt(2, "2025-01-20T00:00:00Z", "2025-09-30");
+        pledged.lien = Some(ReceiptLien {
+            flag_id: 1,
+            lender: "Bank".to_string(),
+            reference: "L-1".to_string(),
+            amount: 1.0,
+            marked_at: "2025-02-01T00:00:00Z".to_string(),
+            released_at: None,
+            invoked: false,
+        });
+        let mut dispatched = receipt(3, "2025-03-01T00:00:00Z", "2025-09-30");
+        dispatched.status = ReceiptStatus::Closed;
+        dispatched.closed_at = Some("2025-04-10T00:00:00Z".to_string());
+        let receipts = [&old, &pledged, &dispatched];
+
+        let alert = bypassed_by(&dispatched, &receipts).unwrap();
+        assert_eq!(alert.bypassed, ["ENWR-1"]);
+        assert_eq!(alert.oldest_age_days, 90);
+
+        // Not a bypass once the old lot had left first
+        let mut gone = old.clone();
+        gone.closed_at = Some("2025-04-01T00:00:00Z".to_string());
+        assert_eq!(
+            bypassed_by(&dispatched, &[&gone, &pledged, &dispatched]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_transfers_are_not_bypasses() {
+        let old = receipt(1, "2025-01-10T00:00:00Z", "2025-09-30");
+        let mut moved = receipt(2, "2025-03-01T00:00:00Z", "2025-09-30");
+        moved.status = ReceiptStatus::Closed;
+        moved.closed_at = Some("2025-04-10T00:00:00Z".to_string());
+        moved.closed_reason = Some(ClosedReason::Transferred);
+        assert_eq!(bypassed_by(&moved, &[&old, &moved]), None);
+
+        // Older stock transferred in after the dispatch was not left behind
+        let mut dispatched = moved.clone();
+        dispatched.closed_reason = Some(ClosedReason::Dispatched);
+        let mut arrived = receipt(3, "2025-04-20T00:00:00Z", "2025-09-30");
+        arrived.stored_since = Some("2025-01-05T00:00:00Z".to_string());
+        assert_eq!(bypassed_by(&dispatched, &[&arrived, &dispatched]), None);
+        let alert = bypassed_by(&dispatched, &[&old, &arrived, &dispatched]).unwrap();
+        assert_eq!(alert.bypassed, ["ENWR-1"]);
+    }
+}
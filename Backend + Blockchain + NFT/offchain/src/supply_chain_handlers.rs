@@ -1,16 +1,32 @@
-use crate::chain::{hash_bytes, hash_string};
+use crate::batch_ledger;
+use crate::chain::hash_string;
+use crate::degradation;
+use crate::delegation::{require_scope, Scope};
+use crate::did;
+use crate::did_resolver::chain_did;
 use crate::error::{format_hash, format_tx_hash, ipfs_gateway_url, ApiError, ApiResult};
 use crate::farmer_verification::{VerifyMobileRequest, VerifyMobileResponse};
+use crate::hash_schemes::{record_folder_hash, HashRecord, HashScheme};
+use crate::holds::{self, ResultSource, Settlement};
+use crate::photo_evidence::{PhotoEvidence, PhotoPurpose};
+use crate::seals::SealCheck;
+use crate::sku_units::UnitTree;
+use crate::sms::normalize_mobile;
 use crate::state::AppState;
-use alloy::primitives::FixedBytes;
-use axum::{extract::State, Json};
+use crate::video_evidence::{VideoEvidence, VideoPurpose};
+use alloy::primitives::{Address, FixedBytes};
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
 use serde::{Deserialize, Serialize};
 
 
 // ======================== BATCH HELPERS ========================
 
 /// Helper function to generate batch folder path
-fn batch_folder(batch_id: &str) -> String {
+pub(crate) fn batch_folder(batch_id: &str) -> String {
     format!("data/{}", batch_id)
 }
 
@@ -19,6 +35,9 @@ fn batch_folder(batch_id: &str) -> String {
 #[derive(Debug, Serialize)]
 pub struct TxResponse {
     pub tx_hash: String,
+    /// Block explorer page of the transaction, when EXPLORER_BASE_URL is set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
     pub message: String,
 }
 
@@ -26,16 +45,23 @@ pub struct TxResponse {
 
 #[derive(Debug, Deserialize)]
 pub struct RegisterFarmerRequest {
-    pub farmer_did: String,
+    /// Derived from `mobile` when omitted
+    #[serde(default)]
+    pub farmer_did: Option<String>,
     pub crop_id: String,
     pub metadata: serde_json::Value,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mobile: Option<String>,
+    /// Proof token from /api/verification/otp/verify for `mobile`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub otp_token: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct RegisterFarmerResponse {
     pub tx_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
     pub farmer_did: String,
     pub crop_id_hash: String,
     pub metadata_cid: String,
@@ -44,14 +70,34 @@ pub struct RegisterFarmerResponse {
 
 pub async fn register_farmer(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<RegisterFarmerRequest>,
 ) -> ApiResult<RegisterFarmerResponse> {
-    tracing::info!(farmer_did = %payload.farmer_did, "Registering farmer");
+    let chain = state.networks.select(&headers)?;
+    let farmer_did_str = match (payload.farmer_did, payload.mobile.as_deref()) {
+        (Some(did), _) => did,
+        (None, Some(mobile)) => did::farmer_did(mobile),
+        (None, None) => return Err(ApiError::bad_request("farmer_did or mobile is required")),
+    };
+    tracing::info!(farmer_did = %farmer_did_str, "Registering farmer");
 
     // Verify mobile number if provided
     if let Some(mobile) = &payload.mobile {
-        let farmer_verification = state.farmer_verification.lock().await;
-        if !farmer_verification.is_mobile_verified(mobile) {
+        // Ownership of the number must be proven by OTP when required
+        if state.otp.required {
+            let proven = match &payload.otp_token {
+                Some(token) => state.otp.consume_proof(token, mobile).await,
+                None => false,
+            };
+            if !proven {
+                return Err(ApiError::unauthorized(
+                    "Verify the mobile number by OTP and pass otp_token",
+                ));
+            }
+        }
+
+        let farmer_verification = &state.farmer_verification;
+        if !farmer_verification.is_mobile_verified(mobile).await? {
             tracing::warn!(mobile = %mobile, "Mobile number not verified in database");
             return Err(ApiError::bad_request(format!(
                 "Mobile number {} is not verified. Please register first.",
@@ -61,11 +107,12 @@ pub async fn register_farmer(
 
         // Verify mobile-DID pair match
         if !farmer_verification
-            .verify_mobile_did_pair(mobile, &payload.farmer_did)
+            .verify_mobile_did_pair(mobile, &farmer_did_str)
+            .await?
         {
             tracing::error!(
                 mobile = %mobile,
-                farmer_did = %payload.farmer_did,
+                farmer_did = %farmer_did_str,
                 "Mobile number and farmer DID mismatch"
             );
             return Err(ApiError::bad_request(
@@ -81,22 +128,19 @@ pub async fn register_farmer(
         .await
         .map_err(ApiError::ipfs_upload_failed)?;
 
-    let farmer_did: FixedBytes<32> = payload
-        .farmer_did
-        .parse()
-        .map_err(|e| ApiError::invalid_did(e))?;
+    let farmer_did = chain_did(&farmer_did_str).map_err(ApiError::invalid_did)?;
 
     let crop_id_hash = hash_string(&payload.crop_id);
 
-    let receipt = state
-        .blockchain_client
+    let receipt = chain
         .register_farmer(farmer_did, crop_id_hash, metadata_cid.clone())
         .await
         .map_err(ApiError::blockchain_failed)?;
 
     Ok(Json(RegisterFarmerResponse {
         tx_hash: format_tx_hash(receipt.transaction_hash),
-        farmer_did: payload.farmer_did,
+        explorer_url: chain.explorer_url(receipt.transaction_hash),
+        farmer_did: farmer_did_str,
         crop_id_hash: format_hash(crop_id_hash),
         metadata_cid: metadata_cid.clone(),
         ipfs_url: ipfs_gateway_url(&metadata_cid),
@@ -108,6 +152,8 @@ pub async fn register_farmer(
 #[derive(Debug, Deserialize)]
 pub struct FpoPurchaseRequest {
     pub batch_id: String,
+    /// Registered FPO making the purchase (see [`crate::fpo_registry`])
+    pub fpo_id: String,
     pub quantity_kg: f64,
     pub transport_method: String,
     pub travel_distance: f64,
@@ -118,25 +164,50 @@ pub struct FpoPurchaseRequest {
     pub crop_type: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mobile: Option<String>,
+    /// Image CIDs of attested procurement photos (see [`crate::photo_evidence`])
+    #[serde(default)]
+    pub photos: Vec<String>,
+    /// CIDs of weighing videos (see [`crate::video_evidence`])
+    #[serde(default)]
+    pub videos: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct FpoPurchaseResponse {
     pub tx_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
     pub cid: String,
 }
 
 pub async fn fpo_purchase(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<FpoPurchaseRequest>,
 ) -> ApiResult<FpoPurchaseResponse> {
-    tracing::info!(batch_id = %payload.batch_id, "Recording FPO purchase");
+    let chain = state.networks.select(&headers)?;
+    let actor = require_scope(&state, &headers, Scope::FpoPurchase).await?;
+    tracing::info!(
+        batch_id = %payload.batch_id,
+        fpo_id = %payload.fpo_id,
+        actor = %actor.label(),
+        "Recording FPO purchase"
+    );
+    let fpo = state.fpos.require(&payload.fpo_id).await?;
+    actor.check_fpo(&fpo.id)?;
+    if !fpo.admits(&payload.farmer_did) {
+        return Err(ApiError::bad_request(format!(
+            "Farmer {} is not a member of FPO {}",
+            payload.farmer_did, fpo.id
+        )));
+    }
 
     // Verify farmer DID is registered
     {
-        let farmer_verification = state.farmer_verification.lock().await;
+        let farmer_verification = &state.farmer_verification;
         if !farmer_verification
             .is_did_registered(&payload.farmer_did)
+            .await?
         {
             tracing::warn!(farmer_did = %payload.farmer_did, "Farmer DID not found in verification database");
             return Err(ApiError::bad_request(format!(
@@ -149,6 +220,7 @@ pub async fn fpo_purchase(
         if let Some(mobile) = &payload.mobile {
             if !farmer_verification
                 .verify_mobile_did_pair(mobile, &payload.farmer_did)
+                .await?
             {
                 tracing::error!(
                     mobile = %mobile,
@@ -169,6 +241,18 @@ pub async fn fpo_purchase(
         "Recording FPO purchase"
     );
 
+    let photos = state
+        .photos
+        .resolve(&payload.photos, PhotoPurpose::Procurement, &payload.batch_id)
+        .await?;
+    let videos = state
+        .videos
+        .resolve(&payload.videos, VideoPurpose::Procurement, &payload.batch_id)
+        .await?;
+    let grade = state
+        .grades
+        .resolve(&payload.crop_type, &payload.quality_grade)?;
+
     // 1) Decide folder for this batch
     let folder = batch_folder(&payload.batch_id);
 
@@ -182,17 +266,21 @@ pub async fn fpo_purchase(
     };
 
     let product_cost = payload.quantity_kg * payload.price_per_kg;
+    let grade_premium = product_cost * grade.premium_percent / 100.0;
     let transport_cost = payload.travel_distance * transport_rates;
-    let total_cost = product_cost + transport_cost;
+    let total_cost = product_cost + grade_premium + transport_cost;
 
     // 3) Create comprehensive metadata for IPFS
     let metadata = serde_json::json!({
         "transaction_type": "fpo_purchase",
         "timestamp": chrono::Utc::now().to_rfc3339(),
+        "submitted_by": actor.label(),
+        "fpo": fpo.metadata_entry(),
         "batch_info": {
             "batch_id": payload.batch_id,
             "quantity_kg": payload.quantity_kg,
-            "quality_grade": payload.quality_grade
+            "quality_grade": grade.code,
+            "grade_score": grade.score
         },
         "farmer_info": {
             "farmer_did": payload.farmer_did,
@@ -208,9 +296,13 @@ pub async fn fpo_purchase(
         "pricing": {
             "price_per_kg": payload.price_per_kg,
             "product_cost": product_cost,
+            "grade_premium_percent": grade.premium_percent,
+            "grade_premium": grade_premium,
             "transport_cost": transport_cost,
             "total_cost": total_cost
         },
+        "photos": photos.iter().map(PhotoEvidence::metadata_entry).collect::<Vec<_>>(),
+        "videos": videos.iter().map(VideoEvidence::metadata_entry).collect::<Vec<_>>(),
         "verification": {
             "blockchain_hash": "pending",
             "ipfs_stored": true
@@ -226,19 +318,15 @@ pub async fn fpo_purchase(
     // 5) Upload entire folder -> get root CID for this batch view
     let metadata_cid = state
         .ipfs_client
-        .upload_folder(&folder)
+        .upload_folder_or_defer(&folder)
         .await
         .map_err(ApiError::ipfs_upload_failed)?;
 
     // 6) Hashes + chain call
     let batch_hash = hash_string(&payload.batch_id);
-    let farmer_did: FixedBytes<32> = payload
-        .farmer_did
-        .parse()
-        .map_err(|e| ApiError::invalid_did(e))?;
+    let farmer_did = chain_did(&payload.farmer_did).map_err(ApiError::invalid_did)?;
 
-    let receipt = state
-        .blockchain_client
+    let receipt = chain
         .fpo_purchase(batch_hash, farmer_did, metadata_cid.clone())
         .await
         .map_err(ApiError::blockchain_failed)?;
@@ -253,14 +341,115 @@ pub async fn fpo_purchase(
 
     Ok(Json(FpoPurchaseResponse {
         tx_hash,
+        explorer_url: chain.explorer_url(receipt.transaction_hash),
         cid: metadata_cid.clone(),
     }))
 }
 
+// ======================== OWNERSHIP TRANSFERS ========================
+
+/// Custody change after the FPO purchase (which records the first one)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferType {
+    Warehouse,
+    Processor,
+    Retail,
+}
+
+impl TransferType {
+    /// `transferType` code of the contract's OwnershipTransfer event
+    pub fn code(self) -> u8 {
+        match self {
+            TransferType::Warehouse => 2,
+            TransferType::Processor => 3,
+            TransferType::Retail => 4,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OwnershipTransferRequest {
+    pub batch_id: String,
+    /// DID of the custodian handing the batch over
+    pub from_did: String,
+    /// Address of the new custodian
+    pub to_address: String,
+    pub transfer_type: TransferType,
+    /// Receipts, weight slips, quality notes, etc.
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OwnershipTransferResponse {
+    pub tx_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
+    pub cid: String,
+}
+
+pub async fn record_ownership_transfer(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<OwnershipTransferRequest>,
+) -> ApiResult<OwnershipTransferResponse> {
+    let chain = state.networks.select(&headers)?;
+    tracing::info!(
+        batch_id = %payload.batch_id,
+        transfer_type = ?payload.transfer_type,
+        "Recording ownership transfer"
+    );
+
+    if batch_ledger::fpo_purchase(&payload.batch_id)?.is_none() {
+        return Err(ApiError::not_found(format!(
+            "Batch {} has no FPO purchase record",
+            payload.batch_id
+        )));
+    }
+    let from_did = chain_did(&payload.from_did).map_err(ApiError::invalid_did)?;
+    let to_address: Address = payload
+        .to_address
+        .parse()
+        .map_err(|e| ApiError::bad_request(format!("Invalid to_address: {}", e)))?;
+
+    let metadata = serde_json::json!({
+        "batch_id": payload.batch_id,
+        "from_did": payload.from_did,
+        "to_address": format!("{:?}", to_address),
+        "transfer_type": payload.transfer_type,
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "details": payload.metadata,
+    });
+    let metadata_cid = state
+        .ipfs_client
+        .upload_json(&metadata)
+        .await
+        .map_err(ApiError::ipfs_upload_failed)?;
+
+    let receipt = chain
+        .record_ownership_transfer(
+            hash_string(&payload.batch_id),
+            from_did,
+            to_address,
+            payload.transfer_type.code(),
+            metadata_cid.clone(),
+        )
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+
+    Ok(Json(OwnershipTransferResponse {
+        tx_hash: format_tx_hash(receipt.transaction_hash),
+        explorer_url: chain.explorer_url(receipt.transaction_hash),
+        cid: metadata_cid,
+    }))
+}
+
 // ======================== STAGE 3: WAREHOUSE STORAGE ========================
 
 #[derive(Debug, Deserialize)]
 pub struct WarehouseUpdateRequest {
+    /// ID of a registered warehouse (see [`crate::warehouse_registry`])
     pub warehouse_id: String,
     pub iot_data: serde_json::Value,
 }
@@ -268,43 +457,136 @@ pub struct WarehouseUpdateRequest {
 #[derive(Debug, Serialize)]
 pub struct WarehouseUpdateResponse {
     pub tx_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
     pub warehouse_id: String,
     pub state_hash: String,
+    pub hash_scheme: HashScheme,
     pub metadata_cid: String,
     pub ipfs_url: String,
 }
 
 pub async fn update_warehouse_state(
     State(state): State<AppState>,
-    Json(payload): Json<WarehouseUpdateRequest>,
+    headers: HeaderMap,
+    Json(mut payload): Json<WarehouseUpdateRequest>,
 ) -> ApiResult<WarehouseUpdateResponse> {
+    let chain = state.networks.select(&headers)?;
     tracing::info!(warehouse_id = %payload.warehouse_id, "Updating warehouse state");
 
+    let warehouse = state.warehouses.require(&payload.warehouse_id).await?;
+    let iot_data = payload
+        .iot_data
+        .as_object_mut()
+        .ok_or_else(|| ApiError::bad_request("iot_data must be a JSON object"))?;
+    iot_data.insert("warehouse".to_string(), warehouse.metadata_entry());
+
     let metadata_cid = state
         .ipfs_client
         .upload_json(&payload.iot_data)
         .await
         .map_err(ApiError::ipfs_upload_failed)?;
 
-    let iot_json = serde_json::to_vec(&payload.iot_data).map_err(ApiError::json_failed)?;
-    let state_hash = hash_bytes(&iot_json);
+    let state_hash = HashRecord::of_json(&payload.iot_data).map_err(ApiError::from)?;
     let warehouse_id = hash_string(&payload.warehouse_id);
 
-    let receipt = state
-        .blockchain_client
-        .update_warehouse_state(warehouse_id, state_hash, metadata_cid.clone())
+    let receipt = chain
+        .update_warehouse_state(warehouse_id, state_hash.hash, metadata_cid.clone())
         .await
         .map_err(ApiError::blockchain_failed)?;
 
     Ok(Json(WarehouseUpdateResponse {
         tx_hash: format_tx_hash(receipt.transaction_hash),
+        explorer_url: chain.explorer_url(receipt.transaction_hash),
         warehouse_id: payload.warehouse_id,
-        state_hash: format_hash(state_hash),
+        state_hash: format_hash(state_hash.hash),
+        hash_scheme: state_hash.scheme,
         metadata_cid: metadata_cid.clone(),
         ipfs_url: ipfs_gateway_url(&metadata_cid),
     }))
 }
 
+#[derive(Debug, Serialize)]
+pub struct WarehouseStateResponse {
+    pub warehouse_id: String,
+    pub warehouse_hash: String,
+    pub state_hash: String,
+    pub last_updated: u64,
+    pub metadata_cid: Option<String>,
+    pub ipfs_url: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    /// Why `metadata` is missing, when it could not be resolved or fetched
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+pub async fn get_warehouse_state(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(warehouse_id): Path<String>,
+) -> ApiResult<WarehouseStateResponse> {
+    let chain = state.networks.select(&headers)?;
+    let warehouse_hash = hash_string(&warehouse_id);
+
+    let (state_hash, last_updated) = chain
+        .get_warehouse_state(warehouse_hash)
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+
+    if last_updated == 0 {
+        return Err(ApiError::not_found(format!(
+            "No state recorded for warehouse {}",
+            warehouse_id
+        )));
+    }
+
+    // Metadata is best effort: the on-chain state is returned even if it cannot be resolved
+    let mut warnings = Vec::new();
+    let metadata_cid = match chain
+        .warehouse_metadata_cid(warehouse_hash, last_updated)
+        .await
+    {
+        Ok(cid) => cid.filter(|cid| !cid.is_empty()),
+        Err(e) => {
+            tracing::warn!(
+                warehouse_id = %warehouse_id,
+                error = %format!("{:#}", e),
+                "Could not resolve warehouse metadata CID"
+            );
+            warnings.push(format!("Metadata CID could not be resolved: {:#}", e));
+            None
+        }
+    };
+
+    let metadata = match &metadata_cid {
+        Some(cid) => match state.ipfs_client.fetch_json(cid).await {
+            Ok(metadata) => Some(metadata),
+            Err(e) => {
+                tracing::warn!(
+                    cid = %cid,
+                    error = %format!("{:#}", e),
+                    "Could not fetch warehouse metadata"
+                );
+                degradation::note(degradation::IPFS_PARTIAL);
+                warnings.push(format!("Metadata {} could not be fetched from IPFS: {:#}", cid, e));
+                None
+            }
+        },
+        None => None,
+    };
+
+    Ok(Json(WarehouseStateResponse {
+        warehouse_id,
+        warehouse_hash: format_hash(warehouse_hash),
+        state_hash: format_hash(state_hash),
+        last_updated,
+        ipfs_url: metadata_cid.as_deref().map(ipfs_gateway_url),
+        metadata_cid,
+        metadata,
+        warnings,
+    }))
+}
+
 // Batch warehouse update
 #[derive(Debug, Deserialize)]
 pub struct BatchWarehouseUpdate {
@@ -319,29 +601,31 @@ pub struct BatchWarehouseUpdateRequest {
 
 pub async fn batch_update_warehouse(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<BatchWarehouseUpdateRequest>,
 ) -> ApiResult<TxResponse> {
+    let chain = state.networks.select(&headers)?;
     tracing::info!(count = payload.updates.len(), "Batch updating warehouses");
 
     let mut warehouse_ids = Vec::with_capacity(payload.updates.len());
     let mut state_hashes = Vec::with_capacity(payload.updates.len());
 
     for update in &payload.updates {
+        state.warehouses.require(&update.warehouse_id).await?;
         let warehouse_id = hash_string(&update.warehouse_id);
-        let iot_json = serde_json::to_vec(&update.iot_data).map_err(ApiError::json_failed)?;
-        let state_hash = hash_bytes(&iot_json);
+        let state_hash = HashRecord::of_json(&update.iot_data).map_err(ApiError::from)?;
         warehouse_ids.push(warehouse_id);
-        state_hashes.push(state_hash);
+        state_hashes.push(state_hash.hash);
     }
 
-    let receipt = state
-        .blockchain_client
+    let receipt = chain
         .batch_update_warehouse(warehouse_ids, state_hashes)
         .await
         .map_err(ApiError::blockchain_failed)?;
 
     Ok(Json(TxResponse {
         tx_hash: format_tx_hash(receipt.transaction_hash),
+        explorer_url: chain.explorer_url(receipt.transaction_hash),
         message: format!("Successfully updated {} warehouses", payload.updates.len()),
     }))
 }
@@ -354,23 +638,58 @@ pub struct LogisticsUpdateRequest {
     pub location: String,
     pub is_delivered: bool,
     pub gps_data: serde_json::Value,
+    /// QR payloads of the seals scanned at this checkpoint (see [`crate::seals`])
+    #[serde(default)]
+    pub seals: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct LogisticsUpdateResponse {
     pub tx_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
     pub shipment_id: String,
     pub location_hash: String,
     pub metadata_cid: String,
     pub ipfs_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seal_check: Option<SealCheck>,
 }
 
 pub async fn record_logistics(
     State(state): State<AppState>,
-    Json(payload): Json<LogisticsUpdateRequest>,
+    headers: HeaderMap,
+    Json(mut payload): Json<LogisticsUpdateRequest>,
 ) -> ApiResult<LogisticsUpdateResponse> {
+    let chain = state.networks.select(&headers)?;
     tracing::info!(shipment_id = %payload.shipment_id, "Recording logistics milestone");
 
+    let Some(gps_data) = payload.gps_data.as_object_mut() else {
+        return Err(ApiError::bad_request("gps_data must be a JSON object"));
+    };
+    let carrier = state.transport.carrier(&payload.shipment_id).await?;
+    gps_data.insert(
+        "carrier".to_string(),
+        serde_json::to_value(&carrier).map_err(ApiError::json_failed)?,
+    );
+
+    let seal_check = state
+        .seals
+        .record_checkpoint(
+            &payload.shipment_id,
+            &payload.location,
+            payload.is_delivered,
+            payload.seals.as_deref(),
+        )
+        .await
+        .map_err(ApiError::from)?;
+    if let (Some(check), Some(gps_data)) = (&seal_check, payload.gps_data.as_object_mut()) {
+        gps_data.insert(
+            "seal_check".to_string(),
+            serde_json::to_value(check).map_err(ApiError::json_failed)?,
+        );
+    }
+
     let metadata_cid = state
         .ipfs_client
         .upload_json(&payload.gps_data)
@@ -380,8 +699,7 @@ pub async fn record_logistics(
     let shipment_id = hash_string(&payload.shipment_id);
     let location_hash = hash_string(&payload.location);
 
-    let receipt = state
-        .blockchain_client
+    let receipt = chain
         .record_logistics(
             shipment_id,
             location_hash,
@@ -390,13 +708,94 @@ pub async fn record_logistics(
         )
         .await
         .map_err(ApiError::blockchain_failed)?;
+    if payload.is_delivered {
+        state.transport.mark_delivered(&payload.shipment_id).await?;
+    }
 
     Ok(Json(LogisticsUpdateResponse {
         tx_hash: format_tx_hash(receipt.transaction_hash),
+        explorer_url: chain.explorer_url(receipt.transaction_hash),
         shipment_id: payload.shipment_id,
         location_hash: format_hash(location_hash),
         metadata_cid: metadata_cid.clone(),
         ipfs_url: ipfs_gateway_url(&metadata_cid),
+        seal_check,
+    }))
+}
+
+// Batch logistics milestones (single transaction, no per-milestone metadata)
+#[derive(Debug, Deserialize)]
+pub struct BatchLogisticsMilestone {
+    pub shipment_id: String,
+    pub location: String,
+    pub is_delivered: bool,
+    #[serde(default)]
+    pub seals: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchLogisticsRequest {
+    pub milestones: Vec<BatchLogisticsMilestone>,
+}
+
+pub async fn batch_record_logistics(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<BatchLogisticsRequest>,
+) -> ApiResult<TxResponse> {
+    let chain = state.networks.select(&headers)?;
+    if payload.milestones.is_empty() {
+        return Err(ApiError::bad_request("milestones must not be empty"));
+    }
+
+    tracing::info!(
+        count = payload.milestones.len(),
+        "Batch recording logistics milestones"
+    );
+
+    let mut shipment_ids = Vec::with_capacity(payload.milestones.len());
+    let mut location_hashes = Vec::with_capacity(payload.milestones.len());
+    let mut delivery_statuses = Vec::with_capacity(payload.milestones.len());
+
+    for milestone in &payload.milestones {
+        if milestone.shipment_id.is_empty() || milestone.location.is_empty() {
+            return Err(ApiError::bad_request(
+                "Every milestone needs a shipment_id and location",
+            ));
+        }
+        state.transport.carrier(&milestone.shipment_id).await?;
+        shipment_ids.push(hash_string(&milestone.shipment_id));
+        location_hashes.push(hash_string(&milestone.location));
+        delivery_statuses.push(milestone.is_delivered);
+    }
+    for milestone in &payload.milestones {
+        state
+            .seals
+            .record_checkpoint(
+                &milestone.shipment_id,
+                &milestone.location,
+                milestone.is_delivered,
+                milestone.seals.as_deref(),
+            )
+            .await
+            .map_err(ApiError::from)?;
+    }
+
+    let receipt = chain
+        .batch_record_logistics(shipment_ids, location_hashes, delivery_statuses)
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+    for milestone in payload.milestones.iter().filter(|m| m.is_delivered) {
+        state.transport.mark_delivered(&milestone.shipment_id).await?;
+    }
+
+    Ok(Json(TxResponse {
+        tx_hash: format_tx_hash(receipt.transaction_hash),
+        explorer_url: chain.explorer_url(receipt.transaction_hash),
+        message: format!(
+            "Successfully recorded {} logistics milestones",
+            payload.milestones.len()
+        ),
     }))
 }
 
@@ -406,14 +805,25 @@ pub async fn record_logistics(
 pub struct ProcessBatchRequest {
     pub input_batch_id: String,
     pub output_batch_ids: Vec<String>,
+    /// Registered facility running the process (see
+    /// [`crate::processor_registry`])
+    pub processor_id: String,
+    /// Must hold a `process_type` the processor is registered for
     pub process_metadata: serde_json::Value,
+    /// CIDs of seal-breaking videos, added to the process metadata as
+    /// `videos` (see [`crate::video_evidence`])
+    #[serde(default)]
+    pub videos: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ProcessBatchResponse {
     pub tx_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
     pub input_batch_hash: String,
     pub transform_hash: String,
+    pub hash_scheme: HashScheme,
     pub output_batch_hashes: Vec<String>,
     pub metadata_cid: String,
     pub ipfs_url: String,
@@ -421,9 +831,44 @@ pub struct ProcessBatchResponse {
 
 pub async fn process_batch(
     State(state): State<AppState>,
-    Json(payload): Json<ProcessBatchRequest>,
+    headers: HeaderMap,
+    Json(mut payload): Json<ProcessBatchRequest>,
 ) -> ApiResult<ProcessBatchResponse> {
-    tracing::info!(input_batch = %payload.input_batch_id, "Processing batch");
+    let chain = state.networks.select(&headers)?;
+    tracing::info!(
+        input_batch = %payload.input_batch_id,
+        processor_id = %payload.processor_id,
+        "Processing batch"
+    );
+    state
+        .financing
+        .ensure_unencumbered(&payload.input_batch_id)
+        .await?;
+    state
+        .acceptance
+        .ensure_accepted(&payload.input_batch_id)
+        .await?;
+    let Some(metadata) = payload.process_metadata.as_object_mut() else {
+        return Err(ApiError::bad_request("process_metadata must be a JSON object"));
+    };
+    let Some(process_type) = metadata.get("process_type").and_then(|v| v.as_str()) else {
+        return Err(ApiError::bad_request("process_metadata.process_type is required"));
+    };
+    let processor = state
+        .processors
+        .require_capable(&payload.processor_id, process_type)
+        .await?;
+    metadata.insert("processor".to_string(), processor.metadata_entry());
+    if !payload.videos.is_empty() {
+        let videos = state
+            .videos
+            .resolve(&payload.videos, VideoPurpose::Processing, &payload.input_batch_id)
+            .await?;
+        metadata.insert(
+            "videos".to_string(),
+            videos.iter().map(VideoEvidence::metadata_entry).collect(),
+        );
+    }
 
     // 1) Folder for this batch
     let folder = batch_folder(&payload.input_batch_id);
@@ -433,11 +878,14 @@ pub async fn process_batch(
         .ipfs_client
         .write_json_to_folder(&folder, "processing.json", &payload.process_metadata)
         .map_err(ApiError::ipfs_upload_failed)?;
+    let transform = HashRecord::of_json(&payload.process_metadata).map_err(ApiError::from)?;
+    record_folder_hash(&folder, "processing.json", transform)
+        .map_err(ApiError::ipfs_upload_failed)?;
 
     // 3) Upload entire folder -> root CID reflects all previous files for this batch
     let metadata_cid = state
         .ipfs_client
-        .upload_folder(&folder)
+        .upload_folder_or_defer(&folder)
         .await
         .map_err(ApiError::ipfs_upload_failed)?;
 
@@ -448,13 +896,9 @@ pub async fn process_batch(
         .iter()
         .map(|id| hash_string(id))
         .collect();
+    let transform_hash = transform.hash;
 
-    let metadata_json =
-        serde_json::to_vec(&payload.process_metadata).map_err(ApiError::json_failed)?;
-    let transform_hash = hash_bytes(&metadata_json);
-
-    let receipt = state
-        .blockchain_client
+    let receipt = chain
         .process_batch(
             input_batch_hash,
             transform_hash,
@@ -466,9 +910,11 @@ pub async fn process_batch(
 
     Ok(Json(ProcessBatchResponse {
         tx_hash: format_tx_hash(receipt.transaction_hash),
+        explorer_url: chain.explorer_url(receipt.transaction_hash),
         input_batch_hash: format_hash(input_batch_hash),
         transform_hash: format_hash(transform_hash),
-        output_batch_hashes: output_batch_hashes.iter().map(|h| format_hash(h)).collect(),
+        hash_scheme: transform.scheme,
+        output_batch_hashes: output_batch_hashes.iter().map(format_hash).collect(),
         metadata_cid: metadata_cid.clone(),
         ipfs_url: ipfs_gateway_url(&metadata_cid),
     }))
@@ -487,18 +933,33 @@ pub struct CreateSkuRequest {
 #[derive(Debug, Serialize)]
 pub struct CreateSkuResponse {
     pub tx_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
     pub sku_id: String,
     pub parent_batch_hash: String,
     pub merkle_root: String,
+    pub tree_scheme: String,
+    pub hash_scheme: HashScheme,
+    pub unit_count: usize,
     pub metadata_cid: String,
     pub ipfs_url: String,
 }
 
 pub async fn create_sku(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<CreateSkuRequest>,
 ) -> ApiResult<CreateSkuResponse> {
+    let chain = state.networks.select(&headers)?;
     tracing::info!(sku_id = %payload.sku_id, "Creating SKU");
+    state
+        .financing
+        .ensure_unencumbered(&payload.parent_batch_id)
+        .await?;
+
+    // Validate the units before anything is written
+    let unit_tree = UnitTree::build(&payload.sku_id, &payload.parent_batch_id, payload.unit_ids)
+        .map_err(|e| ApiError::bad_request(format!("{:#}", e)))?;
 
     // 1) Use parent batch as folder (e.g. batch-0)
     let folder = batch_folder(&payload.parent_batch_id);
@@ -513,34 +974,32 @@ pub async fn create_sku(
     // 3) Upload entire folder -> updated root CID for this batch
     let metadata_cid = state
         .ipfs_client
-        .upload_folder(&folder)
+        .upload_folder_or_defer(&folder)
         .await
         .map_err(ApiError::ipfs_upload_failed)?;
 
-    // 4) Merkle + chain call
-    let unit_hashes: Vec<FixedBytes<32>> =
-        payload.unit_ids.iter().map(|id| hash_string(id)).collect();
-
-    let mut merkle_data = Vec::with_capacity(unit_hashes.len() * 32);
-    for hash in &unit_hashes {
-        merkle_data.extend_from_slice(hash.as_slice());
-    }
-    let merkle_root = hash_bytes(&merkle_data);
-
+    // 4) Chain call with the units' Merkle root
+    let merkle_root = unit_tree.root;
     let sku_id = hash_string(&payload.sku_id);
     let parent_batch_hash = hash_string(&payload.parent_batch_id);
 
-    let receipt = state
-        .blockchain_client
+    let receipt = chain
         .create_sku(sku_id, parent_batch_hash, merkle_root, metadata_cid.clone())
         .await
         .map_err(ApiError::blockchain_failed)?;
 
+    // 5) Keep the leaves so unit proofs can be produced later
+    unit_tree.save().map_err(ApiError::from)?;
+
     Ok(Json(CreateSkuResponse {
         tx_hash: format_tx_hash(receipt.transaction_hash),
+        explorer_url: chain.explorer_url(receipt.transaction_hash),
         sku_id: payload.sku_id,
         parent_batch_hash: format_hash(parent_batch_hash),
         merkle_root: format_hash(merkle_root),
+        tree_scheme: unit_tree.tree_scheme,
+        hash_scheme: unit_tree.hash_scheme,
+        unit_count: unit_tree.unit_ids.len(),
         metadata_cid: metadata_cid.clone(),
         ipfs_url: ipfs_gateway_url(&metadata_cid),
     }))
@@ -552,43 +1011,67 @@ pub async fn create_sku(
 pub struct ReportFraudRequest {
     pub sku_id: String,
     pub evidence: serde_json::Value,
+    /// Image CIDs of attested fraud photos, added to the evidence as `photos`
+    #[serde(default)]
+    pub photos: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct ReportFraudResponse {
     pub tx_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
     pub sku_id: String,
     pub evidence_hash: String,
+    pub hash_scheme: HashScheme,
     pub evidence_cid: String,
     pub ipfs_url: String,
 }
 
 pub async fn report_fraud(
     State(state): State<AppState>,
-    Json(payload): Json<ReportFraudRequest>,
+    headers: HeaderMap,
+    Json(mut payload): Json<ReportFraudRequest>,
 ) -> ApiResult<ReportFraudResponse> {
+    let chain = state.networks.select(&headers)?;
     tracing::info!(sku_id = %payload.sku_id, "Reporting fraud");
 
+    if !payload.photos.is_empty() {
+        let photos = state
+            .photos
+            .resolve(&payload.photos, PhotoPurpose::Fraud, &payload.sku_id)
+            .await?;
+        let Some(evidence) = payload.evidence.as_object_mut() else {
+            return Err(ApiError::bad_request(
+                "evidence must be a JSON object when photos are attached",
+            ));
+        };
+        evidence.insert(
+            "photos".to_string(),
+            photos.iter().map(PhotoEvidence::metadata_entry).collect(),
+        );
+    }
+
     let evidence_cid = state
         .ipfs_client
         .upload_json(&payload.evidence)
         .await
         .map_err(ApiError::ipfs_upload_failed)?;
 
-    let evidence_json = serde_json::to_vec(&payload.evidence).map_err(ApiError::json_failed)?;
-    let evidence_hash = hash_bytes(&evidence_json);
+    let evidence = HashRecord::of_json(&payload.evidence).map_err(ApiError::from)?;
     let sku_id = hash_string(&payload.sku_id);
 
-    let receipt = state
-        .blockchain_client
-        .report_fraud(sku_id, evidence_hash, evidence_cid.clone())
+    let receipt = chain
+        .report_fraud(sku_id, evidence.hash, evidence_cid.clone())
         .await
         .map_err(ApiError::blockchain_failed)?;
 
     Ok(Json(ReportFraudResponse {
         tx_hash: format_tx_hash(receipt.transaction_hash),
+        explorer_url: chain.explorer_url(receipt.transaction_hash),
         sku_id: payload.sku_id,
-        evidence_hash: format_hash(evidence_hash),
+        evidence_hash: format_hash(evidence.hash),
+        hash_scheme: evidence.scheme,
         evidence_cid: evidence_cid.clone(),
         ipfs_url: ipfs_gateway_url(&evidence_cid),
     }))
@@ -606,14 +1089,18 @@ pub struct CommitAiScoreRequest {
 #[derive(Debug, Serialize)]
 pub struct CommitAiScoreResponse {
     pub tx_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
     pub batch_id: String,
     pub commit_hash: String,
 }
 
 pub async fn commit_ai_score(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<CommitAiScoreRequest>,
 ) -> ApiResult<CommitAiScoreResponse> {
+    let chain = state.networks.select(&headers)?;
     tracing::info!(batch_id = %payload.batch_id, "Committing AI score");
 
     let batch_hash = hash_string(&payload.batch_id);
@@ -628,14 +1115,14 @@ pub async fn commit_ai_score(
 
     let commit_hash = crate::chain::generate_commit_hash(reveal_hash, nonce);
 
-    let receipt = state
-        .blockchain_client
+    let receipt = chain
         .commit_ai_score(batch_hash, commit_hash)
         .await
         .map_err(ApiError::blockchain_failed)?;
 
     Ok(Json(CommitAiScoreResponse {
         tx_hash: format_tx_hash(receipt.transaction_hash),
+        explorer_url: chain.explorer_url(receipt.transaction_hash),
         batch_id: payload.batch_id,
         commit_hash: format_hash(commit_hash),
     }))
@@ -651,56 +1138,208 @@ pub struct RevealAiScoreRequest {
 #[derive(Debug, Serialize)]
 pub struct RevealAiScoreResponse {
     pub tx_hash: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explorer_url: Option<String>,
     pub batch_id: String,
     pub reveal_hash: String,
+    pub hash_scheme: HashScheme,
     pub metadata_cid: String,
     pub ipfs_url: String,
+    /// Settlement after the AI score hold rules ran
+    pub settlement: Option<Settlement>,
 }
 
 pub async fn reveal_ai_score(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<RevealAiScoreRequest>,
 ) -> ApiResult<RevealAiScoreResponse> {
+    let chain = state.networks.select(&headers)?;
     tracing::info!(batch_id = %payload.batch_id, "Revealing AI score");
 
     // 1) Use batch folder
     let folder = batch_folder(&payload.batch_id);
 
-    // 2) Save AI score JSON in the batch folder
+    // 2) Save AI score JSON in the batch folder, with the scheme of its
+    //    reveal hash (JSON_HASH_SCHEME must match the one used for the commit)
     state
         .ipfs_client
         .write_json_to_folder(&folder, "ai_score.json", &payload.score_data)
         .map_err(ApiError::ipfs_upload_failed)?;
+    let reveal = HashRecord::of_json(&payload.score_data).map_err(ApiError::from)?;
+    record_folder_hash(&folder, "ai_score.json", reveal).map_err(ApiError::ipfs_upload_failed)?;
+    let reveal_hash = reveal.hash;
 
     // 3) Upload full folder -> one CID
     let metadata_cid = state
         .ipfs_client
-        .upload_folder(&folder)
+        .upload_folder_or_defer(&folder)
         .await
         .map_err(ApiError::ipfs_upload_failed)?;
 
-    // 4) Compute reveal hash and call chain
-    let score_json = serde_json::to_vec(&payload.score_data).map_err(ApiError::json_failed)?;
-    let reveal_hash = hash_bytes(&score_json);
-
+    // 4) Call chain
     let batch_hash = hash_string(&payload.batch_id);
     let nonce: FixedBytes<32> = payload
         .nonce
         .parse()
         .map_err(|e| ApiError::invalid_hash("nonce", e))?;
 
-    let receipt = state
-        .blockchain_client
+    let receipt = chain
         .reveal_ai_score(batch_hash, reveal_hash, nonce, metadata_cid.clone())
         .await
         .map_err(ApiError::blockchain_failed)?;
 
+    let settlement = holds::evaluate_logged(
+        &state,
+        &payload.batch_id,
+        ResultSource::AiScore,
+        &payload.score_data,
+    )
+    .await;
+
     Ok(Json(RevealAiScoreResponse {
         tx_hash: format_tx_hash(receipt.transaction_hash),
+        explorer_url: chain.explorer_url(receipt.transaction_hash),
         batch_id: payload.batch_id,
         reveal_hash: format_hash(reveal_hash),
+        hash_scheme: reveal.scheme,
         metadata_cid: metadata_cid.clone(),
         ipfs_url: ipfs_gateway_url(&metadata_cid),
+        settlement,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AiScoreStatus {
+    /// Nothing committed for this batch yet
+    Pending,
+    /// Commit recorded, reveal still outstanding
+    Committed,
+    Revealed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AiScoreStatusResponse {
+    pub batch_id: String,
+    pub batch_hash: String,
+    pub status: AiScoreStatus,
+    pub commit_hash: String,
+    pub reveal_hash: String,
+    pub committed_at: u64,
+    pub revealed_at: u64,
+}
+
+pub async fn get_ai_score(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(batch_id): Path<String>,
+) -> ApiResult<AiScoreStatusResponse> {
+    let chain = state.networks.select(&headers)?;
+    let batch_hash = hash_string(&batch_id);
+
+    let (commit_hash, reveal_hash, committed_at, revealed_at) = chain
+        .get_ai_score(batch_hash)
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+
+    let status = if revealed_at > 0 {
+        AiScoreStatus::Revealed
+    } else if committed_at > 0 {
+        AiScoreStatus::Committed
+    } else {
+        AiScoreStatus::Pending
+    };
+
+    Ok(Json(AiScoreStatusResponse {
+        batch_id,
+        batch_hash: format_hash(batch_hash),
+        status,
+        commit_hash: format_hash(commit_hash),
+        reveal_hash: format_hash(reveal_hash),
+        committed_at,
+        revealed_at,
+    }))
+}
+
+// ======================== STAGE 9: LAB RESULTS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct LabResultRequest {
+    pub batch_id: String,
+    pub lab_name: String,
+    #[serde(default)]
+    pub report_id: Option<String>,
+    /// Measured values, e.g. `{"aflatoxin_ppb": 12.5, "moisture_pct": 6.8}`
+    pub results: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LabResultResponse {
+    pub batch_id: String,
+    pub result_hash: String,
+    pub hash_scheme: HashScheme,
+    pub metadata_cid: String,
+    pub ipfs_url: String,
+    /// Settlement after the lab result hold rules ran
+    pub settlement: Option<Settlement>,
+}
+
+pub async fn record_lab_result(
+    State(state): State<AppState>,
+    Json(payload): Json<LabResultRequest>,
+) -> ApiResult<LabResultResponse> {
+    tracing::info!(batch_id = %payload.batch_id, lab = %payload.lab_name, "Recording lab result");
+
+    if batch_ledger::fpo_purchase(&payload.batch_id)?.is_none() {
+        return Err(ApiError::not_found(format!(
+            "No FPO purchase for batch {}",
+            payload.batch_id
+        )));
+    }
+    if !payload.results.is_object() {
+        return Err(ApiError::bad_request("results must be a JSON object"));
+    }
+
+    // 1) Save the lab report in the batch folder next to the other stages
+    let folder = batch_folder(&payload.batch_id);
+    let record = serde_json::json!({
+        "batch_id": payload.batch_id,
+        "lab_name": payload.lab_name,
+        "report_id": payload.report_id,
+        "results": payload.results,
+        "received_at": chrono::Utc::now().to_rfc3339(),
+    });
+    state
+        .ipfs_client
+        .write_json_to_folder(&folder, "lab_result.json", &record)
+        .map_err(ApiError::ipfs_upload_failed)?;
+    let hash = HashRecord::of_json(&record).map_err(ApiError::from)?;
+    record_folder_hash(&folder, "lab_result.json", hash).map_err(ApiError::ipfs_upload_failed)?;
+
+    // 2) Upload full folder -> one CID
+    let metadata_cid = state
+        .ipfs_client
+        .upload_folder(&folder)
+        .await
+        .map_err(ApiError::ipfs_upload_failed)?;
+
+    // 3) Run the lab result hold rules
+    let settlement = holds::evaluate_logged(
+        &state,
+        &payload.batch_id,
+        ResultSource::LabResult,
+        &payload.results,
+    )
+    .await;
+
+    Ok(Json(LabResultResponse {
+        batch_id: payload.batch_id,
+        result_hash: format_hash(hash.hash),
+        hash_scheme: hash.scheme,
+        metadata_cid: metadata_cid.clone(),
+        ipfs_url: ipfs_gateway_url(&metadata_cid),
+        settlement,
     }))
 }
 
@@ -729,11 +1368,11 @@ pub async fn verify_mobile(
 ) -> ApiResult<VerifyMobileResponse> {
     tracing::info!(mobile = %payload.mobile, "Verifying mobile number");
 
-    // Check if mobile exists
-    let farmer_opt = {
-        let farmer_verification = state.farmer_verification.lock().await;
-        farmer_verification.get_farmer_by_mobile(&payload.mobile).cloned()
-    };
+    // Check if mobile exists, in the form its DID is derived from
+    let farmer_opt = state
+        .farmer_verification
+        .get_farmer_by_mobile(&normalize_mobile(&payload.mobile))
+        .await?;
 
     match farmer_opt {
         Some(farmer) => {
@@ -805,10 +1444,10 @@ pub async fn get_farmer_by_did(
     tracing::info!(farmer_did = %payload.farmer_did, "Looking up farmer by DID");
 
     // Get farmer details from verification service
-    let farmer_details = {
-        let farmer_verification = state.farmer_verification.lock().await;
-        farmer_verification.get_farmer_by_did(&payload.farmer_did).cloned()
-    };
+    let farmer_details = state
+        .farmer_verification
+        .get_farmer_by_did(&payload.farmer_did)
+        .await?;
     match farmer_details {
         Some(farmer) => Ok(Json(GetFarmerByDidResponse {
             found: true,
@@ -831,13 +1470,13 @@ pub async fn get_farmer_by_did(
 
 pub async fn verify_sku(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<VerifySkuRequest>,
 ) -> ApiResult<VerifySkuResponse> {
+    let chain = state.networks.select(&headers)?;
     let sku_id = hash_string(&payload.sku_id);
 
-    let result = state
-        .blockchain_client
-        .verify_package_origin(sku_id)
+    let result = degradation::package_origin(&state, &chain, sku_id)
         .await
         .map_err(ApiError::blockchain_failed)?;
 
@@ -850,6 +1489,150 @@ pub async fn verify_sku(
     }))
 }
 
+// Bulk SKU verification for retailer receiving
+
+/// Default cap on SKUs per bulk request (override with BULK_VERIFY_MAX_SKUS)
+const DEFAULT_BULK_VERIFY_MAX_SKUS: usize = 500;
+
+/// Concurrent chain reads per bulk request
+const BULK_VERIFY_CONCURRENCY: usize = 16;
+
+#[derive(Debug, Deserialize)]
+pub struct BulkVerifySkuRequest {
+    pub sku_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkVerifySkuResult {
+    pub sku_id: String,
+    pub exists: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_batch_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merkle_root: Option<String>,
+    pub packaged_at: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkVerifySkuResponse {
+    pub total: usize,
+    pub verified: usize,
+    pub not_found: usize,
+    pub failed: usize,
+    pub results: Vec<BulkVerifySkuResult>,
+}
+
+pub async fn verify_sku_bulk(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<BulkVerifySkuRequest>,
+) -> ApiResult<BulkVerifySkuResponse> {
+    let chain = state.networks.select(&headers)?;
+    let max_skus = std::env::var("BULK_VERIFY_MAX_SKUS")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_BULK_VERIFY_MAX_SKUS);
+
+    if payload.sku_ids.is_empty() {
+        return Err(ApiError::bad_request("sku_ids must not be empty"));
+    }
+    if payload.sku_ids.len() > max_skus {
+        return Err(ApiError::bad_request(format!(
+            "At most {} SKUs can be verified per request, got {}",
+            max_skus,
+            payload.sku_ids.len()
+        )));
+    }
+
+    // Preserve request order while resolving each distinct SKU once
+    let mut unique_ids: Vec<String> = Vec::new();
+    for sku_id in &payload.sku_ids {
+        if !unique_ids.contains(sku_id) {
+            unique_ids.push(sku_id.clone());
+        }
+    }
+
+    tracing::info!(
+        requested = payload.sku_ids.len(),
+        unique = unique_ids.len(),
+        "Bulk verifying SKUs"
+    );
+
+    let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new(BULK_VERIFY_CONCURRENCY));
+    let mut lookups = tokio::task::JoinSet::new();
+
+    for sku_id in unique_ids {
+        let state = state.clone();
+        let client = chain.clone();
+        let semaphore = semaphore.clone();
+        let notes = degradation::current();
+        lookups.spawn(degradation::run_with(notes, async move {
+            let _permit = semaphore.acquire_owned().await;
+            let result =
+                degradation::package_origin(&state, &client, hash_string(&sku_id)).await;
+            (sku_id, result)
+        }));
+    }
+
+    let mut resolved = std::collections::HashMap::new();
+    while let Some(joined) = lookups.join_next().await {
+        let (sku_id, result) =
+            joined.map_err(|e| ApiError::internal(format!("SKU lookup task failed: {}", e)))?;
+        resolved.insert(sku_id, result);
+    }
+
+    let mut response = BulkVerifySkuResponse {
+        total: payload.sku_ids.len(),
+        verified: 0,
+        not_found: 0,
+        failed: 0,
+        results: Vec::with_capacity(payload.sku_ids.len()),
+    };
+
+    for sku_id in payload.sku_ids {
+        let result = match &resolved[&sku_id] {
+            Ok((parent_batch_hash, merkle_root, packaged_at)) if *packaged_at > 0 => {
+                response.verified += 1;
+                BulkVerifySkuResult {
+                    sku_id,
+                    exists: true,
+                    parent_batch_hash: Some(format_hash(parent_batch_hash)),
+                    merkle_root: Some(format_hash(merkle_root)),
+                    packaged_at: *packaged_at,
+                    error: None,
+                }
+            }
+            Ok(_) => {
+                response.not_found += 1;
+                BulkVerifySkuResult {
+                    sku_id,
+                    exists: false,
+                    parent_batch_hash: None,
+                    merkle_root: None,
+                    packaged_at: 0,
+                    error: None,
+                }
+            }
+            Err(e) => {
+                response.failed += 1;
+                BulkVerifySkuResult {
+                    sku_id,
+                    exists: false,
+                    parent_batch_hash: None,
+                    merkle_root: None,
+                    packaged_at: 0,
+                    error: Some(format!("{:#}", e)),
+                }
+            }
+        };
+        response.results.push(result);
+    }
+
+    Ok(Json(response))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct VerifyFarmerRequest {
     pub farmer_did: String,
@@ -865,15 +1648,17 @@ pub struct VerifyFarmerResponse {
 
 pub async fn verify_farmer(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<VerifyFarmerRequest>,
 ) -> ApiResult<VerifyFarmerResponse> {
+    let chain = state.networks.select(&headers)?;
     tracing::info!(farmer_did = %payload.farmer_did, "Verifying farmer");
 
     // First check local verification database
-    let local_farmer = {
-        let farmer_verification = state.farmer_verification.lock().await;
-        farmer_verification.get_farmer_by_did(&payload.farmer_did).cloned()
-    };
+    let local_farmer = state
+        .farmer_verification
+        .get_farmer_by_did(&payload.farmer_did)
+        .await?;
 
     if let Some(farmer) = local_farmer {
         tracing::info!(
@@ -898,9 +1683,9 @@ pub async fn verify_farmer(
         "Farmer not in local DB, checking blockchain"
     );
 
-    match payload.farmer_did.parse::<FixedBytes<32>>() {
+    match chain_did(&payload.farmer_did) {
         Ok(farmer_did_hash) => {
-            match state.blockchain_client.verify_farmer(farmer_did_hash).await {
+            match degradation::farmer_registration(&state, &chain, farmer_did_hash).await {
                 Ok(result) => {
                     tracing::info!(
                         farmer_did = %payload.farmer_did,
@@ -971,10 +1756,11 @@ pub async fn upload_farmer_ipfs_data(
     tracing::info!(mobile = %payload.mobile, "Uploading IPFS data for farmer");
 
     // Verify mobile number exists in database
-    let farmer = {
-        let farmer_verification = state.farmer_verification.lock().await;
-        farmer_verification.get_farmer_by_mobile(&payload.mobile).cloned()
-    }.ok_or_else(|| {
+    let farmer = state
+        .farmer_verification
+        .get_farmer_by_mobile(&payload.mobile)
+        .await?
+        .ok_or_else(|| {
         tracing::warn!(mobile = %payload.mobile, "Mobile number not found in database");
         ApiError::bad_request(format!("Mobile number {} not found in database", payload.mobile))
     })?;
@@ -987,18 +1773,12 @@ pub async fn upload_farmer_ipfs_data(
         .map_err(ApiError::ipfs_upload_failed)?;
 
     // Update farmer's IPFS CID in the database
+    if let Err(e) = state
+        .farmer_verification
+        .update_farmer_ipfscid_by_mobile(&payload.mobile, &cid)
+        .await
     {
-        let mut farmer_verification = state.farmer_verification.lock().await;
-        if let Err(e) = farmer_verification.update_farmer_ipfscid_by_mobile(&payload.mobile, &cid) {
-            tracing::error!(error = %e, "Failed to update farmer IPFS CID in memory");
-        }
-
-        // Save updated database to file
-        if let Err(e) = farmer_verification.save_to_file("data/farmers_db.json") {
-            tracing::error!(error = %e, "Failed to save farmer database to file");
-        } else {
-            tracing::info!("Farmer database updated and saved successfully");
-        }
+        tracing::error!(error = %e, "Failed to update farmer IPFS CID in database");
     }
 
     Ok(Json(FarmerIpfsUploadResponse {
@@ -0,0 +1,169 @@
+//! Per-tenant IPFS storage
+//!
+//! Requests made with an API key run as the tenant named by the key (as for
+//! display time zones). A tenant can pin to its own Pinata account and have
+//! its links served through its own dedicated gateway. Tenants are
+//! configured in `data/tenant_storage.json` (override with
+//! TENANT_STORAGE_CONFIG_PATH); tenants without an entry, and all other
+//! requests, use PINATA_API_KEY and the public gateway:
+//!
+//! ```json
+//! { "tenants": { "export-desk-dubai": {
+//!     "pinata_api_key": "...", "pinata_api_secret": "...",
+//!     "gateway": "https://export-desk.mypinata.cloud" } } }
+//! ```
+//!
+//! What each tenant stores is metered and capped by [`crate::metering`].
+
+use crate::auth::Principal;
+use crate::metering;
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::future::Future;
+
+const DEFAULT_CONFIG_PATH: &str = "data/tenant_storage.json";
+const QUOTA_WARNING_HEADER: &str = "x-quota-warning";
+/// Tenant of work done outside an API key's request
+pub const PLATFORM: &str = "platform";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TenantStorageConfig {
+    #[serde(default)]
+    pub pinata_api_key: Option<String>,
+    #[serde(default)]
+    pub pinata_api_secret: Option<String>,
+    /// Base URL of the tenant's gateway, e.g. https://x.mypinata.cloud
+    #[serde(default)]
+    pub gateway: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TenantStorageFile {
+    #[serde(default)]
+    tenants: HashMap<String, TenantStorageConfig>,
+}
+
+#[derive(Debug)]
+pub struct TenantStorage {
+    tenants: HashMap<String, TenantStorageConfig>,
+}
+
+impl TenantStorage {
+    pub fn load() -> Result<Self> {
+        let path = std::env::var("TENANT_STORAGE_CONFIG_PATH")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| DEFAULT_CONFIG_PATH.to_string());
+        let file: TenantStorageFile = if std::path::Path::new(&path).exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read tenant storage {}", path))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid tenant storage {}", path))?
+        } else {
+            TenantStorageFile::default()
+        };
+        for (tenant, config) in &file.tenants {
+            if config.pinata_api_key.is_some() != config.pinata_api_secret.is_some() {
+                anyhow::bail!(
+                    "Tenant {} in {} needs both pinata_api_key and pinata_api_secret",
+                    tenant,
+                    path
+                );
+            }
+        }
+        Ok(Self {
+            tenants: file.tenants,
+        })
+    }
+
+    pub fn config(&self, tenant: &str) -> Option<&TenantStorageConfig> {
+        self.tenants.get(tenant)
+    }
+
+    /// Pinata keys of `tenant`, when it has its own
+    pub fn credentials(&self, tenant: &str) -> Option<(&str, &str)> {
+        let config = self.tenants.get(tenant)?;
+        Some((
+            config.pinata_api_key.as_deref()?,
+            config.pinata_api_secret.as_deref()?,
+        ))
+    }
+}
+
+// ======================== REQUEST TENANT ========================
+
+#[derive(Debug, Clone)]
+struct RequestTenant {
+    name: String,
+    gateway: Option<String>,
+}
+
+tokio::task_local! {
+    static TENANT: Option<RequestTenant>;
+}
+
+/// Tenant whose request is being served, or [`PLATFORM`]
+pub fn current_tenant() -> String {
+    TENANT
+        .try_with(|t| t.as_ref().map(|t| t.name.clone()))
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| PLATFORM.to_string())
+}
+
+/// Dedicated gateway of the tenant whose request is being served
+pub fn current_gateway() -> Option<String> {
+    TENANT
+        .try_with(|t| t.as_ref().and_then(|t| t.gateway.clone()))
+        .ok()
+        .flatten()
+}
+
+impl RequestTenant {
+    fn new(tenants: &TenantStorage, name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            gateway: tenants
+                .config(name)
+                .and_then(|config| config.gateway.as_ref())
+                .map(|g| g.trim_end_matches('/').to_string()),
+        }
+    }
+}
+
+/// Run `future` as `tenant`, for background work started by a tenant's
+/// request
+pub async fn run_as<F: Future>(tenants: &TenantStorage, tenant: &str, future: F) -> F::Output {
+    let tenant = (tenant != PLATFORM).then(|| RequestTenant::new(tenants, tenant));
+    TENANT.scope(tenant, future).await
+}
+
+/// Middleware running an API key's request as its tenant, and flagging
+/// responses of tenants past a soft quota
+pub async fn scope_tenant(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let tenant = match request.extensions().get::<Principal>() {
+        Some(Principal::ApiKey { name, .. }) => {
+            Some(RequestTenant::new(state.ipfs_client.tenants(), name))
+        }
+        _ => None,
+    };
+    let Some(name) = tenant.as_ref().map(|t| t.name.clone()) else {
+        return next.run(request).await;
+    };
+
+    let mut response = TENANT.scope(tenant, next.run(request)).await;
+    if let Some(warning) = metering::global().warning(&name) {
+        if let Ok(value) = HeaderValue::from_str(&warning) {
+            response.headers_mut().insert(QUOTA_WARNING_HEADER, value);
+        }
+    }
+    response
+}
@@ -0,0 +1,350 @@
+//! Chronological timeline of a batch
+//!
+//! `GET /api/trace/batch/:batch_id` stitches the indexed contract events of a
+//! batch (see [`crate::indexer`]) and their metadata into one timeline: FPO
+//! purchase, warehouse updates, logistics milestones, processing, packaging,
+//! fraud reports and AI scores.
+//!
+//! Warehouses and shipments are not keyed by batch on chain. Their events are
+//! included when a batch record names them in a `warehouse_id` or
+//! `shipment_id` field, or when they are passed as `?warehouse_id=` /
+//! `?shipment_id=` (comma separated).
+//!
+//! Metadata is read from the batch folder when the stage wrote a record there
+//! and fetched from IPFS otherwise; `?metadata=false` skips it. An active
+//! lender lien on the batch is reported in `lien`, the physical
+//! counter-samples kept from it in `counter_samples`, and the processor's
+//! latest acceptance decision in `acceptance`.
+
+use crate::acceptance::LotAcceptance;
+use crate::batch_ledger;
+use crate::chain::hash_string;
+use crate::degradation;
+use crate::error::{format_hash, ipfs_gateway_url, ApiError, ApiResult};
+use crate::financing::FinancingFlag;
+use crate::indexer::{EventFilter, IndexedEvent};
+use crate::samples::CounterSample;
+use crate::state::AppState;
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Upper bound on events gathered per batch, warehouse or shipment
+const MAX_EVENTS_PER_SOURCE: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct TimelineParams {
+    #[serde(default)]
+    pub warehouse_id: Option<String>,
+    #[serde(default)]
+    pub shipment_id: Option<String>,
+    #[serde(default = "default_true")]
+    pub metadata: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimelineEntry {
+    pub stage: &'static str,
+    pub event: String,
+    /// Timestamp carried by the event (unix seconds)
+    pub timestamp: u64,
+    pub time: String,
+    pub block_number: u64,
+    pub tx_hash: String,
+    pub subject: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata_cid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ipfs_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    pub fields: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchTimeline {
+    pub batch_id: String,
+    pub batch_hash: String,
+    /// Warehouses and shipments whose events were included
+    pub warehouse_ids: Vec<String>,
+    pub shipment_ids: Vec<String>,
+    /// First block the indexer has not processed yet; later events are missing
+    pub indexed_to_block: Option<u64>,
+    /// Active lien; a liened batch cannot be processed or packaged
+    pub lien: Option<FinancingFlag>,
+    /// Retained physical samples to settle quality disputes against
+    pub counter_samples: Vec<CounterSample>,
+    /// Processor's latest accept/reject decision on the lot
+    pub acceptance: Option<LotAcceptance>,
+    pub entries: Vec<TimelineEntry>,
+    /// Metadata that could not be fetched from IPFS; those entries have none
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+/// Supply chain stage an indexed event belongs to
+pub fn stage(event: &IndexedEvent) -> &'static str {
+    match event.event.as_str() {
+        "OwnershipTransfer" => match event.fields.get("transfer_type").and_then(Value::as_u64) {
+            Some(1) => "fpo_purchase",
+            Some(2) => "warehouse_transfer",
+            Some(3) => "processor_transfer",
+            Some(4) => "retail_transfer",
+            _ => "ownership_transfer",
+        },
+        "WarehouseStateUpdated" => "warehouse",
+        "LogisticsMilestone" => "logistics",
+        "BatchProcessed" => "processing",
+        "SKUPackaged" => "packaging",
+        "FraudDetected" => "fraud_report",
+        "AIScoreCommitted" => "ai_score_commit",
+        "AIScoreRevealed" => "ai_score_reveal",
+        "FarmerRegistered" => "farmer_registration",
+        "FarmerUpdated" => "farmer_update",
+        _ => "other",
+    }
+}
+
+/// String values of every `key` field anywhere in `records`
+pub fn referenced_ids<'a>(
+    records: impl IntoIterator<Item = &'a Value>,
+    key: &str,
+) -> BTreeSet<String> {
+    fn collect(value: &Value, key: &str, ids: &mut BTreeSet<String>) {
+        match value {
+            Value::Object(map) => {
+                for (k, v) in map {
+                    match v {
+                        Value::String(id) if k == key && !id.is_empty() => {
+                            ids.insert(id.clone());
+                        }
+                        _ => collect(v, key, ids),
+                    }
+                }
+            }
+            Value::Array(items) => items.iter().for_each(|v| collect(v, key, ids)),
+            _ => {}
+        }
+    }
+
+    let mut ids = BTreeSet::new();
+    for record in records {
+        collect(record, key, &mut ids);
+    }
+    ids
+}
+
+fn split_ids(param: Option<&str>) -> impl Iterator<Item = String> + '_ {
+    param
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(String::from)
+}
+
+/// Batch folder record holding the metadata of `event`, if the stage wrote one
+fn local_metadata(
+    event: &IndexedEvent,
+    batch_hash: &str,
+    records: &HashMap<String, Value>,
+) -> Option<Value> {
+    let file = match event.event.as_str() {
+        "OwnershipTransfer" if stage(event) == "fpo_purchase" => "fpo_purchase.json".to_string(),
+        // Only the input batch's folder holds the processing record
+        "BatchProcessed" if event.subject == batch_hash => "processing.json".to_string(),
+        "AIScoreRevealed" => "ai_score.json".to_string(),
+        "SKUPackaged" => records.keys().find_map(|name| {
+            let sku_id = name.strip_prefix("packaging_")?.strip_suffix(".json")?;
+            (format_hash(hash_string(sku_id)) == event.subject).then(|| name.clone())
+        })?,
+        _ => return None,
+    };
+    records.get(&file).cloned()
+}
+
+/// Timeline entries for the events gathered from every source, in time order.
+/// Events are keyed by chain position, so one reached twice is listed once;
+/// with `with_metadata` the batch folder records are attached.
+pub fn assemble_entries(
+    events: impl IntoIterator<Item = IndexedEvent>,
+    batch_hash: &str,
+    records: &HashMap<String, Value>,
+    with_metadata: bool,
+) -> Vec<TimelineEntry> {
+    let events: BTreeMap<(u64, u64), IndexedEvent> = events
+        .into_iter()
+        .map(|event| ((event.block_number, event.log_index), event))
+        .collect();
+
+    let mut entries: Vec<TimelineEntry> = events
+        .into_values()
+        .map(|event| {
+            let metadata = with_metadata
+                .then(|| local_metadata(&event, batch_hash, records))
+                .flatten();
+            TimelineEntry {
+                stage: stage(&event),
+                time: chrono::DateTime::from_timestamp(event.timestamp as i64, 0)
+                    .map(|t| t.to_rfc3339())
+                    .unwrap_or_default(),
+                ipfs_url: event.metadata_cid.as_deref().map(ipfs_gateway_url),
+                metadata,
+                event: event.event,
+                timestamp: event.timestamp,
+                block_number: event.block_number,
+                tx_hash: event.tx_hash,
+                subject: event.subject,
+                metadata_cid: event.metadata_cid,
+                fields: event.fields,
+            }
+        })
+        .collect();
+    entries.sort_by_key(|e| (e.timestamp, e.block_number));
+    entries
+}
+
+pub async fn get_batch_timeline(
+    State(state): State<AppState>,
+    Path(batch_id): Path<String>,
+    Query(params): Query<TimelineParams>,
+) -> ApiResult<BatchTimeline> {
+    if !batch_ledger::is_valid_batch_id(&batch_id) {
+        return Err(ApiError::bad_request("Invalid batch ID"));
+    }
+    let batch_hash = format_hash(hash_string(&batch_id));
+
+    let records: HashMap<String, Value> = batch_ledger::batch_records(&batch_id)
+        .map_err(ApiError::from)?
+        .into_iter()
+        .collect();
+
+    let mut warehouse_ids = referenced_ids(records.values(), "warehouse_id");
+    warehouse_ids.extend(split_ids(params.warehouse_id.as_deref()));
+    let mut shipment_ids = referenced_ids(records.values(), "shipment_id");
+    shipment_ids.extend(split_ids(params.shipment_id.as_deref()));
+
+    let mut filters = vec![EventFilter::Batch(batch_hash.clone())];
+    filters.extend(
+        warehouse_ids
+            .iter()
+            .chain(&shipment_ids)
+            .map(|id| EventFilter::Subject(format_hash(hash_string(id)))),
+    );
+
+    let mut events = Vec::new();
+    for filter in &filters {
+        events.extend(
+            state
+                .events
+                .query(filter, None, MAX_EVENTS_PER_SOURCE)
+                .await?,
+        );
+    }
+
+    if events.is_empty() && records.is_empty() {
+        return Err(ApiError::not_found(format!(
+            "No records or indexed events for batch {}",
+            batch_id
+        )));
+    }
+
+    let mut entries = assemble_entries(events, &batch_hash, &records, params.metadata);
+    let mut warnings = Vec::new();
+
+    if params.metadata {
+        // Stages without a local record (warehouse, logistics, fraud evidence)
+        let mut fetches = tokio::task::JoinSet::new();
+        for (index, entry) in entries.iter().enumerate() {
+            if let (None, Some(cid)) = (&entry.metadata, &entry.metadata_cid) {
+                let ipfs = state.ipfs_client.clone();
+                let cid = cid.clone();
+                fetches.spawn(async move { (index, cid.clone(), ipfs.fetch_json(&cid).await) });
+            }
+        }
+        while let Some(joined) = fetches.join_next().await {
+            match joined {
+                Ok((index, _, Ok(metadata))) => entries[index].metadata = Some(metadata),
+                Ok((_, cid, Err(e))) => {
+                    tracing::debug!(cid = %cid, error = %e, "Timeline metadata unavailable");
+                    warnings.push(format!(
+                        "Metadata {} could not be fetched from IPFS: {:#}",
+                        cid, e
+                    ));
+                }
+                Err(e) => {
+                    tracing::warn!(error = %e, "Timeline metadata fetch failed");
+                    warnings.push(format!("Metadata fetch failed: {}", e));
+                }
+            }
+        }
+        if !warnings.is_empty() {
+            degradation::note(degradation::IPFS_PARTIAL);
+        }
+    }
+
+    let lien = state.financing.active_lien(&batch_id).await;
+    let counter_samples = state.samples.for_batch(&batch_id).await;
+    let acceptance = state.acceptance.latest(&batch_id).await;
+    Ok(Json(BatchTimeline {
+        batch_id,
+        batch_hash,
+        warehouse_ids: warehouse_ids.into_iter().collect(),
+        shipment_ids: shipment_ids.into_iter().collect(),
+        indexed_to_block: state.events.next_block().await?,
+        lien,
+        counter_samples,
+        acceptance,
+        entries,
+        warnings,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_referenced_ids_searches_nested_records() {
+        let records = [
+            json!({ "warehouse_id": "WH-1", "logistics": { "shipment_id": "SHIP-9" } }),
+            json!({ "stops": [{ "warehouse_id": "WH-2" }, { "warehouse_id": "" }] }),
+        ];
+        let ids: Vec<String> = referenced_ids(&records, "warehouse_id")
+            .into_iter()
+            .collect();
+        assert_eq!(ids, ["WH-1", "WH-2"]);
+        assert_eq!(referenced_ids(&records, "shipment_id").len(), 1);
+    }
+
+    #[test]
+    fn test_stage_uses_transfer_type() {
+        let mut event = IndexedEvent {
+            block_number: 1,
+            log_index: 0,
+            tx_hash: String::new(),
+            contract: String::new(),
+            event: "OwnershipTransfer".to_string(),
+            timestamp: 0,
+            subject: String::new(),
+            farmer_did: None,
+            metadata_cid: None,
+            batch_hashes: vec![],
+            fields: json!({ "transfer_type": 1 }),
+        };
+        assert_eq!(stage(&event), "fpo_purchase");
+        event.fields = json!({ "transfer_type": 3 });
+        assert_eq!(stage(&event), "processor_transfer");
+        event.event = "LogisticsMilestone".to_string();
+        assert_eq!(stage(&event), "logistics");
+    }
+}
@@ -0,0 +1,291 @@
+//! Display time zones
+//!
+//! Timestamps are stored and sent as UTC RFC 3339 strings. For frontends,
+//! every such string field of a successful JSON response also gets
+//! `<field>_epoch` (Unix seconds) and `<field>_local` (the same instant in the
+//! display time zone) next to it:
+//!
+//! ```json
+//! { "created_at": "2025-06-02T08:00:00+00:00",
+//!   "created_at_epoch": 1748851200,
+//!   "created_at_local": "2025-06-02T13:30:00+05:30" }
+//! ```
+//!
+//! The display zone is taken from the `tz` query parameter, else the
+//! `X-Timezone` header, else the zone of the calling tenant (API key name),
+//! else the default. Zones are read from `data/timezones.json` (override with
+//! TIMEZONES_CONFIG_PATH); without a file the default is UTC:
+//!
+//! ```json
+//! { "default": "Asia/Kolkata",
+//!   "tenants": { "export-desk-dubai": "+04:00" } }
+//! ```
+//!
+//! Zones are fixed offsets (`+05:30`, `-0300`), `UTC`, or one of the names in
+//! [`NAMED_ZONES`]. The zone used is echoed in the `X-Timezone` response header.
+//!
+//! Proof endpoints are left untouched, since their entries are hashed as
+//! served (see [`VERBATIM_PATHS`]).
+
+use crate::auth::Principal;
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Query, Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, FixedOffset, SecondsFormat};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+const DEFAULT_TIMEZONES_CONFIG_PATH: &str = "data/timezones.json";
+const TIMEZONE_HEADER: &str = "x-timezone";
+/// Largest response body the layer will buffer
+const MAX_LOCALIZED_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Responses whose JSON is verified by hashing it as served
+pub const VERBATIM_PATHS: [&str; 3] = [
+    "/api/audit/proof/",
+    "/api/anchors/proof/",
+    "/api/public/proofs/",
+];
+
+/// Zone names accepted besides fixed offsets
+pub const NAMED_ZONES: [(&str, i32); 6] = [
+    ("UTC", 0),
+    ("GMT", 0),
+    ("IST", 330),
+    ("Asia/Kolkata", 330),
+    ("Asia/Calcutta", 330),
+    ("Asia/Dubai", 240),
+];
+
+/// Parse a zone name or a `+HH:MM` / `+HHMM` offset
+pub fn parse_timezone(zone: &str) -> Option<FixedOffset> {
+    let zone = zone.trim();
+    if let Some((_, minutes)) = NAMED_ZONES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(zone))
+    {
+        return FixedOffset::east_opt(minutes * 60);
+    }
+
+    let sign = match zone.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits: String = zone[1..].chars().filter(|c| *c != ':').collect();
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    if hours > 14 || minutes > 59 {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TimezonesFile {
+    #[serde(default)]
+    default: Option<String>,
+    #[serde(default)]
+    tenants: HashMap<String, String>,
+}
+
+pub struct TimezoneConfig {
+    default: FixedOffset,
+    tenants: HashMap<String, FixedOffset>,
+}
+
+impl TimezoneConfig {
+    pub fn load() -> Result<Self> {
+        let path = std::env::var("TIMEZONES_CONFIG_PATH")
+            .unwrap_or_else(|_| DEFAULT_TIMEZONES_CONFIG_PATH.to_string());
+
+        let file: TimezonesFile = if std::path::Path::new(&path).exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read time zones {}", path))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Invalid time zones {}", path))?
+        } else {
+            TimezonesFile::default()
+        };
+
+        let parse = |zone: &str| {
+            parse_timezone(zone)
+                .with_context(|| format!("Unknown time zone {:?} in {}", zone, path))
+        };
+        let default = match &file.default {
+            Some(zone) => parse(zone)?,
+            None => FixedOffset::east_opt(0).expect("zero offset"),
+        };
+        let tenants = file
+            .tenants
+            .iter()
+            .map(|(tenant, zone)| Ok((tenant.clone(), parse(zone)?)))
+            .collect::<Result<_>>()?;
+
+        Ok(Self { default, tenants })
+    }
+
+    fn for_principal(&self, principal: Option<&Principal>) -> FixedOffset {
+        match principal {
+            Some(Principal::ApiKey { name, .. }) => {
+                self.tenants.get(name).copied().unwrap_or(self.default)
+            }
+            _ => self.default,
+        }
+    }
+}
+
+/// Add `_epoch` and `_local` companions to every RFC 3339 string field
+pub fn localize(value: &mut Value, zone: &FixedOffset) {
+    match value {
+        Value::Object(object) => {
+            let mut added = Map::new();
+            for (key, field) in object.iter_mut() {
+                if let Value::String(text) = field {
+                    let Ok(at) = DateTime::parse_from_rfc3339(text) else {
+                        continue;
+                    };
+                    added.insert(format!("{}_epoch", key), Value::from(at.timestamp()));
+                    added.insert(
+                        format!("{}_local", key),
+                        Value::from(
+                            at.with_timezone(zone)
+                                .to_rfc3339_opts(SecondsFormat::AutoSi, false),
+                        ),
+                    );
+                } else {
+                    localize(field, zone);
+                }
+            }
+            for (key, field) in added {
+                object.entry(key).or_insert(field);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| localize(v, zone)),
+        _ => {}
+    }
+}
+
+/// Middleware localizing timestamps of successful JSON responses
+pub async fn localize_timestamps(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    if VERBATIM_PATHS.iter().any(|prefix| path.starts_with(prefix)) {
+        return next.run(request).await;
+    }
+
+    let requested = Query::<HashMap<String, String>>::try_from_uri(request.uri())
+        .ok()
+        .and_then(|Query(params)| params.get("tz").cloned())
+        .or_else(|| {
+            request
+                .headers()
+                .get(TIMEZONE_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .map(String::from)
+        });
+    let zone = requested
+        .as_deref()
+        .and_then(parse_timezone)
+        .unwrap_or_else(|| {
+            state
+                .timezones
+                .for_principal(request.extensions().get::<Principal>())
+        });
+
+    let response = next.run(request).await;
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("application/json"))
+        .unwrap_or(false);
+
+    if !response.status().is_success() || !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_LOCALIZED_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to buffer response for time zone display");
+            return crate::error::ApiError::internal("Failed to localize response").into_response();
+        }
+    };
+
+    let mut value = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(value) => value,
+        Err(_) => return Response::from_parts(parts, Body::from(bytes)),
+    };
+    localize(&mut value, &zone);
+
+    let localized = match serde_json::to_vec(&value) {
+        Ok(localized) => localized,
+        Err(e) => return crate::error::ApiError::json_failed(e).into_response(),
+    };
+
+    parts
+        .headers
+        .insert(header::CONTENT_LENGTH, HeaderValue::from(localized.len()));
+    if let Ok(zone) = HeaderValue::from_str(&zone.to_string()) {
+        parts.headers.insert(TIMEZONE_HEADER, zone);
+    }
+
+    Response::from_parts(parts, Body::from(localized))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_timezone() {
+        let ist = FixedOffset::east_opt(330 * 60).unwrap();
+        assert_eq!(parse_timezone("Asia/Kolkata"), Some(ist));
+        assert_eq!(parse_timezone("+05:30"), Some(ist));
+        assert_eq!(parse_timezone("+0530"), Some(ist));
+        assert_eq!(parse_timezone("-03:00"), FixedOffset::west_opt(3 * 3600));
+        assert_eq!(parse_timezone("utc"), FixedOffset::east_opt(0));
+        assert_eq!(parse_timezone("Europe/Paris"), None);
+        assert_eq!(parse_timezone("+25:00"), None);
+    }
+
+    #[test]
+    fn test_localize_adds_epoch_and_local_fields() {
+        let ist = parse_timezone("IST").unwrap();
+        let mut value = json!({
+            "created_at": "2025-06-02T08:00:00+00:00",
+            "purchase_date": "2025-06-02",
+            "stages": [{ "started_at": "2025-06-02T18:45:00Z" }]
+        });
+        localize(&mut value, &ist);
+
+        assert_eq!(value["created_at_epoch"], json!(1748851200));
+        assert_eq!(
+            value["created_at_local"],
+            json!("2025-06-02T13:30:00+05:30")
+        );
+        assert_eq!(value["created_at"], json!("2025-06-02T08:00:00+00:00"));
+        assert!(value.get("purchase_date_local").is_none());
+        assert_eq!(
+            value["stages"][0]["started_at_local"],
+            json!("2025-06-03T00:15:00+05:30")
+        );
+    }
+}
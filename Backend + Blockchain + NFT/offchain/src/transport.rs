@@ -0,0 +1,603 @@
+//! Transporters, vehicles, drivers and shipment assignment
+//!
+//! Admins register transport companies, their vehicles (by registration
+//! number) and drivers under `/api/admin/transporters`, `/api/admin/vehicles`
+//! and `/api/admin/drivers`. Before a shipment's checkpoints are accepted,
+//! `POST /api/logistics/assign` links it to a vehicle and a driver of the
+//! same transporter; `POST /api/logistics/record` and the batch variant
+//! refuse shipments without one and pin the carrier with the GPS metadata.
+//! A shipment can be reassigned (a handover) until it is delivered.
+//!
+//! Driver mobiles stay in the registry; only names and IDs are pinned.
+//! Everything is kept in `data/transport.json`.
+
+use crate::admin::require_admin;
+use crate::error::{ApiError, ApiResult};
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+const TRANSPORT_PATH: &str = "data/transport.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transporter {
+    pub id: String,
+    pub name: String,
+    /// Goods carriage permit or GSTIN
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub licence_number: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contact: Option<String>,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vehicle {
+    /// Registration number without spaces or hyphens, e.g. `MH12AB1234`
+    pub registration_number: String,
+    pub transporter_id: String,
+    pub vehicle_type: String,
+    pub capacity_kg: f64,
+    #[serde(default)]
+    pub refrigerated: bool,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Driver {
+    pub id: String,
+    pub transporter_id: String,
+    pub name: String,
+    pub licence_number: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mobile: Option<String>,
+    pub updated_at: String,
+}
+
+/// Who carries a shipment, as pinned with its checkpoints
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Carrier {
+    pub transporter_id: String,
+    pub transporter_name: String,
+    pub vehicle: String,
+    pub vehicle_type: String,
+    pub refrigerated: bool,
+    pub driver_id: String,
+    pub driver_name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Assignment {
+    pub shipment_id: String,
+    pub carrier: Carrier,
+    pub assigned_at: String,
+    /// Carriers the shipment was handed over from, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub previous: Vec<Carrier>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delivered_at: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TransportData {
+    #[serde(default)]
+    transporters: Vec<Transporter>,
+    #[serde(default)]
+    vehicles: Vec<Vehicle>,
+    #[serde(default)]
+    drivers: Vec<Driver>,
+    #[serde(default)]
+    assignments: Vec<Assignment>,
+}
+
+impl TransportData {
+    /// Carrier made of `vehicle` and `driver_id`, which must belong to the
+    /// same transporter
+    fn carrier(&self, vehicle: &str, driver_id: &str) -> Result<Carrier, ApiError> {
+        let vehicle = self
+            .vehicles
+            .iter()
+            .find(|v| v.registration_number == vehicle)
+            .ok_or_else(|| {
+                ApiError::bad_request(format!("Vehicle {} is not registered", vehicle))
+            })?;
+        let driver = self
+            .drivers
+            .iter()
+            .find(|d| d.id == driver_id)
+            .ok_or_else(|| {
+                ApiError::bad_request(format!("Driver {} is not registered", driver_id))
+            })?;
+        if driver.transporter_id != vehicle.transporter_id {
+            return Err(ApiError::bad_request(format!(
+                "Driver {} works for {}, vehicle {} belongs to {}",
+                driver.id,
+                driver.transporter_id,
+                vehicle.registration_number,
+                vehicle.transporter_id
+            )));
+        }
+        let transporter = self
+            .transporters
+            .iter()
+            .find(|t| t.id == vehicle.transporter_id)
+            .ok_or_else(|| {
+                ApiError::bad_request(format!(
+                    "Transporter {} is not registered",
+                    vehicle.transporter_id
+                ))
+            })?;
+        Ok(Carrier {
+            transporter_id: transporter.id.clone(),
+            transporter_name: transporter.name.clone(),
+            vehicle: vehicle.registration_number.clone(),
+            vehicle_type: vehicle.vehicle_type.clone(),
+            refrigerated: vehicle.refrigerated,
+            driver_id: driver.id.clone(),
+            driver_name: driver.name.clone(),
+        })
+    }
+}
+
+/// `MH 12-AB 1234` -> `MH12AB1234`
+fn canonical_registration(input: &str) -> Result<String, ApiError> {
+    let canonical: String = input
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-'))
+        .collect::<String>()
+        .to_ascii_uppercase();
+    if !(6..=12).contains(&canonical.len()) || !canonical.chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        return Err(ApiError::bad_request(format!(
+            "{:?} is not a vehicle registration number",
+            input
+        )));
+    }
+    Ok(canonical)
+}
+
+fn required(value: &str, name: &str) -> Result<String, ApiError> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(ApiError::bad_request(format!("{} is required", name)));
+    }
+    Ok(value.to_string())
+}
+
+pub struct TransportRegistry {
+    data: Mutex<TransportData>,
+}
+
+impl TransportRegistry {
+    pub fn load() -> Result<Self> {
+        let data = match std::fs::read_to_string(TRANSPORT_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid transport registry {}", TRANSPORT_PATH))?,
+            Err(_) => TransportData::default(),
+        };
+        Ok(Self {
+            data: Mutex::new(data),
+        })
+    }
+
+    fn save(data: &TransportData) -> Result<()> {
+        std::fs::write(TRANSPORT_PATH, serde_json::to_string_pretty(data)?)
+            .with_context(|| format!("Failed to write {}", TRANSPORT_PATH))
+    }
+
+    /// Carrier of `shipment_id`, if one is assigned
+    pub async fn assigned(&self, shipment_id: &str) -> Option<Carrier> {
+        self.data
+            .lock()
+            .await
+            .assignments
+            .iter()
+            .find(|a| a.shipment_id == shipment_id)
+            .map(|a| a.carrier.clone())
+    }
+
+    /// Carrier of `shipment_id`, as a request error when none is assigned
+    pub async fn carrier(&self, shipment_id: &str) -> Result<Carrier, ApiError> {
+        self.assigned(shipment_id).await.ok_or_else(|| {
+            ApiError::bad_request(format!(
+                "Shipment {} has no vehicle and driver assigned; POST /api/logistics/assign first",
+                shipment_id
+            ))
+        })
+    }
+
+    pub async fn mark_delivered(&self, shipment_id: &str) -> Result<()> {
+        let mut data = self.data.lock().await;
+        let Some(assignment) = data
+            .assignments
+            .iter_mut()
+            .find(|a| a.shipment_id == shipment_id && a.delivered_at.is_none())
+        else {
+            return Ok(());
+        };
+        assignment.delivered_at = Some(Utc::now().to_rfc3339());
+        Self::save(&data)
+    }
+}
+
+// ======================== HANDLERS ========================
+
+/// Registered transporters, ordered by ID
+pub async fn list_transporters(
+    State(state): State<AppState>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<Transporter>> {
+    let transporters = state.transport.data.lock().await.transporters.clone();
+    Ok(Json(paginate(
+        "transporters",
+        transporters,
+        &params,
+        |t| t.id.clone(),
+    )?))
+}
+
+/// Registered vehicles, ordered by registration number
+pub async fn list_vehicles(
+    State(state): State<AppState>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<Vehicle>> {
+    let vehicles = state.transport.data.lock().await.vehicles.clone();
+    Ok(Json(paginate("vehicles", vehicles, &params, |v| {
+        v.registration_number.clone()
+    })?))
+}
+
+/// Registered drivers, ordered by ID
+pub async fn list_drivers(
+    State(state): State<AppState>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<Driver>> {
+    let drivers = state.transport.data.lock().await.drivers.clone();
+    Ok(Json(paginate("drivers", drivers, &params, |d| {
+        d.id.clone()
+    })?))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransporterRequest {
+    pub name: String,
+    #[serde(default)]
+    pub licence_number: Option<String>,
+    #[serde(default)]
+    pub contact: Option<String>,
+}
+
+/// Register or replace a transporter (admin only)
+pub async fn put_transporter(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<TransporterRequest>,
+) -> ApiResult<Transporter> {
+    require_admin(&state, &headers)?;
+
+    let transporter = Transporter {
+        id: required(&id, "id")?,
+        name: required(&payload.name, "name")?,
+        licence_number: payload.licence_number,
+        contact: payload.contact,
+        updated_at: Utc::now().to_rfc3339(),
+    };
+    let mut data = state.transport.data.lock().await;
+    data.transporters.retain(|t| t.id != transporter.id);
+    data.transporters.push(transporter.clone());
+    TransportRegistry::save(&data)?;
+
+    tracing::info!(transporter_id = %transporter.id, "Transporter registered");
+    Ok(Json(transporter))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VehicleRequest {
+    pub transporter_id: String,
+    pub vehicle_type: String,
+    pub capacity_kg: f64,
+    #[serde(default)]
+    pub refrigerated: bool,
+}
+
+/// Register or replace a vehicle of a registered transporter (admin only)
+pub async fn put_vehicle(
+    State(state): State<AppState>,
+    Path(registration_number): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<VehicleRequest>,
+) -> ApiResult<Vehicle> {
+    require_admin(&state, &headers)?;
+
+    if !(payload.capacity_kg.is_finite() && payload.capacity_kg > 0.0) {
+        return Err(ApiError::bad_request("capacity_kg must be positive"));
+    }
+    let vehicle = Vehicle {
+        registration_number: canonical_registration(&registration_number)?,
+        transporter_id: payload.transporter_id.trim().to_string(),
+        vehicle_type: required(&payload.vehicle_type, "vehicle_type")?,
+        capacity_kg: payload.capacity_kg,
+        refrigerated: payload.refrigerated,
+        updated_at: Utc::now().to_rfc3339(),
+    };
+    let mut data = state.transport.data.lock().await;
+    if !data
+        .transporters
+        .iter()
+        .any(|t| t.id == vehicle.transporter_id)
+    {
+        return Err(ApiError::bad_request(format!(
+            "Transporter {} is not registered",
+            vehicle.transporter_id
+        )));
+    }
+    data.vehicles
+        .retain(|v| v.registration_number != vehicle.registration_number);
+    data.vehicles.push(vehicle.clone());
+    TransportRegistry::save(&data)?;
+
+    tracing::info!(vehicle = %vehicle.registration_number, "Vehicle registered");
+    Ok(Json(vehicle))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DriverRequest {
+    pub transporter_id: String,
+    pub name: String,
+    pub licence_number: String,
+    #[serde(default)]
+    pub mobile: Option<String>,
+}
+
+/// Register or replace a driver of a registered transporter (admin only)
+pub async fn put_driver(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<DriverRequest>,
+) -> ApiResult<Driver> {
+    require_admin(&state, &headers)?;
+
+    let driver = Driver {
+        id: required(&id, "id")?,
+        transporter_id: payload.transporter_id.trim().to_string(),
+        name: required(&payload.name, "name")?,
+        licence_number: required(&payload.licence_number, "licence_number")?,
+        mobile: payload.mobile,
+        updated_at: Utc::now().to_rfc3339(),
+    };
+    let mut data = state.transport.data.lock().await;
+    if !data
+        .transporters
+        .iter()
+        .any(|t| t.id == driver.transporter_id)
+    {
+        return Err(ApiError::bad_request(format!(
+            "Transporter {} is not registered",
+            driver.transporter_id
+        )));
+    }
+    data.drivers.retain(|d| d.id != driver.id);
+    data.drivers.push(driver.clone());
+    TransportRegistry::save(&data)?;
+
+    tracing::info!(driver_id = %driver.id, "Driver registered");
+    Ok(Json(driver))
+}
+
+#[derive(Debug, Serialize)]
+pub struct Removed {
+    pub removed: String,
+}
+
+/// Remove a transporter without vehicles or drivers (admin only)
+pub async fn delete_transporter(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<Removed> {
+    require_admin(&state, &headers)?;
+
+    let mut data = state.transport.data.lock().await;
+    if data.vehicles.iter().any(|v| v.transporter_id == id)
+        || data.drivers.iter().any(|d| d.transporter_id == id)
+    {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!("Transporter {} still has vehicles or drivers", id),
+        ));
+    }
+    let before = data.transporters.len();
+    data.transporters.retain(|t| t.id != id);
+    if data.transporters.len() == before {
+        return Err(ApiError::not_found(format!("Transporter {} not found", id)));
+    }
+    TransportRegistry::save(&data)?;
+    Ok(Json(Removed { removed: id }))
+}
+
+/// Remove a vehicle (admin only); assignments keep its details
+pub async fn delete_vehicle(
+    State(state): State<AppState>,
+    Path(registration_number): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<Removed> {
+    require_admin(&state, &headers)?;
+
+    let registration_number = canonical_registration(&registration_number)?;
+    let mut data = state.transport.data.lock().await;
+    let before = data.vehicles.len();
+    data.vehicles
+        .retain(|v| v.registration_number != registration_number);
+    if data.vehicles.len() == before {
+        return Err(ApiError::not_found(format!(
+            "Vehicle {} not found",
+            registration_number
+        )));
+    }
+    TransportRegistry::save(&data)?;
+    Ok(Json(Removed {
+        removed: registration_number,
+    }))
+}
+
+/// Remove a driver (admin only); assignments keep their details
+pub async fn delete_driver(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<Removed> {
+    require_admin(&state, &headers)?;
+
+    let mut data = state.transport.data.lock().await;
+    let before = data.drivers.len();
+    data.drivers.retain(|d| d.id != id);
+    if data.drivers.len() == before {
+        return Err(ApiError::not_found(format!("Driver {} not found", id)));
+    }
+    TransportRegistry::save(&data)?;
+    Ok(Json(Removed { removed: id }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignRequest {
+    pub shipment_id: String,
+    /// Registration number
+    pub vehicle: String,
+    pub driver_id: String,
+}
+
+/// Assign a vehicle and driver to a shipment, or hand it over to new ones
+pub async fn assign_shipment(
+    State(state): State<AppState>,
+    Json(payload): Json<AssignRequest>,
+) -> ApiResult<Assignment> {
+    let shipment_id = required(&payload.shipment_id, "shipment_id")?;
+    let vehicle = canonical_registration(&payload.vehicle)?;
+
+    let mut data = state.transport.data.lock().await;
+    let carrier = data.carrier(&vehicle, payload.driver_id.trim())?;
+    let now = Utc::now().to_rfc3339();
+    let assignment = match data
+        .assignments
+        .iter_mut()
+        .find(|a| a.shipment_id == shipment_id)
+    {
+        Some(existing) if existing.delivered_at.is_some() => {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                format!("Shipment {} has already been delivered", shipment_id),
+            ))
+        }
+        Some(existing) => {
+            if existing.carrier != carrier {
+                let handed_over = std::mem::replace(&mut existing.carrier, carrier);
+                existing.previous.push(handed_over);
+                existing.assigned_at = now;
+            }
+            existing.clone()
+        }
+        None => {
+            let assignment = Assignment {
+                shipment_id: shipment_id.clone(),
+                carrier,
+                assigned_at: now,
+                previous: Vec::new(),
+                delivered_at: None,
+            };
+            data.assignments.push(assignment.clone());
+            assignment
+        }
+    };
+    TransportRegistry::save(&data)?;
+
+    tracing::info!(
+        shipment_id = %shipment_id,
+        vehicle = %assignment.carrier.vehicle,
+        driver_id = %assignment.carrier.driver_id,
+        handovers = assignment.previous.len(),
+        "Shipment assigned"
+    );
+    Ok(Json(assignment))
+}
+
+pub async fn get_assignment(
+    State(state): State<AppState>,
+    Path(shipment_id): Path<String>,
+) -> ApiResult<Assignment> {
+    state
+        .transport
+        .data
+        .lock()
+        .await
+        .assignments
+        .iter()
+        .find(|a| a.shipment_id == shipment_id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Shipment {} is not assigned", shipment_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_carrier_needs_vehicle_and_driver_of_one_transporter() {
+        assert_eq!(
+            canonical_registration("mh 12-ab 1234").unwrap(),
+            "MH12AB1234"
+        );
+        assert!(canonical_registration("MH/12").is_err());
+
+        let transporter = |id: &str| Transporter {
+            id: id.to_string(),
+            name: format!("{} Roadways", id),
+            licence_number: None,
+            contact: None,
+            updated_at: String::new(),
+        };
+        let data = TransportData {
+            transporters: vec![transporter("T1"), transporter("T2")],
+            vehicles: vec![Vehicle {
+                registration_number: "MH12AB1234".to_string(),
+                transporter_id: "T1".to_string(),
+                vehicle_type: "reefer truck".to_string(),
+                capacity_kg: 9000.0,
+                refrigerated: true,
+                updated_at: String::new(),
+            }],
+            drivers: ["T1", "T2"]
+                .iter()
+                .map(|t| Driver {
+                    id: format!("D-{}", t),
+                    transporter_id: t.to_string(),
+                    name: "Ravi".to_string(),
+                    licence_number: "MH1220190001234".to_string(),
+                    mobile: Some("9876543210".to_string()),
+                    updated_at: String::new(),
+                })
+                .collect(),
+            assignments: Vec::new(),
+        };
+
+        let carrier = data.carrier("MH12AB1234", "D-T1").unwrap();
+        assert_eq!(carrier.transporter_name, "T1 Roadways");
+        assert!(carrier.refrigerated);
+        assert!(!serde_json::to_string(&carrier)
+            .unwrap()
+            .contains("9876543210"));
+
+        assert!(data.carrier("MH12AB1234", "D-T2").is_err());
+        assert!(data.carrier("KA01XY0001", "D-T1").is_err());
+    }
+}
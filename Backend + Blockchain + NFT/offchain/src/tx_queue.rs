@@ -0,0 +1,840 @@
+//! Transaction submission queue
+//!
+//! Every contract write of [`crate::chain::ChainClient`] goes through one
+//! writer task, so the backend signers' transactions are sent one at a time
+//! with nonces assigned here instead of racing between concurrent handlers:
+//!
+//! - Each transaction is signed with the key of the role its contract
+//!   function requires (see [`crate::signers`]), locally or by the remote
+//!   signer.
+//! - The next nonce of each signer is read from the node's pending count on
+//!   its first transaction and after any nonce error, then tracked locally.
+//! - Send errors other than reverts are retried with exponential backoff
+//!   (TX_MAX_RETRIES, default 5, starting at TX_RETRY_BASE_MS, default 500).
+//! - A transaction not mined within TX_RECEIPT_TIMEOUT_SECS (default 90) is
+//!   replaced at the same nonce with fees raised by TX_FEE_BUMP_PERCENT
+//!   (default 20, at least the 10 nodes require), up to TX_MAX_REPLACEMENTS
+//!   times (default 3). Whichever of its hashes is mined completes it.
+//! - Fees follow the chain's [`GasStrategy`]: fixed EIP-1559 fees where
+//!   configured, else the node's suggestions, and gas estimates scaled by
+//!   GAS_ESTIMATE_MULTIPLIER.
+//!
+//! Entries are kept in `data/tx_queue.json` (the latest 500; other networks
+//! have their own file, see [`crate::networks`]). A transaction still
+//! unconfirmed when the server stopped is checked on the next start and
+//! marked mined or interrupted; its caller is gone, so it is never sent
+//! again. `GET /api/admin/tx-queue` lists the entries.
+//!
+//! A chain reload starts a new writer on the same entries; the previous
+//! writer finishes the transactions submitted to it and then stops.
+
+use crate::admin::require_admin;
+use crate::chain::AppProvider;
+use crate::error::{format_tx_hash, ApiResult};
+use crate::faults::{self, Fault, FaultPlan};
+use crate::networks::EnvVars;
+use crate::pagination::{paginate, Page, PageParams};
+use crate::signers::{SignerSet, TxSigner};
+use crate::state::AppState;
+use alloy::{
+    network::{EthereumWallet, TransactionBuilder},
+    primitives::{Address, TxHash},
+    providers::Provider,
+    rpc::types::{TransactionReceipt, TransactionRequest},
+};
+use anyhow::{anyhow, bail, Context, Result};
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, Mutex};
+
+/// Queue file of the default network
+pub const QUEUE_PATH: &str = "data/tx_queue.json";
+const MAX_RECORDS: usize = 500;
+const QUEUE_CAPACITY: usize = 256;
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+#[derive(Debug, Clone)]
+struct QueueConfig {
+    max_retries: u32,
+    retry_base: Duration,
+    receipt_timeout: Duration,
+    max_replacements: u32,
+    fee_bump_percent: u32,
+}
+
+impl QueueConfig {
+    fn from_env() -> Self {
+        Self {
+            max_retries: env_u64("TX_MAX_RETRIES", 5) as u32,
+            retry_base: Duration::from_millis(env_u64("TX_RETRY_BASE_MS", 500).max(1)),
+            receipt_timeout: Duration::from_secs(env_u64("TX_RECEIPT_TIMEOUT_SECS", 90).max(5)),
+            max_replacements: env_u64("TX_MAX_REPLACEMENTS", 3) as u32,
+            fee_bump_percent: env_u64("TX_FEE_BUMP_PERCENT", 20).max(10) as u32,
+        }
+    }
+}
+
+const WEI_PER_GWEI: f64 = 1e9;
+
+/// Parse a decimal gwei amount such as "30" or "1.5" into wei
+fn parse_gwei(value: &str) -> Result<u128> {
+    let gwei: f64 = value
+        .trim()
+        .parse()
+        .with_context(|| format!("{:?} is not a gwei amount", value))?;
+    if !gwei.is_finite() || gwei <= 0.0 {
+        bail!("{:?} must be a positive gwei amount", value);
+    }
+    Ok((gwei * WEI_PER_GWEI).round() as u128)
+}
+
+/// EIP-1559 fee controls applied to every transaction the queue sends
+#[derive(Debug, Clone)]
+pub struct GasStrategy {
+    /// Fixed max fee per gas in wei (GAS_MAX_FEE_GWEI); twice the node's gas
+    /// price when unset
+    pub max_fee_per_gas: Option<u128>,
+    /// Fixed priority fee in wei (GAS_PRIORITY_FEE_GWEI); the node's
+    /// suggestion when unset
+    pub max_priority_fee_per_gas: Option<u128>,
+    /// Factor applied to gas estimates (GAS_ESTIMATE_MULTIPLIER, 1.0 to 5.0)
+    pub gas_estimate_multiplier: f64,
+}
+
+impl Default for GasStrategy {
+    fn default() -> Self {
+        Self {
+            max_fee_per_gas: None,
+            max_priority_fee_per_gas: None,
+            gas_estimate_multiplier: 1.0,
+        }
+    }
+}
+
+impl GasStrategy {
+    pub fn from_env(vars: &EnvVars) -> Result<Self> {
+        let gwei = |name: &str| -> Result<Option<u128>> {
+            match vars.non_empty(name) {
+                Some(value) => Ok(Some(parse_gwei(&value).with_context(|| name.to_string())?)),
+                None => Ok(None),
+            }
+        };
+        let strategy = Self {
+            max_fee_per_gas: gwei("GAS_MAX_FEE_GWEI")?,
+            max_priority_fee_per_gas: gwei("GAS_PRIORITY_FEE_GWEI")?,
+            gas_estimate_multiplier: match vars.non_empty("GAS_ESTIMATE_MULTIPLIER") {
+                Some(value) => value
+                    .trim()
+                    .parse()
+                    .context("GAS_ESTIMATE_MULTIPLIER must be a number such as 1.2")?,
+                _ => 1.0,
+            },
+        };
+
+        if !(1.0..=5.0).contains(&strategy.gas_estimate_multiplier) {
+            bail!("GAS_ESTIMATE_MULTIPLIER must be between 1.0 and 5.0");
+        }
+        if let (Some(max_fee), Some(tip)) =
+            (strategy.max_fee_per_gas, strategy.max_priority_fee_per_gas)
+        {
+            if tip > max_fee {
+                bail!("GAS_PRIORITY_FEE_GWEI cannot exceed GAS_MAX_FEE_GWEI");
+            }
+        }
+        Ok(strategy)
+    }
+
+    /// (max fee, priority fee) from the node's gas price and suggested tip
+    fn fees(&self, gas_price: u128, suggested_tip: u128) -> (u128, u128) {
+        let tip = self.max_priority_fee_per_gas.unwrap_or(suggested_tip);
+        // Headroom for the base fee rising before inclusion
+        let max_fee = self
+            .max_fee_per_gas
+            .unwrap_or_else(|| gas_price.saturating_mul(2).max(tip));
+        (max_fee, tip.min(max_fee))
+    }
+
+    fn scales_estimates(&self) -> bool {
+        self.gas_estimate_multiplier > 1.0
+    }
+
+    fn gas_limit(&self, estimate: u64) -> u64 {
+        (estimate as f64 * self.gas_estimate_multiplier).ceil() as u64
+    }
+}
+
+/// Delay before retry number `attempt` (starting at 1)
+fn backoff(base: Duration, attempt: u32) -> Duration {
+    base.saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF)
+}
+
+/// (max fee, priority fee) raised by `percent`, always by at least one wei
+fn bump_fees((max_fee, tip): (u128, u128), percent: u32) -> (u128, u128) {
+    let bump = |fee: u128| (fee.saturating_mul(100 + percent as u128) / 100).max(fee + 1);
+    (bump(max_fee), bump(tip))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SendFailure {
+    /// Would revert; retrying cannot help
+    Revert,
+    /// Our nonce is behind the chain's
+    NonceTooLow,
+    /// A transaction with this nonce is pending at a higher fee
+    Underpriced,
+    Transient,
+}
+
+fn classify(error: &str) -> SendFailure {
+    let error = error.to_lowercase();
+    if error.contains("revert") {
+        SendFailure::Revert
+    } else if error.contains("nonce too low") || error.contains("already known") {
+        SendFailure::NonceTooLow
+    } else if error.contains("underpriced") || error.contains("fee too low") {
+        SendFailure::Underpriced
+    } else {
+        SendFailure::Transient
+    }
+}
+
+// ======================== RECORDS ========================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TxStatus {
+    Queued,
+    Sent,
+    Mined,
+    Failed,
+    /// Unconfirmed when the server stopped and not found mined afterwards
+    Interrupted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedTx {
+    pub id: u64,
+    /// Contract function, e.g. "fpoPurchase"
+    pub label: String,
+    pub status: TxStatus,
+    /// Address that signed the transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+    /// Every hash broadcast for the nonce, replacements last
+    #[serde(default)]
+    pub tx_hashes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mined_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_fee_per_gas: Option<u128>,
+    /// Failed send attempts that were retried
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub queued_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+}
+
+struct Records {
+    path: String,
+    entries: Mutex<Vec<QueuedTx>>,
+}
+
+impl Records {
+    fn load(path: String) -> Result<Self> {
+        let entries = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid transaction queue file {}", path))?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Self {
+            path,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    fn save(&self, entries: &mut Vec<QueuedTx>) {
+        if entries.len() > MAX_RECORDS {
+            let excess = entries.len() - MAX_RECORDS;
+            entries.drain(..excess);
+        }
+        let written = serde_json::to_string_pretty(entries)
+            .context("Failed to serialize transaction queue")
+            .and_then(|json| {
+                if let Some(dir) = std::path::Path::new(&self.path).parent() {
+                    std::fs::create_dir_all(dir)
+                        .with_context(|| format!("Failed to create {}", dir.display()))?;
+                }
+                std::fs::write(&self.path, json)
+                    .with_context(|| format!("Failed to write {}", self.path))
+            });
+        if let Err(e) = written {
+            tracing::error!(error = %format!("{:#}", e), "Failed to save transaction queue");
+        }
+    }
+
+    async fn push(&self, label: &str) -> u64 {
+        let mut entries = self.entries.lock().await;
+        let id = entries.last().map(|t| t.id + 1).unwrap_or(1);
+        entries.push(QueuedTx {
+            id,
+            label: label.to_string(),
+            status: TxStatus::Queued,
+            signer: None,
+            nonce: None,
+            tx_hashes: Vec::new(),
+            mined_hash: None,
+            block_number: None,
+            max_fee_per_gas: None,
+            retries: 0,
+            error: None,
+            queued_at: chrono::Utc::now().to_rfc3339(),
+            finished_at: None,
+        });
+        self.save(&mut entries);
+        id
+    }
+
+    async fn update(&self, id: u64, f: impl FnOnce(&mut QueuedTx)) {
+        let mut entries = self.entries.lock().await;
+        if let Some(entry) = entries.iter_mut().find(|t| t.id == id) {
+            f(entry);
+            self.save(&mut entries);
+        }
+    }
+}
+
+// ======================== QUEUE ========================
+
+struct Submission {
+    id: u64,
+    label: String,
+    request: TransactionRequest,
+    /// Injected faults of the request that queued it
+    faults: Option<FaultPlan>,
+    reply: oneshot::Sender<Result<TransactionReceipt>>,
+}
+
+/// Handle to the writer task; cheap to clone
+#[derive(Clone)]
+pub struct TxQueue {
+    sender: mpsc::Sender<Submission>,
+    records: Arc<Records>,
+}
+
+impl TxQueue {
+    /// Load the queue file at `path` and start the writer task for `signers`
+    pub fn start(
+        provider: AppProvider,
+        signers: Arc<SignerSet>,
+        chain_id: u64,
+        gas: GasStrategy,
+        path: String,
+    ) -> Result<Self> {
+        let mut records = Records::load(path)?;
+        // Taken before anything new is queued
+        let unfinished = records
+            .entries
+            .get_mut()
+            .iter()
+            .filter(|t| matches!(t.status, TxStatus::Queued | TxStatus::Sent))
+            .cloned()
+            .collect();
+        Ok(Self::spawn(
+            provider,
+            signers,
+            chain_id,
+            gas,
+            Arc::new(records),
+            unfinished,
+        ))
+    }
+
+    /// Start a writer with changed chain settings on this queue's entries
+    pub fn restart(
+        &self,
+        provider: AppProvider,
+        signers: Arc<SignerSet>,
+        chain_id: u64,
+        gas: GasStrategy,
+    ) -> Self {
+        Self::spawn(
+            provider,
+            signers,
+            chain_id,
+            gas,
+            self.records.clone(),
+            Vec::new(),
+        )
+    }
+
+    fn spawn(
+        provider: AppProvider,
+        signers: Arc<SignerSet>,
+        chain_id: u64,
+        gas: GasStrategy,
+        records: Arc<Records>,
+        unfinished: Vec<QueuedTx>,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        let writer = Writer {
+            provider,
+            signers,
+            chain_id,
+            nonces: HashMap::new(),
+            config: QueueConfig::from_env(),
+            gas,
+            records: records.clone(),
+        };
+        tokio::spawn(writer.run(receiver, unfinished));
+        Self { sender, records }
+    }
+
+    /// Send a transaction through the queue and wait for its receipt
+    pub async fn submit(
+        &self,
+        label: &str,
+        request: TransactionRequest,
+    ) -> Result<TransactionReceipt> {
+        let id = self.records.push(label).await;
+        let (reply, response) = oneshot::channel();
+        self.sender
+            .send(Submission {
+                id,
+                label: label.to_string(),
+                request,
+                faults: faults::current(),
+                reply,
+            })
+            .await
+            .map_err(|_| anyhow!("Transaction queue is not running"))?;
+        response
+            .await
+            .context("Transaction queue dropped the transaction")?
+    }
+}
+
+struct Writer {
+    provider: AppProvider,
+    signers: Arc<SignerSet>,
+    chain_id: u64,
+    /// Next nonce per signer; re-read from the node when unknown
+    nonces: HashMap<Address, u64>,
+    config: QueueConfig,
+    gas: GasStrategy,
+    records: Arc<Records>,
+}
+
+impl Writer {
+    async fn run(mut self, mut receiver: mpsc::Receiver<Submission>, unfinished: Vec<QueuedTx>) {
+        self.recover(unfinished).await;
+
+        while let Some(submission) = receiver.recv().await {
+            // Picked per transaction so a key reload applies to the next one
+            let signer = self.signers.for_label(&submission.label);
+            let result = faults::run_with(
+                submission.faults,
+                self.process(submission.id, &signer, &submission.request),
+            )
+            .await;
+            let now = chrono::Utc::now().to_rfc3339();
+            match &result {
+                Ok(receipt) => {
+                    self.records
+                        .update(submission.id, |t| {
+                            t.status = TxStatus::Mined;
+                            t.mined_hash = Some(format_tx_hash(receipt.transaction_hash));
+                            t.block_number = receipt.block_number;
+                            t.finished_at = Some(now);
+                        })
+                        .await
+                }
+                Err(e) => {
+                    tracing::error!(id = submission.id, error = %format!("{:#}", e), "Queued transaction failed");
+                    self.records
+                        .update(submission.id, |t| {
+                            t.status = TxStatus::Failed;
+                            t.error = Some(format!("{:#}", e));
+                            t.finished_at = Some(now);
+                        })
+                        .await
+                }
+            }
+            // The caller may have given up waiting; the record stays
+            let _ = submission.reply.send(result);
+        }
+    }
+
+    /// Settle entries left unfinished by a previous run
+    async fn recover(&self, unfinished: Vec<QueuedTx>) {
+        for entry in unfinished {
+            let hashes: Vec<TxHash> = entry
+                .tx_hashes
+                .iter()
+                .filter_map(|h| h.parse().ok())
+                .collect();
+            let receipt = self.find_receipt(&hashes).await;
+            let now = chrono::Utc::now().to_rfc3339();
+            tracing::warn!(
+                id = entry.id,
+                label = %entry.label,
+                mined = receipt.is_some(),
+                "Transaction left unfinished by the previous run"
+            );
+            self.records
+                .update(entry.id, |t| {
+                    match receipt {
+                        Some(receipt) => {
+                            t.status = TxStatus::Mined;
+                            t.mined_hash = Some(format_tx_hash(receipt.transaction_hash));
+                            t.block_number = receipt.block_number;
+                        }
+                        None => t.status = TxStatus::Interrupted,
+                    }
+                    t.finished_at = Some(now);
+                })
+                .await;
+        }
+    }
+
+    async fn process(
+        &mut self,
+        id: u64,
+        signer: &TxSigner,
+        request: &TransactionRequest,
+    ) -> Result<TransactionReceipt> {
+        // Gains a scaled gas limit on the first attempt, kept for replacements
+        let mut request = request.clone();
+        let mut fees = None;
+        let mut attempt = 0;
+        loop {
+            match self.send(id, signer, &mut request, &mut fees).await {
+                Ok((nonce, hash)) => {
+                    let fees = fees.context("Fees are set once a transaction is sent")?;
+                    return self.confirm(id, signer, &request, nonce, hash, fees).await;
+                }
+                Err(e) => {
+                    let error = format!("{:#}", e);
+                    let failure = classify(&error);
+                    if failure == SendFailure::Revert || attempt >= self.config.max_retries {
+                        return Err(e);
+                    }
+                    match failure {
+                        SendFailure::NonceTooLow => {
+                            self.nonces.remove(&signer.address());
+                        }
+                        SendFailure::Underpriced => {
+                            fees = fees.map(|f| bump_fees(f, self.config.fee_bump_percent))
+                        }
+                        _ => {}
+                    }
+
+                    attempt += 1;
+                    let delay = backoff(self.config.retry_base, attempt);
+                    tracing::warn!(
+                        id,
+                        attempt,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %error,
+                        "Transaction send failed, retrying"
+                    );
+                    self.records.update(id, |t| t.retries = attempt).await;
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    /// Send `request` at the signer's next nonce
+    async fn send(
+        &mut self,
+        id: u64,
+        signer: &TxSigner,
+        request: &mut TransactionRequest,
+        fees: &mut Option<(u128, u128)>,
+    ) -> Result<(u64, TxHash)> {
+        let from = signer.address();
+        // Signed here rather than by the provider, so the limit is set up front
+        if TransactionBuilder::gas_limit(request).is_none() {
+            let estimate = request.clone().with_from(from);
+            let gas = self
+                .provider
+                .estimate_gas(&estimate)
+                .await
+                .context("Failed to estimate gas")?;
+            let gas = if self.gas.scales_estimates() {
+                self.gas.gas_limit(gas)
+            } else {
+                gas
+            };
+            request.set_gas_limit(gas);
+        }
+
+        let nonce = match self.nonces.get(&from) {
+            Some(nonce) => *nonce,
+            None => self
+                .provider
+                .get_transaction_count(from)
+                .pending()
+                .await
+                .with_context(|| format!("Failed to read the nonce of signer {:?}", from))?,
+        };
+        let (max_fee, tip) = match *fees {
+            Some(fees) => fees,
+            None => *fees.insert(self.current_fees().await?),
+        };
+
+        if faults::fire(Fault::NonceError) {
+            bail!("Failed to send transaction: nonce too low (injected fault)");
+        }
+        let hash = self
+            .broadcast(signer, request, nonce, (max_fee, tip))
+            .await?;
+        self.nonces.insert(from, nonce + 1);
+        self.records
+            .update(id, |t| {
+                t.status = TxStatus::Sent;
+                t.signer = Some(format!("{:?}", from));
+                t.nonce = Some(nonce);
+                t.tx_hashes.push(format_tx_hash(hash));
+                t.max_fee_per_gas = Some(max_fee);
+            })
+            .await;
+        Ok((nonce, hash))
+    }
+
+    async fn broadcast(
+        &self,
+        signer: &TxSigner,
+        request: &TransactionRequest,
+        nonce: u64,
+        (max_fee, tip): (u128, u128),
+    ) -> Result<TxHash> {
+        let tx = request
+            .clone()
+            .with_from(signer.address())
+            .with_chain_id(self.chain_id)
+            .with_nonce(nonce)
+            .with_max_fee_per_gas(max_fee)
+            .with_max_priority_fee_per_gas(tip);
+        let pending = match signer {
+            TxSigner::Local(key) => {
+                let envelope = tx
+                    .build(&EthereumWallet::from(key.clone()))
+                    .await
+                    .context("Failed to sign transaction")?;
+                self.provider.send_tx_envelope(envelope).await
+            }
+            TxSigner::Remote { signer, .. } => {
+                let raw = signer.sign_transaction(&tx).await?;
+                self.provider.send_raw_transaction(&raw).await
+            }
+        }
+        .context("Failed to send transaction")?;
+        Ok(*pending.tx_hash())
+    }
+
+    async fn current_fees(&self) -> Result<(u128, u128)> {
+        let gas_price = match self.gas.max_fee_per_gas {
+            Some(_) => 0,
+            None => self
+                .provider
+                .get_gas_price()
+                .await
+                .context("Failed to read gas price")?,
+        };
+        let tip = match self.gas.max_priority_fee_per_gas {
+            Some(tip) => tip,
+            None => self
+                .provider
+                .get_max_priority_fee_per_gas()
+                .await
+                .context("Failed to read priority fee")?,
+        };
+        Ok(self.gas.fees(gas_price, tip))
+    }
+
+    /// Wait for the transaction at `nonce`, replacing it with higher fees
+    /// whenever it is not mined in time
+    async fn confirm(
+        &mut self,
+        id: u64,
+        signer: &TxSigner,
+        request: &TransactionRequest,
+        nonce: u64,
+        first: TxHash,
+        mut fees: (u128, u128),
+    ) -> Result<TransactionReceipt> {
+        let mut hashes = vec![first];
+        let mut replacements = 0;
+        let withheld_until =
+            faults::fire(Fault::SlowReceipt).then(|| Instant::now() + faults::delay());
+        loop {
+            let deadline = Instant::now() + self.config.receipt_timeout;
+            while Instant::now() < deadline {
+                // An injected slow receipt is not looked up until its delay passes
+                let withheld = withheld_until.is_some_and(|until| Instant::now() < until);
+                if !withheld {
+                    if let Some(receipt) = self.find_receipt(&hashes).await {
+                        return Ok(receipt);
+                    }
+                }
+                tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+            }
+
+            if replacements >= self.config.max_replacements {
+                // The nonce may still be taken by any of the hashes
+                self.nonces.remove(&signer.address());
+                bail!(
+                    "Transaction at nonce {} not mined after {} replacement(s); last hash {}",
+                    nonce,
+                    replacements,
+                    format_tx_hash(hashes[hashes.len() - 1])
+                );
+            }
+
+            replacements += 1;
+            fees = bump_fees(fees, self.config.fee_bump_percent);
+            match self.broadcast(signer, request, nonce, fees).await {
+                Ok(hash) => {
+                    tracing::warn!(id, nonce, replacement = %format_tx_hash(hash), "Transaction not mined in time, replaced with higher fees");
+                    hashes.push(hash);
+                    self.records
+                        .update(id, |t| {
+                            t.tx_hashes.push(format_tx_hash(hash));
+                            t.max_fee_per_gas = Some(fees.0);
+                        })
+                        .await;
+                }
+                // "nonce too low" here means one of the hashes was just mined
+                Err(e) => {
+                    tracing::warn!(id, nonce, error = %format!("{:#}", e), "Replacement not sent")
+                }
+            }
+        }
+    }
+
+    /// Receipt of whichever of `hashes` was mined
+    async fn find_receipt(&self, hashes: &[TxHash]) -> Option<TransactionReceipt> {
+        for hash in hashes.iter().rev() {
+            match self.provider.get_transaction_receipt(*hash).await {
+                Ok(Some(receipt)) => return Some(receipt),
+                Ok(None) => {}
+                Err(e) => tracing::debug!(tx_hash = ?hash, error = %e, "Receipt lookup failed"),
+            }
+        }
+        None
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct TxQueueView {
+    /// Transactions queued or awaiting a receipt
+    pub in_flight: usize,
+    /// Newest first
+    pub transactions: Page<QueuedTx>,
+}
+
+/// Entries of the transaction queue (admin only)
+pub async fn list_transactions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(params): Query<PageParams>,
+) -> ApiResult<TxQueueView> {
+    require_admin(&state, &headers)?;
+
+    let chain = state.networks.select(&headers)?;
+    let entries = chain.tx_queue().records.entries.lock().await.clone();
+    let in_flight = entries
+        .iter()
+        .filter(|t| matches!(t.status, TxStatus::Queued | TxStatus::Sent))
+        .count();
+    Ok(Json(TxQueueView {
+        in_flight,
+        transactions: paginate("tx-queue", entries, &params, |t| Reverse(t.id))?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        let base = Duration::from_millis(500);
+        assert_eq!(backoff(base, 1), Duration::from_millis(500));
+        assert_eq!(backoff(base, 3), Duration::from_secs(2));
+        assert_eq!(backoff(base, 40), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_gas_strategy_overrides_node_fees() {
+        assert_eq!(parse_gwei("1.5").unwrap(), 1_500_000_000);
+        assert!(parse_gwei("-2").is_err());
+
+        let node = GasStrategy::default();
+        assert_eq!(node.fees(30, 2), (60, 2));
+        assert!(!node.scales_estimates());
+
+        let fixed = GasStrategy {
+            max_fee_per_gas: Some(50),
+            max_priority_fee_per_gas: None,
+            gas_estimate_multiplier: 1.25,
+        };
+        // A suggested tip above the fixed max fee is capped
+        assert_eq!(fixed.fees(30, 80), (50, 50));
+        assert_eq!(fixed.gas_limit(100_001), 125_002);
+    }
+
+    #[test]
+    fn test_bump_fees_raises_both_fees() {
+        assert_eq!(bump_fees((100, 10), 20), (120, 12));
+        // Tiny fees still go up
+        assert_eq!(bump_fees((1, 0), 20), (2, 1));
+    }
+
+    #[test]
+    fn test_classify_send_errors() {
+        assert_eq!(
+            classify("server returned an error response: error code 3: execution reverted: NotAuthorized"),
+            SendFailure::Revert
+        );
+        assert_eq!(
+            classify("nonce too low: next nonce 42"),
+            SendFailure::NonceTooLow
+        );
+        assert_eq!(
+            classify("replacement transaction underpriced"),
+            SendFailure::Underpriced
+        );
+        assert_eq!(
+            classify("error sending request: connection reset"),
+            SendFailure::Transient
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! USSD and IVR gateway endpoints
+//!
+//! Both channels share one menu tree. The caller's position in the tree is
+//! the `*`-separated list of keypad inputs so far (the USSD convention), so
+//! the service keeps no session state of its own:
+//!
+//! ```text
+//! (root)    → 1 Registration status | 2 Last payment | 3 Batch status | 4 Find farmer
+//! 3*<code>  → status of the caller's batch with that 6-digit code
+//! 4*<code>  → name and verification state of the farmer with that code
+//! 4*<code>*<n> → the n-th of several farmers sharing that code
+//! ```
+//!
+//! Every menu except the root is for registered callers only.
+//!
+//! - `POST /api/ussd/session` takes the gateway's form post and answers in
+//!   plain text prefixed with `CON` (expect more input) or `END`.
+//! - `POST /api/ivr/step` takes JSON with the previous `state` and the new
+//!   DTMF `input` and returns the prompt to speak plus the next `state`.
+
+use crate::batch_ledger::{self, short_code};
+use crate::error::{ApiError, ApiResult};
+use crate::farmer_verification::FarmerEntry;
+use crate::sms::{self, normalize_mobile, verify_webhook_secret, SmsQuery};
+use crate::state::AppState;
+use axum::{extract::State, http::HeaderMap, Form, Json};
+use serde::{Deserialize, Serialize};
+
+// ======================== MENU ENGINE ========================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MenuStep {
+    pub prompt: String,
+    pub end: bool,
+}
+
+impl MenuStep {
+    fn more(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            end: false,
+        }
+    }
+
+    fn end(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            end: true,
+        }
+    }
+}
+
+const MAIN_MENU: &str =
+    "Oilseed Value Chain\n1. Registration status\n2. Last payment\n3. Batch status\n4. Find farmer";
+
+/// Split accumulated keypad input (`"3*123456"`) into menu selections
+pub fn parse_path(text: &str) -> Vec<String> {
+    text.split('*')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Resolve the next menu step for a caller at `path`
+pub fn navigate(
+    path: &[String],
+    caller: Option<&FarmerEntry>,
+    farmers: &[FarmerEntry],
+) -> MenuStep {
+    let Some(choice) = path.first() else {
+        return MenuStep::more(MAIN_MENU);
+    };
+
+    match (choice.as_str(), caller) {
+        (_, None) => MenuStep::end("This number is not registered. Please contact your FPO."),
+        ("1", Some(farmer)) => MenuStep::end(format!(
+            "Registered as {} ({}) since {}. Farmer code {}. {}",
+            farmer.name,
+            farmer.location,
+            farmer.registration_date,
+            short_code(&farmer.farmer_did),
+            if farmer.verified {
+                "Verified"
+            } else {
+                "Verification pending"
+            }
+        )),
+        ("2", Some(farmer)) => match sms::answer_query(farmer, &SmsQuery::Payment) {
+            Ok(reply) => MenuStep::end(reply),
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to read payments for USSD");
+                MenuStep::end("Payment information is unavailable. Try again later.")
+            }
+        },
+        ("3", Some(farmer)) => match path.get(1) {
+            None => MenuStep::more("Enter 6-digit batch code"),
+            Some(code) => batch_by_code(farmer, code),
+        },
+        ("4", Some(_)) => match path.get(1) {
+            None => MenuStep::more("Enter 6-digit farmer code"),
+            Some(code) => farmer_by_code(farmers, code, path.get(2)),
+        },
+        _ => MenuStep::end("Invalid choice"),
+    }
+}
+
+/// Farmer with a short code; several farmers sharing it are listed for the
+/// caller to pick one
+fn farmer_by_code(farmers: &[FarmerEntry], code: &str, pick: Option<&String>) -> MenuStep {
+    let mut matches: Vec<&FarmerEntry> = farmers
+        .iter()
+        .filter(|f| short_code(&f.farmer_did) == code)
+        .collect();
+    matches.sort_by(|a, b| a.farmer_did.cmp(&b.farmer_did));
+
+    let farmer = match (matches.as_slice(), pick) {
+        ([], _) => return MenuStep::end(format!("No farmer with code {}", code)),
+        ([farmer], None) => *farmer,
+        (_, None) => {
+            let options: Vec<String> = matches
+                .iter()
+                .enumerate()
+                .map(|(i, f)| format!("{}. {}, {}", i + 1, f.name, f.location))
+                .collect();
+            return MenuStep::more(format!(
+                "Several farmers have code {}:\n{}",
+                code,
+                options.join("\n")
+            ));
+        }
+        (_, Some(pick)) => match pick
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|i| matches.get(i))
+        {
+            Some(farmer) => *farmer,
+            None => return MenuStep::end("Invalid choice"),
+        },
+    };
+    MenuStep::end(format!(
+        "Farmer {}: {}, {}. {}",
+        code,
+        farmer.name,
+        farmer.location,
+        if farmer.verified {
+            "Verified"
+        } else {
+            "Not verified"
+        }
+    ))
+}
+
+fn batch_by_code(farmer: &FarmerEntry, code: &str) -> MenuStep {
+    let payments = match batch_ledger::payments_for_farmer(&farmer.farmer_did) {
+        Ok(payments) => payments,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to list batches for USSD");
+            return MenuStep::end("Batch information is unavailable. Try again later.");
+        }
+    };
+
+    match payments.iter().find(|p| short_code(&p.batch_id) == code) {
+        Some(payment) => {
+            let query = SmsQuery::Status {
+                batch_id: payment.batch_id.clone(),
+            };
+            match sms::answer_query(farmer, &query) {
+                Ok(reply) => MenuStep::end(reply),
+                Err(e) => {
+                    tracing::error!(error = %e, "Failed to read batch status for USSD");
+                    MenuStep::end("Batch information is unavailable. Try again later.")
+                }
+            }
+        }
+        None => MenuStep::end(format!("No batch with code {} for your number", code)),
+    }
+}
+
+async fn resolve_step(state: &AppState, phone: &str, path: &[String]) -> MenuStep {
+    let mobile = normalize_mobile(phone);
+    let farmer_verification = &state.farmer_verification;
+    let lookup = async {
+        let caller = farmer_verification.get_farmer_by_mobile(&mobile).await?;
+
+        // Only the farmer lookup branch needs the full list
+        let farmers: Vec<FarmerEntry> =
+            if caller.is_some() && path.first().map(String::as_str) == Some("4") {
+                farmer_verification.farmers().await?
+            } else {
+                Vec::new()
+            };
+        anyhow::Ok((caller, farmers))
+    };
+    let (caller, farmers) = match lookup.await {
+        Ok(found) => found,
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to read farmer database for USSD");
+            return MenuStep::end("Service is unavailable. Try again later.");
+        }
+    };
+
+    navigate(path, caller.as_ref(), &farmers)
+}
+
+// ======================== USSD GATEWAY ========================
+
+/// Form fields posted by USSD gateways (Africa's Talking naming)
+#[derive(Debug, Deserialize)]
+pub struct UssdRequest {
+    #[serde(rename = "sessionId", alias = "session_id")]
+    pub session_id: String,
+    #[serde(rename = "phoneNumber", alias = "phone_number")]
+    pub phone_number: String,
+    #[serde(default)]
+    pub text: String,
+}
+
+pub async fn ussd_session(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Form(payload): Form<UssdRequest>,
+) -> Result<String, ApiError> {
+    verify_webhook_secret(&headers)?;
+
+    let path = parse_path(&payload.text);
+    tracing::info!(session_id = %payload.session_id, depth = path.len(), "USSD request");
+
+    let step = resolve_step(&state, &payload.phone_number, &path).await;
+    Ok(ussd_text(&step))
+}
+
+/// Gateway reply for `step`: `CON` expects more input, `END` closes the session
+pub fn ussd_text(step: &MenuStep) -> String {
+    let prefix = if step.end { "END" } else { "CON" };
+    format!("{} {}", prefix, step.prompt)
+}
+
+// ======================== IVR GATEWAY ========================
+
+#[derive(Debug, Deserialize)]
+pub struct IvrRequest {
+    pub caller: String,
+    /// State returned by the previous step; absent on the first call
+    #[serde(default)]
+    pub state: Option<String>,
+    /// DTMF digits entered since the previous step
+    #[serde(default)]
+    pub input: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IvrResponse {
+    pub prompt: String,
+    pub state: String,
+    pub end: bool,
+}
+
+impl IvrRequest {
+    /// Menu path after this step's input
+    pub fn path(&self) -> Vec<String> {
+        let mut path = parse_path(self.state.as_deref().unwrap_or_default());
+        if let Some(input) = self.input.as_deref().map(str::trim) {
+            if !input.is_empty() {
+                path.push(input.trim_end_matches('#').to_string());
+            }
+        }
+        path
+    }
+}
+
+impl IvrResponse {
+    pub fn new(path: &[String], step: MenuStep) -> Self {
+        Self {
+            prompt: step.prompt,
+            state: path.join("*"),
+            end: step.end,
+        }
+    }
+}
+
+pub async fn ivr_step(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<IvrRequest>,
+) -> ApiResult<IvrResponse> {
+    verify_webhook_secret(&headers)?;
+
+    let path = payload.path();
+    let step = resolve_step(&state, &payload.caller, &path).await;
+
+    Ok(Json(IvrResponse::new(&path, step)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn farmer() -> FarmerEntry {
+        FarmerEntry {
+            mobile: "9876543210".to_string(),
+            farmer_did: "0x123abc".to_string(),
+            name: "Test Farmer".to_string(),
+            location: "Punjab".to_string(),
+            state_code: "PB".to_string(),
+            district_code: "PB001".to_string(),
+            land_acres: 5.0,
+            crop: "mustard".to_string(),
+            verified: true,
+            registration_date: "2024-01-15".to_string(),
+            ipfscid: "".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_root_shows_main_menu() {
+        let step = navigate(&parse_path(""), None, &[]);
+        assert!(!step.end);
+        assert!(step.prompt.contains("1. Registration status"));
+    }
+
+    #[test]
+    fn test_unregistered_caller_cannot_open_personal_menus() {
+        let step = navigate(&parse_path("1"), None, &[]);
+        assert!(step.end);
+        assert!(step.prompt.contains("not registered"));
+    }
+
+    #[test]
+    fn test_farmer_lookup_by_code() {
+        let farmer = farmer();
+        let code = short_code(&farmer.farmer_did);
+        let path = parse_path(&format!("4*{}", code));
+
+        let step = navigate(&path, Some(&farmer), std::slice::from_ref(&farmer));
+        assert!(step.end);
+        assert!(step.prompt.contains("Test Farmer"));
+
+        let step = navigate(&path, None, &[farmer]);
+        assert!(step.prompt.contains("not registered"));
+    }
+
+    #[test]
+    fn test_shared_farmer_code_asks_which_farmer() {
+        let caller = farmer();
+        let mut first = farmer();
+        first.farmer_did = "0x00056c".to_string();
+        first.name = "Asha Devi".to_string();
+        let mut second = farmer();
+        second.farmer_did = "0x0002ed".to_string();
+        second.name = "Ravi Kumar".to_string();
+        let farmers = [first, second];
+        assert_eq!(short_code("0x00056c"), short_code("0x0002ed"));
+
+        let step = navigate(&parse_path("4*468152"), Some(&caller), &farmers);
+        assert!(!step.end);
+        assert_eq!(
+            step.prompt,
+            "Several farmers have code 468152:\n1. Ravi Kumar, Punjab\n2. Asha Devi, Punjab"
+        );
+
+        let step = navigate(&parse_path("4*468152*2"), Some(&caller), &farmers);
+        assert_eq!(step.prompt, "Farmer 468152: Asha Devi, Punjab. Verified");
+        let step = navigate(&parse_path("4*468152*3"), Some(&caller), &farmers);
+        assert_eq!(step.prompt, "Invalid choice");
+    }
+
+    #[test]
+    fn test_registration_status() {
+        let farmer = farmer();
+        let step = navigate(&parse_path("1"), Some(&farmer), &[]);
+        assert!(step.prompt.starts_with("Registered as Test Farmer"));
+    }
+}
@@ -0,0 +1,554 @@
+//! Short evidence videos with resumable chunked upload
+//!
+//! Procurement weighing and the breaking of seals at the processor are
+//! filmed on site, often over a poor connection, so videos are sent in
+//! chunks of [`CHUNK_SIZE`] bytes:
+//!
+//! 1. `POST /api/evidence/videos/uploads` opens an upload for a known size
+//!    and returns its `upload_id`.
+//! 2. `PUT .../uploads/:upload_id/chunks/:index` stores one chunk; chunks
+//!    may arrive in any order and be re-sent. `GET .../uploads/:upload_id`
+//!    lists the chunks still missing, so an interrupted upload resumes
+//!    where it stopped.
+//! 3. `POST .../uploads/:upload_id/complete` assembles the file, checks its
+//!    size and the keccak256 the client announced, and pins it to IPFS.
+//!
+//! Purchases and processing records reference videos by CID (`videos`),
+//! which puts the video hash into the metadata whose hash goes on-chain.
+//! With object storage configured, videos are kept there and the CID is that
+//! of their pointer document (see [`crate::object_storage`]).
+//! Open uploads live in `data/video_uploads.json` with their chunks under
+//! `data/video_uploads/`, and are dropped after VIDEO_UPLOAD_TTL_HOURS
+//! (default 24). Videos are limited to VIDEO_MAX_MB (default 100).
+
+use crate::chain::hash_bytes;
+use crate::error::{format_hash, ipfs_gateway_url, ApiError, ApiResult};
+use crate::object_storage;
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+const VIDEOS_PATH: &str = "data/video_evidence.json";
+const UPLOADS_PATH: &str = "data/video_uploads.json";
+const CHUNKS_DIR: &str = "data/video_uploads";
+/// Size of every chunk but the last
+pub const CHUNK_SIZE: u64 = 4 * 1024 * 1024;
+const DEFAULT_MAX_MB: u64 = 100;
+const DEFAULT_UPLOAD_TTL_HOURS: i64 = 24;
+/// Videos referenced by one purchase or processing record
+const MAX_VIDEOS_PER_RECORD: usize = 5;
+const CONTENT_TYPES: [(&str, &str); 3] = [
+    ("video/mp4", "mp4"),
+    ("video/webm", "webm"),
+    ("video/quicktime", "mov"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VideoPurpose {
+    /// Weighing at purchase; `reference` is the batch ID
+    Procurement,
+    /// Seal-breaking at the processor; `reference` is the input batch ID
+    Processing,
+}
+
+/// Number of chunks a file of `size` bytes is split into
+fn chunk_count(size: u64) -> u32 {
+    size.div_ceil(CHUNK_SIZE) as u32
+}
+
+/// Expected length of chunk `index`, `None` past the last chunk
+fn chunk_len(size: u64, index: u32) -> Option<u64> {
+    let start = u64::from(index) * CHUNK_SIZE;
+    (index < chunk_count(size)).then(|| (size - start).min(CHUNK_SIZE))
+}
+
+/// Chunk indices not received yet, ascending
+fn missing_chunks(size: u64, received: &[u32]) -> Vec<u32> {
+    (0..chunk_count(size))
+        .filter(|i| !received.contains(i))
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadSession {
+    pub upload_id: String,
+    pub purpose: VideoPurpose,
+    pub reference: String,
+    pub content_type: String,
+    pub size_bytes: u64,
+    /// keccak256 announced by the client, checked on completion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub video_hash: Option<String>,
+    /// Chunk indices stored so far
+    pub received: Vec<u32>,
+    pub created_at: String,
+}
+
+impl UploadSession {
+    fn chunk_dir(&self) -> String {
+        format!("{}/{}", CHUNKS_DIR, self.upload_id)
+    }
+
+    fn chunk_path(&self, index: u32) -> String {
+        format!("{}/{}.part", self.chunk_dir(), index)
+    }
+
+    fn expired(&self, ttl: Duration, now: DateTime<Utc>) -> bool {
+        DateTime::parse_from_rfc3339(&self.created_at)
+            .map(|t| now - t.with_timezone(&Utc) > ttl)
+            .unwrap_or(true)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VideoEvidence {
+    pub video_cid: String,
+    /// keccak256 of the video bytes
+    pub video_hash: String,
+    pub content_type: String,
+    pub size_bytes: u64,
+    pub purpose: VideoPurpose,
+    pub reference: String,
+    pub received_at: String,
+    /// Key in object storage, when the video is kept there
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_key: Option<String>,
+}
+
+impl VideoEvidence {
+    /// Entry embedded in purchase and processing metadata
+    pub fn metadata_entry(&self) -> serde_json::Value {
+        serde_json::json!({
+            "video_cid": self.video_cid,
+            "video_hash": self.video_hash,
+            "content_type": self.content_type,
+            "size_bytes": self.size_bytes,
+        })
+    }
+}
+
+pub struct VideoEvidenceStore {
+    uploads: Mutex<Vec<UploadSession>>,
+    videos: Mutex<Vec<VideoEvidence>>,
+    max_bytes: u64,
+    upload_ttl: Duration,
+}
+
+fn load_list<T: for<'de> Deserialize<'de>>(path: &str) -> Result<Vec<T>> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => {
+            serde_json::from_str(&content).with_context(|| format!("Invalid JSON in {}", path))
+        }
+        Err(_) => Ok(Vec::new()),
+    }
+}
+
+fn save_list<T: Serialize>(path: &str, items: &[T]) -> Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(items)?)
+        .with_context(|| format!("Failed to write {}", path))
+}
+
+impl VideoEvidenceStore {
+    pub fn load() -> Result<Self> {
+        let max_mb = std::env::var("VIDEO_MAX_MB")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_MB);
+        let ttl_hours = std::env::var("VIDEO_UPLOAD_TTL_HOURS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_UPLOAD_TTL_HOURS);
+
+        Ok(Self {
+            uploads: Mutex::new(load_list(UPLOADS_PATH)?),
+            videos: Mutex::new(load_list(VIDEOS_PATH)?),
+            max_bytes: max_mb * 1024 * 1024,
+            upload_ttl: Duration::hours(ttl_hours),
+        })
+    }
+
+    /// Videos referenced by `video_cids`, which must all have been uploaded
+    /// for `purpose` and `reference`
+    pub async fn resolve(
+        &self,
+        video_cids: &[String],
+        purpose: VideoPurpose,
+        reference: &str,
+    ) -> Result<Vec<VideoEvidence>, ApiError> {
+        if video_cids.len() > MAX_VIDEOS_PER_RECORD {
+            return Err(ApiError::bad_request(format!(
+                "At most {} videos per record",
+                MAX_VIDEOS_PER_RECORD
+            )));
+        }
+        let videos = self.videos.lock().await;
+        video_cids
+            .iter()
+            .map(|cid| {
+                let video = videos
+                    .iter()
+                    .find(|v| &v.video_cid == cid)
+                    .ok_or_else(|| ApiError::bad_request(format!("Unknown video {}", cid)))?;
+                if video.purpose != purpose || video.reference != reference {
+                    return Err(ApiError::bad_request(format!(
+                        "Video {} was not recorded for {}",
+                        cid, reference
+                    )));
+                }
+                Ok(video.clone())
+            })
+            .collect()
+    }
+
+    /// Object storage key of the video pinned as `video_cid`
+    pub async fn object_key(&self, video_cid: &str) -> Option<String> {
+        self.videos
+            .lock()
+            .await
+            .iter()
+            .find(|v| v.video_cid == video_cid)
+            .and_then(|v| v.object_key.clone())
+    }
+
+    async fn session(&self, upload_id: &str) -> Result<UploadSession, ApiError> {
+        self.uploads
+            .lock()
+            .await
+            .iter()
+            .find(|s| s.upload_id == upload_id)
+            .cloned()
+            .ok_or_else(|| ApiError::not_found(format!("Upload {} not found", upload_id)))
+    }
+}
+
+/// Read the chunks of a complete upload back into one file
+fn assemble(session: &UploadSession) -> Result<Vec<u8>> {
+    let mut video = Vec::with_capacity(session.size_bytes as usize);
+    for index in 0..chunk_count(session.size_bytes) {
+        let path = session.chunk_path(index);
+        let chunk = std::fs::read(&path).with_context(|| format!("Failed to read {}", path))?;
+        video.extend_from_slice(&chunk);
+    }
+    Ok(video)
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct CreateUploadRequest {
+    pub purpose: VideoPurpose,
+    /// Batch ID for procurement, input batch ID for processing
+    pub reference: String,
+    pub content_type: String,
+    pub size_bytes: u64,
+    #[serde(default)]
+    pub video_hash: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UploadStatusResponse {
+    #[serde(flatten)]
+    pub session: UploadSession,
+    pub chunk_size: u64,
+    pub chunk_count: u32,
+    pub missing: Vec<u32>,
+}
+
+impl From<UploadSession> for UploadStatusResponse {
+    fn from(session: UploadSession) -> Self {
+        Self {
+            chunk_size: CHUNK_SIZE,
+            chunk_count: chunk_count(session.size_bytes),
+            missing: missing_chunks(session.size_bytes, &session.received),
+            session,
+        }
+    }
+}
+
+/// Open a chunked upload for an evidence video
+pub async fn create_upload(
+    State(state): State<AppState>,
+    Json(payload): Json<CreateUploadRequest>,
+) -> ApiResult<UploadStatusResponse> {
+    let store = &state.videos;
+    if payload.reference.trim().is_empty() {
+        return Err(ApiError::bad_request("reference is required"));
+    }
+    if !CONTENT_TYPES
+        .iter()
+        .any(|(t, _)| *t == payload.content_type)
+    {
+        return Err(ApiError::bad_request(format!(
+            "content_type must be one of {}",
+            CONTENT_TYPES.map(|(t, _)| t).join(", ")
+        )));
+    }
+    if payload.size_bytes == 0 || payload.size_bytes > store.max_bytes {
+        return Err(ApiError::bad_request(format!(
+            "Video must be between 1 byte and {} MB",
+            store.max_bytes / (1024 * 1024)
+        )));
+    }
+
+    let session = UploadSession {
+        upload_id: hex::encode(rand::random::<[u8; 16]>()),
+        purpose: payload.purpose,
+        reference: payload.reference,
+        content_type: payload.content_type,
+        size_bytes: payload.size_bytes,
+        video_hash: payload.video_hash.map(|h| h.to_lowercase()),
+        received: Vec::new(),
+        created_at: Utc::now().to_rfc3339(),
+    };
+    std::fs::create_dir_all(session.chunk_dir())
+        .with_context(|| format!("Failed to create {}", session.chunk_dir()))?;
+
+    let mut uploads = store.uploads.lock().await;
+    let now = Utc::now();
+    uploads.retain(|s| {
+        let expired = s.expired(store.upload_ttl, now);
+        if expired {
+            let _ = std::fs::remove_dir_all(s.chunk_dir());
+        }
+        !expired
+    });
+    uploads.push(session.clone());
+    save_list(UPLOADS_PATH, &uploads)?;
+
+    tracing::info!(
+        upload_id = %session.upload_id,
+        purpose = ?session.purpose,
+        reference = %session.reference,
+        size_bytes = session.size_bytes,
+        "Opened evidence video upload"
+    );
+    Ok(Json(session.into()))
+}
+
+/// Received and missing chunks of an upload
+pub async fn upload_status(
+    State(state): State<AppState>,
+    Path(upload_id): Path<String>,
+) -> ApiResult<UploadStatusResponse> {
+    Ok(Json(state.videos.session(&upload_id).await?.into()))
+}
+
+/// Store one chunk; re-sending a chunk replaces it
+pub async fn put_chunk(
+    State(state): State<AppState>,
+    Path((upload_id, index)): Path<(String, u32)>,
+    body: Bytes,
+) -> ApiResult<UploadStatusResponse> {
+    let store = &state.videos;
+    let session = store.session(&upload_id).await?;
+    let expected = chunk_len(session.size_bytes, index).ok_or_else(|| {
+        ApiError::bad_request(format!(
+            "Chunk {} is out of range, upload has {} chunks",
+            index,
+            chunk_count(session.size_bytes)
+        ))
+    })?;
+    if body.len() as u64 != expected {
+        return Err(ApiError::bad_request(format!(
+            "Chunk {} must be {} bytes, got {}",
+            index,
+            expected,
+            body.len()
+        )));
+    }
+
+    tokio::fs::write(session.chunk_path(index), &body)
+        .await
+        .with_context(|| format!("Failed to write chunk {} of {}", index, upload_id))?;
+
+    let mut uploads = store.uploads.lock().await;
+    let session = uploads
+        .iter_mut()
+        .find(|s| s.upload_id == upload_id)
+        .ok_or_else(|| ApiError::not_found(format!("Upload {} not found", upload_id)))?;
+    if !session.received.contains(&index) {
+        session.received.push(index);
+        session.received.sort_unstable();
+    }
+    let session = session.clone();
+    save_list(UPLOADS_PATH, &uploads)?;
+
+    Ok(Json(session.into()))
+}
+
+#[derive(Debug, Serialize)]
+pub struct VideoEvidenceResponse {
+    #[serde(flatten)]
+    pub video: VideoEvidence,
+    pub ipfs_url: String,
+}
+
+/// Assemble a fully received upload and pin the video to IPFS
+pub async fn complete_upload(
+    State(state): State<AppState>,
+    Path(upload_id): Path<String>,
+) -> ApiResult<VideoEvidenceResponse> {
+    let store = &state.videos;
+    // Taken out of the list so a concurrent completion cannot pin it twice
+    let session = {
+        let mut uploads = store.uploads.lock().await;
+        let position = uploads
+            .iter()
+            .position(|s| s.upload_id == upload_id)
+            .ok_or_else(|| ApiError::not_found(format!("Upload {} not found", upload_id)))?;
+        let missing = missing_chunks(uploads[position].size_bytes, &uploads[position].received);
+        if !missing.is_empty() {
+            return Err(ApiError::bad_request(format!(
+                "Upload is missing chunks {:?}",
+                missing
+            )));
+        }
+        uploads.remove(position)
+    };
+
+    match finish(&state, &session).await {
+        Ok(video) => {
+            let _ = tokio::fs::remove_dir_all(session.chunk_dir()).await;
+            save_list(UPLOADS_PATH, &store.uploads.lock().await)?;
+            Ok(Json(VideoEvidenceResponse {
+                ipfs_url: ipfs_gateway_url(&video.video_cid),
+                video,
+            }))
+        }
+        Err(e) => {
+            // Keep the upload; a hash mismatch means the chunks must be re-sent
+            let mut session = session;
+            if e.status == StatusCode::BAD_REQUEST {
+                session.received.clear();
+            }
+            let mut uploads = store.uploads.lock().await;
+            uploads.push(session);
+            save_list(UPLOADS_PATH, &uploads)?;
+            Err(e)
+        }
+    }
+}
+
+async fn finish(state: &AppState, session: &UploadSession) -> Result<VideoEvidence, ApiError> {
+    let store = &state.videos;
+    let assembled = session.clone();
+    let (video, video_hash) = tokio::task::spawn_blocking(move || {
+        let video = assemble(&assembled)?;
+        let hash = format_hash(hash_bytes(&video));
+        anyhow::Ok((video, hash))
+    })
+    .await
+    .map_err(|e| ApiError::internal(format!("Video assembly failed: {}", e)))??;
+
+    if video.len() as u64 != session.size_bytes {
+        return Err(ApiError::bad_request(format!(
+            "Assembled video is {} bytes, expected {}",
+            video.len(),
+            session.size_bytes
+        )));
+    }
+    if let Some(expected) = &session.video_hash {
+        if *expected != video_hash {
+            return Err(ApiError::bad_request(
+                "Assembled video does not match video_hash",
+            ));
+        }
+    }
+    if store
+        .videos
+        .lock()
+        .await
+        .iter()
+        .any(|v| v.video_hash == video_hash)
+    {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            "This video has already been submitted",
+        ));
+    }
+
+    let extension = CONTENT_TYPES
+        .iter()
+        .find(|(t, _)| *t == session.content_type)
+        .map(|(_, ext)| *ext)
+        .unwrap_or("bin");
+    let (video_cid, object_key) = object_storage::store_evidence(
+        state,
+        video,
+        &video_hash,
+        &session.content_type,
+        extension,
+        "videos",
+    )
+    .await?;
+
+    let evidence = VideoEvidence {
+        video_cid,
+        video_hash,
+        content_type: session.content_type.clone(),
+        size_bytes: session.size_bytes,
+        purpose: session.purpose,
+        reference: session.reference.clone(),
+        received_at: Utc::now().to_rfc3339(),
+        object_key,
+    };
+    tracing::info!(
+        upload_id = %session.upload_id,
+        video_cid = %evidence.video_cid,
+        reference = %evidence.reference,
+        "Pinned evidence video"
+    );
+
+    let mut videos = store.videos.lock().await;
+    videos.push(evidence.clone());
+    save_list(VIDEOS_PATH, &videos)?;
+    Ok(evidence)
+}
+
+pub async fn get_video(
+    State(state): State<AppState>,
+    Path(video_cid): Path<String>,
+) -> ApiResult<VideoEvidenceResponse> {
+    let video = state
+        .videos
+        .videos
+        .lock()
+        .await
+        .iter()
+        .find(|v| v.video_cid == video_cid)
+        .cloned()
+        .ok_or_else(|| ApiError::not_found(format!("Video {} not found", video_cid)))?;
+
+    Ok(Json(VideoEvidenceResponse {
+        ipfs_url: ipfs_gateway_url(&video.video_cid),
+        video,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_layout() {
+        let size = 2 * CHUNK_SIZE + 10;
+        assert_eq!(chunk_count(size), 3);
+        assert_eq!(chunk_len(size, 0), Some(CHUNK_SIZE));
+        assert_eq!(chunk_len(size, 2), Some(10));
+        assert_eq!(chunk_len(size, 3), None);
+        assert_eq!(chunk_count(CHUNK_SIZE), 1);
+        assert_eq!(chunk_len(CHUNK_SIZE, 1), None);
+
+        assert_eq!(missing_chunks(size, &[2, 0]), vec![1]);
+        assert!(missing_chunks(size, &[0, 1, 2]).is_empty());
+    }
+}
@@ -0,0 +1,236 @@
+//! Signer balance monitoring
+//!
+//! Every write is paid for by a backend signer, so an empty wallet stops
+//! the stages it signs for with out-of-gas errors. Every WALLET_CHECK_SECS
+//! (default 300) the monitor reads the native token balance of each signer
+//! (see [`crate::signers`]); when one falls below WALLET_LOW_BALANCE (in
+//! whole tokens, default 0.5) an error is logged and, if
+//! WALLET_ALERT_WEBHOOK_URL is set, a JSON alert is posted there. The alert
+//! fires once per drop below the threshold and re-arms when the balance
+//! recovers. The last readings are served at `GET /api/admin/wallet`.
+
+use crate::admin::require_admin;
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+use alloy::primitives::{
+    utils::{format_ether, parse_ether},
+    Address, U256,
+};
+use anyhow::{Context, Result};
+use axum::{extract::State, http::HeaderMap, Json};
+use chrono::Utc;
+use reqwest::Client;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+const DEFAULT_CHECK_SECS: u64 = 300;
+const DEFAULT_LOW_BALANCE: &str = "0.5";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WalletStatus {
+    pub address: Address,
+    /// Roles the signer signs for
+    pub roles: Vec<&'static str>,
+    pub balance_wei: String,
+    /// Balance in whole native tokens
+    pub balance: String,
+    pub low_balance_threshold: String,
+    pub low: bool,
+    pub checked_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transition {
+    /// Dropped below the threshold since the last check
+    Low,
+    /// Back above the threshold
+    Recovered,
+}
+
+fn transition(was_low: bool, is_low: bool) -> Option<Transition> {
+    match (was_low, is_low) {
+        (false, true) => Some(Transition::Low),
+        (true, false) => Some(Transition::Recovered),
+        _ => None,
+    }
+}
+
+pub struct WalletMonitor {
+    threshold: U256,
+    webhook_url: Option<String>,
+    http: Client,
+    status: RwLock<HashMap<Address, WalletStatus>>,
+}
+
+impl WalletMonitor {
+    pub fn from_env() -> Result<Self> {
+        let threshold = std::env::var("WALLET_LOW_BALANCE")
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_LOW_BALANCE.to_string());
+        let threshold = parse_ether(threshold.trim()).with_context(|| {
+            format!(
+                "WALLET_LOW_BALANCE {:?} is not a token amount such as 0.5",
+                threshold
+            )
+        })?;
+
+        Ok(Self {
+            threshold,
+            webhook_url: std::env::var("WALLET_ALERT_WEBHOOK_URL")
+                .ok()
+                .filter(|v| !v.is_empty()),
+            http: Client::new(),
+            status: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Read every signer's balance, store them and alert on a drop below
+    /// the threshold
+    pub async fn check(&self, state: &AppState) -> Result<Vec<WalletStatus>> {
+        let chain = state.chain();
+        let signers = chain.signers().signers();
+        let mut statuses = Vec::with_capacity(signers.len());
+        for signer in signers {
+            let balance = chain.signer_balance(signer.address).await?;
+            statuses.push(
+                self.record(WalletStatus {
+                    address: signer.address,
+                    roles: signer.roles,
+                    balance_wei: balance.to_string(),
+                    balance: format_ether(balance),
+                    low_balance_threshold: format_ether(self.threshold),
+                    low: balance < self.threshold,
+                    checked_at: Utc::now().to_rfc3339(),
+                })
+                .await,
+            );
+        }
+        // Keys rotated out are no longer watched
+        self.status
+            .write()
+            .await
+            .retain(|address, _| statuses.iter().any(|s| s.address == *address));
+        Ok(statuses)
+    }
+
+    /// Store a reading, alerting when it crosses the threshold
+    async fn record(&self, status: WalletStatus) -> WalletStatus {
+        let was_low = self
+            .status
+            .write()
+            .await
+            .insert(status.address, status.clone())
+            .is_some_and(|previous| previous.low);
+        match transition(was_low, status.low) {
+            Some(Transition::Low) => {
+                tracing::error!(
+                    address = ?status.address,
+                    roles = ?status.roles,
+                    balance = %status.balance,
+                    threshold = %status.low_balance_threshold,
+                    "Signer balance is below the low-funds threshold"
+                );
+                self.alert(&status).await;
+            }
+            Some(Transition::Recovered) => {
+                tracing::info!(address = ?status.address, balance = %status.balance, "Signer balance recovered");
+            }
+            None => {}
+        }
+        status
+    }
+
+    async fn alert(&self, status: &WalletStatus) {
+        let Some(url) = &self.webhook_url else {
+            return;
+        };
+        let body = serde_json::json!({
+            "event": "wallet_low_balance",
+            "wallet": status,
+        });
+        let sent = self
+            .http
+            .post(url)
+            .timeout(Duration::from_secs(10))
+            .json(&body)
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+        if let Err(e) = sent {
+            tracing::error!(error = %e, "Failed to deliver low-balance alert");
+        }
+    }
+}
+
+/// Check the signer balances every WALLET_CHECK_SECS
+pub fn spawn(state: AppState) {
+    let secs = std::env::var("WALLET_CHECK_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_CHECK_SECS);
+    let interval = Duration::from_secs(secs.max(30));
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = state.wallet.check(&state).await {
+                tracing::warn!(error = %format!("{:#}", e), "Wallet balance check failed");
+            }
+        }
+    });
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct WalletsView {
+    pub wallets: Vec<WalletStatus>,
+}
+
+/// Current signer balances (admin only); read live when a signer has not
+/// been checked yet
+pub async fn wallet_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> ApiResult<WalletsView> {
+    require_admin(&state, &headers)?;
+
+    let signers = state.chain().signers().signers();
+    {
+        let status = state.wallet.status.read().await;
+        let wallets: Option<Vec<WalletStatus>> = signers
+            .iter()
+            .map(|s| status.get(&s.address).cloned())
+            .collect();
+        if let Some(wallets) = wallets {
+            return Ok(Json(WalletsView { wallets }));
+        }
+    }
+    let wallets = state
+        .wallet
+        .check(&state)
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+    Ok(Json(WalletsView { wallets }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_fires_once_per_drop() {
+        assert_eq!(transition(false, true), Some(Transition::Low));
+        assert_eq!(transition(true, true), None);
+        assert_eq!(transition(true, false), Some(Transition::Recovered));
+        assert_eq!(transition(false, false), None);
+        assert_eq!(
+            parse_ether("0.5").unwrap(),
+            U256::from(500_000_000_000_000_000u64)
+        );
+    }
+}
@@ -0,0 +1,704 @@
+//! Electronic negotiable warehouse receipts (eNWR)
+//!
+//! A warehouse issues a receipt against a stored batch: receipt number,
+//! commodity, quantity, quality grade and a validity date. Receipts follow the
+//! repository workflow banks use for pledge finance:
+//!
+//! - `issued` - receipt created, held by the depositor
+//! - `transferred` - endorsed to a new holder (refused while pledged)
+//! - `pledged` - lien marked for a lender; recorded as a financing pledge
+//!   flag, so a batch cannot be pledged twice (see [`crate::financing`])
+//! - `released` - lien released on repayment, or invoked on default, which
+//!   endorses the receipt to the lender
+//! - `closed` - goods delivered out of the warehouse
+//!
+//! Every event is anchored through the warehouse stage: the receipt state is
+//! uploaded to IPFS and its hash recorded with `updateWarehouseState` for the
+//! issuing warehouse. Receipts are kept in `data/warehouse_receipts.json`.
+//! Receipts for a batch cannot exceed its FPO purchase quantity. Stock moved
+//! to another warehouse continues on a receipt issued there (see
+//! [`crate::warehouse_transfers`]).
+
+use crate::batch_ledger;
+use crate::chain::hash_string;
+use crate::error::{format_hash, format_tx_hash, ApiError, ApiResult};
+use crate::financing::{FinancingKind, RecordFinancingRequest};
+use crate::hash_schemes::HashRecord;
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use crate::stock_rotation;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{Datelike, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+const RECEIPTS_PATH: &str = "data/warehouse_receipts.json";
+const DEFAULT_VALIDITY_DAYS: u32 = 180;
+/// Upper bound on receipt validity (storage period of a negotiable receipt)
+const MAX_VALIDITY_DAYS: u32 = 365;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptStatus {
+    Active,
+    Pledged,
+    Closed,
+}
+
+/// Why a receipt was closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClosedReason {
+    /// Goods delivered out of the warehouse
+    Dispatched,
+    /// Stock moved to another warehouse (see [`crate::warehouse_transfers`])
+    Transferred,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiptLien {
+    /// Financing pledge flag recorded for the lien
+    pub flag_id: u64,
+    pub lender: String,
+    pub reference: String,
+    pub amount: f64,
+    pub marked_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub released_at: Option<String>,
+    /// Lien invoked on default and the receipt endorsed to the lender
+    #[serde(default)]
+    pub invoked: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Endorsement {
+    pub from: String,
+    pub to: String,
+    pub at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiptAnchor {
+    pub event: String,
+    pub tx_hash: String,
+    pub state_hash: String,
+    pub metadata_cid: String,
+    pub at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarehouseReceipt {
+    pub id: u64,
+    pub receipt_number: String,
+    pub batch_id: String,
+    pub warehouse_id: String,
+    pub depositor: String,
+    pub holder: String,
+    pub commodity: String,
+    pub quantity_kg: f64,
+    pub quality_grade: String,
+    /// Assay parameters (moisture, oil content, ...) as reported by the warehouse
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub quality: serde_json::Value,
+    pub issued_at: String,
+    /// Last day the receipt is valid (YYYY-MM-DD)
+    pub valid_until: String,
+    pub status: ReceiptStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lien: Option<ReceiptLien>,
+    #[serde(default)]
+    pub endorsements: Vec<Endorsement>,
+    #[serde(default)]
+    pub anchors: Vec<ReceiptAnchor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closed_at: Option<String>,
+    /// Receipts closed before this was recorded count as dispatched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub closed_reason: Option<ClosedReason>,
+    /// Receipt at the previous warehouse, for stock moved in by a transfer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transferred_from: Option<String>,
+    /// When the stock first entered storage, if before `issued_at`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stored_since: Option<String>,
+}
+
+impl WarehouseReceipt {
+    /// RFC 3339 time the stock entered storage, at this or an earlier
+    /// warehouse
+    pub fn stored_since(&self) -> &str {
+        self.stored_since.as_deref().unwrap_or(&self.issued_at)
+    }
+
+    /// When the goods left the warehouse, for receipts closed on dispatch
+    pub fn dispatched_at(&self) -> Option<&str> {
+        match self.closed_reason {
+            Some(ClosedReason::Transferred) => None,
+            _ => self.closed_at.as_deref(),
+        }
+    }
+
+    /// `today` is YYYY-MM-DD; ISO dates compare as strings
+    pub fn is_expired(&self, today: &str) -> bool {
+        today > self.valid_until.as_str()
+    }
+
+    /// Why the receipt cannot be endorsed to a new holder, if it cannot
+    pub fn transfer_blocker(&self, today: &str) -> Option<&'static str> {
+        match self.status {
+            ReceiptStatus::Closed => Some("receipt is closed"),
+            ReceiptStatus::Pledged => Some("receipt is under lien; release it first"),
+            ReceiptStatus::Active if self.is_expired(today) => Some("receipt has expired"),
+            ReceiptStatus::Active => None,
+        }
+    }
+}
+
+/// Quantity still available for new receipts on a batch
+pub fn issuable_quantity(purchased_kg: f64, batch_id: &str, receipts: &[WarehouseReceipt]) -> f64 {
+    let issued: f64 = receipts
+        .iter()
+        .filter(|r| r.batch_id == batch_id && r.status != ReceiptStatus::Closed)
+        .map(|r| r.quantity_kg)
+        .sum();
+    (purchased_kg - issued).max(0.0)
+}
+
+pub struct ReceiptStore {
+    pub(crate) receipts: Mutex<Vec<WarehouseReceipt>>,
+}
+
+impl ReceiptStore {
+    pub fn load() -> Result<Self> {
+        let receipts = match std::fs::read_to_string(RECEIPTS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid warehouse receipts file {}", RECEIPTS_PATH))?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Self {
+            receipts: Mutex::new(receipts),
+        })
+    }
+
+    pub(crate) fn save(receipts: &[WarehouseReceipt]) -> Result<()> {
+        std::fs::write(RECEIPTS_PATH, serde_json::to_string_pretty(receipts)?)
+            .with_context(|| format!("Failed to write {}", RECEIPTS_PATH))
+    }
+
+    /// Number of the pledged receipt whose lien is financing flag `flag_id`
+    pub async fn receipt_under_lien(&self, flag_id: u64) -> Option<String> {
+        self.receipts
+            .lock()
+            .await
+            .iter()
+            .find(|r| {
+                r.status == ReceiptStatus::Pledged
+                    && r.lien.as_ref().is_some_and(|l| l.flag_id == flag_id)
+            })
+            .map(|r| r.receipt_number.clone())
+    }
+
+    /// Receipts issued by `warehouse_id`, closed ones included
+    pub async fn at_warehouse(&self, warehouse_id: &str) -> Vec<WarehouseReceipt> {
+        self.receipts
+            .lock()
+            .await
+            .iter()
+            .filter(|r| r.warehouse_id == warehouse_id)
+            .cloned()
+            .collect()
+    }
+}
+
+fn today() -> String {
+    Utc::now().format("%Y-%m-%d").to_string()
+}
+
+pub(crate) fn receipt_number(id: u64) -> String {
+    format!("ENWR-{}-{:06}", Utc::now().year(), id)
+}
+
+/// Record the receipt state through the warehouse stage and append the anchor
+async fn anchor(
+    state: &AppState,
+    receipt: &mut WarehouseReceipt,
+    event: &str,
+) -> Result<(), ApiError> {
+    let document = serde_json::json!({
+        "type": "electronic_warehouse_receipt",
+        "event": event,
+        "receipt": receipt,
+        "timestamp": Utc::now().to_rfc3339(),
+    });
+    let metadata_cid = state
+        .ipfs_client
+        .upload_json(&document)
+        .await
+        .map_err(ApiError::ipfs_upload_failed)?;
+    let state_hash = HashRecord::of_json(&document).map_err(ApiError::from)?;
+    let tx = state
+        .chain()
+        .update_warehouse_state(
+            hash_string(&receipt.warehouse_id),
+            state_hash.hash,
+            metadata_cid.clone(),
+        )
+        .await
+        .map_err(ApiError::blockchain_failed)?;
+
+    receipt.anchors.push(ReceiptAnchor {
+        event: event.to_string(),
+        tx_hash: format_tx_hash(tx.transaction_hash),
+        state_hash: format_hash(state_hash.hash),
+        metadata_cid,
+        at: Utc::now().to_rfc3339(),
+    });
+    Ok(())
+}
+
+fn find<'a>(
+    receipts: &'a mut [WarehouseReceipt],
+    number: &str,
+) -> Result<&'a mut WarehouseReceipt, ApiError> {
+    receipts
+        .iter_mut()
+        .find(|r| r.receipt_number == number)
+        .ok_or_else(|| ApiError::not_found(format!("Warehouse receipt {} not found", number)))
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Serialize)]
+pub struct ReceiptView {
+    #[serde(flatten)]
+    pub receipt: WarehouseReceipt,
+    pub expired: bool,
+}
+
+impl From<WarehouseReceipt> for ReceiptView {
+    fn from(receipt: WarehouseReceipt) -> Self {
+        let expired = receipt.is_expired(&today());
+        Self { receipt, expired }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IssueReceiptRequest {
+    pub batch_id: String,
+    pub warehouse_id: String,
+    pub depositor: String,
+    #[serde(default)]
+    pub commodity: Option<String>,
+    pub quantity_kg: f64,
+    pub quality_grade: String,
+    #[serde(default)]
+    pub quality: serde_json::Value,
+    #[serde(default)]
+    pub valid_days: Option<u32>,
+}
+
+pub async fn issue_receipt(
+    State(state): State<AppState>,
+    Json(payload): Json<IssueReceiptRequest>,
+) -> ApiResult<ReceiptView> {
+    if payload.warehouse_id.trim().is_empty() || payload.depositor.trim().is_empty() {
+        return Err(ApiError::bad_request(
+            "warehouse_id and depositor are required",
+        ));
+    }
+    if !(payload.quantity_kg.is_finite() && payload.quantity_kg > 0.0) {
+        return Err(ApiError::bad_request("quantity_kg must be positive"));
+    }
+    let valid_days = payload.valid_days.unwrap_or(DEFAULT_VALIDITY_DAYS);
+    if valid_days == 0 || valid_days > MAX_VALIDITY_DAYS {
+        return Err(ApiError::bad_request(format!(
+            "valid_days must be between 1 and {}",
+            MAX_VALIDITY_DAYS
+        )));
+    }
+    let purchase = batch_ledger::fpo_purchase(&payload.batch_id)?.ok_or_else(|| {
+        ApiError::not_found(format!("No FPO purchase for batch {}", payload.batch_id))
+    })?;
+    let purchased_kg = purchase
+        .pointer("/batch_info/quantity_kg")
+        .and_then(|v| v.as_f64())
+        .ok_or_else(|| ApiError::internal("FPO purchase record has no quantity"))?;
+    let commodity = payload
+        .commodity
+        .or_else(|| {
+            purchase
+                .pointer("/farmer_info/crop_type")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "oilseed".to_string());
+    let quality_grade = state
+        .grades
+        .resolve(&commodity, &payload.quality_grade)?
+        .code
+        .clone();
+
+    let mut receipts = state.receipts.receipts.lock().await;
+    let available = issuable_quantity(purchased_kg, &payload.batch_id, &receipts);
+    if payload.quantity_kg > available {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!(
+                "Batch {} has {:.2} kg left to receipt, requested {:.2} kg",
+                payload.batch_id, available, payload.quantity_kg
+            ),
+        ));
+    }
+
+    let now = Utc::now();
+    let id = receipts.last().map(|r| r.id + 1).unwrap_or(1);
+    let mut receipt = WarehouseReceipt {
+        id,
+        receipt_number: receipt_number(id),
+        batch_id: payload.batch_id,
+        warehouse_id: payload.warehouse_id.trim().to_string(),
+        depositor: payload.depositor.trim().to_string(),
+        holder: payload.depositor.trim().to_string(),
+        commodity,
+        quantity_kg: payload.quantity_kg,
+        quality_grade,
+        quality: payload.quality,
+        issued_at: now.to_rfc3339(),
+        valid_until: (now + Duration::days(valid_days as i64))
+            .format("%Y-%m-%d")
+            .to_string(),
+        status: ReceiptStatus::Active,
+        lien: None,
+        endorsements: Vec::new(),
+        anchors: Vec::new(),
+        closed_at: None,
+        closed_reason: None,
+        transferred_from: None,
+        stored_since: None,
+    };
+    anchor(&state, &mut receipt, "issued").await?;
+    receipts.push(receipt.clone());
+    ReceiptStore::save(&receipts)?;
+
+    tracing::info!(
+        receipt_number = %receipt.receipt_number,
+        batch_id = %receipt.batch_id,
+        quantity_kg = receipt.quantity_kg,
+        "Warehouse receipt issued"
+    );
+    Ok(Json(receipt.into()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReceiptFilter {
+    pub batch_id: Option<String>,
+    pub holder: Option<String>,
+    pub status: Option<ReceiptStatus>,
+}
+
+pub async fn list_receipts(
+    State(state): State<AppState>,
+    Query(filter): Query<ReceiptFilter>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<ReceiptView>> {
+    let receipts: Vec<ReceiptView> = state
+        .receipts
+        .receipts
+        .lock()
+        .await
+        .iter()
+        .filter(|r| filter.batch_id.as_deref().is_none_or(|b| r.batch_id == b))
+        .filter(|r| filter.holder.as_deref().is_none_or(|h| r.holder == h))
+        .filter(|r| filter.status.is_none_or(|s| r.status == s))
+        .cloned()
+        .map(ReceiptView::from)
+        .collect();
+    Ok(Json(paginate(
+        "warehouse-receipts",
+        receipts,
+        &params,
+        |r| r.receipt.id,
+    )?))
+}
+
+pub async fn get_receipt(
+    State(state): State<AppState>,
+    Path(number): Path<String>,
+) -> ApiResult<ReceiptView> {
+    let mut receipts = state.receipts.receipts.lock().await;
+    Ok(Json(find(&mut receipts, &number)?.clone().into()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransferReceiptRequest {
+    pub to: String,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Endorse a receipt to a new holder
+pub async fn transfer_receipt(
+    State(state): State<AppState>,
+    Path(number): Path<String>,
+    Json(payload): Json<TransferReceiptRequest>,
+) -> ApiResult<ReceiptView> {
+    let to = payload.to.trim();
+    if to.is_empty() {
+        return Err(ApiError::bad_request("to is required"));
+    }
+
+    let mut receipts = state.receipts.receipts.lock().await;
+    let stored = find(&mut receipts, &number)?;
+    if let Some(reason) = stored.transfer_blocker(&today()) {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!("Cannot transfer {}: {}", number, reason),
+        ));
+    }
+    if stored.holder == to {
+        return Err(ApiError::bad_request(format!(
+            "{} already holds {}",
+            to, number
+        )));
+    }
+
+    let mut receipt = stored.clone();
+    receipt.endorsements.push(Endorsement {
+        from: receipt.holder.clone(),
+        to: to.to_string(),
+        at: Utc::now().to_rfc3339(),
+        note: payload.note,
+    });
+    receipt.holder = to.to_string();
+    anchor(&state, &mut receipt, "transferred").await?;
+    *stored = receipt.clone();
+    ReceiptStore::save(&receipts)?;
+
+    tracing::info!(receipt_number = %number, holder = %receipt.holder, "Warehouse receipt transferred");
+    Ok(Json(receipt.into()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PledgeReceiptRequest {
+    pub lender: String,
+    pub reference: String,
+    pub amount: f64,
+}
+
+/// Mark a lien on the receipt for a lender
+pub async fn pledge_receipt(
+    State(state): State<AppState>,
+    Path(number): Path<String>,
+    Json(payload): Json<PledgeReceiptRequest>,
+) -> ApiResult<ReceiptView> {
+    let mut receipts = state.receipts.receipts.lock().await;
+    let stored = find(&mut receipts, &number)?;
+    if let Some(reason) = stored.transfer_blocker(&today()) {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!("Cannot pledge {}: {}", number, reason),
+        ));
+    }
+
+    let flag = state
+        .financing
+        .record(RecordFinancingRequest {
+            batch_id: stored.batch_id.clone(),
+            kind: FinancingKind::Pledge,
+            lender: payload.lender,
+            reference: payload.reference,
+            amount: payload.amount,
+        })
+        .await?;
+
+    let mut receipt = stored.clone();
+    receipt.status = ReceiptStatus::Pledged;
+    receipt.lien = Some(ReceiptLien {
+        flag_id: flag.id,
+        lender: flag.lender.clone(),
+        reference: flag.reference.clone(),
+        amount: flag.amount,
+        marked_at: flag.recorded_at.clone(),
+        released_at: None,
+        invoked: false,
+    });
+    if let Err(e) = anchor(&state, &mut receipt, "pledged").await {
+        // Keep the financing flags in step with the receipt
+        if let Err(release_err) = state.financing.release(flag.id).await {
+            tracing::error!(flag_id = flag.id, error = ?release_err, "Failed to roll back pledge flag");
+        }
+        return Err(e);
+    }
+    *stored = receipt.clone();
+    ReceiptStore::save(&receipts)?;
+
+    tracing::info!(receipt_number = %number, lender = %flag.lender, "Warehouse receipt pledged");
+    Ok(Json(receipt.into()))
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ReleaseLienRequest {
+    /// Lender invokes the lien on default and becomes the holder
+    #[serde(default)]
+    pub invoke: bool,
+}
+
+/// Release the lien on repayment, or invoke it in the lender's favour
+pub async fn release_receipt_lien(
+    State(state): State<AppState>,
+    Path(number): Path<String>,
+    payload: Option<Json<ReleaseLienRequest>>,
+) -> ApiResult<ReceiptView> {
+    let invoke = payload.map(|Json(p)| p.invoke).unwrap_or_default();
+
+    let mut receipts = state.receipts.receipts.lock().await;
+    let stored = find(&mut receipts, &number)?;
+    let lien = match (&stored.status, &stored.lien) {
+        (ReceiptStatus::Pledged, Some(lien)) => lien.clone(),
+        _ => {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                format!("Receipt {} is not under lien", number),
+            ))
+        }
+    };
+
+    let now = Utc::now().to_rfc3339();
+    let mut receipt = stored.clone();
+    receipt.status = ReceiptStatus::Active;
+    if invoke && receipt.holder != lien.lender {
+        receipt.endorsements.push(Endorsement {
+            from: receipt.holder.clone(),
+            to: lien.lender.clone(),
+            at: now.clone(),
+            note: Some(format!("Lien invoked ({})", lien.reference)),
+        });
+        receipt.holder = lien.lender.clone();
+    }
+    receipt.lien = Some(ReceiptLien {
+        released_at: Some(now),
+        invoked: invoke,
+        ..lien.clone()
+    });
+    let event = if invoke { "invoked" } else { "released" };
+    anchor(&state, &mut receipt, event).await?;
+    state.financing.release(lien.flag_id).await?;
+    *stored = receipt.clone();
+    ReceiptStore::save(&receipts)?;
+
+    tracing::info!(receipt_number = %number, invoke, "Warehouse receipt lien released");
+    Ok(Json(receipt.into()))
+}
+
+/// Close the receipt when the goods are delivered out
+pub async fn close_receipt(
+    State(state): State<AppState>,
+    Path(number): Path<String>,
+) -> ApiResult<ReceiptView> {
+    let mut receipts = state.receipts.receipts.lock().await;
+    let stored = find(&mut receipts, &number)?;
+    match stored.status {
+        ReceiptStatus::Active => {}
+        ReceiptStatus::Pledged => {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                format!("Receipt {} is under lien; release it first", number),
+            ))
+        }
+        ReceiptStatus::Closed => return Ok(Json(stored.clone().into())),
+    }
+
+    let mut receipt = stored.clone();
+    receipt.status = ReceiptStatus::Closed;
+    receipt.closed_at = Some(Utc::now().to_rfc3339());
+    receipt.closed_reason = Some(ClosedReason::Dispatched);
+    anchor(&state, &mut receipt, "closed").await?;
+    *stored = receipt.clone();
+    ReceiptStore::save(&receipts)?;
+
+    tracing::info!(receipt_number = %number, "Warehouse receipt closed");
+    let stock: Vec<&WarehouseReceipt> = receipts
+        .iter()
+        .filter(|r| r.warehouse_id == receipt.warehouse_id)
+        .collect();
+    if let Some(alert) = stock_rotation::bypassed_by(&receipt, &stock) {
+        tracing::warn!(
+            receipt_number = %number,
+            warehouse_id = %receipt.warehouse_id,
+            bypassed = %alert.bypassed.join(","),
+            oldest_age_days = alert.oldest_age_days,
+            "Dispatch bypassed older stock"
+        );
+    }
+    Ok(Json(receipt.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt(
+        id: u64,
+        batch_id: &str,
+        quantity_kg: f64,
+        status: ReceiptStatus,
+    ) -> WarehouseReceipt {
+        WarehouseReceipt {
+            id,
+            receipt_number: format!("ENWR-2025-{:06}", id),
+            batch_id: batch_id.to_string(),
+            warehouse_id: "WH-1".to_string(),
+            depositor: "FPO-1".to_string(),
+            holder: "FPO-1".to_string(),
+            commodity: "soybean".to_string(),
+            quantity_kg,
+            quality_grade: "A".to_string(),
+            quality: serde_json::Value::Null,
+            issued_at: "2025-06-01T00:00:00Z".to_string(),
+            valid_until: "2025-11-28".to_string(),
+            status,
+            lien: None,
+            endorsements: Vec::new(),
+            anchors: Vec::new(),
+            closed_at: None,
+            closed_reason: None,
+            transferred_from: None,
+            stored_since: None,
+        }
+    }
+
+    #[test]
+    fn test_issuable_quantity_ignores_closed_and_other_batches() {
+        let receipts = vec![
+            receipt(1, "b1", 400.0, ReceiptStatus::Active),
+            receipt(2, "b1", 300.0, ReceiptStatus::Pledged),
+            receipt(3, "b1", 250.0, ReceiptStatus::Closed),
+            receipt(4, "b2", 900.0, ReceiptStatus::Active),
+        ];
+        assert_eq!(issuable_quantity(1000.0, "b1", &receipts), 300.0);
+        assert_eq!(issuable_quantity(600.0, "b1", &receipts), 0.0);
+    }
+
+    #[test]
+    fn test_transfer_blocked_by_lien_closure_and_expiry() {
+        let active = receipt(1, "b1", 100.0, ReceiptStatus::Active);
+        assert_eq!(active.transfer_blocker("2025-11-28"), None);
+        assert_eq!(
+            active.transfer_blocker("2025-11-29"),
+            Some("receipt has expired")
+        );
+        assert!(receipt(2, "b1", 100.0, ReceiptStatus::Pledged)
+            .transfer_blocker("2025-07-01")
+            .is_some());
+        assert!(receipt(3, "b1", 100.0, ReceiptStatus::Closed)
+            .transfer_blocker("2025-07-01")
+            .is_some());
+    }
+}
@@ -0,0 +1,349 @@
+//! Warehouse master data
+//!
+//! Warehouses are registered with their location, storage capacity and
+//! licences (WDRA registration, FSSAI, state licences). Warehouse state
+//! updates, through `POST /api/warehouse/update`, the batch update and the
+//! workflow's storage stage, are refused for unregistered IDs, and the
+//! registry entry is pinned with the IoT data of a single update.
+//!
+//! Admins manage the registry under `/api/admin/warehouses`; anyone can read
+//! it through `GET /api/warehouses`. Entries are kept in
+//! `data/warehouses.json`.
+
+use crate::admin::require_admin;
+use crate::error::{ApiError, ApiResult};
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+const WAREHOUSES_PATH: &str = "data/warehouses.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Licence {
+    /// Issuing authority or scheme, e.g. `WDRA`, `FSSAI`
+    pub authority: String,
+    pub number: String,
+    /// YYYY-MM-DD; licences without one do not lapse
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Warehouse {
+    pub id: String,
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub capacity_tonnes: f64,
+    #[serde(default)]
+    pub licences: Vec<Licence>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl Warehouse {
+    /// Entry pinned with the warehouse's state updates
+    pub fn metadata_entry(&self) -> serde_json::Value {
+        serde_json::json!({
+            "warehouse_id": self.id,
+            "name": self.name,
+            "latitude": self.latitude,
+            "longitude": self.longitude,
+            "capacity_tonnes": self.capacity_tonnes,
+            "licences": self.licences,
+        })
+    }
+}
+
+fn validate_location(latitude: f64, longitude: f64) -> Result<(), ApiError> {
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return Err(ApiError::bad_request(
+            "latitude must be within ±90 and longitude within ±180",
+        ));
+    }
+    Ok(())
+}
+
+fn validate_capacity(capacity_tonnes: f64) -> Result<(), ApiError> {
+    if !(capacity_tonnes.is_finite() && capacity_tonnes > 0.0) {
+        return Err(ApiError::bad_request("capacity_tonnes must be positive"));
+    }
+    Ok(())
+}
+
+/// Trimmed licence with its expiry date checked
+pub(crate) fn validate_licence(licence: &Licence) -> Result<Licence, ApiError> {
+    let authority = licence.authority.trim();
+    let number = licence.number.trim();
+    if authority.is_empty() || number.is_empty() {
+        return Err(ApiError::bad_request(
+            "Every licence needs an authority and a number",
+        ));
+    }
+    if let Some(date) = &licence.valid_until {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+            ApiError::bad_request(format!(
+                "valid_until of licence {} must be YYYY-MM-DD",
+                number
+            ))
+        })?;
+    }
+    Ok(Licence {
+        authority: authority.to_string(),
+        number: number.to_string(),
+        valid_until: licence.valid_until.clone(),
+    })
+}
+
+fn validate_licences(licences: &[Licence]) -> Result<Vec<Licence>, ApiError> {
+    let mut checked: Vec<Licence> = Vec::with_capacity(licences.len());
+    for licence in licences {
+        let licence = validate_licence(licence)?;
+        if checked.iter().any(|l| {
+            l.authority.eq_ignore_ascii_case(&licence.authority) && l.number == licence.number
+        }) {
+            return Err(ApiError::bad_request(format!(
+                "Licence {} {} is listed twice",
+                licence.authority, licence.number
+            )));
+        }
+        checked.push(licence);
+    }
+    Ok(checked)
+}
+
+pub struct WarehouseRegistry {
+    warehouses: Mutex<Vec<Warehouse>>,
+}
+
+impl WarehouseRegistry {
+    pub fn load() -> Result<Self> {
+        let warehouses = match std::fs::read_to_string(WAREHOUSES_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid warehouse registry {}", WAREHOUSES_PATH))?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Self {
+            warehouses: Mutex::new(warehouses),
+        })
+    }
+
+    fn save(warehouses: &[Warehouse]) -> Result<()> {
+        std::fs::write(WAREHOUSES_PATH, serde_json::to_string_pretty(warehouses)?)
+            .with_context(|| format!("Failed to write {}", WAREHOUSES_PATH))
+    }
+
+    pub async fn get(&self, id: &str) -> Option<Warehouse> {
+        self.warehouses
+            .lock()
+            .await
+            .iter()
+            .find(|w| w.id == id)
+            .cloned()
+    }
+
+    /// Registered warehouse `id`, as a request error when there is none
+    pub async fn require(&self, id: &str) -> Result<Warehouse, ApiError> {
+        self.get(id.trim())
+            .await
+            .ok_or_else(|| ApiError::bad_request(format!("Warehouse {} is not registered", id)))
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct CreateWarehouseRequest {
+    pub id: String,
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub capacity_tonnes: f64,
+    #[serde(default)]
+    pub licences: Vec<Licence>,
+}
+
+/// Fields to change; `None` leaves a field as it is
+#[derive(Debug, Default, Deserialize)]
+pub struct UpdateWarehouseRequest {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    #[serde(default)]
+    pub longitude: Option<f64>,
+    #[serde(default)]
+    pub capacity_tonnes: Option<f64>,
+    /// Replaces the licence list
+    #[serde(default)]
+    pub licences: Option<Vec<Licence>>,
+}
+
+/// Registered warehouses, ordered by ID
+pub async fn list_warehouses(
+    State(state): State<AppState>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<Warehouse>> {
+    let warehouses = state.warehouses.warehouses.lock().await.clone();
+    Ok(Json(paginate("warehouses", warehouses, &params, |w| {
+        w.id.clone()
+    })?))
+}
+
+pub async fn get_warehouse(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> ApiResult<Warehouse> {
+    state
+        .warehouses
+        .get(&id)
+        .await
+        .map(Json)
+        .ok_or_else(|| ApiError::not_found(format!("Warehouse {} not found", id)))
+}
+
+/// Register a warehouse (admin only)
+pub async fn create_warehouse(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateWarehouseRequest>,
+) -> ApiResult<Warehouse> {
+    require_admin(&state, &headers)?;
+
+    let id = payload.id.trim().to_string();
+    let name = payload.name.trim().to_string();
+    if id.is_empty() || name.is_empty() {
+        return Err(ApiError::bad_request("id and name are required"));
+    }
+    validate_location(payload.latitude, payload.longitude)?;
+    validate_capacity(payload.capacity_tonnes)?;
+    let now = Utc::now().to_rfc3339();
+    let warehouse = Warehouse {
+        id,
+        name,
+        latitude: payload.latitude,
+        longitude: payload.longitude,
+        capacity_tonnes: payload.capacity_tonnes,
+        licences: validate_licences(&payload.licences)?,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+
+    let mut warehouses = state.warehouses.warehouses.lock().await;
+    if warehouses.iter().any(|w| w.id == warehouse.id) {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!("Warehouse {} already exists", warehouse.id),
+        ));
+    }
+    warehouses.push(warehouse.clone());
+    WarehouseRegistry::save(&warehouses)?;
+
+    tracing::info!(warehouse_id = %warehouse.id, "Warehouse registered");
+    Ok(Json(warehouse))
+}
+
+/// Change a warehouse's details or licences (admin only)
+pub async fn update_warehouse(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<UpdateWarehouseRequest>,
+) -> ApiResult<Warehouse> {
+    require_admin(&state, &headers)?;
+
+    let licences = payload
+        .licences
+        .as_deref()
+        .map(validate_licences)
+        .transpose()?;
+
+    let mut warehouses = state.warehouses.warehouses.lock().await;
+    let warehouse = warehouses
+        .iter_mut()
+        .find(|w| w.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("Warehouse {} not found", id)))?;
+
+    let latitude = payload.latitude.unwrap_or(warehouse.latitude);
+    let longitude = payload.longitude.unwrap_or(warehouse.longitude);
+    validate_location(latitude, longitude)?;
+    let capacity_tonnes = payload.capacity_tonnes.unwrap_or(warehouse.capacity_tonnes);
+    validate_capacity(capacity_tonnes)?;
+    if let Some(name) = &payload.name {
+        if name.trim().is_empty() {
+            return Err(ApiError::bad_request("name is required"));
+        }
+        warehouse.name = name.trim().to_string();
+    }
+    warehouse.latitude = latitude;
+    warehouse.longitude = longitude;
+    warehouse.capacity_tonnes = capacity_tonnes;
+    if let Some(licences) = licences {
+        warehouse.licences = licences;
+    }
+    warehouse.updated_at = Utc::now().to_rfc3339();
+    let warehouse = warehouse.clone();
+    WarehouseRegistry::save(&warehouses)?;
+
+    tracing::info!(warehouse_id = %warehouse.id, "Warehouse updated");
+    Ok(Json(warehouse))
+}
+
+/// Remove a warehouse (admin only); state already anchored keeps its details
+pub async fn delete_warehouse(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> ApiResult<Warehouse> {
+    require_admin(&state, &headers)?;
+
+    let mut warehouses = state.warehouses.warehouses.lock().await;
+    let position = warehouses
+        .iter()
+        .position(|w| w.id == id)
+        .ok_or_else(|| ApiError::not_found(format!("Warehouse {} not found", id)))?;
+    let warehouse = warehouses.remove(position);
+    WarehouseRegistry::save(&warehouses)?;
+
+    tracing::info!(warehouse_id = %warehouse.id, "Warehouse removed");
+    Ok(Json(warehouse))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warehouse_fields_are_validated() {
+        assert!(validate_location(22.72, 75.86).is_ok());
+        assert!(validate_location(122.0, 75.0).is_err());
+        assert!(validate_capacity(5000.0).is_ok());
+        assert!(validate_capacity(0.0).is_err() && validate_capacity(f64::NAN).is_err());
+
+        let licence = |authority: &str, number: &str, valid_until: Option<&str>| Licence {
+            authority: authority.to_string(),
+            number: number.to_string(),
+            valid_until: valid_until.map(str::to_string),
+        };
+        let licences = validate_licences(&[
+            licence(" WDRA ", "MP/2021/0042", Some("2027-03-31")),
+            licence("FSSAI", "10019022008765", None),
+        ])
+        .unwrap();
+        assert_eq!(licences[0].authority, "WDRA");
+        assert!(validate_licences(&[licence("WDRA", "MP/1", Some("31-03-2027"))]).is_err());
+        assert!(
+            validate_licences(&[licence("WDRA", "MP/1", None), licence("wdra", "MP/1", None)])
+                .is_err()
+        );
+        assert!(validate_licences(&[licence("", "MP/1", None)]).is_err());
+    }
+}
@@ -0,0 +1,565 @@
+//! Stock transfers between warehouses
+//!
+//! `POST /api/warehouse/transfer` moves quantities of batches from one
+//! registered warehouse to another on an assigned shipment (see
+//! [`crate::transport`]). Each batch is drawn from its active receipts at the
+//! source, oldest stock first; a receipt drawn down to nothing is closed. The
+//! quantity drawn from each continues on a new receipt at the destination
+//! with the same holder, grade and validity, linked by `transferred_from`
+//! and keeping `stored_since`, so the batch's storage history and age follow
+//! it. The destination's free capacity must cover the transfer.
+//!
+//! The transfer is planned against the receipts, then pinned to IPFS and
+//! anchored with `updateWarehouseState` for both warehouses while it is
+//! `pending`; the receipts are only changed afterwards, once the draws are
+//! checked again. A transfer whose upload or anchoring fails, or whose stock
+//! changed in the meantime, is kept as `failed` with the anchors it got.
+//! Transfers are kept in `data/warehouse_transfers.json`.
+
+use crate::chain::hash_string;
+use crate::error::{format_hash, format_tx_hash, ApiError, ApiResult};
+use crate::hash_schemes::HashRecord;
+use crate::pagination::{paginate, Page, PageParams};
+use crate::state::AppState;
+use crate::transport::Carrier;
+use crate::warehouse_receipts::{
+    receipt_number, ClosedReason, ReceiptAnchor, ReceiptStatus, ReceiptStore, WarehouseReceipt,
+};
+use crate::warehouse_registry::Warehouse;
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{Datelike, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+const TRANSFERS_PATH: &str = "data/warehouse_transfers.json";
+/// Left-over quantity treated as nothing
+const QUANTITY_EPSILON_KG: f64 = 1e-6;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferMovement {
+    pub batch_id: String,
+    pub quantity_kg: f64,
+    pub from_receipt: String,
+    /// Receipt issued at the destination once the transfer completes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_receipt: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferStatus {
+    /// Being pinned and anchored; receipts not changed yet
+    Pending,
+    Completed,
+    /// Not applied to the receipts; see `error` and `anchors`
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferAnchor {
+    pub warehouse_id: String,
+    pub tx_hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarehouseTransfer {
+    pub id: u64,
+    pub transfer_number: String,
+    pub from_warehouse_id: String,
+    pub to_warehouse_id: String,
+    pub shipment_id: String,
+    pub carrier: Carrier,
+    pub movements: Vec<TransferMovement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub status: TransferStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: String,
+    #[serde(default)]
+    pub state_hash: String,
+    #[serde(default)]
+    pub metadata_cid: String,
+    #[serde(default)]
+    pub anchors: Vec<TransferAnchor>,
+}
+
+/// Receipts (by index) and quantities drawn to move `quantity_kg` of
+/// `batch_id` out of `warehouse_id`, oldest stock first
+fn plan_draws(
+    receipts: &[WarehouseReceipt],
+    warehouse_id: &str,
+    batch_id: &str,
+    quantity_kg: f64,
+) -> Result<Vec<(usize, f64)>, ApiError> {
+    let mut lots: Vec<usize> = receipts
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| {
+            r.warehouse_id == warehouse_id
+                && r.batch_id == batch_id
+                && r.status == ReceiptStatus::Active
+        })
+        .map(|(index, _)| index)
+        .collect();
+    lots.sort_by(|&a, &b| {
+        receipts[a]
+            .stored_since()
+            .cmp(receipts[b].stored_since())
+            .then(receipts[a].id.cmp(&receipts[b].id))
+    });
+
+    let mut remaining = quantity_kg;
+    let mut draws = Vec::new();
+    for index in lots {
+        if remaining <= QUANTITY_EPSILON_KG {
+            break;
+        }
+        let drawn = receipts[index].quantity_kg.min(remaining);
+        draws.push((index, drawn));
+        remaining -= drawn;
+    }
+    if remaining > QUANTITY_EPSILON_KG {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!(
+                "Warehouse {} holds {:.2} kg of batch {} free of liens, requested {:.2} kg",
+                warehouse_id,
+                quantity_kg - remaining,
+                batch_id,
+                quantity_kg
+            ),
+        ));
+    }
+    Ok(draws)
+}
+
+/// Quantity stored at `warehouse_id` under open receipts
+fn stock_kg(receipts: &[WarehouseReceipt], warehouse_id: &str) -> f64 {
+    receipts
+        .iter()
+        .filter(|r| r.warehouse_id == warehouse_id && r.status != ReceiptStatus::Closed)
+        .map(|r| r.quantity_kg)
+        .sum()
+}
+
+fn check_capacity(
+    receipts: &[WarehouseReceipt],
+    to: &Warehouse,
+    incoming_kg: f64,
+) -> Result<(), ApiError> {
+    let free_kg = to.capacity_tonnes * 1000.0 - stock_kg(receipts, &to.id);
+    if incoming_kg > free_kg + QUANTITY_EPSILON_KG {
+        return Err(ApiError::new(
+            StatusCode::CONFLICT,
+            format!(
+                "Warehouse {} has {:.2} kg of free capacity, transfer needs {:.2} kg",
+                to.id,
+                free_kg.max(0.0),
+                incoming_kg
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Indices of the source receipts of `movements`, provided each is still
+/// active at `warehouse_id` and holds the quantity drawn from it
+fn recheck_draws(
+    receipts: &[WarehouseReceipt],
+    warehouse_id: &str,
+    movements: &[TransferMovement],
+) -> Result<Vec<usize>, ApiError> {
+    movements
+        .iter()
+        .map(|m| {
+            receipts
+                .iter()
+                .position(|r| {
+                    r.receipt_number == m.from_receipt
+                        && r.warehouse_id == warehouse_id
+                        && r.status == ReceiptStatus::Active
+                        && r.quantity_kg + QUANTITY_EPSILON_KG >= m.quantity_kg
+                })
+                .ok_or_else(|| {
+                    ApiError::new(
+                        StatusCode::CONFLICT,
+                        format!(
+                            "Receipt {} changed while the transfer was anchored",
+                            m.from_receipt
+                        ),
+                    )
+                })
+        })
+        .collect()
+}
+
+pub struct TransferStore {
+    transfers: Mutex<Vec<WarehouseTransfer>>,
+}
+
+impl TransferStore {
+    pub fn load() -> Result<Self> {
+        let transfers = match std::fs::read_to_string(TRANSFERS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid warehouse transfers file {}", TRANSFERS_PATH))?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Self {
+            transfers: Mutex::new(transfers),
+        })
+    }
+
+    fn save(transfers: &[WarehouseTransfer]) -> Result<()> {
+        std::fs::write(TRANSFERS_PATH, serde_json::to_string_pretty(transfers)?)
+            .with_context(|| format!("Failed to write {}", TRANSFERS_PATH))
+    }
+
+    /// Record `transfer` as pending under the next ID
+    async fn open(&self, mut transfer: WarehouseTransfer) -> Result<WarehouseTransfer> {
+        let mut transfers = self.transfers.lock().await;
+        transfer.id = transfers.last().map(|t| t.id + 1).unwrap_or(1);
+        transfer.transfer_number = format!("WTR-{}-{:06}", Utc::now().year(), transfer.id);
+        transfers.push(transfer.clone());
+        Self::save(&transfers)?;
+        Ok(transfer)
+    }
+
+    async fn update(&self, transfer: &WarehouseTransfer) -> Result<()> {
+        let mut transfers = self.transfers.lock().await;
+        if let Some(stored) = transfers.iter_mut().find(|t| t.id == transfer.id) {
+            *stored = transfer.clone();
+        }
+        Self::save(&transfers)
+    }
+
+    /// Keep `transfer` as failed with `error`, and return the error
+    async fn fail(&self, transfer: &mut WarehouseTransfer, error: ApiError) -> ApiError {
+        tracing::warn!(
+            transfer_number = %transfer.transfer_number,
+            anchors = transfer.anchors.len(),
+            error = %error.message,
+            "Warehouse transfer failed"
+        );
+        transfer.status = TransferStatus::Failed;
+        transfer.error = Some(error.message.clone());
+        match self.update(transfer).await {
+            Ok(()) => error,
+            Err(e) => ApiError::from(e),
+        }
+    }
+}
+
+// ======================== HANDLERS ========================
+
+#[derive(Debug, Deserialize)]
+pub struct TransferLine {
+    pub batch_id: String,
+    pub quantity_kg: f64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransferRequest {
+    pub from_warehouse_id: String,
+    pub to_warehouse_id: String,
+    pub shipment_id: String,
+    pub lines: Vec<TransferLine>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// Move stock between warehouses and anchor the transfer for both
+pub async fn transfer_stock(
+    State(state): State<AppState>,
+    Json(payload): Json<TransferRequest>,
+) -> ApiResult<WarehouseTransfer> {
+    let from = state.warehouses.require(&payload.from_warehouse_id).await?;
+    let to = state.warehouses.require(&payload.to_warehouse_id).await?;
+    if from.id == to.id {
+        return Err(ApiError::bad_request(
+            "from_warehouse_id and to_warehouse_id must differ",
+        ));
+    }
+    let carrier = state.transport.carrier(&payload.shipment_id).await?;
+    if payload.lines.is_empty() {
+        return Err(ApiError::bad_request("lines must not be empty"));
+    }
+    for (i, line) in payload.lines.iter().enumerate() {
+        if !(line.quantity_kg.is_finite() && line.quantity_kg > 0.0) {
+            return Err(ApiError::bad_request(format!(
+                "Line {}: quantity_kg must be positive",
+                i
+            )));
+        }
+        if payload.lines[..i]
+            .iter()
+            .any(|l| l.batch_id == line.batch_id)
+        {
+            return Err(ApiError::bad_request(format!(
+                "Batch {} is listed twice",
+                line.batch_id
+            )));
+        }
+    }
+
+    // Plan against the current receipts, without holding them while anchoring
+    let incoming: f64 = payload.lines.iter().map(|l| l.quantity_kg).sum();
+    let movements = {
+        let receipts = state.receipts.receipts.lock().await;
+        let mut movements = Vec::new();
+        for line in &payload.lines {
+            let draws = plan_draws(&receipts, &from.id, &line.batch_id, line.quantity_kg)?;
+            movements.extend(
+                draws
+                    .into_iter()
+                    .map(|(index, quantity_kg)| TransferMovement {
+                        batch_id: receipts[index].batch_id.clone(),
+                        quantity_kg,
+                        from_receipt: receipts[index].receipt_number.clone(),
+                        to_receipt: None,
+                    }),
+            );
+        }
+        check_capacity(&receipts, &to, incoming)?;
+        movements
+    };
+
+    let mut transfer = state
+        .transfers
+        .open(WarehouseTransfer {
+            id: 0,
+            transfer_number: String::new(),
+            from_warehouse_id: from.id.clone(),
+            to_warehouse_id: to.id.clone(),
+            shipment_id: payload.shipment_id.trim().to_string(),
+            carrier,
+            movements,
+            note: payload.note,
+            status: TransferStatus::Pending,
+            error: None,
+            created_at: Utc::now().to_rfc3339(),
+            state_hash: String::new(),
+            metadata_cid: String::new(),
+            anchors: Vec::new(),
+        })
+        .await?;
+
+    let document = serde_json::json!({
+        "type": "warehouse_transfer",
+        "transfer": transfer,
+        "from_warehouse": from.metadata_entry(),
+        "to_warehouse": to.metadata_entry(),
+    });
+    let metadata_cid = match state.ipfs_client.upload_json(&document).await {
+        Ok(cid) => cid,
+        Err(e) => {
+            let error = ApiError::ipfs_upload_failed(e);
+            return Err(state.transfers.fail(&mut transfer, error).await);
+        }
+    };
+    let state_hash = match HashRecord::of_json(&document) {
+        Ok(record) => record,
+        Err(e) => return Err(state.transfers.fail(&mut transfer, e.into()).await),
+    };
+    transfer.state_hash = format_hash(state_hash.hash);
+    transfer.metadata_cid = metadata_cid.clone();
+    for warehouse_id in [&from.id, &to.id] {
+        let anchored = state
+            .chain()
+            .update_warehouse_state(
+                hash_string(warehouse_id),
+                state_hash.hash,
+                metadata_cid.clone(),
+            )
+            .await;
+        match anchored {
+            Ok(receipt) => transfer.anchors.push(TransferAnchor {
+                warehouse_id: warehouse_id.clone(),
+                tx_hash: format_tx_hash(receipt.transaction_hash),
+            }),
+            Err(e) => {
+                let error = ApiError::blockchain_failed(e);
+                return Err(state.transfers.fail(&mut transfer, error).await);
+            }
+        }
+    }
+
+    // Apply to the receipts, provided the planned stock is still there
+    let mut receipts = state.receipts.receipts.lock().await;
+    let sources = match recheck_draws(&receipts, &from.id, &transfer.movements)
+        .and_then(|sources| check_capacity(&receipts, &to, incoming).map(|()| sources))
+    {
+        Ok(sources) => sources,
+        Err(error) => return Err(state.transfers.fail(&mut transfer, error).await),
+    };
+
+    let now = Utc::now().to_rfc3339();
+    let anchor = |event: &str, warehouse: usize| ReceiptAnchor {
+        event: event.to_string(),
+        tx_hash: transfer.anchors[warehouse].tx_hash.clone(),
+        state_hash: transfer.state_hash.clone(),
+        metadata_cid: transfer.metadata_cid.clone(),
+        at: now.clone(),
+    };
+    let first_id = receipts.last().map(|r| r.id + 1).unwrap_or(1);
+    let mut arriving: Vec<WarehouseReceipt> = Vec::with_capacity(sources.len());
+    for ((&index, movement), id) in sources.iter().zip(&transfer.movements).zip(first_id..) {
+        let source = &mut receipts[index];
+        // Continues the drawn stock at the destination
+        arriving.push(WarehouseReceipt {
+            id,
+            receipt_number: receipt_number(id),
+            warehouse_id: to.id.clone(),
+            quantity_kg: movement.quantity_kg,
+            issued_at: now.clone(),
+            status: ReceiptStatus::Active,
+            lien: None,
+            endorsements: Vec::new(),
+            anchors: vec![anchor("transferred_in", 1)],
+            closed_at: None,
+            closed_reason: None,
+            transferred_from: Some(source.receipt_number.clone()),
+            stored_since: Some(source.stored_since().to_string()),
+            ..source.clone()
+        });
+        source.quantity_kg -= movement.quantity_kg;
+        if source.quantity_kg <= QUANTITY_EPSILON_KG {
+            source.quantity_kg = 0.0;
+            source.status = ReceiptStatus::Closed;
+            source.closed_at = Some(now.clone());
+            source.closed_reason = Some(ClosedReason::Transferred);
+        }
+        source.anchors.push(anchor("transferred_out", 0));
+    }
+    for (movement, arrived) in transfer.movements.iter_mut().zip(&arriving) {
+        movement.to_receipt = Some(arrived.receipt_number.clone());
+    }
+    receipts.extend(arriving);
+    ReceiptStore::save(&receipts)?;
+    drop(receipts);
+    transfer.status = TransferStatus::Completed;
+    state.transfers.update(&transfer).await?;
+
+    tracing::info!(
+        transfer_number = %transfer.transfer_number,
+        from = %transfer.from_warehouse_id,
+        to = %transfer.to_warehouse_id,
+        quantity_kg = incoming,
+        "Warehouse transfer recorded"
+    );
+    Ok(Json(transfer))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransferFilter {
+    pub batch_id: Option<String>,
+    pub warehouse_id: Option<String>,
+}
+
+/// Transfers, of one batch or touching one warehouse, oldest first
+pub async fn list_transfers(
+    State(state): State<AppState>,
+    Query(filter): Query<TransferFilter>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Page<WarehouseTransfer>> {
+    let transfers: Vec<WarehouseTransfer> = state
+        .transfers
+        .transfers
+        .lock()
+        .await
+        .iter()
+        .filter(|t| {
+            filter
+                .batch_id
+                .as_ref()
+                .is_none_or(|b| t.movements.iter().any(|m| &m.batch_id == b))
+        })
+        .filter(|t| {
+            filter
+                .warehouse_id
+                .as_ref()
+                .is_none_or(|w| &t.from_warehouse_id == w || &t.to_warehouse_id == w)
+        })
+        .cloned()
+        .collect();
+    Ok(Json(paginate(
+        "warehouse-transfers",
+        transfers,
+        &params,
+        |t| t.id,
+    )?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn receipt(id: u64, batch_id: &str, quantity_kg: f64, stored_since: &str) -> WarehouseReceipt {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "receipt_number": format!("ENWR-{}", id),
+            "batch_id": batch_id,
+            "warehouse_id": "WH-1",
+            "depositor": "FPO-1",
+            "holder": "FPO-1",
+            "commodity": "soybean",
+            "quantity_kg": quantity_kg,
+            "quality_grade": "A",
+            "issued_at": "2025-05-01T00:00:00Z",
+            "stored_since": stored_since,
+            "valid_until": "2025-11-28",
+            "status": "active",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_draws_take_oldest_stock_and_skip_liens() {
+        let mut pledged = receipt(4, "B1", 900.0, "2025-01-01T00:00:00Z");
+        pledged.status = ReceiptStatus::Pledged;
+        let receipts = vec![
+            receipt(1, "B1", 300.0, "2025-04-01T00:00:00Z"),
+            receipt(2, "B1", 200.0, "2025-02-01T00:00:00Z"),
+            receipt(3, "B2", 500.0, "2025-01-01T00:00:00Z"),
+            pledged,
+        ];
+
+        let draws = plan_draws(&receipts, "WH-1", "B1", 350.0).unwrap();
+        assert_eq!(draws, [(1, 200.0), (0, 150.0)]);
+        assert!(plan_draws(&receipts, "WH-1", "B1", 600.0).is_err());
+        assert!(plan_draws(&receipts, "WH-2", "B1", 10.0).is_err());
+        assert_eq!(stock_kg(&receipts, "WH-1"), 1900.0);
+    }
+
+    #[test]
+    fn test_draws_are_rechecked_before_commit() {
+        let mut receipts = vec![
+            receipt(1, "B1", 300.0, "2025-04-01T00:00:00Z"),
+            receipt(2, "B1", 200.0, "2025-02-01T00:00:00Z"),
+        ];
+        let movement = |from: &str, quantity_kg| TransferMovement {
+            batch_id: "B1".to_string(),
+            quantity_kg,
+            from_receipt: from.to_string(),
+            to_receipt: None,
+        };
+        let movements = [movement("ENWR-2", 200.0), movement("ENWR-1", 150.0)];
+        assert_eq!(
+            recheck_draws(&receipts, "WH-1", &movements).unwrap(),
+            [1, 0]
+        );
+
+        // Drawn down by another transfer while this one was anchored
+        receipts[0].quantity_kg = 100.0;
+        let err = recheck_draws(&receipts, "WH-1", &movements).unwrap_err();
+        assert_eq!(err.status, StatusCode::CONFLICT);
+        receipts[0].quantity_kg = 300.0;
+        receipts[1].status = ReceiptStatus::Pledged;
+        assert!(recheck_draws(&receipts, "WH-1", &movements).is_err());
+    }
+}
@@ -0,0 +1,441 @@
+//! Deterministic replay of recorded workflow runs
+//!
+//! A workflow's outcome depends on more than its input: the CIDs Pinata
+//! returns, transaction hashes and reverts from the chain, the financing and
+//! acceptance stores, the clock and the AI commit nonce. Every background
+//! job records each of these interactions, request and response, in
+//! `data/workflow_traces/<workflow_id>-<run>.json` (run 0 is the first
+//! attempt, then one per resume) unless WORKFLOW_TRACES is false.
+//! `GET /api/admin/workflow/trace/:id` returns a job's latest trace.
+//!
+//! Replaying a trace re-executes the orchestration in [`SupplyChainWorkflow`]
+//! against the recorded responses, without network access, signer keys or
+//! the server's data directory:
+//!
+//! ```text
+//! offchain replay-workflow data/workflow_traces/42-1.json
+//! ```
+//!
+//! Each interaction the workflow makes is checked against the next recorded
+//! one. A different kind or request (other metadata uploaded, another
+//! contract call) stops the replay as diverged, pointing at the
+//! orchestration change that explains a difference. Side effects are not
+//! repeated: nothing is uploaded, sent, written to batch folders or evaluated
+//! for holds. The command prints a report and fails unless the replay ends
+//! the way the recorded run did.
+
+use crate::admin::require_admin;
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+use crate::workflows::{CompleteWorkflowData, StageProgress, SupplyChainWorkflow, WorkflowResult};
+use anyhow::{anyhow, bail, Context, Result};
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub const TRACES_DIR: &str = "data/workflow_traces";
+
+/// Whether background jobs record traces (WORKFLOW_TRACES, default on)
+pub fn enabled() -> bool {
+    std::env::var("WORKFLOW_TRACES")
+        .map(|v| !matches!(v.to_lowercase().as_str(), "false" | "0" | "off"))
+        .unwrap_or(true)
+}
+
+/// One external interaction of a workflow run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// e.g. `chain.register_farmer`, `ipfs.upload_json`, `clock.now`
+    pub kind: String,
+    pub request: Value,
+    /// Response, or the error the interaction failed with
+    pub response: Result<Value, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowTrace {
+    pub workflow_id: u64,
+    /// 0 for the first attempt, then one per resume
+    pub run: u32,
+    pub recorded_at: String,
+    pub input: CompleteWorkflowData,
+    /// Job stages when the run started; resumed runs reuse completed ones
+    pub checkpoint: Vec<StageProgress>,
+    pub interactions: Vec<Interaction>,
+    /// Result, or the error the run failed with
+    pub outcome: Result<WorkflowResult, String>,
+}
+
+impl WorkflowTrace {
+    fn path(workflow_id: u64, run: u32) -> String {
+        format!("{}/{}-{}.json", TRACES_DIR, workflow_id, run)
+    }
+
+    /// Write the trace, returning its path
+    pub fn save(&self) -> Result<String> {
+        std::fs::create_dir_all(TRACES_DIR)
+            .with_context(|| format!("Failed to create {}", TRACES_DIR))?;
+        let path = Self::path(self.workflow_id, self.run);
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path))?;
+        Ok(path)
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let content =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+        serde_json::from_str(&content).with_context(|| format!("Invalid workflow trace {}", path))
+    }
+
+    /// Trace of the job's latest recorded run
+    pub fn latest(workflow_id: u64) -> Result<Option<Self>> {
+        let Ok(entries) = std::fs::read_dir(TRACES_DIR) else {
+            return Ok(None);
+        };
+        let prefix = format!("{}-", workflow_id);
+        let latest_run = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                name.strip_prefix(&prefix)?
+                    .strip_suffix(".json")?
+                    .parse::<u32>()
+                    .ok()
+            })
+            .max();
+        latest_run
+            .map(|run| Self::load(&Self::path(workflow_id, run)))
+            .transpose()
+    }
+}
+
+enum Mode {
+    Live,
+    Record(Vec<Interaction>),
+    Replay {
+        recorded: VecDeque<Interaction>,
+        replayed: usize,
+    },
+}
+
+/// External interactions of one workflow run: performed live, performed and
+/// recorded, or answered from a trace
+pub struct Interactions {
+    mode: Mutex<Mode>,
+}
+
+impl Interactions {
+    pub fn live() -> Self {
+        Self {
+            mode: Mutex::new(Mode::Live),
+        }
+    }
+
+    pub fn recording() -> Self {
+        Self {
+            mode: Mutex::new(Mode::Record(Vec::new())),
+        }
+    }
+
+    pub fn replaying(recorded: Vec<Interaction>) -> Self {
+        Self {
+            mode: Mutex::new(Mode::Replay {
+                recorded: recorded.into(),
+                replayed: 0,
+            }),
+        }
+    }
+
+    fn is_replay(&self) -> bool {
+        matches!(*self.mode.lock().unwrap(), Mode::Replay { .. })
+    }
+
+    /// Await `live`, or answer from the trace without polling it
+    pub async fn call<T, F>(&self, kind: &str, request: Value, live: F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: Future<Output = Result<T>>,
+    {
+        if let Some(recorded) = self.next_recorded(kind, &request)? {
+            return answer(kind, recorded);
+        }
+        let outcome = live.await;
+        self.record(kind, request, &outcome);
+        outcome
+    }
+
+    /// [`call`](Self::call) for synchronous interactions
+    pub fn call_sync<T>(
+        &self,
+        kind: &str,
+        request: Value,
+        live: impl FnOnce() -> Result<T>,
+    ) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        if let Some(recorded) = self.next_recorded(kind, &request)? {
+            return answer(kind, recorded);
+        }
+        let outcome = live();
+        self.record(kind, request, &outcome);
+        outcome
+    }
+
+    /// Current time, as recorded when replaying
+    pub fn now(&self) -> Result<DateTime<Utc>> {
+        let now = self.call_sync("clock.now", Value::Null, || Ok(Utc::now().to_rfc3339()))?;
+        Ok(DateTime::parse_from_rfc3339(&now)
+            .with_context(|| format!("Invalid recorded time {}", now))?
+            .with_timezone(&Utc))
+    }
+
+    /// Sleep, skipped when replaying
+    pub async fn pause(&self, duration: Duration) {
+        if !self.is_replay() {
+            tokio::time::sleep(duration).await;
+        }
+    }
+
+    /// Interactions recorded so far, taken out of the recorder
+    pub fn take_recorded(&self) -> Vec<Interaction> {
+        match &mut *self.mode.lock().unwrap() {
+            Mode::Record(recorded) => std::mem::take(recorded),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Recorded interactions the replay has answered so far
+    pub fn replayed(&self) -> usize {
+        match &*self.mode.lock().unwrap() {
+            Mode::Replay { replayed, .. } => *replayed,
+            _ => 0,
+        }
+    }
+
+    /// Next recorded interaction when replaying; an error if the workflow
+    /// asks for something else than the recorded run did
+    fn next_recorded(&self, kind: &str, request: &Value) -> Result<Option<Interaction>> {
+        let mut mode = self.mode.lock().unwrap();
+        let Mode::Replay { recorded, replayed } = &mut *mode else {
+            return Ok(None);
+        };
+        let Some(next) = recorded.pop_front() else {
+            bail!(
+                "Replay diverged after {} interactions: the workflow made {} {} where the recorded run ended",
+                replayed,
+                kind,
+                request
+            );
+        };
+        if next.kind != kind || !same_value(&next.request, request) {
+            bail!(
+                "Replay diverged at interaction {}: recorded {} {}, the workflow made {} {}",
+                *replayed + 1,
+                next.kind,
+                next.request,
+                kind,
+                request
+            );
+        }
+        *replayed += 1;
+        Ok(Some(next))
+    }
+
+    fn record<T: Serialize>(&self, kind: &str, request: Value, outcome: &Result<T>) {
+        if let Mode::Record(recorded) = &mut *self.mode.lock().unwrap() {
+            recorded.push(Interaction {
+                kind: kind.to_string(),
+                request,
+                response: outcome
+                    .as_ref()
+                    .map(|value| serde_json::to_value(value).unwrap_or(Value::Null))
+                    .map_err(|e| format!("{:#}", e)),
+            });
+        }
+    }
+}
+
+/// The recorded response as the interaction's result
+fn answer<T: DeserializeOwned>(kind: &str, recorded: Interaction) -> Result<T> {
+    match recorded.response {
+        Ok(value) => serde_json::from_value(value)
+            .with_context(|| format!("Recorded {} response does not fit", kind)),
+        Err(message) => Err(anyhow!(message)),
+    }
+}
+
+/// Equal JSON, with numbers compared within float rounding so values
+/// parsed back from a trace still match
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => match (x.as_f64(), y.as_f64()) {
+            (Some(x), Some(y)) => (x - y).abs() <= 1e-12 * x.abs().max(y.abs()).max(1.0),
+            _ => x == y,
+        },
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(x, y)| same_value(x, y))
+        }
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len()
+                && x.iter()
+                    .all(|(key, x)| y.get(key).is_some_and(|y| same_value(x, y)))
+        }
+        _ => a == b,
+    }
+}
+
+// ======================== REPLAY ========================
+
+#[derive(Debug, Serialize)]
+pub struct ReplayReport {
+    pub workflow_id: u64,
+    pub run: u32,
+    pub interactions: usize,
+    pub replayed: usize,
+    /// Whether the replay used every interaction and ended like the
+    /// recorded run
+    pub reproduced: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recorded_error: Option<String>,
+    /// Error of the replay: the recorded failure again, or a divergence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<WorkflowResult>,
+}
+
+/// Re-execute a recorded run against its recorded interactions
+pub async fn replay(trace: WorkflowTrace) -> ReplayReport {
+    let interactions = trace.interactions.len();
+    let workflow = SupplyChainWorkflow::replaying(Interactions::replaying(trace.interactions));
+    let outcome = workflow
+        .execute_full_workflow(trace.input, &trace.checkpoint)
+        .await
+        .map_err(|e| format!("{:#}", e));
+    let replayed = workflow.interactions().replayed();
+
+    let same_outcome = match (&outcome, &trace.outcome) {
+        (Ok(result), Ok(recorded)) => serde_json::to_value(result)
+            .ok()
+            .zip(serde_json::to_value(recorded).ok())
+            .is_some_and(|(result, recorded)| same_value(&result, &recorded)),
+        (Err(error), Err(recorded)) => error == recorded,
+        _ => false,
+    };
+    let (result, error) = match outcome {
+        Ok(result) => (Some(result), None),
+        Err(error) => (None, Some(error)),
+    };
+    ReplayReport {
+        workflow_id: trace.workflow_id,
+        run: trace.run,
+        interactions,
+        replayed,
+        reproduced: same_outcome && replayed == interactions,
+        recorded_error: trace.outcome.err(),
+        error,
+        result,
+    }
+}
+
+/// `offchain replay-workflow <trace.json>`: replay a trace and print the report
+pub async fn replay_command(path: Option<String>) -> Result<()> {
+    let path = path.context("Usage: offchain replay-workflow <trace.json>")?;
+    let trace = WorkflowTrace::load(&path)?;
+    tracing::info!(
+        workflow_id = trace.workflow_id,
+        run = trace.run,
+        interactions = trace.interactions.len(),
+        "Replaying workflow trace"
+    );
+
+    let report = replay(trace).await;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    if !report.reproduced {
+        bail!("Replay did not reproduce the recorded run");
+    }
+    Ok(())
+}
+
+// ======================== HANDLERS ========================
+
+/// Latest recorded trace of a workflow job (admin only)
+pub async fn get_trace(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(workflow_id): Path<u64>,
+) -> ApiResult<WorkflowTrace> {
+    require_admin(&state, &headers)?;
+
+    WorkflowTrace::latest(workflow_id)
+        .map_err(|e| ApiError::internal(format!("{:#}", e)))?
+        .map(Json)
+        .ok_or_else(|| {
+            ApiError::not_found(format!("No trace recorded for workflow {}", workflow_id))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_replay_answers_from_recording_and_detects_divergence() {
+        let recorder = Interactions::recording();
+        let cid: String = recorder
+            .call("ipfs.upload_json", json!({"price": 0.1 + 0.2}), async {
+                Ok("QmLive".to_string())
+            })
+            .await
+            .unwrap();
+        assert_eq!(cid, "QmLive");
+        let failed: Result<String> = recorder
+            .call("chain.fpo_purchase", json!({"cid": cid}), async {
+                Err(anyhow!("execution reverted"))
+            })
+            .await;
+        assert!(failed.is_err());
+        let recorded = recorder.take_recorded();
+        assert_eq!(recorded.len(), 2);
+
+        // Round trip through the trace file format
+        let recorded: Vec<Interaction> =
+            serde_json::from_str(&serde_json::to_string(&recorded).unwrap()).unwrap();
+        let replayer = Interactions::replaying(recorded.clone());
+        let cid: String = replayer
+            .call("ipfs.upload_json", json!({"price": 0.1 + 0.2}), async {
+                panic!("Replay must not perform interactions")
+            })
+            .await
+            .unwrap();
+        assert_eq!(cid, "QmLive");
+        let error = replayer
+            .call::<String, _>("chain.fpo_purchase", json!({"cid": cid}), async {
+                panic!("Replay must not perform interactions")
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "execution reverted");
+        assert_eq!(replayer.replayed(), 2);
+
+        let replayer = Interactions::replaying(recorded);
+        let diverged = replayer
+            .call::<String, _>("ipfs.upload_json", json!({"price": 0.4}), async {
+                Ok(String::new())
+            })
+            .await
+            .unwrap_err();
+        assert!(diverged.to_string().contains("diverged at interaction 1"));
+    }
+}
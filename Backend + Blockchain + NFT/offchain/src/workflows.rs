@@ -23,15 +23,52 @@
 //! let workflow = SupplyChainWorkflow::new(app_state);
 //!
 //! // Execute complete workflow
-//! let result = workflow.execute_full_workflow(workflow_data).await?;
+//! let result = workflow.execute_full_workflow(workflow_data, &[]).await?;
 //! ```
+//!
+//! # Background Jobs
+//!
+//! `POST /api/workflow/execute` does not wait for the dozens of transactions
+//! a workflow sends. It records a [`WorkflowJob`], answers 202 Accepted with
+//! its `workflow_id` and runs the workflow in the background;
+//! `GET /api/workflow/status/:id` reports per-stage progress, transaction
+//! hashes and errors, and `GET /api/workflow/stream/:id` pushes the same
+//! progress as server-sent events while the job runs. Jobs run WORKFLOW_MAX_CONCURRENT at a time (default 1,
+//! so one signer's transactions are not interleaved) and are kept in
+//! `data/workflow_jobs.json`; jobs still running when the server stops are
+//! marked failed on the next start.
+//!
+//! The job record is the workflow's checkpoint: every transaction and CID is
+//! saved as it is sent. `POST /api/workflow/resume/:id` restarts a failed job
+//! from the stage that failed, reusing completed stages instead of
+//! registering the farmer and purchasing the batch again. Unless
+//! LOT_ACCEPTANCE_REQUIRED is false, a job stops at processing until the
+//! processor has accepted the lot, and is resumed after that.
+//!
+//! Every external interaction of a stage goes through the workflow's
+//! [`Interactions`], so a job's run can be recorded and replayed locally
+//! (see [`crate::workflow_replay`]).
 
 use crate::chain::{generate_commit_hash, hash_string};
+use crate::did_resolver::chain_did;
+use crate::error::ApiError;
+use crate::grades::Grade;
+use crate::hash_schemes::{record_folder_hash, HashRecord};
+use crate::holds::{self, ResultSource};
+use crate::processor_registry::Processor;
+use crate::shelf_life::{ShelfLife, SourceConditions};
+use crate::sku_units::UnitTree;
 use crate::state::AppState;
+use crate::transport::Carrier;
+use crate::warehouse_registry::Warehouse;
+use crate::workflow_replay::{self, Interactions, WorkflowTrace};
 use alloy::primitives::FixedBytes;
 use anyhow::{Context, Result};
+use axum::http::StatusCode;
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use serde_json::json;
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex, Semaphore};
 
 // ============================================================================
 //                         BATCH HELPERS
@@ -113,6 +150,8 @@ pub struct Checkpoint {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessingData {
     pub input_batch_id: String,
+    /// Registered processor; `process_type` must be one of its capabilities
+    pub processor_id: String,
     pub process_type: String,
     pub yield_percentage: f64,
     pub output_products: Vec<OutputProduct>,
@@ -131,7 +170,10 @@ pub struct PackagingData {
     pub package_type: String,
     pub units_per_package: u32,
     pub total_packages: u32,
-    pub expiry_months: u32,
+    /// Shelf life of products without a shelf-life rule (see
+    /// [`crate::shelf_life`])
+    #[serde(default)]
+    pub expiry_months: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -182,29 +224,523 @@ pub struct WorkflowSummary {
     pub trace_path: String,
 }
 
+// ============================================================================
+//                         WORKFLOW JOBS
+// ============================================================================
+
+const JOBS_PATH: &str = "data/workflow_jobs.json";
+
+/// Stages whose transactions are checkpointed one by one and continue where
+/// they stopped on resume; others restart from scratch
+const ITEMIZED_STAGES: [usize; 2] = [4, 6];
+
+/// Stage names, in execution order (stage numbers start at 1)
+pub const STAGES: [&str; 7] = [
+    "farmer_registration",
+    "fpo_purchase",
+    "warehouse_storage",
+    "logistics",
+    "processing",
+    "packaging",
+    "ai_scoring",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StageStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageProgress {
+    pub stage: u32,
+    pub name: String,
+    pub status: StageStatus,
+    /// Transactions the stage sends when it completes
+    pub expected_txs: u32,
+    pub tx_hashes: Vec<String>,
+    pub ipfs_cids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowJob {
+    pub workflow_id: u64,
+    pub status: JobStatus,
+    pub farmer_did: String,
+    pub batch_id: String,
+    pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+    pub stages: Vec<StageProgress>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<WorkflowResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Times the job was resumed after failing
+    #[serde(default)]
+    pub resumes: u32,
+    /// Workflow input, kept for resuming
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request: Option<CompleteWorkflowData>,
+}
+
+impl WorkflowJob {
+    pub fn new(workflow_id: u64, data: &CompleteWorkflowData) -> Self {
+        let expected = [
+            1,
+            1,
+            1,
+            data.logistics.checkpoints.len() as u32,
+            1,
+            data.packaging.total_packages,
+            if data.ai_scoring.is_some() { 2 } else { 0 },
+        ];
+        let stages = STAGES
+            .iter()
+            .zip(expected)
+            .enumerate()
+            .map(|(idx, (name, expected_txs))| StageProgress {
+                stage: idx as u32 + 1,
+                name: name.to_string(),
+                status: StageStatus::Pending,
+                expected_txs,
+                tx_hashes: Vec::new(),
+                ipfs_cids: Vec::new(),
+                error: None,
+                started_at: None,
+                finished_at: None,
+            })
+            .collect();
+
+        Self {
+            workflow_id,
+            status: JobStatus::Queued,
+            farmer_did: data.farmer.farmer_did.clone(),
+            batch_id: data.fpo_purchase.batch_id.clone(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            started_at: None,
+            finished_at: None,
+            stages,
+            result: None,
+            error: None,
+            resumes: 0,
+            request: Some(data.clone()),
+        }
+    }
+
+    /// Queue a failed job again, keeping the outputs that will be reused
+    pub fn prepare_resume(&mut self) -> Result<CompleteWorkflowData, String> {
+        if self.status != JobStatus::Failed {
+            return Err(format!(
+                "Workflow {} is {:?}, only failed workflows can be resumed",
+                self.workflow_id, self.status
+            ));
+        }
+        let data = self
+            .request
+            .clone()
+            .ok_or_else(|| format!("Workflow {} has no saved input", self.workflow_id))?;
+
+        for stage in &mut self.stages {
+            if stage.status == StageStatus::Completed {
+                continue;
+            }
+            if !ITEMIZED_STAGES.contains(&(stage.stage as usize)) {
+                stage.tx_hashes.clear();
+                stage.ipfs_cids.clear();
+            }
+            stage.status = StageStatus::Pending;
+            stage.error = None;
+            stage.started_at = None;
+            stage.finished_at = None;
+        }
+        self.status = JobStatus::Queued;
+        self.error = None;
+        self.finished_at = None;
+        self.resumes += 1;
+        Ok(data)
+    }
+
+    fn stage_mut(&mut self, stage: usize) -> Option<&mut StageProgress> {
+        self.stages.get_mut(stage.checked_sub(1)?)
+    }
+
+    fn finish(&mut self, status: JobStatus) {
+        let now = chrono::Utc::now().to_rfc3339();
+        for stage in &mut self.stages {
+            if stage.status == StageStatus::Pending && stage.expected_txs == 0 {
+                stage.status = StageStatus::Skipped;
+            }
+        }
+        self.status = status;
+        self.finished_at = Some(now);
+    }
+
+    /// Mark the job failed, blaming the stage that was running
+    pub fn fail(&mut self, error: String) {
+        let now = chrono::Utc::now().to_rfc3339();
+        if let Some(stage) = self
+            .stages
+            .iter_mut()
+            .find(|s| s.status == StageStatus::Running)
+        {
+            stage.status = StageStatus::Failed;
+            stage.error = Some(error.clone());
+            stage.finished_at = Some(now);
+        }
+        self.error = Some(error);
+        self.finish(JobStatus::Failed);
+    }
+}
+
+pub struct WorkflowJobStore {
+    jobs: Mutex<Vec<WorkflowJob>>,
+    slots: Semaphore,
+    /// ID of every job that was just updated, for progress streams
+    updates: broadcast::Sender<u64>,
+}
+
+impl WorkflowJobStore {
+    pub fn load() -> Result<Self> {
+        let mut jobs: Vec<WorkflowJob> = match std::fs::read_to_string(JOBS_PATH) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid workflow jobs file {}", JOBS_PATH))?,
+            Err(_) => Vec::new(),
+        };
+
+        let mut interrupted = 0;
+        for job in jobs
+            .iter_mut()
+            .filter(|j| matches!(j.status, JobStatus::Queued | JobStatus::Running))
+        {
+            job.fail("Interrupted by server restart".to_string());
+            interrupted += 1;
+        }
+        if interrupted > 0 {
+            tracing::warn!(
+                interrupted,
+                "Workflow jobs interrupted by restart marked failed"
+            );
+            Self::save(&jobs)?;
+        }
+
+        let max_concurrent = std::env::var("WORKFLOW_MAX_CONCURRENT")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1usize)
+            .max(1);
+
+        Ok(Self {
+            jobs: Mutex::new(jobs),
+            slots: Semaphore::new(max_concurrent),
+            updates: broadcast::channel(64).0,
+        })
+    }
+
+    fn save(jobs: &[WorkflowJob]) -> Result<()> {
+        std::fs::write(JOBS_PATH, serde_json::to_string_pretty(jobs)?)
+            .with_context(|| format!("Failed to write {}", JOBS_PATH))
+    }
+
+    pub async fn create(&self, data: &CompleteWorkflowData) -> Result<WorkflowJob> {
+        let mut jobs = self.jobs.lock().await;
+        let id = jobs.last().map(|j| j.workflow_id + 1).unwrap_or(1);
+        let job = WorkflowJob::new(id, data);
+        jobs.push(job.clone());
+        Self::save(&jobs)?;
+        Ok(job)
+    }
+
+    pub async fn resume(&self, workflow_id: u64) -> Result<CompleteWorkflowData, ApiError> {
+        let mut jobs = self.jobs.lock().await;
+        let job = jobs
+            .iter_mut()
+            .find(|j| j.workflow_id == workflow_id)
+            .ok_or_else(|| ApiError::not_found(format!("Workflow {} not found", workflow_id)))?;
+        let data = job
+            .prepare_resume()
+            .map_err(|e| ApiError::new(StatusCode::CONFLICT, e))?;
+        Self::save(&jobs)?;
+        Ok(data)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<u64> {
+        self.updates.subscribe()
+    }
+
+    pub async fn get(&self, workflow_id: u64) -> Option<WorkflowJob> {
+        self.jobs
+            .lock()
+            .await
+            .iter()
+            .find(|j| j.workflow_id == workflow_id)
+            .cloned()
+    }
+
+    /// Apply a progress update; a failed write is logged, not fatal to the
+    /// workflow that reported it
+    async fn update(&self, workflow_id: u64, f: impl FnOnce(&mut WorkflowJob)) {
+        let mut jobs = self.jobs.lock().await;
+        let Some(job) = jobs.iter_mut().find(|j| j.workflow_id == workflow_id) else {
+            return;
+        };
+        f(job);
+        if let Err(e) = Self::save(&jobs) {
+            tracing::error!(workflow_id, error = %format!("{:#}", e), "Failed to save workflow job");
+        }
+        // No receivers just means nobody is streaming
+        let _ = self.updates.send(workflow_id);
+    }
+}
+
+/// Run a recorded job in the background
+pub fn spawn_job(state: AppState, workflow_id: u64, data: CompleteWorkflowData) {
+    tokio::spawn(async move {
+        let jobs = state.workflow_jobs.clone();
+        let _slot = jobs.slots.acquire().await;
+        let mut checkpoint = Vec::new();
+        let mut run = 0;
+        jobs.update(workflow_id, |job| {
+            job.status = JobStatus::Running;
+            job.started_at = Some(chrono::Utc::now().to_rfc3339());
+            checkpoint = job.stages.clone();
+            run = job.resumes;
+        })
+        .await;
+
+        let recording = workflow_replay::enabled();
+        let mut workflow = SupplyChainWorkflow::new(state).with_job(workflow_id);
+        if recording {
+            workflow = workflow.recording();
+        }
+        let outcome = workflow
+            .execute_full_workflow(data.clone(), &checkpoint)
+            .await;
+
+        if recording {
+            let trace = WorkflowTrace {
+                workflow_id,
+                run,
+                recorded_at: chrono::Utc::now().to_rfc3339(),
+                input: data,
+                checkpoint,
+                interactions: workflow.interactions().take_recorded(),
+                outcome: outcome.as_ref().map_err(|e| format!("{:#}", e)).cloned(),
+            };
+            match trace.save() {
+                Ok(path) => tracing::info!(workflow_id, trace = %path, "Workflow trace recorded"),
+                Err(e) => tracing::error!(
+                    workflow_id,
+                    error = %format!("{:#}", e),
+                    "Failed to save workflow trace"
+                ),
+            }
+        }
+
+        match outcome {
+            Ok(result) => {
+                jobs.update(workflow_id, |job| {
+                    job.result = Some(result);
+                    job.finish(JobStatus::Completed);
+                })
+                .await;
+            }
+            Err(e) => {
+                let error = format!("{:#}", e);
+                tracing::error!(workflow_id, error = %error, "Workflow job failed");
+                jobs.update(workflow_id, |job| job.fail(error)).await;
+            }
+        }
+    });
+}
+
+/// Transaction and CID of a completed single-transaction stage
+fn completed_output(checkpoint: &[StageProgress], stage: usize) -> Option<(String, String)> {
+    let (txs, cids) = completed_outputs(checkpoint, stage)?;
+    Some((txs.first()?.clone(), cids.first()?.clone()))
+}
+
+fn completed_outputs(
+    checkpoint: &[StageProgress],
+    stage: usize,
+) -> Option<(Vec<String>, Vec<String>)> {
+    let progress = checkpoint.get(stage.checked_sub(1)?)?;
+    (progress.status == StageStatus::Completed).then(|| {
+        tracing::info!(stage, name = %progress.name, "⏭️ Stage restored from checkpoint");
+        (progress.tx_hashes.clone(), progress.ipfs_cids.clone())
+    })
+}
+
+/// Outputs a failed multi-transaction stage sent before it stopped
+fn partial_outputs(checkpoint: &[StageProgress], stage: usize) -> (Vec<String>, Vec<String>) {
+    stage
+        .checked_sub(1)
+        .and_then(|idx| checkpoint.get(idx))
+        .filter(|p| p.tx_hashes.len() == p.ipfs_cids.len())
+        .map(|p| (p.tx_hashes.clone(), p.ipfs_cids.clone()))
+        .unwrap_or_default()
+}
+
+/// Stages whose status or transaction count changed since `seen`, which is
+/// brought up to date
+fn changed_stages<'a>(
+    seen: &mut Vec<(StageStatus, usize)>,
+    job: &'a WorkflowJob,
+) -> Vec<&'a StageProgress> {
+    seen.resize(job.stages.len(), (StageStatus::Pending, 0));
+    job.stages
+        .iter()
+        .zip(seen.iter_mut())
+        .filter_map(|(stage, seen)| {
+            let now = (stage.status, stage.tx_hashes.len());
+            (*seen != now).then(|| {
+                *seen = now;
+                stage
+            })
+        })
+        .collect()
+}
+
+fn sku_ids(data: &PackagingData) -> Vec<String> {
+    (1..=data.total_packages)
+        .map(|package_num| format!("{}-{:04}", data.sku_prefix, package_num))
+        .collect()
+}
+
+/// Crop whose grade taxonomy applies to the purchase; the first crop listed
+/// for the farmer
+fn purchase_crop(farmer: &FarmerData) -> &str {
+    farmer.crops.first().map(String::as_str).unwrap_or_default()
+}
+
 // ============================================================================
 //                         WORKFLOW ORCHESTRATOR
 // ============================================================================
 
 pub struct SupplyChainWorkflow {
-    state: AppState,
+    /// `None` when replaying a trace
+    state: Option<AppState>,
+    /// Background job receiving stage progress
+    job: Option<u64>,
+    /// Chain, IPFS, store, clock and randomness interactions
+    io: Interactions,
 }
 
 impl SupplyChainWorkflow {
     /// Create new workflow orchestrator
     pub fn new(state: AppState) -> Self {
-        Self { state }
+        Self {
+            state: Some(state),
+            job: None,
+            io: Interactions::live(),
+        }
+    }
+
+    /// Orchestrator answering every interaction from a recorded trace
+    pub fn replaying(io: Interactions) -> Self {
+        Self {
+            state: None,
+            job: None,
+            io,
+        }
+    }
+
+    /// Report stage progress to a workflow job
+    pub fn with_job(mut self, workflow_id: u64) -> Self {
+        self.job = Some(workflow_id);
+        self
+    }
+
+    /// Record the run's interactions for replay
+    pub fn recording(mut self) -> Self {
+        self.io = Interactions::recording();
+        self
+    }
+
+    pub fn interactions(&self) -> &Interactions {
+        &self.io
     }
 
-    /// Execute complete supply chain workflow from farmer to retail
+    fn state(&self) -> Result<&AppState> {
+        self.state
+            .as_ref()
+            .context("A replayed workflow has no server state")
+    }
+
+    async fn stage_started(&self, stage: usize) {
+        if let (Some(id), Some(state)) = (self.job, &self.state) {
+            state
+                .workflow_jobs
+                .update(id, |job| {
+                    if let Some(s) = job.stage_mut(stage) {
+                        s.status = StageStatus::Running;
+                        s.started_at = Some(chrono::Utc::now().to_rfc3339());
+                    }
+                })
+                .await;
+        }
+    }
+
+    async fn stage_output(&self, stage: usize, tx: &str, cid: Option<&str>) {
+        if let (Some(id), Some(state)) = (self.job, &self.state) {
+            state
+                .workflow_jobs
+                .update(id, |job| {
+                    if let Some(s) = job.stage_mut(stage) {
+                        s.tx_hashes.push(tx.to_string());
+                        s.ipfs_cids.extend(cid.map(str::to_string));
+                    }
+                })
+                .await;
+        }
+    }
+
+    async fn stage_completed(&self, stage: usize) {
+        if let (Some(id), Some(state)) = (self.job, &self.state) {
+            state
+                .workflow_jobs
+                .update(id, |job| {
+                    if let Some(s) = job.stage_mut(stage) {
+                        s.status = StageStatus::Completed;
+                        s.finished_at = Some(chrono::Utc::now().to_rfc3339());
+                    }
+                })
+                .await;
+        }
+    }
+
+    /// Execute complete supply chain workflow from farmer to retail, reusing
+    /// the outputs of stages `checkpoint` records as completed (pass `&[]`
+    /// for a fresh run). Logistics checkpoints and packages already sent
+    /// by a partly completed stage are not sent again; the AI stage restarts
+    /// from its commit.
     pub async fn execute_full_workflow(
         &self,
         data: CompleteWorkflowData,
+        checkpoint: &[StageProgress],
     ) -> Result<WorkflowResult> {
-        let start_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        let start_time = self.io.now()?;
 
         tracing::info!("🚀 Starting complete supply chain workflow");
 
@@ -240,7 +776,16 @@ impl SupplyChainWorkflow {
 
         // Stage 1: Farmer Registration
         tracing::info!("📝 Stage 1/7: Farmer Registration");
-        let (farmer_tx, farmer_cid) = self.register_farmer(&data.farmer).await?;
+        let (farmer_tx, farmer_cid) = match completed_output(checkpoint, 1) {
+            Some(output) => output,
+            None => {
+                self.stage_started(1).await;
+                let (tx, cid) = self.register_farmer(&data.farmer).await?;
+                self.stage_output(1, &tx, Some(&cid)).await;
+                self.stage_completed(1).await;
+                (tx, cid)
+            }
+        };
         result.farmer_tx = farmer_tx;
         result.ipfs_cids.farmer_metadata = farmer_cid;
         result.summary.successful_stages += 1;
@@ -249,9 +794,18 @@ impl SupplyChainWorkflow {
 
         // Stage 2: FPO Purchase
         tracing::info!("🏢 Stage 2/7: FPO Purchase");
-        let (fpo_tx, fpo_cid) = self
-            .record_fpo_purchase(&data.farmer.farmer_did, &data.fpo_purchase)
-            .await?;
+        let (fpo_tx, fpo_cid) = match completed_output(checkpoint, 2) {
+            Some(output) => output,
+            None => {
+                self.stage_started(2).await;
+                let (tx, cid) = self
+                    .record_fpo_purchase(&data.farmer, &data.fpo_purchase)
+                    .await?;
+                self.stage_output(2, &tx, Some(&cid)).await;
+                self.stage_completed(2).await;
+                (tx, cid)
+            }
+        };
         result.fpo_tx = fpo_tx;
         result.ipfs_cids.fpo_metadata = fpo_cid;
         result.summary.successful_stages += 1;
@@ -260,7 +814,16 @@ impl SupplyChainWorkflow {
 
         // Stage 3: Warehouse Storage
         tracing::info!("🏭 Stage 3/7: Warehouse Storage");
-        let (warehouse_tx, warehouse_cid) = self.record_warehouse_storage(&data.warehouse).await?;
+        let (warehouse_tx, warehouse_cid) = match completed_output(checkpoint, 3) {
+            Some(output) => output,
+            None => {
+                self.stage_started(3).await;
+                let (tx, cid) = self.record_warehouse_storage(&data.warehouse).await?;
+                self.stage_output(3, &tx, Some(&cid)).await;
+                self.stage_completed(3).await;
+                (tx, cid)
+            }
+        };
         result.warehouse_tx = warehouse_tx;
         result.ipfs_cids.warehouse_metadata = warehouse_cid;
         result.summary.successful_stages += 1;
@@ -269,8 +832,16 @@ impl SupplyChainWorkflow {
 
         // Stage 4: Logistics Tracking
         tracing::info!("🚚 Stage 4/7: Logistics Tracking");
-        let (logistics_txs, logistics_cids) =
-            self.record_logistics_journey(&data.logistics).await?;
+        let (logistics_txs, logistics_cids) = match completed_outputs(checkpoint, 4) {
+            Some(outputs) => outputs,
+            None => {
+                self.stage_started(4).await;
+                let sent = partial_outputs(checkpoint, 4);
+                let outputs = self.record_logistics_journey(&data.logistics, sent).await?;
+                self.stage_completed(4).await;
+                outputs
+            }
+        };
         result.logistics_txs = logistics_txs.clone();
         result.ipfs_cids.logistics_metadata = logistics_cids;
         result.summary.successful_stages += 1;
@@ -279,9 +850,18 @@ impl SupplyChainWorkflow {
 
         // Stage 5: Processing
         tracing::info!("⚙️ Stage 5/7: Batch Processing");
-        let (processing_tx, processing_cid, output_batches) = self
-            .process_batch(&data.fpo_purchase.batch_id, &data.processing)
-            .await?;
+        let (processing_tx, processing_cid) = match completed_output(checkpoint, 5) {
+            Some(output) => output,
+            None => {
+                self.stage_started(5).await;
+                let (tx, cid) = self
+                    .process_batch(&data.fpo_purchase.batch_id, &data.processing)
+                    .await?;
+                self.stage_output(5, &tx, Some(&cid)).await;
+                self.stage_completed(5).await;
+                (tx, cid)
+            }
+        };
         result.processing_tx = processing_tx;
         result.ipfs_cids.processing_metadata = processing_cid;
         result.summary.successful_stages += 1;
@@ -290,9 +870,26 @@ impl SupplyChainWorkflow {
 
         // Stage 6: Packaging
         tracing::info!("📦 Stage 6/7: SKU Packaging");
-        let (packaging_txs, packaging_cids, skus) = self
-            .create_retail_packages(&output_batches[0], &data.packaging)
-            .await?;
+        let parent_batch = data
+            .processing
+            .output_products
+            .first()
+            .map(|p| p.product_id.as_str())
+            .context("Processing produced no output batches")?;
+        let skus = sku_ids(&data.packaging);
+        let (packaging_txs, packaging_cids) = match completed_outputs(checkpoint, 6) {
+            Some(outputs) => outputs,
+            None => {
+                self.stage_started(6).await;
+                let sent = partial_outputs(checkpoint, 6);
+                let shelf_life = self.shelf_life(&data).await?;
+                let outputs = self
+                    .create_retail_packages(parent_batch, &data.packaging, &shelf_life, sent)
+                    .await?;
+                self.stage_completed(6).await;
+                outputs
+            }
+        };
         result.packaging_txs = packaging_txs;
         result.ipfs_cids.packaging_metadata = packaging_cids;
         result.final_skus = skus;
@@ -303,9 +900,19 @@ impl SupplyChainWorkflow {
         // Stage 7: AI Quality Scoring (Optional)
         if let Some(ai_data) = &data.ai_scoring {
             tracing::info!("🤖 Stage 7/7: AI Quality Scoring");
-            let (commit_tx, reveal_tx, ai_cid) = self
-                .execute_ai_scoring(&data.fpo_purchase.batch_id, ai_data)
-                .await?;
+            let (commit_tx, reveal_tx, ai_cid) = match completed_outputs(checkpoint, 7) {
+                Some((txs, cids)) if txs.len() == 2 && cids.len() == 1 => {
+                    (txs[0].clone(), txs[1].clone(), cids[0].clone())
+                }
+                _ => {
+                    self.stage_started(7).await;
+                    let outputs = self
+                        .execute_ai_scoring(&data.farmer, &data.fpo_purchase, ai_data)
+                        .await?;
+                    self.stage_completed(7).await;
+                    outputs
+                }
+            };
             result.ai_commit_tx = Some(commit_tx);
             result.ai_reveal_tx = Some(reveal_tx);
             result.ipfs_cids.ai_metadata = Some(ai_cid);
@@ -314,11 +921,8 @@ impl SupplyChainWorkflow {
         }
 
         // Calculate workflow duration
-        let end_time = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        result.summary.workflow_duration_secs = end_time - start_time;
+        let end_time = self.io.now()?;
+        result.summary.workflow_duration_secs = (end_time - start_time).num_seconds().max(0) as u64;
 
         // Build trace path
         result.summary.trace_path = format!(
@@ -357,13 +961,11 @@ impl SupplyChainWorkflow {
             "land_area": data.land_area,
             "crops": data.crops,
             "contact": data.contact,
-            "registration_timestamp": chrono::Utc::now().to_rfc3339()
+            "registration_timestamp": self.io.now()?.to_rfc3339()
         });
 
         // Upload to IPFS
         let cid = self
-            .state
-            .ipfs_client
             .upload_json(&metadata)
             .await
             .context("Failed to upload farmer metadata to IPFS")?;
@@ -378,111 +980,166 @@ impl SupplyChainWorkflow {
         let crop_id_hash = hash_string(&data.crop_id);
 
         // Register on blockchain
-        let receipt = self
-            .state
-            .blockchain_client
-            .register_farmer(farmer_did, crop_id_hash, cid.clone())
+        let request = json!({
+            "farmer_did": farmer_did,
+            "crop_id_hash": crop_id_hash,
+            "metadata_cid": cid,
+        });
+        let tx = self
+            .io
+            .call("chain.register_farmer", request, async {
+                let receipt = self
+                    .state()?
+                    .chain()
+                    .register_farmer(farmer_did, crop_id_hash, cid.clone())
+                    .await?;
+                Ok(format!("{:?}", receipt.transaction_hash))
+            })
             .await
             .context("Blockchain registration failed")?;
 
-        Ok((format!("{:?}", receipt.transaction_hash), cid))
+        Ok((tx, cid))
     }
 
     async fn record_fpo_purchase(
         &self,
-        farmer_did: &str,
+        farmer: &FarmerData,
         data: &FpoPurchaseData,
     ) -> Result<(String, String)> {
+        let farmer_did = farmer.farmer_did.as_str();
+        let grade = self.resolve_grade(purchase_crop(farmer), &data.quality_grade)?;
+
         // Prepare metadata
         let metadata = serde_json::json!({
             "batch_id": data.batch_id,
             "quantity_kg": data.quantity_kg,
-            "quality_grade": data.quality_grade,
+            "quality_grade": grade.code,
+            "grade_score": grade.score,
             "moisture_content": data.moisture_content,
             "purchase_price": data.purchase_price,
             "purchase_date": data.purchase_date,
-            "verification_timestamp": chrono::Utc::now().to_rfc3339()
+            "verification_timestamp": self.io.now()?.to_rfc3339()
         });
 
         // 1) Use batch folder
         let folder = batch_folder(&data.batch_id);
 
         // 2) Write metadata into the batch folder
-        self.state
-            .ipfs_client
-            .write_json_to_folder(&folder, "fpo_purchase.json", &metadata)
+        self.write_json_to_folder(&folder, "fpo_purchase.json", &metadata)
             .context("Failed to write FPO metadata to batch folder")?;
 
         // 3) Upload entire folder -> get root CID
         let cid = self
-            .state
-            .ipfs_client
             .upload_folder(&folder)
             .await
             .context("Failed to upload batch folder to IPFS")?;
 
         // Hash batch ID
         let batch_hash = hash_string(&data.batch_id);
-        let farmer_did: FixedBytes<32> = farmer_did.parse().context("Invalid farmer DID")?;
+        let farmer_did = chain_did(farmer_did)
+            .map_err(anyhow::Error::msg)
+            .context("Invalid farmer DID")?;
 
         // Record on blockchain
-        let receipt = self
-            .state
-            .blockchain_client
-            .fpo_purchase(batch_hash, farmer_did, cid.clone())
+        let request = json!({
+            "batch_hash": batch_hash,
+            "farmer_did": farmer_did,
+            "metadata_cid": cid,
+        });
+        let tx = self
+            .io
+            .call("chain.fpo_purchase", request, async {
+                let receipt = self
+                    .state()?
+                    .chain()
+                    .fpo_purchase(batch_hash, farmer_did, cid.clone())
+                    .await?;
+                Ok(format!("{:?}", receipt.transaction_hash))
+            })
             .await
             .context("Blockchain FPO purchase failed")?;
 
-        Ok((format!("{:?}", receipt.transaction_hash), cid))
+        Ok((tx, cid))
     }
 
     async fn record_warehouse_storage(&self, data: &WarehouseData) -> Result<(String, String)> {
+        let request = json!({ "warehouse_id": data.warehouse_id });
+        let warehouse: Warehouse = self
+            .io
+            .call("warehouses.require", request, async {
+                self.state()?
+                    .warehouses
+                    .require(&data.warehouse_id)
+                    .await
+                    .map_err(|e| anyhow::anyhow!(e.message))
+            })
+            .await?;
+
         // Prepare IoT data
         let iot_data = serde_json::json!({
             "warehouse_id": data.warehouse_id,
+            "warehouse": warehouse.metadata_entry(),
             "temperature_celsius": data.temperature_celsius,
             "humidity_percent": data.humidity_percent,
             "storage_duration_days": data.storage_duration_days,
-            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "timestamp": self.io.now()?.to_rfc3339(),
             "sensor_status": "operational"
         });
 
         // Upload to IPFS
         let cid = self
-            .state
-            .ipfs_client
             .upload_json(&iot_data)
             .await
             .context("Failed to upload warehouse data to IPFS")?;
 
         // Hash warehouse state
         let warehouse_id = hash_string(&data.warehouse_id);
-        let iot_json = serde_json::to_vec(&iot_data)?;
-        let state_hash = crate::chain::hash_bytes(&iot_json);
+        let state_hash = HashRecord::of_json(&iot_data)?.hash;
 
         // Update on blockchain
-        let receipt = self
-            .state
-            .blockchain_client
-            .update_warehouse_state(warehouse_id, state_hash, cid.clone())
+        let request = json!({
+            "warehouse_id": warehouse_id,
+            "state_hash": state_hash,
+            "metadata_cid": cid,
+        });
+        let tx = self
+            .io
+            .call("chain.update_warehouse_state", request, async {
+                let receipt = self
+                    .state()?
+                    .chain()
+                    .update_warehouse_state(warehouse_id, state_hash, cid.clone())
+                    .await?;
+                Ok(format!("{:?}", receipt.transaction_hash))
+            })
             .await
             .context("Blockchain warehouse update failed")?;
 
-        Ok((format!("{:?}", receipt.transaction_hash), cid))
+        Ok((tx, cid))
     }
 
+    /// `sent` holds the transactions and CIDs of checkpoints already recorded
     async fn record_logistics_journey(
         &self,
         data: &LogisticsData,
+        sent: (Vec<String>, Vec<String>),
     ) -> Result<(Vec<String>, Vec<String>)> {
-        let mut txs = Vec::new();
-        let mut cids = Vec::new();
+        let (mut txs, mut cids) = sent;
+
+        // Shipments assigned through the transport registry name their carrier
+        let request = json!({ "shipment_id": data.shipment_id });
+        let carrier: Option<Carrier> = self
+            .io
+            .call("transport.assigned", request, async {
+                Ok(self.state()?.transport.assigned(&data.shipment_id).await)
+            })
+            .await?;
 
-        for (idx, checkpoint) in data.checkpoints.iter().enumerate() {
+        for (idx, checkpoint) in data.checkpoints.iter().enumerate().skip(txs.len()) {
             let is_delivered = idx == data.checkpoints.len() - 1;
 
             // Prepare GPS data
-            let gps_data = serde_json::json!({
+            let mut gps_data = serde_json::json!({
                 "shipment_id": data.shipment_id,
                 "checkpoint": idx + 1,
                 "total_checkpoints": data.checkpoints.len(),
@@ -494,11 +1151,12 @@ impl SupplyChainWorkflow {
                 "timestamp": checkpoint.timestamp,
                 "is_delivered": is_delivered
             });
+            if let Some(carrier) = &carrier {
+                gps_data["carrier"] = serde_json::to_value(carrier)?;
+            }
 
             // Upload to IPFS
             let cid = self
-                .state
-                .ipfs_client
                 .upload_json(&gps_data)
                 .await
                 .context("Failed to upload logistics data to IPFS")?;
@@ -508,16 +1166,40 @@ impl SupplyChainWorkflow {
             let location_hash = hash_string(&checkpoint.location);
 
             // Record on blockchain
-            let receipt = self
-                .state
-                .blockchain_client
-                .record_logistics(shipment_id, location_hash, is_delivered, cid.clone())
+            let request = json!({
+                "shipment_id": shipment_id,
+                "location_hash": location_hash,
+                "is_delivered": is_delivered,
+                "metadata_cid": cid,
+            });
+            let tx: String = self
+                .io
+                .call("chain.record_logistics", request, async {
+                    let receipt = self
+                        .state()?
+                        .chain()
+                        .record_logistics(shipment_id, location_hash, is_delivered, cid.clone())
+                        .await?;
+                    Ok(format!("{:?}", receipt.transaction_hash))
+                })
                 .await
                 .context("Blockchain logistics record failed")?;
 
-            txs.push(format!("{:?}", receipt.transaction_hash));
+            self.stage_output(4, &tx, Some(&cid)).await;
+            txs.push(tx);
             cids.push(cid);
         }
+        if carrier.is_some() {
+            let request = json!({ "shipment_id": data.shipment_id });
+            self.io
+                .call("transport.mark_delivered", request, async {
+                    self.state()?
+                        .transport
+                        .mark_delivered(&data.shipment_id)
+                        .await
+                })
+                .await?;
+        }
 
         Ok((txs, cids))
     }
@@ -526,29 +1208,63 @@ impl SupplyChainWorkflow {
         &self,
         input_batch_id: &str,
         data: &ProcessingData,
-    ) -> Result<(String, String, Vec<String>)> {
+    ) -> Result<(String, String)> {
+        let request = json!({
+            "processor_id": data.processor_id,
+            "process_type": data.process_type,
+        });
+        let processor: Processor = self
+            .io
+            .call("processors.require_capable", request, async {
+                self.state()?
+                    .processors
+                    .require_capable(&data.processor_id, &data.process_type)
+                    .await
+                    .map_err(|e| anyhow::anyhow!(e.message))
+            })
+            .await?;
+
         // Prepare processing metadata
         let metadata = serde_json::json!({
             "input_batch_id": input_batch_id,
+            "processor": processor.metadata_entry(),
             "process_type": data.process_type,
             "yield_percentage": data.yield_percentage,
             "outputs": data.output_products,
-            "processing_timestamp": chrono::Utc::now().to_rfc3339()
+            "processing_timestamp": self.io.now()?.to_rfc3339()
         });
 
+        let batch = json!({ "batch_id": input_batch_id });
+        self.io
+            .call("financing.ensure_unencumbered", batch.clone(), async {
+                let financing = &self.state()?.financing;
+                financing
+                    .ensure_unencumbered(input_batch_id)
+                    .await
+                    .map_err(|e| anyhow::anyhow!(e.message))
+            })
+            .await?;
+        self.io
+            .call("acceptance.ensure_accepted", batch, async {
+                let acceptance = &self.state()?.acceptance;
+                acceptance
+                    .ensure_accepted(input_batch_id)
+                    .await
+                    .map_err(|e| anyhow::anyhow!(e.message))
+            })
+            .await?;
+
         // 1) Use batch folder
         let folder = batch_folder(input_batch_id);
 
         // 2) Write processing metadata to batch folder
-        self.state
-            .ipfs_client
-            .write_json_to_folder(&folder, "processing.json", &metadata)
+        self.write_json_to_folder(&folder, "processing.json", &metadata)
             .context("Failed to write processing metadata to batch folder")?;
+        let transform = HashRecord::of_json(&metadata)?;
+        self.record_folder_hash(&folder, "processing.json", transform)?;
 
         // 3) Upload entire folder -> get root CID
         let cid = self
-            .state
-            .ipfs_client
             .upload_folder(&folder)
             .await
             .context("Failed to upload batch folder to IPFS")?;
@@ -561,60 +1277,107 @@ impl SupplyChainWorkflow {
             .map(|p| hash_string(&p.product_id))
             .collect();
 
-        let metadata_json = serde_json::to_vec(&metadata)?;
-        let transform_hash = crate::chain::hash_bytes(&metadata_json);
+        let transform_hash = transform.hash;
 
         // Record on blockchain
-        let receipt = self
-            .state
-            .blockchain_client
-            .process_batch(
-                input_hash,
-                transform_hash,
-                output_hashes.clone(),
-                cid.clone(),
-            )
+        let request = json!({
+            "input_hash": input_hash,
+            "transform_hash": transform_hash,
+            "output_hashes": output_hashes,
+            "metadata_cid": cid,
+        });
+        let tx = self
+            .io
+            .call("chain.process_batch", request, async {
+                let receipt = self
+                    .state()?
+                    .chain()
+                    .process_batch(
+                        input_hash,
+                        transform_hash,
+                        output_hashes.clone(),
+                        cid.clone(),
+                    )
+                    .await?;
+                Ok(format!("{:?}", receipt.transaction_hash))
+            })
             .await
             .context("Blockchain processing failed")?;
 
-        let output_ids: Vec<String> = data
-            .output_products
-            .iter()
-            .map(|p| p.product_id.clone())
-            .collect();
+        Ok((tx, cid))
+    }
 
-        Ok((format!("{:?}", receipt.transaction_hash), cid, output_ids))
+    /// Shelf life of the packaged product, cut for cold-chain and storage
+    /// breaches of the source batch
+    async fn shelf_life(&self, data: &CompleteWorkflowData) -> Result<ShelfLife> {
+        let product = [
+            purchase_crop(&data.farmer),
+            data.processing.process_type.as_str(),
+            data.packaging.package_type.as_str(),
+        ];
+        let shipment_id = data.logistics.shipment_id.as_str();
+        let request = json!({
+            "product": product,
+            "shipment_id": shipment_id,
+            "warehouse_id": data.warehouse.warehouse_id,
+        });
+        self.io
+            .call("shelf_life.compute", request, async {
+                let state = self.state()?;
+                let conditions = SourceConditions {
+                    cold_chain_risk: state
+                        .cold_chain
+                        .report(shipment_id)
+                        .await
+                        .and_then(|report| report.spoilage)
+                        .map(|spoilage| spoilage.level),
+                    storage_temp_c: Some(data.warehouse.temperature_celsius),
+                    storage_humidity_percent: Some(data.warehouse.humidity_percent),
+                };
+                let fallback_days = data.packaging.expiry_months.map(|months| months * 30);
+                Ok(state
+                    .shelf_life
+                    .compute(product, fallback_days, &conditions))
+            })
+            .await
     }
 
+    /// `sent` holds the transactions and CIDs of packages already created
     async fn create_retail_packages(
         &self,
         parent_batch_id: &str,
         data: &PackagingData,
-    ) -> Result<(Vec<String>, Vec<String>, Vec<String>)> {
-        let mut txs = Vec::new();
-        let mut cids = Vec::new();
-        let mut skus = Vec::new();
+        shelf_life: &ShelfLife,
+        sent: (Vec<String>, Vec<String>),
+    ) -> Result<(Vec<String>, Vec<String>)> {
+        if !shelf_life.breaches.is_empty() {
+            tracing::warn!(
+                batch_id = %parent_batch_id,
+                base_days = shelf_life.base_days,
+                days = shelf_life.days,
+                "Shelf life cut for handling breaches"
+            );
+        }
 
-        for package_num in 1..=data.total_packages {
-            let sku_id = format!("{}-{:04}", data.sku_prefix, package_num);
+        let (mut txs, mut cids) = sent;
 
+        for sku_id in sku_ids(data).into_iter().skip(txs.len()) {
             // Generate unit IDs for this package
             let unit_ids: Vec<String> = (1..=data.units_per_package)
                 .map(|unit| format!("{}-U{:03}", sku_id, unit))
                 .collect();
 
             // Prepare packaging metadata
+            let now = self.io.now()?;
             let metadata = serde_json::json!({
                 "sku_id": sku_id,
                 "parent_batch": parent_batch_id,
                 "package_type": data.package_type,
                 "units_count": data.units_per_package,
                 "unit_ids": unit_ids,
-                "expiry_date": format!(
-                    "{}",
-                    chrono::Utc::now() + chrono::Duration::days((data.expiry_months * 30) as i64)
-                ),
-                "packaging_timestamp": chrono::Utc::now().to_rfc3339()
+                "expiry_date": format!("{}", shelf_life.expiry(now)),
+                "shelf_life": shelf_life,
+                "packaging_timestamp": now.to_rfc3339()
             });
 
             // 1) Use parent batch folder
@@ -622,116 +1385,211 @@ impl SupplyChainWorkflow {
 
             // 2) Write packaging metadata for this SKU to batch folder
             let filename = format!("packaging_{}.json", sku_id);
-            self.state
-                .ipfs_client
-                .write_json_to_folder(&folder, &filename, &metadata)
+            self.write_json_to_folder(&folder, &filename, &metadata)
                 .context("Failed to write packaging metadata to batch folder")?;
 
             // 3) Upload entire batch folder -> get updated root CID
             let cid = self
-                .state
-                .ipfs_client
                 .upload_folder(&folder)
                 .await
                 .context("Failed to upload batch folder to IPFS")?;
 
-            // Generate Merkle root
-            let unit_hashes: Vec<FixedBytes<32>> =
-                unit_ids.iter().map(|id| hash_string(id)).collect();
-            let mut merkle_data = Vec::new();
-            for hash in &unit_hashes {
-                merkle_data.extend_from_slice(hash.as_slice());
-            }
-            let merkle_root = crate::chain::hash_bytes(&merkle_data);
+            // Merkle tree over the units
+            let unit_tree = UnitTree::build(&sku_id, parent_batch_id, unit_ids)?;
+            let merkle_root = unit_tree.root;
 
             // Hash IDs
             let sku_hash = hash_string(&sku_id);
             let parent_hash = hash_string(parent_batch_id);
 
             // Record on blockchain
-            let receipt = self
-                .state
-                .blockchain_client
-                .create_sku(sku_hash, parent_hash, merkle_root, cid.clone())
+            let request = json!({
+                "sku_hash": sku_hash,
+                "parent_hash": parent_hash,
+                "merkle_root": merkle_root,
+                "metadata_cid": cid,
+            });
+            let tx: String = self
+                .io
+                .call("chain.create_sku", request, async {
+                    let receipt = self
+                        .state()?
+                        .chain()
+                        .create_sku(sku_hash, parent_hash, merkle_root, cid.clone())
+                        .await?;
+                    Ok(format!("{:?}", receipt.transaction_hash))
+                })
                 .await
                 .context("Blockchain SKU creation failed")?;
+            let request = json!({ "sku_id": sku_id, "merkle_root": merkle_root });
+            self.io
+                .call_sync("fs.save_unit_tree", request, || unit_tree.save())?;
 
-            txs.push(format!("{:?}", receipt.transaction_hash));
+            self.stage_output(6, &tx, Some(&cid)).await;
+            txs.push(tx);
             cids.push(cid);
-            skus.push(sku_id);
         }
 
-        Ok((txs, cids, skus))
+        Ok((txs, cids))
     }
 
     async fn execute_ai_scoring(
         &self,
-        batch_id: &str,
+        farmer: &FarmerData,
+        purchase: &FpoPurchaseData,
         data: &AiScoringData,
     ) -> Result<(String, String, String)> {
+        let batch_id = purchase.batch_id.as_str();
+        // Grade declared at purchase, as a feature next to the model scores
+        let declared_grade_score = self
+            .resolve_grade(purchase_crop(farmer), &purchase.quality_grade)
+            .ok()
+            .map(|grade| grade.score);
+
         // Prepare AI score data
         let score_data = serde_json::json!({
             "batch_id": batch_id,
+            "declared_grade_score": declared_grade_score,
             "quality_score": data.quality_score,
             "freshness_score": data.freshness_score,
             "purity_score": data.purity_score,
             "overall_score": (data.quality_score + data.freshness_score + data.purity_score) / 3.0,
             "model_version": data.model_version,
-            "evaluation_timestamp": chrono::Utc::now().to_rfc3339()
+            "evaluation_timestamp": self.io.now()?.to_rfc3339()
         });
 
         // 1) Use batch folder
         let folder = batch_folder(batch_id);
 
         // 2) Write AI score to batch folder
-        self.state
-            .ipfs_client
-            .write_json_to_folder(&folder, "ai_score.json", &score_data)
+        self.write_json_to_folder(&folder, "ai_score.json", &score_data)
             .context("Failed to write AI score to batch folder")?;
+        let reveal = HashRecord::of_json(&score_data)?;
+        self.record_folder_hash(&folder, "ai_score.json", reveal)?;
 
         // 3) Upload entire folder -> get updated root CID
         let cid = self
-            .state
-            .ipfs_client
             .upload_folder(&folder)
             .await
             .context("Failed to upload batch folder to IPFS")?;
 
         // Generate commit-reveal hashes
-        let score_json = serde_json::to_vec(&score_data)?;
-        let reveal_hash = crate::chain::hash_bytes(&score_json);
+        let reveal_hash = reveal.hash;
 
         // Generate random nonce
-        let nonce_bytes: [u8; 32] = rand::random();
-        let nonce = FixedBytes::<32>::from(nonce_bytes);
+        let nonce: FixedBytes<32> =
+            self.io
+                .call_sync("random.nonce", serde_json::Value::Null, || {
+                    Ok(FixedBytes::from(rand::random::<[u8; 32]>()))
+                })?;
 
         let commit_hash = generate_commit_hash(reveal_hash, nonce);
 
         // Commit on blockchain
         let batch_hash = hash_string(batch_id);
-        let commit_receipt = self
-            .state
-            .blockchain_client
-            .commit_ai_score(batch_hash, commit_hash)
+        let request = json!({ "batch_hash": batch_hash, "commit_hash": commit_hash });
+        let commit_tx: String = self
+            .io
+            .call("chain.commit_ai_score", request, async {
+                let receipt = self
+                    .state()?
+                    .chain()
+                    .commit_ai_score(batch_hash, commit_hash)
+                    .await?;
+                Ok(format!("{:?}", receipt.transaction_hash))
+            })
             .await
             .context("Blockchain AI commit failed")?;
+        self.stage_output(7, &commit_tx, None).await;
 
         // Wait a bit before reveal (simulating time-lock)
-        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        self.io.pause(Duration::from_secs(2)).await;
 
         // Reveal on blockchain
-        let reveal_receipt = self
-            .state
-            .blockchain_client
-            .reveal_ai_score(batch_hash, reveal_hash, nonce, cid.clone())
+        let request = json!({
+            "batch_hash": batch_hash,
+            "reveal_hash": reveal_hash,
+            "nonce": nonce,
+            "metadata_cid": cid,
+        });
+        let reveal_tx: String = self
+            .io
+            .call("chain.reveal_ai_score", request, async {
+                let receipt = self
+                    .state()?
+                    .chain()
+                    .reveal_ai_score(batch_hash, reveal_hash, nonce, cid.clone())
+                    .await?;
+                Ok(format!("{:?}", receipt.transaction_hash))
+            })
             .await
             .context("Blockchain AI reveal failed")?;
+        self.stage_output(7, &reveal_tx, Some(&cid)).await;
+        let request = json!({ "batch_id": batch_id, "data": score_data });
+        self.io
+            .call("holds.evaluate", request, async {
+                holds::evaluate_logged(self.state()?, batch_id, ResultSource::AiScore, &score_data)
+                    .await;
+                Ok(())
+            })
+            .await?;
 
-        Ok((
-            format!("{:?}", commit_receipt.transaction_hash),
-            format!("{:?}", reveal_receipt.transaction_hash),
-            cid,
-        ))
+        Ok((commit_tx, reveal_tx, cid))
+    }
+
+    // ========================================================================
+    //                      RECORDED INTERACTIONS
+    // ========================================================================
+
+    async fn upload_json(&self, data: &serde_json::Value) -> Result<String> {
+        self.io
+            .call("ipfs.upload_json", data.clone(), async {
+                self.state()?.ipfs_client.upload_json(data).await
+            })
+            .await
+    }
+
+    async fn upload_folder(&self, folder: &str) -> Result<String> {
+        self.io
+            .call("ipfs.upload_folder", json!({ "folder": folder }), async {
+                self.state()?
+                    .ipfs_client
+                    .upload_folder_or_defer(folder)
+                    .await
+            })
+            .await
+    }
+
+    fn write_json_to_folder(
+        &self,
+        folder: &str,
+        filename: &str,
+        data: &serde_json::Value,
+    ) -> Result<()> {
+        let request = json!({ "folder": folder, "file": filename, "data": data });
+        self.io.call_sync("fs.write_json", request, || {
+            self.state()?
+                .ipfs_client
+                .write_json_to_folder(folder, filename, data)
+        })
+    }
+
+    fn record_folder_hash(&self, folder: &str, file: &str, record: HashRecord) -> Result<()> {
+        let request = json!({ "folder": folder, "file": file, "hash": record.hash });
+        self.io.call_sync("fs.record_folder_hash", request, || {
+            record_folder_hash(folder, file, record)
+        })
+    }
+
+    fn resolve_grade(&self, crop: &str, grade: &str) -> Result<Grade> {
+        let request = json!({ "crop": crop, "grade": grade });
+        self.io.call_sync("grades.resolve", request, || {
+            self.state()?
+                .grades
+                .resolve(crop, grade)
+                .cloned()
+                .map_err(|e| anyhow::anyhow!(e.message))
+        })
     }
 
     // ========================================================================
@@ -746,8 +1604,8 @@ impl SupplyChainWorkflow {
 
         // Get SKU origin from blockchain
         let (parent_batch_hash, merkle_root, packaged_at) = self
-            .state
-            .blockchain_client
+            .state()?
+            .chain()
             .verify_package_origin(sku_hash)
             .await
             .context("Failed to verify SKU origin")?;
@@ -770,13 +1628,10 @@ impl SupplyChainWorkflow {
 
     /// Verify farmer registration
     pub async fn verify_farmer(&self, farmer_did: &str) -> Result<FarmerVerification> {
-        let farmer_did_hash: FixedBytes<32> = farmer_did.parse()?;
+        let farmer_did_hash = chain_did(farmer_did).map_err(anyhow::Error::msg)?;
 
-        let (exists, crop_id_hash, registered_at) = self
-            .state
-            .blockchain_client
-            .verify_farmer(farmer_did_hash)
-            .await?;
+        let (exists, crop_id_hash, registered_at) =
+            self.state()?.chain().verify_farmer(farmer_did_hash).await?;
 
         Ok(FarmerVerification {
             farmer_did: farmer_did.to_string(),
@@ -816,39 +1671,183 @@ pub struct FarmerVerification {
 pub mod http_handlers {
     use super::*;
     use crate::state::AppState;
-    use axum::{extract::State, http::StatusCode, Json};
+    use axum::{
+        extract::{Path, State},
+        http::StatusCode,
+        response::sse::{Event, KeepAlive, Sse},
+        Json,
+    };
+    use tokio::sync::{broadcast::error::RecvError, mpsc};
+    use tokio_stream::wrappers::ReceiverStream;
 
     #[derive(Debug, Serialize)]
     pub struct ErrorResponse {
         pub error: String,
     }
 
-    /// Execute complete workflow endpoint
+    fn error_response(status: StatusCode, error: String) -> (StatusCode, Json<ErrorResponse>) {
+        (status, Json(ErrorResponse { error }))
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct WorkflowAccepted {
+        pub workflow_id: u64,
+        pub status: JobStatus,
+        pub status_url: String,
+    }
+
+    /// Execute complete workflow endpoint; the workflow runs as a background job
     pub async fn execute_workflow(
         State(state): State<AppState>,
         Json(payload): Json<CompleteWorkflowData>,
-    ) -> Result<Json<WorkflowResult>, (StatusCode, Json<ErrorResponse>)> {
+    ) -> Result<(StatusCode, Json<WorkflowAccepted>), (StatusCode, Json<ErrorResponse>)> {
         tracing::info!("🚀 Received complete workflow execution request");
 
-        let workflow = SupplyChainWorkflow::new(state);
+        // Reject input that would only fail once the job is under way
+        if chain_did(&payload.farmer.farmer_did).is_err() {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                "Invalid farmer DID format".to_string(),
+            ));
+        }
+        if payload.processing.output_products.is_empty() {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                "processing.output_products must not be empty".to_string(),
+            ));
+        }
 
-        let result = workflow.execute_full_workflow(payload).await.map_err(|e| {
-            tracing::error!(error = %e, "Workflow execution failed");
-            (
+        let job = state.workflow_jobs.create(&payload).await.map_err(|e| {
+            tracing::error!(error = %e, "Failed to record workflow job");
+            error_response(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: format!("Workflow failed: {}", e),
-                }),
+                format!("Workflow failed: {}", e),
             )
         })?;
 
-        tracing::info!(
-            "✅ Workflow completed: {} stages, {} transactions",
-            result.summary.successful_stages,
-            result.summary.total_transactions
-        );
+        tracing::info!(workflow_id = job.workflow_id, "Workflow job queued");
+        spawn_job(state, job.workflow_id, payload);
 
-        Ok(Json(result))
+        Ok((
+            StatusCode::ACCEPTED,
+            Json(WorkflowAccepted {
+                workflow_id: job.workflow_id,
+                status: job.status,
+                status_url: format!("/api/workflow/status/{}", job.workflow_id),
+            }),
+        ))
+    }
+
+    /// Resume a failed workflow job from its checkpoint
+    pub async fn resume_workflow(
+        State(state): State<AppState>,
+        Path(workflow_id): Path<u64>,
+    ) -> Result<(StatusCode, Json<WorkflowAccepted>), (StatusCode, Json<ErrorResponse>)> {
+        let data = state
+            .workflow_jobs
+            .resume(workflow_id)
+            .await
+            .map_err(|e| error_response(e.status, e.message))?;
+
+        tracing::info!(workflow_id, "Workflow job resumed");
+        spawn_job(state, workflow_id, data);
+
+        Ok((
+            StatusCode::ACCEPTED,
+            Json(WorkflowAccepted {
+                workflow_id,
+                status: JobStatus::Queued,
+                status_url: format!("/api/workflow/status/{}", workflow_id),
+            }),
+        ))
+    }
+
+    /// Workflow job progress endpoint
+    pub async fn workflow_status(
+        State(state): State<AppState>,
+        Path(workflow_id): Path<u64>,
+    ) -> Result<Json<WorkflowJob>, (StatusCode, Json<ErrorResponse>)> {
+        state
+            .workflow_jobs
+            .get(workflow_id)
+            .await
+            .map(Json)
+            .ok_or_else(|| {
+                error_response(
+                    StatusCode::NOT_FOUND,
+                    format!("Workflow {} not found", workflow_id),
+                )
+            })
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct WorkflowDone<'a> {
+        pub workflow_id: u64,
+        pub status: JobStatus,
+        pub result: Option<&'a WorkflowResult>,
+        pub error: Option<&'a str>,
+    }
+
+    type EventStream = ReceiverStream<Result<Event, axum::Error>>;
+
+    /// Workflow job progress as server-sent events: a `stage` event (the
+    /// stage's progress) whenever a stage changes status or sends a
+    /// transaction, starting with stages already past pending, then one
+    /// `done` event when the job completes or fails
+    pub async fn workflow_stream(
+        State(state): State<AppState>,
+        Path(workflow_id): Path<u64>,
+    ) -> Result<Sse<EventStream>, (StatusCode, Json<ErrorResponse>)> {
+        // Subscribe before the first read so no update is missed
+        let mut updates = state.workflow_jobs.subscribe();
+        if state.workflow_jobs.get(workflow_id).await.is_none() {
+            return Err(error_response(
+                StatusCode::NOT_FOUND,
+                format!("Workflow {} not found", workflow_id),
+            ));
+        }
+
+        let (tx, rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            let mut seen = Vec::new();
+            loop {
+                let Some(job) = state.workflow_jobs.get(workflow_id).await else {
+                    return;
+                };
+                for stage in changed_stages(&mut seen, &job) {
+                    let event = Event::default().event("stage").json_data(stage);
+                    if tx.send(event).await.is_err() {
+                        return;
+                    }
+                }
+                if matches!(job.status, JobStatus::Completed | JobStatus::Failed) {
+                    let done = WorkflowDone {
+                        workflow_id,
+                        status: job.status,
+                        result: job.result.as_ref(),
+                        error: job.error.as_deref(),
+                    };
+                    let _ = tx
+                        .send(Event::default().event("done").json_data(done))
+                        .await;
+                    return;
+                }
+
+                // Wait for this job to change; after a lag re-read it anyway
+                loop {
+                    tokio::select! {
+                        _ = tx.closed() => return,
+                        update = updates.recv() => match update {
+                            Ok(id) if id != workflow_id => continue,
+                            Ok(_) | Err(RecvError::Lagged(_)) => break,
+                            Err(RecvError::Closed) => return,
+                        },
+                    }
+                }
+            }
+        });
+
+        Ok(Sse::new(ReceiverStream::new(rx)).keep_alive(KeepAlive::default()))
     }
 
     /// Verify SKU traceability endpoint
@@ -911,3 +1910,189 @@ pub mod http_handlers {
         Ok(Json(result))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workflow_data(with_ai: bool) -> CompleteWorkflowData {
+        let checkpoint = serde_json::json!({
+            "location": "Indore", "latitude": 22.7, "longitude": 75.8,
+            "timestamp": "2025-01-10T10:00:00Z"
+        });
+        let mut data = serde_json::json!({
+            "farmer": {
+                "farmer_did": format!("0x{}", "11".repeat(32)), "crop_id": "SOY-1",
+                "name": "Ramesh", "location": "Dewas", "land_area": "2 ha",
+                "crops": ["soybean"], "contact": "9876543210"
+            },
+            "fpo_purchase": {
+                "batch_id": "BATCH-1", "quantity_kg": 500.0, "quality_grade": "A",
+                "moisture_content": "9%", "purchase_price": 4600.0,
+                "purchase_date": "2025-01-05"
+            },
+            "warehouse": {
+                "warehouse_id": "WH-1", "temperature_celsius": 24.0,
+                "humidity_percent": 55.0, "storage_duration_days": 10
+            },
+            "logistics": {
+                "shipment_id": "SHIP-1", "origin": "Dewas", "destination": "Indore",
+                "checkpoints": [checkpoint.clone(), checkpoint]
+            },
+            "processing": {
+                "input_batch_id": "BATCH-1", "processor_id": "PROC-1",
+                "process_type": "expeller",
+                "yield_percentage": 18.0,
+                "output_products": [{ "product_id": "OIL-1", "product_type": "oil", "quantity_kg": 90.0 }]
+            },
+            "packaging": {
+                "sku_prefix": "SKU", "package_type": "bottle", "units_per_package": 12,
+                "total_packages": 3, "expiry_months": 12
+            },
+            "ai_scoring": null
+        });
+        if with_ai {
+            data["ai_scoring"] = serde_json::json!({
+                "quality_score": 90.0, "freshness_score": 85.0,
+                "purity_score": 95.0, "model_version": "v1"
+            });
+        }
+        serde_json::from_value(data).unwrap()
+    }
+
+    #[test]
+    fn test_job_expects_transactions_per_stage() {
+        let job = WorkflowJob::new(1, &workflow_data(true));
+        let expected: Vec<u32> = job.stages.iter().map(|s| s.expected_txs).collect();
+        assert_eq!(expected, vec![1, 1, 1, 2, 1, 3, 2]);
+        assert_eq!(job.stages[3].name, "logistics");
+        assert_eq!(job.status, JobStatus::Queued);
+    }
+
+    #[test]
+    fn test_failure_blames_running_stage() {
+        let mut job = WorkflowJob::new(1, &workflow_data(false));
+        job.stage_mut(1).unwrap().status = StageStatus::Completed;
+        job.stage_mut(2).unwrap().status = StageStatus::Running;
+        job.fail("Blockchain FPO purchase failed".to_string());
+
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.stages[1].status, StageStatus::Failed);
+        assert_eq!(
+            job.stages[1].error.as_deref(),
+            Some("Blockchain FPO purchase failed")
+        );
+        assert_eq!(job.stages[2].status, StageStatus::Pending);
+        // No AI data, so that stage was never going to run
+        assert_eq!(job.stages[6].status, StageStatus::Skipped);
+    }
+
+    #[test]
+    fn test_resume_keeps_completed_and_itemized_outputs() {
+        let mut job = WorkflowJob::new(1, &workflow_data(true));
+        assert!(job.prepare_resume().is_err());
+
+        for stage in 1..=3 {
+            let s = job.stage_mut(stage).unwrap();
+            s.status = StageStatus::Completed;
+            s.tx_hashes.push(format!("0x{}", stage));
+            s.ipfs_cids.push(format!("cid{}", stage));
+        }
+        let logistics = job.stage_mut(4).unwrap();
+        logistics.status = StageStatus::Running;
+        logistics.tx_hashes.push("0x4a".to_string());
+        logistics.ipfs_cids.push("cid4a".to_string());
+        job.fail("RPC timeout".to_string());
+
+        let data = job.prepare_resume().unwrap();
+        assert_eq!(data.fpo_purchase.batch_id, "BATCH-1");
+        assert_eq!(job.status, JobStatus::Queued);
+        assert_eq!(job.resumes, 1);
+        assert_eq!(job.stages[0].status, StageStatus::Completed);
+        assert_eq!(job.stages[3].status, StageStatus::Pending);
+        assert_eq!(
+            partial_outputs(&job.stages, 4),
+            (vec!["0x4a".to_string()], vec!["cid4a".to_string()])
+        );
+        assert_eq!(
+            completed_output(&job.stages, 2),
+            Some(("0x2".to_string(), "cid2".to_string()))
+        );
+        assert_eq!(completed_output(&job.stages, 4), None);
+    }
+
+    #[test]
+    fn test_changed_stages_reports_each_change_once() {
+        let mut job = WorkflowJob::new(1, &workflow_data(false));
+        let mut seen = Vec::new();
+        assert!(changed_stages(&mut seen, &job).is_empty());
+
+        job.stage_mut(1).unwrap().status = StageStatus::Completed;
+        job.stage_mut(4).unwrap().status = StageStatus::Running;
+        let changed: Vec<u32> = changed_stages(&mut seen, &job)
+            .iter()
+            .map(|s| s.stage)
+            .collect();
+        assert_eq!(changed, vec![1, 4]);
+        assert!(changed_stages(&mut seen, &job).is_empty());
+
+        // Each logistics checkpoint sent is progress too
+        job.stage_mut(4).unwrap().tx_hashes.push("0x4a".to_string());
+        assert_eq!(changed_stages(&mut seen, &job)[0].stage, 4);
+    }
+
+    #[tokio::test]
+    async fn test_replay_reproduces_recorded_failure() {
+        use crate::workflow_replay::{replay, Interaction};
+
+        let data = workflow_data(false);
+        let now = "2025-01-10T10:00:00+00:00";
+        let interaction = |kind: &str, request, response| Interaction {
+            kind: kind.to_string(),
+            request,
+            response,
+        };
+        let metadata = json!({
+            "name": "Ramesh", "location": "Dewas", "land_area": "2 ha",
+            "crops": ["soybean"], "contact": "9876543210",
+            "registration_timestamp": now
+        });
+        let trace = WorkflowTrace {
+            workflow_id: 7,
+            run: 0,
+            recorded_at: now.to_string(),
+            input: data.clone(),
+            checkpoint: Vec::new(),
+            interactions: vec![
+                interaction("clock.now", serde_json::Value::Null, Ok(json!(now))),
+                interaction("clock.now", serde_json::Value::Null, Ok(json!(now))),
+                interaction("ipfs.upload_json", metadata, Ok(json!("QmFarmer"))),
+                interaction(
+                    "chain.register_farmer",
+                    json!({
+                        "farmer_did": data.farmer.farmer_did,
+                        "crop_id_hash": hash_string("SOY-1"),
+                        "metadata_cid": "QmFarmer",
+                    }),
+                    Err("execution reverted: FarmerAlreadyRegistered".to_string()),
+                ),
+            ],
+            outcome: Err(
+                "Blockchain registration failed: execution reverted: FarmerAlreadyRegistered"
+                    .to_string(),
+            ),
+        };
+
+        let report = replay(trace.clone()).await;
+        assert!(report.reproduced, "{:?}", report);
+        assert_eq!(report.replayed, 4);
+
+        // A change to the orchestration shows where the runs part
+        let mut changed = trace;
+        changed.input.farmer.name = "Suresh".to_string();
+        let report = replay(changed).await;
+        assert!(!report.reproduced);
+        assert_eq!(report.replayed, 2);
+        assert!(report.error.unwrap().contains("diverged at interaction 3"));
+    }
+}